@@ -0,0 +1,128 @@
+use std::hint::black_box;
+
+use alloy_primitives::{I256, U256};
+use criterion::{criterion_group, criterion_main, Criterion};
+use uniswap_v3_math::{
+    fee_tier::FeeTier,
+    full_math::mul_div,
+    pool::PoolSnapshot,
+    storage::InitializedTicks,
+    tick::Tick,
+    tick_math::{
+        get_sqrt_ratio_at_tick, get_sqrt_ratios_at_ticks, get_tick_at_sqrt_ratio, MIN_SQRT_RATIO,
+    },
+};
+
+// run with `cargo bench`, optionally followed by a filter on the benchmark names, e.g. `cargo bench -- swap/`
+
+const SNAPSHOT: &str = include_str!("fixtures/usdc_weth_500.csv");
+
+// builds the pool of the fixture, see `fixtures/usdc_weth_500.csv`
+fn snapshot() -> PoolSnapshot {
+    let mut lines = SNAPSHOT.lines().filter(|line| !line.starts_with('#'));
+    let mut globals = lines.next().unwrap().split(',');
+    let tick: i32 = globals.next().unwrap().parse().unwrap();
+    let sqrt_price_x_96: U256 = globals.next().unwrap().parse().unwrap();
+    let mut pool = PoolSnapshot::new(sqrt_price_x_96, FeeTier::Low).unwrap();
+    assert_eq!(pool.slot0.tick, tick);
+    pool.liquidity = globals.next().unwrap().parse().unwrap();
+
+    for line in lines {
+        let mut values = line.split(',');
+        let key: i32 = values.next().unwrap().parse().unwrap();
+        let info = Tick {
+            liquidity_net: values.next().unwrap().parse().unwrap(),
+            liquidity_gross: values.next().unwrap().parse().unwrap(),
+            initialized: true,
+            ..Default::default()
+        };
+        pool.ticks.insert(key, info);
+        pool.tick_bitmap
+            .flip_tick(key, pool.fee_tier.tick_spacing())
+            .unwrap();
+    }
+
+    pool
+}
+
+fn bench_mul_div(c: &mut Criterion) {
+    let a = U256::from(0x1234_5678_9abc_def0_u64) << 160;
+    let b = U256::from(0x0fed_cba9_8765_4321_u64) << 96;
+    let denominator = U256::from(0xabcd_ef01_u64) << 128;
+    c.bench_function("mul_div", |bencher| {
+        bencher.iter(|| mul_div(black_box(a), black_box(b), black_box(denominator)))
+    });
+}
+
+fn bench_tick_math(c: &mut Criterion) {
+    let ticks: Vec<i32> = (0..1024).map(|i| i * 1733 - 887_000).collect();
+    let sqrt_prices: Vec<U256> = ticks
+        .iter()
+        .map(|&tick| get_sqrt_ratio_at_tick(tick).unwrap())
+        .collect();
+
+    let mut i = 0;
+    c.bench_function("get_sqrt_ratio_at_tick", |bencher| {
+        bencher.iter(|| {
+            i = (i + 1) % ticks.len();
+            get_sqrt_ratio_at_tick(black_box(ticks[i]))
+        })
+    });
+    c.bench_function("get_sqrt_ratio_at_tick/1024", |bencher| {
+        bencher.iter(|| {
+            black_box(&ticks)
+                .iter()
+                .map(|&tick| get_sqrt_ratio_at_tick(tick))
+                .collect::<Result<Vec<_>, _>>()
+        })
+    });
+    c.bench_function("get_sqrt_ratios_at_ticks/1024", |bencher| {
+        bencher.iter(|| get_sqrt_ratios_at_ticks(black_box(&ticks)))
+    });
+    c.bench_function("get_tick_at_sqrt_ratio", |bencher| {
+        bencher.iter(|| {
+            i = (i + 1) % sqrt_prices.len();
+            get_tick_at_sqrt_ratio(black_box(sqrt_prices[i]))
+        })
+    });
+}
+
+fn bench_swap(c: &mut Criterion) {
+    let pool = snapshot();
+    let limit = MIN_SQRT_RATIO + U256::from(1);
+
+    // small enough to stay within the current tick
+    let amount = I256::try_from(1_000_000u64).unwrap();
+    assert_eq!(
+        pool.quote(true, amount, limit)
+            .unwrap()
+            .initialized_ticks_crossed,
+        0
+    );
+    c.bench_function("swap/single_step", |bencher| {
+        bencher.iter(|| pool.quote(true, black_box(amount), limit))
+    });
+
+    // the exact input that moves the price through the next 50 initialized ticks
+    let mut target = pool.slot0.tick + 1;
+    for _ in 0..50 {
+        target = (target - 1).div_euclid(10) * 10;
+        while !pool.ticks.contains_key(&target) {
+            target -= 10;
+        }
+    }
+    let target_price = get_sqrt_ratio_at_tick(target - 1).unwrap();
+    let amount = I256::from_raw(pool.get_amount_in_to_target_price(target_price).unwrap());
+    assert_eq!(
+        pool.quote(true, amount, limit)
+            .unwrap()
+            .initialized_ticks_crossed,
+        50
+    );
+    c.bench_function("swap/50_ticks", |bencher| {
+        bencher.iter(|| pool.quote(true, black_box(amount), limit))
+    });
+}
+
+criterion_group!(benches, bench_mul_div, bench_tick_math, bench_swap);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy_primitives::{address, Address};
+use uniswap_v3_math::fetch::{
+    pool_snapshot_multicall, DEFAULT_MULTICALL_BATCH_SIZE, MULTICALL3_ADDRESS,
+};
+
+// dumps the USDC/WETH 0.05% pool on mainnet in the format of `benches/fixtures/usdc_weth_500.csv`, run with
+// `cargo run --features cli --example dump_bench_fixture -- --rpc-url <URL> [--block <NUMBER>]`
+
+const USDC_WETH_500: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let (mut rpc_url, mut block) = (None, None);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rpc-url" => rpc_url = args.next(),
+            "--block" => block = args.next().map(|block| block.parse()).transpose()?,
+            _ => eyre::bail!("unknown argument {arg}"),
+        }
+    }
+    let rpc_url = rpc_url.ok_or_else(|| eyre::eyre!("missing --rpc-url"))?;
+
+    let provider = Arc::new(ProviderBuilder::new().on_http(rpc_url.parse()?));
+    let block = match block {
+        Some(block) => block,
+        None => provider.get_block_number().await?,
+    };
+    let pool = pool_snapshot_multicall(
+        provider,
+        USDC_WETH_500,
+        MULTICALL3_ADDRESS,
+        Some(block),
+        DEFAULT_MULTICALL_BATCH_SIZE,
+    )
+    .await?;
+
+    println!(
+        "# the USDC/WETH 0.05% pool ({USDC_WETH_500}) on mainnet at block {block}, dumped with"
+    );
+    println!("# `cargo run --features cli --example dump_bench_fixture`. The first line is tick,sqrt_price_x_96,liquidity");
+    println!(
+        "# of the pool, followed by tick,liquidity_net,liquidity_gross of each initialized tick"
+    );
+    println!(
+        "{},{},{}",
+        pool.slot0.tick, pool.slot0.sqrt_price_x_96, pool.liquidity
+    );
+    let mut ticks = pool.ticks.into_iter().collect::<Vec<_>>();
+    ticks.sort_unstable_by_key(|&(tick, _)| tick);
+    for (tick, info) in ticks {
+        println!("{tick},{},{}", info.liquidity_net, info.liquidity_gross);
+    }
+
+    Ok(())
+}
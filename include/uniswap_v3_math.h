@@ -0,0 +1,57 @@
+/*
+ * C declarations of the uniswap_v3_math C API, see src/capi.rs. Build the library with
+ *
+ *   cargo rustc --release --features capi --crate-type staticlib
+ *
+ * uint256 and int256 values are 32 byte big endian arrays, uint128 and int128 values 16 byte big endian arrays,
+ * signed values in two's complement. Every function returns UV3_OK on success and leaves its outputs untouched
+ * otherwise.
+ */
+
+#ifndef UNISWAP_V3_MATH_H
+#define UNISWAP_V3_MATH_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define UV3_OK 0
+/* the math failed in a case in which the contract reverts, see uv3_last_error_message */
+#define UV3_ERROR 1
+#define UV3_NULL_POINTER 2
+/* a bug in the library, the panic is caught instead of unwinding into the caller, see uv3_last_error_message */
+#define UV3_PANIC 3
+
+typedef struct Uv3SwapStep {
+    uint8_t sqrt_ratio_next_x_96[32];
+    uint8_t amount_in[32];
+    uint8_t amount_out[32];
+    uint8_t fee_amount[32];
+} Uv3SwapStep;
+
+/* the message of the last failed call on this thread or NULL, valid until the next failed call on the thread */
+const char *uv3_last_error_message(void);
+
+int32_t uv3_get_sqrt_ratio_at_tick(int32_t tick, uint8_t sqrt_ratio_x_96[32]);
+
+int32_t uv3_get_tick_at_sqrt_ratio(const uint8_t sqrt_ratio_x_96[32], int32_t *tick);
+
+/* liquidity is an int128, the amount an int256 */
+int32_t uv3_get_amount_0_delta(const uint8_t sqrt_ratio_a_x_96[32], const uint8_t sqrt_ratio_b_x_96[32],
+                               const uint8_t liquidity[16], uint8_t amount_0[32]);
+
+int32_t uv3_get_amount_1_delta(const uint8_t sqrt_ratio_a_x_96[32], const uint8_t sqrt_ratio_b_x_96[32],
+                               const uint8_t liquidity[16], uint8_t amount_1[32]);
+
+/* liquidity is a uint128, the amount remaining an int256, positive for exact input and negative for exact output */
+int32_t uv3_compute_swap_step(const uint8_t sqrt_ratio_current_x_96[32], const uint8_t sqrt_ratio_target_x_96[32],
+                              const uint8_t liquidity[16], const uint8_t amount_remaining[32], uint32_t fee_pips,
+                              Uv3SwapStep *step);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
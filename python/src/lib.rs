@@ -0,0 +1,317 @@
+use alloy_primitives::{Sign, I256, U256};
+use num_bigint::{BigInt, BigUint};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use uniswap_v3_math::{
+    error::UniswapV3MathError,
+    fee_tier::FeeTier,
+    liquidity_amounts,
+    pool::{PoolSnapshot, SwapFill, SwapParams, SwapResult},
+    position, sqrt_price_math, tick_math,
+};
+
+// python bindings to the math crate. Every amount, price and liquidity crosses the boundary as a python int, which is
+// arbitrary precision, so values are exactly the ones the contract computes. Errors are raised as ValueError with the
+// message of the crate error
+
+fn to_py_err(error: UniswapV3MathError) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+fn to_u256(value: BigUint) -> PyResult<U256> {
+    U256::try_from_le_slice(&value.to_bytes_le())
+        .ok_or_else(|| PyValueError::new_err("value does not fit a uint256"))
+}
+
+fn from_u256(value: U256) -> BigUint {
+    BigUint::from_bytes_le(&value.to_le_bytes::<32>())
+}
+
+fn to_i256(value: BigInt) -> PyResult<I256> {
+    let (sign, abs) = value.into_parts();
+    let sign = if sign == num_bigint::Sign::Minus {
+        Sign::Negative
+    } else {
+        Sign::Positive
+    };
+    I256::checked_from_sign_and_abs(sign, to_u256(abs)?)
+        .ok_or_else(|| PyValueError::new_err("value does not fit an int256"))
+}
+
+fn from_i256(value: I256) -> BigInt {
+    let (sign, abs) = value.into_sign_and_abs();
+    let sign = if sign.is_negative() {
+        num_bigint::Sign::Minus
+    } else {
+        num_bigint::Sign::Plus
+    };
+    BigInt::from_biguint(sign, from_u256(abs))
+}
+
+#[pyfunction]
+fn get_sqrt_ratio_at_tick(tick: i32) -> PyResult<BigUint> {
+    tick_math::get_sqrt_ratio_at_tick(tick)
+        .map(from_u256)
+        .map_err(to_py_err)
+}
+
+#[pyfunction]
+fn get_tick_at_sqrt_ratio(sqrt_price_x_96: BigUint) -> PyResult<i32> {
+    tick_math::get_tick_at_sqrt_ratio(to_u256(sqrt_price_x_96)?).map_err(to_py_err)
+}
+
+#[pyfunction]
+fn get_amount_0_delta(
+    sqrt_ratio_a_x_96: BigUint,
+    sqrt_ratio_b_x_96: BigUint,
+    liquidity: i128,
+) -> PyResult<BigInt> {
+    sqrt_price_math::get_amount_0_delta(
+        to_u256(sqrt_ratio_a_x_96)?,
+        to_u256(sqrt_ratio_b_x_96)?,
+        liquidity,
+    )
+    .map(from_i256)
+    .map_err(to_py_err)
+}
+
+#[pyfunction]
+fn get_amount_1_delta(
+    sqrt_ratio_a_x_96: BigUint,
+    sqrt_ratio_b_x_96: BigUint,
+    liquidity: i128,
+) -> PyResult<BigInt> {
+    sqrt_price_math::get_amount_1_delta(
+        to_u256(sqrt_ratio_a_x_96)?,
+        to_u256(sqrt_ratio_b_x_96)?,
+        liquidity,
+    )
+    .map(from_i256)
+    .map_err(to_py_err)
+}
+
+#[pyfunction]
+fn get_liquidity_for_amounts(
+    sqrt_ratio_x_96: BigUint,
+    sqrt_ratio_a_x_96: BigUint,
+    sqrt_ratio_b_x_96: BigUint,
+    amount_0: BigUint,
+    amount_1: BigUint,
+) -> PyResult<u128> {
+    liquidity_amounts::get_liquidity_for_amounts(
+        to_u256(sqrt_ratio_x_96)?,
+        to_u256(sqrt_ratio_a_x_96)?,
+        to_u256(sqrt_ratio_b_x_96)?,
+        to_u256(amount_0)?,
+        to_u256(amount_1)?,
+    )
+    .map_err(to_py_err)
+}
+
+// the token0/token1 amounts a position is worth at a price, rounded down like the amounts returned when burning it
+#[pyfunction]
+fn amounts_at_price(
+    sqrt_price_x_96: BigUint,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+) -> PyResult<(BigUint, BigUint)> {
+    let (amount_0, amount_1) =
+        position::amounts_at_price(to_u256(sqrt_price_x_96)?, tick_lower, tick_upper, liquidity)
+            .map_err(to_py_err)?;
+    Ok((from_u256(amount_0), from_u256(amount_1)))
+}
+
+//A pool snapshot, swaps and positions are simulated against it exactly like against the contract
+#[pyclass(name = "Pool")]
+#[derive(Clone)]
+struct PyPool(PoolSnapshot);
+
+#[pymethods]
+impl PyPool {
+    // an empty pool initialized at the given price
+    #[new]
+    fn new(sqrt_price_x_96: BigUint, fee: u32, tick_spacing: i32) -> PyResult<Self> {
+        let fee_tier = FeeTier::custom(fee, tick_spacing).map_err(to_py_err)?;
+        PoolSnapshot::new(to_u256(sqrt_price_x_96)?, fee_tier)
+            .map(Self)
+            .map_err(to_py_err)
+    }
+
+    #[getter]
+    fn sqrt_price_x_96(&self) -> BigUint {
+        from_u256(self.0.slot0.sqrt_price_x_96)
+    }
+
+    #[getter]
+    fn tick(&self) -> i32 {
+        self.0.slot0.tick
+    }
+
+    #[getter]
+    fn liquidity(&self) -> u128 {
+        self.0.liquidity
+    }
+
+    // mints or burns liquidity in a range and returns the signed token0/token1 amounts owed to the pool
+    fn modify_position(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> PyResult<(BigInt, BigInt)> {
+        let (amount_0, amount_1) = self
+            .0
+            .modify_position(tick_lower, tick_upper, liquidity_delta)
+            .map_err(to_py_err)?;
+        Ok((from_i256(amount_0), from_i256(amount_1)))
+    }
+
+    // quotes a swap without modifying the pool, a positive amount is exact input and a negative amount exact output
+    #[pyo3(signature = (zero_for_one, amount_specified, sqrt_price_limit_x_96=None))]
+    fn quote<'py>(
+        &self,
+        py: Python<'py>,
+        zero_for_one: bool,
+        amount_specified: BigInt,
+        sqrt_price_limit_x_96: Option<BigUint>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let params = swap_params(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+        let result = self.0.quote_with(&params).map_err(to_py_err)?;
+        swap_result(py, &result)
+    }
+
+    // same as `quote`, and applies the swap to the pool
+    #[pyo3(signature = (zero_for_one, amount_specified, sqrt_price_limit_x_96=None))]
+    fn swap<'py>(
+        &mut self,
+        py: Python<'py>,
+        zero_for_one: bool,
+        amount_specified: BigInt,
+        sqrt_price_limit_x_96: Option<BigUint>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let params = swap_params(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+        let result = self.0.swap_with(&params).map_err(to_py_err)?;
+        swap_result(py, &result)
+    }
+
+    // returns the principal and the fees earned since the fee growth inside was last checkpointed of a position
+    #[pyo3(signature = (tick_lower, tick_upper, liquidity, fee_growth_inside_0_last_x_128=BigUint::ZERO, fee_growth_inside_1_last_x_128=BigUint::ZERO))]
+    fn position_value(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        fee_growth_inside_0_last_x_128: BigUint,
+        fee_growth_inside_1_last_x_128: BigUint,
+    ) -> PyResult<(BigUint, BigUint, BigUint, BigUint)> {
+        let (amount_0, amount_1) = position::amounts_at_price(
+            self.0.slot0.sqrt_price_x_96,
+            tick_lower,
+            tick_upper,
+            liquidity,
+        )
+        .map_err(to_py_err)?;
+        let (fees_0, fees_1) = position::uncollected_fees(
+            &self.0,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            to_u256(fee_growth_inside_0_last_x_128)?,
+            to_u256(fee_growth_inside_1_last_x_128)?,
+        )
+        .map_err(to_py_err)?;
+
+        Ok((
+            from_u256(amount_0),
+            from_u256(amount_1),
+            from_u256(fees_0),
+            from_u256(fees_1),
+        ))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Pool(sqrt_price_x_96={}, tick={}, liquidity={})",
+            self.0.slot0.sqrt_price_x_96, self.0.slot0.tick, self.0.liquidity
+        )
+    }
+}
+
+fn swap_params(
+    zero_for_one: bool,
+    amount_specified: BigInt,
+    sqrt_price_limit_x_96: Option<BigUint>,
+) -> PyResult<SwapParams> {
+    let mut builder = SwapParams::builder()
+        .zero_for_one(zero_for_one)
+        .amount_specified(to_i256(amount_specified)?);
+    if let Some(sqrt_price_limit_x_96) = sqrt_price_limit_x_96 {
+        builder = builder.sqrt_price_limit(to_u256(sqrt_price_limit_x_96)?);
+    }
+    builder.build().map_err(to_py_err)
+}
+
+fn swap_result<'py>(py: Python<'py>, result: &SwapResult) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("amount_0", from_i256(result.amount_0))?;
+    dict.set_item("amount_1", from_i256(result.amount_1))?;
+    dict.set_item("sqrt_price_x_96", from_u256(result.sqrt_price_x_96))?;
+    dict.set_item("tick", result.tick)?;
+    dict.set_item("liquidity", result.liquidity)?;
+    dict.set_item(
+        "initialized_ticks_crossed",
+        result.initialized_ticks_crossed,
+    )?;
+    dict.set_item("fee_amount", from_u256(result.fee_amount))?;
+    dict.set_item(
+        "amount_specified_remaining",
+        from_i256(result.amount_specified_remaining),
+    )?;
+    dict.set_item(
+        "fill",
+        match result.fill {
+            SwapFill::Full => "full",
+            SwapFill::PriceLimit => "price_limit",
+            SwapFill::LiquidityExhausted => "liquidity_exhausted",
+            SwapFill::Stopped => "stopped",
+        },
+    )?;
+    Ok(dict)
+}
+
+// named after the crate in python, the rust name would shadow the crate in the imports above
+#[pymodule]
+#[pyo3(name = "uniswap_v3_math")]
+fn py_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(get_sqrt_ratio_at_tick, module)?)?;
+    module.add_function(wrap_pyfunction!(get_tick_at_sqrt_ratio, module)?)?;
+    module.add_function(wrap_pyfunction!(get_amount_0_delta, module)?)?;
+    module.add_function(wrap_pyfunction!(get_amount_1_delta, module)?)?;
+    module.add_function(wrap_pyfunction!(get_liquidity_for_amounts, module)?)?;
+    module.add_function(wrap_pyfunction!(amounts_at_price, module)?)?;
+    module.add_class::<PyPool>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+    use num_bigint::{BigInt, BigUint};
+
+    use super::{from_i256, from_u256, to_i256, to_u256};
+
+    #[test]
+    fn test_conversions() {
+        for value in [U256::ZERO, U256::from(1), U256::MAX] {
+            assert_eq!(to_u256(from_u256(value)).unwrap(), value);
+        }
+        for value in [I256::ZERO, I256::MINUS_ONE, I256::MIN, I256::MAX] {
+            assert_eq!(to_i256(from_i256(value)).unwrap(), value);
+        }
+
+        //fails if the value does not fit
+        assert!(to_u256(BigUint::from(1u8) << 256usize).is_err());
+        assert!(to_i256(BigInt::from(1u8) << 255usize).is_err());
+        assert!(to_i256(-(BigInt::from(1u8) << 255usize)).is_ok());
+    }
+}
@@ -0,0 +1,52 @@
+import unittest
+
+import uniswap_v3_math as m
+
+Q96 = 2**96
+MIN_SQRT_RATIO = 4295128739
+
+
+class TestBindings(unittest.TestCase):
+    def test_tick_math(self):
+        self.assertEqual(m.get_sqrt_ratio_at_tick(0), Q96)
+        self.assertEqual(m.get_tick_at_sqrt_ratio(m.get_sqrt_ratio_at_tick(-1234)), -1234)
+        self.assertEqual(m.get_sqrt_ratio_at_tick(-887272), MIN_SQRT_RATIO)
+        with self.assertRaises(ValueError):
+            m.get_sqrt_ratio_at_tick(887273)
+
+    def test_amount_deltas(self):
+        lower, upper = m.get_sqrt_ratio_at_tick(-600), m.get_sqrt_ratio_at_tick(600)
+        # owed to the pool for positive liquidity, paid out for negative liquidity
+        self.assertGreater(m.get_amount_0_delta(lower, upper, 10**18), 0)
+        self.assertLess(m.get_amount_1_delta(lower, upper, -(10**18)), 0)
+
+    def test_pool(self):
+        pool = m.Pool(Q96, 3000, 60)
+        amount_0, amount_1 = pool.modify_position(-600, 600, 10**21)
+        self.assertEqual(pool.liquidity, 10**21)
+        self.assertEqual(amount_0, amount_1)
+
+        # quoting leaves the pool as is, swapping moves it
+        quote = pool.quote(True, 10**18)
+        self.assertEqual(pool.tick, 0)
+        self.assertEqual(quote["amount_0"], 10**18)
+        self.assertLess(quote["amount_1"], 0)
+        self.assertEqual(quote["fee_amount"], 3 * 10**15)
+        self.assertEqual(quote["fill"], "full")
+
+        swap = pool.swap(True, 10**18)
+        self.assertEqual(swap, quote)
+        self.assertEqual(pool.tick, quote["tick"])
+        self.assertEqual(pool.sqrt_price_x_96, quote["sqrt_price_x_96"])
+
+        # the swap fee accrues to the only position
+        _, _, fees_0, fees_1 = pool.position_value(-600, 600, 10**21)
+        self.assertEqual(fees_1, 0)
+        self.assertGreater(fees_0, 0)
+
+        with self.assertRaises(ValueError):
+            pool.quote(True, 10**18, Q96 * 2)
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,324 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    decode::{bits, signed_24, signed_56},
+    error::UniswapV3MathError,
+    fee_tier::FeeTier,
+    full_math::sqrt,
+    pool::{PoolSnapshot, Slot0},
+    variant::PoolVariant,
+};
+
+// community fees are in thousandths of the swap fee
+pub const COMMUNITY_FEE_DENOMINATOR: u32 = 1000;
+// Algebra V1 pools are deployed with a fixed tick spacing
+pub const ALGEBRA_TICK_SPACING: i32 = 60;
+// the volume per liquidity of a single swap is capped at 100000 << 64
+pub const MAX_VOLUME_PER_LIQUIDITY: U256 = U256::from_limbs([0, 100000, 0, 0]);
+
+//Algebra V1 pools, used by QuickSwap, Camelot and other forks. The pool fee is not fixed, the pool recomputes it from
+//the volatility and volume of the recent timepoints, see `dynamic_fee`, and the snapshot's fee tier holds the fee the
+//pool last stored in its global state. The community fee of each token is stored in slot0's fee protocol, token0 in
+//the lower 8 bits and token1 in the next 8 bits, matching the packed global state
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Algebra;
+
+impl PoolVariant for Algebra {
+    fn fee_tier(&self, fee: u32) -> Option<FeeTier> {
+        FeeTier::custom(fee, ALGEBRA_TICK_SPACING).ok()
+    }
+
+    fn fee_protocol(&self, slot0: &Slot0, zero_for_one: bool) -> u32 {
+        if zero_for_one {
+            slot0.fee_protocol & 0xff
+        } else {
+            (slot0.fee_protocol >> 8) & 0xff
+        }
+    }
+
+    fn protocol_fee(&self, fee_amount: U256, fee_protocol: u32) -> U256 {
+        fee_amount * U256::from(fee_protocol) / U256::from(COMMUNITY_FEE_DENOMINATOR)
+    }
+}
+
+//The parameters of the adaptive fee, mirrors AdaptiveFee.Configuration. The fee is the base fee plus the sum of two
+//volatility sigmoids, scaled by a third sigmoid of the volume per liquidity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AdaptiveFeeConfiguration {
+    // max value of the first sigmoid
+    pub alpha_1: u16,
+    // max value of the second sigmoid
+    pub alpha_2: u16,
+    // shift along the x-axis of the first sigmoid
+    pub beta_1: u32,
+    // shift along the x-axis of the second sigmoid
+    pub beta_2: u32,
+    // horizontal stretch factor of the first sigmoid
+    pub gamma_1: u16,
+    // horizontal stretch factor of the second sigmoid
+    pub gamma_2: u16,
+    // shift along the x-axis of the outer volume sigmoid
+    pub volume_beta: u32,
+    // horizontal stretch factor of the outer volume sigmoid
+    pub volume_gamma: u16,
+    // minimum possible fee
+    pub base_fee: u16,
+}
+
+impl Default for AdaptiveFeeConfiguration {
+    // the configuration new pools are created with by the Algebra V1 factory
+    fn default() -> Self {
+        Self {
+            alpha_1: 2900,
+            alpha_2: 15000 - 3000,
+            beta_1: 360,
+            beta_2: 60000,
+            gamma_1: 59,
+            gamma_2: 8500,
+            volume_beta: 0,
+            volume_gamma: 10,
+            base_fee: 100,
+        }
+    }
+}
+
+// a single timepoint of the pool's data storage, mirrors DataStorage.Timepoint
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timepoint {
+    pub initialized: bool,
+    pub block_timestamp: u32,
+    pub tick_cumulative: i64,
+    pub seconds_per_liquidity_cumulative: U256,
+    // the volatility accumulator, an uint88
+    pub volatility_cumulative: U256,
+    // the average tick over the last 24 hours at the time of the timepoint
+    pub average_tick: i32,
+    // the volume per liquidity accumulator, an uint144
+    pub volume_per_liquidity_cumulative: U256,
+}
+
+// returns the fee in hundredths of a bip for the given volatility and volume per liquidity averages, mirrors
+// AdaptiveFee.getFee. The pool passes the 24 hour volatility average divided by 15
+pub fn dynamic_fee(
+    volatility: U256,
+    volume_per_liquidity: U256,
+    config: &AdaptiveFeeConfiguration,
+) -> u16 {
+    let sum_of_sigmoids = sigmoid(
+        volatility,
+        config.gamma_1,
+        config.alpha_1,
+        U256::from(config.beta_1),
+    ) + sigmoid(
+        volatility,
+        config.gamma_2,
+        config.alpha_2,
+        U256::from(config.beta_2),
+    );
+    let sum_of_sigmoids = sum_of_sigmoids.min(U256::from(u16::MAX)).to::<u16>();
+
+    let fee = U256::from(config.base_fee)
+        + sigmoid(
+            volume_per_liquidity,
+            config.volume_gamma,
+            sum_of_sigmoids,
+            U256::from(config.volume_beta),
+        );
+    fee.wrapping_to::<u16>()
+}
+
+// returns the volume per liquidity of a swap, i.e. the geometric mean of the absolute amounts in Q64 per unit of
+// liquidity, mirrors DataStorage.calculateVolumePerLiquidity
+pub fn volume_per_liquidity(liquidity: u128, amount_0: I256, amount_1: I256) -> U256 {
+    let volume = sqrt(amount_0.unsigned_abs()) * sqrt(amount_1.unsigned_abs());
+    let liquidity = U256::from(liquidity.max(1));
+    let volume_shifted = if volume >= U256::from(1) << 192 {
+        U256::MAX / liquidity
+    } else {
+        (volume << 64) / liquidity
+    };
+
+    volume_shifted.min(MAX_VOLUME_PER_LIQUIDITY)
+}
+
+// sets the fee of an Algebra pool, e.g. to the result of `dynamic_fee` when a new timepoint is written
+pub fn set_fee<T, B>(
+    pool: &mut PoolSnapshot<T, B, Algebra>,
+    fee: u16,
+) -> Result<(), UniswapV3MathError> {
+    pool.fee_tier = FeeTier::custom(fee as u32, pool.fee_tier.tick_spacing())?;
+    Ok(())
+}
+
+// returns a timepoint from its two consecutive storage words in the timepoints array
+pub fn timepoint(words: &[U256; 2]) -> Timepoint {
+    Timepoint {
+        initialized: !bits(words[0], 0, 8).is_zero(),
+        block_timestamp: bits(words[0], 8, 32).to::<u32>(),
+        tick_cumulative: signed_56(bits(words[0], 40, 56)),
+        seconds_per_liquidity_cumulative: bits(words[0], 96, 160),
+        volatility_cumulative: bits(words[1], 0, 88),
+        average_tick: signed_24(bits(words[1], 88, 24)),
+        volume_per_liquidity_cumulative: bits(words[1], 112, 144),
+    }
+}
+
+// alpha / (1 + e^((beta - x) / gamma)), approximated with an 8th degree taylor expansion of the exponent
+fn sigmoid(x: U256, gamma: u16, alpha: u16, beta: U256) -> U256 {
+    let gamma_u256 = U256::from(gamma);
+    let alpha = U256::from(alpha);
+
+    if x > beta {
+        let x = x - beta;
+        if x >= U256::from(6) * gamma_u256 {
+            return alpha;
+        }
+        let g_8 = gamma_u256.pow(U256::from(8));
+        let ex = exp(x, gamma, g_8);
+        alpha * ex / (g_8 + ex)
+    } else {
+        let x = beta - x;
+        if x >= U256::from(6) * gamma_u256 {
+            return U256::ZERO;
+        }
+        let g_8 = gamma_u256.pow(U256::from(8));
+        let ex = g_8 + exp(x, gamma, g_8);
+        alpha * g_8 / ex
+    }
+}
+
+// g^8 * e^(x / g), x must be less than 6 * g
+fn exp(x: U256, gamma: u16, g_highest_degree: U256) -> U256 {
+    let g = U256::from(gamma);
+    let mut g_highest_degree = g_highest_degree;
+    let mut x_lowest_degree = x;
+
+    let mut res = g_highest_degree;
+    g_highest_degree /= g;
+    res += x_lowest_degree * g_highest_degree;
+
+    for factorial in [2u64, 6, 24, 120, 720] {
+        g_highest_degree /= g;
+        x_lowest_degree *= x;
+        res += x_lowest_degree * g_highest_degree / U256::from(factorial);
+    }
+
+    x_lowest_degree *= x;
+    res + x_lowest_degree * g / U256::from(5040) + x_lowest_degree * x / U256::from(40320)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        dynamic_fee, set_fee, timepoint, volume_per_liquidity, AdaptiveFeeConfiguration, Algebra,
+        MAX_VOLUME_PER_LIQUIDITY,
+    };
+    use crate::{
+        fee_tier::FeeTier,
+        pool::{PoolSnapshot, Slot0},
+        tick_math::{get_sqrt_ratio_at_tick, MIN_SQRT_RATIO},
+        variant::PoolVariant,
+    };
+
+    #[test]
+    fn test_dynamic_fee() {
+        let config = AdaptiveFeeConfiguration::default();
+
+        //no volatility, the base fee
+        assert_eq!(dynamic_fee(U256::ZERO, U256::ZERO, &config), 100);
+
+        //saturated sigmoids, the base fee plus both alphas
+        let max = U256::from(1e9 as u64);
+        assert_eq!(dynamic_fee(max, max, &config), 15000);
+
+        //the fee grows with the volatility
+        let mut last = 0;
+        for volatility in (0..2000).step_by(50) {
+            let fee = dynamic_fee(U256::from(volatility), max, &config);
+            assert!(fee >= last);
+            last = fee;
+        }
+        assert!(last > 100 && last < 15000);
+
+        //at the midpoint of a sigmoid half of its alpha is added
+        let config = AdaptiveFeeConfiguration {
+            alpha_2: 0,
+            ..config
+        };
+        assert_eq!(
+            dynamic_fee(U256::from(config.beta_1), max, &config),
+            100 + 2900 / 2
+        );
+    }
+
+    #[test]
+    fn test_volume_per_liquidity() {
+        let amount = I256::from_raw(U256::from(1e18 as u128));
+        assert_eq!(
+            volume_per_liquidity(1e18 as u128, amount, -amount),
+            U256::from(1) << 64
+        );
+
+        //capped for tiny liquidity
+        assert_eq!(
+            volume_per_liquidity(0, amount, -amount),
+            MAX_VOLUME_PER_LIQUIDITY
+        );
+    }
+
+    #[test]
+    fn test_timepoint() {
+        let words = [
+            U256::from(1)
+                | (U256::from(1_700_000_000u32) << 8)
+                | (I256::unchecked_from(-120).into_raw() & ((U256::from(1) << 56) - U256::from(1)))
+                    << 40
+                | (U256::from(7) << 96),
+            U256::from(11) | (U256::from(0xffffff - 59) << 88) | (U256::from(13) << 112),
+        ];
+
+        let timepoint = timepoint(&words);
+        assert!(timepoint.initialized);
+        assert_eq!(timepoint.block_timestamp, 1_700_000_000);
+        assert_eq!(timepoint.tick_cumulative, -120);
+        assert_eq!(timepoint.seconds_per_liquidity_cumulative, U256::from(7));
+        assert_eq!(timepoint.volatility_cumulative, U256::from(11));
+        assert_eq!(timepoint.average_tick, -60);
+        assert_eq!(timepoint.volume_per_liquidity_cumulative, U256::from(13));
+    }
+
+    #[test]
+    fn test_algebra_pool() {
+        let slot0 = Slot0 {
+            fee_protocol: 100 + (200 << 8),
+            ..Default::default()
+        };
+        assert_eq!(Algebra.fee_protocol(&slot0, true), 100);
+        assert_eq!(Algebra.fee_protocol(&slot0, false), 200);
+        assert_eq!(Algebra.fee_tier(500).unwrap().tick_spacing(), 60);
+
+        let mut pool = PoolSnapshot::new(
+            get_sqrt_ratio_at_tick(0).unwrap(),
+            Algebra.fee_tier(100).unwrap(),
+        )
+        .unwrap()
+        .with_variant(Algebra);
+        pool.modify_position(-887220, 887220, 1e20 as i128).unwrap();
+        pool.slot0.fee_protocol = 100;
+
+        //a higher dynamic fee returns less output
+        let amount = I256::from_raw(U256::from(1e18 as u128));
+        let limit = MIN_SQRT_RATIO + U256::from(1);
+        let low_fee = pool.quote(true, amount, limit).unwrap();
+        set_fee(&mut pool, 15000).unwrap();
+        assert_eq!(pool.fee_tier, FeeTier::custom(15000, 60).unwrap());
+        let high_fee = pool.quote(true, amount, limit).unwrap();
+        assert!(high_fee.amount_1 > low_fee.amount_1);
+
+        //10% of the fee goes to the community
+        pool.swap(true, amount, limit).unwrap();
+        assert_eq!(pool.protocol_fees.token_0, 1500000000000000);
+    }
+}
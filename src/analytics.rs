@@ -0,0 +1,1187 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    approx::{amount_0_delta, sqrt_price_x_96_to_f64},
+    error::UniswapV3MathError,
+    full_math::mul_div,
+    liquidity_math::add_delta,
+    oracle::Observation,
+    orientation::amounts_in_out,
+    pool::{PoolSnapshot, SwapParams},
+    position::{amounts_at_price, uncollected_fees},
+    q_number::{FeeGrowthX128, SqrtPriceX96},
+    quote::{batch_quote, QuoteRequest},
+    sqrt_price_math::Q96,
+    storage::{InitializedTicks, TickStorage},
+    tick::{get_fee_growth_inside, Tick},
+    tick_math::{
+        check_ticks, get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MAX_TICK,
+        MIN_SQRT_RATIO, MIN_TICK,
+    },
+    variant::PoolVariant,
+    U256_1,
+};
+
+pub const SECONDS_PER_YEAR: u64 = 31536000;
+
+// liquidity used for ratios that do not depend on the size of a position, large enough to keep rounding negligible
+pub(crate) const REFERENCE_LIQUIDITY: u128 = 1 << 96;
+
+//The outcome of adding liquidity right before a swap and removing it right after
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JitProfit {
+    // the token0/token1 amounts paid to mint the liquidity
+    pub amount_0_minted: U256,
+    pub amount_1_minted: U256,
+    // the token0/token1 amounts received when burning the liquidity, excluding fees
+    pub amount_0_burned: U256,
+    pub amount_1_burned: U256,
+    // the fees earned from the swap
+    pub fees_0: U256,
+    pub fees_1: U256,
+    // the value of the burned amounts and fees minus the value of the minted amounts, in token1 at the price after
+    // the swap
+    pub profit: I256,
+}
+
+//A range sized for a volatility estimate, see `range_for_volatility`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VolatilityRange {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    // the expected fraction of the horizon that the price spends inside the range
+    pub expected_time_in_range: f64,
+}
+
+//A short that delta hedges a position and the prices at which to adjust it, see `delta_hedge`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeltaHedge {
+    // the amount of token0 to short at the current price, in raw units
+    pub short_0: f64,
+    // the hedge is adjusted once the price leaves the band between these ticks
+    pub rehedge_tick_lower: i32,
+    pub rehedge_tick_upper: i32,
+    // the amount of token0 to short once the price reaches either end of the band
+    pub short_0_at_lower: f64,
+    pub short_0_at_upper: f64,
+}
+
+//The liquidity between two adjacent initialized ticks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidityRange {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    // the liquidity that is active while the current tick is within [tick_lower, tick_upper)
+    pub liquidity_active: u128,
+    // the total position liquidity that references tick_lower
+    pub liquidity_gross: u128,
+}
+
+//The sensitivities of the value of a position in token1 to the price of token0 in token1, see `position_greeks`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionGreeks {
+    // dV/dP, the token0 exposure of the position, which is the amount of token0 it holds
+    pub delta: f64,
+    // d²V/dP², the change in delta per unit of price. Never positive, the position sells token0 as the price rises
+    pub gamma: f64,
+}
+
+//The output of an exact input swap of a trade size in each of several pools, see `compare_fee_tiers`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TierExecution {
+    pub amount_in: U256,
+    // the output in each pool by pool index, none if the pool can not fill the whole input
+    pub amounts_out: Vec<Option<U256>>,
+    // the index of the pool with the largest output, the first of them on a tie
+    pub best: Option<usize>,
+}
+
+//A trade size at which the pool with the best execution changes, see `compare_fee_tiers`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TierCrossover {
+    pub amount_in: U256,
+    // the pool with the best execution at the size before and at this size
+    pub from: Option<usize>,
+    pub to: Option<usize>,
+}
+
+//The best execution of each trade size across several pools of the same pair, see `compare_fee_tiers`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeeTierComparison {
+    pub executions: Vec<TierExecution>,
+    pub crossovers: Vec<TierCrossover>,
+}
+
+// returns the value of amount0 and amount1 in token1 at the given price
+pub fn value_in_token_1(
+    amount_0: U256,
+    amount_1: U256,
+    sqrt_price_x_96: impl Into<SqrtPriceX96>,
+) -> Result<U256, UniswapV3MathError> {
+    let sqrt_price_x_96 = sqrt_price_x_96.into().0;
+    let amount_0_in_token_1 = mul_div(
+        mul_div(amount_0, sqrt_price_x_96, Q96)?,
+        sqrt_price_x_96,
+        Q96,
+    )?;
+
+    amount_0_in_token_1
+        .checked_add(amount_1)
+        .ok_or(UniswapV3MathError::Overflow)
+}
+
+// returns the impermanent loss of a position in the given range as a Q96 fraction of the value the tokens deposited at
+// `entry_sqrt_price_x_96` would have if they were held instead, (hodl - lp) / hodl. The loss is independent of liquidity
+pub fn impermanent_loss(
+    entry_sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    current_sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Result<U256, UniswapV3MathError> {
+    let current_sqrt_price_x_96 = current_sqrt_price_x_96.into();
+    let (entry_amount_0, entry_amount_1) = amounts_at_price(
+        entry_sqrt_price_x_96,
+        tick_lower,
+        tick_upper,
+        REFERENCE_LIQUIDITY,
+    )?;
+    let (amount_0, amount_1) = amounts_at_price(
+        current_sqrt_price_x_96,
+        tick_lower,
+        tick_upper,
+        REFERENCE_LIQUIDITY,
+    )?;
+
+    let hodl_value = value_in_token_1(entry_amount_0, entry_amount_1, current_sqrt_price_x_96)?;
+    let lp_value = value_in_token_1(amount_0, amount_1, current_sqrt_price_x_96)?;
+
+    // rounding can leave the lp value a few wei above the hodl value when the price has not moved
+    mul_div(hodl_value.saturating_sub(lp_value), Q96, hodl_value)
+}
+
+// same as `impermanent_loss` but returns the loss as a fraction between 0 and 1
+pub fn impermanent_loss_f64(
+    entry_sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    current_sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Result<f64, UniswapV3MathError> {
+    let loss_x_96 = impermanent_loss(
+        entry_sqrt_price_x_96,
+        current_sqrt_price_x_96,
+        tick_lower,
+        tick_upper,
+    )?;
+
+    Ok(x96_to_f64(loss_x_96))
+}
+
+// returns the delta and gamma of `liquidity` in the given range at `sqrt_price_x_96` from the closed form amounts,
+// with the price P of token0 in token1 in raw units. The value V = x * P + y of a position holding x token0 and
+// y token1 has dV/dP = x, and inside the range x = L * (1 / sqrt(P) - 1 / sqrt(P_upper)) so d²V/dP² = -L / (2 * P^1.5).
+// Outside the range the position holds a single token and gamma is 0, at the bounds the greeks are those of the side
+// `amounts_at_price` assigns the price to
+pub fn position_greeks(
+    sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+) -> Result<PositionGreeks, UniswapV3MathError> {
+    check_ticks(tick_lower, tick_upper)?;
+    let sqrt_price_x_96 = sqrt_price_x_96.into().0;
+    let sqrt_ratio_lower_x_96 = get_sqrt_ratio_at_tick(tick_lower)?;
+    let sqrt_ratio_upper_x_96 = get_sqrt_ratio_at_tick(tick_upper)?;
+
+    let sqrt_price = sqrt_price_x_96_to_f64(sqrt_price_x_96);
+    let sqrt_price_lower = sqrt_price_x_96_to_f64(sqrt_ratio_lower_x_96);
+    let sqrt_price_upper = sqrt_price_x_96_to_f64(sqrt_ratio_upper_x_96);
+    let liquidity = liquidity as f64;
+
+    Ok(if sqrt_price_x_96 <= sqrt_ratio_lower_x_96 {
+        PositionGreeks {
+            delta: amount_0_delta(sqrt_price_lower, sqrt_price_upper, liquidity),
+            gamma: 0.0,
+        }
+    } else if sqrt_price_x_96 < sqrt_ratio_upper_x_96 {
+        PositionGreeks {
+            delta: amount_0_delta(sqrt_price, sqrt_price_upper, liquidity),
+            gamma: -liquidity / (2.0 * sqrt_price.powi(3)),
+        }
+    } else {
+        PositionGreeks {
+            delta: 0.0,
+            gamma: 0.0,
+        }
+    })
+}
+
+// returns the short of token0, e.g. on a perp or borrowed spot, that makes `liquidity` in the given range delta neutral
+// at `sqrt_price_x_96`, along with the band of `band_ticks` around the current tick within which the hedge is kept.
+// The position sells token0 as the price rises, so the short shrinks towards the upper end of the band and grows
+// towards the lower end, the sizes at both ends are what the short is adjusted to when the price leaves the band
+pub fn delta_hedge(
+    sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+    band_ticks: u32,
+) -> Result<DeltaHedge, UniswapV3MathError> {
+    let sqrt_price_x_96 = sqrt_price_x_96.into().0;
+    let tick_current = get_tick_at_sqrt_ratio(sqrt_price_x_96)?;
+    let band_ticks = band_ticks.min((MAX_TICK - MIN_TICK) as u32) as i32;
+    let rehedge_tick_lower = tick_current.saturating_sub(band_ticks).max(MIN_TICK);
+    let rehedge_tick_upper = tick_current.saturating_add(band_ticks).min(MAX_TICK);
+
+    let short_0_at = |tick: i32| -> Result<f64, UniswapV3MathError> {
+        Ok(position_greeks(
+            get_sqrt_ratio_at_tick(tick)?,
+            tick_lower,
+            tick_upper,
+            liquidity,
+        )?
+        .delta)
+    };
+
+    Ok(DeltaHedge {
+        short_0: position_greeks(sqrt_price_x_96, tick_lower, tick_upper, liquidity)?.delta,
+        rehedge_tick_lower,
+        rehedge_tick_upper,
+        short_0_at_lower: short_0_at(rehedge_tick_lower)?,
+        short_0_at_upper: short_0_at(rehedge_tick_upper)?,
+    })
+}
+
+// returns the fees earned by `liquidity` between two fee growth inside checkpoints of a single token
+pub fn fees_earned(
+    fee_growth_inside_start_x_128: impl Into<FeeGrowthX128>,
+    fee_growth_inside_end_x_128: impl Into<FeeGrowthX128>,
+    liquidity: u128,
+) -> Result<U256, UniswapV3MathError> {
+    // fee growth inside is allowed to overflow, the difference is still correct when computed with wrapping arithmetic
+    fee_growth_inside_end_x_128
+        .into()
+        .wrapping_sub(fee_growth_inside_start_x_128.into())
+        .fees(liquidity)
+}
+
+// returns the annualized fee return of a position as a Q96 fraction of `position_value`,
+// the fee growth and the position value must be denominated in the same token
+pub fn fee_apr(
+    fee_growth_inside_start_x_128: impl Into<FeeGrowthX128>,
+    fee_growth_inside_end_x_128: impl Into<FeeGrowthX128>,
+    elapsed_seconds: u64,
+    liquidity: u128,
+    position_value: U256,
+) -> Result<U256, UniswapV3MathError> {
+    let fees = fees_earned(
+        fee_growth_inside_start_x_128,
+        fee_growth_inside_end_x_128,
+        liquidity,
+    )?;
+
+    annualize(fees, elapsed_seconds, position_value)
+}
+
+// same as `fee_apr` but for the fees of both tokens, which are valued in token1 at `sqrt_price_x_96`.
+// `position_value` must be denominated in token1
+pub fn fee_apr_in_token_1(
+    fee_growth_inside_start_x_128: (U256, U256),
+    fee_growth_inside_end_x_128: (U256, U256),
+    elapsed_seconds: u64,
+    liquidity: u128,
+    sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    position_value: U256,
+) -> Result<U256, UniswapV3MathError> {
+    let fees_0 = fees_earned(
+        fee_growth_inside_start_x_128.0,
+        fee_growth_inside_end_x_128.0,
+        liquidity,
+    )?;
+    let fees_1 = fees_earned(
+        fee_growth_inside_start_x_128.1,
+        fee_growth_inside_end_x_128.1,
+        liquidity,
+    )?;
+
+    annualize(
+        value_in_token_1(fees_0, fees_1, sqrt_price_x_96)?,
+        elapsed_seconds,
+        position_value,
+    )
+}
+
+// returns the token0 amount available between the current price and the price `pct` above it, and the token1 amount
+// available between the current price and the price `pct` below it, for each fraction in `pct_ranges`, e.g. 0.02 for
+// ±2%. Amounts are exact, initialized ticks are crossed the same way a swap would cross them. Fractions must be in
+// [0, 1), the price can not fall by 100% or more
+pub fn depth(
+    pool: &PoolSnapshot,
+    pct_ranges: &[f64],
+) -> Result<Vec<(U256, U256)>, UniswapV3MathError> {
+    pct_ranges
+        .iter()
+        .map(|&pct| {
+            if !(0.0..1.0).contains(&pct) {
+                return Err(UniswapV3MathError::InvalidDepthRange);
+            }
+            let amount_0 = depth_to_price(pool, false, (1.0 + pct).sqrt())?;
+            let amount_1 = depth_to_price(pool, true, (1.0 - pct).sqrt())?;
+            Ok((amount_0, amount_1))
+        })
+        .collect()
+}
+
+// returns the output amount of a swap that moves the price to the current sqrt price scaled by `sqrt_factor`
+fn depth_to_price(
+    pool: &PoolSnapshot,
+    zero_for_one: bool,
+    sqrt_factor: f64,
+) -> Result<U256, UniswapV3MathError> {
+    let sqrt_factor_x_96 = U256::from((sqrt_factor * 2f64.powi(96)) as u128);
+    let target = mul_div(pool.slot0.sqrt_price_x_96, sqrt_factor_x_96, Q96)?
+        .clamp(MIN_SQRT_RATIO + U256_1, MAX_SQRT_RATIO - U256_1);
+
+    if (zero_for_one && target >= pool.slot0.sqrt_price_x_96)
+        || (!zero_for_one && target <= pool.slot0.sqrt_price_x_96)
+    {
+        return Ok(U256::ZERO);
+    }
+
+    // the output of an unbounded exact input swap to the target is everything the liquidity holds up to that price
+    let result = pool.quote(zero_for_one, I256::MAX, target)?;
+
+    Ok(if zero_for_one {
+        result.amount_1.unsigned_abs()
+    } else {
+        result.amount_0.unsigned_abs()
+    })
+}
+
+// returns the active liquidity of every range between adjacent initialized ticks in ascending tick order, resolving
+// liquidity_net cumulatively from `liquidity` being active at `tick_current`, the same way a swap crosses ticks
+pub fn liquidity_distribution(
+    ticks: &HashMap<i32, Tick>,
+    tick_current: i32,
+    liquidity: u128,
+) -> Result<Vec<LiquidityRange>, UniswapV3MathError> {
+    let mut initialized = ticks
+        .iter()
+        .filter(|(_, info)| info.liquidity_gross > 0)
+        .map(|(&tick, info)| (tick, *info))
+        .collect::<Vec<_>>();
+    initialized.sort_unstable_by_key(|&(tick, _)| tick);
+
+    // the first tick above the current tick, every tick before it is at or below the current tick
+    let above = initialized.partition_point(|&(tick, _)| tick <= tick_current);
+
+    // the liquidity that is active in the range that starts at each tick
+    let mut liquidity_active = vec![0; initialized.len()];
+
+    // moving rightward, liquidity_net is added when crossing into the range that starts at the tick
+    let mut active = liquidity;
+    for (i, (_, info)) in initialized.iter().enumerate().skip(above) {
+        active = add_delta(active, info.liquidity_net)?;
+        liquidity_active[i] = active;
+    }
+
+    // moving leftward, liquidity_net is subtracted when crossing out of the range that starts at the tick
+    let mut active = liquidity;
+    for (i, (_, info)) in initialized.iter().enumerate().take(above).rev() {
+        liquidity_active[i] = active;
+        active = add_delta(active, info.liquidity_net.wrapping_neg())?;
+    }
+
+    let ranges = initialized
+        .windows(2)
+        .zip(liquidity_active)
+        .map(|(window, liquidity_active)| LiquidityRange {
+            tick_lower: window[0].0,
+            tick_upper: window[1].0,
+            liquidity_active,
+            liquidity_gross: window[0].1.liquidity_gross,
+        })
+        .collect();
+
+    Ok(ranges)
+}
+
+// same as `liquidity_distribution` for the ticks and active liquidity of a pool
+pub fn pool_liquidity_distribution(
+    pool: &PoolSnapshot,
+) -> Result<Vec<LiquidityRange>, UniswapV3MathError> {
+    liquidity_distribution(&pool.ticks, pool.slot0.tick, pool.liquidity)
+}
+
+// returns the token0/token1 amounts held by all in range and out of range liquidity of a pool at its current price,
+// summed over every range between adjacent initialized ticks. Unlike the pool balances this excludes uncollected fees,
+// protocol fees and tokens sent to the pool directly
+pub fn pool_tvl(pool: &PoolSnapshot) -> Result<(U256, U256), UniswapV3MathError> {
+    let mut amount_0 = U256::ZERO;
+    let mut amount_1 = U256::ZERO;
+
+    for range in pool_liquidity_distribution(pool)? {
+        let (range_amount_0, range_amount_1) = amounts_at_price(
+            pool.slot0.sqrt_price_x_96,
+            range.tick_lower,
+            range.tick_upper,
+            range.liquidity_active,
+        )?;
+        amount_0 = amount_0
+            .checked_add(range_amount_0)
+            .ok_or(UniswapV3MathError::Overflow)?;
+        amount_1 = amount_1
+            .checked_add(range_amount_1)
+            .ok_or(UniswapV3MathError::Overflow)?;
+    }
+
+    Ok((amount_0, amount_1))
+}
+
+// simulates minting `jit_liquidity` in `jit_range`, executing `victim_swap` and burning the liquidity again, and returns
+// the fees earned along with the change in inventory
+pub fn jit_profit(
+    pool: &PoolSnapshot,
+    victim_swap: &SwapParams,
+    jit_range: (i32, i32),
+    jit_liquidity: u128,
+) -> Result<JitProfit, UniswapV3MathError> {
+    let (tick_lower, tick_upper) = jit_range;
+    let liquidity_delta =
+        i128::try_from(jit_liquidity).map_err(|_| UniswapV3MathError::LiquidityOverflow)?;
+
+    let mut pool = pool.clone();
+    let (amount_0_minted, amount_1_minted) =
+        pool.modify_position(tick_lower, tick_upper, liquidity_delta)?;
+    let (fee_growth_inside_0_x_128, fee_growth_inside_1_x_128) = get_fee_growth_inside(
+        &pool.ticks,
+        tick_lower,
+        tick_upper,
+        pool.slot0.tick,
+        pool.fee_growth_global_0_x_128,
+        pool.fee_growth_global_1_x_128,
+    );
+
+    pool.swap_with(victim_swap)?;
+
+    let (fees_0, fees_1) = uncollected_fees(
+        &pool,
+        tick_lower,
+        tick_upper,
+        jit_liquidity,
+        fee_growth_inside_0_x_128,
+        fee_growth_inside_1_x_128,
+    )?;
+    let (amount_0_burned, amount_1_burned) =
+        pool.modify_position(tick_lower, tick_upper, -liquidity_delta)?;
+
+    let amount_0_minted = amount_0_minted.into_raw();
+    let amount_1_minted = amount_1_minted.into_raw();
+    let amount_0_burned = amount_0_burned.unsigned_abs();
+    let amount_1_burned = amount_1_burned.unsigned_abs();
+
+    let sqrt_price_x_96 = pool.slot0.sqrt_price_x_96;
+    let received = value_in_token_1(
+        amount_0_burned + fees_0,
+        amount_1_burned + fees_1,
+        sqrt_price_x_96,
+    )?;
+    let paid = value_in_token_1(amount_0_minted, amount_1_minted, sqrt_price_x_96)?;
+
+    Ok(JitProfit {
+        amount_0_minted,
+        amount_1_minted,
+        amount_0_burned,
+        amount_1_burned,
+        fees_0,
+        fees_1,
+        profit: I256::from_raw(received) - I256::from_raw(paid),
+    })
+}
+
+// returns the range around `tick_current` that contains the price at the end of `horizon` with probability `coverage`,
+// assuming a driftless lognormal price with volatility `sigma` per square root of the time unit of `horizon`, e.g. an
+// annualized volatility and a horizon in years. The bounds are widened to multiples of `tick_spacing`
+pub fn range_for_volatility(
+    tick_current: i32,
+    tick_spacing: i32,
+    sigma: f64,
+    horizon: f64,
+    coverage: f64,
+) -> Result<VolatilityRange, UniswapV3MathError> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3MathError::TickSpacingError);
+    }
+    if !(sigma >= 0.0 && horizon >= 0.0 && coverage > 0.0 && coverage < 1.0) {
+        return Err(UniswapV3MathError::InvalidVolatilityInput);
+    }
+
+    // the half width of the range in log price, then in ticks
+    let ln_tick = 1.0001f64.ln();
+    let half_width = inverse_normal_cdf((1.0 + coverage) / 2.0) * sigma * horizon.sqrt();
+    let half_width_ticks = half_width / ln_tick;
+
+    let min_tick = MIN_TICK.div_euclid(tick_spacing) * tick_spacing + tick_spacing;
+    let max_tick = MAX_TICK.div_euclid(tick_spacing) * tick_spacing;
+    let lower = ((tick_current as f64 - half_width_ticks) / tick_spacing as f64).floor();
+    let upper = ((tick_current as f64 + half_width_ticks) / tick_spacing as f64).ceil();
+    let tick_lower = ((lower * tick_spacing as f64).max(min_tick as f64) as i32).min(max_tick);
+    let mut tick_upper = ((upper * tick_spacing as f64).min(max_tick as f64) as i32).max(min_tick);
+    if tick_upper == tick_lower {
+        tick_upper += tick_spacing;
+    }
+
+    // the log price distance from the current price to each bound
+    let to_lower = (tick_lower - tick_current) as f64 * ln_tick;
+    let to_upper = (tick_upper - tick_current) as f64 * ln_tick;
+
+    Ok(VolatilityRange {
+        tick_lower,
+        tick_upper,
+        expected_time_in_range: expected_time_in_range(to_lower, to_upper, sigma, horizon),
+    })
+}
+
+// returns the annualized realized volatility of the price from the tick returns between consecutive observations within
+// `window` seconds of the latest one. Each pair of observations gives the average tick over its interval from the
+// tick_cumulative difference, and the return is the change in average tick between adjacent intervals. Observations
+// must be in chronological order, returns None if the window covers fewer than three observations
+pub fn realized_volatility(observations: &[Observation], window: u32) -> Option<f64> {
+    let latest = observations.last()?.block_timestamp;
+    let start = observations
+        .iter()
+        .position(|observation| latest.wrapping_sub(observation.block_timestamp) <= window)?;
+
+    // the average tick and length of each interval between consecutive observations
+    let intervals = observations[start..]
+        .windows(2)
+        .filter_map(|pair| {
+            let elapsed = pair[1]
+                .block_timestamp
+                .wrapping_sub(pair[0].block_timestamp);
+            (elapsed > 0).then(|| {
+                let tick_delta = pair[1]
+                    .tick_cumulative
+                    .wrapping_sub(pair[0].tick_cumulative);
+                (tick_delta as f64 / elapsed as f64, elapsed as f64)
+            })
+        })
+        .collect::<Vec<_>>();
+    if intervals.len() < 2 {
+        return None;
+    }
+
+    // the variance per second of the log price, the return between adjacent intervals spans from the middle of one
+    // to the middle of the next
+    let ln_tick = 1.0001f64.ln();
+    let (squared_returns, elapsed) = intervals.windows(2).fold((0.0, 0.0), |acc, pair| {
+        let log_return = (pair[1].0 - pair[0].0) * ln_tick;
+        (
+            acc.0 + log_return * log_return,
+            acc.1 + (pair[0].1 + pair[1].1) / 2.0,
+        )
+    });
+
+    Some((squared_returns / elapsed * SECONDS_PER_YEAR as f64).sqrt())
+}
+
+// quotes every trade size in `amounts_in` as an exact input swap without a price limit in each pool, e.g. snapshots of
+// the same pair in different fee tiers at the same block, and returns which pool gives the largest output for each
+// size with the sizes at which that changes. Deep low fee tiers usually win small trades while deeper high fee tiers
+// take over as the size grows. A pool that can not fill the whole input does not execute the size. Sizes are expected
+// in ascending order, the crossovers are then the first size at which a pool gives the best execution, the actual
+// crossover lies between it and the size before it
+pub fn compare_fee_tiers<T, B, V>(
+    pools: &[PoolSnapshot<T, B, V>],
+    zero_for_one: bool,
+    amounts_in: &[U256],
+) -> Result<FeeTierComparison, UniswapV3MathError>
+where
+    T: TickStorage + Sync,
+    B: InitializedTicks + Sync,
+    V: PoolVariant + Sync,
+{
+    let mut requests = Vec::with_capacity(amounts_in.len() * pools.len());
+    for &amount_in in amounts_in {
+        let params = SwapParams::builder()
+            .zero_for_one(zero_for_one)
+            .amount_in(amount_in)
+            .build()?;
+        requests.extend((0..pools.len()).map(|pool| QuoteRequest::new(pool, params)));
+    }
+
+    let mut results = batch_quote(pools, &requests).into_iter();
+    let mut comparison = FeeTierComparison::default();
+    for &amount_in in amounts_in {
+        let amounts_out = results
+            .by_ref()
+            .take(pools.len())
+            .map(|result| {
+                let result = result?;
+                Ok(result
+                    .amount_specified_remaining
+                    .is_zero()
+                    .then(|| amounts_in_out(zero_for_one, result.amount_0, result.amount_1).1))
+            })
+            .collect::<Result<Vec<_>, UniswapV3MathError>>()?;
+
+        let mut best: Option<usize> = None;
+        for (pool, amount_out) in amounts_out.iter().enumerate() {
+            if amount_out.is_some() && best.is_none_or(|best| *amount_out > amounts_out[best]) {
+                best = Some(pool);
+            }
+        }
+
+        if let Some(previous) = comparison.executions.last() {
+            if previous.best != best {
+                comparison.crossovers.push(TierCrossover {
+                    amount_in,
+                    from: previous.best,
+                    to: best,
+                });
+            }
+        }
+        comparison.executions.push(TierExecution {
+            amount_in,
+            amounts_out,
+            best,
+        });
+    }
+
+    Ok(comparison)
+}
+
+// returns the expected fraction of the horizon a driftless brownian motion starting at zero spends in [lower, upper],
+// the time average of P(lower <= X_t <= upper) integrated with the midpoint rule
+fn expected_time_in_range(lower: f64, upper: f64, sigma: f64, horizon: f64) -> f64 {
+    const STEPS: u32 = 1000;
+
+    if sigma == 0.0 || horizon == 0.0 {
+        return if lower <= 0.0 && upper >= 0.0 {
+            1.0
+        } else {
+            0.0
+        };
+    }
+
+    let mut total = 0.0;
+    for step in 0..STEPS {
+        let std_dev = sigma * (horizon * (step as f64 + 0.5) / STEPS as f64).sqrt();
+        total += normal_cdf(upper / std_dev) - normal_cdf(lower / std_dev);
+    }
+
+    total / STEPS as f64
+}
+
+// standard normal cdf using the complementary error function approximation from Numerical Recipes, with a
+// fractional error below 1.2e-7
+fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let erfc = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+
+    if x >= 0.0 {
+        1.0 - erfc / 2.0
+    } else {
+        erfc / 2.0
+    }
+}
+
+// inverse of `normal_cdf` by bisection
+fn inverse_normal_cdf(p: f64) -> f64 {
+    let (mut low, mut high) = (-40.0, 40.0);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if normal_cdf(mid) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+// returns earnings / value * year / elapsed as a Q96 fraction
+fn annualize(
+    earnings: U256,
+    elapsed_seconds: u64,
+    value: U256,
+) -> Result<U256, UniswapV3MathError> {
+    let denominator = value
+        .checked_mul(U256::from(elapsed_seconds))
+        .ok_or(UniswapV3MathError::Overflow)?;
+
+    mul_div(earnings, Q96 * U256::from(SECONDS_PER_YEAR), denominator)
+}
+
+// converts a Q96 value to f64
+fn x96_to_f64(value_x_96: U256) -> f64 {
+    f64::from(value_x_96) / 2f64.powi(96)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        compare_fee_tiers, delta_hedge, depth, fee_apr, fee_apr_in_token_1, fees_earned,
+        impermanent_loss, impermanent_loss_f64, jit_profit, liquidity_distribution,
+        pool_liquidity_distribution, pool_tvl, position_greeks, range_for_volatility,
+        realized_volatility, LiquidityRange, TierCrossover, SECONDS_PER_YEAR,
+    };
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        full_math::Q128,
+        oracle::{transform, Observation},
+        pool::{PoolSnapshot, SwapParams, ZeroLiquidity},
+        position::amounts_at_price,
+        sqrt_price_math::{_get_amount_0_delta, _get_amount_1_delta, encode_sqrt_ratio_x96, Q96},
+        test_utils::PoolBuilder,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK},
+        U256_1,
+    };
+
+    fn pool_at(tick_current: i32, fee_tier: FeeTier, ranges: &[(i32, i32, i128)]) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .fee_tier(fee_tier)
+            .positions(ranges)
+            .build()
+    }
+
+    #[test]
+    fn test_impermanent_loss() {
+        let entry = encode_sqrt_ratio_x96(U256::from(1), U256::from(1)).unwrap();
+
+        //no loss if the price does not move
+        let loss = impermanent_loss(entry, entry, -600, 600).unwrap();
+        assert!(loss < U256::from(2));
+
+        //full range positions match the constant product formula, 1 - 2 * sqrt(r) / (1 + r)
+        let current = encode_sqrt_ratio_x96(U256::from(4), U256::from(1)).unwrap();
+        let loss = impermanent_loss_f64(entry, current, -887220, 887220).unwrap();
+        assert!((loss - 0.2).abs() < 1e-6);
+
+        //the loss is symmetric for the inverse price move
+        let current = encode_sqrt_ratio_x96(U256::from(1), U256::from(4)).unwrap();
+        let loss = impermanent_loss_f64(entry, current, -887220, 887220).unwrap();
+        assert!((loss - 0.2).abs() < 1e-6);
+
+        //concentrated positions amplify the loss
+        let current = get_sqrt_ratio_at_tick(500).unwrap();
+        let full_range = impermanent_loss(entry, current, -887220, 887220).unwrap();
+        let concentrated = impermanent_loss(entry, current, -600, 600).unwrap();
+        assert!(concentrated > full_range);
+
+        //the loss is bounded by 1
+        let current = get_sqrt_ratio_at_tick(800000).unwrap();
+        let loss = impermanent_loss(entry, current, -600, 600).unwrap();
+        assert!(loss < Q96);
+
+        //fails for invalid ticks
+        assert!(impermanent_loss(entry, entry, -887273, 600).is_err());
+    }
+
+    #[test]
+    fn test_fee_apr() {
+        //fees earned from a fee growth delta
+        let fees = fees_earned(Q128, Q128 * U256::from(3), 500).unwrap();
+        assert_eq!(fees, U256::from(1000));
+
+        //fee growth inside wraps around
+        let fees = fees_earned(U256::MAX - Q128 + U256::from(1), Q128, 500).unwrap();
+        assert_eq!(fees, U256::from(1000));
+
+        //1000 fees on a position worth 10000 over half a year is a 20% apr
+        let apr = fee_apr(
+            Q128,
+            Q128 * U256::from(3),
+            SECONDS_PER_YEAR / 2,
+            500,
+            U256::from(10000),
+        )
+        .unwrap();
+        assert_eq!(apr, Q96 / U256::from(5));
+
+        //values token0 fees at the current price
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(4), U256::from(1)).unwrap();
+        let apr = fee_apr_in_token_1(
+            (U256::ZERO, U256::ZERO),
+            (Q128, Q128 * U256::from(2)),
+            SECONDS_PER_YEAR,
+            500,
+            sqrt_price,
+            U256::from(30000),
+        )
+        .unwrap();
+        // 500 * 4 + 1000 = 3000
+        assert_eq!(apr, Q96 / U256::from(10));
+
+        //fails if no time has elapsed
+        assert!(fee_apr(U256::ZERO, Q128, 0, 1, U256::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_depth() {
+        let pool = pool_at(
+            0,
+            FeeTier::Medium,
+            &[(-60000, 60000, 1e18 as i128), (-600, 600, 1e18 as i128)],
+        );
+        let levels = depth(&pool, &[0.0, 0.001, 0.1, 0.5, 0.999999]).unwrap();
+        let sqrt_price = pool.slot0.sqrt_price_x_96;
+
+        //no depth at the current price
+        assert_eq!(levels[0], (U256::ZERO, U256::ZERO));
+
+        //within the inner range both positions are active, ±0.1% is about ±10 ticks
+        let liquidity = 2e18 as u128;
+        let upper = get_sqrt_ratio_at_tick(10).unwrap();
+        let lower = get_sqrt_ratio_at_tick(-10).unwrap();
+        let amount_0 = _get_amount_0_delta(sqrt_price, upper, liquidity, false).unwrap();
+        let amount_1 = _get_amount_1_delta(lower, sqrt_price, liquidity, false).unwrap();
+        assert!(levels[1].0.abs_diff(amount_0) < amount_0 / U256::from(100));
+        assert!(levels[1].1.abs_diff(amount_1) < amount_1 / U256::from(100));
+
+        //crossing the inner range only adds the depth of the outer position beyond it
+        let inner_0 = _get_amount_0_delta(
+            sqrt_price,
+            get_sqrt_ratio_at_tick(600).unwrap(),
+            1e18 as u128,
+            false,
+        )
+        .unwrap();
+        let outer_0 = _get_amount_0_delta(
+            sqrt_price,
+            encode_sqrt_ratio_x96(U256::from(110), U256::from(100)).unwrap(),
+            1e18 as u128,
+            false,
+        )
+        .unwrap();
+        let expected = inner_0 + outer_0;
+        assert!(levels[2].0.abs_diff(expected) < expected / U256::from(1e9 as u64));
+
+        //depth grows with the range and is bounded by the liquidity in the pool
+        for window in levels.windows(2) {
+            assert!(window[1].0 > window[0].0);
+            assert!(window[1].1 > window[0].1);
+        }
+        let total_1 = _get_amount_1_delta(
+            get_sqrt_ratio_at_tick(-60000).unwrap(),
+            sqrt_price,
+            1e18 as u128,
+            false,
+        )
+        .unwrap()
+            + _get_amount_1_delta(
+                get_sqrt_ratio_at_tick(-600).unwrap(),
+                sqrt_price,
+                1e18 as u128,
+                false,
+            )
+            .unwrap();
+        assert!(levels[4].1 <= total_1);
+        assert!(levels[4].1 > total_1 - U256::from(10));
+
+        //ranges outside [0, 1) are rejected
+        for pct in [-0.01, 1.0, 2.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                depth(&pool, &[0.01, pct]),
+                Err(UniswapV3MathError::InvalidDepthRange)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_liquidity_distribution() {
+        let mut pool = pool_at(
+            0,
+            FeeTier::Medium,
+            &[
+                (-600, 600, 1e18 as i128),
+                (-1200, 60, 2e18 as i128),
+                (600, 1200, 4e18 as i128),
+            ],
+        );
+        let range =
+            |tick_lower, tick_upper, liquidity_active: f64, liquidity_gross: f64| LiquidityRange {
+                tick_lower,
+                tick_upper,
+                liquidity_active: liquidity_active as u128,
+                liquidity_gross: liquidity_gross as u128,
+            };
+        let expected = vec![
+            range(-1200, -600, 2e18, 2e18),
+            range(-600, 60, 3e18, 1e18),
+            range(60, 600, 1e18, 2e18),
+            range(600, 1200, 4e18, 5e18),
+        ];
+        assert_eq!(pool_liquidity_distribution(&pool).unwrap(), expected);
+
+        //resolving from any other reference tick gives the same distribution
+        for (tick_current, liquidity) in [(-1200, 2e18), (-601, 2e18), (60, 1e18), (1199, 4e18)] {
+            let distribution =
+                liquidity_distribution(&pool.ticks, tick_current, liquidity as u128).unwrap();
+            assert_eq!(distribution, expected);
+        }
+
+        //ticks outside of all positions leave the boundary ranges unchanged
+        let distribution = liquidity_distribution(&pool.ticks, 5000, 0).unwrap();
+        assert_eq!(distribution, expected);
+
+        //fails if the reference liquidity is inconsistent with the ticks
+        assert!(liquidity_distribution(&pool.ticks, 0, 1e18 as u128).is_err());
+
+        //no ranges without initialized ticks
+        pool.ticks.clear();
+        assert!(pool_liquidity_distribution(&pool).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pool_tvl() {
+        let positions = [
+            (-600, 600, 1e18 as i128),
+            (-1200, 60, 2e18 as i128),
+            (600, 1200, 4e18 as i128),
+            (-3000, -1800, 3e18 as i128),
+        ];
+        let pool = pool_at(0, FeeTier::Medium, &positions);
+
+        //matches the sum of the amounts of every position up to rounding
+        let (expected_0, expected_1) = positions.iter().fold(
+            (U256::ZERO, U256::ZERO),
+            |(amount_0, amount_1), &(tick_lower, tick_upper, liquidity)| {
+                let (position_0, position_1) = amounts_at_price(
+                    pool.slot0.sqrt_price_x_96,
+                    tick_lower,
+                    tick_upper,
+                    liquidity as u128,
+                )
+                .unwrap();
+                (amount_0 + position_0, amount_1 + position_1)
+            },
+        );
+        let (amount_0, amount_1) = pool_tvl(&pool).unwrap();
+        assert!(amount_0.abs_diff(expected_0) < U256::from(10));
+        assert!(amount_1.abs_diff(expected_1) < U256::from(10));
+
+        assert!(expected_0 > U256::ZERO && expected_1 > U256::ZERO);
+
+        //positions out of range on either side only hold a single token
+        let pool = pool_at(0, FeeTier::Medium, &[(600, 1200, 4e18 as i128)]);
+        assert_eq!(pool_tvl(&pool).unwrap().1, U256::ZERO);
+        let pool = pool_at(0, FeeTier::Medium, &[(-3000, -1800, 3e18 as i128)]);
+        assert_eq!(pool_tvl(&pool).unwrap().0, U256::ZERO);
+
+        //an empty pool holds nothing
+        let pool = pool_at(0, FeeTier::Medium, &[]);
+        assert_eq!(pool_tvl(&pool).unwrap(), (U256::ZERO, U256::ZERO));
+    }
+
+    #[test]
+    fn test_jit_profit() {
+        let pool = pool_at(0, FeeTier::Medium, &[(-887220, 887220, 1e18 as i128)]);
+        let victim_swap = SwapParams {
+            zero_for_one: true,
+            amount_specified: I256::from_raw(U256::from(1e16 as u128)),
+            sqrt_price_limit_x_96: MIN_SQRT_RATIO + U256_1,
+            zero_liquidity: ZeroLiquidity::Skip,
+        };
+
+        //liquidity concentrated around the price captures most of the fee and profits
+        let result = jit_profit(&pool, &victim_swap, (-60, 60), 1e21 as u128).unwrap();
+        let fee = U256::from(3e13 as u128);
+        assert!(result.fees_0 > fee * U256::from(99) / U256::from(100));
+        assert!(result.fees_0 < fee);
+        assert_eq!(result.fees_1, U256::ZERO);
+        assert!(result.amount_0_burned > result.amount_0_minted);
+        assert!(result.amount_1_burned < result.amount_1_minted);
+        assert!(result.profit > I256::ZERO);
+
+        //liquidity the swap does not reach earns nothing and loses the rounding of the mint and burn
+        let result = jit_profit(&pool, &victim_swap, (600, 1200), 1e21 as u128).unwrap();
+        assert_eq!((result.fees_0, result.fees_1), (U256::ZERO, U256::ZERO));
+        assert!(result.profit <= I256::ZERO);
+        assert!(result.profit > -I256::from_raw(U256::from(10)));
+
+        //fails for invalid ranges
+        assert!(jit_profit(&pool, &victim_swap, (60, -60), 1).is_err());
+    }
+
+    #[test]
+    fn test_range_for_volatility() {
+        //a 95% range for 1 day at 80% annualized volatility is ±8.2% in log price, or ±821 ticks
+        let range = range_for_volatility(1000, 60, 0.8, 1.0 / 365.0, 0.95).unwrap();
+        assert_eq!((range.tick_lower, range.tick_upper), (120, 1860));
+        assert!(range.expected_time_in_range > 0.95 && range.expected_time_in_range < 1.0);
+
+        //higher coverage widens the range and keeps the price in it longer
+        let wider = range_for_volatility(1000, 60, 0.8, 1.0 / 365.0, 0.99).unwrap();
+        assert!(wider.tick_lower < range.tick_lower && wider.tick_upper > range.tick_upper);
+        assert!(wider.expected_time_in_range > range.expected_time_in_range);
+
+        //without volatility the range is the spacing around the current tick
+        let range = range_for_volatility(-1000, 60, 0.0, 1.0, 0.5).unwrap();
+        assert_eq!((range.tick_lower, range.tick_upper), (-1020, -960));
+        assert_eq!(range.expected_time_in_range, 1.0);
+
+        //extreme volatility is bounded by the usable ticks
+        let range = range_for_volatility(0, 60, 100.0, 1.0, 0.99).unwrap();
+        assert_eq!((range.tick_lower, range.tick_upper), (-887220, 887220));
+
+        //fails for invalid inputs
+        assert!(range_for_volatility(0, 60, -0.1, 1.0, 0.5).is_err());
+        assert!(range_for_volatility(0, 60, 0.1, 1.0, 1.0).is_err());
+        assert!(range_for_volatility(0, 0, 0.1, 1.0, 0.5).is_err());
+    }
+
+    #[test]
+    fn test_realized_volatility() {
+        // one observation per hour, the average tick alternates by ±100 ticks every hour
+        let mut observations = vec![Observation {
+            block_timestamp: 1000,
+            initialized: true,
+            ..Default::default()
+        }];
+        for hour in 0..48 {
+            let last = observations.last().unwrap();
+            let tick = if hour % 2 == 0 { 100 } else { -100 };
+            observations.push(transform(last, last.block_timestamp + 3600, tick, 1));
+        }
+
+        //every hourly return is 200 ticks
+        let volatility = realized_volatility(&observations, 24 * 3600).unwrap();
+        let hourly = 200.0 * 1.0001f64.ln();
+        let expected = hourly * (SECONDS_PER_YEAR as f64 / 3600.0).sqrt();
+        assert!((volatility - expected).abs() < 1e-9);
+
+        //a constant tick has no volatility
+        let mut constant = observations[..1].to_vec();
+        for _ in 0..10 {
+            let last = constant.last().unwrap();
+            constant.push(transform(last, last.block_timestamp + 60, -5000, 1));
+        }
+        assert_eq!(realized_volatility(&constant, 3600), Some(0.0));
+
+        //at least two intervals are needed within the window
+        assert_eq!(realized_volatility(&observations, 3600), None);
+        assert_eq!(realized_volatility(&observations[..2], 24 * 3600), None);
+        assert_eq!(realized_volatility(&[], 3600), None);
+    }
+
+    #[test]
+    fn test_position_greeks() {
+        let liquidity = 1e18 as u128;
+
+        //delta is the token0 held by the position
+        let sqrt_price_x_96 = get_sqrt_ratio_at_tick(100).unwrap();
+        let greeks = position_greeks(sqrt_price_x_96, -600, 600, liquidity).unwrap();
+        let (amount_0, _) = amounts_at_price(sqrt_price_x_96, -600, 600, liquidity).unwrap();
+        assert!((greeks.delta / f64::from(amount_0) - 1.0).abs() < 1e-9);
+
+        //gamma matches the change in delta between neighbouring prices
+        let next =
+            position_greeks(get_sqrt_ratio_at_tick(101).unwrap(), -600, 600, liquidity).unwrap();
+        let finite_difference =
+            (next.delta - greeks.delta) / (1.0001f64.powi(101) - 1.0001f64.powi(100));
+        assert!(greeks.gamma < 0.0);
+        assert!((finite_difference / greeks.gamma - 1.0).abs() < 1e-3);
+
+        //below the range the position holds a fixed amount of token0, above it none
+        let below =
+            position_greeks(get_sqrt_ratio_at_tick(-1200).unwrap(), -600, 600, liquidity).unwrap();
+        let (amount_0, _) =
+            amounts_at_price(get_sqrt_ratio_at_tick(-600).unwrap(), -600, 600, liquidity).unwrap();
+        assert!((below.delta / f64::from(amount_0) - 1.0).abs() < 1e-9);
+        assert_eq!(below.gamma, 0.0);
+        let above =
+            position_greeks(get_sqrt_ratio_at_tick(1200).unwrap(), -600, 600, liquidity).unwrap();
+        assert_eq!((above.delta, above.gamma), (0.0, 0.0));
+
+        assert!(position_greeks(sqrt_price_x_96, 600, -600, liquidity).is_err());
+    }
+
+    #[test]
+    fn test_delta_hedge() {
+        let liquidity = 1e18 as u128;
+        let sqrt_price_x_96 = get_sqrt_ratio_at_tick(100).unwrap();
+        let hedge = delta_hedge(sqrt_price_x_96, -600, 600, liquidity, 60).unwrap();
+
+        assert_eq!(
+            hedge.short_0,
+            position_greeks(sqrt_price_x_96, -600, 600, liquidity)
+                .unwrap()
+                .delta
+        );
+        assert_eq!(
+            (hedge.rehedge_tick_lower, hedge.rehedge_tick_upper),
+            (40, 160)
+        );
+        //the short shrinks as the price rises
+        assert!(hedge.short_0_at_lower > hedge.short_0);
+        assert!(hedge.short_0_at_upper < hedge.short_0);
+
+        //a band past the range needs no short at its upper end
+        let hedge = delta_hedge(sqrt_price_x_96, -600, 600, liquidity, 1000).unwrap();
+        assert_eq!(hedge.short_0_at_upper, 0.0);
+
+        //the band is clamped to the tick bounds
+        let hedge = delta_hedge(sqrt_price_x_96, -600, 600, liquidity, u32::MAX).unwrap();
+        assert_eq!(
+            (hedge.rehedge_tick_lower, hedge.rehedge_tick_upper),
+            (MIN_TICK, MAX_TICK)
+        );
+    }
+
+    #[test]
+    fn test_compare_fee_tiers() {
+        let pools = [
+            pool_at(0, FeeTier::Lowest, &[(-10000, 10000, 1e18 as i128)]),
+            pool_at(0, FeeTier::Medium, &[(-60000, 60000, 1e21 as i128)]),
+        ];
+        let amounts_in = [1e12, 1e17, 1e18].map(|amount| U256::from(amount as u128));
+
+        //the low fee tier wins small trades and the deep pool large ones
+        let comparison = compare_fee_tiers(&pools, true, &amounts_in).unwrap();
+        let best: Vec<_> = comparison
+            .executions
+            .iter()
+            .map(|execution| execution.best)
+            .collect();
+        assert_eq!(best, [Some(0), Some(1), Some(1)]);
+        assert_eq!(
+            comparison.crossovers,
+            [TierCrossover {
+                amount_in: amounts_in[1],
+                from: Some(0),
+                to: Some(1),
+            }]
+        );
+        for (execution, amount_in) in comparison.executions.iter().zip(amounts_in) {
+            assert_eq!(execution.amount_in, amount_in);
+            let best = execution.best.unwrap();
+            for amount_out in &execution.amounts_out {
+                assert!(*amount_out <= execution.amounts_out[best]);
+            }
+        }
+
+        //the shallow pool can not fill the largest trade
+        assert_eq!(comparison.executions[2].amounts_out[0], None);
+
+        //no pool executes a trade none of them can fill
+        let comparison = compare_fee_tiers(&pools[..1], true, &amounts_in[2..]).unwrap();
+        assert_eq!(comparison.executions[0].best, None);
+        assert!(comparison.crossovers.is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+use alloy_primitives::U256;
+
+use crate::{
+    fee_tier::FEE_DENOMINATOR,
+    pool::PoolSnapshot,
+    storage::{InitializedTicks, TickStorage},
+    tick_math::{MAX_TICK, MIN_TICK},
+    variant::PoolVariant,
+};
+
+// floating point approximations of the pool math. Nothing in this module is bit exact with the contracts, results drift
+// from the exact math by a few ulps per operation and do not round in the pool's favor. Use it for charting, heuristics
+// and pre-filtering candidates, then verify with the exact math before acting on a result
+
+const LN_1_0001: f64 = 0.00009999500033330834;
+
+// converts a Q64.96 sqrt price to f64
+pub fn sqrt_price_x_96_to_f64(sqrt_price_x_96: U256) -> f64 {
+    f64::from(sqrt_price_x_96) / 2f64.powi(96)
+}
+
+// converts a sqrt price to Q64.96, returns None if it is negative, not finite or does not fit
+pub fn sqrt_price_to_x_96(sqrt_price: f64) -> Option<U256> {
+    U256::try_from(sqrt_price * 2f64.powi(96)).ok()
+}
+
+// 1.0001^tick
+pub fn tick_to_price(tick: i32) -> f64 {
+    (tick as f64 * LN_1_0001).exp()
+}
+
+// 1.0001^(tick / 2)
+pub fn tick_to_sqrt_price(tick: i32) -> f64 {
+    (tick as f64 * LN_1_0001 / 2.0).exp()
+}
+
+// returns the greatest tick whose price is less than or equal to `price`, ticks close to a boundary may be off by one
+pub fn price_to_tick(price: f64) -> i32 {
+    ((price.ln() / LN_1_0001).floor() as i32).clamp(MIN_TICK, MAX_TICK)
+}
+
+pub fn sqrt_price_to_tick(sqrt_price: f64) -> i32 {
+    price_to_tick(sqrt_price * sqrt_price)
+}
+
+// the amount of token0 between two sqrt prices for `liquidity`, i.e. L * (sqrt_b - sqrt_a) / (sqrt_a * sqrt_b)
+pub fn amount_0_delta(sqrt_price_a: f64, sqrt_price_b: f64, liquidity: f64) -> f64 {
+    let (sqrt_price_a, sqrt_price_b) = (
+        sqrt_price_a.min(sqrt_price_b),
+        sqrt_price_a.max(sqrt_price_b),
+    );
+    liquidity * (sqrt_price_b - sqrt_price_a) / (sqrt_price_a * sqrt_price_b)
+}
+
+// the amount of token1 between two sqrt prices for `liquidity`, i.e. L * (sqrt_b - sqrt_a)
+pub fn amount_1_delta(sqrt_price_a: f64, sqrt_price_b: f64, liquidity: f64) -> f64 {
+    liquidity * (sqrt_price_a - sqrt_price_b).abs()
+}
+
+// returns the output amount and the sqrt price after swapping `amount_in`, including the fee, against constant
+// liquidity, i.e. without crossing any ticks
+pub fn swap_output(
+    sqrt_price: f64,
+    liquidity: f64,
+    amount_in: f64,
+    zero_for_one: bool,
+    fee_pips: u32,
+) -> (f64, f64) {
+    let amount_in_less_fee = amount_in * fee_factor(fee_pips);
+
+    if zero_for_one {
+        let sqrt_price_next =
+            liquidity * sqrt_price / (liquidity + amount_in_less_fee * sqrt_price);
+        (
+            amount_1_delta(sqrt_price, sqrt_price_next, liquidity),
+            sqrt_price_next,
+        )
+    } else {
+        let sqrt_price_next = sqrt_price + amount_in_less_fee / liquidity;
+        (
+            amount_0_delta(sqrt_price, sqrt_price_next, liquidity),
+            sqrt_price_next,
+        )
+    }
+}
+
+// returns the output amount of an exact input swap of `amount_in` against the pool, crossing initialized ticks until
+// the input is exhausted or the pool runs out of liquidity
+pub fn quote<T, B, V>(pool: &PoolSnapshot<T, B, V>, zero_for_one: bool, amount_in: f64) -> f64
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let tick_spacing = pool.fee_tier.tick_spacing();
+    let fee_factor = fee_factor(pool.fee_tier.fee());
+
+    let mut sqrt_price = sqrt_price_x_96_to_f64(pool.slot0.sqrt_price_x_96);
+    let mut tick = pool.slot0.tick;
+    let mut liquidity = pool.liquidity as f64;
+    let mut amount_remaining = amount_in;
+    let mut amount_out = 0.0;
+
+    while amount_remaining > 0.0 {
+        let Ok((tick_next, initialized)) = pool.tick_bitmap.next_initialized_tick_within_one_word(
+            tick,
+            tick_spacing,
+            zero_for_one,
+        ) else {
+            break;
+        };
+        let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+        let sqrt_price_next = tick_to_sqrt_price(tick_next);
+
+        // the input needed to reach the next tick, including the fee
+        let amount_to_next = if zero_for_one {
+            amount_0_delta(sqrt_price, sqrt_price_next, liquidity)
+        } else {
+            amount_1_delta(sqrt_price, sqrt_price_next, liquidity)
+        } / fee_factor;
+
+        if liquidity > 0.0 && amount_remaining < amount_to_next {
+            amount_out += swap_output(
+                sqrt_price,
+                liquidity,
+                amount_remaining,
+                zero_for_one,
+                pool.fee_tier.fee(),
+            )
+            .0;
+            break;
+        }
+
+        if liquidity > 0.0 {
+            amount_remaining -= amount_to_next;
+            amount_out += if zero_for_one {
+                amount_1_delta(sqrt_price, sqrt_price_next, liquidity)
+            } else {
+                amount_0_delta(sqrt_price, sqrt_price_next, liquidity)
+            };
+        }
+        sqrt_price = sqrt_price_next;
+
+        if initialized {
+            let liquidity_net = pool.ticks.liquidity_net(tick_next) as f64;
+            liquidity += if zero_for_one {
+                -liquidity_net
+            } else {
+                liquidity_net
+            };
+        }
+        if tick_next == MIN_TICK || tick_next == MAX_TICK {
+            break;
+        }
+        tick = if zero_for_one {
+            tick_next - 1
+        } else {
+            tick_next
+        };
+    }
+
+    amount_out
+}
+
+// the fraction of the input that is swapped after the fee
+fn fee_factor(fee_pips: u32) -> f64 {
+    1.0 - fee_pips as f64 / FEE_DENOMINATOR as f64
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        amount_0_delta, amount_1_delta, price_to_tick, quote, sqrt_price_to_x_96,
+        sqrt_price_x_96_to_f64, swap_output, tick_to_price, tick_to_sqrt_price,
+    };
+    use crate::{
+        fee_tier::FeeTier,
+        pool::PoolSnapshot,
+        sqrt_price_math::{_get_amount_0_delta, _get_amount_1_delta},
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    };
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            ((actual - expected) / expected).abs() < 1e-9,
+            "{actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn test_tick_conversions() {
+        for tick in [-887272, -200000, -1, 0, 1, 60, 200000, 887272] {
+            let sqrt_price_x_96 = get_sqrt_ratio_at_tick(tick).unwrap();
+            assert_close(
+                tick_to_sqrt_price(tick),
+                sqrt_price_x_96_to_f64(sqrt_price_x_96),
+            );
+            assert_close(tick_to_price(tick), tick_to_sqrt_price(tick).powi(2));
+        }
+
+        assert_eq!(price_to_tick(1.0), 0);
+        assert_eq!(price_to_tick(tick_to_price(-200000) * 1.00005), -200000);
+        assert_eq!(price_to_tick(tick_to_price(200000) * 1.00005), 200000);
+
+        let sqrt_price_x_96 = get_sqrt_ratio_at_tick(1000).unwrap();
+        let roundtrip = sqrt_price_to_x_96(sqrt_price_x_96_to_f64(sqrt_price_x_96)).unwrap();
+        assert_close(f64::from(roundtrip), f64::from(sqrt_price_x_96));
+        assert_eq!(sqrt_price_to_x_96(-1.0), None);
+    }
+
+    #[test]
+    fn test_amount_deltas() {
+        let (lower, upper) = (
+            get_sqrt_ratio_at_tick(-600).unwrap(),
+            get_sqrt_ratio_at_tick(1200).unwrap(),
+        );
+        let liquidity = 1e21 as u128;
+
+        let amount_0 = _get_amount_0_delta(lower, upper, liquidity, false).unwrap();
+        let amount_1 = _get_amount_1_delta(lower, upper, liquidity, false).unwrap();
+        let (lower, upper) = (sqrt_price_x_96_to_f64(lower), sqrt_price_x_96_to_f64(upper));
+        assert_close(
+            amount_0_delta(upper, lower, liquidity as f64),
+            f64::from(amount_0),
+        );
+        assert_close(
+            amount_1_delta(lower, upper, liquidity as f64),
+            f64::from(amount_1),
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-887220, 887220, 1e20 as i128).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-30000, 30000, 1e19 as i128).unwrap();
+
+        //within a single range
+        let (amount_out, _) = swap_output(1.0, pool.liquidity as f64, 1e18, true, 3000);
+        assert_close(amount_out, quote(&pool, true, 1e18));
+
+        //across initialized ticks, in both directions
+        for (zero_for_one, limit) in [
+            (true, MIN_SQRT_RATIO + U256::from(1)),
+            (false, MAX_SQRT_RATIO - U256::from(1)),
+        ] {
+            for amount in [1e18, 1e20, 1e22] {
+                let exact = pool
+                    .quote(
+                        zero_for_one,
+                        I256::from_raw(U256::from(amount as u128)),
+                        limit,
+                    )
+                    .unwrap();
+                let amount_out = if zero_for_one {
+                    -exact.amount_1
+                } else {
+                    -exact.amount_0
+                };
+                assert_close(
+                    quote(&pool, zero_for_one, amount),
+                    f64::from(amount_out.into_raw()),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    fee_tier::FEE_DENOMINATOR,
+    full_math::{mul_div, sqrt},
+    pool::PoolSnapshot,
+    sqrt_price_math::{Q192, Q96},
+    tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    U256_1,
+};
+
+//An arbitrage between two pools of the same pair, token0 is bought from one pool and sold into the other
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Arbitrage {
+    // true if token0 is bought from pool a and sold into pool b, false for the reverse
+    pub buy_from_a: bool,
+    // the amount of token0 moved between the pools
+    pub amount_0: U256,
+    // the amount of token1 paid to the pool token0 is bought from
+    pub amount_1_in: U256,
+    // the amount of token1 received from the pool token0 is sold into
+    pub amount_1_out: U256,
+    // the profit in token1, amount_1_out - amount_1_in
+    pub profit: U256,
+}
+
+// returns the arbitrage that equalizes the marginal prices of both pools net of fees, assuming that neither pool crosses
+// an initialized tick. The amounts and profit are quoted exactly against both pools, so the result is still valid if the
+// trade crosses ticks, it is just not guaranteed to be optimal. Returns None if there is no profitable arbitrage
+pub fn optimal_arbitrage(
+    pool_a: &PoolSnapshot,
+    pool_b: &PoolSnapshot,
+) -> Result<Option<Arbitrage>, UniswapV3MathError> {
+    let buy_from_a = pool_a.slot0.sqrt_price_x_96 < pool_b.slot0.sqrt_price_x_96;
+    let (buy_pool, sell_pool) = if buy_from_a {
+        (pool_a, pool_b)
+    } else {
+        (pool_b, pool_a)
+    };
+
+    let amount_0 = closed_form_amount_0(buy_pool, sell_pool)?;
+    if amount_0.is_zero() {
+        return Ok(None);
+    }
+
+    let arbitrage = evaluate(buy_pool, sell_pool, amount_0, buy_from_a)?;
+    Ok(arbitrage.filter(|arbitrage| !arbitrage.profit.is_zero()))
+}
+
+// returns the most profitable arbitrage between the two pools, searching over exact quotes that account for tick
+// crossings and rounding. Returns None if there is no profitable arbitrage
+pub fn optimal_arbitrage_iterative(
+    pool_a: &PoolSnapshot,
+    pool_b: &PoolSnapshot,
+) -> Result<Option<Arbitrage>, UniswapV3MathError> {
+    let buy_from_a = pool_a.slot0.sqrt_price_x_96 < pool_b.slot0.sqrt_price_x_96;
+    let (buy_pool, sell_pool) = if buy_from_a {
+        (pool_a, pool_b)
+    } else {
+        (pool_b, pool_a)
+    };
+
+    if buy_pool.slot0.sqrt_price_x_96 == sell_pool.slot0.sqrt_price_x_96 {
+        return Ok(None);
+    }
+
+    // buying more token0 than it takes to move the buy pool to the sell pool's price can never be profitable
+    let amount_in = buy_pool.get_amount_in_to_target_price(sell_pool.slot0.sqrt_price_x_96)?;
+    let result = buy_pool.quote(
+        false,
+        I256::from_raw(amount_in),
+        sell_pool.slot0.sqrt_price_x_96,
+    )?;
+    let mut high = (-result.amount_0).into_raw();
+    let mut low = U256::ZERO;
+
+    // the profit is concave in the traded amount, so a ternary search converges to the optimum
+    let profit = |amount_0: U256| -> Result<Option<Arbitrage>, UniswapV3MathError> {
+        if amount_0.is_zero() {
+            return Ok(None);
+        }
+        evaluate(buy_pool, sell_pool, amount_0, buy_from_a)
+    };
+    let profit_of = |arbitrage: &Option<Arbitrage>| arbitrage.map(|a| a.profit).unwrap_or_default();
+
+    while high - low > U256::from(2) {
+        let third = (high - low) / U256::from(3);
+        let mid_low = low + third;
+        let mid_high = high - third;
+
+        if profit_of(&profit(mid_low)?) < profit_of(&profit(mid_high)?) {
+            low = mid_low;
+        } else {
+            high = mid_high;
+        }
+    }
+
+    let mut best: Option<Arbitrage> = None;
+    let mut amount_0 = low;
+    while amount_0 <= high {
+        let candidate = profit(amount_0)?;
+        if profit_of(&candidate) > profit_of(&best) {
+            best = candidate;
+        }
+        amount_0 += U256_1;
+    }
+
+    Ok(best.filter(|arbitrage| !arbitrage.profit.is_zero()))
+}
+
+// solves (1 - fee_buy) * (1 - fee_sell) * sqrt_price_sell' ** 2 = sqrt_price_buy' ** 2 for the amount of token0 moved,
+// where both pools are treated as having constant liquidity
+fn closed_form_amount_0(
+    buy_pool: &PoolSnapshot,
+    sell_pool: &PoolSnapshot,
+) -> Result<U256, UniswapV3MathError> {
+    if buy_pool.liquidity == 0 || sell_pool.liquidity == 0 {
+        return Ok(U256::ZERO);
+    }
+
+    let gamma_buy = U256::from(FEE_DENOMINATOR - buy_pool.fee_tier.fee());
+    let gamma_sell = U256::from(FEE_DENOMINATOR - sell_pool.fee_tier.fee());
+    // sqrt((1 - fee_buy) * (1 - fee_sell)) in pips as a Q96
+    let gamma_x_96 = sqrt((gamma_buy * gamma_sell) << 192_usize);
+
+    // 1 / sqrt_price as Q96
+    let inverse_buy_x_96 = Q192 / buy_pool.slot0.sqrt_price_x_96;
+    let inverse_sell_x_96 = Q192 / sell_pool.slot0.sqrt_price_x_96;
+
+    let lhs = mul_div(gamma_x_96, inverse_buy_x_96, Q96)?;
+    let rhs = U256::from(FEE_DENOMINATOR) * inverse_sell_x_96;
+    if lhs <= rhs {
+        return Ok(U256::ZERO);
+    }
+
+    let liquidity_buy = U256::from(buy_pool.liquidity);
+    let liquidity_sell = U256::from(sell_pool.liquidity);
+    let denominator = mul_div(gamma_x_96, liquidity_sell, Q96)? + gamma_sell * liquidity_buy;
+
+    mul_div(
+        mul_div(lhs - rhs, liquidity_buy, Q96)?,
+        liquidity_sell,
+        denominator,
+    )
+}
+
+// quotes buying `amount_0` of token0 from the buy pool and selling it into the sell pool
+fn evaluate(
+    buy_pool: &PoolSnapshot,
+    sell_pool: &PoolSnapshot,
+    amount_0: U256,
+    buy_from_a: bool,
+) -> Result<Option<Arbitrage>, UniswapV3MathError> {
+    let bought = buy_pool.quote(false, -I256::from_raw(amount_0), MAX_SQRT_RATIO - U256_1)?;
+    // the buy pool may run out of liquidity before the full amount is delivered
+    let amount_0 = (-bought.amount_0).into_raw();
+    let amount_1_in = bought.amount_1.into_raw();
+    if amount_0.is_zero() {
+        return Ok(None);
+    }
+
+    let sold = sell_pool.quote(true, I256::from_raw(amount_0), MIN_SQRT_RATIO + U256_1)?;
+    let amount_1_out = (-sold.amount_1).into_raw();
+
+    if amount_1_out <= amount_1_in {
+        return Ok(None);
+    }
+
+    Ok(Some(Arbitrage {
+        buy_from_a,
+        amount_0,
+        amount_1_in,
+        amount_1_out,
+        profit: amount_1_out - amount_1_in,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{optimal_arbitrage, optimal_arbitrage_iterative};
+    use crate::{
+        fee_tier::FeeTier, pool::PoolSnapshot, test_utils::PoolBuilder,
+        tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    fn pool_at(tick_current: i32, fee_tier: FeeTier, ranges: &[(i32, i32, i128)]) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .fee_tier(fee_tier)
+            .positions(ranges)
+            .build()
+    }
+
+    #[test]
+    fn test_optimal_arbitrage() {
+        let pool_a = pool_at(0, FeeTier::Low, &[(-887270, 887270, 1e21 as i128)]);
+        let pool_b = pool_at(200, FeeTier::Medium, &[(-887220, 887220, 1e21 as i128)]);
+
+        //token0 is cheaper in pool a
+        let arbitrage = optimal_arbitrage(&pool_a, &pool_b).unwrap().unwrap();
+        assert!(arbitrage.buy_from_a);
+        assert_eq!(
+            arbitrage.profit,
+            arbitrage.amount_1_out - arbitrage.amount_1_in
+        );
+
+        //the iterative search agrees with the closed form up to rounding when no ticks are crossed
+        let iterative = optimal_arbitrage_iterative(&pool_a, &pool_b)
+            .unwrap()
+            .unwrap();
+        assert!(iterative.profit >= arbitrage.profit);
+        assert!(iterative.profit - arbitrage.profit <= U256::from(2));
+
+        //the direction flips with the pools
+        let reversed = optimal_arbitrage(&pool_b, &pool_a).unwrap().unwrap();
+        assert!(!reversed.buy_from_a);
+        assert_eq!(reversed.amount_0, arbitrage.amount_0);
+
+        //no arbitrage if the price difference does not cover the fees
+        let pool_c = pool_at(20, FeeTier::Medium, &[(-887220, 887220, 1e21 as i128)]);
+        assert_eq!(optimal_arbitrage(&pool_a, &pool_c).unwrap(), None);
+        assert_eq!(optimal_arbitrage_iterative(&pool_a, &pool_c).unwrap(), None);
+    }
+
+    #[test]
+    fn test_optimal_arbitrage_iterative_crosses_ticks() {
+        // pool a has concentrated liquidity that runs out just above the current price
+        let pool_a = pool_at(
+            0,
+            FeeTier::Low,
+            &[(-887270, 887270, 1e20 as i128), (-50, 50, 1e22 as i128)],
+        );
+        let pool_b = pool_at(400, FeeTier::Low, &[(-887270, 887270, 1e22 as i128)]);
+
+        let closed_form = optimal_arbitrage(&pool_a, &pool_b).unwrap();
+        let iterative = optimal_arbitrage_iterative(&pool_a, &pool_b)
+            .unwrap()
+            .unwrap();
+
+        // the closed form overestimates the depth of pool a above tick 50
+        assert!(iterative.profit > closed_form.map(|a| a.profit).unwrap_or_default());
+
+        // the optimum moves pool a past the concentrated range
+        let result = pool_a
+            .quote(
+                false,
+                -alloy_primitives::I256::from_raw(iterative.amount_0),
+                get_sqrt_ratio_at_tick(887000).unwrap(),
+            )
+            .unwrap();
+        assert!(result.tick >= 50);
+    }
+}
@@ -0,0 +1,711 @@
+use std::ops::ControlFlow;
+
+use alloy_primitives::U256;
+
+use crate::{
+    analytics::value_in_token_1,
+    constants::Q128,
+    error::UniswapV3MathError,
+    full_math::mul_div,
+    liquidity_amounts::get_liquidity_for_amounts,
+    pool::{PoolSnapshot, SwapParams, SwapStep},
+    position::{amounts_at_price, uncollected_fees},
+    storage::{InitializedTicks, TickStorage},
+    tick::get_fee_growth_inside,
+    tick_math::get_sqrt_ratio_at_tick,
+    variant::PoolVariant,
+};
+
+// a historical pool event to replay
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Swap(SwapParams),
+    Mint {
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    },
+    Burn {
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    },
+}
+
+impl Event {
+    // applies the event to a pool, the one place that maps events to pool updates
+    pub fn apply_to(&self, pool: &mut impl EventTarget) -> Result<(), UniswapV3MathError> {
+        match *self {
+            Event::Swap(params) => pool.apply_swap(&params),
+            Event::Mint {
+                tick_lower,
+                tick_upper,
+                liquidity,
+            } => pool.apply_liquidity(tick_lower, tick_upper, liquidity_delta(liquidity)?),
+            Event::Burn {
+                tick_lower,
+                tick_upper,
+                liquidity,
+            } => pool.apply_liquidity(tick_lower, tick_upper, -liquidity_delta(liquidity)?),
+        }
+    }
+}
+
+//A pool that events can be applied to, see `Event::apply_to`
+pub trait EventTarget {
+    // executes a swap
+    fn apply_swap(&mut self, params: &SwapParams) -> Result<(), UniswapV3MathError>;
+
+    // adds or removes liquidity, the amounts owed are of no interest when replaying
+    fn apply_liquidity(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> Result<(), UniswapV3MathError>;
+}
+
+impl<T: TickStorage, B: InitializedTicks, V: PoolVariant> EventTarget for PoolSnapshot<T, B, V> {
+    fn apply_swap(&mut self, params: &SwapParams) -> Result<(), UniswapV3MathError> {
+        self.swap_with(params).map(drop)
+    }
+
+    fn apply_liquidity(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> Result<(), UniswapV3MathError> {
+        self.modify_position(tick_lower, tick_upper, liquidity_delta)
+            .map(drop)
+    }
+}
+
+// an event scheduled to be applied before the historical event at index `before`, or after the last one if `before` is
+// the number of events or more, e.g. a known vault rebalance
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    pub before: usize,
+    pub event: Event,
+}
+
+// an action a strategy takes on a tracked position after an event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    // burns the position, collects its fees and mints as much liquidity as possible in the new range
+    Rebalance {
+        position: usize,
+        tick_lower: i32,
+        tick_upper: i32,
+    },
+}
+
+// a hypothetical position that is minted into the replayed pool, so it earns fees and affects swaps
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrackedPosition {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    // fee growth inside the range as of the last mint
+    pub fee_growth_inside_0_last_x_128: U256,
+    pub fee_growth_inside_1_last_x_128: U256,
+    // the fees collected on rebalances
+    pub fees_collected_0: U256,
+    pub fees_collected_1: U256,
+    // token balances that are not in the range, i.e. collected fees and dust left over from rebalances
+    pub idle_0: U256,
+    pub idle_1: U256,
+    // the token amounts initially deposited
+    pub deposited_0: U256,
+    pub deposited_1: U256,
+    // the number of rebalances
+    pub rebalances: u32,
+}
+
+// the state of a tracked position at the current pool price
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PositionReport {
+    // the token amounts held, including idle balances and uncollected fees
+    pub amount_0: U256,
+    pub amount_1: U256,
+    // all fees earned, collected or not
+    pub fees_0: U256,
+    pub fees_1: U256,
+    // the value of the position in token1
+    pub value: U256,
+    // the value in token1 of holding the deposited amounts instead
+    pub hodl_value: U256,
+}
+
+// the global fee growth added while the active liquidity of the pool was constant, i.e. during a single swap step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeInterval {
+    // the tick at the start of the step, the active liquidity is that of the positions whose range contains it
+    pub tick: i32,
+    pub liquidity: u128,
+    pub fee_growth_global_0_x_128_delta: U256,
+    pub fee_growth_global_1_x_128_delta: U256,
+}
+
+impl FeeInterval {
+    pub fn new(zero_for_one: bool, step: &SwapStep) -> Self {
+        let (delta_0, delta_1) = if zero_for_one {
+            (step.fee_growth_global_x_128_delta, U256::ZERO)
+        } else {
+            (U256::ZERO, step.fee_growth_global_x_128_delta)
+        };
+
+        Self {
+            tick: step.tick_before,
+            liquidity: step.liquidity,
+            fee_growth_global_0_x_128_delta: delta_0,
+            fee_growth_global_1_x_128_delta: delta_1,
+        }
+    }
+}
+
+// the fees attributed to liquidity in a range over a series of fee intervals, see `attribute_fees`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeAttribution {
+    // the fees earned by the liquidity, rounded down like the fees the pool owes a position
+    pub fees_0: U256,
+    pub fees_1: U256,
+    // the fees earned by all active liquidity in the intervals the range was active in
+    pub in_range_fees_0: U256,
+    pub in_range_fees_1: U256,
+    pub intervals_in_range: usize,
+}
+
+// returns the fees earned by `liquidity` in the given range over the intervals, e.g. the fee intervals recorded by a
+// backtest between two rebalances of a position. The fee growth of the intervals in which the range was active is
+// summed and multiplied by the liquidity once, the way the pool computes the fees of a position from its fee growth
+// inside, so the fees are exactly those the pool owes a position that held `liquidity` in the range throughout
+pub fn attribute_fees(
+    intervals: &[FeeInterval],
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+) -> Result<FeeAttribution, UniswapV3MathError> {
+    let mut attribution = FeeAttribution::default();
+    let mut fee_growth_0_x_128 = U256::ZERO;
+    let mut fee_growth_1_x_128 = U256::ZERO;
+
+    for interval in intervals {
+        if interval.tick < tick_lower || interval.tick >= tick_upper {
+            continue;
+        }
+
+        fee_growth_0_x_128 =
+            fee_growth_0_x_128.wrapping_add(interval.fee_growth_global_0_x_128_delta);
+        fee_growth_1_x_128 =
+            fee_growth_1_x_128.wrapping_add(interval.fee_growth_global_1_x_128_delta);
+        attribution.in_range_fees_0 += mul_div(
+            interval.fee_growth_global_0_x_128_delta,
+            U256::from(interval.liquidity),
+            Q128,
+        )?;
+        attribution.in_range_fees_1 += mul_div(
+            interval.fee_growth_global_1_x_128_delta,
+            U256::from(interval.liquidity),
+            Q128,
+        )?;
+        attribution.intervals_in_range += 1;
+    }
+
+    attribution.fees_0 = mul_div(fee_growth_0_x_128, U256::from(liquidity), Q128)?;
+    attribution.fees_1 = mul_div(fee_growth_1_x_128, U256::from(liquidity), Q128)?;
+    Ok(attribution)
+}
+
+//Replays pool events against a snapshot while tracking hypothetical positions
+#[derive(Clone, Debug)]
+pub struct Backtest {
+    pub pool: PoolSnapshot,
+    pub positions: Vec<TrackedPosition>,
+    // the fee interval of every swap step, only recorded if enabled
+    fee_intervals: Option<Vec<FeeInterval>>,
+}
+
+impl Backtest {
+    pub fn new(pool: PoolSnapshot) -> Self {
+        Self {
+            pool,
+            positions: vec![],
+            fee_intervals: None,
+        }
+    }
+
+    // records a fee interval for every step of the swaps replayed from now on, see `attribute_fees`
+    pub fn record_fee_intervals(mut self) -> Self {
+        self.fee_intervals.get_or_insert_with(Vec::new);
+        self
+    }
+
+    // the recorded fee intervals in the order of the swap steps, empty unless recording is enabled
+    pub fn fee_intervals(&self) -> &[FeeInterval] {
+        self.fee_intervals.as_deref().unwrap_or_default()
+    }
+
+    // mints a tracked position and returns its index
+    pub fn add_position(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    ) -> Result<usize, UniswapV3MathError> {
+        let mut position = TrackedPosition {
+            tick_lower,
+            tick_upper,
+            ..Default::default()
+        };
+        let (amount_0, amount_1) = self.mint(&mut position, liquidity)?;
+        position.deposited_0 = amount_0;
+        position.deposited_1 = amount_1;
+
+        self.positions.push(position);
+        Ok(self.positions.len() - 1)
+    }
+
+    // applies a single event to the pool
+    pub fn apply(&mut self, event: &Event) -> Result<(), UniswapV3MathError> {
+        match (event, &mut self.fee_intervals) {
+            (Event::Swap(params), Some(fee_intervals)) => {
+                // the intervals are taken from the steps of the swap itself, kept only if it succeeds
+                let mut steps = vec![];
+                let state = self
+                    .pool
+                    .compute_swap_with(params, &mut |step: &SwapStep| {
+                        steps.push(FeeInterval::new(params.zero_for_one, step));
+                        ControlFlow::Continue(())
+                    })?;
+                self.pool.commit_swap(params.zero_for_one, &state);
+                fee_intervals.extend(steps);
+                Ok(())
+            }
+            _ => event.apply_to(&mut self.pool),
+        }
+    }
+
+    // replays the events, calling `strategy` after each one and executing the actions it returns
+    pub fn run<F>(&mut self, events: &[Event], strategy: F) -> Result<(), UniswapV3MathError>
+    where
+        F: FnMut(&Backtest) -> Vec<Action>,
+    {
+        self.run_with_schedule(events, &[], strategy)
+    }
+
+    // same as run, interleaving the scheduled events with the historical ones. Scheduled events for the same index are
+    // applied in the order given, and `strategy` is called after each of them as well
+    pub fn run_with_schedule<F>(
+        &mut self,
+        events: &[Event],
+        schedule: &[ScheduledEvent],
+        mut strategy: F,
+    ) -> Result<(), UniswapV3MathError>
+    where
+        F: FnMut(&Backtest) -> Vec<Action>,
+    {
+        let mut schedule = schedule.iter().collect::<Vec<_>>();
+        schedule.sort_by_key(|scheduled| scheduled.before);
+        let mut scheduled = schedule.into_iter().peekable();
+
+        for (index, event) in events.iter().enumerate() {
+            while let Some(next) = scheduled.next_if(|scheduled| scheduled.before <= index) {
+                self.step(&next.event, &mut strategy)?;
+            }
+            self.step(event, &mut strategy)?;
+        }
+        // everything scheduled beyond the historical events is applied after the last one
+        for next in scheduled {
+            self.step(&next.event, &mut strategy)?;
+        }
+
+        Ok(())
+    }
+
+    fn step<F>(&mut self, event: &Event, strategy: &mut F) -> Result<(), UniswapV3MathError>
+    where
+        F: FnMut(&Backtest) -> Vec<Action>,
+    {
+        self.apply(event)?;
+
+        for action in strategy(self) {
+            match action {
+                Action::Rebalance {
+                    position,
+                    tick_lower,
+                    tick_upper,
+                } => self.rebalance(position, tick_lower, tick_upper)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    // burns a tracked position, collects its fees and mints as much liquidity as the balances allow in the new range,
+    // anything left over stays idle. The burn and the mint succeed or fail together, if the mint fails the pool and
+    // the position are left as they were
+    pub fn rebalance(
+        &mut self,
+        index: usize,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<(), UniswapV3MathError> {
+        let position = *self
+            .positions
+            .get(index)
+            .ok_or(UniswapV3MathError::PositionNotFound)?;
+
+        // the burn can only be undone by restoring the pool
+        let pool = self.pool.clone();
+        match self.move_position(position, tick_lower, tick_upper) {
+            Ok(position) => {
+                self.positions[index] = position;
+                Ok(())
+            }
+            Err(err) => {
+                self.pool = pool;
+                Err(err)
+            }
+        }
+    }
+
+    fn move_position(
+        &mut self,
+        mut position: TrackedPosition,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<TrackedPosition, UniswapV3MathError> {
+        let (fees_0, fees_1) = self.uncollected_fees(&position)?;
+        let (amount_0, amount_1) = self.pool.modify_position(
+            position.tick_lower,
+            position.tick_upper,
+            -liquidity_delta(position.liquidity)?,
+        )?;
+        position.fees_collected_0 += fees_0;
+        position.fees_collected_1 += fees_1;
+        position.idle_0 += amount_0.unsigned_abs() + fees_0;
+        position.idle_1 += amount_1.unsigned_abs() + fees_1;
+
+        position.tick_lower = tick_lower;
+        position.tick_upper = tick_upper;
+        let liquidity = get_liquidity_for_amounts(
+            self.pool.slot0.sqrt_price_x_96,
+            get_sqrt_ratio_at_tick(tick_lower)?,
+            get_sqrt_ratio_at_tick(tick_upper)?,
+            position.idle_0,
+            position.idle_1,
+        )?;
+        // the pool rounds the amounts up, so the liquidity is reduced by one to stay within the balances
+        let (amount_0, amount_1) = self.mint(&mut position, liquidity.saturating_sub(1))?;
+        position.idle_0 -= amount_0;
+        position.idle_1 -= amount_1;
+        position.rebalances += 1;
+
+        Ok(position)
+    }
+
+    // returns the holdings, fees and value of a tracked position against holding the deposited amounts
+    pub fn report(&self, index: usize) -> Result<PositionReport, UniswapV3MathError> {
+        let position = self
+            .positions
+            .get(index)
+            .ok_or(UniswapV3MathError::PositionNotFound)?;
+        let sqrt_price_x_96 = self.pool.slot0.sqrt_price_x_96;
+
+        let (fees_0, fees_1) = self.uncollected_fees(position)?;
+        let (amount_0, amount_1) = amounts_at_price(
+            sqrt_price_x_96,
+            position.tick_lower,
+            position.tick_upper,
+            position.liquidity,
+        )?;
+        let amount_0 = amount_0 + position.idle_0 + fees_0;
+        let amount_1 = amount_1 + position.idle_1 + fees_1;
+
+        Ok(PositionReport {
+            amount_0,
+            amount_1,
+            fees_0: position.fees_collected_0 + fees_0,
+            fees_1: position.fees_collected_1 + fees_1,
+            value: value_in_token_1(amount_0, amount_1, sqrt_price_x_96)?,
+            hodl_value: value_in_token_1(
+                position.deposited_0,
+                position.deposited_1,
+                sqrt_price_x_96,
+            )?,
+        })
+    }
+
+    // mints liquidity for the position at its range and checkpoints its fee growth, returns the amounts paid
+    fn mint(
+        &mut self,
+        position: &mut TrackedPosition,
+        liquidity: u128,
+    ) -> Result<(U256, U256), UniswapV3MathError> {
+        let (amount_0, amount_1) = self.pool.modify_position(
+            position.tick_lower,
+            position.tick_upper,
+            liquidity_delta(liquidity)?,
+        )?;
+
+        position.liquidity = liquidity;
+        (
+            position.fee_growth_inside_0_last_x_128,
+            position.fee_growth_inside_1_last_x_128,
+        ) = get_fee_growth_inside(
+            &self.pool.ticks,
+            position.tick_lower,
+            position.tick_upper,
+            self.pool.slot0.tick,
+            self.pool.fee_growth_global_0_x_128,
+            self.pool.fee_growth_global_1_x_128,
+        );
+
+        Ok((amount_0.into_raw(), amount_1.into_raw()))
+    }
+
+    fn uncollected_fees(
+        &self,
+        position: &TrackedPosition,
+    ) -> Result<(U256, U256), UniswapV3MathError> {
+        if position.liquidity == 0 {
+            return Ok((U256::ZERO, U256::ZERO));
+        }
+
+        uncollected_fees(
+            &self.pool,
+            position.tick_lower,
+            position.tick_upper,
+            position.liquidity,
+            position.fee_growth_inside_0_last_x_128,
+            position.fee_growth_inside_1_last_x_128,
+        )
+    }
+}
+
+pub(crate) fn liquidity_delta(liquidity: u128) -> Result<i128, UniswapV3MathError> {
+    i128::try_from(liquidity).map_err(|_| UniswapV3MathError::LiquidityOverflow)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{attribute_fees, Action, Backtest, Event, ScheduledEvent};
+    use crate::{
+        error::UniswapV3MathError,
+        test_utils::{swap, PoolBuilder},
+    };
+
+    fn backtest() -> Backtest {
+        Backtest::new(PoolBuilder::at_tick(0).full_range(1e20 as i128).build())
+    }
+
+    #[test]
+    fn test_backtest_accrues_fees() {
+        let mut backtest = backtest();
+        let index = backtest.add_position(-600, 600, 1e20 as u128).unwrap();
+        let deposited = backtest.report(index).unwrap();
+
+        //swapping back and forth earns fees in both tokens
+        let events = [swap(true, 1e18 as u128), swap(false, 1e18 as u128)].repeat(5);
+        backtest.run(&events, |_| vec![]).unwrap();
+
+        let report = backtest.report(index).unwrap();
+        assert!(report.fees_0 > U256::ZERO && report.fees_1 > U256::ZERO);
+        // the position holds about half of the in range liquidity and earns about half of the 0.3% fee
+        let fee = U256::from(5 * 3e15 as u128);
+        assert!(report.fees_0 > fee * U256::from(45) / U256::from(100));
+        assert!(report.fees_0 < fee * U256::from(55) / U256::from(100));
+        assert!(report.value > deposited.value);
+        assert_eq!(backtest.positions[index].rebalances, 0);
+
+        //mints and burns change the liquidity of the pool
+        backtest
+            .apply(&Event::Mint {
+                tick_lower: -60,
+                tick_upper: 60,
+                liquidity: 1e18 as u128,
+            })
+            .unwrap();
+        assert!(backtest.pool.liquidity > 2e20 as u128);
+        backtest
+            .apply(&Event::Burn {
+                tick_lower: -60,
+                tick_upper: 60,
+                liquidity: 1e18 as u128,
+            })
+            .unwrap();
+        assert!(backtest.pool.liquidity < 2e20 as u128 + 1);
+    }
+
+    #[test]
+    fn test_backtest_rebalances() {
+        let mut backtest = backtest();
+        let index = backtest.add_position(-120, 120, 1e19 as u128).unwrap();
+
+        //moves the position, which is all token0 once the price falls through it, to just above the price
+        let events = vec![swap(true, 5e18 as u128); 10];
+        backtest
+            .run(&events, |backtest| {
+                let position = &backtest.positions[index];
+                let tick = backtest.pool.slot0.tick;
+                if tick < position.tick_lower {
+                    let tick_lower = tick.div_euclid(60) * 60 + 60;
+                    vec![Action::Rebalance {
+                        position: index,
+                        tick_lower,
+                        tick_upper: tick_lower + 240,
+                    }]
+                } else {
+                    vec![]
+                }
+            })
+            .unwrap();
+
+        let position = backtest.positions[index];
+        assert!(position.rebalances > 1);
+        assert!(position.tick_lower > backtest.pool.slot0.tick);
+        assert!(position.liquidity > 0);
+        assert_eq!(position.idle_1, position.fees_collected_1);
+
+        //fees collected on rebalances are included in the report, and the price drop causes a loss against holding
+        let report = backtest.report(index).unwrap();
+        assert!(position.fees_collected_0 > U256::ZERO);
+        assert!(report.fees_0 >= position.fees_collected_0);
+        assert!(report.value < report.hodl_value);
+
+        //a mint that fails after the burn leaves the pool and the position as they were
+        let (pool, position) = (backtest.pool.clone(), backtest.positions[index]);
+        let result = backtest.rebalance(index, 0, 121);
+        assert!(matches!(result, Err(UniswapV3MathError::TickSpacingError)));
+        assert_eq!(backtest.pool, pool);
+        assert_eq!(backtest.positions[index], position);
+
+        //unknown positions are an error
+        assert!(matches!(
+            backtest.rebalance(index + 1, 0, 120),
+            Err(UniswapV3MathError::PositionNotFound)
+        ));
+        assert!(matches!(
+            backtest.report(index + 1),
+            Err(UniswapV3MathError::PositionNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_backtest_schedule() {
+        let events = [swap(true, 1e18 as u128), swap(false, 1e18 as u128)].repeat(4);
+        let vault = Event::Mint {
+            tick_lower: -600,
+            tick_upper: 600,
+            liquidity: 1e20 as u128,
+        };
+        let vault_burn = Event::Burn {
+            tick_lower: -600,
+            tick_upper: 600,
+            liquidity: 1e20 as u128,
+        };
+
+        let mut without = backtest();
+        let index = without.add_position(-600, 600, 1e20 as u128).unwrap();
+        without.run(&events, |_| vec![]).unwrap();
+
+        //a vault in the same range for half of the swaps takes a share of the fees
+        let mut with = backtest();
+        with.add_position(-600, 600, 1e20 as u128).unwrap();
+        let liquidity = with.pool.liquidity;
+        let schedule = [
+            ScheduledEvent {
+                before: 4,
+                event: vault_burn,
+            },
+            ScheduledEvent {
+                before: 0,
+                event: vault,
+            },
+        ];
+        let mut liquidities = vec![];
+        with.run_with_schedule(&events, &schedule, |backtest| {
+            liquidities.push(backtest.pool.liquidity);
+            vec![]
+        })
+        .unwrap();
+        assert_eq!(liquidities.len(), events.len() + schedule.len());
+        assert!(liquidities[..5].iter().all(|&l| l > liquidity));
+        assert!(liquidities[5..]
+            .iter()
+            .all(|&l| l < liquidity + 1e18 as u128));
+
+        let fees = with.report(index).unwrap().fees_0;
+        let fees_without = without.report(index).unwrap().fees_0;
+        assert!(fees < fees_without);
+        assert!(fees > fees_without * U256::from(2) / U256::from(3));
+
+        //events scheduled after the last one are applied at the end, as are events scheduled beyond it
+        for before in [events.len(), events.len() + 10] {
+            let mut end = backtest();
+            let mut liquidities = vec![];
+            end.run_with_schedule(
+                &events,
+                &[ScheduledEvent {
+                    before,
+                    event: vault,
+                }],
+                |backtest| {
+                    liquidities.push(backtest.pool.liquidity);
+                    vec![]
+                },
+            )
+            .unwrap();
+            assert_eq!(liquidities.len(), events.len() + 1);
+            assert!(liquidities[..events.len()]
+                .iter()
+                .all(|&l| l < 1e20 as u128 * 2));
+            assert!(end.pool.liquidity > 1e20 as u128 * 2 - 1);
+        }
+    }
+
+    #[test]
+    fn test_attribute_fees() {
+        let mut backtest = backtest().record_fee_intervals();
+        let index = backtest.add_position(-600, 600, 1e20 as u128).unwrap();
+        backtest
+            .apply(&Event::Mint {
+                tick_lower: -6000,
+                tick_upper: -1200,
+                liquidity: 1e21 as u128,
+            })
+            .unwrap();
+
+        //the price moves out of the range of the position and back
+        let events = [swap(true, 3e19 as u128), swap(false, 3e19 as u128)].repeat(2);
+        backtest.run(&events, |_| vec![]).unwrap();
+        let intervals = backtest.fee_intervals();
+        assert!(intervals.iter().any(|interval| interval.tick < -600));
+
+        //the attributed fees are exactly the fees the pool owes the position
+        let attribution = attribute_fees(intervals, -600, 600, 1e20 as u128).unwrap();
+        let report = backtest.report(index).unwrap();
+        assert_eq!(
+            (attribution.fees_0, attribution.fees_1),
+            (report.fees_0, report.fees_1)
+        );
+        assert!(attribution.intervals_in_range < intervals.len());
+
+        //the position holds half of the liquidity in its range
+        assert!(attribution.in_range_fees_0 > attribution.fees_0 * U256::from(2) - U256::from(10));
+        assert!(attribution.in_range_fees_0 < attribution.fees_0 * U256::from(2) + U256::from(10));
+
+        //nothing is attributed to a range the price never reached
+        let attribution = attribute_fees(intervals, 6000, 12000, 1e20 as u128).unwrap();
+        assert_eq!(attribution.fees_0 + attribution.fees_1, U256::ZERO);
+        assert_eq!(attribution.intervals_in_range, 0);
+
+        //nothing is recorded unless enabled
+        let mut plain = Backtest::new(backtest.pool.clone());
+        plain.run(&events, |_| vec![]).unwrap();
+        assert!(plain.fee_intervals().is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use std::{str::FromStr, sync::Arc};
+
+use alloy::providers::ProviderBuilder;
+use alloy_primitives::{Address, U256};
+use uniswap_v3_math::{
+    fetch::{pool_snapshot_multicall, DEFAULT_MULTICALL_BATCH_SIZE, MULTICALL3_ADDRESS},
+    pool::SwapParams,
+    quote::{price_impact, GasModel, QuoteResponse},
+};
+
+// fetches a pool over RPC and prints a quote computed locally against the snapshot, the reference for wiring the fetch
+// and simulation pieces together. Build with `cargo run --features cli --bin uniswap-v3-quote -- <args>`. Amounts and
+// prices are raw, i.e. not scaled by the token decimals, prices are of token0 in token1
+
+const USAGE: &str = "usage: uniswap-v3-quote --rpc-url <URL> --pool <ADDRESS> (--amount-in <AMOUNT> | --amount-out \
+                     <AMOUNT>) [--one-for-zero] [--block <NUMBER>]";
+
+struct Args {
+    rpc_url: String,
+    pool: Address,
+    // the amount of an exact input swap, or of an exact output swap if `exact_input` is false
+    amount: U256,
+    exact_input: bool,
+    zero_for_one: bool,
+    block: Option<u64>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> eyre::Result<Self> {
+        let (mut rpc_url, mut pool, mut amount, mut block) = (None, None, None, None);
+        let mut zero_for_one = true;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| eyre::eyre!("missing value for {arg}\n{USAGE}"))
+            };
+            match arg.as_str() {
+                "--rpc-url" => rpc_url = Some(value()?),
+                "--pool" => pool = Some(Address::from_str(&value()?)?),
+                "--amount-in" => amount = Some((U256::from_str(&value()?)?, true)),
+                "--amount-out" => amount = Some((U256::from_str(&value()?)?, false)),
+                "--block" => block = Some(value()?.parse()?),
+                "--one-for-zero" => zero_for_one = false,
+                _ => eyre::bail!("unknown argument {arg}\n{USAGE}"),
+            }
+        }
+
+        let (amount, exact_input) = amount.ok_or_else(|| eyre::eyre!("missing amount\n{USAGE}"))?;
+        Ok(Self {
+            rpc_url: rpc_url.ok_or_else(|| eyre::eyre!("missing --rpc-url\n{USAGE}"))?,
+            pool: pool.ok_or_else(|| eyre::eyre!("missing --pool\n{USAGE}"))?,
+            amount,
+            exact_input,
+            zero_for_one,
+            block,
+        })
+    }
+}
+
+// returns a Q96 price as f64
+fn x96_to_f64(value_x_96: U256) -> f64 {
+    f64::from(value_x_96) / 2f64.powi(96)
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let args = Args::parse(std::env::args().skip(1))?;
+
+    let provider = Arc::new(ProviderBuilder::new().on_http(args.rpc_url.parse()?));
+    let pool = pool_snapshot_multicall(
+        provider,
+        args.pool,
+        MULTICALL3_ADDRESS,
+        args.block,
+        DEFAULT_MULTICALL_BATCH_SIZE,
+    )
+    .await?;
+
+    let builder = SwapParams::builder().zero_for_one(args.zero_for_one);
+    let builder = if args.exact_input {
+        builder.amount_in(args.amount)
+    } else {
+        builder.amount_out(args.amount)
+    };
+    let params = builder.build()?;
+    let result = pool.quote_with(&params)?;
+    let quote = QuoteResponse::new(args.zero_for_one, &result, &GasModel::default());
+    // the exact input swap of the input of an exact output quote is the same swap
+    let impact = price_impact(&pool, quote.amount_in, args.zero_for_one)?;
+
+    println!("pool           {}", args.pool);
+    println!(
+        "direction      {}",
+        if args.zero_for_one {
+            "token0 -> token1"
+        } else {
+            "token1 -> token0"
+        }
+    );
+    println!("amount in      {}", quote.amount_in);
+    println!("amount out     {}", quote.amount_out);
+    println!("fee            {}", quote.fee_amount);
+    if !result.amount_specified_remaining.is_zero() {
+        println!(
+            "unfilled       {}",
+            result.amount_specified_remaining.unsigned_abs()
+        );
+    }
+    println!("tick           {} -> {}", pool.slot0.tick, quote.tick_after);
+    println!("ticks crossed  {}", quote.initialized_ticks_crossed);
+    println!("gas estimate   {}", quote.gas_estimate);
+    println!(
+        "price          {} -> {}",
+        x96_to_f64(impact.spot_price_x_96),
+        x96_to_f64(impact.price_after_x_96)
+    );
+    println!("execution      {}", x96_to_f64(impact.execution_price_x_96));
+    println!("price impact   {} bps", impact.impact_bps);
+
+    Ok(())
+}
@@ -0,0 +1,337 @@
+use std::{
+    cell::RefCell,
+    ffi::CString,
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use alloy_primitives::{I256, U256};
+
+use crate::{error::UniswapV3MathError, sqrt_price_math, swap_math::compute_swap_step, tick_math};
+
+// a C ABI over the core math, so code that is not written in Rust can link the same math. uint256 and int256 values
+// are passed as 32 byte big endian arrays and uint128 and int128 values as 16 byte big endian arrays, signed values in
+// two's complement, the same encoding the EVM uses for its words. Pointers are taken as nullable references, so passing
+// null fails with `UV3_NULL_POINTER` instead of dereferencing it. Functions return `UV3_OK` on success and leave their
+// outputs untouched otherwise, the message of the last error on the calling thread is returned by
+// `uv3_last_error_message`. A panic never unwinds into the caller, it is caught and reported as `UV3_PANIC`. Build a static or dynamic library with
+// `cargo rustc --release --features capi --crate-type staticlib` (or `cdylib`), the declarations are in
+// `include/uniswap_v3_math.h`
+
+pub const UV3_OK: i32 = 0;
+// the math failed, e.g. a tick out of bounds or an overflow, the same cases in which the contract reverts
+pub const UV3_ERROR: i32 = 1;
+pub const UV3_NULL_POINTER: i32 = 2;
+// a bug in the library, unwinding across the C ABI is undefined behavior so the panic is caught at the boundary
+pub const UV3_PANIC: i32 = 3;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// runs the body of an exported function and maps its result, or its panic, to a status code
+fn status(f: impl FnOnce() -> Result<(), Option<UniswapV3MathError>>) -> i32 {
+    let (code, message) = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => return UV3_OK,
+        Ok(Err(Some(error))) => (UV3_ERROR, error.to_string()),
+        Ok(Err(None)) => (UV3_NULL_POINTER, "null pointer".to_string()),
+        Err(payload) => {
+            let reason = payload
+                .downcast_ref::<&str>()
+                .map(|reason| reason.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            (UV3_PANIC, format!("panic: {reason}"))
+        }
+    };
+    // a nul byte would cut the message short, panic messages are not ours to vouch for
+    let message = message.replace('\0', "");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+    code
+}
+
+fn int128(bytes: [u8; 16]) -> i128 {
+    i128::from_be_bytes(bytes)
+}
+
+fn uint128(bytes: [u8; 16]) -> u128 {
+    u128::from_be_bytes(bytes)
+}
+
+fn uint256(bytes: [u8; 32]) -> U256 {
+    U256::from_be_bytes(bytes)
+}
+
+fn int256(bytes: [u8; 32]) -> I256 {
+    I256::from_raw(U256::from_be_bytes(bytes))
+}
+
+// returns the message of the last failed call on this thread, or null if no call failed yet. The string is owned by
+// the library and valid until the next failed call on the same thread
+#[no_mangle]
+pub extern "C" fn uv3_last_error_message() -> *const c_char {
+    panic::catch_unwind(|| {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    })
+    .unwrap_or(ptr::null())
+}
+
+// see `tick_math::get_sqrt_ratio_at_tick`
+#[no_mangle]
+pub extern "C" fn uv3_get_sqrt_ratio_at_tick(
+    tick: i32,
+    sqrt_ratio_x_96: Option<&mut [u8; 32]>,
+) -> i32 {
+    status(|| {
+        let out = sqrt_ratio_x_96.ok_or(None)?;
+        *out = tick_math::get_sqrt_ratio_at_tick(tick)?.to_be_bytes();
+        Ok(())
+    })
+}
+
+// see `tick_math::get_tick_at_sqrt_ratio`
+#[no_mangle]
+pub extern "C" fn uv3_get_tick_at_sqrt_ratio(
+    sqrt_ratio_x_96: Option<&[u8; 32]>,
+    tick: Option<&mut i32>,
+) -> i32 {
+    status(|| {
+        let (sqrt_ratio_x_96, out) = sqrt_ratio_x_96.zip(tick).ok_or(None)?;
+        *out = tick_math::get_tick_at_sqrt_ratio(uint256(*sqrt_ratio_x_96))?;
+        Ok(())
+    })
+}
+
+// the signed token0 delta of a signed liquidity delta, see `sqrt_price_math::get_amount_0_delta`
+#[no_mangle]
+pub extern "C" fn uv3_get_amount_0_delta(
+    sqrt_ratio_a_x_96: Option<&[u8; 32]>,
+    sqrt_ratio_b_x_96: Option<&[u8; 32]>,
+    liquidity: Option<&[u8; 16]>,
+    amount_0: Option<&mut [u8; 32]>,
+) -> i32 {
+    status(|| {
+        let (((a, b), liquidity), out) = sqrt_ratio_a_x_96
+            .zip(sqrt_ratio_b_x_96)
+            .zip(liquidity)
+            .zip(amount_0)
+            .ok_or(None)?;
+        *out = sqrt_price_math::get_amount_0_delta(uint256(*a), uint256(*b), int128(*liquidity))?
+            .into_raw()
+            .to_be_bytes();
+        Ok(())
+    })
+}
+
+// the signed token1 delta of a signed liquidity delta, see `sqrt_price_math::get_amount_1_delta`
+#[no_mangle]
+pub extern "C" fn uv3_get_amount_1_delta(
+    sqrt_ratio_a_x_96: Option<&[u8; 32]>,
+    sqrt_ratio_b_x_96: Option<&[u8; 32]>,
+    liquidity: Option<&[u8; 16]>,
+    amount_1: Option<&mut [u8; 32]>,
+) -> i32 {
+    status(|| {
+        let (((a, b), liquidity), out) = sqrt_ratio_a_x_96
+            .zip(sqrt_ratio_b_x_96)
+            .zip(liquidity)
+            .zip(amount_1)
+            .ok_or(None)?;
+        *out = sqrt_price_math::get_amount_1_delta(uint256(*a), uint256(*b), int128(*liquidity))?
+            .into_raw()
+            .to_be_bytes();
+        Ok(())
+    })
+}
+
+//The outputs of a single swap step, see `uv3_compute_swap_step`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Uv3SwapStep {
+    pub sqrt_ratio_next_x_96: [u8; 32],
+    pub amount_in: [u8; 32],
+    pub amount_out: [u8; 32],
+    pub fee_amount: [u8; 32],
+}
+
+// a single step of a swap within one tick range, see `swap_math::compute_swap_step`. The amount remaining is an int256,
+// positive for exact input and negative for exact output, and the liquidity a uint128
+#[no_mangle]
+pub extern "C" fn uv3_compute_swap_step(
+    sqrt_ratio_current_x_96: Option<&[u8; 32]>,
+    sqrt_ratio_target_x_96: Option<&[u8; 32]>,
+    liquidity: Option<&[u8; 16]>,
+    amount_remaining: Option<&[u8; 32]>,
+    fee_pips: u32,
+    step: Option<&mut Uv3SwapStep>,
+) -> i32 {
+    status(|| {
+        let ((((current, target), liquidity), amount_remaining), out) = sqrt_ratio_current_x_96
+            .zip(sqrt_ratio_target_x_96)
+            .zip(liquidity)
+            .zip(amount_remaining)
+            .zip(step)
+            .ok_or(None)?;
+        let (sqrt_ratio_next_x_96, amount_in, amount_out, fee_amount) = compute_swap_step(
+            uint256(*current),
+            uint256(*target),
+            uint128(*liquidity),
+            int256(*amount_remaining),
+            fee_pips,
+        )?;
+        *out = Uv3SwapStep {
+            sqrt_ratio_next_x_96: sqrt_ratio_next_x_96.to_be_bytes(),
+            amount_in: amount_in.to_be_bytes(),
+            amount_out: amount_out.to_be_bytes(),
+            fee_amount: fee_amount.to_be_bytes(),
+        };
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::CStr;
+
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        status, uv3_compute_swap_step, uv3_get_amount_0_delta, uv3_get_amount_1_delta,
+        uv3_get_sqrt_ratio_at_tick, uv3_get_tick_at_sqrt_ratio, uv3_last_error_message,
+        Uv3SwapStep, UV3_ERROR, UV3_NULL_POINTER, UV3_OK, UV3_PANIC,
+    };
+    use crate::{
+        sqrt_price_math::{get_amount_0_delta, get_amount_1_delta},
+        swap_math::compute_swap_step,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_TICK},
+    };
+
+    #[test]
+    fn test_capi() {
+        let mut sqrt_ratio = [0; 32];
+        assert_eq!(
+            uv3_get_sqrt_ratio_at_tick(-600, Some(&mut sqrt_ratio)),
+            UV3_OK
+        );
+        let sqrt_ratio_lower = get_sqrt_ratio_at_tick(-600).unwrap();
+        assert_eq!(U256::from_be_bytes(sqrt_ratio), sqrt_ratio_lower);
+
+        let mut tick = 0;
+        assert_eq!(
+            uv3_get_tick_at_sqrt_ratio(Some(&sqrt_ratio), Some(&mut tick)),
+            UV3_OK
+        );
+        assert_eq!(tick, -600);
+
+        //amount deltas are signed, negative liquidity is passed in two's complement
+        let sqrt_ratio_upper = get_sqrt_ratio_at_tick(600).unwrap();
+        for liquidity in [1e18 as i128, -1e18 as i128] {
+            let (mut amount_0, mut amount_1) = ([0; 32], [0; 32]);
+            let args = (
+                &sqrt_ratio_lower.to_be_bytes(),
+                &sqrt_ratio_upper.to_be_bytes(),
+                &liquidity.to_be_bytes(),
+            );
+            assert_eq!(
+                uv3_get_amount_0_delta(
+                    Some(args.0),
+                    Some(args.1),
+                    Some(args.2),
+                    Some(&mut amount_0)
+                ),
+                UV3_OK
+            );
+            assert_eq!(
+                uv3_get_amount_1_delta(
+                    Some(args.0),
+                    Some(args.1),
+                    Some(args.2),
+                    Some(&mut amount_1)
+                ),
+                UV3_OK
+            );
+            assert_eq!(
+                I256::from_raw(U256::from_be_bytes(amount_0)),
+                get_amount_0_delta(sqrt_ratio_lower, sqrt_ratio_upper, liquidity).unwrap()
+            );
+            assert_eq!(
+                I256::from_raw(U256::from_be_bytes(amount_1)),
+                get_amount_1_delta(sqrt_ratio_lower, sqrt_ratio_upper, liquidity).unwrap()
+            );
+        }
+
+        let amount_remaining = -I256::from_raw(U256::from(1e15 as u128));
+        let mut step = Uv3SwapStep::default();
+        assert_eq!(
+            uv3_compute_swap_step(
+                Some(&sqrt_ratio_upper.to_be_bytes()),
+                Some(&sqrt_ratio_lower.to_be_bytes()),
+                Some(&(1e18 as u128).to_be_bytes()),
+                Some(&amount_remaining.into_raw().to_be_bytes()),
+                3000,
+                Some(&mut step),
+            ),
+            UV3_OK
+        );
+        let (sqrt_ratio_next, amount_in, amount_out, fee_amount) = compute_swap_step(
+            sqrt_ratio_upper,
+            sqrt_ratio_lower,
+            1e18 as u128,
+            amount_remaining,
+            3000,
+        )
+        .unwrap();
+        assert_eq!(
+            step,
+            Uv3SwapStep {
+                sqrt_ratio_next_x_96: sqrt_ratio_next.to_be_bytes(),
+                amount_in: amount_in.to_be_bytes(),
+                amount_out: amount_out.to_be_bytes(),
+                fee_amount: fee_amount.to_be_bytes(),
+            }
+        );
+
+        //fails if the math fails, leaving the output untouched
+        let mut out = [7; 32];
+        assert_eq!(
+            uv3_get_sqrt_ratio_at_tick(MAX_TICK + 1, Some(&mut out)),
+            UV3_ERROR
+        );
+        assert_eq!(out, [7; 32]);
+        let message = unsafe { CStr::from_ptr(uv3_last_error_message()) };
+        assert!(message.to_str().unwrap().contains("maximum tick"));
+
+        //or if a pointer is null
+        assert_eq!(
+            uv3_get_tick_at_sqrt_ratio(Some(&sqrt_ratio), None),
+            UV3_NULL_POINTER
+        );
+        let message = unsafe { CStr::from_ptr(uv3_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "null pointer");
+
+        //an exact output of INT256_MIN is an error, not a panic
+        let mut step = Uv3SwapStep::default();
+        assert_eq!(
+            uv3_compute_swap_step(
+                Some(&sqrt_ratio_upper.to_be_bytes()),
+                Some(&sqrt_ratio_lower.to_be_bytes()),
+                Some(&(1e18 as u128).to_be_bytes()),
+                Some(&I256::MIN.into_raw().to_be_bytes()),
+                3000,
+                Some(&mut step),
+            ),
+            UV3_ERROR
+        );
+        assert_eq!(step, Uv3SwapStep::default());
+
+        //panics are caught at the boundary
+        assert_eq!(status(|| panic!("invariant broken")), UV3_PANIC);
+        let message = unsafe { CStr::from_ptr(uv3_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "panic: invariant broken");
+    }
+}
@@ -0,0 +1,224 @@
+use alloy_primitives::{address, b256, Address, B256};
+
+use crate::{
+    fee_tier::FeeTier,
+    pool_key::{PoolKey, POOL_INIT_CODE_HASH, UNISWAP_V3_FACTORY},
+    variant::{PancakeSwapV3, PoolVariant, SushiSwapV3, UniswapV3},
+};
+
+// the factories of the canonical Uniswap V3 deployment and major forks by chain, with what is needed to compute pool
+// addresses and quote their pools. Chains with a different CREATE2 scheme, e.g. zkSync Era, are not listed since
+// their pool addresses can not be computed with `PoolKey::compute_address_with`
+
+pub const ETHEREUM: u64 = 1;
+pub const OPTIMISM: u64 = 10;
+pub const BNB_CHAIN: u64 = 56;
+pub const POLYGON: u64 = 137;
+pub const BASE: u64 = 8453;
+pub const ARBITRUM: u64 = 42161;
+pub const CELO: u64 = 42220;
+pub const AVALANCHE: u64 = 43114;
+pub const BLAST: u64 = 81457;
+
+// keccak256 of the PancakeV3Pool creation code
+pub const PANCAKESWAP_V3_POOL_INIT_CODE_HASH: B256 =
+    b256!("6ce8eb472fa82df5469c6ab6d485f17c3ad13c8cd7af59b3d4a8026c5ce0f7e2");
+// the PancakeSwap V3 factory and the pool deployer its pools are created by, the same on every chain
+pub const PANCAKESWAP_V3_FACTORY: Address = address!("0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865");
+pub const PANCAKESWAP_V3_POOL_DEPLOYER: Address =
+    address!("41ff9AA7e16B8B1a8a8dc4f0eFacd93D02d071c9");
+
+const UNISWAP_V3_FEE_TIERS: &[FeeTier] = &[
+    FeeTier::Lowest,
+    FeeTier::Low,
+    FeeTier::Medium,
+    FeeTier::High,
+];
+const PANCAKESWAP_V3_FEE_TIERS: &[FeeTier] = &[
+    FeeTier::Lowest,
+    FeeTier::Low,
+    FeeTier::custom_const(2500, 50),
+    FeeTier::High,
+];
+
+//The protocol of a deployment, which `PoolVariant` its pools are quoted with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    UniswapV3,
+    SushiSwapV3,
+    PancakeSwapV3,
+}
+
+impl Protocol {
+    // returns the fee tier the protocol's factory enables for a fee amount, see `PoolVariant::fee_tier`
+    pub fn fee_tier(&self, fee: u32) -> Option<FeeTier> {
+        match self {
+            Self::UniswapV3 => UniswapV3.fee_tier(fee),
+            Self::SushiSwapV3 => SushiSwapV3.fee_tier(fee),
+            Self::PancakeSwapV3 => PancakeSwapV3.fee_tier(fee),
+        }
+    }
+}
+
+//A factory of a protocol on a chain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Deployment {
+    pub chain_id: u64,
+    pub protocol: Protocol,
+    pub factory: Address,
+    // the address pools are created from with CREATE2, the factory itself unless the protocol uses a separate deployer
+    pub pool_deployer: Address,
+    pub init_code_hash: B256,
+    // the fee tiers enabled on the factory
+    pub fee_tiers: &'static [FeeTier],
+}
+
+impl Deployment {
+    const fn uniswap_v3(chain_id: u64, factory: Address) -> Self {
+        Self {
+            chain_id,
+            protocol: Protocol::UniswapV3,
+            factory,
+            pool_deployer: factory,
+            init_code_hash: POOL_INIT_CODE_HASH,
+            fee_tiers: UNISWAP_V3_FEE_TIERS,
+        }
+    }
+
+    const fn sushiswap_v3(chain_id: u64, factory: Address) -> Self {
+        Self {
+            protocol: Protocol::SushiSwapV3,
+            ..Self::uniswap_v3(chain_id, factory)
+        }
+    }
+
+    const fn pancakeswap_v3(chain_id: u64) -> Self {
+        Self {
+            chain_id,
+            protocol: Protocol::PancakeSwapV3,
+            factory: PANCAKESWAP_V3_FACTORY,
+            pool_deployer: PANCAKESWAP_V3_POOL_DEPLOYER,
+            init_code_hash: PANCAKESWAP_V3_POOL_INIT_CODE_HASH,
+            fee_tiers: PANCAKESWAP_V3_FEE_TIERS,
+        }
+    }
+
+    // returns the address of the pool of `key` created by this deployment
+    pub fn compute_address(&self, key: &PoolKey) -> Address {
+        key.compute_address_with(self.pool_deployer, self.init_code_hash)
+    }
+
+    // returns the fee tier for a fee amount if it is enabled on the factory
+    pub fn fee_tier(&self, fee: u32) -> Option<FeeTier> {
+        self.fee_tiers
+            .iter()
+            .copied()
+            .find(|fee_tier| fee_tier.fee() == fee)
+    }
+}
+
+// every known deployment, ordered by chain id and protocol
+pub const DEPLOYMENTS: &[Deployment] = &[
+    Deployment::uniswap_v3(ETHEREUM, UNISWAP_V3_FACTORY),
+    Deployment::sushiswap_v3(
+        ETHEREUM,
+        address!("bACEB8eC6b9355Dfc0269C18bac9d6E2Bdc29C4F"),
+    ),
+    Deployment::pancakeswap_v3(ETHEREUM),
+    Deployment::uniswap_v3(OPTIMISM, UNISWAP_V3_FACTORY),
+    Deployment::uniswap_v3(
+        BNB_CHAIN,
+        address!("dB1d10011AD0Ff90774D0C6Bb92e5C5c8b4461F7"),
+    ),
+    Deployment::pancakeswap_v3(BNB_CHAIN),
+    Deployment::uniswap_v3(POLYGON, UNISWAP_V3_FACTORY),
+    Deployment::sushiswap_v3(
+        POLYGON,
+        address!("917933899c6a5F8E37F31E19f92CdBFF7e8FF0e2"),
+    ),
+    Deployment::uniswap_v3(BASE, address!("33128a8fC17869897dcE68Ed026d694621f6FDfD")),
+    Deployment::pancakeswap_v3(BASE),
+    Deployment::uniswap_v3(ARBITRUM, UNISWAP_V3_FACTORY),
+    Deployment::sushiswap_v3(
+        ARBITRUM,
+        address!("1af415a1EbA07a4986a52B6f2e7dE7003D82231e"),
+    ),
+    Deployment::pancakeswap_v3(ARBITRUM),
+    Deployment::uniswap_v3(CELO, address!("AfE208a311B21f13EF87E33A90049fC17A7acDEc")),
+    Deployment::uniswap_v3(
+        AVALANCHE,
+        address!("740b1c1de25031C31FF4fC9A62f554A55cdC1baD"),
+    ),
+    Deployment::uniswap_v3(BLAST, address!("792edAdE80af5fC680d96a2eD80A44247D2Cf6Fd")),
+];
+
+// returns the deployments on a chain
+pub fn deployments(chain_id: u64) -> impl Iterator<Item = &'static Deployment> {
+    DEPLOYMENTS
+        .iter()
+        .filter(move |deployment| deployment.chain_id == chain_id)
+}
+
+// returns the deployment of a protocol on a chain, if any
+pub fn deployment(chain_id: u64, protocol: Protocol) -> Option<&'static Deployment> {
+    deployments(chain_id).find(|deployment| deployment.protocol == protocol)
+}
+
+// returns the deployment whose factory is `factory` on a chain, e.g. to find how to quote a pool from its factory
+pub fn deployment_of_factory(chain_id: u64, factory: Address) -> Option<&'static Deployment> {
+    deployments(chain_id).find(|deployment| deployment.factory == factory)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::address;
+
+    use super::{
+        deployment, deployment_of_factory, deployments, Protocol, ARBITRUM, BASE, DEPLOYMENTS,
+        ETHEREUM,
+    };
+    use crate::{fee_tier::FeeTier, pool_key::PoolKey};
+
+    #[test]
+    fn test_deployments() {
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+        //the canonical deployment computes the addresses of the canonical pools
+        let uniswap = deployment(ETHEREUM, Protocol::UniswapV3).unwrap();
+        assert_eq!(
+            uniswap.compute_address(&PoolKey::new(usdc, weth, 500)),
+            address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")
+        );
+        assert_eq!(
+            deployment_of_factory(ETHEREUM, uniswap.factory),
+            Some(uniswap)
+        );
+        assert_eq!(deployments(ETHEREUM).count(), 3);
+        assert_eq!(deployments(ARBITRUM).count(), 3);
+        assert_ne!(
+            deployment(BASE, Protocol::UniswapV3).unwrap().factory,
+            uniswap.factory
+        );
+        assert_eq!(deployment(BASE, Protocol::SushiSwapV3), None);
+
+        //the fee tiers of every deployment are those of its protocol
+        for deployment in DEPLOYMENTS {
+            for fee_tier in deployment.fee_tiers {
+                assert_eq!(
+                    deployment.protocol.fee_tier(fee_tier.fee()),
+                    Some(*fee_tier)
+                );
+            }
+            assert_eq!(
+                deployment.fee_tier(3000).is_some(),
+                deployment.protocol != Protocol::PancakeSwapV3
+            );
+        }
+
+        //pancakeswap pools are created by the pool deployer
+        let pancakeswap = deployment(ETHEREUM, Protocol::PancakeSwapV3).unwrap();
+        assert_ne!(pancakeswap.pool_deployer, pancakeswap.factory);
+        assert_eq!(pancakeswap.fee_tier(2500), FeeTier::custom(2500, 50).ok());
+    }
+}
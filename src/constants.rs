@@ -0,0 +1,57 @@
+use alloy_primitives::U256;
+
+// the canonical constants of the core contracts in one place. The modules that historically defined them re-export
+// them, so `tick_math::MIN_TICK` and `constants::MIN_TICK` are the same constant
+
+// the minimum tick that may be passed to get_sqrt_ratio_at_tick, computed from log base 1.0001 of 2**-128
+pub const MIN_TICK: i32 = -887272;
+// the maximum tick that may be passed to get_sqrt_ratio_at_tick, computed from log base 1.0001 of 2**128
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+// the minimum value that can be returned from get_sqrt_ratio_at_tick, equivalent to get_sqrt_ratio_at_tick(MIN_TICK).
+// A Q64.96 sqrt price
+pub const MIN_SQRT_RATIO: U256 = U256::from_limbs([4295128739, 0, 0, 0]);
+// the maximum value that can be returned from get_sqrt_ratio_at_tick, equivalent to get_sqrt_ratio_at_tick(MAX_TICK).
+// A Q64.96 sqrt price
+pub const MAX_SQRT_RATIO: U256 =
+    U256::from_limbs([6743328256752651558, 17280870778742802505, 4294805859, 0]);
+
+// 2**96, one in Q64.96, the format of sqrt prices
+pub const Q96: U256 = U256::from_limbs([0, 4294967296, 0, 0]);
+// the number of fractional bits of a Q64.96
+pub const FIXED_POINT_96_RESOLUTION: U256 = U256::from_limbs([96, 0, 0, 0]);
+// 2**128, one in Q128.128, the format of fee growth and seconds per liquidity
+pub const Q128: U256 = U256::from_limbs([0, 0, 1, 0]);
+// 2**192, one in Q64.192, the format of a squared Q64.96 sqrt price, i.e. a price
+pub const Q192: U256 = U256::from_limbs([0, 0, 0, 1]);
+
+// 2**160 - 1, the largest uint160, the storage type of sqrt prices and seconds per liquidity
+pub const MAX_U160: U256 =
+    U256::from_limbs([18446744073709551615, 18446744073709551615, 4294967295, 0]);
+
+// fees are expressed in hundredths of a bip, i.e. 1e-6
+pub const FEE_DENOMINATOR: u32 = 1000000;
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{
+        FIXED_POINT_96_RESOLUTION, MAX_SQRT_RATIO, MAX_TICK, MAX_U160, MIN_SQRT_RATIO, MIN_TICK,
+        Q128, Q192, Q96,
+    };
+    use crate::tick_math::get_sqrt_ratio_at_tick;
+
+    #[test]
+    fn test_constants() {
+        let one = U256::from(1);
+        assert_eq!(Q96, one << 96);
+        assert_eq!(Q96, one << FIXED_POINT_96_RESOLUTION.to::<usize>());
+        assert_eq!(Q128, one << 128);
+        assert_eq!(Q192, one << 192);
+        assert_eq!(MAX_U160, (one << 160) - one);
+
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK).unwrap(), MIN_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK).unwrap(), MAX_SQRT_RATIO);
+    }
+}
@@ -0,0 +1,181 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    minimal_ticks::MinimalTick, oracle::Observation, pool::Slot0, position::Position, tick::Tick,
+    U256_1,
+};
+
+// returns the slot0 of a pool from its packed storage word, as returned by eth_getStorageAt for slot 0
+pub fn slot0(word: U256) -> Slot0 {
+    Slot0 {
+        sqrt_price_x_96: bits(word, 0, 160),
+        tick: signed_24(bits(word, 160, 24)),
+        observation_index: bits(word, 184, 16).to::<u16>(),
+        observation_cardinality: bits(word, 200, 16).to::<u16>(),
+        observation_cardinality_next: bits(word, 216, 16).to::<u16>(),
+        fee_protocol: bits(word, 232, 8).to::<u32>(),
+        unlocked: !bits(word, 240, 8).is_zero(),
+    }
+}
+
+// returns a tick from the four consecutive storage words of a Tick.Info in the ticks mapping
+pub fn tick_info(words: &[U256; 4]) -> Tick {
+    Tick {
+        liquidity_gross: bits(words[0], 0, 128).to::<u128>(),
+        liquidity_net: bits(words[0], 128, 128).to::<u128>() as i128,
+        fee_growth_outside_0_x_128: words[1],
+        fee_growth_outside_1_x_128: words[2],
+        // the int56 is sign extended to 256 bits
+        tick_cumulative_outside: I256::unchecked_from(signed_56(bits(words[3], 0, 56))).into_raw(),
+        seconds_per_liquidity_outside_x_128: bits(words[3], 56, 160),
+        seconds_outside: bits(words[3], 216, 32).to::<u32>(),
+        initialized: !bits(words[3], 248, 8).is_zero(),
+    }
+}
+
+// returns the part of a tick a swap reads from the first and last storage word of a Tick.Info, see `tick_info`
+pub fn minimal_tick_info(first: U256, last: U256) -> MinimalTick {
+    MinimalTick {
+        liquidity_net: bits(first, 128, 128).to::<u128>() as i128,
+        initialized: !bits(last, 248, 8).is_zero(),
+    }
+}
+
+// returns a position from the four consecutive storage words of a Position.Info in the positions mapping
+pub fn position_info(words: &[U256; 4]) -> Position {
+    Position {
+        liquidity: bits(words[0], 0, 128).to::<u128>(),
+        fee_growth_inside_0_last_x_128: words[1],
+        fee_growth_inside_1_last_x_128: words[2],
+        tokens_owed_0: bits(words[3], 0, 128).to::<u128>(),
+        tokens_owed_1: bits(words[3], 128, 128).to::<u128>(),
+    }
+}
+
+// returns an oracle observation from its packed storage word in the observations array
+pub fn observation(word: U256) -> Observation {
+    Observation {
+        block_timestamp: bits(word, 0, 32).to::<u32>(),
+        tick_cumulative: signed_56(bits(word, 32, 56)),
+        seconds_per_liquidity_cumulative_x_128: bits(word, 88, 160),
+        initialized: !bits(word, 248, 8).is_zero(),
+    }
+}
+
+// returns the `len` bits of `word` starting at bit `offset`
+pub(crate) fn bits(word: U256, offset: usize, len: usize) -> U256 {
+    (word >> offset) & ((U256_1 << len) - U256_1)
+}
+
+pub(crate) fn signed_24(value: U256) -> i32 {
+    ((value.to::<u32>() << 8) as i32) >> 8
+}
+
+pub(crate) fn signed_56(value: U256) -> i64 {
+    ((value.to::<u64>() << 8) as i64) >> 8
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{observation, slot0, tick_info};
+    use crate::tick_math::get_sqrt_ratio_at_tick;
+
+    // packs (value, bit length) pairs into a word, starting from the lowest bits
+    fn pack(fields: &[(U256, usize)]) -> U256 {
+        let mut word = U256::ZERO;
+        let mut offset = 0;
+        for &(value, len) in fields {
+            let mask = (U256::from(1) << len) - U256::from(1);
+            word |= (value & mask) << offset;
+            offset += len;
+        }
+        word
+    }
+
+    // the two's complement of a negative value truncated to the field
+    fn negative(value: i64) -> U256 {
+        I256::unchecked_from(value).into_raw()
+    }
+
+    #[test]
+    fn test_slot0() {
+        let sqrt_price_x_96 = get_sqrt_ratio_at_tick(-201000).unwrap();
+        let word = pack(&[
+            (sqrt_price_x_96, 160),
+            (negative(-201000), 24),
+            (U256::from(17), 16),
+            (U256::from(720), 16),
+            (U256::from(723), 16),
+            (U256::from(0x44), 8),
+            (U256::from(1), 8),
+        ]);
+
+        let decoded = slot0(word);
+        assert_eq!(decoded.sqrt_price_x_96, sqrt_price_x_96);
+        assert_eq!(decoded.tick, -201000);
+        assert_eq!(decoded.observation_index, 17);
+        assert_eq!(decoded.observation_cardinality, 720);
+        assert_eq!(decoded.observation_cardinality_next, 723);
+        assert_eq!(decoded.fee_protocol_0(), 4);
+        assert_eq!(decoded.fee_protocol_1(), 4);
+        assert!(decoded.unlocked);
+
+        //positive ticks are not sign extended
+        let decoded = slot0(pack(&[(sqrt_price_x_96, 160), (U256::from(887272), 24)]));
+        assert_eq!(decoded.tick, 887272);
+        assert!(!decoded.unlocked);
+    }
+
+    #[test]
+    fn test_tick_info() {
+        let words = [
+            pack(&[
+                (U256::from(3e18 as u128), 128),
+                (negative(-(2e18 as i64)), 128),
+            ]),
+            U256::from(11),
+            U256::MAX,
+            pack(&[
+                (negative(-123456789), 56),
+                (U256::from(99), 160),
+                (U256::from(1_700_000_000), 32),
+                (U256::from(1), 8),
+            ]),
+        ];
+
+        let tick = tick_info(&words);
+        assert_eq!(tick.liquidity_gross, 3e18 as u128);
+        assert_eq!(tick.liquidity_net, -(2e18 as i128));
+        assert_eq!(tick.fee_growth_outside_0_x_128, U256::from(11));
+        assert_eq!(tick.fee_growth_outside_1_x_128, U256::MAX);
+        assert_eq!(
+            I256::from_raw(tick.tick_cumulative_outside),
+            I256::unchecked_from(-123456789)
+        );
+        assert_eq!(tick.seconds_per_liquidity_outside_x_128, U256::from(99));
+        assert_eq!(tick.seconds_outside, 1_700_000_000);
+        assert!(tick.initialized);
+    }
+
+    #[test]
+    fn test_observation() {
+        let word = pack(&[
+            (U256::from(1_700_000_000), 32),
+            (negative(-36028797018963968), 56),
+            (U256::from(1) << 159, 160),
+            (U256::from(1), 8),
+        ]);
+
+        //the tick cumulative is the minimum int56
+        let observation = observation(word);
+        assert_eq!(observation.block_timestamp, 1_700_000_000);
+        assert_eq!(observation.tick_cumulative, -36028797018963968);
+        assert_eq!(
+            observation.seconds_per_liquidity_cumulative_x_128,
+            U256::from(1) << 159
+        );
+        assert!(observation.initialized);
+    }
+}
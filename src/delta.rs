@@ -0,0 +1,168 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    pool::PoolSnapshot,
+    storage::{InitializedTicks, TickStorage},
+    tick_bitmap::position,
+};
+
+// a pool event that changes the state of the pool, reduced to the ticks it touches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolEvent {
+    // the tick of the pool after the swap
+    Swap { tick: i32 },
+    Mint { tick_lower: i32, tick_upper: i32 },
+    Burn { tick_lower: i32, tick_upper: i32 },
+}
+
+//The storage of a pool that has to be read again to bring a snapshot up to date with a set of events. The globals,
+//slot0, liquidity, fee growth and protocol fees, are always stale after any event and are not listed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDelta {
+    pub ticks: BTreeSet<i32>,
+    pub tick_bitmap_words: BTreeSet<i16>,
+}
+
+impl StateDelta {
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty() && self.tick_bitmap_words.is_empty()
+    }
+}
+
+// returns the ticks and tick bitmap words that `events` may have modified since `pool` was taken. Mints and burns touch
+// their range ticks and the bitmap words holding them. Swaps only emit the tick they end at, so any tick between the
+// lowest and highest tick the pool was at may have been crossed
+pub fn touched_state<T, B, V>(pool: &PoolSnapshot<T, B, V>, events: &[PoolEvent]) -> StateDelta
+where
+    T: TickStorage,
+    B: InitializedTicks,
+{
+    let tick_spacing = pool.fee_tier.tick_spacing();
+    let mut delta = StateDelta::default();
+    let mut swapped = false;
+    let (mut tick_min, mut tick_max) = (pool.slot0.tick, pool.slot0.tick);
+
+    for event in events {
+        match *event {
+            PoolEvent::Swap { tick } => {
+                swapped = true;
+                tick_min = tick_min.min(tick);
+                tick_max = tick_max.max(tick);
+            }
+            PoolEvent::Mint {
+                tick_lower,
+                tick_upper,
+            }
+            | PoolEvent::Burn {
+                tick_lower,
+                tick_upper,
+            } => {
+                for tick in [tick_lower, tick_upper] {
+                    delta.ticks.insert(tick);
+                    delta
+                        .tick_bitmap_words
+                        .insert(position(tick / tick_spacing).0);
+                }
+            }
+        }
+    }
+
+    if swapped {
+        // moving down through a tick leaves the pool at the tick below it and moving up leaves it at the tick, so the
+        // crossed ticks are in (tick_min, tick_max]
+        let mut tick = tick_min;
+        while let Ok((next, initialized)) =
+            pool.tick_bitmap
+                .next_initialized_tick_within_one_word(tick, tick_spacing, false)
+        {
+            if next > tick_max {
+                break;
+            }
+            if initialized {
+                delta.ticks.insert(next);
+            }
+            tick = next;
+        }
+    }
+
+    delta
+}
+
+#[cfg(test)]
+mod test {
+    use super::{touched_state, PoolEvent};
+    use crate::{pool::PoolSnapshot, test_utils::PoolBuilder};
+
+    fn pool_at(tick_current: i32) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .full_range(1e20 as i128)
+            .position(-600, 600, 1e21 as i128)
+            .position(-30000, 30000, 1e19 as i128)
+            .build()
+    }
+
+    #[test]
+    fn test_touched_state() {
+        let pool = pool_at(0);
+
+        //no events, nothing to read besides the globals
+        assert!(touched_state(&pool, &[]).is_empty());
+
+        //mints and burns touch their ticks and bitmap words
+        let delta = touched_state(
+            &pool,
+            &[PoolEvent::Mint {
+                tick_lower: -60,
+                tick_upper: 15360,
+            }],
+        );
+        assert_eq!(
+            delta.ticks.into_iter().collect::<Vec<_>>(),
+            vec![-60, 15360]
+        );
+        assert_eq!(
+            delta.tick_bitmap_words.into_iter().collect::<Vec<_>>(),
+            vec![-1, 1]
+        );
+
+        //swaps touch every initialized tick between the lowest and highest tick, across bitmap words
+        let delta = touched_state(
+            &pool,
+            &[
+                PoolEvent::Swap { tick: 700 },
+                PoolEvent::Swap { tick: -30000 },
+                PoolEvent::Swap { tick: 100 },
+            ],
+        );
+        assert_eq!(delta.ticks.into_iter().collect::<Vec<_>>(), vec![-600, 600]);
+
+        //swaps within a range without initialized ticks touch nothing
+        let delta = touched_state(
+            &pool,
+            &[
+                PoolEvent::Swap { tick: 500 },
+                PoolEvent::Swap { tick: -599 },
+            ],
+        );
+        assert!(delta.is_empty());
+
+        //the tick the pool ends at moving up is crossed, the one it ends at moving down is not
+        let delta = touched_state(&pool, &[PoolEvent::Swap { tick: 600 }]);
+        assert_eq!(delta.ticks.into_iter().collect::<Vec<_>>(), vec![600]);
+        let delta = touched_state(&pool, &[PoolEvent::Swap { tick: -600 }]);
+        assert!(delta.is_empty());
+
+        //ticks minted before a swap may be crossed by it
+        let delta = touched_state(
+            &pool,
+            &[
+                PoolEvent::Mint {
+                    tick_lower: 60,
+                    tick_upper: 120,
+                },
+                PoolEvent::Swap { tick: 200 },
+            ],
+        );
+        assert_eq!(delta.ticks.into_iter().collect::<Vec<_>>(), vec![60, 120]);
+    }
+}
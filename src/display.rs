@@ -0,0 +1,321 @@
+use std::fmt;
+
+use alloy_primitives::U256;
+
+use crate::{
+    approx::{sqrt_price_x_96_to_f64, tick_to_price},
+    fee_tier::FeeTier,
+    position::Position,
+    q_number::SqrtPriceX96,
+};
+
+// human readable formatting of prices, ticks, amounts and positions for logs. Raw values are scaled by the token
+// decimals and prices are quoted in whichever direction is at least one, e.g. "1 WETH = 1784.74 USDC" rather than
+// "1 USDC = 0.000560306 WETH". Prices go through f64 and are rounded to a few significant digits, amounts are exact
+
+// the number of significant digits of formatted prices
+const SIGNIFICANT_DIGITS: i32 = 6;
+
+//A token symbol and the number of decimals of its raw amounts
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl Token {
+    pub fn new(symbol: impl Into<String>, decimals: u8) -> Self {
+        Self {
+            symbol: symbol.into(),
+            decimals,
+        }
+    }
+}
+
+//The tokens of a pool, formats raw pool values for display
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TokenPair {
+    pub token_0: Token,
+    pub token_1: Token,
+}
+
+impl TokenPair {
+    pub fn new(token_0: Token, token_1: Token) -> Self {
+        Self { token_0, token_1 }
+    }
+
+    // e.g. "1 WETH = 1784.74 USDC"
+    pub fn price(&self, sqrt_price_x_96: impl Into<SqrtPriceX96>) -> PriceDisplay<'_> {
+        PriceDisplay {
+            pair: self,
+            price: sqrt_price_x_96_to_f64(sqrt_price_x_96.into().0).powi(2),
+        }
+    }
+
+    // e.g. "tick 201450 ≈ 1 WETH = 1784.74 USDC"
+    pub fn tick(&self, tick: i32) -> TickDisplay<'_> {
+        TickDisplay { pair: self, tick }
+    }
+
+    // e.g. "1.5 USDC"
+    pub fn amount_0(&self, amount: U256) -> AmountDisplay<'_> {
+        AmountDisplay {
+            token: &self.token_0,
+            amount,
+        }
+    }
+
+    pub fn amount_1(&self, amount: U256) -> AmountDisplay<'_> {
+        AmountDisplay {
+            token: &self.token_1,
+            amount,
+        }
+    }
+
+    // e.g. "1900.79 - 2006.25 USDC per WETH (ticks 200280 - 200820)"
+    pub fn range(&self, tick_lower: i32, tick_upper: i32) -> RangeDisplay<'_> {
+        RangeDisplay {
+            pair: self,
+            tick_lower,
+            tick_upper,
+        }
+    }
+
+    // e.g. "liquidity 1000 in 1900.79 - 2006.25 USDC per WETH (ticks 200280 - 200820), owed 1.5 USDC and 0 WETH"
+    pub fn position<'a>(
+        &'a self,
+        tick_lower: i32,
+        tick_upper: i32,
+        position: &'a Position,
+    ) -> PositionDisplay<'a> {
+        PositionDisplay {
+            pair: self,
+            tick_lower,
+            tick_upper,
+            position,
+        }
+    }
+
+    // converts a raw price of token0 in token1 to whole token units
+    fn scale(&self, raw_price: f64) -> f64 {
+        raw_price * 10f64.powi(self.token_0.decimals as i32 - self.token_1.decimals as i32)
+    }
+
+    // writes "1 BASE = PRICE QUOTE", quoting the token that is worth more as the base
+    fn write_price(&self, f: &mut fmt::Formatter<'_>, raw_price: f64) -> fmt::Result {
+        let price = self.scale(raw_price);
+        let (base, quote, price) = if price >= 1.0 {
+            (&self.token_0, &self.token_1, price)
+        } else {
+            (&self.token_1, &self.token_0, price.recip())
+        };
+
+        write!(f, "1 {} = ", base.symbol)?;
+        write_significant(f, price)?;
+        write!(f, " {}", quote.symbol)
+    }
+
+    fn write_range(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> fmt::Result {
+        let (lower, upper) = (
+            self.scale(tick_to_price(tick_lower)),
+            self.scale(tick_to_price(tick_upper)),
+        );
+        // quote the range in the same direction as the price in its middle
+        let (base, quote, lower, upper) = if lower * upper >= 1.0 {
+            (&self.token_0, &self.token_1, lower, upper)
+        } else {
+            (&self.token_1, &self.token_0, upper.recip(), lower.recip())
+        };
+
+        write_significant(f, lower)?;
+        f.write_str(" - ")?;
+        write_significant(f, upper)?;
+        write!(
+            f,
+            " {} per {} (ticks {tick_lower} - {tick_upper})",
+            quote.symbol, base.symbol
+        )
+    }
+}
+
+pub struct PriceDisplay<'a> {
+    pair: &'a TokenPair,
+    price: f64,
+}
+
+impl fmt::Display for PriceDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pair.write_price(f, self.price)
+    }
+}
+
+pub struct TickDisplay<'a> {
+    pair: &'a TokenPair,
+    tick: i32,
+}
+
+impl fmt::Display for TickDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tick {} ≈ ", self.tick)?;
+        self.pair.write_price(f, tick_to_price(self.tick))
+    }
+}
+
+pub struct AmountDisplay<'a> {
+    token: &'a Token,
+    amount: U256,
+}
+
+impl fmt::Display for AmountDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_amount(f, self.amount, self.token.decimals)?;
+        write!(f, " {}", self.token.symbol)
+    }
+}
+
+pub struct RangeDisplay<'a> {
+    pair: &'a TokenPair,
+    tick_lower: i32,
+    tick_upper: i32,
+}
+
+impl fmt::Display for RangeDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pair.write_range(f, self.tick_lower, self.tick_upper)
+    }
+}
+
+pub struct PositionDisplay<'a> {
+    pair: &'a TokenPair,
+    tick_lower: i32,
+    tick_upper: i32,
+    position: &'a Position,
+}
+
+impl fmt::Display for PositionDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "liquidity {} in ", self.position.liquidity)?;
+        self.pair.write_range(f, self.tick_lower, self.tick_upper)?;
+        write!(
+            f,
+            ", owed {} and {}",
+            self.pair.amount_0(U256::from(self.position.tokens_owed_0)),
+            self.pair.amount_1(U256::from(self.position.tokens_owed_1))
+        )
+    }
+}
+
+// e.g. "0.3%"
+impl fmt::Display for FeeTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the fee is in hundredths of a bip, so the percentage has at most 4 decimals
+        let fee = self.fee();
+        write_amount(f, U256::from(fee), 4)?;
+        f.write_str("%")
+    }
+}
+
+// writes a raw amount in whole units without trailing zeros
+fn write_amount(f: &mut fmt::Formatter<'_>, amount: U256, decimals: u8) -> fmt::Result {
+    let digits = format!("{amount:0>width$}", width = decimals as usize + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        f.write_str(integer)
+    } else {
+        write!(f, "{integer}.{fraction}")
+    }
+}
+
+fn write_significant(f: &mut fmt::Formatter<'_>, value: f64) -> fmt::Result {
+    if !value.is_normal() {
+        return write!(f, "{value}");
+    }
+
+    let decimals = (SIGNIFICANT_DIGITS - 1 - value.abs().log10().floor() as i32).max(0) as usize;
+    write!(f, "{value:.decimals$}")
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{Token, TokenPair};
+    use crate::{
+        fee_tier::FeeTier, position::Position, sqrt_price_math::Q96,
+        tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    fn usdc_weth() -> TokenPair {
+        TokenPair::new(Token::new("USDC", 6), Token::new("WETH", 18))
+    }
+
+    #[test]
+    fn test_price() {
+        let pair = usdc_weth();
+        assert_eq!(
+            pair.tick(201450).to_string(),
+            "tick 201450 ≈ 1 WETH = 1784.74 USDC"
+        );
+        assert_eq!(
+            pair.price(get_sqrt_ratio_at_tick(201450).unwrap())
+                .to_string(),
+            "1 WETH = 1784.74 USDC"
+        );
+
+        //quotes the token that is worth more as the base
+        let pair = TokenPair::new(Token::new("WETH", 18), Token::new("USDC", 6));
+        assert_eq!(
+            pair.tick(-201450).to_string(),
+            "tick -201450 ≈ 1 WETH = 1784.74 USDC"
+        );
+        let pair = TokenPair::new(Token::new("A", 18), Token::new("B", 18));
+        assert_eq!(pair.price(Q96).to_string(), "1 A = 1.00000 B");
+        assert_eq!(pair.tick(-6932).to_string(), "tick -6932 ≈ 1 B = 2.00004 A");
+    }
+
+    #[test]
+    fn test_amounts() {
+        let pair = usdc_weth();
+        assert_eq!(pair.amount_0(U256::from(1500000)).to_string(), "1.5 USDC");
+        assert_eq!(pair.amount_0(U256::ZERO).to_string(), "0 USDC");
+        assert_eq!(
+            pair.amount_1(U256::from(1)).to_string(),
+            "0.000000000000000001 WETH"
+        );
+        assert_eq!(
+            pair.amount_1(U256::from(12) * U256::from(10).pow(U256::from(18)))
+                .to_string(),
+            "12 WETH"
+        );
+
+        assert_eq!(FeeTier::Medium.to_string(), "0.3%");
+        assert_eq!(FeeTier::Lowest.to_string(), "0.01%");
+        assert_eq!(FeeTier::High.to_string(), "1%");
+    }
+
+    #[test]
+    fn test_position() {
+        let pair = usdc_weth();
+        assert_eq!(
+            pair.range(200280, 200820).to_string(),
+            "1900.79 - 2006.25 USDC per WETH (ticks 200280 - 200820)"
+        );
+
+        let position = Position {
+            liquidity: 1000,
+            tokens_owed_0: 1500000,
+            ..Default::default()
+        };
+        assert_eq!(
+            pair.position(200280, 200820, &position).to_string(),
+            "liquidity 1000 in 1900.79 - 2006.25 USDC per WETH (ticks 200280 - 200820), owed 1.5 USDC and 0 WETH"
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use alloy_primitives::ruint::ParseError;
 use thiserror::Error;
 
+use crate::invariants::InvariantViolation;
+
 // TODO: make these errors better, some errors in univ3 libs are just require(condition) without a message.
 #[derive(Error, Debug)]
 pub enum UniswapV3MathError {
@@ -10,6 +12,10 @@ pub enum UniswapV3MathError {
     ResultIsU256MAX,
     #[error("Sqrt price is 0")]
     SqrtPriceIsZero,
+    #[error("The lower sqrt price must be less than or equal to the upper sqrt price")]
+    SqrtPricesOutOfOrder,
+    #[error("Sqrt price does not fit in 160 bits")]
+    SqrtPriceOverflow,
     #[error("Sqrt price is less than or equal to quotient")]
     SqrtPriceIsLteQuotient,
     #[error("Can not get most significant bit or least significant bit on zero value")]
@@ -27,18 +33,112 @@ pub enum UniswapV3MathError {
     LiquiditySub,
     #[error("Liquidity Add")]
     LiquidityAdd,
+    #[error("Positions without liquidity can not be poked")]
+    NP,
+    #[error("Liquidity gross exceeds the max liquidity per tick")]
+    LiquidityOverflow,
+    #[error("Liquidity net of a tick overflows int128")]
+    LiquidityNetOverflow,
+    #[error("The tick storage does not keep the gross liquidity that minting and burning need")]
+    LiquidityGrossNotStored,
     #[error("The given tick must be less than, or equal to, the maximum tick")]
     T,
     #[error(
         "Second inequality must be < because the price can never reach the price at the max tick"
     )]
     R,
+    #[error("Arithmetic overflow")]
+    Overflow,
     #[error("Overflow when casting to U160")]
     SafeCastToU160Overflow,
+    #[error("Fee must be less than 1e6 pips")]
+    InvalidFee,
+    #[error("Amount specified must not be 0")]
+    AS,
+    #[error("The direction of the swap must be set")]
+    SwapDirectionNotSet,
+    #[error("Sqrt price limit is out of bounds or on the wrong side of the current price")]
+    SPL,
     #[error("Tick spacing error")]
     TickSpacingError,
+    #[error("The lower tick must be less than the upper tick")]
+    TLU,
+    #[error("The lower tick must be greater than, or equal to, the minimum tick")]
+    TLM,
+    #[error("The upper tick must be less than, or equal to, the maximum tick")]
+    TUM,
+    #[error("Current time must be greater than or equal to the incentive start time")]
+    IncentiveNotStarted,
+    #[error("The limit order range must not contain the current price")]
+    LimitOrderInRange,
+    #[error("Volatility and horizon must not be negative and coverage must be between 0 and 1")]
+    InvalidVolatilityInput,
+    #[error("Depth ranges must be at least 0 and less than 1")]
+    InvalidDepthRange,
+    #[error("The blocks a manipulation is held for must fit into the window and must not be 0")]
+    InvalidManipulationWindow,
+    #[error("Target observation is older than the oldest observation")]
+    OLD,
+    #[error("The period of a time weighted average must not be 0")]
+    BP,
+    #[error("The oracle must be initialized and its observations must cover its cardinality")]
+    I,
+    #[error("Unknown fee tier, the tick spacing of custom fee tiers must be given")]
+    UnknownFeeTier,
+    #[error("Fees can not be donated to a pool without in range liquidity")]
+    NoLiquidityToReceiveFees,
+    #[error("The tokens of the trade are not the tokens of the pool")]
+    TokenNotInPool,
+    #[error("Transfer fees must be less than 10000 basis points")]
+    InvalidTransferFee,
+    #[error("The swap crosses beyond the ticks loaded into the partial snapshot")]
+    QuoteTruncated,
+    #[error("Blocks must be applied in ascending order of their number")]
+    BlockOutOfOrder,
+    #[error("The reorg is deeper than the blocks kept in the journal")]
+    ReorgTooDeep,
+    #[error(
+        "The path must be a token followed by at least one fee and token, with connected hops"
+    )]
+    InvalidPath,
+    #[error("No pool for the key, address or index")]
+    PoolNotFound,
+    #[error("No tracked position at the index")]
+    PositionNotFound,
+    #[error("A token1 price is required for every pool")]
+    PriceCountMismatch,
+    #[error("The pool does not have the liquidity to fill the exact output amount")]
+    OutputNotFilled,
+    #[error("Too little received")]
+    TooLittleReceived,
+    #[error("Too much requested")]
+    TooMuchRequested,
+    #[error("Invalid ABI encoded return data")]
+    InvalidReturnData,
+    #[error("Failed to write results: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "arrow")]
+    #[error("Failed to build a record batch: {0}")]
+    Arrow(#[from] arrow::error::ArrowError),
+    #[cfg(feature = "arrow")]
+    #[error("Failed to write parquet: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error("Invariant violated: {0}")]
+    InvariantViolation(Box<InvariantViolation>),
     #[error("Middleware error when getting next_initialized_tick_within_one_word")]
     MiddlewareError(String),
     #[error("Parse error")]
     ParseError(#[from] ParseError),
 }
+
+impl UniswapV3MathError {
+    // returns the name of the variant, which identifies the error independent of its message and payload
+    pub fn kind(&self) -> String {
+        let debug = format!("{self:?}");
+        debug
+            .split(['(', ' ', '{'])
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::Write;
+
+use crate::{backtest::Backtest, error::UniswapV3MathError};
+
+// backtest results are written as two CSV tables in long format, one row per record for the pool and one row per
+// record and tracked position, joined on block and step. Amounts, prices and fee growth are written as unsigned decimal
+// integers in their raw units, e.g. sqrt prices as Q64.96, so no precision is lost and they can be parsed into
+// arbitrary precision integers or cast to floats by the reader. With the `arrow` feature the same tables can be recorded
+// as Arrow record batches or Parquet files, see `export_arrow`
+
+const POOL_HEADER: &str =
+    "block,step,tick,sqrt_price_x_96,liquidity,fee_growth_global_0_x_128,fee_growth_global_1_x_128";
+const POSITION_HEADER: &str = "block,step,position,tick_lower,tick_upper,liquidity,amount_0,amount_1,fees_0,fees_1,value,hodl_value,rebalances";
+
+//Streams the state of a backtest to CSV every time `record` is called, e.g. from the strategy after every event for
+//per step results or once per block for per block results
+#[derive(Debug)]
+pub struct CsvRecorder<W> {
+    pools: W,
+    positions: W,
+    step: u64,
+}
+
+impl<W: Write> CsvRecorder<W> {
+    // writes the headers of the pool and position tables
+    pub fn new(mut pools: W, mut positions: W) -> Result<Self, UniswapV3MathError> {
+        writeln!(pools, "{POOL_HEADER}")?;
+        writeln!(positions, "{POSITION_HEADER}")?;
+
+        Ok(Self {
+            pools,
+            positions,
+            step: 0,
+        })
+    }
+
+    // writes a row for the pool and a row for each tracked position
+    pub fn record(&mut self, backtest: &Backtest, block: u64) -> Result<(), UniswapV3MathError> {
+        let pool = &backtest.pool;
+        writeln!(
+            self.pools,
+            "{block},{},{},{},{},{},{}",
+            self.step,
+            pool.slot0.tick,
+            pool.slot0.sqrt_price_x_96,
+            pool.liquidity,
+            pool.fee_growth_global_0_x_128,
+            pool.fee_growth_global_1_x_128,
+        )?;
+
+        for (index, position) in backtest.positions.iter().enumerate() {
+            let report = backtest.report(index)?;
+            writeln!(
+                self.positions,
+                "{block},{},{index},{},{},{},{},{},{},{},{},{},{}",
+                self.step,
+                position.tick_lower,
+                position.tick_upper,
+                position.liquidity,
+                report.amount_0,
+                report.amount_1,
+                report.fees_0,
+                report.fees_1,
+                report.value,
+                report.hodl_value,
+                position.rebalances,
+            )?;
+        }
+
+        self.step += 1;
+        Ok(())
+    }
+
+    // the number of records written
+    pub fn steps(&self) -> u64 {
+        self.step
+    }
+
+    // flushes both tables and returns the writers
+    pub fn finish(mut self) -> Result<(W, W), UniswapV3MathError> {
+        self.pools.flush()?;
+        self.positions.flush()?;
+        Ok((self.pools, self.positions))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::CsvRecorder;
+    use crate::{
+        backtest::{Backtest, Event},
+        fee_tier::FeeTier,
+        pool::{PoolSnapshot, SwapParams, ZeroLiquidity},
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    #[test]
+    fn test_csv_recorder() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-887220, 887220, 1e20 as i128).unwrap();
+        let mut backtest = Backtest::new(pool);
+        backtest.add_position(-600, 600, 1e20 as u128).unwrap();
+        backtest.add_position(-120, 120, 1e19 as u128).unwrap();
+
+        let events = [true, false, true].map(|zero_for_one| {
+            Event::Swap(SwapParams {
+                zero_for_one,
+                amount_specified: I256::from_raw(U256::from(1e18 as u128)),
+                sqrt_price_limit_x_96: if zero_for_one {
+                    MIN_SQRT_RATIO + U256_1
+                } else {
+                    MAX_SQRT_RATIO - U256_1
+                },
+                zero_liquidity: ZeroLiquidity::Skip,
+            })
+        });
+
+        //one record per event, each in its own block
+        let mut recorder = CsvRecorder::new(vec![], vec![]).unwrap();
+        let mut block = 100;
+        backtest
+            .run(&events, |backtest| {
+                recorder.record(backtest, block).unwrap();
+                block += 1;
+                vec![]
+            })
+            .unwrap();
+        assert_eq!(recorder.steps(), 3);
+        let (pools, positions) = recorder.finish().unwrap();
+        let pools = String::from_utf8(pools).unwrap();
+        let positions = String::from_utf8(positions).unwrap();
+
+        let rows: Vec<Vec<&str>> = pools.lines().map(|row| row.split(',').collect()).collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|row| row.len() == rows[0].len()));
+        assert_eq!(rows[0][..3], ["block", "step", "tick"]);
+        assert_eq!(rows[3][..2], ["102", "2"]);
+        assert_eq!(rows[3][2], backtest.pool.slot0.tick.to_string());
+        assert_eq!(rows[3][3], backtest.pool.slot0.sqrt_price_x_96.to_string());
+
+        let rows: Vec<Vec<&str>> = positions
+            .lines()
+            .map(|row| row.split(',').collect())
+            .collect();
+        assert_eq!(rows.len(), 1 + 3 * 2);
+        assert!(rows.iter().all(|row| row.len() == rows[0].len()));
+        let report = backtest.report(1).unwrap();
+        assert_eq!(rows[6][..5], ["102", "2", "1", "-120", "120"]);
+        assert_eq!(rows[6][8], report.fees_0.to_string());
+        assert_eq!(rows[6][10], report.value.to_string());
+    }
+}
@@ -0,0 +1,368 @@
+use std::{io::Write, sync::Arc};
+
+use arrow::{
+    array::{ArrayBuilder, ArrayRef, Int32Builder, StringBuilder, UInt32Builder, UInt64Builder},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use parquet::arrow::ArrowWriter;
+
+use crate::{backtest::Backtest, error::UniswapV3MathError};
+
+// backtest results as Arrow record batches with the columns of the CSV tables of `export::CsvRecorder`, one batch for
+// the pool and one for the tracked positions, joined on block and step. Arrow has no integer type wide enough for u128
+// and U256, so liquidity, amounts, prices and fee growth are utf8 columns of unsigned decimal integers in their raw
+// units, which can be parsed losslessly or cast to floats by the reader
+
+// the number of pool records buffered by `ParquetRecorder` before they are written as a row group
+pub const PARQUET_BATCH_ROWS: usize = 8192;
+
+// returns the schema of the pool batches
+pub fn pool_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("block", DataType::UInt64, false),
+        Field::new("step", DataType::UInt64, false),
+        Field::new("tick", DataType::Int32, false),
+        Field::new("sqrt_price_x_96", DataType::Utf8, false),
+        Field::new("liquidity", DataType::Utf8, false),
+        Field::new("fee_growth_global_0_x_128", DataType::Utf8, false),
+        Field::new("fee_growth_global_1_x_128", DataType::Utf8, false),
+    ]))
+}
+
+// returns the schema of the position batches
+pub fn position_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("block", DataType::UInt64, false),
+        Field::new("step", DataType::UInt64, false),
+        Field::new("position", DataType::UInt64, false),
+        Field::new("tick_lower", DataType::Int32, false),
+        Field::new("tick_upper", DataType::Int32, false),
+        Field::new("liquidity", DataType::Utf8, false),
+        Field::new("amount_0", DataType::Utf8, false),
+        Field::new("amount_1", DataType::Utf8, false),
+        Field::new("fees_0", DataType::Utf8, false),
+        Field::new("fees_1", DataType::Utf8, false),
+        Field::new("value", DataType::Utf8, false),
+        Field::new("hodl_value", DataType::Utf8, false),
+        Field::new("rebalances", DataType::UInt32, false),
+    ]))
+}
+
+#[derive(Debug, Default)]
+struct PoolColumns {
+    block: UInt64Builder,
+    step: UInt64Builder,
+    tick: Int32Builder,
+    sqrt_price_x_96: StringBuilder,
+    liquidity: StringBuilder,
+    fee_growth_global_0_x_128: StringBuilder,
+    fee_growth_global_1_x_128: StringBuilder,
+}
+
+impl PoolColumns {
+    fn finish(&mut self) -> Result<RecordBatch, UniswapV3MathError> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.block.finish()),
+            Arc::new(self.step.finish()),
+            Arc::new(self.tick.finish()),
+            Arc::new(self.sqrt_price_x_96.finish()),
+            Arc::new(self.liquidity.finish()),
+            Arc::new(self.fee_growth_global_0_x_128.finish()),
+            Arc::new(self.fee_growth_global_1_x_128.finish()),
+        ];
+        Ok(RecordBatch::try_new(pool_schema(), columns)?)
+    }
+}
+
+#[derive(Debug, Default)]
+struct PositionColumns {
+    block: UInt64Builder,
+    step: UInt64Builder,
+    position: UInt64Builder,
+    tick_lower: Int32Builder,
+    tick_upper: Int32Builder,
+    liquidity: StringBuilder,
+    amount_0: StringBuilder,
+    amount_1: StringBuilder,
+    fees_0: StringBuilder,
+    fees_1: StringBuilder,
+    value: StringBuilder,
+    hodl_value: StringBuilder,
+    rebalances: UInt32Builder,
+}
+
+impl PositionColumns {
+    fn finish(&mut self) -> Result<RecordBatch, UniswapV3MathError> {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.block.finish()),
+            Arc::new(self.step.finish()),
+            Arc::new(self.position.finish()),
+            Arc::new(self.tick_lower.finish()),
+            Arc::new(self.tick_upper.finish()),
+            Arc::new(self.liquidity.finish()),
+            Arc::new(self.amount_0.finish()),
+            Arc::new(self.amount_1.finish()),
+            Arc::new(self.fees_0.finish()),
+            Arc::new(self.fees_1.finish()),
+            Arc::new(self.value.finish()),
+            Arc::new(self.hodl_value.finish()),
+            Arc::new(self.rebalances.finish()),
+        ];
+        Ok(RecordBatch::try_new(position_schema(), columns)?)
+    }
+}
+
+//Collects the state of a backtest into Arrow columns every time `record` is called, e.g. from the strategy after every
+//event for per step results or once per block for per block results. The records are taken out as record batches
+#[derive(Debug, Default)]
+pub struct ArrowRecorder {
+    pools: PoolColumns,
+    positions: PositionColumns,
+    step: u64,
+}
+
+impl ArrowRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // appends a row for the pool and a row for each tracked position
+    pub fn record(&mut self, backtest: &Backtest, block: u64) -> Result<(), UniswapV3MathError> {
+        let pool = &backtest.pool;
+        let pools = &mut self.pools;
+        pools.block.append_value(block);
+        pools.step.append_value(self.step);
+        pools.tick.append_value(pool.slot0.tick);
+        pools
+            .sqrt_price_x_96
+            .append_value(pool.slot0.sqrt_price_x_96.to_string());
+        pools.liquidity.append_value(pool.liquidity.to_string());
+        pools
+            .fee_growth_global_0_x_128
+            .append_value(pool.fee_growth_global_0_x_128.to_string());
+        pools
+            .fee_growth_global_1_x_128
+            .append_value(pool.fee_growth_global_1_x_128.to_string());
+
+        for (index, position) in backtest.positions.iter().enumerate() {
+            let report = backtest.report(index)?;
+            let positions = &mut self.positions;
+            positions.block.append_value(block);
+            positions.step.append_value(self.step);
+            positions.position.append_value(index as u64);
+            positions.tick_lower.append_value(position.tick_lower);
+            positions.tick_upper.append_value(position.tick_upper);
+            positions
+                .liquidity
+                .append_value(position.liquidity.to_string());
+            positions.amount_0.append_value(report.amount_0.to_string());
+            positions.amount_1.append_value(report.amount_1.to_string());
+            positions.fees_0.append_value(report.fees_0.to_string());
+            positions.fees_1.append_value(report.fees_1.to_string());
+            positions.value.append_value(report.value.to_string());
+            positions
+                .hodl_value
+                .append_value(report.hodl_value.to_string());
+            positions.rebalances.append_value(position.rebalances);
+        }
+
+        self.step += 1;
+        Ok(())
+    }
+
+    // the number of records appended
+    pub fn steps(&self) -> u64 {
+        self.step
+    }
+
+    // the number of records appended since the batches were last taken
+    pub fn pending(&self) -> usize {
+        self.pools.block.len()
+    }
+
+    // returns the pool and position records appended since the last call as record batches, e.g. to hand them to
+    // polars or a parquet writer every few blocks instead of keeping a whole backtest in memory
+    pub fn take_batches(&mut self) -> Result<(RecordBatch, RecordBatch), UniswapV3MathError> {
+        Ok((self.pools.finish()?, self.positions.finish()?))
+    }
+}
+
+//Streams the state of a backtest to two Parquet files, records are buffered in an `ArrowRecorder` and written as a row
+//group every `PARQUET_BATCH_ROWS` records
+#[derive(Debug)]
+pub struct ParquetRecorder<W: Write + Send> {
+    recorder: ArrowRecorder,
+    pools: ArrowWriter<W>,
+    positions: ArrowWriter<W>,
+}
+
+impl<W: Write + Send> ParquetRecorder<W> {
+    pub fn new(pools: W, positions: W) -> Result<Self, UniswapV3MathError> {
+        Ok(Self {
+            recorder: ArrowRecorder::new(),
+            pools: ArrowWriter::try_new(pools, pool_schema(), None)?,
+            positions: ArrowWriter::try_new(positions, position_schema(), None)?,
+        })
+    }
+
+    // buffers a row for the pool and a row for each tracked position
+    pub fn record(&mut self, backtest: &Backtest, block: u64) -> Result<(), UniswapV3MathError> {
+        self.recorder.record(backtest, block)?;
+        if self.recorder.pending() >= PARQUET_BATCH_ROWS {
+            self.write_batches()?;
+        }
+        Ok(())
+    }
+
+    // the number of records written or buffered
+    pub fn steps(&self) -> u64 {
+        self.recorder.steps()
+    }
+
+    // writes the buffered records and the footers of both files and returns the writers
+    pub fn finish(mut self) -> Result<(W, W), UniswapV3MathError> {
+        self.write_batches()?;
+        Ok((self.pools.into_inner()?, self.positions.into_inner()?))
+    }
+
+    fn write_batches(&mut self) -> Result<(), UniswapV3MathError> {
+        let (pools, positions) = self.recorder.take_batches()?;
+        self.pools.write(&pools)?;
+        self.positions.write(&positions)?;
+        self.pools.flush()?;
+        self.positions.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+    use arrow::{
+        array::{AsArray, RecordBatch},
+        datatypes::{Int32Type, UInt64Type},
+    };
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::{pool_schema, position_schema, ArrowRecorder, ParquetRecorder};
+    use crate::{
+        backtest::{Backtest, Event},
+        fee_tier::FeeTier,
+        pool::{PoolSnapshot, SwapParams, ZeroLiquidity},
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    fn backtest() -> (Backtest, [Event; 3]) {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-887220, 887220, 1e20 as i128).unwrap();
+        let mut backtest = Backtest::new(pool);
+        backtest.add_position(-600, 600, 1e20 as u128).unwrap();
+        backtest.add_position(-120, 120, 1e19 as u128).unwrap();
+
+        let events = [true, false, true].map(|zero_for_one| {
+            Event::Swap(SwapParams {
+                zero_for_one,
+                amount_specified: I256::from_raw(U256::from(1e18 as u128)),
+                sqrt_price_limit_x_96: if zero_for_one {
+                    MIN_SQRT_RATIO + U256_1
+                } else {
+                    MAX_SQRT_RATIO - U256_1
+                },
+                zero_liquidity: ZeroLiquidity::Skip,
+            })
+        });
+        (backtest, events)
+    }
+
+    fn check_batches(backtest: &Backtest, pools: &RecordBatch, positions: &RecordBatch) {
+        assert_eq!(pools.schema(), pool_schema());
+        assert_eq!(pools.num_rows(), 3);
+        assert_eq!(pools.column(0).as_primitive::<UInt64Type>().value(2), 102);
+        assert_eq!(pools.column(1).as_primitive::<UInt64Type>().value(2), 2);
+        assert_eq!(
+            pools.column(2).as_primitive::<Int32Type>().value(2),
+            backtest.pool.slot0.tick
+        );
+        assert_eq!(
+            pools.column(3).as_string::<i32>().value(2),
+            backtest.pool.slot0.sqrt_price_x_96.to_string()
+        );
+
+        assert_eq!(positions.schema(), position_schema());
+        assert_eq!(positions.num_rows(), 3 * 2);
+        let report = backtest.report(1).unwrap();
+        assert_eq!(positions.column(2).as_primitive::<UInt64Type>().value(5), 1);
+        assert_eq!(
+            positions.column(3).as_primitive::<Int32Type>().value(5),
+            -120
+        );
+        assert_eq!(
+            positions.column(8).as_string::<i32>().value(5),
+            report.fees_0.to_string()
+        );
+        assert_eq!(
+            positions.column(10).as_string::<i32>().value(5),
+            report.value.to_string()
+        );
+    }
+
+    #[test]
+    fn test_arrow_recorder() {
+        let (mut backtest, events) = backtest();
+
+        //one record per event, each in its own block
+        let mut recorder = ArrowRecorder::new();
+        let mut block = 100;
+        backtest
+            .run(&events, |backtest| {
+                recorder.record(backtest, block).unwrap();
+                block += 1;
+                vec![]
+            })
+            .unwrap();
+        assert_eq!(recorder.steps(), 3);
+        assert_eq!(recorder.pending(), 3);
+
+        let (pools, positions) = recorder.take_batches().unwrap();
+        check_batches(&backtest, &pools, &positions);
+
+        //taking the batches starts new ones
+        assert_eq!(recorder.pending(), 0);
+        let (pools, positions) = recorder.take_batches().unwrap();
+        assert_eq!((pools.num_rows(), positions.num_rows()), (0, 0));
+    }
+
+    #[test]
+    fn test_parquet_recorder() {
+        let (mut backtest, events) = backtest();
+
+        let mut recorder = ParquetRecorder::new(vec![], vec![]).unwrap();
+        let mut block = 100;
+        backtest
+            .run(&events, |backtest| {
+                recorder.record(backtest, block).unwrap();
+                block += 1;
+                vec![]
+            })
+            .unwrap();
+        assert_eq!(recorder.steps(), 3);
+        let (pools, positions) = recorder.finish().unwrap();
+
+        //reads back the batches that were recorded
+        let read = |file: Vec<u8>| {
+            let batches = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file))
+                .unwrap()
+                .build()
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(batches.len(), 1);
+            batches.into_iter().next().unwrap()
+        };
+        check_batches(&backtest, &read(pools), &read(positions));
+    }
+}
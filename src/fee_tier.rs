@@ -0,0 +1,216 @@
+use crate::{error::UniswapV3MathError, tick::tick_spacing_to_max_liquidity_per_tick};
+
+pub use crate::constants::FEE_DENOMINATOR;
+// mirrors the bounds enforced by UniswapV3Factory.enableFeeAmount
+pub const MAX_TICK_SPACING: i32 = 16383;
+
+//Fee amount and tick spacing pairs enabled on the canonical factory, plus arbitrary pairs for forks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FeeTier {
+    // 0.01%, tick spacing 1
+    Lowest,
+    // 0.05%, tick spacing 10
+    Low,
+    // 0.3%, tick spacing 60
+    Medium,
+    // 1%, tick spacing 200
+    High,
+    Custom(CustomFeeTier),
+}
+
+//A fee amount and tick spacing pair that is not a canonical tier. The fields are private so every custom tier goes
+//through the validation of `FeeTier::custom`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "CustomFeeTierFields"))]
+pub struct CustomFeeTier {
+    fee: u32,
+    tick_spacing: i32,
+}
+
+impl CustomFeeTier {
+    pub const fn fee(&self) -> u32 {
+        self.fee
+    }
+
+    pub const fn tick_spacing(&self) -> i32 {
+        self.tick_spacing
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct CustomFeeTierFields {
+    fee: u32,
+    tick_spacing: i32,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CustomFeeTierFields> for CustomFeeTier {
+    type Error = UniswapV3MathError;
+
+    fn try_from(fields: CustomFeeTierFields) -> Result<Self, UniswapV3MathError> {
+        FeeTier::check_custom(fields.fee, fields.tick_spacing, crate::v4::MAX_TICK_SPACING)?;
+        Ok(Self {
+            fee: fields.fee,
+            tick_spacing: fields.tick_spacing,
+        })
+    }
+}
+
+impl FeeTier {
+    // returns a custom fee tier, validated the same way the factory validates new fee amounts
+    pub fn custom(fee: u32, tick_spacing: i32) -> Result<Self, UniswapV3MathError> {
+        Self::check_custom(fee, tick_spacing, MAX_TICK_SPACING)?;
+        Ok(Self::custom_unchecked(fee, tick_spacing))
+    }
+
+    // same as `custom` with the tick spacing bound of another pool manager, V4 accepts any spacing up to int16 max
+    pub(crate) fn custom_with_max_tick_spacing(
+        fee: u32,
+        tick_spacing: i32,
+        max_tick_spacing: i32,
+    ) -> Result<Self, UniswapV3MathError> {
+        Self::check_custom(fee, tick_spacing, max_tick_spacing)?;
+        Ok(Self::custom_unchecked(fee, tick_spacing))
+    }
+
+    // same as `custom` for fee tiers known at compile time, an invalid pair fails the build
+    pub(crate) const fn custom_const(fee: u32, tick_spacing: i32) -> Self {
+        if fee >= FEE_DENOMINATOR || tick_spacing <= 0 || tick_spacing > MAX_TICK_SPACING {
+            panic!("invalid fee tier");
+        }
+        Self::custom_unchecked(fee, tick_spacing)
+    }
+
+    const fn check_custom(
+        fee: u32,
+        tick_spacing: i32,
+        max_tick_spacing: i32,
+    ) -> Result<(), UniswapV3MathError> {
+        if fee >= FEE_DENOMINATOR {
+            return Err(UniswapV3MathError::InvalidFee);
+        }
+        if tick_spacing <= 0 || tick_spacing > max_tick_spacing {
+            return Err(UniswapV3MathError::TickSpacingError);
+        }
+        Ok(())
+    }
+
+    // canonical tiers are normalized, so a custom pair matching one compares equal to it
+    const fn custom_unchecked(fee: u32, tick_spacing: i32) -> Self {
+        match Self::from_fee(fee) {
+            Some(tier) if tier.tick_spacing() == tick_spacing => tier,
+            _ => Self::Custom(CustomFeeTier { fee, tick_spacing }),
+        }
+    }
+
+    // returns the canonical fee tier for a fee amount, if any
+    pub const fn from_fee(fee: u32) -> Option<Self> {
+        match fee {
+            100 => Some(Self::Lowest),
+            500 => Some(Self::Low),
+            3000 => Some(Self::Medium),
+            10000 => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    pub const fn fee(&self) -> u32 {
+        match self {
+            Self::Lowest => 100,
+            Self::Low => 500,
+            Self::Medium => 3000,
+            Self::High => 10000,
+            Self::Custom(custom) => custom.fee,
+        }
+    }
+
+    pub const fn tick_spacing(&self) -> i32 {
+        match self {
+            Self::Lowest => 1,
+            Self::Low => 10,
+            Self::Medium => 60,
+            Self::High => 200,
+            Self::Custom(custom) => custom.tick_spacing,
+        }
+    }
+
+    pub fn max_liquidity_per_tick(&self) -> Result<u128, UniswapV3MathError> {
+        tick_spacing_to_max_liquidity_per_tick(self.tick_spacing())
+    }
+}
+
+impl From<FeeTier> for u32 {
+    fn from(tier: FeeTier) -> Self {
+        tier.fee()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FeeTier;
+
+    #[test]
+    fn test_fee_tier() {
+        //canonical tiers
+        assert_eq!(FeeTier::Lowest.fee(), 100);
+        assert_eq!(FeeTier::Lowest.tick_spacing(), 1);
+        assert_eq!(FeeTier::Low.fee(), 500);
+        assert_eq!(FeeTier::Low.tick_spacing(), 10);
+        assert_eq!(FeeTier::Medium.fee(), 3000);
+        assert_eq!(FeeTier::Medium.tick_spacing(), 60);
+        assert_eq!(FeeTier::High.fee(), 10000);
+        assert_eq!(FeeTier::High.tick_spacing(), 200);
+        assert_eq!(
+            FeeTier::Medium.max_liquidity_per_tick().unwrap(),
+            11505743598341114571880798222544994
+        );
+
+        //from fee
+        assert_eq!(FeeTier::from_fee(500), Some(FeeTier::Low));
+        assert_eq!(FeeTier::from_fee(2500), None);
+
+        //custom tiers
+        let tier = FeeTier::custom(2500, 50).unwrap();
+        assert_eq!(tier.fee(), 2500);
+        assert_eq!(tier.tick_spacing(), 50);
+        assert_eq!(u32::from(tier), 2500);
+
+        //custom tiers matching a canonical tier are normalized
+        assert_eq!(FeeTier::custom(3000, 60).unwrap(), FeeTier::Medium);
+        let tier = FeeTier::custom(3000, 10).unwrap();
+        assert!(matches!(tier, FeeTier::Custom(_)));
+        assert_eq!((tier.fee(), tier.tick_spacing()), (3000, 10));
+        assert_eq!(
+            FeeTier::custom_const(2500, 50),
+            FeeTier::custom(2500, 50).unwrap()
+        );
+
+        //fee must be less than 100%
+        let result = FeeTier::custom(1000000, 60);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Fee must be less than 1e6 pips"
+        );
+
+        //tick spacing must be within bounds
+        assert!(FeeTier::custom(3000, 0).is_err());
+        assert!(FeeTier::custom(3000, 16384).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_fee_tier_serde() {
+        let tier = FeeTier::custom(2500, 50).unwrap();
+        let json = serde_json::to_string(&tier).unwrap();
+        assert_eq!(serde_json::from_str::<FeeTier>(&json).unwrap(), tier);
+
+        //custom tiers are validated when deserialized
+        let json = r#"{"Custom":{"fee":1000000,"tick_spacing":50}}"#;
+        assert!(serde_json::from_str::<FeeTier>(json).is_err());
+        let json = r#"{"Custom":{"fee":2500,"tick_spacing":0}}"#;
+        assert!(serde_json::from_str::<FeeTier>(json).is_err());
+    }
+}
@@ -0,0 +1,490 @@
+use std::{collections::HashMap, future::IntoFuture, sync::Arc};
+
+use alloy::{
+    eips::BlockId,
+    providers::Provider,
+    rpc::types::Filter,
+    sol,
+    sol_types::{SolCall, SolEvent},
+};
+use alloy_primitives::{address, Address, BlockNumber, Bytes, I256, U256};
+use futures::{stream, StreamExt, TryStreamExt};
+
+use crate::{
+    delta::{touched_state, PoolEvent},
+    error::UniswapV3MathError,
+    fee_tier::FeeTier,
+    pool::{PoolSnapshot, ProtocolFees, Slot0},
+    tick::Tick,
+    tick_bitmap::position,
+    tick_math::{MAX_TICK, MIN_TICK},
+    variant::UniswapV3,
+};
+
+sol! {
+    #[sol(rpc)]
+    interface IUniswapV3PoolState {
+        function slot0() external view returns (uint160 sqrtPriceX96, int24 tick, uint16 observationIndex, uint16 observationCardinality, uint16 observationCardinalityNext, uint8 feeProtocol, bool unlocked);
+        function feeGrowthGlobal0X128() external view returns (uint256 feeGrowthGlobal0X128);
+        function feeGrowthGlobal1X128() external view returns (uint256 feeGrowthGlobal1X128);
+        function protocolFees() external view returns (uint128 token0, uint128 token1);
+        function liquidity() external view returns (uint128 liquidity);
+        function fee() external view returns (uint24 fee);
+        function tickSpacing() external view returns (int24 tickSpacing);
+        function tickBitmap(int16 wordPosition) external view returns (uint256 word);
+        function ticks(int24 tick) external view returns (uint128 liquidityGross, int128 liquidityNet, uint256 feeGrowthOutside0X128, uint256 feeGrowthOutside1X128, int56 tickCumulativeOutside, uint160 secondsPerLiquidityOutsideX128, uint32 secondsOutside, bool initialized);
+
+        event Mint(address sender, address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1);
+        event Burn(address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1);
+        event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick);
+    }
+
+    #[sol(rpc)]
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+    }
+}
+
+// the address Multicall3 is deployed at on mainnet and most other chains
+pub const MULTICALL3_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+// the number of calls packed into a single aggregate3 call by default. Tick reads return 8 words, so a batch of 500
+// returns well under the response size limits of common providers
+pub const DEFAULT_MULTICALL_BATCH_SIZE: usize = 500;
+
+// the number of tick bitmap word or tick reads that are in flight at once
+const MAX_CONCURRENT_READS: usize = 32;
+
+// returns a snapshot of the pool at `pool_address` at the given block, or the latest block if none is given. The latest
+// block number is resolved once and every read is made at it, so the snapshot is consistent even if blocks are mined
+// while it is taken. The pool state is read through its public getters, then every tick bitmap word that can hold a
+// tick for the pool's tick spacing is read, followed by the data of each initialized tick, up to
+// `MAX_CONCURRENT_READS` reads at a time. Pools with small tick spacings have many words, so this is best run against a
+// node without strict rate limits
+pub async fn pool_snapshot<P: Provider>(
+    provider: Arc<P>,
+    pool_address: Address,
+    block_number: Option<BlockNumber>,
+) -> Result<PoolSnapshot, UniswapV3MathError> {
+    let block = pinned_block(provider.as_ref(), block_number).await?;
+    let pool = IUniswapV3PoolState::new(pool_address, provider);
+
+    let fee = pool
+        .fee()
+        .block(block)
+        .call()
+        .await
+        .map_err(middleware_error)?;
+    let tick_spacing = pool
+        .tickSpacing()
+        .block(block)
+        .call()
+        .await
+        .map_err(middleware_error)?;
+    let fee_tier = FeeTier::custom(fee.fee.to::<u32>(), tick_spacing.tickSpacing.as_i32())?;
+
+    let mut snapshot = PoolSnapshot {
+        slot0: Slot0::default(),
+        liquidity: 0,
+        fee_tier,
+        fee_growth_global_0_x_128: U256::ZERO,
+        fee_growth_global_1_x_128: U256::ZERO,
+        protocol_fees: ProtocolFees::default(),
+        ticks: HashMap::new(),
+        tick_bitmap: HashMap::new(),
+        variant: UniswapV3,
+    };
+    fetch_globals(&pool, &mut snapshot, block).await?;
+
+    let tick_spacing = fee_tier.tick_spacing();
+    let (min_word, _) = position(MIN_TICK / tick_spacing);
+    let (max_word, _) = position(MAX_TICK / tick_spacing);
+    let words = fetch_words(&pool, min_word..=max_word, block).await?;
+
+    let mut ticks = vec![];
+    for (word_pos, word) in words {
+        if word.is_zero() {
+            continue;
+        }
+        snapshot.tick_bitmap.insert(word_pos, word);
+
+        for bit_pos in 0..256 {
+            if word.bit(bit_pos) {
+                ticks.push(((word_pos as i32) * 256 + bit_pos as i32) * tick_spacing);
+            }
+        }
+    }
+    snapshot
+        .ticks
+        .extend(fetch_ticks(&pool, ticks, block).await?);
+
+    Ok(snapshot)
+}
+
+// updates `snapshot`, taken at `from_block`, to the state of the pool at `pool_address` at `to_block`. Instead of
+// reading every tick again, the Mint, Burn and Swap logs of the pool in (from_block, to_block] are fetched and only the
+// ticks and tick bitmap words those events may have touched are read, along with the pool globals. Ticks that are no
+// longer initialized are removed from the snapshot
+pub async fn refresh<P: Provider>(
+    snapshot: &mut PoolSnapshot,
+    provider: Arc<P>,
+    pool_address: Address,
+    from_block: BlockNumber,
+    to_block: BlockNumber,
+) -> Result<(), UniswapV3MathError> {
+    if to_block <= from_block {
+        return Ok(());
+    }
+
+    let filter = Filter::new()
+        .address(pool_address)
+        .event_signature(vec![
+            IUniswapV3PoolState::Mint::SIGNATURE_HASH,
+            IUniswapV3PoolState::Burn::SIGNATURE_HASH,
+            IUniswapV3PoolState::Swap::SIGNATURE_HASH,
+        ])
+        .from_block(from_block + 1)
+        .to_block(to_block);
+    let logs = provider.get_logs(&filter).await.map_err(middleware_error)?;
+
+    let mut events = Vec::with_capacity(logs.len());
+    for log in &logs {
+        let event = match log.topic0() {
+            Some(&IUniswapV3PoolState::Mint::SIGNATURE_HASH) => {
+                let mint = log
+                    .log_decode::<IUniswapV3PoolState::Mint>()
+                    .map_err(middleware_error)?;
+                PoolEvent::Mint {
+                    tick_lower: mint.inner.tickLower.as_i32(),
+                    tick_upper: mint.inner.tickUpper.as_i32(),
+                }
+            }
+            Some(&IUniswapV3PoolState::Burn::SIGNATURE_HASH) => {
+                let burn = log
+                    .log_decode::<IUniswapV3PoolState::Burn>()
+                    .map_err(middleware_error)?;
+                PoolEvent::Burn {
+                    tick_lower: burn.inner.tickLower.as_i32(),
+                    tick_upper: burn.inner.tickUpper.as_i32(),
+                }
+            }
+            Some(&IUniswapV3PoolState::Swap::SIGNATURE_HASH) => {
+                let swap = log
+                    .log_decode::<IUniswapV3PoolState::Swap>()
+                    .map_err(middleware_error)?;
+                PoolEvent::Swap {
+                    tick: swap.inner.tick.as_i32(),
+                }
+            }
+            _ => continue,
+        };
+        events.push(event);
+    }
+
+    let delta = touched_state(snapshot, &events);
+    let pool = IUniswapV3PoolState::new(pool_address, provider);
+    let block = BlockId::from(to_block);
+
+    // flash and collect only move the globals, which are read on every refresh
+    fetch_globals(&pool, snapshot, block).await?;
+
+    for (word_pos, word) in fetch_words(&pool, delta.tick_bitmap_words, block).await? {
+        if word.is_zero() {
+            snapshot.tick_bitmap.remove(&word_pos);
+        } else {
+            snapshot.tick_bitmap.insert(word_pos, word);
+        }
+    }
+
+    for (tick, info) in fetch_ticks(&pool, delta.ticks, block).await? {
+        if info.initialized {
+            snapshot.ticks.insert(tick, info);
+        } else {
+            snapshot.ticks.remove(&tick);
+        }
+    }
+
+    Ok(())
+}
+
+// same as `pool_snapshot`, but packs the reads into aggregate3 calls to the Multicall3 contract at `multicall_address`,
+// at most `batch_size` reads per call. The pool state is read in one call, then all tick bitmap words and finally all
+// initialized ticks in as many calls as needed, so a pool with thousands of initialized ticks takes a handful of
+// requests instead of one per word and tick
+pub async fn pool_snapshot_multicall<P: Provider>(
+    provider: Arc<P>,
+    pool_address: Address,
+    multicall_address: Address,
+    block_number: Option<BlockNumber>,
+    batch_size: usize,
+) -> Result<PoolSnapshot, UniswapV3MathError> {
+    // the batches are separate calls, without a pinned block they could each see a different latest block
+    let block = pinned_block(provider.as_ref(), block_number).await?;
+    let multicall = IMulticall3::new(multicall_address, provider);
+
+    let globals = aggregate(
+        &multicall,
+        pool_address,
+        vec![
+            IUniswapV3PoolState::feeCall {}.abi_encode(),
+            IUniswapV3PoolState::tickSpacingCall {}.abi_encode(),
+            IUniswapV3PoolState::slot0Call {}.abi_encode(),
+            IUniswapV3PoolState::liquidityCall {}.abi_encode(),
+            IUniswapV3PoolState::feeGrowthGlobal0X128Call {}.abi_encode(),
+            IUniswapV3PoolState::feeGrowthGlobal1X128Call {}.abi_encode(),
+            IUniswapV3PoolState::protocolFeesCall {}.abi_encode(),
+        ],
+        block,
+        batch_size,
+    )
+    .await?;
+    let fee = decode_return::<IUniswapV3PoolState::feeCall>(&globals[0])?.fee;
+    let tick_spacing =
+        decode_return::<IUniswapV3PoolState::tickSpacingCall>(&globals[1])?.tickSpacing;
+    let fee_tier = FeeTier::custom(fee.to::<u32>(), tick_spacing.as_i32())?;
+    let protocol_fees = decode_return::<IUniswapV3PoolState::protocolFeesCall>(&globals[6])?;
+
+    let mut snapshot = PoolSnapshot {
+        slot0: slot0_from_return(decode_return::<IUniswapV3PoolState::slot0Call>(
+            &globals[2],
+        )?),
+        liquidity: decode_return::<IUniswapV3PoolState::liquidityCall>(&globals[3])?.liquidity,
+        fee_tier,
+        fee_growth_global_0_x_128: decode_return::<IUniswapV3PoolState::feeGrowthGlobal0X128Call>(
+            &globals[4],
+        )?
+        .feeGrowthGlobal0X128,
+        fee_growth_global_1_x_128: decode_return::<IUniswapV3PoolState::feeGrowthGlobal1X128Call>(
+            &globals[5],
+        )?
+        .feeGrowthGlobal1X128,
+        protocol_fees: ProtocolFees {
+            token_0: protocol_fees.token0,
+            token_1: protocol_fees.token1,
+        },
+        ticks: HashMap::new(),
+        tick_bitmap: HashMap::new(),
+        variant: UniswapV3,
+    };
+
+    let tick_spacing = fee_tier.tick_spacing();
+    let (min_word, _) = position(MIN_TICK / tick_spacing);
+    let (max_word, _) = position(MAX_TICK / tick_spacing);
+    let words = aggregate(
+        &multicall,
+        pool_address,
+        (min_word..=max_word)
+            .map(|word_pos| {
+                IUniswapV3PoolState::tickBitmapCall {
+                    wordPosition: word_pos,
+                }
+                .abi_encode()
+            })
+            .collect(),
+        block,
+        batch_size,
+    )
+    .await?;
+
+    let mut ticks = vec![];
+    for (word_pos, word) in (min_word..=max_word).zip(&words) {
+        let word = decode_return::<IUniswapV3PoolState::tickBitmapCall>(word)?.word;
+        if word.is_zero() {
+            continue;
+        }
+        snapshot.tick_bitmap.insert(word_pos, word);
+
+        for bit_pos in 0..256 {
+            if word.bit(bit_pos) {
+                ticks.push(((word_pos as i32) * 256 + bit_pos as i32) * tick_spacing);
+            }
+        }
+    }
+
+    let calls = ticks
+        .iter()
+        .map(|&tick| {
+            Ok(IUniswapV3PoolState::ticksCall {
+                tick: tick.try_into().map_err(|_| UniswapV3MathError::T)?,
+            }
+            .abi_encode())
+        })
+        .collect::<Result<_, UniswapV3MathError>>()?;
+    let infos = aggregate(&multicall, pool_address, calls, block, batch_size).await?;
+    for (tick, info) in ticks.into_iter().zip(&infos) {
+        let info = decode_return::<IUniswapV3PoolState::ticksCall>(info)?;
+        snapshot.ticks.insert(tick, tick_from_return(info));
+    }
+
+    Ok(snapshot)
+}
+
+// reads slot0, liquidity, fee growth and protocol fees into `snapshot`, concurrently and at the same block
+async fn fetch_globals<P: Provider>(
+    pool: &IUniswapV3PoolState::IUniswapV3PoolStateInstance<(), Arc<P>>,
+    snapshot: &mut PoolSnapshot,
+    block: BlockId,
+) -> Result<(), UniswapV3MathError> {
+    let slot0 = pool.slot0().block(block);
+    let liquidity = pool.liquidity().block(block);
+    let fee_growth_global_0_x_128 = pool.feeGrowthGlobal0X128().block(block);
+    let fee_growth_global_1_x_128 = pool.feeGrowthGlobal1X128().block(block);
+    let protocol_fees = pool.protocolFees().block(block);
+    let (slot0, liquidity, fee_growth_global_0_x_128, fee_growth_global_1_x_128, protocol_fees) =
+        futures::try_join!(
+            slot0.call().into_future(),
+            liquidity.call().into_future(),
+            fee_growth_global_0_x_128.call().into_future(),
+            fee_growth_global_1_x_128.call().into_future(),
+            protocol_fees.call().into_future(),
+        )
+        .map_err(middleware_error)?;
+
+    snapshot.slot0 = slot0_from_return(slot0);
+    snapshot.liquidity = liquidity.liquidity;
+    snapshot.fee_growth_global_0_x_128 = fee_growth_global_0_x_128.feeGrowthGlobal0X128;
+    snapshot.fee_growth_global_1_x_128 = fee_growth_global_1_x_128.feeGrowthGlobal1X128;
+    snapshot.protocol_fees = ProtocolFees {
+        token_0: protocol_fees.token0,
+        token_1: protocol_fees.token1,
+    };
+
+    Ok(())
+}
+
+// reads the tick bitmap words, `MAX_CONCURRENT_READS` at a time, and returns them in the given order
+async fn fetch_words<P: Provider>(
+    pool: &IUniswapV3PoolState::IUniswapV3PoolStateInstance<(), Arc<P>>,
+    word_positions: impl IntoIterator<Item = i16>,
+    block: BlockId,
+) -> Result<Vec<(i16, U256)>, UniswapV3MathError> {
+    stream::iter(word_positions)
+        .map(|word_pos| async move {
+            let word = pool
+                .tickBitmap(word_pos)
+                .block(block)
+                .call()
+                .await
+                .map_err(middleware_error)?
+                .word;
+            Ok((word_pos, word))
+        })
+        .buffered(MAX_CONCURRENT_READS)
+        .try_collect()
+        .await
+}
+
+// reads the ticks, `MAX_CONCURRENT_READS` at a time, and returns them in the given order
+async fn fetch_ticks<P: Provider>(
+    pool: &IUniswapV3PoolState::IUniswapV3PoolStateInstance<(), Arc<P>>,
+    ticks: impl IntoIterator<Item = i32>,
+    block: BlockId,
+) -> Result<Vec<(i32, Tick)>, UniswapV3MathError> {
+    stream::iter(ticks)
+        .map(|tick| async move {
+            let info = pool
+                .ticks(tick.try_into().map_err(|_| UniswapV3MathError::T)?)
+                .block(block)
+                .call()
+                .await
+                .map_err(middleware_error)?;
+            Ok((tick, tick_from_return(info)))
+        })
+        .buffered(MAX_CONCURRENT_READS)
+        .try_collect()
+        .await
+}
+
+// returns the given block, or the current block number so that every read of a snapshot is made at the same block
+async fn pinned_block<P: Provider>(
+    provider: &P,
+    block_number: Option<BlockNumber>,
+) -> Result<BlockId, UniswapV3MathError> {
+    let block_number = match block_number {
+        Some(block_number) => block_number,
+        None => provider
+            .get_block_number()
+            .await
+            .map_err(middleware_error)?,
+    };
+
+    Ok(BlockId::from(block_number))
+}
+
+fn slot0_from_return(slot0: IUniswapV3PoolState::slot0Return) -> Slot0 {
+    Slot0 {
+        sqrt_price_x_96: U256::from(slot0.sqrtPriceX96),
+        tick: slot0.tick.as_i32(),
+        observation_index: slot0.observationIndex,
+        observation_cardinality: slot0.observationCardinality,
+        observation_cardinality_next: slot0.observationCardinalityNext,
+        fee_protocol: slot0.feeProtocol.into(),
+        unlocked: slot0.unlocked,
+    }
+}
+
+fn tick_from_return(info: IUniswapV3PoolState::ticksReturn) -> Tick {
+    Tick {
+        liquidity_gross: info.liquidityGross,
+        liquidity_net: info.liquidityNet,
+        fee_growth_outside_0_x_128: info.feeGrowthOutside0X128,
+        fee_growth_outside_1_x_128: info.feeGrowthOutside1X128,
+        // the int56 is sign extended to 256 bits
+        tick_cumulative_outside: I256::unchecked_from(info.tickCumulativeOutside.as_i64())
+            .into_raw(),
+        seconds_per_liquidity_outside_x_128: U256::from(info.secondsPerLiquidityOutsideX128),
+        seconds_outside: info.secondsOutside,
+        initialized: info.initialized,
+    }
+}
+
+// calls `target` with each of `calls` through aggregate3, `batch_size` calls at a time, and returns the return data
+// in the order of the calls. A reverting call fails the whole batch
+async fn aggregate<P: Provider>(
+    multicall: &IMulticall3::IMulticall3Instance<(), Arc<P>>,
+    target: Address,
+    calls: Vec<Vec<u8>>,
+    block: BlockId,
+    batch_size: usize,
+) -> Result<Vec<Bytes>, UniswapV3MathError> {
+    let mut return_data = Vec::with_capacity(calls.len());
+    for batch in calls.chunks(batch_size.max(1)) {
+        let batch = batch
+            .iter()
+            .map(|call_data| IMulticall3::Call3 {
+                target,
+                allowFailure: false,
+                callData: call_data.clone().into(),
+            })
+            .collect();
+        let results = multicall
+            .aggregate3(batch)
+            .block(block)
+            .call()
+            .await
+            .map_err(middleware_error)?
+            .returnData;
+        return_data.extend(results.into_iter().map(|result| result.returnData));
+    }
+
+    Ok(return_data)
+}
+
+fn decode_return<C: SolCall>(data: &Bytes) -> Result<C::Return, UniswapV3MathError> {
+    C::abi_decode_returns(data, true).map_err(middleware_error)
+}
+
+fn middleware_error(err: impl ToString) -> UniswapV3MathError {
+    UniswapV3MathError::MiddlewareError(err.to_string())
+}
@@ -4,8 +4,106 @@ use alloy_primitives::U256;
 
 use crate::{error::UniswapV3MathError, U256_1, U256_2, U256_3};
 
-// returns (uint256 result)
-pub fn mul_div(a: U256, b: U256, mut denominator: U256) -> Result<U256, UniswapV3MathError> {
+pub use crate::constants::Q128;
+
+//An unsigned 512 bit number as its high and low 256 bits, e.g. the full product of two U256 from `mul_512`. Lets
+//formulas that are not in the crate keep full precision through intermediate products the same way `mul_div` does
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uint512 {
+    pub hi: U256,
+    pub lo: U256,
+}
+
+impl Uint512 {
+    pub const ZERO: Self = Self {
+        hi: U256::ZERO,
+        lo: U256::ZERO,
+    };
+
+    pub const fn new(hi: U256, lo: U256) -> Self {
+        Self { hi, lo }
+    }
+
+    // returns self + other, `None` on overflow
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        let (lo, carry) = self.lo.overflowing_add(other.lo);
+        let hi = self
+            .hi
+            .checked_add(other.hi)?
+            .checked_add(U256::from(carry))?;
+        Some(Self { hi, lo })
+    }
+
+    // returns self - other, `None` on underflow
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        let (lo, borrow) = self.lo.overflowing_sub(other.lo);
+        let hi = self
+            .hi
+            .checked_sub(other.hi)?
+            .checked_sub(U256::from(borrow))?;
+        Some(Self { hi, lo })
+    }
+
+    // returns self mod denominator, fails with `DenominatorIsZero` if the denominator is 0
+    pub fn rem_u256(self, denominator: U256) -> Result<U256, UniswapV3MathError> {
+        if denominator.is_zero() {
+            return Err(UniswapV3MathError::DenominatorIsZero);
+        }
+
+        // hi * 2**256 + lo with 2**256 mod denominator = (2**256 - 1) mod denominator + 1
+        let two_256 = (U256::MAX % denominator).add_mod(U256_1, denominator);
+        Ok(self
+            .hi
+            .mul_mod(two_256, denominator)
+            .add_mod(self.lo, denominator))
+    }
+
+    // returns floor(self / denominator) like `mul_div`, fails with `DenominatorIsZero` if the denominator is 0 and with
+    // `DenominatorIsLteProdOne` if the quotient does not fit in 256 bits
+    pub fn div_u256(self, denominator: U256) -> Result<U256, UniswapV3MathError> {
+        if self.hi.is_zero() {
+            if denominator.is_zero() {
+                return Err(UniswapV3MathError::DenominatorIsZero);
+            }
+            return Ok(self.lo / denominator);
+        }
+
+        if denominator <= self.hi {
+            return Err(UniswapV3MathError::DenominatorIsLteProdOne);
+        }
+
+        Ok(div_512(
+            self.lo,
+            self.hi,
+            self.rem_u256(denominator)?,
+            denominator,
+        ))
+    }
+
+    // returns ceil(self / denominator) like `mul_div_rounding_up`, see `div_u256`
+    pub fn div_u256_rounding_up(self, denominator: U256) -> Result<U256, UniswapV3MathError> {
+        let result = self.div_u256(denominator)?;
+
+        if self.rem_u256(denominator)? > U256::ZERO {
+            if result == U256::MAX {
+                Err(UniswapV3MathError::ResultIsU256MAX)
+            } else {
+                Ok(result + U256_1)
+            }
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+impl From<U256> for Uint512 {
+    fn from(value: U256) -> Self {
+        Self::new(U256::ZERO, value)
+    }
+}
+
+// returns the full 512 bit product a * b
+pub fn mul_512(a: U256, b: U256) -> Uint512 {
     // 512-bit multiply [prod1 prod0] = a * b
     // Compute the product mod 2**256 and mod 2**256 - 1
     // then use the Chinese Remainder Theorem to reconstruct
@@ -13,13 +111,23 @@ pub fn mul_div(a: U256, b: U256, mut denominator: U256) -> Result<U256, UniswapV
     // variables such that product = prod1 * 2**256 + prod0
     let mm = a.mul_mod(b, U256::MAX);
 
-    let mut prod_0 = a.overflowing_mul(b).0; // Least significant 256 bits of the product
-    let mut prod_1 = mm
+    let prod_0 = a.overflowing_mul(b).0; // Least significant 256 bits of the product
+    let prod_1 = mm
         .overflowing_sub(prod_0)
         .0
         .overflowing_sub(U256::from((mm < prod_0) as u8))
         .0;
 
+    Uint512::new(prod_1, prod_0)
+}
+
+// returns (uint256 result)
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Result<U256, UniswapV3MathError> {
+    let Uint512 {
+        hi: prod_1,
+        lo: prod_0,
+    } = mul_512(a, b);
+
     // Handle non-overflow cases, 256 by 256 division
     if prod_1 == U256::ZERO {
         if denominator == U256::ZERO {
@@ -43,6 +151,11 @@ pub fn mul_div(a: U256, b: U256, mut denominator: U256) -> Result<U256, UniswapV
     // Compute remainder using mulmod
     let remainder = a.mul_mod(b, denominator);
 
+    Ok(div_512(prod_0, prod_1, remainder, denominator))
+}
+
+// returns [prod1 prod0] / denominator given the remainder of the division, the quotient must fit in 256 bits
+fn div_512(mut prod_0: U256, mut prod_1: U256, remainder: U256, mut denominator: U256) -> U256 {
     // Subtract 256 bit number from 512 bit number
     prod_1 = prod_1
         .overflowing_sub(U256::from((remainder > prod_0) as u8))
@@ -98,7 +211,7 @@ pub fn mul_div(a: U256, b: U256, mut denominator: U256) -> Result<U256, UniswapV
     // We don't need to compute the high bits of the result and prod1
     // is no longer required.
 
-    Ok(U256::from_le_slice((prod_0 * inv).as_le_slice()))
+    U256::from_le_slice((prod_0 * inv).as_le_slice())
 }
 
 pub fn mul_div_rounding_up(
@@ -119,12 +232,76 @@ pub fn mul_div_rounding_up(
     }
 }
 
+// returns floor(sqrt(x)), computed with Newton's method starting from a power of two above the root
+pub fn sqrt(x: U256) -> U256 {
+    if x < U256_2 {
+        return x;
+    }
+
+    // 2**ceil(bits / 2) is always >= sqrt(x), so the iteration decreases monotonically
+    let mut z = U256_1 << x.bit_len().div_ceil(2);
+    let mut y = (z + x / z) >> 1;
+
+    while y < z {
+        z = y;
+        y = (z + x / z) >> 1;
+    }
+
+    z
+}
+
+// returns ceil(sqrt(x)). Can not overflow, the root of U256::MAX rounded up is 2**128
+pub fn sqrt_rounding_up(x: U256) -> U256 {
+    let root = sqrt(x);
+    if root * root < x {
+        root + U256_1
+    } else {
+        root
+    }
+}
+
+// 2**64 / log2(1.0001), rounded down
+const LOG_2_1_0001_RECIPROCAL_X_64: U256 = U256::from_limbs([15096324921010923074, 6931, 0, 0]);
+
+// returns log2(x) as a Q64.64, fails with `ZeroValue` if x is 0. The integer part is the index of the most significant
+// bit and the 64 fractional bits are computed by repeated squaring like in TickMath.getTickAtSqrtRatio. The result is
+// rounded down, it is never above the exact value and at most 2**-63 below it. Logs of ratios are the difference of
+// the logs of the numerator and the denominator
+pub fn log2(x: U256) -> Result<U256, UniswapV3MathError> {
+    if x.is_zero() {
+        return Err(UniswapV3MathError::ZeroValue);
+    }
+
+    let msb = x.bit_len() - 1;
+    // x / 2**msb in [1, 2) as a Q1.127
+    let mut r = if msb >= 127 {
+        x >> (msb - 127)
+    } else {
+        x << (127 - msb)
+    };
+
+    let mut log_2 = U256::from(msb) << 64;
+    for bit in (0..64).rev() {
+        // squaring doubles the log, if the square is at least 2 the next bit is set
+        r = (r * r) >> 127;
+        let f = r >> 128;
+        log_2 |= f << bit;
+        r >>= f;
+    }
+
+    Ok(log_2)
+}
+
+// returns log base 1.0001 of x as a Q64.64, e.g. the fractional tick of a price. Fails with `ZeroValue` if x is 0.
+// Rounded down, the result is never above the exact value and at most 2**-51 below it, see `log2`
+pub fn log_1_0001(x: U256) -> Result<U256, UniswapV3MathError> {
+    Ok((log2(x)? * LOG_2_1_0001_RECIPROCAL_X_64) >> 64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const Q128: U256 = U256::from_limbs([0, 0, 1, 0]);
-
     #[test]
     fn test_mul_div() {
         //Revert if the denominator is zero
@@ -153,9 +330,11 @@ mod test {
     use alloy_primitives::U256;
     use std::ops::{Div, Mul, Sub};
 
-    use super::mul_div;
-
-    const Q128: U256 = U256::from_limbs([0, 0, 1, 0]);
+    use super::{
+        log2, log_1_0001, mul_512, mul_div, mul_div_rounding_up, sqrt, sqrt_rounding_up, Uint512,
+        Q128,
+    };
+    use crate::error::UniswapV3MathError;
 
     #[test]
     fn test_mul_div() {
@@ -207,4 +386,142 @@ mod test {
         let result = mul_div(Q128, U256::from(1000).mul(Q128), U256::from(3000).mul(Q128));
         assert_eq!(result.unwrap(), Q128.div(U256::from(3)));
     }
+
+    #[test]
+    fn test_sqrt() {
+        assert_eq!(sqrt(U256::ZERO), U256::ZERO);
+        assert_eq!(sqrt(U256_1), U256_1);
+        assert_eq!(sqrt(U256::from(3)), U256_1);
+        assert_eq!(sqrt(U256::from(4)), U256::from(2));
+        assert_eq!(sqrt(U256::from(99)), U256::from(9));
+        assert_eq!(sqrt(U256::from(100)), U256::from(10));
+
+        // Rounds down for non perfect squares
+        assert_eq!(sqrt(Q128.sub(U256_1)), U256::from(u64::MAX));
+        assert_eq!(sqrt(Q128), U256::from(u64::MAX) + U256_1);
+
+        // Max input
+        assert_eq!(sqrt(U256::MAX), U256::from(u128::MAX));
+    }
+
+    #[test]
+    fn test_uint512() {
+        let to_u512 = |value: Uint512| {
+            (alloy_primitives::U512::from(value.hi) << 256) + alloy_primitives::U512::from(value.lo)
+        };
+        let values = [
+            U256::ZERO,
+            U256_1,
+            U256::from(3),
+            Q128 - U256_1,
+            Q128,
+            U256::MAX / U256::from(3),
+            U256::MAX - U256_1,
+            U256::MAX,
+        ];
+
+        for a in values {
+            for b in values {
+                //the product is exact
+                let product = mul_512(a, b);
+                assert_eq!(
+                    to_u512(product),
+                    alloy_primitives::U512::from(a) * alloy_primitives::U512::from(b)
+                );
+
+                //division and modulo agree with mul_div and mulmod
+                for denominator in values {
+                    assert_eq!(
+                        product.div_u256(denominator).ok(),
+                        mul_div(a, b, denominator).ok()
+                    );
+                    assert_eq!(
+                        product.div_u256_rounding_up(denominator).ok(),
+                        mul_div_rounding_up(a, b, denominator).ok()
+                    );
+                    if !denominator.is_zero() {
+                        assert_eq!(
+                            product.rem_u256(denominator).unwrap(),
+                            a.mul_mod(b, denominator)
+                        );
+                    }
+                }
+            }
+        }
+
+        //sums carry into the high bits and fail on overflow
+        let max = Uint512::from(U256::MAX);
+        let sum = max.checked_add(max).unwrap();
+        assert_eq!(sum, Uint512::new(U256_1, U256::MAX - U256_1));
+        assert_eq!(sum.checked_sub(max), Some(max));
+        assert_eq!(Uint512::ZERO.checked_sub(Uint512::from(U256_1)), None);
+        assert_eq!(
+            Uint512::new(U256::MAX, U256::MAX).checked_add(Uint512::from(U256_1)),
+            None
+        );
+        assert!(sum > max);
+
+        //a quotient that does not fit in 256 bits fails
+        assert_eq!(
+            sum.div_u256(U256_1).unwrap_err().to_string(),
+            "Denominator is less than or equal to prod_1"
+        );
+        assert_eq!(sum.div_u256(U256::from(2)).unwrap(), U256::MAX);
+        assert_eq!(sum.rem_u256(U256::from(2)).unwrap(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_sqrt_rounding_up() {
+        assert_eq!(sqrt_rounding_up(U256::ZERO), U256::ZERO);
+        assert_eq!(sqrt_rounding_up(U256::from(4)), U256::from(2));
+        assert_eq!(sqrt_rounding_up(U256::from(5)), U256::from(3));
+        assert_eq!(sqrt_rounding_up(U256::from(99)), U256::from(10));
+        assert_eq!(
+            sqrt_rounding_up(Q128.sub(U256_1)),
+            U256::from(u64::MAX) + U256_1
+        );
+        assert_eq!(sqrt_rounding_up(U256::MAX), U256_1 << 128);
+    }
+
+    #[test]
+    fn test_log2() {
+        //fails if x is 0
+        assert!(matches!(
+            log2(U256::ZERO),
+            Err(UniswapV3MathError::ZeroValue)
+        ));
+
+        //powers of two are exact
+        for bit in [0, 1, 64, 127, 128, 255] {
+            assert_eq!(log2(U256_1 << bit).unwrap(), U256::from(bit) << 64);
+        }
+
+        //rounds down and is within 2**-63 of the exact value
+        for (x, expected) in [
+            (U256::from(3), 1.584962500721156),
+            (U256::from(10).pow(U256::from(18)), 59.79470570797252),
+            (U256::MAX, 256.0),
+        ] {
+            let log_2 = log2(x).unwrap();
+            let approx = log_2.to::<u128>() as f64 / 2f64.powi(64);
+            assert!((approx - expected).abs() < 1e-12);
+        }
+        assert!(log2(U256::MAX).unwrap() < U256::from(256) << 64);
+        assert!(log2(U256::MAX).unwrap() >= (U256::from(256) << 64) - U256::from(2));
+    }
+
+    #[test]
+    fn test_log_1_0001() {
+        assert_eq!(log_1_0001(U256_1).unwrap(), U256::ZERO);
+        assert!(matches!(
+            log_1_0001(U256::ZERO),
+            Err(UniswapV3MathError::ZeroValue)
+        ));
+
+        //the tick of a price of 2 is 6931.8183...
+        let tick_x_64 = log_1_0001(U256::from(2)).unwrap();
+        assert_eq!(tick_x_64 >> 64, U256::from(6931));
+        let approx = tick_x_64.to::<u128>() as f64 / 2f64.powi(64);
+        assert!((approx - 2f64.ln() / 1.0001f64.ln()).abs() < 1e-9);
+    }
 }
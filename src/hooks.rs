@@ -0,0 +1,123 @@
+use std::ops::ControlFlow;
+
+use crate::pool::SwapStep;
+
+//Callbacks invoked while a swap is simulated, see `PoolSnapshot::quote_with_hooks`. The implementor carries its own
+//state, which it can update from every callback. Returning `ControlFlow::Break` stops the swap after the current
+//step, as if the price limit had been the price at the end of the step
+pub trait SwapHooks {
+    // called after every step of the swap
+    fn on_step(&mut self, _step: &SwapStep) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+
+    // called after `on_step` for steps that end by crossing an initialized tick
+    fn on_tick_cross(&mut self, _tick: i32, _step: &SwapStep) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+// no hooks
+impl SwapHooks for () {}
+
+// a closure is called on every step
+impl<F: FnMut(&SwapStep) -> ControlFlow<()>> SwapHooks for F {
+    fn on_step(&mut self, step: &SwapStep) -> ControlFlow<()> {
+        self(step)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::ControlFlow;
+
+    use alloy_primitives::{I256, U256};
+
+    use super::SwapHooks;
+    use crate::{
+        fee_tier::FeeTier,
+        pool::{PoolSnapshot, SwapFill, SwapResult, SwapStep},
+        tick_math::{get_sqrt_ratio_at_tick, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    // stops the swap once the price moves below a limit order range
+    struct StopBelowRange {
+        tick_lower: i32,
+        crossed: Vec<i32>,
+    }
+
+    impl SwapHooks for StopBelowRange {
+        fn on_tick_cross(&mut self, tick: i32, _step: &SwapStep) -> ControlFlow<()> {
+            self.crossed.push(tick);
+            if tick == self.tick_lower {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    fn pool() -> PoolSnapshot {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-1200, -600, 1e20 as i128).unwrap();
+        pool.modify_position(-6000, 6000, 1e20 as i128).unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_swap_hooks() {
+        let mut pool = pool();
+        let amount = I256::from_raw(U256::from(1e21 as u128));
+        let limit = MIN_SQRT_RATIO + U256_1;
+
+        //stopping at a tick cross is the same as a price limit at the tick
+        let mut hooks = StopBelowRange {
+            tick_lower: -1200,
+            crossed: vec![],
+        };
+        let result = pool
+            .quote_with_hooks(true, amount, limit, &mut hooks)
+            .unwrap();
+        assert_eq!(hooks.crossed, vec![-600, -1200]);
+        let expected = pool
+            .quote(true, amount, get_sqrt_ratio_at_tick(-1200).unwrap())
+            .unwrap();
+        assert_eq!(
+            (result.fill, expected.fill),
+            (SwapFill::Stopped, SwapFill::PriceLimit)
+        );
+        assert_eq!(
+            SwapResult {
+                fill: SwapFill::PriceLimit,
+                ..result
+            },
+            expected
+        );
+        assert!(result.amount_0 < amount);
+
+        //swapping commits the state up to the stop
+        hooks.crossed.clear();
+        assert_eq!(
+            pool.swap_with_hooks(true, amount, limit, &mut hooks)
+                .unwrap(),
+            result
+        );
+        assert_eq!(pool.slot0.tick, -1201);
+        assert_eq!(pool.liquidity, expected.liquidity);
+
+        //closures are called on every step
+        let pool = self::pool();
+        let mut steps = 0;
+        let result = pool
+            .quote_with_hooks(true, amount, limit, &mut |_: &SwapStep| {
+                steps += 1;
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(result, pool.quote(true, amount, limit).unwrap());
+        assert_eq!(steps, pool.swap_steps(true, amount, limit).unwrap().count());
+    }
+}
@@ -0,0 +1,399 @@
+use alloy_primitives::{I256, U256};
+use thiserror::Error;
+
+use crate::{
+    error::UniswapV3MathError,
+    pool::SwapStep,
+    sqrt_price_math::{_get_amount_0_delta, _get_amount_1_delta},
+    storage::{InitializedTicks, TickStorage},
+    tick_math::{get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+};
+
+// checks for the invariants a consistent pool upholds. With the `debug-invariants` feature, swaps and position
+// updates run these checks as they go and fail with `UniswapV3MathError::InvariantViolation` instead of silently
+// computing on broken state, which is useful when ingesting state from untrusted sources. The checks only look at the
+// state that is touched, a full scan of the storage is not possible for every storage layout
+
+//A violated pool invariant along with the values that violate it
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    #[error("sqrt price {sqrt_price_x_96} is outside of [MIN_SQRT_RATIO, MAX_SQRT_RATIO)")]
+    SqrtPriceOutOfBounds { sqrt_price_x_96: U256 },
+    #[error("tick {tick} does not match sqrt price {sqrt_price_x_96}")]
+    TickPriceMismatch { tick: i32, sqrt_price_x_96: U256 },
+    #[error("crossing tick {tick} with liquidity net {liquidity_net} makes liquidity {liquidity} negative")]
+    NegativeLiquidity {
+        tick: i32,
+        liquidity: u128,
+        liquidity_net: i128,
+    },
+    #[error("liquidity net {liquidity_net} of tick {tick} exceeds its liquidity gross {liquidity_gross}")]
+    LiquidityNetExceedsGross {
+        tick: i32,
+        liquidity_gross: u128,
+        liquidity_net: i128,
+    },
+    #[error("tick {tick} is {} in the tick bitmap but {} in the tick map", initialized(*.in_bitmap), initialized(*.in_ticks))]
+    TickBitmapMismatch {
+        tick: i32,
+        in_bitmap: bool,
+        in_ticks: bool,
+    },
+    #[error("swap step amounts {amount_in} in and {amount_out} out are off the curve, expected at least {min_amount_in} in and at most {max_amount_out} out")]
+    AmountNotConserved {
+        amount_in: U256,
+        amount_out: U256,
+        min_amount_in: U256,
+        max_amount_out: U256,
+    },
+    #[error("initialized tick {tick} is outside of [MIN_TICK, MAX_TICK]")]
+    TickOutOfRange { tick: i64 },
+    #[error("the liquidity net of all ticks sums to {sum} instead of 0")]
+    LiquidityNetNotBalanced { sum: I256 },
+    #[error("the in range liquidity is {liquidity} but the liquidity net of the ticks up to the current tick sums to {expected}")]
+    LiquidityMismatch { liquidity: u128, expected: I256 },
+    #[error("swap amounts {amount_in} in and {amount_out} out do not match the {steps_amount_in} in and {steps_amount_out} out of its steps")]
+    SwapAmountMismatch {
+        amount_in: U256,
+        amount_out: U256,
+        steps_amount_in: U256,
+        steps_amount_out: U256,
+    },
+    #[error("swap used more than the amount specified {amount_specified}, {amount_specified_remaining} remaining")]
+    AmountSpecifiedExceeded {
+        amount_specified: I256,
+        amount_specified_remaining: I256,
+    },
+}
+
+impl From<InvariantViolation> for UniswapV3MathError {
+    fn from(violation: InvariantViolation) -> Self {
+        Self::InvariantViolation(Box::new(violation))
+    }
+}
+
+// checks that the price is within bounds and that the tick is the tick of the price. After crossing a tick from
+// right to left the pool sits exactly on the crossed tick's price with the tick below it, like the contract
+pub fn check_price(sqrt_price_x_96: U256, tick: i32) -> Result<(), UniswapV3MathError> {
+    if !(MIN_SQRT_RATIO..MAX_SQRT_RATIO).contains(&sqrt_price_x_96) {
+        return Err(InvariantViolation::SqrtPriceOutOfBounds { sqrt_price_x_96 }.into());
+    }
+
+    let price_tick = get_tick_at_sqrt_ratio(sqrt_price_x_96)?;
+    if tick == price_tick
+        || (tick == price_tick - 1 && get_sqrt_ratio_at_tick(price_tick)? == sqrt_price_x_96)
+    {
+        Ok(())
+    } else {
+        Err(InvariantViolation::TickPriceMismatch {
+            tick,
+            sqrt_price_x_96,
+        }
+        .into())
+    }
+}
+
+// checks that crossing a tick does not take more liquidity out of range than is in range
+pub fn check_cross(
+    tick: i32,
+    liquidity: u128,
+    liquidity_net: i128,
+) -> Result<(), UniswapV3MathError> {
+    if liquidity_net < 0 && liquidity_net.unsigned_abs() > liquidity {
+        return Err(InvariantViolation::NegativeLiquidity {
+            tick,
+            liquidity,
+            liquidity_net,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+// checks that the tick bitmap and the tick map agree on whether `tick` is initialized, and that the tick does not
+// add or remove more liquidity than references it. Storages without the gross liquidity are checked by the initialized
+// flag instead
+pub fn check_tick<T: TickStorage>(
+    ticks: &T,
+    tick_bitmap: &impl InitializedTicks,
+    tick: i32,
+    tick_spacing: i32,
+) -> Result<(), UniswapV3MathError> {
+    let info = ticks.get(&tick).unwrap_or_default();
+    if !T::LIQUIDITY_GROSS {
+        let in_bitmap =
+            tick_bitmap.next_initialized_tick_within_one_word(tick, tick_spacing, true)?
+                == (tick, true);
+        if in_bitmap != info.initialized {
+            return Err(InvariantViolation::TickBitmapMismatch {
+                tick,
+                in_bitmap,
+                in_ticks: info.initialized,
+            }
+            .into());
+        }
+        return Ok(());
+    }
+
+    let in_ticks = info.liquidity_gross > 0;
+    let in_bitmap = tick_bitmap.next_initialized_tick_within_one_word(tick, tick_spacing, true)?
+        == (tick, true);
+
+    if in_bitmap != in_ticks {
+        return Err(InvariantViolation::TickBitmapMismatch {
+            tick,
+            in_bitmap,
+            in_ticks,
+        }
+        .into());
+    }
+    if info.liquidity_net.unsigned_abs() > info.liquidity_gross {
+        return Err(InvariantViolation::LiquidityNetExceedsGross {
+            tick,
+            liquidity_gross: info.liquidity_gross,
+            liquidity_net: info.liquidity_net,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+// checks that a swap step received at least and paid at most what moving the price along the curve is worth, i.e.
+// that every rounding went in favor of the pool
+pub fn check_swap_step(step: &SwapStep, zero_for_one: bool) -> Result<(), UniswapV3MathError> {
+    let (min_amount_in, max_amount_out) = swap_step_bounds(step, zero_for_one)?;
+    if step.amount_in < min_amount_in || step.amount_out > max_amount_out {
+        return Err(InvariantViolation::AmountNotConserved {
+            amount_in: step.amount_in,
+            amount_out: step.amount_out,
+            min_amount_in,
+            max_amount_out,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+// returns the input rounded down and the output rounded up of moving the price of a step along the curve, a step must
+// receive at least the former and pay at most the latter
+pub(crate) fn swap_step_bounds(
+    step: &SwapStep,
+    zero_for_one: bool,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    let (before, after) = (step.sqrt_price_before_x_96, step.sqrt_price_after_x_96);
+    Ok(if zero_for_one {
+        (
+            _get_amount_0_delta(after, before, step.liquidity, false)?,
+            _get_amount_1_delta(after, before, step.liquidity, true)?,
+        )
+    } else {
+        (
+            _get_amount_1_delta(before, after, step.liquidity, false)?,
+            _get_amount_0_delta(before, after, step.liquidity, true)?,
+        )
+    })
+}
+
+// checks that a swap never consumes more input or pays out more output than specified, i.e. that the remaining
+// amount never changes sign
+pub fn check_amount_remaining(
+    amount_specified: I256,
+    amount_specified_remaining: I256,
+) -> Result<(), UniswapV3MathError> {
+    if !amount_specified_remaining.is_zero()
+        && amount_specified_remaining.is_negative() != amount_specified.is_negative()
+    {
+        return Err(InvariantViolation::AmountSpecifiedExceeded {
+            amount_specified,
+            amount_specified_remaining,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+fn initialized(initialized: bool) -> &'static str {
+    if initialized {
+        "initialized"
+    } else {
+        "not initialized"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        check_amount_remaining, check_cross, check_price, check_swap_step, check_tick,
+        InvariantViolation,
+    };
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        pool::PoolSnapshot,
+        tick::Tick,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    };
+
+    fn violation(result: Result<(), UniswapV3MathError>) -> InvariantViolation {
+        match result {
+            Err(UniswapV3MathError::InvariantViolation(violation)) => *violation,
+            other => panic!("expected an invariant violation, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_check_price() {
+        let sqrt_price_x_96 = get_sqrt_ratio_at_tick(60).unwrap();
+        check_price(sqrt_price_x_96, 60).unwrap();
+        //sitting on a tick after crossing it from right to left
+        check_price(sqrt_price_x_96, 59).unwrap();
+        check_price(sqrt_price_x_96 + U256::from(1), 60).unwrap();
+
+        //fails if the tick does not match the price
+        assert_eq!(
+            violation(check_price(sqrt_price_x_96 + U256::from(1), 59)),
+            InvariantViolation::TickPriceMismatch {
+                tick: 59,
+                sqrt_price_x_96: sqrt_price_x_96 + U256::from(1)
+            }
+        );
+        assert!(matches!(
+            violation(check_price(sqrt_price_x_96, 61)),
+            InvariantViolation::TickPriceMismatch { .. }
+        ));
+
+        //fails if the price is out of bounds
+        check_price(MIN_SQRT_RATIO, -887272).unwrap();
+        assert!(matches!(
+            violation(check_price(MAX_SQRT_RATIO, 887272)),
+            InvariantViolation::SqrtPriceOutOfBounds { .. }
+        ));
+    }
+
+    #[test]
+    fn test_check_tick() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1000).unwrap();
+        check_tick(&pool.ticks, &pool.tick_bitmap, -600, 60).unwrap();
+        check_tick(&pool.ticks, &pool.tick_bitmap, 600, 60).unwrap();
+        check_tick(&pool.ticks, &pool.tick_bitmap, 60, 60).unwrap();
+
+        //fails if the tick map and the bitmap disagree
+        let mut ticks = pool.ticks.clone();
+        ticks.remove(&600);
+        assert_eq!(
+            violation(check_tick(&ticks, &pool.tick_bitmap, 600, 60)),
+            InvariantViolation::TickBitmapMismatch {
+                tick: 600,
+                in_bitmap: true,
+                in_ticks: false
+            }
+        );
+        assert_eq!(
+            violation(check_tick(&pool.ticks, &HashMap::new(), 600, 60)),
+            InvariantViolation::TickBitmapMismatch {
+                tick: 600,
+                in_bitmap: false,
+                in_ticks: true
+            }
+        );
+
+        //fails if the liquidity net exceeds the liquidity gross
+        ticks.insert(
+            -600,
+            Tick {
+                liquidity_gross: 1000,
+                liquidity_net: 1001,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            violation(check_tick(&ticks, &pool.tick_bitmap, -600, 60)),
+            InvariantViolation::LiquidityNetExceedsGross { .. }
+        ));
+    }
+
+    #[test]
+    fn test_check_swap() {
+        check_cross(60, 1000, -1000).unwrap();
+        check_cross(60, 1000, 5000).unwrap();
+        //fails if crossing makes liquidity negative
+        assert_eq!(
+            violation(check_cross(60, 1000, -1001)),
+            InvariantViolation::NegativeLiquidity {
+                tick: 60,
+                liquidity: 1000,
+                liquidity_net: -1001
+            }
+        );
+
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        let amount_specified = I256::from_raw(U256::from(1e18 as u128));
+        for zero_for_one in [true, false] {
+            let limit = if zero_for_one {
+                MIN_SQRT_RATIO + U256::from(1)
+            } else {
+                MAX_SQRT_RATIO - U256::from(1)
+            };
+            for step in pool
+                .swap_steps(zero_for_one, amount_specified, limit)
+                .unwrap()
+            {
+                let mut step = step.unwrap();
+                check_swap_step(&step, zero_for_one).unwrap();
+
+                //fails if the pool pays out more than the curve allows, beyond rounding
+                step.amount_out += U256::from(2);
+                assert!(matches!(
+                    violation(check_swap_step(&step, zero_for_one)),
+                    InvariantViolation::AmountNotConserved { .. }
+                ));
+            }
+        }
+
+        check_amount_remaining(amount_specified, I256::ZERO).unwrap();
+        check_amount_remaining(-amount_specified, -I256::ONE).unwrap();
+        //fails if the swap used more than specified
+        assert!(matches!(
+            violation(check_amount_remaining(amount_specified, -I256::ONE)),
+            InvariantViolation::AmountSpecifiedExceeded { .. }
+        ));
+    }
+
+    #[cfg(feature = "debug-invariants")]
+    #[test]
+    fn test_swap_on_broken_state() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+
+        //the lower tick is missing from the tick map but still set in the bitmap
+        pool.ticks.remove(&-600);
+        let result = pool
+            .quote(
+                true,
+                I256::from_raw(U256::from(1e20 as u128)),
+                MIN_SQRT_RATIO + U256::from(1),
+            )
+            .map(|_| ());
+        assert_eq!(
+            violation(result),
+            InvariantViolation::TickBitmapMismatch {
+                tick: -600,
+                in_bitmap: true,
+                in_ticks: false
+            }
+        );
+    }
+}
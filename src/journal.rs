@@ -0,0 +1,530 @@
+use std::collections::VecDeque;
+
+use alloy_primitives::{B256, I256, U256};
+
+use crate::{
+    backtest::{Event, EventTarget},
+    error::UniswapV3MathError,
+    pool::{swap_result, PoolSnapshot, ProtocolFees, Slot0, SwapParams, SwapResult},
+    tick::Tick,
+    tick_bitmap::position,
+};
+
+// identifies a point in the journal that the pool can be reverted to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotId(usize);
+
+// the previous value of a piece of pool state, recorded before it is overwritten
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum JournalEntry {
+    Globals {
+        slot0: Slot0,
+        liquidity: u128,
+        fee_growth_global_0_x_128: U256,
+        fee_growth_global_1_x_128: U256,
+        protocol_fees: ProtocolFees,
+    },
+    Tick(i32, Option<Tick>),
+    TickBitmapWord(i16, Option<U256>),
+}
+
+//A pool that records the previous value of every tick, bitmap word and global it mutates, so it can be reverted to
+//any earlier snapshot without cloning the tick map. Branching a simulation costs a snapshot id, and reverting it costs
+//the number of values written since the snapshot
+#[derive(Clone, Debug)]
+pub struct JournaledPool {
+    pool: PoolSnapshot,
+    journal: Vec<JournalEntry>,
+}
+
+impl JournaledPool {
+    pub fn new(pool: PoolSnapshot) -> Self {
+        Self {
+            pool,
+            journal: vec![],
+        }
+    }
+
+    pub fn pool(&self) -> &PoolSnapshot {
+        &self.pool
+    }
+
+    // returns the pool state, dropping the journal
+    pub fn into_inner(self) -> PoolSnapshot {
+        self.pool
+    }
+
+    // returns an id that `revert` can restore the current state from
+    pub fn snapshot(&self) -> SnapshotId {
+        SnapshotId(self.journal.len())
+    }
+
+    // restores the state at the time `id` was taken. Snapshots taken after `id` are invalidated, as are all snapshots
+    // once `commit` is called. Returns false and leaves the state untouched if `id` is no longer valid
+    pub fn revert(&mut self, id: SnapshotId) -> bool {
+        if id.0 > self.journal.len() {
+            return false;
+        }
+
+        let undone = self.journal.split_off(id.0);
+        for entry in undone.into_iter().rev() {
+            match entry {
+                JournalEntry::Globals {
+                    slot0,
+                    liquidity,
+                    fee_growth_global_0_x_128,
+                    fee_growth_global_1_x_128,
+                    protocol_fees,
+                } => {
+                    self.pool.slot0 = slot0;
+                    self.pool.liquidity = liquidity;
+                    self.pool.fee_growth_global_0_x_128 = fee_growth_global_0_x_128;
+                    self.pool.fee_growth_global_1_x_128 = fee_growth_global_1_x_128;
+                    self.pool.protocol_fees = protocol_fees;
+                }
+                JournalEntry::Tick(tick, Some(info)) => {
+                    self.pool.ticks.insert(tick, info);
+                }
+                JournalEntry::Tick(tick, None) => {
+                    self.pool.ticks.remove(&tick);
+                }
+                JournalEntry::TickBitmapWord(word_pos, Some(word)) => {
+                    self.pool.tick_bitmap.insert(word_pos, word);
+                }
+                JournalEntry::TickBitmapWord(word_pos, None) => {
+                    self.pool.tick_bitmap.remove(&word_pos);
+                }
+            }
+        }
+
+        true
+    }
+
+    // keeps the current state and drops the journal, invalidating all snapshots
+    pub fn commit(&mut self) {
+        self.journal.clear();
+    }
+
+    // see `PoolSnapshot::quote`
+    pub fn quote(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: U256,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        self.pool
+            .quote(zero_for_one, amount_specified, sqrt_price_limit_x_96)
+    }
+
+    // see `PoolSnapshot::swap`, the previous globals and crossed ticks are journaled
+    pub fn swap(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: U256,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let state =
+            self.pool
+                .compute_swap(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+
+        self.journal_globals();
+        for &(tick, _) in &state.crossed_ticks {
+            self.journal_tick(tick);
+        }
+        self.pool.commit_swap(zero_for_one, &state);
+
+        Ok(swap_result(zero_for_one, amount_specified, &state))
+    }
+
+    // see `PoolSnapshot::swap_with`, journaled like `swap`
+    pub fn swap_with(&mut self, params: &SwapParams) -> Result<SwapResult, UniswapV3MathError> {
+        let state = self.pool.compute_swap_with(params, &mut ())?;
+
+        self.journal_globals();
+        for &(tick, _) in &state.crossed_ticks {
+            self.journal_tick(tick);
+        }
+        self.pool.commit_swap(params.zero_for_one, &state);
+
+        Ok(swap_result(
+            params.zero_for_one,
+            params.amount_specified,
+            &state,
+        ))
+    }
+
+    // see `PoolSnapshot::modify_position`, the previous globals, range ticks and their bitmap words are journaled. A
+    // failed update leaves the pool untouched, so nothing is journaled for it
+    pub fn modify_position(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> Result<(I256, I256), UniswapV3MathError> {
+        let snapshot = self.snapshot();
+        let tick_spacing = self.pool.fee_tier.tick_spacing();
+
+        self.journal_globals();
+        for tick in [tick_lower, tick_upper] {
+            self.journal_tick(tick);
+            self.journal_tick_bitmap_word(position(tick / tick_spacing).0);
+        }
+
+        let result = self
+            .pool
+            .modify_position(tick_lower, tick_upper, liquidity_delta);
+        if result.is_err() {
+            self.journal.truncate(snapshot.0);
+        }
+
+        result
+    }
+
+    fn journal_globals(&mut self) {
+        self.journal.push(JournalEntry::Globals {
+            slot0: self.pool.slot0,
+            liquidity: self.pool.liquidity,
+            fee_growth_global_0_x_128: self.pool.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: self.pool.fee_growth_global_1_x_128,
+            protocol_fees: self.pool.protocol_fees,
+        });
+    }
+
+    fn journal_tick(&mut self, tick: i32) {
+        let info = self.pool.ticks.get(&tick).copied();
+        self.journal.push(JournalEntry::Tick(tick, info));
+    }
+
+    fn journal_tick_bitmap_word(&mut self, word_pos: i16) {
+        let word = self.pool.tick_bitmap.get(&word_pos).copied();
+        self.journal
+            .push(JournalEntry::TickBitmapWord(word_pos, word));
+    }
+}
+
+// events applied to a journaled pool are journaled like its own updates
+impl EventTarget for JournaledPool {
+    fn apply_swap(&mut self, params: &SwapParams) -> Result<(), UniswapV3MathError> {
+        self.swap_with(params).map(drop)
+    }
+
+    fn apply_liquidity(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> Result<(), UniswapV3MathError> {
+        self.modify_position(tick_lower, tick_upper, liquidity_delta)
+            .map(drop)
+    }
+}
+
+// a block applied to a `ChainPool`, with the journal entries that undo its events
+#[derive(Clone, Debug)]
+struct JournaledBlock {
+    number: u64,
+    hash: B256,
+    journal: Vec<JournalEntry>,
+}
+
+//A pool tracked at the chain head. Events are applied a block at a time and the undo log of the last `max_depth`
+//blocks is kept, so a reorg unwinds the orphaned blocks and the canonical ones are applied on top of the common
+//ancestor, instead of fetching a new snapshot. Unwinding a block costs the number of values its events wrote
+#[derive(Clone, Debug)]
+pub struct ChainPool {
+    pool: JournaledPool,
+    // the blocks that can be unwound, oldest first
+    blocks: VecDeque<JournaledBlock>,
+    // the block of the state before the oldest block that can be unwound
+    base: (u64, B256),
+    max_depth: usize,
+}
+
+impl ChainPool {
+    // tracks `pool` as of the block `number` with hash `hash`, keeping the undo log of up to `max_depth` blocks
+    pub fn new(pool: PoolSnapshot, number: u64, hash: B256, max_depth: usize) -> Self {
+        Self {
+            pool: JournaledPool::new(pool),
+            blocks: VecDeque::new(),
+            base: (number, hash),
+            max_depth,
+        }
+    }
+
+    pub fn pool(&self) -> &PoolSnapshot {
+        self.pool.pool()
+    }
+
+    // returns the number and hash of the block of the current state
+    pub fn head(&self) -> (u64, B256) {
+        self.blocks
+            .back()
+            .map_or(self.base, |block| (block.number, block.hash))
+    }
+
+    // returns the hash of an applied block that can still be unwound to, e.g. to find the common ancestor of a reorg
+    // by comparing it with the canonical hash. `None` if the block was not applied or is older than the undo log
+    pub fn block_hash(&self, number: u64) -> Option<B256> {
+        if number == self.base.0 {
+            return Some(self.base.1);
+        }
+
+        self.blocks
+            .binary_search_by_key(&number, |block| block.number)
+            .ok()
+            .map(|index| self.blocks[index].hash)
+    }
+
+    // applies the events of a block in order. Blocks without events for the pool may be skipped, but the number must
+    // be above the head. If an event fails the state is left as it was before the block
+    pub fn apply_block(
+        &mut self,
+        number: u64,
+        hash: B256,
+        events: &[Event],
+    ) -> Result<(), UniswapV3MathError> {
+        if number <= self.head().0 {
+            return Err(UniswapV3MathError::BlockOutOfOrder);
+        }
+
+        let start = self.pool.snapshot();
+        for event in events {
+            if let Err(err) = event.apply_to(&mut self.pool) {
+                self.pool.revert(start);
+                return Err(err);
+            }
+        }
+
+        let journal = std::mem::take(&mut self.pool.journal);
+        self.blocks.push_back(JournaledBlock {
+            number,
+            hash,
+            journal,
+        });
+        while self.blocks.len() > self.max_depth {
+            if let Some(block) = self.blocks.pop_front() {
+                self.base = (block.number, block.hash);
+            }
+        }
+
+        Ok(())
+    }
+
+    // unwinds the blocks above `number`, so the state is the state as of `number` and the canonical blocks after it can
+    // be applied. Fails with `ReorgTooDeep` and leaves the state untouched if `number` is older than the undo log
+    pub fn unwind_to(&mut self, number: u64) -> Result<(), UniswapV3MathError> {
+        if number < self.base.0 {
+            return Err(UniswapV3MathError::ReorgTooDeep);
+        }
+
+        while self
+            .blocks
+            .back()
+            .is_some_and(|block| block.number > number)
+        {
+            if let Some(block) = self.blocks.pop_back() {
+                self.pool.journal = block.journal;
+                self.pool.revert(SnapshotId(0));
+            }
+        }
+
+        Ok(())
+    }
+
+    // unwinds the last `blocks` applied blocks, see `unwind_to`
+    pub fn unwind(&mut self, blocks: usize) -> Result<(), UniswapV3MathError> {
+        if blocks > self.blocks.len() {
+            return Err(UniswapV3MathError::ReorgTooDeep);
+        }
+
+        let number = match self.blocks.len() - blocks {
+            0 => self.base.0,
+            kept => self.blocks[kept - 1].number,
+        };
+        self.unwind_to(number)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{B256, I256, U256};
+
+    use super::{ChainPool, JournaledPool};
+    use crate::{
+        backtest::Event,
+        error::UniswapV3MathError,
+        pool::{PoolSnapshot, SwapParams},
+        test_utils::swap,
+        test_utils::PoolBuilder,
+        tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    };
+
+    fn pool_at(tick_current: i32) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .full_range(1e20 as i128)
+            .position(-600, 600, 1e21 as i128)
+            .build()
+    }
+
+    #[test]
+    fn test_revert() {
+        let original = pool_at(0);
+        let mut pool = JournaledPool::new(original.clone());
+        let start = pool.snapshot();
+
+        //swapping across ticks and minting new ranges is undone by a revert
+        pool.swap(
+            true,
+            I256::from_raw(U256::from(1e21 as u128)),
+            MIN_SQRT_RATIO + U256::from(1),
+        )
+        .unwrap();
+        let after_swap = pool.pool().clone();
+        let branch = pool.snapshot();
+        pool.modify_position(-1200, -60, 1e19 as i128).unwrap();
+        pool.swap(
+            false,
+            I256::from_raw(U256::from(1e21 as u128)),
+            MAX_SQRT_RATIO - U256::from(1),
+        )
+        .unwrap();
+        assert_ne!(pool.pool(), &after_swap);
+
+        //the same swap from a fork point gives the same result every time
+        assert!(pool.revert(branch));
+        assert_eq!(pool.pool(), &after_swap);
+        let first = pool
+            .swap(
+                false,
+                I256::from_raw(U256::from(1e18 as u128)),
+                MAX_SQRT_RATIO - U256::from(1),
+            )
+            .unwrap();
+        assert!(pool.revert(branch));
+        let second = pool
+            .swap(
+                false,
+                I256::from_raw(U256::from(1e18 as u128)),
+                MAX_SQRT_RATIO - U256::from(1),
+            )
+            .unwrap();
+        assert_eq!(first, second);
+
+        assert!(pool.revert(start));
+        assert_eq!(pool.pool(), &original);
+
+        //snapshots taken after the reverted point are no longer valid
+        assert!(!pool.revert(branch));
+        assert_eq!(pool.pool(), &original);
+    }
+
+    #[test]
+    fn test_modify_position_failure_is_reverted() {
+        let original = pool_at(0);
+        let mut pool = JournaledPool::new(original.clone());
+
+        //fails if the burn exceeds the liquidity of the upper tick, without journaling anything
+        let id = pool.snapshot();
+        assert!(pool.modify_position(-600, 1200, -(1e21 as i128)).is_err());
+        assert_eq!(pool.pool(), &original);
+        assert_eq!(pool.snapshot(), id);
+
+        //committing keeps the state and drops the journal
+        pool.modify_position(-600, 600, -(1e21 as i128)).unwrap();
+        let id = pool.snapshot();
+        pool.commit();
+        assert!(!pool.revert(id));
+        assert_ne!(pool.pool(), &original);
+    }
+
+    #[test]
+    fn test_chain_pool_reorg() {
+        let original = pool_at(0);
+        let mut chain = ChainPool::new(original.clone(), 100, B256::with_last_byte(100), 3);
+        let blocks = [
+            vec![swap(true, 1e21 as u128)],
+            vec![Event::Mint {
+                tick_lower: -1200,
+                tick_upper: -60,
+                liquidity: 1e19 as u128,
+            }],
+            vec![swap(false, 2e21 as u128), swap(true, 1e18 as u128)],
+        ];
+        let mut states = vec![original.clone()];
+        for (number, events) in (101..).zip(&blocks) {
+            chain
+                .apply_block(number, B256::with_last_byte(number as u8), events)
+                .unwrap();
+            states.push(chain.pool().clone());
+        }
+        assert_eq!(chain.head(), (103, B256::with_last_byte(103)));
+        assert_eq!(chain.block_hash(101), Some(B256::with_last_byte(101)));
+
+        //fails if the block is not above the head
+        assert!(matches!(
+            chain.apply_block(103, B256::ZERO, &[]),
+            Err(UniswapV3MathError::BlockOutOfOrder)
+        ));
+
+        //unwinding restores the state as of each block
+        chain.unwind(1).unwrap();
+        assert_eq!(chain.pool(), &states[2]);
+        chain.unwind_to(101).unwrap();
+        assert_eq!(chain.pool(), &states[1]);
+        assert_eq!(chain.block_hash(102), None);
+
+        //the canonical blocks are applied on top of the common ancestor like on a fresh pool
+        let canonical = [vec![swap(false, 5e20 as u128)], vec![]];
+        chain.apply_block(102, B256::ZERO, &canonical[0]).unwrap();
+        chain.apply_block(104, B256::ZERO, &canonical[1]).unwrap();
+        let mut expected = states[1].clone();
+        expected
+            .swap_with(
+                &SwapParams::builder()
+                    .zero_for_one(false)
+                    .amount_in(U256::from(5e20 as u128))
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(chain.pool(), &expected);
+        chain.unwind(3).unwrap();
+        assert_eq!(chain.pool(), &original);
+
+        //a failed event leaves the state as it was before the block
+        assert!(chain
+            .apply_block(
+                101,
+                B256::ZERO,
+                &[
+                    swap(true, 1e18 as u128),
+                    Event::Burn {
+                        tick_lower: -600,
+                        tick_upper: 600,
+                        liquidity: 2e21 as u128,
+                    },
+                ],
+            )
+            .is_err());
+        assert_eq!(chain.pool(), &original);
+        assert_eq!(chain.head().0, 100);
+
+        //only the last `max_depth` blocks can be unwound
+        for number in 101..=104 {
+            chain
+                .apply_block(number, B256::ZERO, &[swap(number % 2 == 0, 1e18 as u128)])
+                .unwrap();
+        }
+        assert_eq!(chain.block_hash(100), None);
+        assert!(matches!(
+            chain.unwind(4),
+            Err(UniswapV3MathError::ReorgTooDeep)
+        ));
+        assert!(matches!(
+            chain.unwind_to(100),
+            Err(UniswapV3MathError::ReorgTooDeep)
+        ));
+        chain.unwind(3).unwrap();
+        assert_eq!(chain.head().0, 101);
+    }
+}
@@ -1,15 +1,79 @@
 use alloy_primitives::U256;
 
+pub mod algebra;
+pub mod analytics;
+pub mod approx;
+pub mod arbitrage;
+pub mod backtest;
 pub mod bit_math;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod chains;
+pub mod constants;
+pub mod decode;
+pub mod delta;
+pub mod display;
 pub mod error;
+pub mod export;
+#[cfg(feature = "arrow")]
+pub mod export_arrow;
+pub mod fee_tier;
+#[cfg(feature = "contract")]
+pub mod fetch;
 pub mod full_math;
+pub mod hooks;
+pub mod invariants;
+pub mod journal;
+pub mod limit_order;
+pub mod liquidity_amounts;
 pub mod liquidity_math;
+pub mod manipulation;
+pub mod migration;
+pub mod minimal_ticks;
+pub mod oracle;
+pub mod orientation;
+pub mod overflow;
+pub mod path;
+pub mod pool;
+pub mod pool_key;
+pub mod pool_storage;
+pub mod portfolio;
+pub mod position;
+pub mod position_manager;
+pub mod price;
+pub mod q_number;
+pub mod quote;
+pub mod quote_cache;
+#[cfg(feature = "big-rational")]
+pub mod rational;
+pub mod rebalance;
+pub mod replay;
+pub mod router;
+pub mod routing;
+pub mod sandwich;
+#[cfg(feature = "service")]
+pub mod service;
+pub mod solver;
 pub mod sqrt_price_math;
+pub mod staker_math;
+pub mod storage;
+#[cfg(feature = "subgraph")]
+pub mod subgraph;
 pub mod swap_math;
+#[cfg(test)]
+pub(crate) mod test_utils;
 pub mod tick;
+pub mod tick_array;
 pub mod tick_bitmap;
+pub mod tick_index;
 pub mod tick_math;
+pub mod tick_provider;
+pub mod transfer_fee;
+pub mod uint160;
 pub mod unsafe_math;
+pub mod v4;
+pub mod variant;
+pub mod verify;
 
 const U256_1: U256 = U256::from_limbs([1, 0, 0, 0]);
 const U256_2: U256 = U256::from_limbs([2, 0, 0, 0]);
@@ -18,26 +82,7 @@ const U256_4: U256 = U256::from_limbs([4, 0, 0, 0]);
 const U256_5: U256 = U256::from_limbs([5, 0, 0, 0]);
 const U256_6: U256 = U256::from_limbs([6, 0, 0, 0]);
 const U256_7: U256 = U256::from_limbs([7, 0, 0, 0]);
-const U256_8: U256 = U256::from_limbs([8, 0, 0, 0]);
 const U256_15: U256 = U256::from_limbs([15, 0, 0, 0]);
-const U256_16: U256 = U256::from_limbs([16, 0, 0, 0]);
-const U256_32: U256 = U256::from_limbs([32, 0, 0, 0]);
-const U256_64: U256 = U256::from_limbs([64, 0, 0, 0]);
 const U256_127: U256 = U256::from_limbs([127, 0, 0, 0]);
 const U256_128: U256 = U256::from_limbs([128, 0, 0, 0]);
 const U256_255: U256 = U256::from_limbs([255, 0, 0, 0]);
-
-const U256_256: U256 = U256::from_limbs([256, 0, 0, 0]);
-const U256_512: U256 = U256::from_limbs([512, 0, 0, 0]);
-const U256_1024: U256 = U256::from_limbs([1024, 0, 0, 0]);
-const U256_2048: U256 = U256::from_limbs([2048, 0, 0, 0]);
-const U256_4096: U256 = U256::from_limbs([4096, 0, 0, 0]);
-const U256_8192: U256 = U256::from_limbs([8192, 0, 0, 0]);
-const U256_16384: U256 = U256::from_limbs([16384, 0, 0, 0]);
-const U256_32768: U256 = U256::from_limbs([32768, 0, 0, 0]);
-const U256_65536: U256 = U256::from_limbs([65536, 0, 0, 0]);
-const U256_131072: U256 = U256::from_limbs([131072, 0, 0, 0]);
-const U256_262144: U256 = U256::from_limbs([262144, 0, 0, 0]);
-const U256_524288: U256 = U256::from_limbs([524288, 0, 0, 0]);
-
-const U256_MAX_TICK: U256 = U256::from_limbs([887272, 0, 0, 0]);
@@ -0,0 +1,218 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    full_math::mul_div,
+    pool::{PoolSnapshot, SwapResult},
+    position::amounts_at_price,
+    sqrt_price_math::Q96,
+    tick_math::{get_sqrt_ratio_at_tick, MAX_TICK, MIN_TICK},
+};
+
+//The state of a limit order after an incoming swap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LimitOrderFill {
+    // the token0/token1 amounts the order is worth after the swap, excluding fees earned
+    pub amount_0: U256,
+    pub amount_1: U256,
+    // the fraction of the order that has been converted into the other token as a Q96
+    pub filled_x_96: U256,
+    // the result of the incoming swap against the pool including the order
+    pub swap: SwapResult,
+}
+
+// returns the range one tick spacing wide that is closest to the current tick and entirely on the side of the price
+// that makes it a limit order, above the current price when selling token0 and below it when selling token1
+pub fn limit_order_range(
+    tick_current: i32,
+    tick_spacing: i32,
+    zero_for_one: bool,
+) -> Result<(i32, i32), UniswapV3MathError> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3MathError::TickSpacingError);
+    }
+
+    let compressed = tick_current.div_euclid(tick_spacing) * tick_spacing;
+    let (tick_lower, tick_upper) = if zero_for_one {
+        (compressed + tick_spacing, compressed + 2 * tick_spacing)
+    } else {
+        (compressed - tick_spacing, compressed)
+    };
+
+    if tick_lower < MIN_TICK {
+        return Err(UniswapV3MathError::TLM);
+    }
+    if tick_upper > MAX_TICK {
+        return Err(UniswapV3MathError::TUM);
+    }
+
+    Ok((tick_lower, tick_upper))
+}
+
+// simulates an incoming swap against the pool with a limit order of `liquidity` in the given range added to it and
+// returns how much of the order is filled. The order sells token0 if the range is above the current price and token1
+// if it is below, the range must not contain the current price
+pub fn simulate_fill(
+    pool: &PoolSnapshot,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+    zero_for_one: bool,
+    amount_specified: I256,
+    sqrt_price_limit_x_96: U256,
+) -> Result<LimitOrderFill, UniswapV3MathError> {
+    let sqrt_price_x_96 = pool.slot0.sqrt_price_x_96;
+    let sells_token_0 = sqrt_price_x_96 <= get_sqrt_ratio_at_tick(tick_lower)?;
+    if !sells_token_0 && sqrt_price_x_96 < get_sqrt_ratio_at_tick(tick_upper)? {
+        return Err(UniswapV3MathError::LimitOrderInRange);
+    }
+
+    let mut pool = pool.clone();
+    let liquidity_delta =
+        i128::try_from(liquidity).map_err(|_| UniswapV3MathError::LiquidityOverflow)?;
+    pool.modify_position(tick_lower, tick_upper, liquidity_delta)?;
+
+    let (order_amount_0, order_amount_1) =
+        amounts_at_price(sqrt_price_x_96, tick_lower, tick_upper, liquidity)?;
+
+    let swap = pool.swap(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+
+    let (amount_0, amount_1) =
+        amounts_at_price(swap.sqrt_price_x_96, tick_lower, tick_upper, liquidity)?;
+
+    let (order_amount, remaining) = if sells_token_0 {
+        (order_amount_0, amount_0)
+    } else {
+        (order_amount_1, amount_1)
+    };
+    let filled_x_96 = if order_amount.is_zero() {
+        U256::ZERO
+    } else {
+        mul_div(order_amount.saturating_sub(remaining), Q96, order_amount)?
+    };
+
+    Ok(LimitOrderFill {
+        amount_0,
+        amount_1,
+        filled_x_96,
+        swap,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{limit_order_range, simulate_fill};
+    use crate::{
+        error::UniswapV3MathError,
+        pool::PoolSnapshot,
+        sqrt_price_math::Q96,
+        test_utils::PoolBuilder,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    fn pool_at(tick_current: i32) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .full_range(1e18 as i128)
+            .build()
+    }
+
+    #[test]
+    fn test_limit_order_range() {
+        assert_eq!(limit_order_range(0, 60, true).unwrap(), (60, 120));
+        assert_eq!(limit_order_range(0, 60, false).unwrap(), (-60, 0));
+        assert_eq!(limit_order_range(-1, 60, true).unwrap(), (0, 60));
+        assert_eq!(limit_order_range(-1, 60, false).unwrap(), (-120, -60));
+        assert_eq!(limit_order_range(59, 60, false).unwrap(), (-60, 0));
+
+        //fails at the tick bounds
+        assert!(limit_order_range(887270, 10, true).is_err());
+        assert!(limit_order_range(-887270, 10, false).is_err());
+        assert!(limit_order_range(0, 0, true).is_err());
+    }
+
+    #[test]
+    fn test_simulate_fill() {
+        let pool = pool_at(10);
+        let (tick_lower, tick_upper) = limit_order_range(10, 60, true).unwrap();
+        let liquidity = 1e17 as u128;
+
+        //a swap in the other direction does not touch the order
+        let fill = simulate_fill(
+            &pool,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            true,
+            I256::from_raw(U256::from(1e16 as u128)),
+            MIN_SQRT_RATIO + U256_1,
+        )
+        .unwrap();
+        assert_eq!(fill.filled_x_96, U256::ZERO);
+        assert_eq!(fill.amount_1, U256::ZERO);
+
+        //a swap that ends inside the range partially fills the order
+        let middle = get_sqrt_ratio_at_tick(90).unwrap();
+        let fill = simulate_fill(
+            &pool,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            false,
+            I256::MAX,
+            middle,
+        )
+        .unwrap();
+        assert_eq!(fill.swap.sqrt_price_x_96, middle);
+        assert!(fill.filled_x_96 > Q96 * U256::from(45) / U256::from(100));
+        assert!(fill.filled_x_96 < Q96 * U256::from(55) / U256::from(100));
+        assert!(fill.amount_0 > U256::ZERO && fill.amount_1 > U256::ZERO);
+
+        //crossing the whole range fills the order completely
+        let fill = simulate_fill(
+            &pool,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            false,
+            I256::MAX,
+            get_sqrt_ratio_at_tick(200).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(fill.filled_x_96, Q96);
+        assert_eq!(fill.amount_0, U256::ZERO);
+        assert_eq!(fill.swap.initialized_ticks_crossed, 2);
+
+        //orders selling token1 fill when the price moves down
+        let (tick_lower, tick_upper) = limit_order_range(10, 60, false).unwrap();
+        let fill = simulate_fill(
+            &pool,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            true,
+            I256::MAX,
+            get_sqrt_ratio_at_tick(-100).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(fill.filled_x_96, Q96);
+        assert_eq!(fill.amount_1, U256::ZERO);
+
+        //fails if the range contains the current price
+        let result = simulate_fill(
+            &pool,
+            -60,
+            60,
+            liquidity,
+            false,
+            I256::MAX,
+            MAX_SQRT_RATIO - U256_1,
+        );
+        assert!(matches!(
+            result.unwrap_err(),
+            UniswapV3MathError::LimitOrderInRange
+        ));
+    }
+}
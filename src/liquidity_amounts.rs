@@ -0,0 +1,379 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    full_math::mul_div,
+    sqrt_price_math::{amounts_for_range, Q96},
+    tick_math::get_sqrt_ratio_at_tick,
+};
+
+// returns (uint128 liquidity), the liquidity received for a given amount of token0 and price range,
+// mirrors LiquidityAmounts.getLiquidityForAmount0
+pub fn get_liquidity_for_amount_0(
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    amount_0: U256,
+) -> Result<u128, UniswapV3MathError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        (sqrt_ratio_a_x_96, sqrt_ratio_b_x_96) = (sqrt_ratio_b_x_96, sqrt_ratio_a_x_96)
+    };
+
+    let intermediate = mul_div(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, Q96)?;
+    to_u128(mul_div(
+        amount_0,
+        intermediate,
+        sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96,
+    )?)
+}
+
+// returns (uint128 liquidity), the liquidity received for a given amount of token1 and price range,
+// mirrors LiquidityAmounts.getLiquidityForAmount1
+pub fn get_liquidity_for_amount_1(
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    amount_1: U256,
+) -> Result<u128, UniswapV3MathError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        (sqrt_ratio_a_x_96, sqrt_ratio_b_x_96) = (sqrt_ratio_b_x_96, sqrt_ratio_a_x_96)
+    };
+
+    to_u128(mul_div(
+        amount_1,
+        Q96,
+        sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96,
+    )?)
+}
+
+// returns (uint128 liquidity), the maximum liquidity received for the given amounts of token0 and token1 at the
+// current price, mirrors LiquidityAmounts.getLiquidityForAmounts
+pub fn get_liquidity_for_amounts(
+    sqrt_ratio_x_96: U256,
+    mut sqrt_ratio_a_x_96: U256,
+    mut sqrt_ratio_b_x_96: U256,
+    amount_0: U256,
+    amount_1: U256,
+) -> Result<u128, UniswapV3MathError> {
+    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
+        (sqrt_ratio_a_x_96, sqrt_ratio_b_x_96) = (sqrt_ratio_b_x_96, sqrt_ratio_a_x_96)
+    };
+
+    if sqrt_ratio_x_96 <= sqrt_ratio_a_x_96 {
+        get_liquidity_for_amount_0(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, amount_0)
+    } else if sqrt_ratio_x_96 < sqrt_ratio_b_x_96 {
+        let liquidity_0 = get_liquidity_for_amount_0(sqrt_ratio_x_96, sqrt_ratio_b_x_96, amount_0)?;
+        let liquidity_1 = get_liquidity_for_amount_1(sqrt_ratio_a_x_96, sqrt_ratio_x_96, amount_1)?;
+
+        Ok(liquidity_0.min(liquidity_1))
+    } else {
+        get_liquidity_for_amount_1(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, amount_1)
+    }
+}
+
+// returns (uint256 amount0, uint256 amount1), the amounts of token0 and token1 that `liquidity` is worth at the
+// current price, rounded down, mirrors LiquidityAmounts.getAmountsForLiquidity
+pub fn get_amounts_for_liquidity(
+    sqrt_ratio_x_96: U256,
+    sqrt_ratio_a_x_96: U256,
+    sqrt_ratio_b_x_96: U256,
+    liquidity: u128,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    amounts_for_liquidity(
+        sqrt_ratio_x_96,
+        sqrt_ratio_a_x_96,
+        sqrt_ratio_b_x_96,
+        liquidity,
+        false,
+    )
+}
+
+// returns the largest liquidity that can be minted in the given range from collected fees at the current price, along
+// with the token0/token1 amounts left over after paying for the mint. The leftover accounts for the pool rounding the
+// mint amounts up, so minting the liquidity never requires more than the fees
+pub fn max_liquidity_for_fees(
+    sqrt_price_x_96: U256,
+    tick_lower: i32,
+    tick_upper: i32,
+    fees_0: U256,
+    fees_1: U256,
+) -> Result<(u128, U256, U256), UniswapV3MathError> {
+    let sqrt_ratio_a_x_96 = get_sqrt_ratio_at_tick(tick_lower)?;
+    let sqrt_ratio_b_x_96 = get_sqrt_ratio_at_tick(tick_upper)?;
+
+    let mut liquidity = get_liquidity_for_amounts(
+        sqrt_price_x_96,
+        sqrt_ratio_a_x_96,
+        sqrt_ratio_b_x_96,
+        fees_0,
+        fees_1,
+    )?;
+
+    // rounding the amounts up can exceed the fees by a wei, in which case the liquidity is reduced until it fits
+    loop {
+        let (amount_0, amount_1) = amounts_for_liquidity(
+            sqrt_price_x_96,
+            sqrt_ratio_a_x_96,
+            sqrt_ratio_b_x_96,
+            liquidity,
+            true,
+        )?;
+
+        if amount_0 <= fees_0 && amount_1 <= fees_1 {
+            return Ok((liquidity, fees_0 - amount_0, fees_1 - amount_1));
+        }
+        liquidity -= 1;
+    }
+}
+
+//The liquidity minted from desired amounts and what is left of them, see `mint_dust`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MintDust {
+    pub liquidity: u128,
+    // the amounts the pool takes for the liquidity, rounded up like UniswapV3Pool.mint
+    pub amount_0: U256,
+    pub amount_1: U256,
+    // the desired amounts minus the amounts taken, left with the sender. Negative if the pool rounds an amount up past
+    // the desired amount, which the manager does not prevent and only happens by a wei
+    pub dust_0: I256,
+    pub dust_1: I256,
+}
+
+// returns the exact amounts taken and left over when minting or increasing a position in the given range from desired
+// amounts the way the NonfungiblePositionManager does: the liquidity is `get_liquidity_for_amounts` of the desired
+// amounts, truncated to an integer, and the pool rounds the amounts for it up. In range the dust of the token that
+// does not limit the liquidity is the unbalanced part of the amounts, the other is a few wei of truncation. Use
+// `max_liquidity_for_fees` for the liquidity that never takes more than the desired amounts
+pub fn mint_dust(
+    sqrt_price_x_96: U256,
+    tick_lower: i32,
+    tick_upper: i32,
+    amount_0_desired: U256,
+    amount_1_desired: U256,
+) -> Result<MintDust, UniswapV3MathError> {
+    let sqrt_ratio_a_x_96 = get_sqrt_ratio_at_tick(tick_lower)?;
+    let sqrt_ratio_b_x_96 = get_sqrt_ratio_at_tick(tick_upper)?;
+
+    let liquidity = get_liquidity_for_amounts(
+        sqrt_price_x_96,
+        sqrt_ratio_a_x_96,
+        sqrt_ratio_b_x_96,
+        amount_0_desired,
+        amount_1_desired,
+    )?;
+    let (amount_0, amount_1) = amounts_for_liquidity(
+        sqrt_price_x_96,
+        sqrt_ratio_a_x_96,
+        sqrt_ratio_b_x_96,
+        liquidity,
+        true,
+    )?;
+
+    let dust = |desired: U256, amount: U256| {
+        I256::try_from(desired)
+            .ok()
+            .zip(I256::try_from(amount).ok())
+            .and_then(|(desired, amount)| desired.checked_sub(amount))
+            .ok_or(UniswapV3MathError::Overflow)
+    };
+
+    Ok(MintDust {
+        liquidity,
+        amount_0,
+        amount_1,
+        dust_0: dust(amount_0_desired, amount_0)?,
+        dust_1: dust(amount_1_desired, amount_1)?,
+    })
+}
+
+fn amounts_for_liquidity(
+    sqrt_ratio_x_96: U256,
+    sqrt_ratio_a_x_96: U256,
+    sqrt_ratio_b_x_96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    amounts_for_range(
+        sqrt_ratio_x_96,
+        sqrt_ratio_a_x_96.min(sqrt_ratio_b_x_96),
+        sqrt_ratio_a_x_96.max(sqrt_ratio_b_x_96),
+        liquidity,
+        round_up.into(),
+    )
+}
+
+fn to_u128(value: U256) -> Result<u128, UniswapV3MathError> {
+    u128::try_from(value).map_err(|_| UniswapV3MathError::Overflow)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        amounts_for_liquidity, get_amounts_for_liquidity, get_liquidity_for_amounts,
+        max_liquidity_for_fees, mint_dust,
+    };
+    use crate::{sqrt_price_math::encode_sqrt_ratio_x96, tick_math::get_sqrt_ratio_at_tick};
+
+    #[test]
+    fn test_get_liquidity_for_amounts() {
+        // values from the LiquidityAmounts tests in v3-periphery
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(1), U256::from(1)).unwrap();
+        let sqrt_price_a = encode_sqrt_ratio_x96(U256::from(100), U256::from(110)).unwrap();
+        let sqrt_price_b = encode_sqrt_ratio_x96(U256::from(110), U256::from(100)).unwrap();
+
+        //price inside
+        let liquidity = get_liquidity_for_amounts(
+            sqrt_price,
+            sqrt_price_a,
+            sqrt_price_b,
+            U256::from(100),
+            U256::from(200),
+        )
+        .unwrap();
+        assert_eq!(liquidity, 2148);
+
+        //price below
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(99), U256::from(110)).unwrap();
+        let liquidity = get_liquidity_for_amounts(
+            sqrt_price,
+            sqrt_price_a,
+            sqrt_price_b,
+            U256::from(100),
+            U256::from(200),
+        )
+        .unwrap();
+        assert_eq!(liquidity, 1048);
+
+        //price above
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(111), U256::from(100)).unwrap();
+        let liquidity = get_liquidity_for_amounts(
+            sqrt_price,
+            sqrt_price_a,
+            sqrt_price_b,
+            U256::from(100),
+            U256::from(200),
+        )
+        .unwrap();
+        assert_eq!(liquidity, 2097);
+    }
+
+    #[test]
+    fn test_get_amounts_for_liquidity() {
+        // values from the LiquidityAmounts tests in v3-periphery
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(1), U256::from(1)).unwrap();
+        let sqrt_price_a = encode_sqrt_ratio_x96(U256::from(100), U256::from(110)).unwrap();
+        let sqrt_price_b = encode_sqrt_ratio_x96(U256::from(110), U256::from(100)).unwrap();
+
+        let amounts =
+            get_amounts_for_liquidity(sqrt_price, sqrt_price_a, sqrt_price_b, 2148).unwrap();
+        assert_eq!(amounts, (U256::from(99), U256::from(99)));
+
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(99), U256::from(110)).unwrap();
+        let amounts =
+            get_amounts_for_liquidity(sqrt_price, sqrt_price_a, sqrt_price_b, 1048).unwrap();
+        assert_eq!(amounts, (U256::from(99), U256::ZERO));
+
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(111), U256::from(100)).unwrap();
+        let amounts =
+            get_amounts_for_liquidity(sqrt_price, sqrt_price_a, sqrt_price_b, 2097).unwrap();
+        assert_eq!(amounts, (U256::ZERO, U256::from(199)));
+    }
+
+    #[test]
+    fn test_max_liquidity_for_fees() {
+        let sqrt_price = get_sqrt_ratio_at_tick(0).unwrap();
+
+        //in range, the scarcer token limits the liquidity and the other is left over
+        let (liquidity, leftover_0, leftover_1) = max_liquidity_for_fees(
+            sqrt_price,
+            -600,
+            600,
+            U256::from(1e18 as u128),
+            U256::from(5e17 as u128),
+        )
+        .unwrap();
+        assert!(liquidity > 0);
+        assert!(leftover_0 > U256::from(4e17 as u128));
+        assert!(leftover_1 < U256::from(1e6));
+
+        //one more unit of liquidity can not be paid for
+        let (amount_0, amount_1) = amounts_for_liquidity(
+            sqrt_price,
+            get_sqrt_ratio_at_tick(-600).unwrap(),
+            get_sqrt_ratio_at_tick(600).unwrap(),
+            liquidity + 1,
+            true,
+        )
+        .unwrap();
+        assert!(amount_0 > U256::from(1e18 as u128) || amount_1 > U256::from(5e17 as u128));
+
+        //out of range positions only use a single token
+        let (liquidity, leftover_0, leftover_1) = max_liquidity_for_fees(
+            sqrt_price,
+            600,
+            1200,
+            U256::from(1e18 as u128),
+            U256::from(5e17 as u128),
+        )
+        .unwrap();
+        assert!(liquidity > 0);
+        assert!(leftover_0 < U256::from(10));
+        assert_eq!(leftover_1, U256::from(5e17 as u128));
+
+        //no fees, no liquidity
+        let result = max_liquidity_for_fees(sqrt_price, -600, 600, U256::ZERO, U256::ZERO).unwrap();
+        assert_eq!(result, (0, U256::ZERO, U256::ZERO));
+    }
+
+    #[test]
+    fn test_mint_dust() {
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(3), U256::from(7)).unwrap();
+        let (amount_0_desired, amount_1_desired) =
+            (U256::from(1e18 as u128), U256::from(1e18 as u128));
+
+        //in range, the token that does not limit the liquidity is left over
+        let dust = mint_dust(
+            sqrt_price,
+            -12000,
+            -6000,
+            amount_0_desired,
+            amount_1_desired,
+        )
+        .unwrap();
+        let (amount_0, amount_1) = amounts_for_liquidity(
+            sqrt_price,
+            get_sqrt_ratio_at_tick(-12000).unwrap(),
+            get_sqrt_ratio_at_tick(-6000).unwrap(),
+            dust.liquidity,
+            true,
+        )
+        .unwrap();
+        assert_eq!((dust.amount_0, dust.amount_1), (amount_0, amount_1));
+        assert_eq!(
+            dust.dust_0,
+            I256::from_raw(amount_0_desired) - I256::from_raw(amount_0)
+        );
+        assert_eq!(
+            dust.dust_1,
+            I256::from_raw(amount_1_desired) - I256::from_raw(amount_1)
+        );
+        let (limiting, unbalanced) = if dust.dust_0 < dust.dust_1 {
+            (dust.dust_0, dust.dust_1)
+        } else {
+            (dust.dust_1, dust.dust_0)
+        };
+        assert!(limiting.unsigned_abs() < U256::from(1e6));
+        assert!(unbalanced > I256::try_from(1e16 as u128).unwrap());
+
+        //out of range only one token is taken and the other is left over entirely
+        let dust = mint_dust(sqrt_price, 600, 1200, amount_0_desired, amount_1_desired).unwrap();
+        assert_eq!(dust.amount_1, U256::ZERO);
+        assert_eq!(dust.dust_1, I256::from_raw(amount_1_desired));
+        assert!(dust.dust_0.unsigned_abs() < U256::from(10));
+
+        //nothing desired, nothing minted
+        let dust = mint_dust(sqrt_price, -12000, -6000, U256::ZERO, U256::ZERO).unwrap();
+        assert_eq!(dust.liquidity, 0);
+        assert_eq!((dust.dust_0, dust.dust_1), (I256::ZERO, I256::ZERO));
+    }
+}
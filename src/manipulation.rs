@@ -0,0 +1,194 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    oracle::Oracle,
+    pool::PoolSnapshot,
+    solver::amount_for_target_tick,
+    tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    U256_1,
+};
+
+// a TWAP is moved by holding the pool at a manipulated tick for part of its window. The attacker swaps to the tick at
+// the end of each block and arbitrageurs swap it back at the start of the next, so the manipulated tick is what the
+// oracle records for the time between the blocks, and every block starts from the unmanipulated pool again
+
+//The swap of a single block of a manipulation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ManipulationBlock {
+    // seconds since the start of the TWAP window
+    pub block_timestamp: u32,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+}
+
+//The cost of moving the TWAP of a pool, see `manipulation_cost`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ManipulationCost {
+    // the tick the pool is held at in every block
+    pub tick: i32,
+    // the arithmetic mean tick over the window the oracle returns at the end of the manipulation
+    pub twap_tick: i32,
+    pub blocks: Vec<ManipulationBlock>,
+    // the sums over all blocks, the input is what the attacker has to put up if nothing is recovered in between
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+}
+
+// returns the swaps that move the arithmetic mean tick of the `window` seconds ending after the last block by
+// `tick_delta` ticks from the current tick of the pool, holding the pool at a manipulated tick for `blocks` blocks of
+// `block_time` seconds at the end of the window. The manipulated tick is the closest one at which the oracle, with its
+// rounding towards negative infinity, returns a mean tick at least `tick_delta` away, and the amount of each block is
+// the exact input that lands the pool on it
+pub fn manipulation_cost(
+    pool: &PoolSnapshot,
+    tick_delta: i32,
+    window: u32,
+    blocks: u32,
+    block_time: u32,
+) -> Result<ManipulationCost, UniswapV3MathError> {
+    let held = blocks as u64 * block_time as u64;
+    if held == 0 || held > window as u64 || blocks >= u16::MAX as u32 {
+        return Err(UniswapV3MathError::InvalidManipulationWindow);
+    }
+    let start = window - held as u32;
+    let tick_current = pool.slot0.tick;
+    let twap_target = tick_current as i64 + tick_delta as i64;
+
+    // the oracle observes the current tick until the first block and the manipulated tick from then on
+    let twap = |tick: i32| -> Result<i32, UniswapV3MathError> {
+        let mut oracle = Oracle::initialize(0);
+        oracle.increase_cardinality_next(blocks as u16 + 1);
+        let mut tick_before = tick_current;
+        for block in 0..blocks {
+            oracle.write(start + block * block_time, tick_before, pool.liquidity);
+            tick_before = tick;
+        }
+        oracle.consult(window, window, tick, pool.liquidity)
+    };
+
+    // the manipulated tick scales the delta by the part of the window it is held for, rounded away from the current
+    // tick, and is moved further until the rounding of the oracle is accounted for
+    let delta = (tick_delta.unsigned_abs() as u64 * window as u64).div_ceil(held) as i64;
+    let mut tick = i32::try_from(tick_current as i64 + tick_delta.signum() as i64 * delta)
+        .map_err(|_| UniswapV3MathError::T)?;
+    let twap_tick = loop {
+        let twap_tick = twap(tick)?;
+        if (tick_delta >= 0 && twap_tick as i64 >= twap_target)
+            || (tick_delta < 0 && twap_tick as i64 <= twap_target)
+        {
+            break twap_tick;
+        }
+        tick += if tick_delta < 0 { -1 } else { 1 };
+    };
+
+    // every block swaps from the unmanipulated pool to the same tick
+    let zero_for_one = tick < tick_current;
+    let amount_in = amount_for_target_tick(pool, tick, zero_for_one)?;
+    let (amount_out, fee_amount) = if amount_in.is_zero() {
+        (U256::ZERO, U256::ZERO)
+    } else {
+        let sqrt_price_limit_x_96 = if zero_for_one {
+            MIN_SQRT_RATIO + U256_1
+        } else {
+            MAX_SQRT_RATIO - U256_1
+        };
+        let result = pool.quote(
+            zero_for_one,
+            I256::try_from(amount_in).map_err(|_| UniswapV3MathError::Overflow)?,
+            sqrt_price_limit_x_96,
+        )?;
+        let amount_out = if zero_for_one {
+            result.amount_1
+        } else {
+            result.amount_0
+        };
+        (amount_out.unsigned_abs(), result.fee_amount)
+    };
+
+    let mut cost = ManipulationCost {
+        tick,
+        twap_tick,
+        ..Default::default()
+    };
+    for block in 0..blocks {
+        cost.blocks.push(ManipulationBlock {
+            block_timestamp: start + block * block_time,
+            amount_in,
+            amount_out,
+            fee_amount,
+        });
+        cost.amount_in += amount_in;
+        cost.amount_out += amount_out;
+        cost.fee_amount += fee_amount;
+    }
+
+    Ok(cost)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::manipulation_cost;
+    use crate::{
+        error::UniswapV3MathError, pool::PoolSnapshot, solver::amount_for_target_tick,
+        test_utils::PoolBuilder, tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    fn pool() -> PoolSnapshot {
+        PoolBuilder::at_price(
+            (get_sqrt_ratio_at_tick(0).unwrap() + get_sqrt_ratio_at_tick(1).unwrap())
+                / U256::from(2),
+        )
+        .full_range(1e18 as i128)
+        .position(-6000, 6000, 1e19 as i128)
+        .build()
+    }
+
+    #[test]
+    fn test_manipulation_cost() {
+        let pool = pool();
+
+        //holding for a tenth of the window takes ten times the delta
+        let cost = manipulation_cost(&pool, 100, 1800, 15, 12).unwrap();
+        assert_eq!(cost.tick, 1000);
+        assert_eq!(cost.twap_tick, 100);
+        assert_eq!(cost.blocks.len(), 15);
+        assert_eq!(cost.blocks[0].block_timestamp, 1620);
+        assert_eq!(cost.blocks[14].block_timestamp, 1788);
+        let amount_in = amount_for_target_tick(&pool, 1000, false).unwrap();
+        assert!(cost
+            .blocks
+            .iter()
+            .all(|block| block.amount_in == amount_in && !block.fee_amount.is_zero()));
+        assert_eq!(cost.amount_in, amount_in * U256::from(15));
+        assert_eq!(cost.fee_amount, cost.blocks[0].fee_amount * U256::from(15));
+
+        //the delta is rounded away from the current tick and the mean tick towards negative infinity
+        let cost = manipulation_cost(&pool, 1, 1800, 7, 12).unwrap();
+        assert_eq!(cost.tick, 22);
+        assert_eq!(cost.twap_tick, 1);
+        let cost = manipulation_cost(&pool, -1, 1800, 7, 12).unwrap();
+        assert_eq!(cost.tick, -22);
+        assert_eq!(cost.twap_tick, -2);
+
+        //holding for more blocks moves the price less in each, the input is convex in the tick so this is cheaper
+        let short = manipulation_cost(&pool, -50, 1800, 5, 12).unwrap();
+        let long = manipulation_cost(&pool, -50, 1800, 50, 12).unwrap();
+        assert_eq!((short.tick, long.tick), (-1500, -150));
+        assert!(long.blocks[0].amount_in < short.blocks[0].amount_in);
+        assert!(long.amount_in < short.amount_in);
+        assert!(long.fee_amount < short.fee_amount);
+
+        //fails if the blocks do not fit into the window
+        for (window, blocks, block_time) in [(1800, 151, 12), (1800, 0, 12), (0, 1, 12)] {
+            assert!(matches!(
+                manipulation_cost(&pool, 1, window, blocks, block_time).unwrap_err(),
+                UniswapV3MathError::InvalidManipulationWindow
+            ));
+        }
+    }
+}
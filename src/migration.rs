@@ -0,0 +1,167 @@
+use alloy_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    liquidity_amounts::max_liquidity_for_fees,
+    pool::{preview_burn, BurnPreview, PoolSnapshot},
+    position::Position,
+    rebalance::{rebalance, Rebalance},
+    tick_math::{check_ticks, nearest_usable_tick},
+};
+
+// moving a position to another fee tier of the same pair: the position is burned and collected in the source pool,
+// the tokens are rebalanced with a swap in the target pool at its current price and minted over the same price range
+// there. The target pool usually has a different tick spacing, so the range is snapped to the closest usable ticks
+
+//The end to end result of moving a position between fee tiers, see `migrate_position`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Migration {
+    // the principal and fees collected from the source pool
+    pub burn: BurnPreview,
+    // the range in the target pool
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    // the swap in the target pool and the liquidity minted after it, the liquidity accounts for the mint rounding
+    // the amounts up, so the mint never asks for more than the balances after the swap
+    pub rebalance: Rebalance,
+}
+
+impl Migration {
+    // the balances left over after minting in the target pool
+    pub fn dust(&self) -> Result<(U256, U256), UniswapV3MathError> {
+        let (_, dust_0, dust_1) = max_liquidity_for_fees(
+            self.rebalance.sqrt_price_x_96,
+            self.tick_lower,
+            self.tick_upper,
+            self.rebalance.amount_0,
+            self.rebalance.amount_1,
+        )?;
+        Ok((dust_0, dust_1))
+    }
+}
+
+// returns the liquidity and swap that recreate `position` over the range `tick_lower`..`tick_upper` of pool `from` in
+// pool `to`, burning all of its liquidity and collecting the fees it is owed. Both pools must be of the same pair
+pub fn migrate_position(
+    from: &PoolSnapshot,
+    to: &PoolSnapshot,
+    tick_lower: i32,
+    tick_upper: i32,
+    position: &Position,
+) -> Result<Migration, UniswapV3MathError> {
+    let burn = preview_burn(from, tick_lower, tick_upper, position, position.liquidity)?;
+    let (amount_0, amount_1) = burn.collect_amounts();
+
+    let tick_spacing = to.fee_tier.tick_spacing();
+    let target_lower = nearest_usable_tick(tick_lower, tick_spacing)?;
+    let mut target_upper = nearest_usable_tick(tick_upper, tick_spacing)?;
+    // a range narrower than the tick spacing snaps to a single tick, keep at least one spacing
+    if target_upper <= target_lower {
+        target_upper = target_lower + tick_spacing;
+    }
+    check_ticks(target_lower, target_upper)?;
+
+    let mut rebalance = rebalance(
+        to,
+        U256::from(amount_0),
+        U256::from(amount_1),
+        target_lower,
+        target_upper,
+    )?;
+    (rebalance.liquidity, _, _) = max_liquidity_for_fees(
+        rebalance.sqrt_price_x_96,
+        target_lower,
+        target_upper,
+        rebalance.amount_0,
+        rebalance.amount_1,
+    )?;
+
+    Ok(Migration {
+        burn,
+        tick_lower: target_lower,
+        tick_upper: target_upper,
+        rebalance,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::migrate_position;
+    use crate::{
+        fee_tier::FeeTier,
+        pool::{preview_mint, PoolSnapshot},
+        position::Position,
+        test_utils::PoolBuilder,
+        tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    fn pool_at(tick_current: i32, fee_tier: FeeTier) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .fee_tier(fee_tier)
+            .full_range(1e20 as i128)
+            .build()
+    }
+
+    #[test]
+    fn test_migrate_position() {
+        let position = Position {
+            liquidity: 1e18 as u128,
+            tokens_owed_1: 1000,
+            ..Default::default()
+        };
+        let mut from = pool_at(0, FeeTier::Medium);
+        from.modify_position(-600, 600, position.liquidity as i128)
+            .unwrap();
+
+        //the same price, the balances already match the range
+        let to = pool_at(0, FeeTier::Low);
+        let migration = migrate_position(&from, &to, -600, 600, &position).unwrap();
+        assert_eq!((migration.tick_lower, migration.tick_upper), (-600, 600));
+        assert!(migration.rebalance.amount_in <= U256::from(1000));
+        assert!(migration.rebalance.liquidity > position.liquidity - position.liquidity / 1000);
+
+        //the mint after the swap never asks for more than the balances
+        let mut swapped = to.clone();
+        if !migration.rebalance.amount_in.is_zero() {
+            let limit = if migration.rebalance.zero_for_one {
+                MIN_SQRT_RATIO + U256_1
+            } else {
+                MAX_SQRT_RATIO - U256_1
+            };
+            swapped
+                .swap(
+                    migration.rebalance.zero_for_one,
+                    I256::from_raw(migration.rebalance.amount_in),
+                    limit,
+                )
+                .unwrap();
+        }
+        let (amount_0, amount_1) = preview_mint(
+            &swapped,
+            migration.tick_lower,
+            migration.tick_upper,
+            migration.rebalance.liquidity,
+        )
+        .unwrap();
+        assert!(amount_0 <= migration.rebalance.amount_0);
+        assert!(amount_1 <= migration.rebalance.amount_1);
+
+        //at a lower price the range holds more token0, so token1 is sold
+        let to = pool_at(-300, FeeTier::Low);
+        let migration = migrate_position(&from, &to, -600, 600, &position).unwrap();
+        assert!(!migration.rebalance.zero_for_one);
+        assert!(migration.rebalance.amount_in > U256::ZERO);
+        let (dust_0, dust_1) = migration.dust().unwrap();
+        assert!(dust_0 < U256::from(1e12 as u128) && dust_1 < U256::from(1e12 as u128));
+
+        //ticks are snapped to the spacing of the target pool
+        from.modify_position(-660, 660, position.liquidity as i128)
+            .unwrap();
+        let to = pool_at(0, FeeTier::High);
+        let migration = migrate_position(&from, &to, -660, 660, &position).unwrap();
+        assert_eq!((migration.tick_lower, migration.tick_upper), (-600, 600));
+    }
+}
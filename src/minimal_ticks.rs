@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use crate::{
+    pool::PoolSnapshot,
+    storage::{Storage, TickStorage},
+    tick::Tick,
+    tick_provider::TickProvider,
+};
+
+// a swap only reads the liquidity net of the ticks it crosses and writes their fee growth, seconds and tick cumulative
+// outside, which are only needed to compute fee growth inside ranges and oracle values. Users that only quote can drop
+// them and keep a fraction of the memory, at the cost of everything that depends on them, e.g. position fees, and of
+// minting and burning, which needs the gross liquidity of a tick to know when to flip it
+
+//The part of a tick a swap reads
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MinimalTick {
+    pub liquidity_net: i128,
+    pub initialized: bool,
+}
+
+impl From<Tick> for MinimalTick {
+    fn from(info: Tick) -> Self {
+        Self {
+            liquidity_net: info.liquidity_net,
+            initialized: info.initialized,
+        }
+    }
+}
+
+impl From<MinimalTick> for Tick {
+    fn from(info: MinimalTick) -> Self {
+        Self {
+            liquidity_net: info.liquidity_net,
+            initialized: info.initialized,
+            ..Default::default()
+        }
+    }
+}
+
+//Tick storage that only keeps the liquidity net and initialized flag of each tick, see `MinimalTick`. Ticks are
+//returned with all other fields zeroed and writes drop them
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MinimalTicks(HashMap<i32, MinimalTick>);
+
+impl MinimalTicks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&i32, &MinimalTick)> {
+        self.0.iter()
+    }
+}
+
+impl Storage<i32, Tick> for MinimalTicks {
+    fn get(&self, key: &i32) -> Option<Tick> {
+        self.0.get(key).map(|&info| info.into())
+    }
+
+    fn insert(&mut self, key: i32, value: Tick) -> Option<Tick> {
+        self.0.insert(key, value.into()).map(Tick::from)
+    }
+
+    fn remove(&mut self, key: &i32) -> Option<Tick> {
+        self.0.remove(key).map(Tick::from)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = i32> + '_ {
+        self.0.keys().copied()
+    }
+}
+
+impl TickStorage for MinimalTicks {
+    const LIQUIDITY_GROSS: bool = false;
+
+    fn liquidity_net(&self, tick: i32) -> i128 {
+        self.0.get(&tick).map_or(0, |info| info.liquidity_net)
+    }
+
+    fn load_tick<P: TickProvider>(&mut self, provider: &mut P, tick: i32) -> Result<(), P::Error> {
+        let info = provider.minimal_tick(tick)?;
+        self.0.insert(tick, info);
+        Ok(())
+    }
+}
+
+impl FromIterator<(i32, MinimalTick)> for MinimalTicks {
+    fn from_iter<I: IntoIterator<Item = (i32, MinimalTick)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<(i32, Tick)> for MinimalTicks {
+    fn from_iter<I: IntoIterator<Item = (i32, Tick)>>(iter: I) -> Self {
+        iter.into_iter()
+            .map(|(tick, info)| (tick, MinimalTick::from(info)))
+            .collect()
+    }
+}
+
+//A pool snapshot that only keeps what quoting needs, see `MinimalTicks`
+pub type MinimalPoolSnapshot = PoolSnapshot<MinimalTicks>;
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, mem::size_of};
+
+    use alloy_primitives::{Address, U256};
+
+    use super::{MinimalPoolSnapshot, MinimalTick, MinimalTicks};
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        pool::{PoolSnapshot, SwapParams},
+        pool_storage::tick_slot,
+        storage::{Storage, TickStorage},
+        tick::Tick,
+        tick_math::get_sqrt_ratio_at_tick,
+        tick_provider::{StorageTickProvider, TickProvider},
+    };
+
+    #[test]
+    fn test_minimal_pool_snapshot() {
+        assert!(size_of::<Tick>() >= 4 * size_of::<MinimalTick>());
+
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-887220, 887220, 1e20 as i128).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-1200, -60, 1e19 as i128).unwrap();
+        let mut minimal = MinimalPoolSnapshot::from(pool.clone());
+        assert_eq!(minimal.ticks.len(), pool.ticks.len());
+
+        //quotes and swaps are the same as with full ticks
+        for zero_for_one in [true, false] {
+            let params = SwapParams::builder()
+                .zero_for_one(zero_for_one)
+                .amount_in(U256::from(5e19 as u128))
+                .build()
+                .unwrap();
+            assert_eq!(
+                minimal.quote_with(&params).unwrap(),
+                pool.quote_with(&params).unwrap()
+            );
+            assert_eq!(
+                minimal.swap_with(&params).unwrap(),
+                pool.swap_with(&params).unwrap()
+            );
+            assert_eq!(minimal.slot0, pool.slot0);
+            assert_eq!(minimal.liquidity, pool.liquidity);
+        }
+
+        //the fields a swap does not read are dropped
+        let info = minimal.ticks.get(&-600).unwrap();
+        assert_eq!(info.liquidity_net, pool.ticks.liquidity_net(-600));
+        assert!(info.initialized);
+        assert_eq!(info.fee_growth_outside_0_x_128, U256::ZERO);
+        assert_ne!(pool.ticks[&-600].fee_growth_outside_0_x_128, U256::ZERO);
+
+        //minting and burning need the gross liquidity and fail without touching the pool
+        let before = minimal.clone();
+        for liquidity_delta in [1e18 as i128, -1e18 as i128] {
+            assert!(matches!(
+                minimal.modify_position(-600, 600, liquidity_delta),
+                Err(UniswapV3MathError::LiquidityGrossNotStored)
+            ));
+        }
+        assert_eq!(minimal, before);
+
+        //ticks are loaded through a provider reading only the words a swap needs
+        let slot = tick_slot(-600);
+        let mut reads = vec![];
+        let storage = |_: Address, index: U256| -> Result<U256, ()> {
+            reads.push(index);
+            Ok(if index == slot {
+                U256::from((-7i128) as u128) << 128 | U256::from(9)
+            } else {
+                U256::from(1) << 248
+            })
+        };
+        let mut provider = StorageTickProvider {
+            provider: storage,
+            address: Address::ZERO,
+        };
+        assert_eq!(
+            provider.minimal_tick(-600).unwrap(),
+            MinimalTick {
+                liquidity_net: -7,
+                initialized: true,
+            }
+        );
+        let mut ticks = MinimalTicks::new();
+        ticks.load_tick(&mut provider, -600).unwrap();
+        assert_eq!(ticks.liquidity_net(-600), -7);
+        assert_eq!(reads.len(), 4);
+        assert_eq!(reads[1], slot + U256::from(3));
+
+        //full tick storage loads whole ticks
+        let mut reads = 0;
+        let mut provider = StorageTickProvider {
+            provider: |_: Address, _: U256| -> Result<U256, ()> {
+                reads += 1;
+                Ok(U256::from(1) << 248)
+            },
+            address: Address::ZERO,
+        };
+        let mut ticks = HashMap::<i32, Tick>::new();
+        ticks.load_tick(&mut provider, 60).unwrap();
+        assert!(ticks[&60].initialized);
+        assert_eq!(reads, 4);
+    }
+}
@@ -0,0 +1,740 @@
+use alloy_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    full_math::{mul_div, Q128},
+    overflow::{
+        checked_seconds_per_liquidity_add, checked_tick_cumulative_add,
+        wrapping_seconds_per_liquidity_add, wrapping_seconds_per_liquidity_sub,
+        wrapping_tick_cumulative_add, wrapping_tick_cumulative_sub,
+    },
+    sqrt_price_math::Q192,
+    tick_math::get_sqrt_ratio_at_tick,
+};
+
+// a single price and liquidity observation, mirrors Oracle.Observation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Observation {
+    // the block timestamp of the observation
+    pub block_timestamp: u32,
+    // the tick accumulator, i.e. tick * time elapsed since the pool was first initialized
+    pub tick_cumulative: i64,
+    // the seconds per liquidity, i.e. seconds elapsed / max(1, liquidity) since the pool was first initialized
+    pub seconds_per_liquidity_cumulative_x_128: U256,
+    // whether or not the observation is initialized
+    pub initialized: bool,
+}
+
+// transforms a previous observation into a new observation, given the passage of time and the current tick and
+// liquidity values, mirrors Oracle.transform. The accumulators wrap at 56 and 160 bits like the contract
+pub fn transform(
+    last: &Observation,
+    block_timestamp: u32,
+    tick: i32,
+    liquidity: u128,
+) -> Observation {
+    let delta = block_timestamp.wrapping_sub(last.block_timestamp);
+
+    Observation {
+        block_timestamp,
+        tick_cumulative: wrapping_tick_cumulative_add(
+            last.tick_cumulative,
+            tick as i64 * delta as i64,
+        ),
+        seconds_per_liquidity_cumulative_x_128: wrapping_seconds_per_liquidity_add(
+            last.seconds_per_liquidity_cumulative_x_128,
+            seconds_per_liquidity_delta(delta, liquidity),
+        ),
+        initialized: true,
+    }
+}
+
+// same as transform, but fails instead of wrapping if an accumulator overflows
+pub fn checked_transform(
+    last: &Observation,
+    block_timestamp: u32,
+    tick: i32,
+    liquidity: u128,
+) -> Result<Observation, UniswapV3MathError> {
+    let delta = block_timestamp
+        .checked_sub(last.block_timestamp)
+        .ok_or(UniswapV3MathError::Overflow)?;
+
+    Ok(Observation {
+        block_timestamp,
+        tick_cumulative: checked_tick_cumulative_add(
+            last.tick_cumulative,
+            tick as i64 * delta as i64,
+        )?,
+        seconds_per_liquidity_cumulative_x_128: checked_seconds_per_liquidity_add(
+            last.seconds_per_liquidity_cumulative_x_128,
+            seconds_per_liquidity_delta(delta, liquidity),
+        )?,
+        initialized: true,
+    })
+}
+
+fn seconds_per_liquidity_delta(delta: u32, liquidity: u128) -> U256 {
+    (U256::from(delta) << 128) / U256::from(liquidity.max(1))
+}
+
+// returns the tick cumulative at `block_timestamp`, interpolating between the surrounding observations the same way
+// Oracle.observeSingle does. Observations must be in chronological order and the latest observation is treated as the
+// current state of the pool, so targets after it are not extrapolated
+pub fn tick_cumulative_at(
+    observations: &[Observation],
+    block_timestamp: u32,
+) -> Result<i64, UniswapV3MathError> {
+    let at_or_after = observations
+        .iter()
+        .position(|observation| observation.block_timestamp >= block_timestamp)
+        .ok_or(UniswapV3MathError::OLD)?;
+    let at_or_after_observation = observations[at_or_after];
+
+    if at_or_after_observation.block_timestamp == block_timestamp {
+        return Ok(at_or_after_observation.tick_cumulative);
+    }
+    if at_or_after == 0 {
+        return Err(UniswapV3MathError::OLD);
+    }
+
+    // we're in the middle
+    let before_or_at = observations[at_or_after - 1];
+    let observation_time_delta =
+        (at_or_after_observation.block_timestamp - before_or_at.block_timestamp) as i64;
+    let target_delta = (block_timestamp - before_or_at.block_timestamp) as i64;
+
+    Ok(wrapping_tick_cumulative_add(
+        before_or_at.tick_cumulative,
+        wrapping_tick_cumulative_sub(
+            at_or_after_observation.tick_cumulative,
+            before_or_at.tick_cumulative,
+        ) / observation_time_delta
+            * target_delta,
+    ))
+}
+
+// returns the arithmetic mean tick over the `period` seconds before the latest observation, rounded towards negative
+// infinity, mirrors OracleLibrary.consult
+pub fn consult(observations: &[Observation], period: u32) -> Result<i32, UniswapV3MathError> {
+    if period == 0 {
+        return Err(UniswapV3MathError::BP);
+    }
+
+    let latest = observations.last().ok_or(UniswapV3MathError::OLD)?;
+    let start = latest
+        .block_timestamp
+        .checked_sub(period)
+        .ok_or(UniswapV3MathError::OLD)?;
+
+    let tick_cumulatives_delta = wrapping_tick_cumulative_sub(
+        latest.tick_cumulative,
+        tick_cumulative_at(observations, start)?,
+    );
+
+    let mut arithmetic_mean_tick = tick_cumulatives_delta / period as i64;
+    // always round to negative infinity
+    if tick_cumulatives_delta < 0 && tick_cumulatives_delta % period as i64 != 0 {
+        arithmetic_mean_tick -= 1;
+    }
+
+    Ok(arithmetic_mean_tick as i32)
+}
+
+//The observations array of a pool together with the index and cardinality of slot0, mirrors the Oracle library as the
+//pool uses it. Growing the cardinality only writes the timestamp of the new slots, and they are used once the index
+//wraps around the current cardinality, so the observations a backtest reads back are the ones the pool would have
+//returned at the same block. Observations are written by the caller where the pool writes them, on swaps that change
+//the tick and on position changes while the position is in range
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Oracle {
+    // the slots up to the cardinality next, later slots are never read
+    observations: Vec<Observation>,
+    index: u16,
+    cardinality: u16,
+    cardinality_next: u16,
+}
+
+impl Oracle {
+    // returns the oracle of a pool initialized at `block_timestamp`, mirrors Oracle.initialize
+    pub fn initialize(block_timestamp: u32) -> Self {
+        Self {
+            observations: vec![Observation {
+                block_timestamp,
+                initialized: true,
+                ..Default::default()
+            }],
+            index: 0,
+            cardinality: 1,
+            cardinality_next: 1,
+        }
+    }
+
+    // returns the oracle from the observations array in storage order and the observation fields of slot0, e.g. as
+    // loaded by `pool_storage::load_observation_array`. Slots between the cardinality and the cardinality next that are not
+    // given are grown
+    pub fn new(
+        mut observations: Vec<Observation>,
+        index: u16,
+        cardinality: u16,
+        cardinality_next: u16,
+    ) -> Result<Self, UniswapV3MathError> {
+        if cardinality == 0
+            || index >= cardinality
+            || cardinality_next < cardinality
+            || observations.len() < cardinality as usize
+        {
+            return Err(UniswapV3MathError::I);
+        }
+        observations.resize(
+            cardinality_next as usize,
+            Observation {
+                block_timestamp: 1,
+                ..Default::default()
+            },
+        );
+
+        Ok(Self {
+            observations,
+            index,
+            cardinality,
+            cardinality_next,
+        })
+    }
+
+    pub fn observations(&self) -> &[Observation] {
+        &self.observations
+    }
+
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    pub fn cardinality(&self) -> u16 {
+        self.cardinality
+    }
+
+    pub fn cardinality_next(&self) -> u16 {
+        self.cardinality_next
+    }
+
+    // writes an observation at most once per block, moving to the next cardinality when the index reaches the end of
+    // the current one, mirrors Oracle.write
+    pub fn write(&mut self, block_timestamp: u32, tick: i32, liquidity: u128) {
+        let last = self.observations[self.index as usize];
+
+        // early return if we've already written an observation this block
+        if last.block_timestamp == block_timestamp {
+            return;
+        }
+
+        // if the conditions are right, we can bump the cardinality
+        if self.cardinality_next > self.cardinality && self.index == self.cardinality - 1 {
+            self.cardinality = self.cardinality_next;
+        }
+
+        self.index = (self.index + 1) % self.cardinality;
+        self.observations[self.index as usize] = transform(&last, block_timestamp, tick, liquidity);
+    }
+
+    // prepares the slots up to `cardinality_next` by writing their timestamp, does nothing if the cardinality next is
+    // already as large, mirrors UniswapV3Pool.increaseObservationCardinalityNext and Oracle.grow
+    pub fn increase_cardinality_next(&mut self, cardinality_next: u16) {
+        if cardinality_next <= self.cardinality_next {
+            return;
+        }
+
+        // store in each slot to prevent fresh SSTOREs in swaps, this data will not be used because the initialized
+        // boolean is still false
+        for _ in self.cardinality_next..cardinality_next {
+            self.observations.push(Observation {
+                block_timestamp: 1,
+                ..Default::default()
+            });
+        }
+        self.cardinality_next = cardinality_next;
+    }
+
+    // returns the tick and seconds per liquidity cumulatives `seconds_ago` seconds before `block_timestamp`, given
+    // the current tick and liquidity, mirrors Oracle.observeSingle
+    pub fn observe_single(
+        &self,
+        block_timestamp: u32,
+        seconds_ago: u32,
+        tick: i32,
+        liquidity: u128,
+    ) -> Result<(i64, U256), UniswapV3MathError> {
+        if seconds_ago == 0 {
+            let mut last = self.observations[self.index as usize];
+            if last.block_timestamp != block_timestamp {
+                last = transform(&last, block_timestamp, tick, liquidity);
+            }
+            return Ok((
+                last.tick_cumulative,
+                last.seconds_per_liquidity_cumulative_x_128,
+            ));
+        }
+
+        let target = block_timestamp.wrapping_sub(seconds_ago);
+        let (before_or_at, at_or_after) =
+            self.surrounding_observations(block_timestamp, target, tick, liquidity)?;
+
+        if target == before_or_at.block_timestamp {
+            // we're at the left boundary
+            Ok((
+                before_or_at.tick_cumulative,
+                before_or_at.seconds_per_liquidity_cumulative_x_128,
+            ))
+        } else if target == at_or_after.block_timestamp {
+            // we're at the right boundary
+            Ok((
+                at_or_after.tick_cumulative,
+                at_or_after.seconds_per_liquidity_cumulative_x_128,
+            ))
+        } else {
+            // we're in the middle
+            let observation_time_delta = at_or_after
+                .block_timestamp
+                .wrapping_sub(before_or_at.block_timestamp);
+            let target_delta = target.wrapping_sub(before_or_at.block_timestamp);
+
+            Ok((
+                wrapping_tick_cumulative_add(
+                    before_or_at.tick_cumulative,
+                    wrapping_tick_cumulative_sub(
+                        at_or_after.tick_cumulative,
+                        before_or_at.tick_cumulative,
+                    ) / observation_time_delta as i64
+                        * target_delta as i64,
+                ),
+                wrapping_seconds_per_liquidity_add(
+                    before_or_at.seconds_per_liquidity_cumulative_x_128,
+                    wrapping_seconds_per_liquidity_sub(
+                        at_or_after.seconds_per_liquidity_cumulative_x_128,
+                        before_or_at.seconds_per_liquidity_cumulative_x_128,
+                    ) * U256::from(target_delta)
+                        / U256::from(observation_time_delta),
+                ),
+            ))
+        }
+    }
+
+    // returns the cumulatives for each of `seconds_agos`, mirrors Oracle.observe
+    pub fn observe(
+        &self,
+        block_timestamp: u32,
+        seconds_agos: &[u32],
+        tick: i32,
+        liquidity: u128,
+    ) -> Result<Vec<(i64, U256)>, UniswapV3MathError> {
+        seconds_agos
+            .iter()
+            .map(|&seconds_ago| self.observe_single(block_timestamp, seconds_ago, tick, liquidity))
+            .collect()
+    }
+
+    // returns the arithmetic mean tick over the `period` seconds before `block_timestamp`, rounded towards negative
+    // infinity, the value OracleLibrary.consult returns at that block
+    pub fn consult(
+        &self,
+        block_timestamp: u32,
+        period: u32,
+        tick: i32,
+        liquidity: u128,
+    ) -> Result<i32, UniswapV3MathError> {
+        if period == 0 {
+            return Err(UniswapV3MathError::BP);
+        }
+
+        let (start, _) = self.observe_single(block_timestamp, period, tick, liquidity)?;
+        let (end, _) = self.observe_single(block_timestamp, 0, tick, liquidity)?;
+        let tick_cumulatives_delta = wrapping_tick_cumulative_sub(end, start);
+
+        let mut arithmetic_mean_tick = tick_cumulatives_delta / period as i64;
+        // always round to negative infinity
+        if tick_cumulatives_delta < 0 && tick_cumulatives_delta % period as i64 != 0 {
+            arithmetic_mean_tick -= 1;
+        }
+
+        Ok(arithmetic_mean_tick as i32)
+    }
+
+    // returns the observations at or surrounding `target`, the second is the transform of the latest observation if
+    // the target is after it, mirrors Oracle.getSurroundingObservations
+    fn surrounding_observations(
+        &self,
+        time: u32,
+        target: u32,
+        tick: i32,
+        liquidity: u128,
+    ) -> Result<(Observation, Observation), UniswapV3MathError> {
+        // optimistically set before to the newest observation
+        let before_or_at = self.observations[self.index as usize];
+
+        // if the target is chronologically at or after the newest observation, we can early return
+        if lte(time, before_or_at.block_timestamp, target) {
+            if before_or_at.block_timestamp == target {
+                return Ok((before_or_at, Observation::default()));
+            }
+            return Ok((
+                before_or_at,
+                transform(&before_or_at, target, tick, liquidity),
+            ));
+        }
+
+        // now, set before to the oldest observation, slots past the ones written so far are not initialized yet
+        let mut before_or_at = self.observations[((self.index + 1) % self.cardinality) as usize];
+        if !before_or_at.initialized {
+            before_or_at = self.observations[0];
+        }
+
+        // ensure that the target is chronologically at or after the oldest observation
+        if !lte(time, before_or_at.block_timestamp, target) {
+            return Err(UniswapV3MathError::OLD);
+        }
+
+        Ok(self.binary_search(time, target))
+    }
+
+    // returns the observations surrounding `target` within the written observations, mirrors Oracle.binarySearch
+    fn binary_search(&self, time: u32, target: u32) -> (Observation, Observation) {
+        let cardinality = self.cardinality as usize;
+        // oldest observation
+        let mut l = (self.index as usize + 1) % cardinality;
+        // newest observation
+        let mut r = l + cardinality - 1;
+
+        loop {
+            let i = (l + r) / 2;
+            let before_or_at = self.observations[i % cardinality];
+
+            // we've landed on an uninitialized tick, keep searching higher (more recently)
+            if !before_or_at.initialized {
+                l = i + 1;
+                continue;
+            }
+
+            let at_or_after = self.observations[(i + 1) % cardinality];
+            let target_at_or_after = lte(time, before_or_at.block_timestamp, target);
+
+            // check if we've found the answer!
+            if target_at_or_after && lte(time, target, at_or_after.block_timestamp) {
+                return (before_or_at, at_or_after);
+            }
+
+            if !target_at_or_after {
+                r = i - 1;
+            } else {
+                l = i + 1;
+            }
+        }
+    }
+}
+
+// returns whether `a` is chronologically at or before `b`, both at or before `time` across a single wrap of the
+// timestamp, mirrors Oracle.lte
+fn lte(time: u32, a: u32, b: u32) -> bool {
+    // if there hasn't been overflow, no need to adjust
+    if a <= time && b <= time {
+        return a <= b;
+    }
+
+    let a_adjusted = if a > time {
+        a as u64
+    } else {
+        a as u64 + (1 << 32)
+    };
+    let b_adjusted = if b > time {
+        b as u64
+    } else {
+        b as u64 + (1 << 32)
+    };
+    a_adjusted <= b_adjusted
+}
+
+// returns the amount of quote token received for `base_amount` of base token at the price of `tick`,
+// mirrors OracleLibrary.getQuoteAtTick
+pub fn get_quote_at_tick(
+    tick: i32,
+    base_amount: u128,
+    base_is_token_0: bool,
+) -> Result<U256, UniswapV3MathError> {
+    let sqrt_ratio_x_96 = get_sqrt_ratio_at_tick(tick)?;
+    let base_amount = U256::from(base_amount);
+
+    // calculate quote with better precision if it doesn't overflow when multiplied by itself
+    if sqrt_ratio_x_96 <= U256::from(u128::MAX) {
+        let ratio_x_192 = sqrt_ratio_x_96 * sqrt_ratio_x_96;
+        if base_is_token_0 {
+            mul_div(ratio_x_192, base_amount, Q192)
+        } else {
+            mul_div(Q192, base_amount, ratio_x_192)
+        }
+    } else {
+        let ratio_x_128 = mul_div(sqrt_ratio_x_96, sqrt_ratio_x_96, U256::from(1_u128 << 64))?;
+        if base_is_token_0 {
+            mul_div(ratio_x_128, base_amount, Q128)
+        } else {
+            mul_div(Q128, base_amount, ratio_x_128)
+        }
+    }
+}
+
+// returns the amount of quote token received for `base_amount` of base token at the geometric mean price over the
+// `period` seconds before the latest observation, composing `consult` and `get_quote_at_tick`
+pub fn get_quote_at_twap(
+    observations: &[Observation],
+    period: u32,
+    base_amount: u128,
+    base_is_token_0: bool,
+) -> Result<U256, UniswapV3MathError> {
+    let arithmetic_mean_tick = consult(observations, period)?;
+    get_quote_at_tick(arithmetic_mean_tick, base_amount, base_is_token_0)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{
+        checked_transform, consult, get_quote_at_tick, get_quote_at_twap, tick_cumulative_at,
+        transform, Observation, Oracle,
+    };
+    use crate::{
+        error::UniswapV3MathError,
+        overflow::{MAX_TICK_CUMULATIVE, MIN_TICK_CUMULATIVE},
+        sqrt_price_math::MAX_U160,
+        tick_math::{MAX_TICK, MIN_TICK},
+    };
+
+    // observations every `interval` seconds starting at timestamp 1000 with the given ticks in between
+    fn observations(interval: u32, ticks: &[i32]) -> Vec<Observation> {
+        let mut observations = vec![Observation {
+            block_timestamp: 1000,
+            initialized: true,
+            ..Default::default()
+        }];
+        for &tick in ticks {
+            let last = observations.last().unwrap();
+            observations.push(transform(last, last.block_timestamp + interval, tick, 1));
+        }
+        observations
+    }
+
+    #[test]
+    fn test_transform() {
+        let first = Observation {
+            block_timestamp: 100,
+            initialized: true,
+            ..Default::default()
+        };
+
+        let next = transform(&first, 112, -5, 4);
+        assert_eq!(next.block_timestamp, 112);
+        assert_eq!(next.tick_cumulative, -60);
+        assert_eq!(
+            next.seconds_per_liquidity_cumulative_x_128,
+            U256::from(3) << 128
+        );
+        assert!(next.initialized);
+
+        //zero liquidity is treated as one
+        let next = transform(&next, 113, 10, 0);
+        assert_eq!(next.tick_cumulative, -50);
+        assert_eq!(
+            next.seconds_per_liquidity_cumulative_x_128,
+            U256::from(4) << 128
+        );
+
+        //the timestamp is allowed to wrap
+        let last = Observation {
+            block_timestamp: u32::MAX,
+            ..Default::default()
+        };
+        assert_eq!(transform(&last, 1, 7, 1).tick_cumulative, 14);
+    }
+
+    #[test]
+    fn test_checked_transform() {
+        let last = Observation {
+            block_timestamp: 100,
+            tick_cumulative: MAX_TICK_CUMULATIVE,
+            seconds_per_liquidity_cumulative_x_128: MAX_U160,
+            initialized: true,
+        };
+
+        //the accumulators wrap at 56 and 160 bits like the contract
+        let next = transform(&last, 101, 1, 1 << 127);
+        assert_eq!(next.tick_cumulative, MIN_TICK_CUMULATIVE);
+        assert_eq!(next.seconds_per_liquidity_cumulative_x_128, U256::from(1));
+
+        //fails if the contract would have wrapped
+        assert!(checked_transform(&last, 101, 1, 1 << 127).is_err());
+        assert!(checked_transform(&last, 101, -1, u128::MAX).is_err());
+        assert!(checked_transform(&last, 99, 0, 1).is_err());
+
+        let last = Observation {
+            block_timestamp: 100,
+            initialized: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            checked_transform(&last, 112, -5, 4).unwrap(),
+            transform(&last, 112, -5, 4)
+        );
+    }
+
+    #[test]
+    fn test_consult() {
+        let observations = observations(60, &[100, 200, -300]);
+
+        //interpolates between observations
+        assert_eq!(tick_cumulative_at(&observations, 1000).unwrap(), 0);
+        assert_eq!(tick_cumulative_at(&observations, 1030).unwrap(), 3000);
+        assert_eq!(tick_cumulative_at(&observations, 1090).unwrap(), 12000);
+        assert_eq!(tick_cumulative_at(&observations, 1180).unwrap(), 0);
+
+        assert_eq!(consult(&observations, 60).unwrap(), -300);
+        assert_eq!(consult(&observations, 120).unwrap(), -50);
+        assert_eq!(consult(&observations, 180).unwrap(), 0);
+
+        //rounds towards negative infinity
+        assert_eq!(consult(&observations, 90).unwrap(), -134);
+
+        //fails for a zero period or a period beyond the oldest observation
+        assert!(matches!(
+            consult(&observations, 0).unwrap_err(),
+            UniswapV3MathError::BP
+        ));
+        assert!(matches!(
+            consult(&observations, 181).unwrap_err(),
+            UniswapV3MathError::OLD
+        ));
+        assert!(matches!(
+            consult(&[], 60).unwrap_err(),
+            UniswapV3MathError::OLD
+        ));
+    }
+
+    #[test]
+    fn test_get_quote_at_tick() {
+        //one to one at tick 0
+        assert_eq!(
+            get_quote_at_tick(0, 1e18 as u128, true).unwrap(),
+            U256::from(1e18 as u128)
+        );
+        assert_eq!(
+            get_quote_at_tick(0, 1e18 as u128, false).unwrap(),
+            U256::from(1e18 as u128)
+        );
+
+        //quotes follow 1.0001 ** tick in both directions
+        for tick in [-50000, -1, 1, 23028, 100000] {
+            let expected = 1.0001f64.powi(tick) * 1e18;
+            let quote = f64::from(get_quote_at_tick(tick, 1e18 as u128, true).unwrap());
+            assert!((quote / expected - 1.0).abs() < 1e-9);
+
+            let expected = 1.0001f64.powi(-tick) * 1e18;
+            let quote = f64::from(get_quote_at_tick(tick, 1e18 as u128, false).unwrap());
+            assert!((quote / expected - 1.0).abs() < 1e-9);
+        }
+
+        //does not overflow at the tick bounds
+        for tick in [MIN_TICK, MAX_TICK] {
+            for base_is_token_0 in [true, false] {
+                assert!(get_quote_at_tick(tick, u128::MAX, base_is_token_0).is_ok());
+            }
+        }
+        assert!(get_quote_at_tick(MAX_TICK + 1, 1, true).is_err());
+    }
+
+    #[test]
+    fn test_get_quote_at_twap() {
+        let observations = observations(600, &[23028, 23028]);
+
+        //a constant tick of 23028 is about 10 token1 per token0
+        let quote = get_quote_at_twap(&observations, 1200, 1e18 as u128, true).unwrap();
+        assert_eq!(quote, get_quote_at_tick(23028, 1e18 as u128, true).unwrap());
+        assert!((f64::from(quote) / 1e19 - 1.0).abs() < 1e-3);
+        let quote = get_quote_at_twap(&observations, 600, 1e18 as u128, false).unwrap();
+        assert!((f64::from(quote) / 1e17 - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_oracle() {
+        //without growing the cardinality every write overwrites the only observation
+        let mut oracle = Oracle::initialize(1000);
+        assert_eq!(oracle.consult(1060, 60, 10, 1).unwrap(), 10);
+        oracle.write(1060, 10, 1);
+        oracle.write(1120, 20, 1);
+        assert_eq!(oracle.cardinality(), 1);
+        assert!(matches!(
+            oracle.consult(1150, 60, 30, 1).unwrap_err(),
+            UniswapV3MathError::OLD
+        ));
+        assert_eq!(oracle.consult(1150, 30, 30, 1).unwrap(), 30);
+
+        //grown slots only get their timestamp and are used once the index wraps around the current cardinality
+        let mut oracle = Oracle::initialize(1000);
+        oracle.increase_cardinality_next(4);
+        assert_eq!((oracle.cardinality(), oracle.cardinality_next()), (1, 4));
+        assert_eq!(oracle.observations()[3].block_timestamp, 1);
+        assert!(!oracle.observations()[3].initialized);
+        assert_eq!(
+            Oracle::new(oracle.observations()[..1].to_vec(), 0, 1, 4).unwrap(),
+            oracle
+        );
+
+        //the first write bumps the cardinality, the oldest observation is the first until the slots are written
+        oracle.write(1060, 10, 1);
+        oracle.write(1120, 20, 1);
+        assert_eq!((oracle.index(), oracle.cardinality()), (2, 4));
+        assert_eq!(oracle.consult(1150, 60, 30, 1).unwrap(), 25);
+        assert_eq!(oracle.consult(1150, 150, 30, 1).unwrap(), 18);
+        assert!(oracle.consult(1150, 151, 30, 1).is_err());
+
+        //the same as the chronological observations
+        let mut observations = oracle.observations()[..3].to_vec();
+        observations.push(transform(&observations[2], 1150, 30, 1));
+        for period in [1, 29, 30, 60, 89, 150] {
+            assert_eq!(
+                oracle.consult(1150, period, 30, 1).unwrap(),
+                consult(&observations, period).unwrap()
+            );
+        }
+        assert_eq!(
+            oracle.observe(1150, &[0], 30, 1).unwrap(),
+            vec![(
+                observations[3].tick_cumulative,
+                observations[3].seconds_per_liquidity_cumulative_x_128
+            )]
+        );
+
+        //wrapping around the ring overwrites the oldest observation
+        oracle.increase_cardinality_next(2);
+        assert_eq!(oracle.cardinality_next(), 4);
+        oracle.write(1180, 30, 1);
+        oracle.write(1240, 40, 1);
+        assert_eq!(oracle.index(), 0);
+        assert!(oracle.consult(1240, 240, 40, 1).is_err());
+        assert_eq!(oracle.consult(1240, 180, 40, 1).unwrap(), 30);
+
+        //writes once per block
+        let before = oracle.clone();
+        oracle.write(1240, -40, 1);
+        assert_eq!(oracle, before);
+
+        //the timestamp is allowed to wrap
+        let mut oracle = Oracle::initialize(u32::MAX - 29);
+        oracle.increase_cardinality_next(2);
+        oracle.write(30, 10, 1);
+        assert_eq!(oracle.consult(60, 90, 20, 1).unwrap(), 13);
+
+        //fails for an oracle that is not initialized
+        assert!(matches!(
+            Oracle::new(vec![], 0, 0, 0).unwrap_err(),
+            UniswapV3MathError::I
+        ));
+        assert!(Oracle::new(vec![Observation::default()], 1, 1, 1).is_err());
+    }
+}
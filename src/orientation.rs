@@ -0,0 +1,207 @@
+use alloy_primitives::{Address, I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    pool::{SwapParams, SwapParamsBuilder},
+    tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    U256_1,
+};
+
+// pools only know token0 and token1, token0 has the lower address. A trade is usually expressed as selling one token for
+// the other, which maps to the pool as follows:
+// - selling token0 is zero_for_one, the price (token1 per token0) moves down and the price limit must be below it
+// - selling token1 is one_for_zero, the price moves up and the price limit must be above it
+// - the amount specified is positive for an exact input and negative for an exact output
+// - the amounts returned by a swap are positive for the token the pool receives and negative for the token it pays
+
+// returns the tokens sorted the way a pool sorts them, (token0, token1)
+pub fn order_tokens(token_a: Address, token_b: Address) -> (Address, Address) {
+    if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    }
+}
+
+// returns the direction of a swap selling `token_in` for `token_out` in the pool of `token_0` and `token_1`
+pub fn zero_for_one(
+    token_0: Address,
+    token_1: Address,
+    token_in: Address,
+    token_out: Address,
+) -> Result<bool, UniswapV3MathError> {
+    if (token_in, token_out) == (token_0, token_1) {
+        Ok(true)
+    } else if (token_in, token_out) == (token_1, token_0) {
+        Ok(false)
+    } else {
+        Err(UniswapV3MathError::TokenNotInPool)
+    }
+}
+
+// the price limit that lets a swap in the given direction move the price as far as the pool allows
+pub fn unbounded_sqrt_price_limit(zero_for_one: bool) -> U256 {
+    if zero_for_one {
+        MIN_SQRT_RATIO + U256_1
+    } else {
+        MAX_SQRT_RATIO - U256_1
+    }
+}
+
+// returns true if a swap in the given direction accepts the price limit at the current price, mirrors the SPL check of
+// UniswapV3Pool.swap
+pub fn is_valid_sqrt_price_limit(
+    zero_for_one: bool,
+    sqrt_price_x_96: U256,
+    sqrt_price_limit_x_96: U256,
+) -> bool {
+    if zero_for_one {
+        sqrt_price_limit_x_96 < sqrt_price_x_96 && sqrt_price_limit_x_96 > MIN_SQRT_RATIO
+    } else {
+        sqrt_price_limit_x_96 > sqrt_price_x_96 && sqrt_price_limit_x_96 < MAX_SQRT_RATIO
+    }
+}
+
+// returns the amounts the swapper pays and receives from the token0 and token1 amounts a swap returns
+pub fn amounts_in_out(zero_for_one: bool, amount_0: I256, amount_1: I256) -> (U256, U256) {
+    let (amount_in, amount_out) = if zero_for_one {
+        (amount_0, amount_1)
+    } else {
+        (amount_1, amount_0)
+    };
+    (amount_in.unsigned_abs(), amount_out.unsigned_abs())
+}
+
+//The amount of a trade, in the input token for an exact input and in the output token for an exact output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TradeAmount {
+    ExactInput(U256),
+    ExactOutput(U256),
+}
+
+//Selling `token_in` for `token_out`, independent of which of the two is token0 of the pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Trade {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount: TradeAmount,
+}
+
+impl Trade {
+    pub fn exact_input(token_in: Address, token_out: Address, amount_in: U256) -> Self {
+        Self {
+            token_in,
+            token_out,
+            amount: TradeAmount::ExactInput(amount_in),
+        }
+    }
+
+    pub fn exact_output(token_in: Address, token_out: Address, amount_out: U256) -> Self {
+        Self {
+            token_in,
+            token_out,
+            amount: TradeAmount::ExactOutput(amount_out),
+        }
+    }
+
+    // returns the direction and amount of the swap in the pool of `token_0` and `token_1`, the price limit can be set
+    // on the builder and defaults to no limit in the direction of the swap
+    pub fn swap_params(
+        &self,
+        token_0: Address,
+        token_1: Address,
+    ) -> Result<SwapParamsBuilder, UniswapV3MathError> {
+        let builder = SwapParams::builder().zero_for_one(zero_for_one(
+            token_0,
+            token_1,
+            self.token_in,
+            self.token_out,
+        )?);
+
+        Ok(match self.amount {
+            TradeAmount::ExactInput(amount_in) => builder.amount_in(amount_in),
+            TradeAmount::ExactOutput(amount_out) => builder.amount_out(amount_out),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, I256, U256};
+
+    use super::{
+        amounts_in_out, is_valid_sqrt_price_limit, order_tokens, unbounded_sqrt_price_limit, Trade,
+    };
+    use crate::{
+        error::UniswapV3MathError, fee_tier::FeeTier, pool::PoolSnapshot,
+        tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    #[test]
+    fn test_trade_orientation() {
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let (token_0, token_1) = order_tokens(weth, usdc);
+        assert_eq!((token_0, token_1), (usdc, weth));
+        assert_eq!(order_tokens(usdc, weth), (usdc, weth));
+
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        let amount = U256::from(1e18 as u128);
+
+        //selling usdc, token0, moves the price down
+        let params = Trade::exact_input(usdc, weth, amount)
+            .swap_params(token_0, token_1)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(params.zero_for_one);
+        assert_eq!(params.amount_specified, I256::from_raw(amount));
+        assert_eq!(
+            params.sqrt_price_limit_x_96,
+            unbounded_sqrt_price_limit(true)
+        );
+        let result = pool.quote_with(&params).unwrap();
+        assert!(result.sqrt_price_x_96 < pool.slot0.sqrt_price_x_96);
+        let (amount_in, amount_out) =
+            amounts_in_out(params.zero_for_one, result.amount_0, result.amount_1);
+        assert_eq!(amount_in, amount);
+        assert_eq!(amount_out, result.amount_1.unsigned_abs());
+
+        //buying exactly some usdc sells weth, token1, and moves the price up
+        let params = Trade::exact_output(weth, usdc, amount)
+            .swap_params(token_0, token_1)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(!params.zero_for_one);
+        assert_eq!(params.amount_specified, -I256::from_raw(amount));
+        let result = pool.quote_with(&params).unwrap();
+        assert!(result.sqrt_price_x_96 > pool.slot0.sqrt_price_x_96);
+        let (_, amount_out) = amounts_in_out(params.zero_for_one, result.amount_0, result.amount_1);
+        assert_eq!(amount_out, amount);
+
+        //the limit must be on the side the price moves to
+        let price = pool.slot0.sqrt_price_x_96;
+        let below = get_sqrt_ratio_at_tick(-60).unwrap();
+        assert!(is_valid_sqrt_price_limit(true, price, below));
+        assert!(!is_valid_sqrt_price_limit(false, price, below));
+        assert!(is_valid_sqrt_price_limit(
+            false,
+            price,
+            unbounded_sqrt_price_limit(false)
+        ));
+
+        //fails if the trade is not for the tokens of the pool
+        let dai = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+        assert!(matches!(
+            Trade::exact_input(dai, weth, amount).swap_params(token_0, token_1),
+            Err(UniswapV3MathError::TokenNotInPool)
+        ));
+        assert!(matches!(
+            Trade::exact_input(usdc, usdc, amount).swap_params(token_0, token_1),
+            Err(UniswapV3MathError::TokenNotInPool)
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+use alloy_primitives::U256;
+
+use crate::{error::UniswapV3MathError, uint160::Uint160};
+
+// the core contracts are compiled with solidity 0.7, where arithmetic silently wraps, and fee growth and the oracle
+// accumulators rely on it. They are only meaningful as differences between two checkpoints, and the differences stay
+// correct across an overflow. The wrapping functions reproduce on chain values bit for bit and are what the rest of
+// the crate uses. The checked functions fail wherever the contract would have wrapped, which is useful to validate
+// state that is not expected to overflow, e.g. a freshly indexed pool or a hand built fixture
+
+// the bounds of the int56 tick accumulators
+pub const MIN_TICK_CUMULATIVE: i64 = -(1 << 55);
+pub const MAX_TICK_CUMULATIVE: i64 = (1 << 55) - 1;
+
+// feeGrowthGlobal - feeGrowthOutside as a uint256, wrapping like the contract
+pub fn wrapping_fee_growth_sub(a: U256, b: U256) -> U256 {
+    a.wrapping_sub(b)
+}
+
+pub fn checked_fee_growth_sub(a: U256, b: U256) -> Result<U256, UniswapV3MathError> {
+    a.checked_sub(b).ok_or(UniswapV3MathError::Overflow)
+}
+
+// tickCumulative + delta as an int56, wrapping like the contract
+pub fn wrapping_tick_cumulative_add(a: i64, b: i64) -> i64 {
+    to_i56(a.wrapping_add(b))
+}
+
+pub fn checked_tick_cumulative_add(a: i64, b: i64) -> Result<i64, UniswapV3MathError> {
+    checked_i56(a.checked_add(b))
+}
+
+pub fn wrapping_tick_cumulative_sub(a: i64, b: i64) -> i64 {
+    to_i56(a.wrapping_sub(b))
+}
+
+pub fn checked_tick_cumulative_sub(a: i64, b: i64) -> Result<i64, UniswapV3MathError> {
+    checked_i56(a.checked_sub(b))
+}
+
+// secondsPerLiquidityCumulativeX128 + delta as a uint160, wrapping like the contract. The arguments are uint160s,
+// larger values are truncated like the contract casts them
+pub fn wrapping_seconds_per_liquidity_add(a: U256, b: U256) -> U256 {
+    Uint160::truncate(a)
+        .wrapping_add(Uint160::truncate(b))
+        .into()
+}
+
+pub fn checked_seconds_per_liquidity_add(a: U256, b: U256) -> Result<U256, UniswapV3MathError> {
+    Ok(Uint160::new(a)?.checked_add(Uint160::new(b)?)?.into())
+}
+
+pub fn wrapping_seconds_per_liquidity_sub(a: U256, b: U256) -> U256 {
+    Uint160::truncate(a)
+        .wrapping_sub(Uint160::truncate(b))
+        .into()
+}
+
+pub fn checked_seconds_per_liquidity_sub(a: U256, b: U256) -> Result<U256, UniswapV3MathError> {
+    Ok(Uint160::new(a)?.checked_sub(Uint160::new(b)?)?.into())
+}
+
+// sign extends the low 56 bits
+fn to_i56(value: i64) -> i64 {
+    (value << 8) >> 8
+}
+
+fn checked_i56(value: Option<i64>) -> Result<i64, UniswapV3MathError> {
+    value
+        .filter(|value| (MIN_TICK_CUMULATIVE..=MAX_TICK_CUMULATIVE).contains(value))
+        .ok_or(UniswapV3MathError::Overflow)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{
+        checked_fee_growth_sub, checked_seconds_per_liquidity_add,
+        checked_seconds_per_liquidity_sub, checked_tick_cumulative_add,
+        checked_tick_cumulative_sub, wrapping_fee_growth_sub, wrapping_seconds_per_liquidity_add,
+        wrapping_seconds_per_liquidity_sub, wrapping_tick_cumulative_add,
+        wrapping_tick_cumulative_sub, MAX_TICK_CUMULATIVE, MIN_TICK_CUMULATIVE,
+    };
+    use crate::sqrt_price_math::MAX_U160;
+
+    #[test]
+    fn test_fee_growth_sub() {
+        let (a, b) = (U256::from(5), U256::from(7));
+        assert_eq!(wrapping_fee_growth_sub(b, a), U256::from(2));
+        assert_eq!(checked_fee_growth_sub(b, a).unwrap(), U256::from(2));
+
+        //wraps like the contract
+        assert_eq!(wrapping_fee_growth_sub(a, b), U256::MAX - U256::from(1));
+        //fails if the contract would have wrapped
+        assert!(checked_fee_growth_sub(a, b).is_err());
+    }
+
+    #[test]
+    fn test_tick_cumulative() {
+        assert_eq!(wrapping_tick_cumulative_add(-60, 10), -50);
+        assert_eq!(checked_tick_cumulative_add(-60, 10).unwrap(), -50);
+
+        //wraps at 56 bits, not 64
+        assert_eq!(
+            wrapping_tick_cumulative_add(MAX_TICK_CUMULATIVE, 1),
+            MIN_TICK_CUMULATIVE
+        );
+        assert_eq!(
+            wrapping_tick_cumulative_sub(MIN_TICK_CUMULATIVE, 1),
+            MAX_TICK_CUMULATIVE
+        );
+        //differences across an overflow are still correct
+        let end = wrapping_tick_cumulative_add(MAX_TICK_CUMULATIVE, 100);
+        assert_eq!(wrapping_tick_cumulative_sub(end, MAX_TICK_CUMULATIVE), 100);
+
+        //fails if the contract would have wrapped
+        assert!(checked_tick_cumulative_add(MAX_TICK_CUMULATIVE, 1).is_err());
+        assert!(checked_tick_cumulative_sub(MIN_TICK_CUMULATIVE, 1).is_err());
+        assert!(checked_tick_cumulative_sub(end, MAX_TICK_CUMULATIVE).is_err());
+    }
+
+    #[test]
+    fn test_seconds_per_liquidity() {
+        let one = U256::from(1);
+
+        //wraps at 160 bits, not 256
+        assert_eq!(
+            wrapping_seconds_per_liquidity_add(MAX_U160, one),
+            U256::ZERO
+        );
+        assert_eq!(
+            wrapping_seconds_per_liquidity_sub(U256::ZERO, one),
+            MAX_U160
+        );
+        assert_eq!(
+            wrapping_seconds_per_liquidity_sub(U256::ZERO, MAX_U160),
+            one
+        );
+
+        assert_eq!(
+            checked_seconds_per_liquidity_add(MAX_U160 - one, one).unwrap(),
+            MAX_U160
+        );
+        //fails if the contract would have wrapped
+        assert!(checked_seconds_per_liquidity_add(MAX_U160, one).is_err());
+        assert!(checked_seconds_per_liquidity_sub(U256::ZERO, one).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+use alloy_primitives::{Address, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    orientation::{amounts_in_out, Trade, TradeAmount},
+    pool::{PoolSnapshot, SwapParamsBuilder, SwapResult},
+    pool_key::PoolKey,
+};
+
+// the router encodes a multi hop path as the tokens the swap goes through, packed with the fee of the pool between
+// each pair of them, mirrors Path.sol. Exact output paths are encoded in reverse, starting with the output token
+
+// the number of bytes of an encoded token address
+pub const ADDR_SIZE: usize = 20;
+// the number of bytes of an encoded fee
+pub const FEE_SIZE: usize = 3;
+// the number of bytes of an encoded hop after the first token, the fee and the next token
+pub const NEXT_OFFSET: usize = ADDR_SIZE + FEE_SIZE;
+
+//A single swap of a multi hop path, selling `token_in` for `token_out` in their pool with fee `fee`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Hop {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub fee: u32,
+}
+
+impl Hop {
+    pub fn new(token_in: Address, token_out: Address, fee: u32) -> Self {
+        Self {
+            token_in,
+            token_out,
+            fee,
+        }
+    }
+
+    // returns the key of the pool the hop swaps in
+    pub fn pool_key(&self) -> PoolKey {
+        PoolKey::new(self.token_in, self.token_out, self.fee)
+    }
+
+    // returns the direction and amount of the swap of the hop in its pool, see `Trade::swap_params`
+    pub fn swap_params(
+        &self,
+        amount: TradeAmount,
+    ) -> Result<SwapParamsBuilder, UniswapV3MathError> {
+        let key = self.pool_key();
+        Trade {
+            token_in: self.token_in,
+            token_out: self.token_out,
+            amount,
+        }
+        .swap_params(key.token_0, key.token_1)
+    }
+}
+
+// returns the packed path of the hops, each hop must start with the output token of the one before it and have a fee
+// that fits in 24 bits
+pub fn encode_path(hops: &[Hop]) -> Result<Vec<u8>, UniswapV3MathError> {
+    let Some(first) = hops.first() else {
+        return Err(UniswapV3MathError::InvalidPath);
+    };
+
+    let mut path = Vec::with_capacity(ADDR_SIZE + hops.len() * NEXT_OFFSET);
+    path.extend_from_slice(first.token_in.as_slice());
+
+    let mut token = first.token_in;
+    for hop in hops {
+        if hop.token_in != token || hop.fee >= 1 << (FEE_SIZE * 8) {
+            return Err(UniswapV3MathError::InvalidPath);
+        }
+
+        path.extend_from_slice(&hop.fee.to_be_bytes()[4 - FEE_SIZE..]);
+        path.extend_from_slice(hop.token_out.as_slice());
+        token = hop.token_out;
+    }
+
+    Ok(path)
+}
+
+// returns the hops of a packed path in the order they are encoded. Fails with `InvalidPath` unless the path is a
+// token followed by at least one fee and token
+pub fn decode_path(path: &[u8]) -> Result<Vec<Hop>, UniswapV3MathError> {
+    if path.len() < ADDR_SIZE + NEXT_OFFSET || (path.len() - ADDR_SIZE) % NEXT_OFFSET != 0 {
+        return Err(UniswapV3MathError::InvalidPath);
+    }
+
+    let hops = (0..(path.len() - ADDR_SIZE) / NEXT_OFFSET)
+        .map(|index| {
+            let hop = &path[index * NEXT_OFFSET..(index + 1) * NEXT_OFFSET + ADDR_SIZE];
+            let mut fee = [0u8; 4];
+            fee[4 - FEE_SIZE..].copy_from_slice(&hop[ADDR_SIZE..NEXT_OFFSET]);
+
+            Hop {
+                token_in: Address::from_slice(&hop[..ADDR_SIZE]),
+                token_out: Address::from_slice(&hop[NEXT_OFFSET..]),
+                fee: u32::from_be_bytes(fee),
+            }
+        })
+        .collect();
+
+    Ok(hops)
+}
+
+// returns the hops of the path of an exactOutput call in the order the tokens flow, from the input to the output token
+pub fn decode_exact_output_path(path: &[u8]) -> Result<Vec<Hop>, UniswapV3MathError> {
+    Ok(decode_path(path)?
+        .into_iter()
+        .rev()
+        .map(|hop| Hop::new(hop.token_out, hop.token_in, hop.fee))
+        .collect())
+}
+
+//A quote of a multi hop swap
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathQuote {
+    // the amount paid for the first hop and received from the last
+    pub amount_in: U256,
+    pub amount_out: U256,
+    // the result of the swap of each hop, in the order of the hops
+    pub results: Vec<SwapResult>,
+}
+
+// quotes a multi hop swap through the hops in the order the tokens flow, without price limits, like the router and
+// QuoterV2. Exact input hops are quoted from the first to the last, each selling the output of the one before it, and
+// exact output hops from the last to the first, each buying the input of the one after it. `pool` returns the pool of
+// a hop's key, fails with `PoolNotFound` if it returns `None`, and with `OutputNotFilled` if an exact output hop runs
+// out of liquidity, which the router reverts on
+pub fn quote_path<'a>(
+    hops: &[Hop],
+    amount: TradeAmount,
+    pool: impl Fn(&PoolKey) -> Option<&'a PoolSnapshot>,
+) -> Result<PathQuote, UniswapV3MathError> {
+    if hops.is_empty() {
+        return Err(UniswapV3MathError::InvalidPath);
+    }
+
+    let quote_hop = |hop: &Hop, amount: TradeAmount| {
+        let pool = pool(&hop.pool_key()).ok_or(UniswapV3MathError::PoolNotFound)?;
+        let params = hop.swap_params(amount)?.build()?;
+
+        let result = pool.quote_with(&params)?;
+        if matches!(amount, TradeAmount::ExactOutput(_))
+            && !result.amount_specified_remaining.is_zero()
+        {
+            return Err(UniswapV3MathError::OutputNotFilled);
+        }
+
+        let (amount_in, amount_out) =
+            amounts_in_out(params.zero_for_one, result.amount_0, result.amount_1);
+        Ok((amount_in, amount_out, result))
+    };
+
+    let mut results = Vec::with_capacity(hops.len());
+    let (amount_in, amount_out) = match amount {
+        TradeAmount::ExactInput(amount_in) => {
+            let mut amount = amount_in;
+            for hop in hops {
+                let (_, amount_out, result) = quote_hop(hop, TradeAmount::ExactInput(amount))?;
+                results.push(result);
+                amount = amount_out;
+            }
+            (amount_in, amount)
+        }
+        TradeAmount::ExactOutput(amount_out) => {
+            let mut amount = amount_out;
+            for hop in hops.iter().rev() {
+                let (amount_in, _, result) = quote_hop(hop, TradeAmount::ExactOutput(amount))?;
+                results.push(result);
+                amount = amount_in;
+            }
+            results.reverse();
+            (amount, amount_out)
+        }
+    };
+
+    Ok(PathQuote {
+        amount_in,
+        amount_out,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use alloy_primitives::{address, hex, Address, U256};
+
+    use super::{decode_exact_output_path, decode_path, encode_path, quote_path, Hop};
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        orientation::{amounts_in_out, TradeAmount},
+        pool::{PoolSnapshot, SwapParams},
+        pool_key::PoolKey,
+        test_utils::PoolBuilder,
+    };
+
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+
+    fn pool(fee_tier: FeeTier) -> PoolSnapshot {
+        PoolBuilder::at_tick(0)
+            .fee_tier(fee_tier)
+            .full_range(1e21 as i128)
+            .build()
+    }
+
+    #[test]
+    fn test_encode_decode_path() {
+        let hops = [Hop::new(WETH, USDC, 500), Hop::new(USDC, DAI, 100)];
+        let path = encode_path(&hops).unwrap();
+        assert_eq!(
+            path,
+            hex!(
+                "C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+                "0001f4"
+                "A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+                "000064"
+                "6B175474E89094C44Da98b954EedeAC495271d0F"
+            )
+        );
+        assert_eq!(decode_path(&path).unwrap(), hops);
+
+        //an exact output path is the reverse of the path of the same swap
+        let reversed = encode_path(&[Hop::new(DAI, USDC, 100), Hop::new(USDC, WETH, 500)]).unwrap();
+        assert_eq!(decode_exact_output_path(&reversed).unwrap(), hops);
+
+        //fails if the hops are not connected, the fee does not fit or the length is not that of a path
+        assert!(matches!(
+            encode_path(&[Hop::new(WETH, USDC, 500), Hop::new(WETH, DAI, 100)]),
+            Err(UniswapV3MathError::InvalidPath)
+        ));
+        assert!(matches!(
+            encode_path(&[Hop::new(WETH, USDC, 1 << 24)]),
+            Err(UniswapV3MathError::InvalidPath)
+        ));
+        assert!(matches!(
+            encode_path(&[]),
+            Err(UniswapV3MathError::InvalidPath)
+        ));
+        for len in [0, 20, 42, path.len() - 1] {
+            assert!(matches!(
+                decode_path(&path[..len]),
+                Err(UniswapV3MathError::InvalidPath)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_quote_path() {
+        let pools: HashMap<PoolKey, PoolSnapshot> = [
+            (PoolKey::new(WETH, USDC, 500), pool(FeeTier::Low)),
+            (PoolKey::new(USDC, DAI, 100), pool(FeeTier::Lowest)),
+        ]
+        .into_iter()
+        .collect();
+        let hops = decode_path(
+            &encode_path(&[Hop::new(WETH, USDC, 500), Hop::new(USDC, DAI, 100)]).unwrap(),
+        )
+        .unwrap();
+
+        //each hop sells the output of the one before it
+        let amount_in = U256::from(1e18 as u128);
+        let quote = quote_path(&hops, TradeAmount::ExactInput(amount_in), |key| {
+            pools.get(key)
+        })
+        .unwrap();
+        let mut amount = amount_in;
+        for (hop, result) in hops.iter().zip(&quote.results) {
+            let key = hop.pool_key();
+            let zero_for_one = hop.token_in == key.token_0;
+            let params = SwapParams::builder()
+                .zero_for_one(zero_for_one)
+                .amount_in(amount)
+                .build()
+                .unwrap();
+            assert_eq!(pools[&key].quote_with(&params).unwrap(), *result);
+            amount = amounts_in_out(zero_for_one, result.amount_0, result.amount_1).1;
+        }
+        assert_eq!(quote.amount_in, amount_in);
+        assert_eq!(quote.amount_out, amount);
+
+        //buying the output of the exact input quote needs about the same input
+        let exact_output = quote_path(&hops, TradeAmount::ExactOutput(quote.amount_out), |key| {
+            pools.get(key)
+        })
+        .unwrap();
+        assert_eq!(exact_output.amount_out, quote.amount_out);
+        assert!(exact_output.amount_in <= amount_in);
+        assert!(exact_output.amount_in > amount_in - U256::from(10));
+
+        //fails if a pool is missing or an exact output hop can not be filled
+        assert!(matches!(
+            quote_path(
+                &[Hop::new(WETH, DAI, 3000)],
+                TradeAmount::ExactInput(amount_in),
+                |key| { pools.get(key) }
+            ),
+            Err(UniswapV3MathError::PoolNotFound)
+        ));
+        assert!(matches!(
+            quote_path(
+                &hops,
+                TradeAmount::ExactOutput(U256::from(2e21 as u128)),
+                |key| { pools.get(key) }
+            ),
+            Err(UniswapV3MathError::OutputNotFilled)
+        ));
+    }
+}
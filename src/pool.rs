@@ -0,0 +1,2445 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    fee_tier::FeeTier,
+    full_math::{mul_div, mul_div_rounding_up, Q128},
+    hooks::SwapHooks,
+    liquidity_amounts::max_liquidity_for_fees,
+    liquidity_math::add_delta,
+    minimal_ticks::MinimalPoolSnapshot,
+    orientation::unbounded_sqrt_price_limit,
+    position::Position,
+    q_number::{LiquidityNet, SqrtPriceX96},
+    quote_cache::reach_hash,
+    sqrt_price_math::get_amounts_delta,
+    storage::{CowMap, InitializedTicks, TickStorage},
+    swap_math::compute_swap_step_with_fee_denominator,
+    tick::{self, Tick},
+    tick_array::TickArray,
+    tick_math::{
+        check_ticks, get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MAX_TICK,
+        MIN_SQRT_RATIO, MIN_TICK,
+    },
+    variant::{PoolVariant, UniswapV3},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Slot0 {
+    pub sqrt_price_x_96: U256,
+    pub tick: i32,
+    pub observation_index: u16,
+    pub observation_cardinality: u16,
+    pub observation_cardinality_next: u16,
+    // the protocol fee for token0 is stored in the lower 4 bits and the protocol fee for token1 in the upper 4 bits,
+    // each is the denominator of the fraction of the swap fee that is taken by the protocol. Forks with a wider
+    // encoding use the upper bits, see `PoolVariant::fee_protocol`
+    pub fee_protocol: u32,
+    pub unlocked: bool,
+}
+
+impl Slot0 {
+    pub fn fee_protocol_0(&self) -> u8 {
+        (self.fee_protocol % 16) as u8
+    }
+
+    pub fn fee_protocol_1(&self) -> u8 {
+        ((self.fee_protocol >> 4) % 16) as u8
+    }
+}
+
+// accumulated protocol fees in token0/token1 units
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProtocolFees {
+    pub token_0: u128,
+    pub token_1: u128,
+}
+
+//In memory copy of the state of a UniswapV3Pool that swaps can be simulated against. Ticks and tick bitmap words are
+//kept in HashMaps by default, see `PersistentPoolSnapshot` for pools that are cloned often. Forks with different fee
+//plumbing are simulated by swapping the variant, see `PoolVariant`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolSnapshot<T = HashMap<i32, Tick>, B = HashMap<i16, U256>, V = UniswapV3> {
+    pub slot0: Slot0,
+    pub liquidity: u128,
+    pub fee_tier: FeeTier,
+    pub fee_growth_global_0_x_128: U256,
+    pub fee_growth_global_1_x_128: U256,
+    pub protocol_fees: ProtocolFees,
+    pub ticks: T,
+    pub tick_bitmap: B,
+    pub variant: V,
+}
+
+//A pool snapshot backed by persistent maps, cloning it is O(1) regardless of the number of initialized ticks and a
+//write to a clone only copies the O(log n) trie nodes on the path to the tick or bitmap word it modifies
+pub type PersistentPoolSnapshot = PoolSnapshot<CowMap<i32, Tick>, CowMap<i16, U256>>;
+
+// quoting only requires a shared reference, so a single snapshot can be quoted from many threads without a lock
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<PoolSnapshot>();
+    assert_send_sync::<PersistentPoolSnapshot>();
+    assert_send_sync::<SwapSteps<'static>>();
+};
+
+// the result of a simulated swap, amounts are signed from the perspective of the pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapResult {
+    pub amount_0: I256,
+    pub amount_1: I256,
+    pub sqrt_price_x_96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    // the number of initialized ticks crossed by the swap
+    pub initialized_ticks_crossed: u32,
+    // the increase of the global fee growth of token0/token1, only the input token earns fees so the other is 0.
+    // Fees earned by a position are its in range liquidity times the growth inside its range, see `get_fee_growth_inside`
+    pub fee_growth_global_0_x_128_delta: U256,
+    pub fee_growth_global_1_x_128_delta: U256,
+    // the part of the amount specified that was not swapped, zero unless the swap was only partially filled
+    pub amount_specified_remaining: I256,
+    pub fill: SwapFill,
+    // the number of ticks the price moved through without active liquidity, see `ZeroLiquidity`
+    pub zero_liquidity_ticks: u32,
+    // the fees paid in the input token, including the protocol fee
+    pub fee_amount: U256,
+}
+
+//How much of the amount specified a swap filled, the contract executes partial swaps the same way and only reverts if
+//the caller does not pay for them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SwapFill {
+    // the whole amount specified was swapped
+    #[default]
+    Full,
+    // the swap stopped at the price limit while liquidity was still active
+    PriceLimit,
+    // the swap ran through the last active liquidity and stopped at the price limit without any liquidity left, a
+    // more distant price limit only fills more if there is liquidity beyond it
+    LiquidityExhausted,
+    // the swap was stopped early by a hook or at a range without liquidity, or is still being stepped through
+    Stopped,
+}
+
+// the arguments of a swap, see `PoolSnapshot::swap`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapParams {
+    pub zero_for_one: bool,
+    pub amount_specified: I256,
+    pub sqrt_price_limit_x_96: U256,
+    pub zero_liquidity: ZeroLiquidity,
+}
+
+//What a swap does when the price moves into a range without active liquidity. The contract moves the price through
+//such a range for free, which is right for quoting the contract but usually not what analytics want, e.g. a depth
+//calculation should not jump a thin long tail pool across an empty gap
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ZeroLiquidity {
+    // move through the gap to the next initialized tick like the contract
+    #[default]
+    Skip,
+    // end the swap at the start of the gap
+    Stop,
+    // fail with `LiquidityIsZero`
+    Error,
+}
+
+impl SwapParams {
+    // e.g. `SwapParams::builder().zero_for_one(true).amount_in(amount).build()`
+    pub fn builder() -> SwapParamsBuilder {
+        SwapParamsBuilder::default()
+    }
+}
+
+//Builds `SwapParams` from named arguments. The direction and the amount are required, the price limit defaults to
+//no limit in the direction of the swap
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapParamsBuilder {
+    zero_for_one: Option<bool>,
+    amount_specified: SwapAmount,
+    sqrt_price_limit_x_96: Option<U256>,
+    zero_liquidity: ZeroLiquidity,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum SwapAmount {
+    #[default]
+    Unset,
+    ExactInput(U256),
+    ExactOutput(U256),
+    Specified(I256),
+}
+
+impl SwapParamsBuilder {
+    pub fn zero_for_one(mut self, zero_for_one: bool) -> Self {
+        self.zero_for_one = Some(zero_for_one);
+        self
+    }
+
+    // swaps exactly `amount_in` of the input token, including the fee
+    pub fn amount_in(mut self, amount_in: U256) -> Self {
+        self.amount_specified = SwapAmount::ExactInput(amount_in);
+        self
+    }
+
+    // swaps for exactly `amount_out` of the output token
+    pub fn amount_out(mut self, amount_out: U256) -> Self {
+        self.amount_specified = SwapAmount::ExactOutput(amount_out);
+        self
+    }
+
+    // the signed amount like UniswapV3Pool.swap takes it, positive for exact input and negative for exact output
+    pub fn amount_specified(mut self, amount_specified: I256) -> Self {
+        self.amount_specified = SwapAmount::Specified(amount_specified);
+        self
+    }
+
+    pub fn sqrt_price_limit(mut self, sqrt_price_limit_x_96: impl Into<SqrtPriceX96>) -> Self {
+        self.sqrt_price_limit_x_96 = Some(sqrt_price_limit_x_96.into().0);
+        self
+    }
+
+    // defaults to `ZeroLiquidity::Skip`
+    pub fn zero_liquidity(mut self, zero_liquidity: ZeroLiquidity) -> Self {
+        self.zero_liquidity = zero_liquidity;
+        self
+    }
+
+    // fails if the direction or the amount is missing, the amount is zero or does not fit an int256
+    pub fn build(self) -> Result<SwapParams, UniswapV3MathError> {
+        let zero_for_one = self
+            .zero_for_one
+            .ok_or(UniswapV3MathError::SwapDirectionNotSet)?;
+
+        let amount_specified = match self.amount_specified {
+            SwapAmount::Unset => I256::ZERO,
+            SwapAmount::ExactInput(amount_in) => {
+                I256::try_from(amount_in).map_err(|_| UniswapV3MathError::Overflow)?
+            }
+            SwapAmount::ExactOutput(amount_out) => {
+                -I256::try_from(amount_out).map_err(|_| UniswapV3MathError::Overflow)?
+            }
+            SwapAmount::Specified(amount_specified) => amount_specified,
+        };
+        if amount_specified.is_zero() {
+            return Err(UniswapV3MathError::AS);
+        }
+
+        let sqrt_price_limit_x_96 = self
+            .sqrt_price_limit_x_96
+            .unwrap_or_else(|| unbounded_sqrt_price_limit(zero_for_one));
+
+        Ok(SwapParams {
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x_96,
+            zero_liquidity: self.zero_liquidity,
+        })
+    }
+}
+
+// the top level state of the swap, the results of which are recorded in storage at the end
+pub(crate) struct SwapState {
+    amount_specified_remaining: I256,
+    sqrt_price_limit_x_96: U256,
+    amount_calculated: I256,
+    sqrt_price_x_96: U256,
+    tick: i32,
+    fee_growth_global_x_128: U256,
+    // the global fee growth of the input token before the swap
+    fee_growth_global_start_x_128: U256,
+    // the number of ticks moved through without active liquidity
+    zero_liquidity_ticks: u32,
+    // the fees paid in the input token, including the protocol fee
+    fee_amount: U256,
+    protocol_fee: u128,
+    liquidity: u128,
+    // ticks crossed during the swap along with the global fee growth of the input token at the time of the cross
+    pub(crate) crossed_ticks: Vec<(i32, U256)>,
+}
+
+impl PoolSnapshot {
+    // returns an empty pool initialized at the given price, mirrors UniswapV3Pool.initialize
+    pub fn new(
+        sqrt_price_x_96: impl Into<SqrtPriceX96>,
+        fee_tier: FeeTier,
+    ) -> Result<Self, UniswapV3MathError> {
+        let sqrt_price_x_96 = sqrt_price_x_96.into().0;
+        let tick = get_tick_at_sqrt_ratio(sqrt_price_x_96)?;
+
+        Ok(Self {
+            slot0: Slot0 {
+                sqrt_price_x_96,
+                tick,
+                observation_cardinality: 1,
+                observation_cardinality_next: 1,
+                unlocked: true,
+                ..Default::default()
+            },
+            liquidity: 0,
+            fee_tier,
+            fee_growth_global_0_x_128: U256::ZERO,
+            fee_growth_global_1_x_128: U256::ZERO,
+            protocol_fees: ProtocolFees::default(),
+            ticks: HashMap::new(),
+            tick_bitmap: HashMap::new(),
+            variant: UniswapV3,
+        })
+    }
+}
+
+impl<T, B, V> PoolSnapshot<T, B, V> {
+    // returns the pool with its fee plumbing replaced by the given fork variant
+    pub fn with_variant<W: PoolVariant>(self, variant: W) -> PoolSnapshot<T, B, W> {
+        PoolSnapshot {
+            slot0: self.slot0,
+            liquidity: self.liquidity,
+            fee_tier: self.fee_tier,
+            fee_growth_global_0_x_128: self.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: self.fee_growth_global_1_x_128,
+            protocol_fees: self.protocol_fees,
+            ticks: self.ticks,
+            tick_bitmap: self.tick_bitmap,
+            variant,
+        }
+    }
+}
+
+impl<T: TickStorage, B: InitializedTicks, V: PoolVariant> PoolSnapshot<T, B, V> {
+    // simulates a swap without modifying the pool state
+    pub fn quote(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: impl Into<SqrtPriceX96>,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let sqrt_price_limit_x_96 = sqrt_price_limit_x_96.into().0;
+        let state = self.compute_swap(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+        Ok(swap_result(zero_for_one, amount_specified, &state))
+    }
+
+    // same as quote, with the arguments given as `SwapParams`
+    pub fn quote_with(&self, params: &SwapParams) -> Result<SwapResult, UniswapV3MathError> {
+        let state = self.compute_swap_with(params, &mut ())?;
+        Ok(swap_result(
+            params.zero_for_one,
+            params.amount_specified,
+            &state,
+        ))
+    }
+
+    // swaps token0 for token1, or token1 for token0 and updates the pool state, mirrors UniswapV3Pool.swap
+    pub fn swap(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: impl Into<SqrtPriceX96>,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let sqrt_price_limit_x_96 = sqrt_price_limit_x_96.into().0;
+        let state = self.compute_swap(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+        self.commit_swap(zero_for_one, &state);
+
+        Ok(swap_result(zero_for_one, amount_specified, &state))
+    }
+
+    // same as swap, with the arguments given as `SwapParams`
+    pub fn swap_with(&mut self, params: &SwapParams) -> Result<SwapResult, UniswapV3MathError> {
+        let state = self.compute_swap_with(params, &mut ())?;
+        self.commit_swap(params.zero_for_one, &state);
+
+        Ok(swap_result(
+            params.zero_for_one,
+            params.amount_specified,
+            &state,
+        ))
+    }
+
+    // same as swap, calling `hooks` on every step and tick cross. If a hook stops the swap early, the pool state is
+    // updated up to the last step
+    pub fn swap_with_hooks(
+        &mut self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: impl Into<SqrtPriceX96>,
+        hooks: &mut impl SwapHooks,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let sqrt_price_limit_x_96 = sqrt_price_limit_x_96.into().0;
+        let state = self.compute_swap_with_hooks(
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x_96,
+            hooks,
+        )?;
+        self.commit_swap(zero_for_one, &state);
+
+        Ok(swap_result(zero_for_one, amount_specified, &state))
+    }
+
+    // writes the result of a computed swap to the pool state
+    pub(crate) fn commit_swap(&mut self, zero_for_one: bool, state: &SwapState) {
+        for &(tick, fee_growth_global_x_128) in &state.crossed_ticks {
+            if zero_for_one {
+                tick::cross(
+                    &mut self.ticks,
+                    tick,
+                    fee_growth_global_x_128,
+                    self.fee_growth_global_1_x_128,
+                );
+            } else {
+                tick::cross(
+                    &mut self.ticks,
+                    tick,
+                    self.fee_growth_global_0_x_128,
+                    fee_growth_global_x_128,
+                );
+            }
+        }
+
+        self.slot0.sqrt_price_x_96 = state.sqrt_price_x_96;
+        self.slot0.tick = state.tick;
+        self.liquidity = state.liquidity;
+
+        // update fee growth global and, if necessary, protocol fees
+        if zero_for_one {
+            self.fee_growth_global_0_x_128 = state.fee_growth_global_x_128;
+            self.protocol_fees.token_0 =
+                self.protocol_fees.token_0.wrapping_add(state.protocol_fee);
+        } else {
+            self.fee_growth_global_1_x_128 = state.fee_growth_global_x_128;
+            self.protocol_fees.token_1 =
+                self.protocol_fees.token_1.wrapping_add(state.protocol_fee);
+        }
+    }
+
+    // adds or removes liquidity from the given range and returns the amounts of token0/token1 owed to the pool,
+    // negative when removing liquidity, mirrors UniswapV3Pool._modifyPosition without tracking the position itself.
+    // The snapshot does not track the oracle, so newly initialized ticks do not record seconds or tick cumulatives.
+    // Both ticks are updated on a staged copy and written only once every check passed, a failing call leaves the pool
+    // as it was. Fails for tick storages without the gross liquidity of ticks, see `MinimalTicks`
+    pub fn modify_position(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: impl Into<LiquidityNet>,
+    ) -> Result<(I256, I256), UniswapV3MathError> {
+        let liquidity_delta = liquidity_delta.into().0;
+        check_ticks(tick_lower, tick_upper)?;
+
+        let amounts = self.position_amounts(tick_lower, tick_upper, liquidity_delta)?;
+        let liquidity = if self.slot0.tick >= tick_lower && self.slot0.tick < tick_upper {
+            add_delta(self.liquidity, liquidity_delta)?
+        } else {
+            self.liquidity
+        };
+
+        if liquidity_delta != 0 {
+            // without the gross liquidity the update can not tell when a tick flips
+            if !T::LIQUIDITY_GROSS {
+                return Err(UniswapV3MathError::LiquidityGrossNotStored);
+            }
+            let max_liquidity = self.fee_tier.max_liquidity_per_tick()?;
+            let tick_spacing = self.fee_tier.tick_spacing();
+            if tick_lower % tick_spacing != 0 || tick_upper % tick_spacing != 0 {
+                return Err(UniswapV3MathError::TickSpacingError);
+            }
+
+            #[cfg(feature = "debug-invariants")]
+            for tick in [tick_lower, tick_upper] {
+                crate::invariants::check_tick(&self.ticks, &self.tick_bitmap, tick, tick_spacing)?;
+            }
+
+            let mut staged = [tick_lower, tick_upper]
+                .into_iter()
+                .filter_map(|tick| Some((tick, self.ticks.get(&tick)?)))
+                .collect::<HashMap<_, _>>();
+            let mut flipped = [false; 2];
+            for (i, (tick, upper)) in [(tick_lower, false), (tick_upper, true)]
+                .into_iter()
+                .enumerate()
+            {
+                flipped[i] = tick::update(
+                    &mut staged,
+                    tick,
+                    self.slot0.tick,
+                    liquidity_delta,
+                    self.fee_growth_global_0_x_128,
+                    self.fee_growth_global_1_x_128,
+                    U256::ZERO,
+                    U256::ZERO,
+                    0,
+                    upper,
+                    Some(max_liquidity),
+                )?;
+            }
+
+            for (tick, flipped) in [tick_lower, tick_upper].into_iter().zip(flipped) {
+                if flipped {
+                    self.tick_bitmap.flip_tick(tick, tick_spacing)?;
+                }
+                // clear any tick data that is no longer needed
+                if flipped && liquidity_delta < 0 {
+                    tick::clear(&mut self.ticks, tick);
+                } else {
+                    self.ticks.insert(tick, staged[&tick]);
+                }
+            }
+
+            #[cfg(feature = "debug-invariants")]
+            for tick in [tick_lower, tick_upper] {
+                crate::invariants::check_tick(&self.ticks, &self.tick_bitmap, tick, tick_spacing)?;
+            }
+        }
+
+        self.liquidity = liquidity;
+
+        Ok(amounts)
+    }
+
+    // returns the amounts of token0/token1 owed to the pool for adding `liquidity_delta` to the given range at the
+    // current price, negative when removing liquidity. Rounds up when adding and down when removing liquidity
+    fn position_amounts(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    ) -> Result<(I256, I256), UniswapV3MathError> {
+        // branches on the price rather than the tick like the contract, see `get_amounts_delta`
+        get_amounts_delta(
+            self.slot0.sqrt_price_x_96,
+            get_sqrt_ratio_at_tick(tick_lower)?,
+            get_sqrt_ratio_at_tick(tick_upper)?,
+            liquidity_delta,
+        )
+    }
+
+    // borrows the amounts in a flash loan that is repaid with exactly its fee, and credits the fee to the in range
+    // liquidity minus the protocol's share, mirrors UniswapV3Pool.flash. Paying the fee changes the fee growth but not
+    // the price or liquidity, so this is how borrowing from the pool itself affects LPs
+    pub fn flash(
+        &mut self,
+        amount_0: U256,
+        amount_1: U256,
+    ) -> Result<FlashResult, UniswapV3MathError> {
+        if self.liquidity == 0 {
+            return Err(UniswapV3MathError::LiquidityIsZero);
+        }
+
+        let fee = U256::from(self.fee_tier.fee());
+        let fee_denominator = U256::from(self.variant.fee_denominator());
+        let mut result = FlashResult {
+            fee_0: mul_div_rounding_up(amount_0, fee, fee_denominator)?,
+            fee_1: mul_div_rounding_up(amount_1, fee, fee_denominator)?,
+            ..Default::default()
+        };
+
+        // the fees paid are the fees owed, as the loan is repaid with exactly its fee
+        let liquidity = U256::from(self.liquidity);
+        if result.fee_0 > U256::ZERO {
+            let fee_protocol = self.variant.fee_protocol(&self.slot0, true);
+            let protocol_fee = self.variant.protocol_fee(result.fee_0, fee_protocol);
+            result.protocol_fee_0 = protocol_fee.wrapping_to::<u128>();
+            result.fee_growth_global_0_x_128_delta =
+                mul_div(result.fee_0 - protocol_fee, Q128, liquidity)?;
+
+            self.protocol_fees.token_0 = self
+                .protocol_fees
+                .token_0
+                .wrapping_add(result.protocol_fee_0);
+            self.fee_growth_global_0_x_128 = self
+                .fee_growth_global_0_x_128
+                .wrapping_add(result.fee_growth_global_0_x_128_delta);
+        }
+        if result.fee_1 > U256::ZERO {
+            let fee_protocol = self.variant.fee_protocol(&self.slot0, false);
+            let protocol_fee = self.variant.protocol_fee(result.fee_1, fee_protocol);
+            result.protocol_fee_1 = protocol_fee.wrapping_to::<u128>();
+            result.fee_growth_global_1_x_128_delta =
+                mul_div(result.fee_1 - protocol_fee, Q128, liquidity)?;
+
+            self.protocol_fees.token_1 = self
+                .protocol_fees
+                .token_1
+                .wrapping_add(result.protocol_fee_1);
+            self.fee_growth_global_1_x_128 = self
+                .fee_growth_global_1_x_128
+                .wrapping_add(result.fee_growth_global_1_x_128_delta);
+        }
+
+        Ok(result)
+    }
+
+    // returns a hash of the state quotes depend on: the price, tick and active liquidity, the fee and protocol fees
+    // and every initialized tick with its liquidity net, walked from the current tick the way swaps walk the bitmap.
+    // Fee growth, protocol fees owed and the oracle do not affect quotes and are left out, so the fingerprint only
+    // changes if a quote could change, e.g. as a cache key or to detect updates that matter. The hash is stable for a
+    // build of the crate but not across releases, so it should not be persisted
+    pub fn fingerprint(&self) -> Result<u64, UniswapV3MathError> {
+        let mut hasher = DefaultHasher::new();
+        (
+            self.slot0.sqrt_price_x_96,
+            self.slot0.tick,
+            self.liquidity,
+            self.fee_tier.fee(),
+            self.variant.fee_protocol(&self.slot0, true),
+            self.variant.fee_protocol(&self.slot0, false),
+        )
+            .hash(&mut hasher);
+        (
+            reach_hash(self, true, MIN_TICK)?,
+            reach_hash(self, false, MAX_TICK)?,
+        )
+            .hash(&mut hasher);
+
+        Ok(hasher.finish())
+    }
+
+    // same as quote, calling `hooks` on every step and tick cross, the result covers the steps up to the hook that
+    // stopped the swap, if any
+    pub fn quote_with_hooks(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: impl Into<SqrtPriceX96>,
+        hooks: &mut impl SwapHooks,
+    ) -> Result<SwapResult, UniswapV3MathError> {
+        let state = self.compute_swap_with_hooks(
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x_96.into().0,
+            hooks,
+        )?;
+
+        Ok(swap_result(zero_for_one, amount_specified, &state))
+    }
+
+    // returns the input amount, including fees, required to move the price to `sqrt_price_target_x_96`,
+    // crossing any initialized ticks in between
+    pub fn get_amount_in_to_target_price(
+        &self,
+        sqrt_price_target_x_96: impl Into<SqrtPriceX96>,
+    ) -> Result<U256, UniswapV3MathError> {
+        let sqrt_price_target_x_96 = sqrt_price_target_x_96.into().0;
+        if sqrt_price_target_x_96 == self.slot0.sqrt_price_x_96 {
+            return Ok(U256::ZERO);
+        }
+
+        // an unbounded exact input swap stops at the price limit with exactly the input needed to get there
+        let zero_for_one = sqrt_price_target_x_96 < self.slot0.sqrt_price_x_96;
+        let state = self.compute_swap(zero_for_one, I256::MAX, sqrt_price_target_x_96)?;
+
+        Ok((I256::MAX - state.amount_specified_remaining).into_raw())
+    }
+
+    // returns an iterator over the steps of a swap without modifying the pool state, each step swaps up to the next
+    // initialized tick, the end of the current tick bitmap word, the price limit or until the amount is exhausted
+    pub fn swap_steps(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: impl Into<SqrtPriceX96>,
+    ) -> Result<SwapSteps<'_, T, B, V>, UniswapV3MathError> {
+        let sqrt_price_limit_x_96 = sqrt_price_limit_x_96.into().0;
+        if amount_specified.is_zero() {
+            return Err(UniswapV3MathError::AS);
+        }
+
+        let slot0_start = self.slot0;
+
+        if zero_for_one {
+            if !(sqrt_price_limit_x_96 < slot0_start.sqrt_price_x_96
+                && sqrt_price_limit_x_96 > MIN_SQRT_RATIO)
+            {
+                return Err(UniswapV3MathError::SPL);
+            }
+        } else if !(sqrt_price_limit_x_96 > slot0_start.sqrt_price_x_96
+            && sqrt_price_limit_x_96 < MAX_SQRT_RATIO)
+        {
+            return Err(UniswapV3MathError::SPL);
+        }
+
+        let fee_protocol = self.variant.fee_protocol(&slot0_start, zero_for_one);
+        let fee_growth_global_x_128 = if zero_for_one {
+            self.fee_growth_global_0_x_128
+        } else {
+            self.fee_growth_global_1_x_128
+        };
+
+        Ok(SwapSteps {
+            pool: self,
+            zero_for_one,
+            amount_specified,
+            fee_protocol,
+            zero_liquidity: ZeroLiquidity::Skip,
+            state: SwapState {
+                amount_specified_remaining: amount_specified,
+                sqrt_price_limit_x_96,
+                amount_calculated: I256::ZERO,
+                sqrt_price_x_96: slot0_start.sqrt_price_x_96,
+                tick: slot0_start.tick,
+                fee_growth_global_x_128,
+                fee_growth_global_start_x_128: fee_growth_global_x_128,
+                zero_liquidity_ticks: 0,
+                fee_amount: U256::ZERO,
+                protocol_fee: 0,
+                liquidity: self.liquidity,
+                crossed_ticks: vec![],
+            },
+            failed: false,
+        })
+    }
+
+    fn compute_swap_with_hooks(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: U256,
+        hooks: &mut impl SwapHooks,
+    ) -> Result<SwapState, UniswapV3MathError> {
+        let params = SwapParams {
+            zero_for_one,
+            amount_specified,
+            sqrt_price_limit_x_96,
+            zero_liquidity: ZeroLiquidity::Skip,
+        };
+        self.compute_swap_with(&params, hooks)
+    }
+
+    pub(crate) fn compute_swap_with(
+        &self,
+        params: &SwapParams,
+        hooks: &mut impl SwapHooks,
+    ) -> Result<SwapState, UniswapV3MathError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "swap",
+            zero_for_one = params.zero_for_one,
+            amount_specified = %params.amount_specified,
+            sqrt_price_limit_x_96 = %params.sqrt_price_limit_x_96,
+        )
+        .entered();
+
+        let mut steps = self
+            .swap_steps(
+                params.zero_for_one,
+                params.amount_specified,
+                params.sqrt_price_limit_x_96,
+            )?
+            .zero_liquidity(params.zero_liquidity);
+        for step in &mut steps {
+            let step = step?;
+            if hooks.on_step(&step).is_break() {
+                break;
+            }
+            if let Some(tick) = step.tick_crossed {
+                if hooks.on_tick_cross(tick, &step).is_break() {
+                    break;
+                }
+            }
+        }
+
+        Ok(steps.state)
+    }
+
+    pub(crate) fn compute_swap(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x_96: U256,
+    ) -> Result<SwapState, UniswapV3MathError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "swap",
+            zero_for_one,
+            amount_specified = %amount_specified,
+            sqrt_price_limit_x_96 = %sqrt_price_limit_x_96,
+        )
+        .entered();
+
+        let mut steps = self.swap_steps(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+        for step in &mut steps {
+            step?;
+        }
+
+        Ok(steps.state)
+    }
+}
+
+// returns the state of `pool` after the swap along with the result of the swap, as a pure function of the state and the
+// arguments. The swap is computed against `pool` and only applied to a clone of it if it succeeds, with a
+// `PersistentPoolSnapshot` the new state shares every trie node the swap did not write to with the old one, so
+// states can be kept around cheaply, e.g. as the nodes of a search
+pub fn swap<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    params: &SwapParams,
+) -> Result<(PoolSnapshot<T, B, V>, SwapResult), UniswapV3MathError>
+where
+    T: TickStorage + Clone,
+    B: InitializedTicks + Clone,
+    V: PoolVariant + Clone,
+{
+    let state = pool.compute_swap_with(params, &mut ())?;
+    let mut next = pool.clone();
+    next.commit_swap(params.zero_for_one, &state);
+
+    Ok((
+        next,
+        swap_result(params.zero_for_one, params.amount_specified, &state),
+    ))
+}
+
+// returns the amounts of token0/token1 the mint callback will be asked for when minting `liquidity` in the given range,
+// rounded up exactly like UniswapV3Pool.mint. Approving less than this, e.g. amounts computed with
+// `get_amounts_for_liquidity`, which rounds down, makes the mint revert
+pub fn preview_mint<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+) -> Result<(U256, U256), UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    check_ticks(tick_lower, tick_upper)?;
+    if liquidity == 0 {
+        return Err(UniswapV3MathError::LiquidityIsZero);
+    }
+
+    let liquidity_delta = i128::try_from(liquidity).map_err(|_| UniswapV3MathError::Overflow)?;
+    let (amount_0, amount_1) = pool.position_amounts(tick_lower, tick_upper, liquidity_delta)?;
+
+    Ok((amount_0.into_raw(), amount_1.into_raw()))
+}
+
+// returns the largest liquidity that can be minted in the given range without the mint callback asking for more than
+// `amount_0_max`/`amount_1_max`, the inverse of `preview_mint`
+pub fn liquidity_for_max_amounts<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    tick_lower: i32,
+    tick_upper: i32,
+    amount_0_max: U256,
+    amount_1_max: U256,
+) -> Result<u128, UniswapV3MathError> {
+    check_ticks(tick_lower, tick_upper)?;
+    let (liquidity, _, _) = max_liquidity_for_fees(
+        pool.slot0.sqrt_price_x_96,
+        tick_lower,
+        tick_upper,
+        amount_0_max,
+        amount_1_max,
+    )?;
+
+    Ok(liquidity)
+}
+
+//A range of the depth profile minted into a new pool, see `initialize`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepthRange {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+}
+
+impl DepthRange {
+    pub fn new(tick_lower: i32, tick_upper: i32, liquidity: u128) -> Self {
+        Self {
+            tick_lower,
+            tick_upper,
+            liquidity,
+        }
+    }
+}
+
+//A new pool with its starting depth profile minted, see `initialize`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitializedPool {
+    pub pool: PoolSnapshot,
+    // the amounts of token0/token1 the mint callback is asked for by each range, in the order of the ranges
+    pub amounts: Vec<(U256, U256)>,
+    // the total amounts of token0/token1 needed to mint every range
+    pub amount_0: U256,
+    pub amount_1: U256,
+}
+
+// returns a pool initialized at the given price with every range of the depth profile minted in order, along with the
+// amounts each mint needs, e.g. to script the launch of a pool. The ticks of every range must be aligned to the tick
+// spacing of the fee tier and the liquidity gross of every tick, summed over the ranges that reference it, must not
+// exceed the max liquidity per tick, which fail with `TickSpacingError` and `LiquidityOverflow`
+pub fn initialize(
+    sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    fee_tier: FeeTier,
+    ranges: &[DepthRange],
+) -> Result<InitializedPool, UniswapV3MathError> {
+    let mut pool = PoolSnapshot::new(sqrt_price_x_96, fee_tier)?;
+    let tick_spacing = fee_tier.tick_spacing();
+
+    let mut amounts = Vec::with_capacity(ranges.len());
+    let (mut amount_0, mut amount_1) = (U256::ZERO, U256::ZERO);
+    for range in ranges {
+        check_ticks(range.tick_lower, range.tick_upper)?;
+        if range.tick_lower % tick_spacing != 0 || range.tick_upper % tick_spacing != 0 {
+            return Err(UniswapV3MathError::TickSpacingError);
+        }
+
+        let (amount_0_range, amount_1_range) =
+            preview_mint(&pool, range.tick_lower, range.tick_upper, range.liquidity)?;
+        pool.modify_position(
+            range.tick_lower,
+            range.tick_upper,
+            i128::try_from(range.liquidity).map_err(|_| UniswapV3MathError::Overflow)?,
+        )?;
+
+        amount_0 = amount_0
+            .checked_add(amount_0_range)
+            .ok_or(UniswapV3MathError::Overflow)?;
+        amount_1 = amount_1
+            .checked_add(amount_1_range)
+            .ok_or(UniswapV3MathError::Overflow)?;
+        amounts.push((amount_0_range, amount_1_range));
+    }
+
+    Ok(InitializedPool {
+        pool,
+        amounts,
+        amount_0,
+        amount_1,
+    })
+}
+
+//The fees of a flash loan and how they were split between the protocol and the in range liquidity, see
+//`PoolSnapshot::flash`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlashResult {
+    // the fees owed on top of the amounts borrowed, rounded up like the contract
+    pub fee_0: U256,
+    pub fee_1: U256,
+    pub protocol_fee_0: u128,
+    pub protocol_fee_1: u128,
+    // the increase of the global fee growth, the fees earned by LPs per unit of in range liquidity
+    pub fee_growth_global_0_x_128_delta: U256,
+    pub fee_growth_global_1_x_128_delta: U256,
+}
+
+//The tokens released by burning liquidity from a position, see `preview_burn`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BurnPreview {
+    // the principal of the burned liquidity, rounded down like the amounts of the Burn event
+    pub amount_0: U256,
+    pub amount_1: U256,
+    // the fees owed to the position after the burn, the tokens already owed plus the fees accrued since the position
+    // was last updated
+    pub fees_0: u128,
+    pub fees_1: u128,
+}
+
+impl BurnPreview {
+    // returns the amounts of the Collect event when collecting everything owed after the burn, the principal is added
+    // to the tokens owed as a uint128 like in UniswapV3Pool.burn
+    pub fn collect_amounts(&self) -> (u128, u128) {
+        (
+            self.fees_0
+                .wrapping_add(self.amount_0.wrapping_to::<u128>()),
+            self.fees_1
+                .wrapping_add(self.amount_1.wrapping_to::<u128>()),
+        )
+    }
+}
+
+// returns the principal and fees released by burning `liquidity` from `position` in the given range, exactly like
+// UniswapV3Pool.burn followed by collecting everything owed. A `liquidity` of 0 previews a poke, which only credits
+// the fees
+pub fn preview_burn<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    tick_lower: i32,
+    tick_upper: i32,
+    position: &Position,
+    liquidity: u128,
+) -> Result<BurnPreview, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    check_ticks(tick_lower, tick_upper)?;
+    let liquidity_delta = -i128::try_from(liquidity).map_err(|_| UniswapV3MathError::Overflow)?;
+
+    let (fee_growth_inside_0_x_128, fee_growth_inside_1_x_128) = tick::get_fee_growth_inside(
+        &pool.ticks,
+        tick_lower,
+        tick_upper,
+        pool.slot0.tick,
+        pool.fee_growth_global_0_x_128,
+        pool.fee_growth_global_1_x_128,
+    );
+    let mut position = *position;
+    position.update(
+        liquidity_delta,
+        fee_growth_inside_0_x_128,
+        fee_growth_inside_1_x_128,
+    )?;
+
+    let (amount_0, amount_1) = pool.position_amounts(tick_lower, tick_upper, liquidity_delta)?;
+
+    Ok(BurnPreview {
+        amount_0: amount_0.unsigned_abs(),
+        amount_1: amount_1.unsigned_abs(),
+        fees_0: position.tokens_owed_0,
+        fees_1: position.tokens_owed_1,
+    })
+}
+
+impl From<PoolSnapshot> for PersistentPoolSnapshot {
+    fn from(pool: PoolSnapshot) -> Self {
+        Self {
+            slot0: pool.slot0,
+            liquidity: pool.liquidity,
+            fee_tier: pool.fee_tier,
+            fee_growth_global_0_x_128: pool.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: pool.fee_growth_global_1_x_128,
+            protocol_fees: pool.protocol_fees,
+            ticks: pool.ticks.into_iter().collect(),
+            tick_bitmap: pool.tick_bitmap.into_iter().collect(),
+            variant: pool.variant,
+        }
+    }
+}
+
+impl From<PoolSnapshot> for PoolSnapshot<TickArray> {
+    fn from(pool: PoolSnapshot) -> Self {
+        Self {
+            slot0: pool.slot0,
+            liquidity: pool.liquidity,
+            fee_tier: pool.fee_tier,
+            fee_growth_global_0_x_128: pool.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: pool.fee_growth_global_1_x_128,
+            protocol_fees: pool.protocol_fees,
+            ticks: pool.ticks.into_iter().collect(),
+            tick_bitmap: pool.tick_bitmap,
+            variant: pool.variant,
+        }
+    }
+}
+
+impl From<PoolSnapshot> for MinimalPoolSnapshot {
+    fn from(pool: PoolSnapshot) -> Self {
+        Self {
+            slot0: pool.slot0,
+            liquidity: pool.liquidity,
+            fee_tier: pool.fee_tier,
+            fee_growth_global_0_x_128: pool.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: pool.fee_growth_global_1_x_128,
+            protocol_fees: pool.protocol_fees,
+            ticks: pool.ticks.into_iter().collect(),
+            tick_bitmap: pool.tick_bitmap,
+            variant: pool.variant,
+        }
+    }
+}
+
+impl From<PersistentPoolSnapshot> for PoolSnapshot {
+    fn from(pool: PersistentPoolSnapshot) -> Self {
+        Self {
+            slot0: pool.slot0,
+            liquidity: pool.liquidity,
+            fee_tier: pool.fee_tier,
+            fee_growth_global_0_x_128: pool.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: pool.fee_growth_global_1_x_128,
+            protocol_fees: pool.protocol_fees,
+            ticks: pool
+                .ticks
+                .iter()
+                .map(|(&tick, &info)| (tick, info))
+                .collect(),
+            tick_bitmap: pool
+                .tick_bitmap
+                .iter()
+                .map(|(&word_pos, &word)| (word_pos, word))
+                .collect(),
+            variant: pool.variant,
+        }
+    }
+}
+
+//A single step of a swap, see `PoolSnapshot::swap_steps`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapStep {
+    pub tick_before: i32,
+    pub tick_after: i32,
+    pub sqrt_price_before_x_96: U256,
+    pub sqrt_price_after_x_96: U256,
+    // the input amount excluding the fee
+    pub amount_in: U256,
+    pub amount_out: U256,
+    // the fee paid in the input token, including the protocol fee
+    pub fee_amount: U256,
+    // the increase of the global fee growth of the input token, the fee net of the protocol fee per unit of liquidity
+    pub fee_growth_global_x_128_delta: U256,
+    // the liquidity that was active during the step
+    pub liquidity: u128,
+    // the initialized tick crossed at the end of the step, if any
+    pub tick_crossed: Option<i32>,
+}
+
+//Lazily computes the steps of a swap against a pool snapshot, yields an error at most once and then stops
+pub struct SwapSteps<'a, T = HashMap<i32, Tick>, B = HashMap<i16, U256>, V = UniswapV3> {
+    pool: &'a PoolSnapshot<T, B, V>,
+    zero_for_one: bool,
+    amount_specified: I256,
+    fee_protocol: u32,
+    zero_liquidity: ZeroLiquidity,
+    state: SwapState,
+    failed: bool,
+}
+
+impl<T: TickStorage, B: InitializedTicks, V: PoolVariant> SwapSteps<'_, T, B, V> {
+    // returns the result of the swap up to the last yielded step
+    pub fn result(&self) -> SwapResult {
+        swap_result(self.zero_for_one, self.amount_specified, &self.state)
+    }
+
+    // sets what happens when the swap moves into a range without liquidity, defaults to `ZeroLiquidity::Skip`
+    pub fn zero_liquidity(mut self, zero_liquidity: ZeroLiquidity) -> Self {
+        self.zero_liquidity = zero_liquidity;
+        self
+    }
+
+    // computes the first step towards the boundary of the current tick or the price limit, whichever is closer, and
+    // returns it if it stops short of its target. Initialized ticks are integer ticks, so such a step can not cross one
+    // and the price stays within the current tick, and the amounts of a step that stops short of its target do not
+    // depend on the target, so it is the step the general path computes without the bitmap lookup and the tick
+    // recomputation. Most quotes are small enough to end here
+    fn step_within_tick(&self) -> Result<Option<(U256, U256, U256, U256)>, UniswapV3MathError> {
+        let state = &self.state;
+        if state.liquidity == 0
+            || state.sqrt_price_x_96 != self.pool.slot0.sqrt_price_x_96
+            || !(MIN_TICK..MAX_TICK).contains(&state.tick)
+        {
+            return Ok(None);
+        }
+
+        let sqrt_price_boundary_x_96 = if self.zero_for_one {
+            get_sqrt_ratio_at_tick(state.tick)?.max(state.sqrt_price_limit_x_96)
+        } else {
+            get_sqrt_ratio_at_tick(state.tick + 1)?.min(state.sqrt_price_limit_x_96)
+        };
+        // on the lower boundary of the current tick the tick itself is the next one
+        if sqrt_price_boundary_x_96 == state.sqrt_price_x_96 {
+            return Ok(None);
+        }
+        let step = compute_swap_step_with_fee_denominator(
+            state.sqrt_price_x_96,
+            sqrt_price_boundary_x_96,
+            state.liquidity,
+            state.amount_specified_remaining,
+            self.pool.fee_tier.fee(),
+            self.pool.variant.fee_denominator(),
+        )?;
+
+        Ok((step.0 != sqrt_price_boundary_x_96).then_some(step))
+    }
+
+    fn step(&mut self) -> Result<SwapStep, UniswapV3MathError> {
+        let pool = self.pool;
+        let zero_for_one = self.zero_for_one;
+        let exact_input = self.amount_specified > I256::ZERO;
+        let within_tick = self.step_within_tick()?;
+        let state = &mut self.state;
+
+        let tick_before = state.tick;
+        let liquidity = state.liquidity;
+        let sqrt_price_start_x_96 = state.sqrt_price_x_96;
+
+        // the next tick, whether it is initialized and its price, unless the step stays within the current tick
+        let mut next = None;
+        let (sqrt_price_x_96, amount_in, amount_out, fee_amount) = match within_tick {
+            Some(step) => step,
+            None => {
+                let (tick_next, initialized) =
+                    pool.tick_bitmap.next_initialized_tick_within_one_word(
+                        state.tick,
+                        pool.fee_tier.tick_spacing(),
+                        zero_for_one,
+                    )?;
+
+                // ensure that we do not overshoot the min/max tick, as the tick bitmap is not aware of these bounds
+                let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+
+                let sqrt_price_next_x_96 = get_sqrt_ratio_at_tick(tick_next)?;
+                next = Some((tick_next, initialized, sqrt_price_next_x_96));
+
+                let sqrt_price_target_x_96 = if (zero_for_one
+                    && sqrt_price_next_x_96 < state.sqrt_price_limit_x_96)
+                    || (!zero_for_one && sqrt_price_next_x_96 > state.sqrt_price_limit_x_96)
+                {
+                    state.sqrt_price_limit_x_96
+                } else {
+                    sqrt_price_next_x_96
+                };
+
+                // compute values to swap to the target tick, price limit, or point where input/output amount is
+                // exhausted
+                compute_swap_step_with_fee_denominator(
+                    state.sqrt_price_x_96,
+                    sqrt_price_target_x_96,
+                    state.liquidity,
+                    state.amount_specified_remaining,
+                    pool.fee_tier.fee(),
+                    pool.variant.fee_denominator(),
+                )?
+            }
+        };
+        state.sqrt_price_x_96 = sqrt_price_x_96;
+        state.fee_amount += fee_amount;
+
+        if exact_input {
+            state.amount_specified_remaining -= I256::from_raw(amount_in + fee_amount);
+            state.amount_calculated -= I256::from_raw(amount_out);
+        } else {
+            state.amount_specified_remaining += I256::from_raw(amount_out);
+            state.amount_calculated += I256::from_raw(amount_in + fee_amount);
+        }
+
+        // if the protocol fee is on, calculate how much is owed, decrement fee_amount, and increment protocol_fee
+        let mut lp_fee_amount = fee_amount;
+        if self.fee_protocol > 0 {
+            let delta = pool.variant.protocol_fee(fee_amount, self.fee_protocol);
+            lp_fee_amount -= delta;
+            state.protocol_fee = state.protocol_fee.wrapping_add(delta.wrapping_to::<u128>());
+        }
+
+        // update global fee tracker
+        let mut fee_growth_global_x_128_delta = U256::ZERO;
+        if state.liquidity > 0 {
+            fee_growth_global_x_128_delta =
+                mul_div(lp_fee_amount, Q128, U256::from(state.liquidity))?;
+            state.fee_growth_global_x_128 = state
+                .fee_growth_global_x_128
+                .wrapping_add(fee_growth_global_x_128_delta);
+        }
+
+        let mut tick_crossed = None;
+
+        // shift tick if we reached the next price, a step within the current tick stays in it
+        if let Some((tick_next, initialized, sqrt_price_next_x_96)) = next {
+            if state.sqrt_price_x_96 == sqrt_price_next_x_96 {
+                // if the tick is initialized, run the tick transition
+                if initialized {
+                    let mut liquidity_net = pool.ticks.liquidity_net(tick_next);
+
+                    // if we're moving leftward, we interpret liquidity_net as the opposite sign
+                    if zero_for_one {
+                        liquidity_net = liquidity_net.wrapping_neg();
+                    }
+
+                    #[cfg(feature = "debug-invariants")]
+                    {
+                        crate::invariants::check_tick(
+                            &pool.ticks,
+                            &pool.tick_bitmap,
+                            tick_next,
+                            pool.fee_tier.tick_spacing(),
+                        )?;
+                        crate::invariants::check_cross(tick_next, state.liquidity, liquidity_net)?;
+                    }
+
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        tick = tick_next,
+                        liquidity_net,
+                        liquidity_before = state.liquidity,
+                        "tick crossed"
+                    );
+
+                    state.liquidity = add_delta(state.liquidity, liquidity_net)?;
+                    state
+                        .crossed_ticks
+                        .push((tick_next, state.fee_growth_global_x_128));
+                    tick_crossed = Some(tick_next);
+                }
+
+                state.tick = if zero_for_one {
+                    tick_next - 1
+                } else {
+                    tick_next
+                };
+            } else if state.sqrt_price_x_96 != sqrt_price_start_x_96 {
+                // recompute unless we're on a lower tick boundary (i.e. already transitioned ticks), and haven't moved
+                state.tick = get_tick_at_sqrt_ratio(state.sqrt_price_x_96)?;
+            }
+        }
+
+        if liquidity == 0 {
+            state.zero_liquidity_ticks += tick_before.abs_diff(state.tick);
+        }
+
+        let step = SwapStep {
+            tick_before,
+            tick_after: state.tick,
+            sqrt_price_before_x_96: sqrt_price_start_x_96,
+            sqrt_price_after_x_96: state.sqrt_price_x_96,
+            amount_in,
+            amount_out,
+            fee_amount,
+            fee_growth_global_x_128_delta,
+            liquidity,
+            tick_crossed,
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            tick_before = step.tick_before,
+            tick_after = step.tick_after,
+            sqrt_price_after_x_96 = %step.sqrt_price_after_x_96,
+            amount_in = %step.amount_in,
+            amount_out = %step.amount_out,
+            fee_amount = %step.fee_amount,
+            liquidity = step.liquidity,
+            "swap step"
+        );
+
+        #[cfg(feature = "debug-invariants")]
+        {
+            crate::invariants::check_swap_step(&step, zero_for_one)?;
+            crate::invariants::check_amount_remaining(
+                self.amount_specified,
+                state.amount_specified_remaining,
+            )?;
+            crate::invariants::check_price(state.sqrt_price_x_96, state.tick)?;
+        }
+
+        Ok(step)
+    }
+}
+
+impl<T: TickStorage, B: InitializedTicks, V: PoolVariant> Iterator for SwapSteps<'_, T, B, V> {
+    type Item = Result<SwapStep, UniswapV3MathError>;
+
+    // continue swapping as long as we haven't used the entire input/output and haven't reached the price limit
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed
+            || self.state.amount_specified_remaining.is_zero()
+            || self.state.sqrt_price_x_96 == self.state.sqrt_price_limit_x_96
+        {
+            return None;
+        }
+
+        if self.state.liquidity == 0 {
+            match self.zero_liquidity {
+                ZeroLiquidity::Skip => {}
+                ZeroLiquidity::Stop => return None,
+                ZeroLiquidity::Error => {
+                    self.failed = true;
+                    return Some(Err(UniswapV3MathError::LiquidityIsZero));
+                }
+            }
+        }
+
+        let step = self.step();
+        self.failed = step.is_err();
+        Some(step)
+    }
+}
+
+pub(crate) fn swap_result(
+    zero_for_one: bool,
+    amount_specified: I256,
+    state: &SwapState,
+) -> SwapResult {
+    let (amount_0, amount_1) = if zero_for_one == (amount_specified > I256::ZERO) {
+        (
+            amount_specified - state.amount_specified_remaining,
+            state.amount_calculated,
+        )
+    } else {
+        (
+            state.amount_calculated,
+            amount_specified - state.amount_specified_remaining,
+        )
+    };
+
+    // fee growth wraps like in the contract, so the delta does too
+    let fee_growth_global_x_128_delta = state
+        .fee_growth_global_x_128
+        .wrapping_sub(state.fee_growth_global_start_x_128);
+    let (fee_growth_global_0_x_128_delta, fee_growth_global_1_x_128_delta) = if zero_for_one {
+        (fee_growth_global_x_128_delta, U256::ZERO)
+    } else {
+        (U256::ZERO, fee_growth_global_x_128_delta)
+    };
+
+    let fill = if state.amount_specified_remaining.is_zero() {
+        SwapFill::Full
+    } else if state.sqrt_price_x_96 != state.sqrt_price_limit_x_96 {
+        SwapFill::Stopped
+    } else if state.liquidity == 0 {
+        SwapFill::LiquidityExhausted
+    } else {
+        SwapFill::PriceLimit
+    };
+
+    SwapResult {
+        amount_0,
+        amount_1,
+        sqrt_price_x_96: state.sqrt_price_x_96,
+        tick: state.tick,
+        liquidity: state.liquidity,
+        initialized_ticks_crossed: state.crossed_ticks.len() as u32,
+        fee_growth_global_0_x_128_delta,
+        fee_growth_global_1_x_128_delta,
+        amount_specified_remaining: state.amount_specified_remaining,
+        fill,
+        zero_liquidity_ticks: state.zero_liquidity_ticks,
+        fee_amount: state.fee_amount,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        initialize, liquidity_for_max_amounts, preview_burn, preview_mint, swap, DepthRange,
+        FlashResult, PersistentPoolSnapshot, PoolSnapshot, SwapFill, SwapParams, SwapResult,
+        ZeroLiquidity,
+    };
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        full_math::{mul_div, Q128},
+        liquidity_amounts::get_amounts_for_liquidity,
+        position::{uncollected_fees, Position},
+        sqrt_price_math::encode_sqrt_ratio_x96,
+        swap_math::compute_swap_step,
+        tick, tick_bitmap,
+        tick_math::{
+            get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MIN_SQRT_RATIO,
+        },
+        U256_1,
+    };
+
+    // adds liquidity to the given range the same way UniswapV3Pool._updatePosition does
+    fn add_liquidity(pool: &mut PoolSnapshot, tick_lower: i32, tick_upper: i32, liquidity: i128) {
+        let tick_spacing = pool.fee_tier.tick_spacing();
+        for (tick, upper) in [(tick_lower, false), (tick_upper, true)] {
+            let flipped = tick::update(
+                &mut pool.ticks,
+                tick,
+                pool.slot0.tick,
+                liquidity,
+                pool.fee_growth_global_0_x_128,
+                pool.fee_growth_global_1_x_128,
+                U256::ZERO,
+                U256::ZERO,
+                0,
+                upper,
+                None,
+            )
+            .unwrap();
+            if flipped {
+                tick_bitmap::flip_tick(&mut pool.tick_bitmap, tick, tick_spacing).unwrap();
+            }
+        }
+        if tick_lower <= pool.slot0.tick && pool.slot0.tick < tick_upper {
+            pool.liquidity = pool.liquidity.checked_add(liquidity as u128).unwrap();
+        }
+    }
+
+    fn init_pool() -> PoolSnapshot {
+        let mut pool = PoolSnapshot::new(
+            encode_sqrt_ratio_x96(U256_1, U256_1).unwrap(),
+            FeeTier::Medium,
+        )
+        .unwrap();
+        add_liquidity(&mut pool, -887220, 887220, 2e18 as i128);
+        pool
+    }
+
+    #[test]
+    fn test_swap_within_one_tick_range() {
+        let mut pool = init_pool();
+        let sqrt_price = pool.slot0.sqrt_price_x_96;
+
+        let result = pool
+            .swap(
+                true,
+                I256::from_str("1000").unwrap(),
+                MIN_SQRT_RATIO + U256_1,
+            )
+            .unwrap();
+
+        // the first step moves onto the current tick boundary, the second step is bounded by the next word
+        let (sqrt_price_next, amount_in, amount_out, fee_amount) = compute_swap_step(
+            sqrt_price,
+            get_sqrt_ratio_at_tick(-15360).unwrap(),
+            2e18 as u128,
+            I256::from_str("1000").unwrap(),
+            3000,
+        )
+        .unwrap();
+
+        assert_eq!(amount_in + fee_amount, U256::from(1000));
+        assert_eq!(result.amount_0, I256::from_str("1000").unwrap());
+        assert_eq!(result.amount_1, -I256::from_raw(amount_out));
+        assert_eq!(result.sqrt_price_x_96, sqrt_price_next);
+        assert_eq!(result.tick, -1);
+        assert_eq!(result.liquidity, 2e18 as u128);
+
+        assert_eq!(pool.slot0.sqrt_price_x_96, sqrt_price_next);
+        assert_eq!(pool.slot0.tick, -1);
+        assert_eq!(
+            pool.fee_growth_global_0_x_128,
+            mul_div(fee_amount, Q128, U256::from(2e18 as u128)).unwrap()
+        );
+        assert_eq!(pool.fee_growth_global_1_x_128, U256::ZERO);
+        assert_eq!(pool.protocol_fees.token_0, 0);
+    }
+
+    #[test]
+    fn test_swap_exact_output() {
+        let mut pool = init_pool();
+
+        let result = pool
+            .swap(
+                false,
+                -I256::from_str("1000").unwrap(),
+                MAX_SQRT_RATIO - U256_1,
+            )
+            .unwrap();
+
+        assert_eq!(result.amount_0, -I256::from_str("1000").unwrap());
+        assert!(result.amount_1 > I256::from_str("1000").unwrap());
+        assert!(pool.fee_growth_global_1_x_128 > U256::ZERO);
+        assert_eq!(pool.fee_growth_global_0_x_128, U256::ZERO);
+        assert_eq!(pool.slot0.tick, 0);
+    }
+
+    #[test]
+    fn test_swap_protocol_fee() {
+        let mut pool = init_pool();
+        // 1/4 of the swap fee goes to the protocol on token0 and 1/5 on token1
+        pool.slot0.fee_protocol = 4 + (5 << 4);
+        assert_eq!(pool.slot0.fee_protocol_0(), 4);
+        assert_eq!(pool.slot0.fee_protocol_1(), 5);
+
+        let amount = I256::from_str("1000000000000000").unwrap();
+        let result = pool.swap(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+
+        let (_, amount_in, _, fee_amount) = compute_swap_step(
+            encode_sqrt_ratio_x96(U256_1, U256_1).unwrap(),
+            get_sqrt_ratio_at_tick(-15360).unwrap(),
+            2e18 as u128,
+            amount,
+            3000,
+        )
+        .unwrap();
+        assert_eq!(result.amount_0, I256::from_raw(amount_in + fee_amount));
+
+        let protocol_fee = fee_amount / U256::from(4);
+        assert_eq!(U256::from(pool.protocol_fees.token_0), protocol_fee);
+        assert_eq!(pool.protocol_fees.token_1, 0);
+        assert_eq!(
+            pool.fee_growth_global_0_x_128,
+            mul_div(fee_amount - protocol_fee, Q128, U256::from(2e18 as u128)).unwrap()
+        );
+
+        // swapping back accrues the token1 protocol fee
+        pool.swap(false, amount, MAX_SQRT_RATIO - U256_1).unwrap();
+        assert!(pool.protocol_fees.token_1 > 0);
+    }
+
+    #[test]
+    fn test_swap_crosses_initialized_ticks() {
+        let mut pool = init_pool();
+        add_liquidity(&mut pool, -120, 120, 1e18 as i128);
+        assert_eq!(pool.liquidity, 3e18 as u128);
+
+        let amount = I256::from_str("100000000000000000").unwrap();
+        let quote = pool.quote(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+
+        // quoting does not modify the pool
+        assert_eq!(pool, {
+            let mut pool = init_pool();
+            add_liquidity(&mut pool, -120, 120, 1e18 as i128);
+            pool
+        });
+
+        let result = pool.swap(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+        assert_eq!(quote, result);
+        assert!(result.tick < -120);
+        assert_eq!(result.liquidity, 2e18 as u128);
+        assert_eq!(result.initialized_ticks_crossed, 1);
+        assert_eq!(pool.liquidity, 2e18 as u128);
+
+        // the crossed tick now tracks the fee growth above it
+        assert!(pool.ticks[&-120].fee_growth_outside_0_x_128 > U256::ZERO);
+        assert!(pool.ticks[&-120].fee_growth_outside_0_x_128 < pool.fee_growth_global_0_x_128);
+        assert_eq!(pool.ticks[&120].fee_growth_outside_0_x_128, U256::ZERO);
+
+        // swapping back crosses the tick again and restores the liquidity
+        pool.swap(false, amount, MAX_SQRT_RATIO - U256_1).unwrap();
+        assert!(pool.slot0.tick >= -120);
+        assert_eq!(pool.liquidity, 3e18 as u128);
+    }
+
+    #[test]
+    fn test_swap_price_limit() {
+        let mut pool = init_pool();
+
+        // stops at the price limit and leaves the rest of the amount unswapped
+        let limit = get_sqrt_ratio_at_tick(-10).unwrap();
+        let result = pool
+            .swap(true, I256::from_str("1000000000000000000").unwrap(), limit)
+            .unwrap();
+        assert_eq!(result.sqrt_price_x_96, limit);
+        assert_eq!(result.tick, -10);
+        assert!(result.amount_0 < I256::from_str("1000000000000000000").unwrap());
+
+        //fails if the amount specified is zero
+        let result = pool.swap(true, I256::ZERO, MIN_SQRT_RATIO + U256_1);
+        assert!(matches!(result.unwrap_err(), UniswapV3MathError::AS));
+
+        //fails if the price limit is on the wrong side of the current price
+        let result = pool.swap(true, I256::ONE, MAX_SQRT_RATIO - U256_1);
+        assert!(matches!(result.unwrap_err(), UniswapV3MathError::SPL));
+
+        //fails if the price limit is out of bounds
+        let result = pool.swap(true, I256::ONE, MIN_SQRT_RATIO);
+        assert!(matches!(result.unwrap_err(), UniswapV3MathError::SPL));
+        let result = pool.swap(false, I256::ONE, MAX_SQRT_RATIO);
+        assert!(matches!(result.unwrap_err(), UniswapV3MathError::SPL));
+    }
+
+    #[test]
+    fn test_get_amount_in_to_target_price() {
+        let mut pool = init_pool();
+        add_liquidity(&mut pool, -120, 120, 1e18 as i128);
+
+        for target_tick in [-1000, -120, -60, 60, 120, 1000] {
+            let target = get_sqrt_ratio_at_tick(target_tick).unwrap();
+            let zero_for_one = target_tick < 0;
+            let amount_in = pool.get_amount_in_to_target_price(target).unwrap();
+
+            //swapping the amount reaches the target and consumes the entire input
+            let amount = I256::from_raw(amount_in);
+            let result = pool.quote(zero_for_one, amount, target).unwrap();
+            assert_eq!(result.sqrt_price_x_96, target);
+            assert_eq!(
+                if zero_for_one {
+                    result.amount_0
+                } else {
+                    result.amount_1
+                },
+                amount
+            );
+
+            //one wei less stops short of the target
+            let result = pool
+                .quote(zero_for_one, amount - I256::ONE, target)
+                .unwrap();
+            assert_ne!(result.sqrt_price_x_96, target);
+        }
+
+        //returns zero for the current price
+        let amount_in = pool
+            .get_amount_in_to_target_price(pool.slot0.sqrt_price_x_96)
+            .unwrap();
+        assert_eq!(amount_in, U256::ZERO);
+    }
+
+    #[test]
+    fn test_swap_steps() {
+        let mut pool = init_pool();
+        add_liquidity(&mut pool, -120, 120, 1e18 as i128);
+
+        let amount = I256::from_str("100000000000000000").unwrap();
+        let quote = pool.quote(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+
+        let mut steps = pool
+            .swap_steps(true, amount, MIN_SQRT_RATIO + U256_1)
+            .unwrap();
+        let all = steps.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(steps.result(), quote);
+
+        //steps are contiguous and add up to the swap amounts
+        let (amount_in, amount_out) = all.iter().fold((U256::ZERO, U256::ZERO), |acc, step| {
+            (
+                acc.0 + step.amount_in + step.fee_amount,
+                acc.1 + step.amount_out,
+            )
+        });
+        assert_eq!(I256::from_raw(amount_in), quote.amount_0);
+        assert_eq!(-I256::from_raw(amount_out), quote.amount_1);
+        for window in all.windows(2) {
+            assert_eq!(window[0].tick_after, window[1].tick_before);
+            assert_eq!(
+                window[0].sqrt_price_after_x_96,
+                window[1].sqrt_price_before_x_96
+            );
+        }
+
+        //the initialized tick is crossed once and changes the active liquidity
+        let crossed = all
+            .iter()
+            .position(|step| step.tick_crossed.is_some())
+            .unwrap();
+        assert_eq!(all[crossed].tick_crossed, Some(-120));
+        assert_eq!(all[crossed].liquidity, 3e18 as u128);
+        assert_eq!(all[crossed + 1].liquidity, 2e18 as u128);
+        assert_eq!(
+            all.iter()
+                .filter(|step| step.tick_crossed.is_some())
+                .count(),
+            1
+        );
+
+        //stopping after the cross matches a swap with the tick as the price limit
+        let mut steps = pool
+            .swap_steps(true, amount, MIN_SQRT_RATIO + U256_1)
+            .unwrap();
+        for step in steps.by_ref() {
+            if step.unwrap().tick_crossed.is_some() {
+                break;
+            }
+        }
+        let limit = get_sqrt_ratio_at_tick(-120).unwrap();
+        let result = pool.quote(true, amount, limit).unwrap();
+        assert_eq!(result.tick, -121);
+        //only the reason the swap ended differs
+        assert_eq!(
+            steps.result(),
+            SwapResult {
+                fill: SwapFill::Stopped,
+                ..result
+            }
+        );
+
+        //fails before yielding any steps for invalid arguments
+        let result = pool.swap_steps(true, I256::ZERO, MIN_SQRT_RATIO + U256_1);
+        assert!(matches!(result.err().unwrap(), UniswapV3MathError::AS));
+    }
+
+    #[test]
+    fn test_modify_position() {
+        let mut pool = init_pool();
+        let before = pool.clone();
+
+        //in range positions provide both tokens and become active
+        let (amount_0, amount_1) = pool.modify_position(-120, 120, 1e18 as i128).unwrap();
+        assert!(amount_0 > I256::ZERO && amount_1 > I256::ZERO);
+        assert_eq!(pool.liquidity, 3e18 as u128);
+        assert_eq!(pool.ticks[&-120].liquidity_net, 1e18 as i128);
+        assert_eq!(pool.ticks[&120].liquidity_net, -1e18 as i128);
+
+        //positions above or below the price only provide a single token
+        let (amount_0, amount_1) = pool.modify_position(120, 180, 1e18 as i128).unwrap();
+        assert!(amount_0 > I256::ZERO);
+        assert_eq!(amount_1, I256::ZERO);
+        let (amount_0, amount_1) = pool.modify_position(-180, -120, 1e18 as i128).unwrap();
+        assert_eq!(amount_0, I256::ZERO);
+        assert!(amount_1 > I256::ZERO);
+        assert_eq!(pool.liquidity, 3e18 as u128);
+
+        //burning returns one wei less than was minted due to rounding and restores the pool
+        let minted = {
+            let mut pool = init_pool();
+            pool.modify_position(-120, 120, 1e18 as i128).unwrap()
+        };
+        let (amount_0, amount_1) = pool.modify_position(-120, 120, -1e18 as i128).unwrap();
+        assert_eq!(amount_0, -(minted.0 - I256::ONE));
+        assert_eq!(amount_1, -(minted.1 - I256::ONE));
+        pool.modify_position(120, 180, -1e18 as i128).unwrap();
+        pool.modify_position(-180, -120, -1e18 as i128).unwrap();
+        assert_eq!(pool.ticks, before.ticks);
+        assert_eq!(pool.liquidity, before.liquidity);
+        pool.tick_bitmap.retain(|_, word| !word.is_zero());
+        assert_eq!(pool.tick_bitmap, before.tick_bitmap);
+
+        //fails for invalid ranges or ticks not on the tick spacing
+        let result = pool.modify_position(120, -120, 1);
+        assert!(matches!(result.unwrap_err(), UniswapV3MathError::TLU));
+        assert!(pool.modify_position(-121, 120, 1).is_err());
+        assert!(pool.modify_position(-120, 120, -1).is_err());
+
+        //a failing call leaves both ticks untouched, even when only the upper tick is invalid
+        let max_liquidity = pool.fee_tier.max_liquidity_per_tick().unwrap();
+        pool.modify_position(180, 240, (max_liquidity - 1) as i128)
+            .unwrap();
+        let before = pool.clone();
+        let result = pool.modify_position(-120, 121, 1);
+        assert!(matches!(result, Err(UniswapV3MathError::TickSpacingError)));
+        assert_eq!(pool, before);
+        let result = pool.modify_position(-120, 180, 2);
+        assert!(matches!(result, Err(UniswapV3MathError::LiquidityOverflow)));
+        assert_eq!(pool, before);
+        let result = pool.modify_position(-120, 180, -1);
+        assert!(result.is_err());
+        assert_eq!(pool, before);
+    }
+
+    #[test]
+    fn test_persistent_pool_snapshot() {
+        let mut pool = init_pool();
+        add_liquidity(&mut pool, -600, 600, 1e19 as i128);
+        add_liquidity(&mut pool, -1200, -60, 1e19 as i128);
+        let persistent = PersistentPoolSnapshot::from(pool.clone());
+
+        //swaps and position updates against a fork match the HashMap backed pool
+        let mut fork = persistent.clone();
+        let amount = I256::from_raw(U256::from(1e19 as u128));
+        let expected = pool.swap(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+        assert_eq!(
+            fork.swap(true, amount, MIN_SQRT_RATIO + U256_1).unwrap(),
+            expected
+        );
+        assert_eq!(
+            fork.modify_position(-120, 120, 1e18 as i128).unwrap(),
+            pool.modify_position(-120, 120, 1e18 as i128).unwrap()
+        );
+        assert_eq!(PoolSnapshot::from(fork.clone()), pool);
+
+        //the original is unaffected by writes to the fork
+        assert!(!fork.ticks.ptr_eq(&persistent.ticks));
+        assert_eq!(persistent.ticks.len(), 6);
+        assert_eq!(fork.ticks.len(), 8);
+        assert_eq!(
+            persistent
+                .quote(true, amount, MIN_SQRT_RATIO + U256_1)
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_pure_swap() {
+        let mut pool = init_pool();
+        add_liquidity(&mut pool, -600, 600, 1e19 as i128);
+        add_liquidity(&mut pool, -1200, -60, 1e19 as i128);
+        let persistent = PersistentPoolSnapshot::from(pool.clone());
+        let params = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e16 as u128))
+            .build()
+            .unwrap();
+
+        //matches swapping in place and leaves the state untouched
+        let (next, result) = swap(&persistent, &params).unwrap();
+        let mut expected = pool.clone();
+        assert_eq!(expected.swap_with(&params).unwrap(), result);
+        assert_eq!(PoolSnapshot::from(next.clone()), expected);
+        assert_eq!(PoolSnapshot::from(persistent.clone()), pool);
+
+        //states chain, and ticks are shared until a swap crosses one
+        assert!(next.ticks.ptr_eq(&persistent.ticks));
+        let params = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e19 as u128))
+            .build()
+            .unwrap();
+        let (after, result) = swap(&next, &params).unwrap();
+        assert!(result.initialized_ticks_crossed > 0);
+        assert!(!after.ticks.ptr_eq(&next.ticks));
+        assert_eq!(expected.swap_with(&params).unwrap(), result);
+        assert_eq!(PoolSnapshot::from(after), expected);
+
+        //failed swaps return the error without a state
+        let params = SwapParams {
+            sqrt_price_limit_x_96: next.slot0.sqrt_price_x_96,
+            ..params
+        };
+        assert!(matches!(swap(&next, &params), Err(UniswapV3MathError::SPL)));
+    }
+
+    #[test]
+    fn test_concurrent_quotes() {
+        let mut pool = init_pool();
+        add_liquidity(&mut pool, -600, 600, 1e19 as i128);
+        let pool = std::sync::Arc::new(pool);
+        let amount = I256::from_raw(U256::from(1e19 as u128));
+        let expected = pool.quote(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+
+        //threads quote the same snapshot through shared references
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let pool = pool.clone();
+                std::thread::spawn(move || {
+                    pool.quote(true, amount, MIN_SQRT_RATIO + U256_1).unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_swap_params_builder() {
+        let amount = U256::from(1000);
+
+        let params = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(amount)
+            .build()
+            .unwrap();
+        assert_eq!(
+            params,
+            SwapParams {
+                zero_for_one: true,
+                amount_specified: I256::from_raw(amount),
+                sqrt_price_limit_x_96: MIN_SQRT_RATIO + U256_1,
+                zero_liquidity: ZeroLiquidity::Skip,
+            }
+        );
+
+        let limit = get_sqrt_ratio_at_tick(600).unwrap();
+        let params = SwapParams::builder()
+            .zero_for_one(false)
+            .amount_out(amount)
+            .sqrt_price_limit(limit)
+            .build()
+            .unwrap();
+        assert_eq!(params.amount_specified, -I256::from_raw(amount));
+        assert_eq!(params.sqrt_price_limit_x_96, limit);
+        assert_eq!(
+            SwapParams::builder()
+                .zero_for_one(false)
+                .build()
+                .map_err(|err| err.to_string()),
+            Err(UniswapV3MathError::AS.to_string())
+        );
+        assert_eq!(
+            SwapParams::builder()
+                .amount_specified(I256::ONE)
+                .build()
+                .map_err(|err| err.to_string()),
+            Err(UniswapV3MathError::SwapDirectionNotSet.to_string())
+        );
+        //fails if the amount does not fit an int256
+        assert!(SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::MAX)
+            .build()
+            .is_err());
+
+        //quotes the same as the positional arguments
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        let params = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e18 as u128))
+            .build()
+            .unwrap();
+        let expected = pool
+            .quote(true, params.amount_specified, MIN_SQRT_RATIO + U256_1)
+            .unwrap();
+        assert_eq!(pool.quote_with(&params).unwrap(), expected);
+        assert_eq!(pool.swap_with(&params).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_preview_mint() {
+        let mut pool = PoolSnapshot::new(
+            encode_sqrt_ratio_x96(U256::from(3), U256::from(7)).unwrap(),
+            FeeTier::Medium,
+        )
+        .unwrap();
+        pool.modify_position(-9000, -7800, 1e21 as i128).unwrap();
+        let liquidity = 123456789u128;
+
+        for (tick_lower, tick_upper) in [(-18000, -12000), (-12000, -6000), (-6000, 6000)] {
+            //asks for exactly what the mint callback is asked for
+            let (amount_0, amount_1) =
+                preview_mint(&pool, tick_lower, tick_upper, liquidity).unwrap();
+            let mut minted = pool.clone();
+            let (owed_0, owed_1) = minted
+                .modify_position(tick_lower, tick_upper, liquidity as i128)
+                .unwrap();
+            assert_eq!((amount_0, amount_1), (owed_0.into_raw(), owed_1.into_raw()));
+
+            //the inverse never asks for more than the given amounts
+            let max_liquidity =
+                liquidity_for_max_amounts(&pool, tick_lower, tick_upper, amount_0, amount_1)
+                    .unwrap();
+            assert!(max_liquidity >= liquidity);
+            let (max_amount_0, max_amount_1) =
+                preview_mint(&pool, tick_lower, tick_upper, max_liquidity).unwrap();
+            assert!(max_amount_0 <= amount_0 && max_amount_1 <= amount_1);
+            let (over_0, over_1) =
+                preview_mint(&pool, tick_lower, tick_upper, max_liquidity + 1).unwrap();
+            assert!(over_0 > amount_0 || over_1 > amount_1);
+        }
+
+        //rounds up where the amounts for liquidity round down
+        let (amount_0, amount_1) = preview_mint(&pool, -12000, -6000, liquidity).unwrap();
+        let (floor_0, floor_1) = get_amounts_for_liquidity(
+            pool.slot0.sqrt_price_x_96,
+            get_sqrt_ratio_at_tick(-12000).unwrap(),
+            get_sqrt_ratio_at_tick(-6000).unwrap(),
+            liquidity,
+        )
+        .unwrap();
+        assert_eq!(amount_0, floor_0 + U256_1);
+        assert_eq!(amount_1, floor_1 + U256_1);
+
+        //fails if the mint would revert
+        assert_eq!(
+            preview_mint(&pool, -6000, 6000, 0).map_err(|err| err.to_string()),
+            Err(UniswapV3MathError::LiquidityIsZero.to_string())
+        );
+        assert!(preview_mint(&pool, 6000, -6000, liquidity).is_err());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        let fingerprint = pool.fingerprint().unwrap();
+
+        //state that does not affect quotes is left out
+        let mut other = pool.clone();
+        other.fee_growth_global_0_x_128 += U256::from(12345);
+        other.protocol_fees.token_1 += 1;
+        other.slot0.observation_index += 1;
+        assert_eq!(other.fingerprint().unwrap(), fingerprint);
+
+        //far away ticks are reachable by a large enough swap
+        for (tick_lower, tick_upper) in [(-887220, -886980), (886980, 887220)] {
+            let mut other = pool.clone();
+            other
+                .modify_position(tick_lower, tick_upper, 1e18 as i128)
+                .unwrap();
+            assert_ne!(other.fingerprint().unwrap(), fingerprint);
+        }
+
+        //quotes change with the price and with the protocol fee
+        let mut other = pool.clone();
+        other
+            .swap(
+                true,
+                I256::try_from(1e6 as u128).unwrap(),
+                MIN_SQRT_RATIO + U256_1,
+            )
+            .unwrap();
+        assert_ne!(other.fingerprint().unwrap(), fingerprint);
+        let mut other = pool.clone();
+        other.slot0.fee_protocol = 4 | (4 << 4);
+        assert_ne!(other.fingerprint().unwrap(), fingerprint);
+    }
+
+    #[test]
+    fn test_initialize() {
+        let sqrt_price_x_96 = encode_sqrt_ratio_x96(U256::from(3), U256::from(7)).unwrap();
+        let ranges = [
+            DepthRange::new(-60000, 60000, 1e18 as u128),
+            DepthRange::new(-9000, -7800, 1e21 as u128),
+            DepthRange::new(-7800, -6000, 1e20 as u128),
+        ];
+
+        //mints every range like minting them one by one into a new pool
+        let initialized = initialize(sqrt_price_x_96, FeeTier::Medium, &ranges).unwrap();
+        let mut pool = PoolSnapshot::new(sqrt_price_x_96, FeeTier::Medium).unwrap();
+        for (range, amounts) in ranges.iter().zip(&initialized.amounts) {
+            assert_eq!(
+                preview_mint(&pool, range.tick_lower, range.tick_upper, range.liquidity).unwrap(),
+                *amounts
+            );
+            pool.modify_position(range.tick_lower, range.tick_upper, range.liquidity as i128)
+                .unwrap();
+        }
+        assert_eq!(initialized.pool, pool);
+        assert_eq!(
+            initialized.amount_0,
+            initialized.amounts.iter().map(|amounts| amounts.0).sum()
+        );
+        assert_eq!(
+            initialized.amount_1,
+            initialized.amounts.iter().map(|amounts| amounts.1).sum()
+        );
+        assert_eq!(pool.liquidity, 1e18 as u128 + 1e21 as u128);
+
+        //fails if a tick is not aligned or a tick exceeds the max liquidity per tick
+        assert_eq!(
+            initialize(
+                sqrt_price_x_96,
+                FeeTier::Medium,
+                &[DepthRange::new(-50, 60, 1)]
+            )
+            .map_err(|err| err.to_string()),
+            Err(UniswapV3MathError::TickSpacingError.to_string())
+        );
+        let max_liquidity = FeeTier::Medium.max_liquidity_per_tick().unwrap();
+        assert_eq!(
+            initialize(
+                sqrt_price_x_96,
+                FeeTier::Medium,
+                &[
+                    DepthRange::new(-60, 60, max_liquidity),
+                    DepthRange::new(60, 120, 1)
+                ]
+            )
+            .map_err(|err| err.to_string()),
+            Err(UniswapV3MathError::LiquidityOverflow.to_string())
+        );
+    }
+
+    #[test]
+    fn test_preview_burn() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        let (fee_growth_inside_0_x_128, fee_growth_inside_1_x_128) = tick::get_fee_growth_inside(
+            &pool.ticks,
+            -600,
+            600,
+            pool.slot0.tick,
+            pool.fee_growth_global_0_x_128,
+            pool.fee_growth_global_1_x_128,
+        );
+        let mut position = Position::default();
+        position
+            .update(
+                1e21 as i128,
+                fee_growth_inside_0_x_128,
+                fee_growth_inside_1_x_128,
+            )
+            .unwrap();
+        position.tokens_owed_0 = 7;
+
+        //accrue fees in both tokens
+        let amount = I256::from_raw(U256::from(1e18 as u128));
+        pool.swap(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+        pool.swap(false, amount, MAX_SQRT_RATIO - U256_1).unwrap();
+
+        let preview = preview_burn(&pool, -600, 600, &position, 4e20 as u128).unwrap();
+
+        //the principal matches the amounts of the burn
+        let (amount_0, amount_1) = pool
+            .clone()
+            .modify_position(-600, 600, -4e20 as i128)
+            .unwrap();
+        assert_eq!(preview.amount_0, amount_0.unsigned_abs());
+        assert_eq!(preview.amount_1, amount_1.unsigned_abs());
+
+        //the fees include the tokens already owed
+        let (fees_0, fees_1) = uncollected_fees(
+            &pool,
+            -600,
+            600,
+            position.liquidity,
+            position.fee_growth_inside_0_last_x_128,
+            position.fee_growth_inside_1_last_x_128,
+        )
+        .unwrap();
+        assert!(!fees_0.is_zero() && !fees_1.is_zero());
+        assert_eq!(U256::from(preview.fees_0), fees_0 + U256::from(7));
+        assert_eq!(U256::from(preview.fees_1), fees_1);
+        assert_eq!(
+            preview.collect_amounts(),
+            (
+                preview.fees_0 + preview.amount_0.to::<u128>(),
+                preview.fees_1 + preview.amount_1.to::<u128>()
+            )
+        );
+
+        //a poke only credits the fees
+        let poke = preview_burn(&pool, -600, 600, &position, 0).unwrap();
+        assert!(poke.amount_0.is_zero() && poke.amount_1.is_zero());
+        assert_eq!((poke.fees_0, poke.fees_1), (preview.fees_0, preview.fees_1));
+
+        //fails if the burn would revert
+        assert!(preview_burn(&pool, -600, 600, &position, 1e21 as u128 + 1).is_err());
+        assert!(preview_burn(&pool, -600, 600, &Position::default(), 0).is_err());
+    }
+
+    #[test]
+    fn test_swap_fee_growth_delta() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-6000, 6000, 1e20 as i128).unwrap();
+        let amount = I256::from_raw(U256::from(1e20 as u128));
+
+        for (zero_for_one, limit) in [
+            (true, MIN_SQRT_RATIO + U256_1),
+            (false, MAX_SQRT_RATIO - U256_1),
+        ] {
+            let (fee_growth_global_0_x_128, fee_growth_global_1_x_128) = (
+                pool.fee_growth_global_0_x_128,
+                pool.fee_growth_global_1_x_128,
+            );
+            let quote = pool.quote(zero_for_one, amount, limit).unwrap();
+            let result = pool.swap(zero_for_one, amount, limit).unwrap();
+            assert_eq!(quote, result);
+            //crosses ticks, so the growth is accumulated over steps with different liquidity
+            assert!(result.initialized_ticks_crossed > 0);
+
+            assert_eq!(
+                result.fee_growth_global_0_x_128_delta,
+                pool.fee_growth_global_0_x_128 - fee_growth_global_0_x_128
+            );
+            assert_eq!(
+                result.fee_growth_global_1_x_128_delta,
+                pool.fee_growth_global_1_x_128 - fee_growth_global_1_x_128
+            );
+            //only the input token earns fees
+            let (input, output) = if zero_for_one {
+                (
+                    result.fee_growth_global_0_x_128_delta,
+                    result.fee_growth_global_1_x_128_delta,
+                )
+            } else {
+                (
+                    result.fee_growth_global_1_x_128_delta,
+                    result.fee_growth_global_0_x_128_delta,
+                )
+            };
+            assert!(input > U256::ZERO);
+            assert_eq!(output, U256::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_swap_fill() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        let amount = I256::from_raw(U256::from(1e18 as u128));
+
+        let result = pool.quote(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+        assert_eq!(result.fill, SwapFill::Full);
+        assert_eq!(result.amount_specified_remaining, I256::ZERO);
+
+        //the price limit is hit with liquidity left
+        let limit = get_sqrt_ratio_at_tick(-1).unwrap();
+        let result = pool.quote(true, amount, limit).unwrap();
+        assert_eq!(result.fill, SwapFill::PriceLimit);
+        assert_eq!(result.amount_0 + result.amount_specified_remaining, amount);
+        assert!(result.amount_specified_remaining.is_positive());
+
+        //the swap runs out of liquidity, for exact output too
+        let amount = I256::from_raw(U256::from(1e23 as u128));
+        for amount_specified in [amount, -amount] {
+            let result = pool
+                .quote(true, amount_specified, MIN_SQRT_RATIO + U256_1)
+                .unwrap();
+            assert_eq!(result.fill, SwapFill::LiquidityExhausted);
+            assert_eq!(result.liquidity, 0);
+            assert_eq!(
+                result.amount_specified_remaining.sign(),
+                amount_specified.sign()
+            );
+        }
+        //out of liquidity, but the price limit is reached first
+        let limit = get_sqrt_ratio_at_tick(-1200).unwrap();
+        assert_eq!(
+            pool.quote(true, amount, limit).unwrap().fill,
+            SwapFill::LiquidityExhausted
+        );
+        let mut steps = pool.swap_steps(true, amount, limit).unwrap();
+        steps.next().unwrap().unwrap();
+        assert_eq!(steps.result().fill, SwapFill::Stopped);
+    }
+
+    #[test]
+    fn test_zero_liquidity() {
+        //an empty gap between -1200 and -600
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-3000, -1200, 1e21 as i128).unwrap();
+        let builder = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e20 as u128));
+
+        //moves through the gap like the contract
+        let result = pool.quote_with(&builder.build().unwrap()).unwrap();
+        assert_eq!(
+            result,
+            pool.quote_with(&builder.zero_liquidity(ZeroLiquidity::Skip).build().unwrap())
+                .unwrap()
+        );
+        assert!(result.tick < -1200);
+        assert_eq!(result.zero_liquidity_ticks, 600);
+
+        //stops at the start of the gap
+        let params = builder.zero_liquidity(ZeroLiquidity::Stop).build().unwrap();
+        let result = pool.quote_with(&params).unwrap();
+        let expected = pool
+            .quote(
+                true,
+                params.amount_specified,
+                get_sqrt_ratio_at_tick(-600).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(result.fill, SwapFill::Stopped);
+        assert_eq!(
+            SwapResult {
+                fill: expected.fill,
+                ..result
+            },
+            expected
+        );
+        assert_eq!(result.zero_liquidity_ticks, 0);
+        let mut swapped = pool.clone();
+        assert_eq!(swapped.swap_with(&params).unwrap(), result);
+        assert_eq!(swapped.slot0.tick, -601);
+
+        //fails at the start of the gap
+        let params = builder
+            .zero_liquidity(ZeroLiquidity::Error)
+            .build()
+            .unwrap();
+        assert_eq!(
+            pool.quote_with(&params).map_err(|err| err.to_string()),
+            Err(UniswapV3MathError::LiquidityIsZero.to_string())
+        );
+        let mut steps = pool
+            .swap_steps(true, params.amount_specified, params.sqrt_price_limit_x_96)
+            .unwrap()
+            .zero_liquidity(ZeroLiquidity::Error);
+        assert!(steps.by_ref().any(|step| step.is_err()));
+        assert!(steps.next().is_none());
+    }
+
+    #[test]
+    fn test_step_within_tick() {
+        //a price within tick 100 rather than on its boundary
+        let sqrt_price = (get_sqrt_ratio_at_tick(100).unwrap()
+            + get_sqrt_ratio_at_tick(101).unwrap())
+            / U256::from(2);
+        let mut pool = PoolSnapshot::new(sqrt_price, FeeTier::Medium).unwrap();
+        add_liquidity(&mut pool, -887220, 887220, 2e18 as i128);
+        add_liquidity(&mut pool, 60, 180, 1e18 as i128);
+
+        for zero_for_one in [true, false] {
+            let limit = if zero_for_one {
+                MIN_SQRT_RATIO + U256_1
+            } else {
+                MAX_SQRT_RATIO - U256_1
+            };
+            for amount_specified in [1i64, 1000, 1_000_000, -1, -1000, -1_000_000] {
+                let amount_specified = I256::try_from(amount_specified).unwrap();
+                let result = pool.quote(zero_for_one, amount_specified, limit).unwrap();
+
+                //the step of the general path, towards the next initialized tick
+                let (tick_next, _) = tick_bitmap::next_initialized_tick_within_one_word(
+                    &pool.tick_bitmap,
+                    pool.slot0.tick,
+                    pool.fee_tier.tick_spacing(),
+                    zero_for_one,
+                )
+                .unwrap();
+                let (sqrt_price_next, amount_in, amount_out, fee_amount) = compute_swap_step(
+                    sqrt_price,
+                    get_sqrt_ratio_at_tick(tick_next).unwrap(),
+                    pool.liquidity,
+                    amount_specified,
+                    3000,
+                )
+                .unwrap();
+                let (amount_in, amount_out) = (
+                    I256::from_raw(amount_in + fee_amount),
+                    -I256::from_raw(amount_out),
+                );
+
+                assert_eq!(result.sqrt_price_x_96, sqrt_price_next);
+                assert_eq!(
+                    result.tick,
+                    get_tick_at_sqrt_ratio(sqrt_price_next).unwrap()
+                );
+                assert_eq!(result.tick, 100);
+                assert_eq!(result.liquidity, 3e18 as u128);
+                assert_eq!(result.fee_amount, fee_amount);
+                if zero_for_one {
+                    assert_eq!((result.amount_0, result.amount_1), (amount_in, amount_out));
+                } else {
+                    assert_eq!((result.amount_1, result.amount_0), (amount_in, amount_out));
+                }
+            }
+        }
+
+        //leaving the tick takes the general path
+        let result = pool
+            .quote(
+                true,
+                I256::try_from(1e17 as i64).unwrap(),
+                MIN_SQRT_RATIO + U256_1,
+            )
+            .unwrap();
+        assert!(result.tick < 60);
+        assert_eq!(result.initialized_ticks_crossed, 1);
+        assert_eq!(result.liquidity, 2e18 as u128);
+    }
+
+    #[test]
+    fn test_flash() {
+        let mut pool = init_pool();
+        pool.slot0.fee_protocol = 4 | (5 << 4);
+        let before = pool.clone();
+
+        let result = pool
+            .flash(U256::from(1e18 as u128), U256::from(1_000_001))
+            .unwrap();
+        assert_eq!(result.fee_0, U256::from(3e15 as u128));
+        //the fee is rounded up
+        assert_eq!(result.fee_1, U256::from(3001));
+        assert_eq!(
+            (result.protocol_fee_0, result.protocol_fee_1),
+            (75e13 as u128, 600)
+        );
+        assert_eq!(
+            result.fee_growth_global_1_x_128_delta,
+            mul_div(U256::from(2401), Q128, U256::from(2e18 as u128)).unwrap()
+        );
+
+        //the fee is credited to the in range liquidity without moving the price
+        assert_eq!(pool.protocol_fees.token_0, 75e13 as u128);
+        assert_eq!(pool.protocol_fees.token_1, 600);
+        assert_eq!(
+            pool.fee_growth_global_0_x_128,
+            result.fee_growth_global_0_x_128_delta
+        );
+        assert_eq!(
+            pool.fee_growth_global_1_x_128,
+            result.fee_growth_global_1_x_128_delta
+        );
+        assert_eq!(pool.slot0, before.slot0);
+        assert_eq!(pool.liquidity, before.liquidity);
+        let (fees_0, fees_1) =
+            uncollected_fees(&pool, -887220, 887220, 2e18 as u128, U256::ZERO, U256::ZERO).unwrap();
+        assert_eq!(fees_0, U256::from(225e13 as u128 - 1));
+        assert_eq!(fees_1, U256::from(2400));
+
+        //borrowing nothing charges nothing
+        let mut flashed = before.clone();
+        assert_eq!(
+            flashed.flash(U256::ZERO, U256::ZERO).unwrap(),
+            FlashResult::default()
+        );
+        assert_eq!(flashed, before);
+
+        //fails without in range liquidity
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        assert!(matches!(
+            pool.flash(U256::from(1), U256::ZERO).unwrap_err(),
+            UniswapV3MathError::LiquidityIsZero
+        ));
+    }
+}
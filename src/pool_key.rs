@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{address, b256, keccak256, Address, B256, U256};
+
+use crate::orientation::order_tokens;
+
+// pools are deployed by the factory with CREATE2, salted with the hash of the key, so their address can be computed
+// off chain from the factory address and the hash of the pool init code. Forks that deploy the same pools with a
+// modified pool contract, and some chains with a different CREATE2 scheme, use another init code hash
+
+// the address of the Uniswap V3 factory on mainnet and most other chains
+pub const UNISWAP_V3_FACTORY: Address = address!("1F98431c8aD98523631AE4a59f267346ea31F984");
+// keccak256 of the UniswapV3Pool creation code, mirrors PoolAddress.POOL_INIT_CODE_HASH
+pub const POOL_INIT_CODE_HASH: B256 =
+    b256!("e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54");
+
+//Identifies a V3 pool deployed by a factory, mirrors PoolAddress.PoolKey. Tokens are sorted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub token_0: Address,
+    pub token_1: Address,
+    pub fee: u32,
+}
+
+impl PoolKey {
+    // returns the key of the pool of two tokens given in any order, mirrors PoolAddress.getPoolKey
+    pub fn new(token_a: Address, token_b: Address, fee: u32) -> Self {
+        let (token_0, token_1) = order_tokens(token_a, token_b);
+        Self {
+            token_0,
+            token_1,
+            fee,
+        }
+    }
+
+    // returns keccak256(abi.encode(token0, token1, fee)), the CREATE2 salt of the pool
+    pub fn salt(&self) -> B256 {
+        let mut encoded = [0u8; 96];
+        encoded[12..32].copy_from_slice(self.token_0.as_slice());
+        encoded[44..64].copy_from_slice(self.token_1.as_slice());
+        encoded[64..96].copy_from_slice(&U256::from(self.fee).to_be_bytes::<32>());
+        keccak256(encoded)
+    }
+
+    // returns the address of the pool deployed by `factory`, mirrors PoolAddress.computeAddress
+    pub fn compute_address(&self, factory: Address) -> Address {
+        self.compute_address_with(factory, POOL_INIT_CODE_HASH)
+    }
+
+    // returns the address of the pool deployed by `factory` for a pool contract with another init code hash
+    pub fn compute_address_with(&self, factory: Address, init_code_hash: B256) -> Address {
+        factory.create2(self.salt(), init_code_hash)
+    }
+}
+
+//The init code hash of the pools deployed by a factory, by chain id. Chains without an override use the default
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InitCodeHashes {
+    pub default: B256,
+    pub overrides: HashMap<u64, B256>,
+}
+
+impl InitCodeHashes {
+    pub fn new(default: B256) -> Self {
+        Self {
+            default,
+            overrides: HashMap::new(),
+        }
+    }
+
+    pub fn with_override(mut self, chain_id: u64, init_code_hash: B256) -> Self {
+        self.overrides.insert(chain_id, init_code_hash);
+        self
+    }
+
+    pub fn get(&self, chain_id: u64) -> B256 {
+        self.overrides
+            .get(&chain_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    // returns the address of the pool deployed by `factory` on the chain with `chain_id`
+    pub fn compute_address(&self, key: &PoolKey, factory: Address, chain_id: u64) -> Address {
+        key.compute_address_with(factory, self.get(chain_id))
+    }
+}
+
+impl Default for InitCodeHashes {
+    fn default() -> Self {
+        Self::new(POOL_INIT_CODE_HASH)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, b256};
+
+    use super::{InitCodeHashes, PoolKey, UNISWAP_V3_FACTORY};
+    use crate::fee_tier::FeeTier;
+
+    #[test]
+    fn test_compute_address() {
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+        //tokens are sorted
+        let key = PoolKey::new(weth, usdc, FeeTier::Low.fee());
+        assert_eq!(key, PoolKey::new(usdc, weth, FeeTier::Low.fee()));
+        assert_eq!((key.token_0, key.token_1), (usdc, weth));
+
+        assert_eq!(
+            key.compute_address(UNISWAP_V3_FACTORY),
+            address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")
+        );
+        assert_eq!(
+            PoolKey::new(usdc, weth, FeeTier::Medium.fee()).compute_address(UNISWAP_V3_FACTORY),
+            address!("8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8")
+        );
+
+        //chains without an override use the default init code hash
+        let other = b256!("0000000000000000000000000000000000000000000000000000000000000001");
+        let hashes = InitCodeHashes::default().with_override(324, other);
+        assert_eq!(
+            hashes.compute_address(&key, UNISWAP_V3_FACTORY, 1),
+            key.compute_address(UNISWAP_V3_FACTORY)
+        );
+        assert_eq!(
+            hashes.compute_address(&key, UNISWAP_V3_FACTORY, 324),
+            key.compute_address_with(UNISWAP_V3_FACTORY, other)
+        );
+        assert_ne!(
+            hashes.compute_address(&key, UNISWAP_V3_FACTORY, 324),
+            key.compute_address(UNISWAP_V3_FACTORY)
+        );
+    }
+}
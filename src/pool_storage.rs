@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{keccak256, Address, B256, I256, U256};
+
+use crate::{
+    decode,
+    fee_tier::FeeTier,
+    oracle::Observation,
+    pool::{PoolSnapshot, ProtocolFees},
+    tick_bitmap::position,
+    tick_math::{MAX_TICK, MIN_TICK},
+    variant::UniswapV3,
+};
+
+// storage slots of the UniswapV3Pool state variables
+pub const SLOT0_SLOT: U256 = U256::from_limbs([0, 0, 0, 0]);
+pub const FEE_GROWTH_GLOBAL_0_X_128_SLOT: U256 = U256::from_limbs([1, 0, 0, 0]);
+pub const FEE_GROWTH_GLOBAL_1_X_128_SLOT: U256 = U256::from_limbs([2, 0, 0, 0]);
+pub const PROTOCOL_FEES_SLOT: U256 = U256::from_limbs([3, 0, 0, 0]);
+pub const LIQUIDITY_SLOT: U256 = U256::from_limbs([4, 0, 0, 0]);
+pub const TICKS_SLOT: U256 = U256::from_limbs([5, 0, 0, 0]);
+pub const TICK_BITMAP_SLOT: U256 = U256::from_limbs([6, 0, 0, 0]);
+pub const POSITIONS_SLOT: U256 = U256::from_limbs([7, 0, 0, 0]);
+pub const OBSERVATIONS_SLOT: U256 = U256::from_limbs([8, 0, 0, 0]);
+
+//Reads raw contract storage, the signature matches revm's Database::storage so any EVM state that can serve storage
+//reads, like a forked database or a state dump, can back a pool snapshot
+pub trait StorageProvider {
+    type Error;
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error>;
+}
+
+impl<E, F: FnMut(Address, U256) -> Result<U256, E>> StorageProvider for F {
+    type Error = E;
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, E> {
+        self(address, index)
+    }
+}
+
+//Serves storage reads from a revm database, e.g. a CacheDB over a forked node or a state dump loaded into an InMemoryDB
+#[cfg(feature = "revm")]
+#[derive(Clone, Debug, Default)]
+pub struct RevmStorage<DB>(pub DB);
+
+#[cfg(feature = "revm")]
+impl<DB: revm::Database> StorageProvider for RevmStorage<DB> {
+    type Error = DB::Error;
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, DB::Error> {
+        // revm has its own alloy-primitives, the address goes through its bytes while U256 is the same ruint type
+        self.0
+            .storage(revm::primitives::Address::from(address.0 .0), index)
+    }
+}
+
+// returns the slot of the first of the four words of `ticks[tick]`
+pub fn tick_slot(tick: i32) -> U256 {
+    mapping_slot(I256::unchecked_from(tick).into_raw(), TICKS_SLOT)
+}
+
+// returns the slot of `tickBitmap[word_pos]`
+pub fn tick_bitmap_slot(word_pos: i16) -> U256 {
+    mapping_slot(I256::unchecked_from(word_pos).into_raw(), TICK_BITMAP_SLOT)
+}
+
+// returns the slot of the first of the four words of `positions[key]`, see `position_manager::position_key`
+pub fn position_slot(key: B256) -> U256 {
+    mapping_slot(U256::from_be_bytes(key.0), POSITIONS_SLOT)
+}
+
+// returns the slot of `observations[index]`, the array is fixed size so observations are stored inline
+pub fn observation_slot(index: u16) -> U256 {
+    OBSERVATIONS_SLOT + U256::from(index)
+}
+
+// keccak256(abi.encode(key, slot))
+fn mapping_slot(key: U256, slot: U256) -> U256 {
+    let mut encoded = [0u8; 64];
+    encoded[..32].copy_from_slice(&key.to_be_bytes::<32>());
+    encoded[32..].copy_from_slice(&slot.to_be_bytes::<32>());
+    U256::from_be_bytes(keccak256(encoded).0)
+}
+
+// returns a snapshot of the pool at `address` read from raw storage. The fee tier is an immutable of the pool and can
+// not be read from storage. Every tick bitmap word that can hold a tick for the tick spacing is read, followed by the
+// four words of each initialized tick
+pub fn load_pool<P: StorageProvider>(
+    provider: &mut P,
+    address: Address,
+    fee_tier: FeeTier,
+) -> Result<PoolSnapshot, P::Error> {
+    let tick_spacing = fee_tier.tick_spacing();
+    let protocol_fees: U256 = provider.storage(address, PROTOCOL_FEES_SLOT)?;
+
+    let mut pool = PoolSnapshot {
+        slot0: decode::slot0(provider.storage(address, SLOT0_SLOT)?),
+        liquidity: provider
+            .storage(address, LIQUIDITY_SLOT)?
+            .wrapping_to::<u128>(),
+        fee_tier,
+        fee_growth_global_0_x_128: provider.storage(address, FEE_GROWTH_GLOBAL_0_X_128_SLOT)?,
+        fee_growth_global_1_x_128: provider.storage(address, FEE_GROWTH_GLOBAL_1_X_128_SLOT)?,
+        protocol_fees: ProtocolFees {
+            token_0: protocol_fees.wrapping_to::<u128>(),
+            token_1: (protocol_fees >> 128_usize).wrapping_to::<u128>(),
+        },
+        ticks: HashMap::new(),
+        tick_bitmap: HashMap::new(),
+        variant: UniswapV3,
+    };
+
+    let (min_word, _) = position(MIN_TICK / tick_spacing);
+    let (max_word, _) = position(MAX_TICK / tick_spacing);
+    for word_pos in min_word..=max_word {
+        let word = provider.storage(address, tick_bitmap_slot(word_pos))?;
+        if word.is_zero() {
+            continue;
+        }
+        pool.tick_bitmap.insert(word_pos, word);
+
+        for bit_pos in 0..256 {
+            if !word.bit(bit_pos) {
+                continue;
+            }
+
+            let tick = ((word_pos as i32) * 256 + bit_pos as i32) * tick_spacing;
+            let slot = tick_slot(tick);
+            let words = [
+                provider.storage(address, slot)?,
+                provider.storage(address, slot + U256::from(1))?,
+                provider.storage(address, slot + U256::from(2))?,
+                provider.storage(address, slot + U256::from(3))?,
+            ];
+            pool.ticks.insert(tick, decode::tick_info(&words));
+        }
+    }
+
+    Ok(pool)
+}
+
+// returns the initialized observations of the pool at `address` in the order they are stored, up to the cardinality
+pub fn load_observations<P: StorageProvider>(
+    provider: &mut P,
+    address: Address,
+    observation_cardinality: u16,
+) -> Result<Vec<Observation>, P::Error> {
+    let mut observations = Vec::with_capacity(observation_cardinality as usize);
+    for index in 0..observation_cardinality {
+        let observation = decode::observation(provider.storage(address, observation_slot(index))?);
+        if observation.initialized {
+            observations.push(observation);
+        }
+    }
+
+    Ok(observations)
+}
+
+// returns the observations array of the pool at `address` up to the cardinality next, including the slots that are not
+// initialized, to build an `Oracle` from together with the observation fields of slot0
+pub fn load_observation_array<P: StorageProvider>(
+    provider: &mut P,
+    address: Address,
+    observation_cardinality_next: u16,
+) -> Result<Vec<Observation>, P::Error> {
+    (0..observation_cardinality_next)
+        .map(|index| {
+            Ok(decode::observation(
+                provider.storage(address, observation_slot(index))?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use alloy_primitives::{address, keccak256, Address, U256};
+
+    use super::{
+        load_pool, tick_bitmap_slot, tick_slot, FEE_GROWTH_GLOBAL_0_X_128_SLOT, LIQUIDITY_SLOT,
+        SLOT0_SLOT,
+    };
+    use crate::{fee_tier::FeeTier, pool::PoolSnapshot, tick_math::get_sqrt_ratio_at_tick};
+
+    const POOL: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+
+    #[test]
+    fn test_slots() {
+        //keys are abi encoded as 32 bytes followed by the slot of the mapping
+        let mut encoded = [0u8; 64];
+        encoded[63] = 5;
+        assert_eq!(tick_slot(0), U256::from_be_bytes(keccak256(encoded).0));
+
+        //negative keys are sign extended
+        encoded[..30].fill(0xff);
+        encoded[30..32].copy_from_slice(&[0xff, 0xc4]);
+        assert_eq!(tick_slot(-60), U256::from_be_bytes(keccak256(encoded).0));
+
+        encoded[63] = 6;
+        encoded[..32].fill(0xff);
+        assert_eq!(
+            tick_bitmap_slot(-1),
+            U256::from_be_bytes(keccak256(encoded).0)
+        );
+    }
+
+    fn pool() -> PoolSnapshot {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(-200).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-887220, 887220, 1e20 as i128).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-15420, -15360, 1e19 as i128).unwrap();
+        pool.fee_growth_global_0_x_128 = U256::from(12345);
+        pool
+    }
+
+    // returns the storage words of the pool the way the contract lays them out
+    fn pool_storage(pool: &PoolSnapshot) -> HashMap<U256, U256> {
+        let mut storage = HashMap::new();
+        let slot0 = U256::from(pool.slot0.sqrt_price_x_96)
+            | (U256::from(pool.slot0.tick as u32 & 0xffffff) << 160)
+            | (U256::from(pool.slot0.observation_cardinality) << 200)
+            | (U256::from(pool.slot0.observation_cardinality_next) << 216)
+            | (U256::from(1) << 240);
+        storage.insert(SLOT0_SLOT, slot0);
+        storage.insert(LIQUIDITY_SLOT, U256::from(pool.liquidity));
+        storage.insert(
+            FEE_GROWTH_GLOBAL_0_X_128_SLOT,
+            pool.fee_growth_global_0_x_128,
+        );
+        for (&word_pos, &word) in &pool.tick_bitmap {
+            storage.insert(tick_bitmap_slot(word_pos), word);
+        }
+        for (&tick, info) in &pool.ticks {
+            storage.insert(
+                tick_slot(tick),
+                U256::from(info.liquidity_gross) | (U256::from(info.liquidity_net as u128) << 128),
+            );
+            storage.insert(tick_slot(tick) + U256::from(3), U256::from(1) << 248);
+        }
+        storage
+    }
+
+    #[test]
+    fn test_load_pool() {
+        let pool = pool();
+        let storage = pool_storage(&pool);
+
+        let mut reads = 0;
+        let loaded = load_pool(
+            &mut |address: Address, index: U256| -> Result<U256, ()> {
+                assert_eq!(address, POOL);
+                reads += 1;
+                Ok(storage.get(&index).copied().unwrap_or_default())
+            },
+            POOL,
+            FeeTier::Medium,
+        )
+        .unwrap();
+
+        assert_eq!(loaded, pool);
+        assert!(reads > pool.ticks.len() * 4);
+
+        //provider errors are returned as is
+        let result = load_pool(
+            &mut |_: Address, _: U256| -> Result<U256, &str> { Err("missing") },
+            POOL,
+            FeeTier::Medium,
+        );
+        assert_eq!(result, Err("missing"));
+    }
+
+    #[cfg(feature = "revm")]
+    #[test]
+    fn test_load_pool_revm() {
+        use revm::{db::InMemoryDB, primitives::Address as RevmAddress};
+
+        use super::RevmStorage;
+
+        let pool = pool();
+        let mut db = InMemoryDB::default();
+        for (index, value) in pool_storage(&pool) {
+            db.insert_account_storage(RevmAddress::from(POOL.0 .0), index, value)
+                .unwrap();
+        }
+
+        let loaded = load_pool(&mut RevmStorage(db), POOL, FeeTier::Medium).unwrap();
+        assert_eq!(loaded, pool);
+    }
+}
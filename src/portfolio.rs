@@ -0,0 +1,298 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    analytics::value_in_token_1,
+    backtest::{Backtest, Event, PositionReport},
+    error::UniswapV3MathError,
+    full_math::mul_div,
+    pool::PoolSnapshot,
+    sqrt_price_math::Q96,
+};
+
+// an event of a block, applied to the pool at index `pool` of the portfolio
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortfolioEvent {
+    pub pool: usize,
+    pub event: Event,
+}
+
+impl PortfolioEvent {
+    pub fn new(pool: usize, event: Event) -> Self {
+        Self { pool, event }
+    }
+}
+
+// identifies a tracked position of a portfolio, the position at index `position` of the pool at index `pool`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PositionId {
+    pub pool: usize,
+    pub position: usize,
+}
+
+//The holdings of every tracked position, per pool and across all pools. The amounts and values of a pool are in its
+//own tokens, the totals are in the numeraire of the token1 prices passed to `Portfolio::report`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PortfolioReport {
+    pub positions: Vec<(PositionId, PositionReport)>,
+    // the sum of the reports of the positions in each pool, by pool index
+    pub pools: Vec<PositionReport>,
+    // the value of all fees earned
+    pub fees_value: U256,
+    pub value: U256,
+    // the value of holding the deposited amounts of every position instead
+    pub hodl_value: U256,
+    // value - hodl_value
+    pub pnl: I256,
+}
+
+//Replays blocks of events against several pools while tracking positions across them, e.g. to backtest a vault that
+//spreads its liquidity over multiple pools. Each pool is a `Backtest`, so its positions earn fees and affect swaps
+#[derive(Clone, Debug, Default)]
+pub struct Portfolio {
+    pub pools: Vec<Backtest>,
+}
+
+impl Portfolio {
+    pub fn new(pools: Vec<PoolSnapshot>) -> Self {
+        Self {
+            pools: pools.into_iter().map(Backtest::new).collect(),
+        }
+    }
+
+    // mints a tracked position in the pool at index `pool`
+    pub fn add_position(
+        &mut self,
+        pool: usize,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    ) -> Result<PositionId, UniswapV3MathError> {
+        let position = self
+            .pools
+            .get_mut(pool)
+            .ok_or(UniswapV3MathError::PoolNotFound)?
+            .add_position(tick_lower, tick_upper, liquidity)?;
+        Ok(PositionId { pool, position })
+    }
+
+    // see `Backtest::rebalance`
+    pub fn rebalance(
+        &mut self,
+        id: PositionId,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<(), UniswapV3MathError> {
+        self.pools
+            .get_mut(id.pool)
+            .ok_or(UniswapV3MathError::PoolNotFound)?
+            .rebalance(id.position, tick_lower, tick_upper)
+    }
+
+    // applies the events of a block to their pools in order. The events are applied to forks of the pools they touch,
+    // which replace the pools once all of them succeeded, so if an event fails or refers to a pool index that is out
+    // of bounds no pool is changed
+    pub fn apply_block(&mut self, events: &[PortfolioEvent]) -> Result<(), UniswapV3MathError> {
+        let mut forks = HashMap::new();
+        for event in events {
+            let fork = match forks.entry(event.pool) {
+                Entry::Occupied(fork) => fork.into_mut(),
+                Entry::Vacant(fork) => fork.insert(
+                    self.pools
+                        .get(event.pool)
+                        .ok_or(UniswapV3MathError::PoolNotFound)?
+                        .clone(),
+                ),
+            };
+            fork.apply(&event.event)?;
+        }
+
+        for (index, fork) in forks {
+            self.pools[index] = fork;
+        }
+        Ok(())
+    }
+
+    // returns the reports of all tracked positions with their sums per pool, and the totals valued with the price of
+    // the token1 of each pool in a common numeraire as a Q96, by pool index. Fails unless there is a price for every
+    // pool
+    pub fn report(
+        &self,
+        token_1_prices_x_96: &[U256],
+    ) -> Result<PortfolioReport, UniswapV3MathError> {
+        if token_1_prices_x_96.len() != self.pools.len() {
+            return Err(UniswapV3MathError::PriceCountMismatch);
+        }
+
+        let mut report = PortfolioReport::default();
+        for (index, (backtest, &price_x_96)) in
+            self.pools.iter().zip(token_1_prices_x_96).enumerate()
+        {
+            let mut pool_report = PositionReport::default();
+            for position in 0..backtest.positions.len() {
+                let position_report = backtest.report(position)?;
+                pool_report.amount_0 += position_report.amount_0;
+                pool_report.amount_1 += position_report.amount_1;
+                pool_report.fees_0 += position_report.fees_0;
+                pool_report.fees_1 += position_report.fees_1;
+                pool_report.value += position_report.value;
+                pool_report.hodl_value += position_report.hodl_value;
+                report.positions.push((
+                    PositionId {
+                        pool: index,
+                        position,
+                    },
+                    position_report,
+                ));
+            }
+
+            let fees_value = value_in_token_1(
+                pool_report.fees_0,
+                pool_report.fees_1,
+                backtest.pool.slot0.sqrt_price_x_96,
+            )?;
+            report.fees_value += mul_div(fees_value, price_x_96, Q96)?;
+            report.value += mul_div(pool_report.value, price_x_96, Q96)?;
+            report.hodl_value += mul_div(pool_report.hodl_value, price_x_96, Q96)?;
+            report.pools.push(pool_report);
+        }
+
+        report.pnl = I256::try_from(report.value)
+            .ok()
+            .zip(I256::try_from(report.hodl_value).ok())
+            .and_then(|(value, hodl_value)| value.checked_sub(hodl_value))
+            .ok_or(UniswapV3MathError::Overflow)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{Portfolio, PortfolioEvent, PositionId};
+    use crate::{
+        backtest::{Backtest, Event},
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        pool::PoolSnapshot,
+        sqrt_price_math::Q96,
+        test_utils,
+        test_utils::PoolBuilder,
+    };
+
+    fn pool(fee_tier: FeeTier) -> PoolSnapshot {
+        PoolBuilder::at_tick(0)
+            .fee_tier(fee_tier)
+            .full_range(1e20 as i128)
+            .build()
+    }
+
+    fn swap(pool: usize, zero_for_one: bool, amount_in: u128) -> PortfolioEvent {
+        PortfolioEvent::new(pool, test_utils::swap(zero_for_one, amount_in))
+    }
+
+    #[test]
+    fn test_portfolio() {
+        let pools = vec![pool(FeeTier::Medium), pool(FeeTier::Low)];
+        let mut portfolio = Portfolio::new(pools.clone());
+        let mut backtests: Vec<_> = pools.into_iter().map(Backtest::new).collect();
+
+        let first = portfolio.add_position(0, -600, 600, 1e20 as u128).unwrap();
+        let second = portfolio.add_position(1, -100, 100, 1e20 as u128).unwrap();
+        assert_eq!(
+            second,
+            PositionId {
+                pool: 1,
+                position: 0
+            }
+        );
+        backtests[0].add_position(-600, 600, 1e20 as u128).unwrap();
+        backtests[1].add_position(-100, 100, 1e20 as u128).unwrap();
+
+        let blocks = [
+            vec![swap(0, true, 1e18 as u128), swap(1, false, 1e18 as u128)],
+            vec![swap(1, true, 2e18 as u128), swap(0, false, 1e18 as u128)],
+        ];
+        for block in &blocks {
+            portfolio.apply_block(block).unwrap();
+            for event in block {
+                backtests[event.pool].apply(&event.event).unwrap();
+            }
+        }
+
+        //each pool is replayed like a backtest of it alone
+        let report = portfolio.report(&[Q96, Q96 * U256::from(2)]).unwrap();
+        assert_eq!(report.positions.len(), 2);
+        assert_eq!(report.positions[0].0, first);
+        for (index, backtest) in backtests.iter().enumerate() {
+            assert_eq!(portfolio.pools[index].pool, backtest.pool);
+            assert_eq!(report.pools[index], backtest.report(0).unwrap());
+        }
+
+        //the totals weigh each pool by the price of its token1
+        let value = report.pools[0].value + report.pools[1].value * U256::from(2);
+        let hodl_value = report.pools[0].hodl_value + report.pools[1].hodl_value * U256::from(2);
+        assert_eq!(report.value, value);
+        assert_eq!(report.hodl_value, hodl_value);
+        assert_eq!(
+            report.pnl,
+            I256::from_raw(value) - I256::from_raw(hodl_value)
+        );
+        assert!(report.fees_value > U256::ZERO);
+
+        //rebalancing a position only affects its pool
+        portfolio.rebalance(first, -1200, 0).unwrap();
+        assert_eq!(portfolio.pools[0].positions[0].rebalances, 1);
+        assert_eq!(portfolio.pools[1].pool, backtests[1].pool);
+    }
+
+    #[test]
+    fn test_portfolio_errors() {
+        let mut portfolio = Portfolio::new(vec![pool(FeeTier::Medium), pool(FeeTier::Low)]);
+        let id = portfolio.add_position(0, -600, 600, 1e20 as u128).unwrap();
+
+        //indices out of bounds are errors
+        assert!(matches!(
+            portfolio.add_position(2, -600, 600, 1e20 as u128),
+            Err(UniswapV3MathError::PoolNotFound)
+        ));
+        assert!(matches!(
+            portfolio.rebalance(PositionId { pool: 2, ..id }, -1200, 0),
+            Err(UniswapV3MathError::PoolNotFound)
+        ));
+        assert!(matches!(
+            portfolio.rebalance(PositionId { position: 1, ..id }, -1200, 0),
+            Err(UniswapV3MathError::PositionNotFound)
+        ));
+        assert!(matches!(
+            portfolio.report(&[Q96]),
+            Err(UniswapV3MathError::PriceCountMismatch)
+        ));
+
+        //a block with a failing event changes no pool, not even the ones of the events before it
+        let before = portfolio.clone();
+        let result = portfolio.apply_block(&[
+            swap(0, true, 1e18 as u128),
+            swap(1, true, 1e18 as u128),
+            swap(2, true, 1e18 as u128),
+        ]);
+        assert!(matches!(result, Err(UniswapV3MathError::PoolNotFound)));
+        let burn = PortfolioEvent::new(
+            1,
+            Event::Burn {
+                tick_lower: -600,
+                tick_upper: 600,
+                liquidity: 1,
+            },
+        );
+        assert!(portfolio
+            .apply_block(&[swap(0, true, 1e18 as u128), burn])
+            .is_err());
+        for (pool, before) in portfolio.pools.iter().zip(&before.pools) {
+            assert_eq!(pool.pool, before.pool);
+            assert_eq!(pool.positions, before.positions);
+        }
+    }
+}
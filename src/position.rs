@@ -0,0 +1,741 @@
+use std::collections::{BTreeSet, HashMap};
+
+use alloy_primitives::{Address, B256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    full_math::{mul_div, Q128},
+    liquidity_math::add_delta,
+    pool::PoolSnapshot,
+    position_manager::position_key,
+    q_number::{FeeGrowthX128, LiquidityNet, SqrtPriceX96},
+    sqrt_price_math::{amounts_for_range, Rounding},
+    storage::{InitializedTicks, TickStorage},
+    tick::get_fee_growth_inside,
+    tick_math::get_sqrt_ratio_at_tick,
+    variant::PoolVariant,
+};
+
+// info stored for each user's position
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    // the amount of liquidity owned by this position
+    pub liquidity: u128,
+    // fee growth per unit of liquidity as of the last update to liquidity or fees owed
+    pub fee_growth_inside_0_last_x_128: U256,
+    pub fee_growth_inside_1_last_x_128: U256,
+    // the fees owed to the position owner in token0/token1
+    pub tokens_owed_0: u128,
+    pub tokens_owed_1: u128,
+}
+
+impl Position {
+    //Credits accumulated fees to a user's position, mirrors Position.update
+    pub fn update(
+        &mut self,
+        liquidity_delta: impl Into<LiquidityNet>,
+        fee_growth_inside_0_x_128: impl Into<FeeGrowthX128>,
+        fee_growth_inside_1_x_128: impl Into<FeeGrowthX128>,
+    ) -> Result<(), UniswapV3MathError> {
+        let liquidity_delta = liquidity_delta.into().0;
+        let fee_growth_inside_0_x_128 = fee_growth_inside_0_x_128.into().0;
+        let fee_growth_inside_1_x_128 = fee_growth_inside_1_x_128.into().0;
+        let liquidity_next = if liquidity_delta == 0 {
+            // disallow pokes for 0 liquidity positions
+            if self.liquidity == 0 {
+                return Err(UniswapV3MathError::NP);
+            }
+            self.liquidity
+        } else {
+            add_delta(self.liquidity, liquidity_delta)?
+        };
+
+        // calculate accumulated fees, overflow of the uint128 cast is accepted like in the contract
+        let (tokens_owed_0, tokens_owed_1) = fees_owed(
+            self.liquidity,
+            self.fee_growth_inside_0_last_x_128,
+            self.fee_growth_inside_1_last_x_128,
+            fee_growth_inside_0_x_128,
+            fee_growth_inside_1_x_128,
+        )?;
+
+        // update the position
+        self.liquidity = liquidity_next;
+        self.fee_growth_inside_0_last_x_128 = fee_growth_inside_0_x_128;
+        self.fee_growth_inside_1_last_x_128 = fee_growth_inside_1_x_128;
+        self.tokens_owed_0 = self
+            .tokens_owed_0
+            .wrapping_add(tokens_owed_0.wrapping_to::<u128>());
+        self.tokens_owed_1 = self
+            .tokens_owed_1
+            .wrapping_add(tokens_owed_1.wrapping_to::<u128>());
+
+        Ok(())
+    }
+}
+
+// returns the fees accrued by `liquidity` between the last and the current fee growth inside the range
+fn fees_owed(
+    liquidity: u128,
+    fee_growth_inside_0_last_x_128: U256,
+    fee_growth_inside_1_last_x_128: U256,
+    fee_growth_inside_0_x_128: U256,
+    fee_growth_inside_1_x_128: U256,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    Ok((
+        mul_div(
+            fee_growth_inside_0_x_128.wrapping_sub(fee_growth_inside_0_last_x_128),
+            U256::from(liquidity),
+            Q128,
+        )?,
+        mul_div(
+            fee_growth_inside_1_x_128.wrapping_sub(fee_growth_inside_1_last_x_128),
+            U256::from(liquidity),
+            Q128,
+        )?,
+    ))
+}
+
+// returns the fees in token0/token1 that a position has earned since its fee growth inside was last checkpointed,
+// not including tokens already credited to tokens_owed
+pub fn uncollected_fees(
+    pool: &PoolSnapshot,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+    fee_growth_inside_0_last_x_128: impl Into<FeeGrowthX128>,
+    fee_growth_inside_1_last_x_128: impl Into<FeeGrowthX128>,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    let fee_growth_inside_0_last_x_128 = fee_growth_inside_0_last_x_128.into().0;
+    let fee_growth_inside_1_last_x_128 = fee_growth_inside_1_last_x_128.into().0;
+    let (fee_growth_inside_0_x_128, fee_growth_inside_1_x_128) = get_fee_growth_inside(
+        &pool.ticks,
+        tick_lower,
+        tick_upper,
+        pool.slot0.tick,
+        pool.fee_growth_global_0_x_128,
+        pool.fee_growth_global_1_x_128,
+    );
+
+    fees_owed(
+        liquidity,
+        fee_growth_inside_0_last_x_128,
+        fee_growth_inside_1_last_x_128,
+        fee_growth_inside_0_x_128,
+        fee_growth_inside_1_x_128,
+    )
+}
+
+// returns the token0/token1 amounts that `liquidity` in the given range is worth at `sqrt_price_x_96`,
+// rounded down like the amounts returned when burning the position
+pub fn amounts_at_price(
+    sqrt_price_x_96: impl Into<SqrtPriceX96>,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    amounts_at_sqrt_ratios(
+        sqrt_price_x_96.into().0,
+        get_sqrt_ratio_at_tick(tick_lower)?,
+        get_sqrt_ratio_at_tick(tick_upper)?,
+        liquidity,
+    )
+}
+
+fn amounts_at_sqrt_ratios(
+    sqrt_price_x_96: U256,
+    sqrt_ratio_lower_x_96: U256,
+    sqrt_ratio_upper_x_96: U256,
+    liquidity: u128,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    amounts_for_range(
+        sqrt_price_x_96,
+        sqrt_ratio_lower_x_96,
+        sqrt_ratio_upper_x_96,
+        liquidity,
+        Rounding::Down,
+    )
+}
+
+//A position and the range it is in, see `value_many`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PositionRange {
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub position: Position,
+}
+
+//What a position is worth at the current pool price
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PositionValue {
+    // the principal, rounded down like the amounts returned when burning the position
+    pub amount_0: U256,
+    pub amount_1: U256,
+    // the fees owed to the position plus the fees it earned since it was last updated
+    pub fees_0: U256,
+    pub fees_1: U256,
+}
+
+// returns the value of each of `positions` in `pool`, in the order of the positions. Positions usually share their
+// ranges, so the sqrt ratio of each tick and the fee growth inside each range are only computed once
+pub fn value_many<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    positions: &[PositionRange],
+) -> Result<Vec<PositionValue>, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let mut sqrt_ratios = HashMap::new();
+    let mut fee_growths_inside = HashMap::new();
+    let mut sqrt_ratio_at_tick = |tick: i32| -> Result<U256, UniswapV3MathError> {
+        if let Some(&sqrt_ratio) = sqrt_ratios.get(&tick) {
+            return Ok(sqrt_ratio);
+        }
+        let sqrt_ratio = get_sqrt_ratio_at_tick(tick)?;
+        sqrt_ratios.insert(tick, sqrt_ratio);
+        Ok(sqrt_ratio)
+    };
+
+    positions
+        .iter()
+        .map(|range| {
+            let position = &range.position;
+            let (amount_0, amount_1) = amounts_at_sqrt_ratios(
+                pool.slot0.sqrt_price_x_96,
+                sqrt_ratio_at_tick(range.tick_lower)?,
+                sqrt_ratio_at_tick(range.tick_upper)?,
+                position.liquidity,
+            )?;
+
+            let (fee_growth_inside_0_x_128, fee_growth_inside_1_x_128) = *fee_growths_inside
+                .entry((range.tick_lower, range.tick_upper))
+                .or_insert_with(|| {
+                    get_fee_growth_inside(
+                        &pool.ticks,
+                        range.tick_lower,
+                        range.tick_upper,
+                        pool.slot0.tick,
+                        pool.fee_growth_global_0_x_128,
+                        pool.fee_growth_global_1_x_128,
+                    )
+                });
+            let (fees_0, fees_1) = fees_owed(
+                position.liquidity,
+                position.fee_growth_inside_0_last_x_128,
+                position.fee_growth_inside_1_last_x_128,
+                fee_growth_inside_0_x_128,
+                fee_growth_inside_1_x_128,
+            )?;
+
+            Ok(PositionValue {
+                amount_0,
+                amount_1,
+                fees_0: fees_0.saturating_add(U256::from(position.tokens_owed_0)),
+                fees_1: fees_1.saturating_add(U256::from(position.tokens_owed_1)),
+            })
+        })
+        .collect()
+}
+
+//A pool together with its positions, keyed by `position_key(owner, tick_lower, tick_upper)` like the positions
+//mapping of UniswapV3Pool, so simulations with several actors, e.g. a vault, a JIT bot and passive LPs, credit fees and
+//tokens owed to the owner that earned them. Swaps are applied to `pool` directly
+#[derive(Clone, Debug)]
+pub struct PoolPositions {
+    pub pool: PoolSnapshot,
+    positions: HashMap<B256, Position>,
+    // the ranges each owner has a position in, the keys can not be reversed
+    owners: HashMap<Address, BTreeSet<(i32, i32)>>,
+}
+
+impl PoolPositions {
+    pub fn new(pool: PoolSnapshot) -> Self {
+        Self {
+            pool,
+            positions: HashMap::new(),
+            owners: HashMap::new(),
+        }
+    }
+
+    // returns the position of `owner` in the given range, None if it was never minted
+    pub fn position(&self, owner: Address, tick_lower: i32, tick_upper: i32) -> Option<&Position> {
+        self.positions
+            .get(&position_key(owner, tick_lower, tick_upper))
+    }
+
+    // returns the ranges and positions of `owner`, ordered by range
+    pub fn positions_of(&self, owner: Address) -> impl Iterator<Item = (i32, i32, &Position)> + '_ {
+        self.owners
+            .get(&owner)
+            .into_iter()
+            .flatten()
+            .map(move |&(tick_lower, tick_upper)| {
+                (
+                    tick_lower,
+                    tick_upper,
+                    &self.positions[&position_key(owner, tick_lower, tick_upper)],
+                )
+            })
+    }
+
+    // adds `amount` of liquidity to the position of `owner` and returns the amounts of token0/token1 owed to the pool,
+    // mirrors UniswapV3Pool.mint
+    pub fn mint(
+        &mut self,
+        owner: Address,
+        tick_lower: i32,
+        tick_upper: i32,
+        amount: u128,
+    ) -> Result<(U256, U256), UniswapV3MathError> {
+        if amount == 0 {
+            return Err(UniswapV3MathError::LiquidityIsZero);
+        }
+        let liquidity_delta = i128::try_from(amount).map_err(|_| UniswapV3MathError::Overflow)?;
+        // the position is updated after the pool, so the liquidity it can take is checked before the pool is changed
+        let liquidity = self
+            .position(owner, tick_lower, tick_upper)
+            .map_or(0, |position| position.liquidity);
+        add_delta(liquidity, liquidity_delta)?;
+
+        // the fee growth inside is read after the ticks are updated, newly initialized ticks start with the global fee
+        // growth outside if they are at or below the current tick
+        let (amount_0, amount_1) =
+            self.pool
+                .modify_position(tick_lower, tick_upper, liquidity_delta)?;
+        let (fee_growth_inside_0_x_128, fee_growth_inside_1_x_128) =
+            self.fee_growth_inside(tick_lower, tick_upper);
+        self.position_mut(owner, tick_lower, tick_upper).update(
+            liquidity_delta,
+            fee_growth_inside_0_x_128,
+            fee_growth_inside_1_x_128,
+        )?;
+
+        Ok((amount_0.into_raw(), amount_1.into_raw()))
+    }
+
+    // removes `amount` of liquidity from the position of `owner` and credits the principal to its tokens owed, returns
+    // the principal. An `amount` of 0 pokes the position, which only credits the fees, mirrors UniswapV3Pool.burn
+    pub fn burn(
+        &mut self,
+        owner: Address,
+        tick_lower: i32,
+        tick_upper: i32,
+        amount: u128,
+    ) -> Result<(U256, U256), UniswapV3MathError> {
+        let liquidity_delta = -i128::try_from(amount).map_err(|_| UniswapV3MathError::Overflow)?;
+        let key = position_key(owner, tick_lower, tick_upper);
+
+        // removing liquidity does not change the fee growth outside of the ticks, but may clear them, so the position
+        // is updated first, which also fails before the pool is changed if it does not have the liquidity
+        let (fee_growth_inside_0_x_128, fee_growth_inside_1_x_128) =
+            self.fee_growth_inside(tick_lower, tick_upper);
+        let mut position = self.positions.get(&key).copied().unwrap_or_default();
+        position.update(
+            liquidity_delta,
+            fee_growth_inside_0_x_128,
+            fee_growth_inside_1_x_128,
+        )?;
+
+        let (amount_0, amount_1) =
+            self.pool
+                .modify_position(tick_lower, tick_upper, liquidity_delta)?;
+        let (amount_0, amount_1) = (amount_0.unsigned_abs(), amount_1.unsigned_abs());
+        position.tokens_owed_0 = position
+            .tokens_owed_0
+            .wrapping_add(amount_0.wrapping_to::<u128>());
+        position.tokens_owed_1 = position
+            .tokens_owed_1
+            .wrapping_add(amount_1.wrapping_to::<u128>());
+        *self.position_mut(owner, tick_lower, tick_upper) = position;
+
+        Ok((amount_0, amount_1))
+    }
+
+    // collects up to the requested amounts of the tokens owed to the position of `owner` and returns the amounts
+    // collected, mirrors UniswapV3Pool.collect
+    pub fn collect(
+        &mut self,
+        owner: Address,
+        tick_lower: i32,
+        tick_upper: i32,
+        amount_0_requested: u128,
+        amount_1_requested: u128,
+    ) -> (u128, u128) {
+        let Some(position) = self
+            .positions
+            .get_mut(&position_key(owner, tick_lower, tick_upper))
+        else {
+            return (0, 0);
+        };
+
+        let amount_0 = amount_0_requested.min(position.tokens_owed_0);
+        let amount_1 = amount_1_requested.min(position.tokens_owed_1);
+        position.tokens_owed_0 -= amount_0;
+        position.tokens_owed_1 -= amount_1;
+
+        (amount_0, amount_1)
+    }
+
+    // returns the fees and principal `owner` can collect across all its positions, i.e. the tokens owed plus the fees
+    // earned since each position was last updated, without burning any liquidity
+    pub fn tokens_owed(&self, owner: Address) -> Result<(U256, U256), UniswapV3MathError> {
+        self.positions_of(owner).try_fold(
+            (U256::ZERO, U256::ZERO),
+            |(owed_0, owed_1), (tick_lower, tick_upper, position)| {
+                let (fees_0, fees_1) = uncollected_fees(
+                    &self.pool,
+                    tick_lower,
+                    tick_upper,
+                    position.liquidity,
+                    position.fee_growth_inside_0_last_x_128,
+                    position.fee_growth_inside_1_last_x_128,
+                )?;
+                Ok((
+                    owed_0 + fees_0 + U256::from(position.tokens_owed_0),
+                    owed_1 + fees_1 + U256::from(position.tokens_owed_1),
+                ))
+            },
+        )
+    }
+
+    fn position_mut(&mut self, owner: Address, tick_lower: i32, tick_upper: i32) -> &mut Position {
+        self.owners
+            .entry(owner)
+            .or_default()
+            .insert((tick_lower, tick_upper));
+        self.positions
+            .entry(position_key(owner, tick_lower, tick_upper))
+            .or_default()
+    }
+
+    fn fee_growth_inside(&self, tick_lower: i32, tick_upper: i32) -> (U256, U256) {
+        get_fee_growth_inside(
+            &self.pool.ticks,
+            tick_lower,
+            tick_upper,
+            self.pool.slot0.tick,
+            self.pool.fee_growth_global_0_x_128,
+            self.pool.fee_growth_global_1_x_128,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, Address, I256, U256};
+
+    use std::str::FromStr;
+
+    use super::{
+        amounts_at_price, uncollected_fees, value_many, PoolPositions, Position, PositionRange,
+    };
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        full_math::{mul_div, Q128},
+        pool::{PersistentPoolSnapshot, PoolSnapshot},
+        tick::Tick,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    #[test]
+    fn test_position_update() {
+        //fails to poke a position without liquidity
+        let mut position = Position::default();
+        let result = position.update(0, U256::ZERO, U256::ZERO);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Positions without liquidity can not be poked"
+        );
+
+        //credits fees accrued since the last update
+        position.update(100, Q128, Q128 * U256::from(2)).unwrap();
+        assert_eq!(position.liquidity, 100);
+        assert_eq!(position.tokens_owed_0, 0);
+        assert_eq!(position.tokens_owed_1, 0);
+
+        position
+            .update(-50, Q128 * U256::from(3), Q128 * U256::from(3))
+            .unwrap();
+        assert_eq!(position.liquidity, 50);
+        assert_eq!(position.tokens_owed_0, 200);
+        assert_eq!(position.tokens_owed_1, 100);
+        assert_eq!(
+            position.fee_growth_inside_0_last_x_128,
+            Q128 * U256::from(3)
+        );
+
+        //fee growth inside wraps like the contract
+        let mut position = Position {
+            liquidity: 1,
+            fee_growth_inside_0_last_x_128: U256::MAX - Q128 + U256::from(1),
+            ..Default::default()
+        };
+        position.update(0, Q128, U256::ZERO).unwrap();
+        assert_eq!(position.tokens_owed_0, 2);
+    }
+
+    #[test]
+    fn test_uncollected_fees() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.fee_growth_global_0_x_128 = Q128 * U256::from(10);
+        pool.fee_growth_global_1_x_128 = Q128 * U256::from(20);
+        pool.ticks.insert(
+            -60,
+            Tick {
+                fee_growth_outside_0_x_128: Q128 * U256::from(2),
+                fee_growth_outside_1_x_128: Q128 * U256::from(4),
+                ..Default::default()
+            },
+        );
+        pool.ticks.insert(
+            60,
+            Tick {
+                fee_growth_outside_0_x_128: Q128,
+                fee_growth_outside_1_x_128: Q128,
+                ..Default::default()
+            },
+        );
+
+        // fee growth inside is 10 - 2 - 1 = 7 and 20 - 4 - 1 = 15
+        let fees =
+            uncollected_fees(&pool, -60, 60, 1000, Q128 * U256::from(5), U256::ZERO).unwrap();
+        assert_eq!(fees, (U256::from(2000), U256::from(15000)));
+
+        // a position entirely above the current tick has not earned anything since the checkpoint
+        let fees = uncollected_fees(&pool, 120, 180, 1000, U256::ZERO, U256::ZERO).unwrap();
+        assert_eq!(fees, (U256::ZERO, U256::ZERO));
+    }
+
+    #[test]
+    fn test_amounts_at_price() {
+        let liquidity = 1e18 as u128;
+
+        //price below the range is all token0
+        let (amount_0, amount_1) =
+            amounts_at_price(get_sqrt_ratio_at_tick(-120).unwrap(), -60, 60, liquidity).unwrap();
+        assert_eq!(amount_0, U256::from_str("5999709018652706").unwrap());
+        assert_eq!(amount_1, U256::ZERO);
+
+        //price exactly at the lower tick is still all token0
+        let (amount_0_at_lower, amount_1) =
+            amounts_at_price(get_sqrt_ratio_at_tick(-60).unwrap(), -60, 60, liquidity).unwrap();
+        assert_eq!(amount_0_at_lower, amount_0);
+        assert_eq!(amount_1, U256::ZERO);
+
+        //price inside the range holds both tokens
+        let (amount_0, amount_1) =
+            amounts_at_price(get_sqrt_ratio_at_tick(0).unwrap(), -60, 60, liquidity).unwrap();
+        assert_eq!(amount_0, U256::from_str("2995354955910780").unwrap());
+        assert_eq!(amount_1, U256::from_str("2995354955910780").unwrap());
+
+        //price exactly at the upper tick is all token1
+        let (amount_0, amount_1_at_upper) =
+            amounts_at_price(get_sqrt_ratio_at_tick(60).unwrap(), -60, 60, liquidity).unwrap();
+        assert_eq!(amount_0, U256::ZERO);
+
+        //price above the range is all token1
+        let (amount_0, amount_1) =
+            amounts_at_price(get_sqrt_ratio_at_tick(120).unwrap(), -60, 60, liquidity).unwrap();
+        assert_eq!(amount_0, U256::ZERO);
+        assert_eq!(amount_1, amount_1_at_upper);
+
+        //fails for ticks out of bounds
+        assert!(amounts_at_price(get_sqrt_ratio_at_tick(0).unwrap(), -887273, 60, 1).is_err());
+    }
+
+    #[test]
+    fn test_value_many() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-1200, 1200, 1e20 as i128).unwrap();
+        let amount = I256::from_raw(U256::from(1e20 as u128));
+        pool.swap(true, amount, MIN_SQRT_RATIO + U256_1).unwrap();
+        pool.swap(false, amount, MAX_SQRT_RATIO - U256_1).unwrap();
+
+        let ranges = [
+            PositionRange {
+                tick_lower: -600,
+                tick_upper: 600,
+                position: Position {
+                    liquidity: 1e18 as u128,
+                    tokens_owed_0: 7,
+                    ..Default::default()
+                },
+            },
+            PositionRange {
+                tick_lower: -1200,
+                tick_upper: 1200,
+                position: Position {
+                    liquidity: 1e19 as u128,
+                    ..Default::default()
+                },
+            },
+            PositionRange {
+                tick_lower: -600,
+                tick_upper: 600,
+                position: Position {
+                    liquidity: 1e20 as u128,
+                    ..Default::default()
+                },
+            },
+        ];
+
+        //matches valuing each position on its own
+        let values = value_many(&pool, &ranges).unwrap();
+        assert_eq!(values.len(), ranges.len());
+        for (range, value) in ranges.iter().zip(&values) {
+            let position = &range.position;
+            let (amount_0, amount_1) = amounts_at_price(
+                pool.slot0.sqrt_price_x_96,
+                range.tick_lower,
+                range.tick_upper,
+                position.liquidity,
+            )
+            .unwrap();
+            let (fees_0, fees_1) = uncollected_fees(
+                &pool,
+                range.tick_lower,
+                range.tick_upper,
+                position.liquidity,
+                position.fee_growth_inside_0_last_x_128,
+                position.fee_growth_inside_1_last_x_128,
+            )
+            .unwrap();
+            assert_eq!((value.amount_0, value.amount_1), (amount_0, amount_1));
+            assert_eq!(
+                (value.fees_0, value.fees_1),
+                (
+                    fees_0 + U256::from(position.tokens_owed_0),
+                    fees_1 + U256::from(position.tokens_owed_1)
+                )
+            );
+            assert!(value.fees_0 > U256::ZERO && value.fees_1 > U256::ZERO);
+        }
+
+        //any tick storage gives the same values
+        let persistent = PersistentPoolSnapshot::from(pool.clone());
+        assert_eq!(value_many(&persistent, &ranges).unwrap(), values);
+
+        //fails for ticks out of bounds
+        let ranges = [PositionRange {
+            tick_lower: -887273,
+            tick_upper: 60,
+            ..Default::default()
+        }];
+        assert!(value_many(&pool, &ranges).is_err());
+    }
+
+    #[test]
+    fn test_pool_positions() {
+        const PASSIVE: Address = address!("0000000000000000000000000000000000000001");
+        const JIT: Address = address!("0000000000000000000000000000000000000002");
+        const VAULT: Address = address!("0000000000000000000000000000000000000003");
+
+        let mut pool = PoolPositions::new(
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap(),
+        );
+        let amounts = pool.mint(PASSIVE, -600, 600, 1e18 as u128).unwrap();
+        pool.mint(JIT, -60, 60, 1e18 as u128).unwrap();
+        assert_eq!(pool.mint(VAULT, -600, 600, 1e18 as u128).unwrap(), amounts);
+        assert_eq!(pool.pool.liquidity, 3e18 as u128);
+
+        //owners of the same range have their own positions
+        assert_eq!(
+            pool.position(PASSIVE, -600, 600).unwrap().liquidity,
+            1e18 as u128
+        );
+        assert_eq!(
+            pool.position(VAULT, -600, 600).unwrap().liquidity,
+            1e18 as u128
+        );
+        assert!(pool.position(JIT, -600, 600).is_none());
+        assert_eq!(
+            pool.positions_of(JIT)
+                .map(|(tick_lower, tick_upper, _)| (tick_lower, tick_upper))
+                .collect::<Vec<_>>(),
+            vec![(-60, 60)]
+        );
+
+        //fees are shared by the liquidity in range
+        let fee_growth = pool.pool.fee_growth_global_0_x_128;
+        pool.pool
+            .swap(
+                true,
+                I256::try_from(1e15 as i64).unwrap(),
+                MIN_SQRT_RATIO + U256_1,
+            )
+            .unwrap();
+        assert!(pool.pool.slot0.tick > -60);
+        let fees = mul_div(
+            pool.pool.fee_growth_global_0_x_128 - fee_growth,
+            U256::from(1e18 as u128),
+            Q128,
+        )
+        .unwrap();
+        for owner in [PASSIVE, JIT, VAULT] {
+            assert_eq!(pool.tokens_owed(owner).unwrap(), (fees, U256::ZERO));
+        }
+
+        //burning credits the principal and the fees to the tokens owed of the owner
+        let (amount_0, amount_1) = pool.burn(JIT, -60, 60, 1e18 as u128).unwrap();
+        let position = *pool.position(JIT, -60, 60).unwrap();
+        assert_eq!(position.liquidity, 0);
+        assert_eq!(U256::from(position.tokens_owed_0), amount_0 + fees);
+        assert_eq!(U256::from(position.tokens_owed_1), amount_1);
+        assert_eq!(pool.pool.liquidity, 2e18 as u128);
+        assert!(!pool.pool.ticks.contains_key(&-60));
+        assert_eq!(
+            pool.collect(JIT, -60, 60, u128::MAX, 1),
+            (position.tokens_owed_0, 1)
+        );
+        assert_eq!(
+            pool.collect(JIT, -60, 60, u128::MAX, u128::MAX),
+            (0, position.tokens_owed_1 - 1)
+        );
+        assert_eq!(pool.tokens_owed(JIT).unwrap(), (U256::ZERO, U256::ZERO));
+
+        //poking credits the fees without changing what is owed
+        pool.burn(PASSIVE, -600, 600, 0).unwrap();
+        assert_eq!(
+            U256::from(pool.position(PASSIVE, -600, 600).unwrap().tokens_owed_0),
+            fees
+        );
+        assert_eq!(pool.tokens_owed(PASSIVE).unwrap(), (fees, U256::ZERO));
+        assert_eq!(pool.collect(VAULT, -60, 60, u128::MAX, u128::MAX), (0, 0));
+
+        //fails without changing the pool
+        let before = pool.pool.clone();
+        assert!(matches!(
+            pool.burn(PASSIVE, -600, 600, 2e18 as u128).unwrap_err(),
+            UniswapV3MathError::LiquiditySub
+        ));
+        assert!(matches!(
+            pool.burn(JIT, -600, 600, 0).unwrap_err(),
+            UniswapV3MathError::NP
+        ));
+        assert!(matches!(
+            pool.mint(JIT, -600, 600, 0).unwrap_err(),
+            UniswapV3MathError::LiquidityIsZero
+        ));
+        assert_eq!(pool.pool, before);
+        assert!(pool.position(JIT, -600, 600).is_none());
+
+        //also if the pool could take the liquidity but the position can not
+        pool.position_mut(VAULT, -600, 600).liquidity = u128::MAX;
+        assert!(matches!(
+            pool.mint(VAULT, -600, 600, 1).unwrap_err(),
+            UniswapV3MathError::LiquidityAdd
+        ));
+        assert_eq!(pool.pool, before);
+        assert_eq!(
+            pool.position(VAULT, -600, 600).unwrap().liquidity,
+            u128::MAX
+        );
+    }
+}
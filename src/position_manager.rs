@@ -0,0 +1,240 @@
+use alloy_primitives::{address, keccak256, Address, B256, I256, U256};
+
+use crate::{error::UniswapV3MathError, pool_key::PoolKey, position::Position};
+
+// positions minted through the NonfungiblePositionManager are owned by the manager in the pool, so all NFTs over the
+// same range of a pool share a single pool position keyed by the manager address. The manager keeps its own fee
+// growth checkpoints and tokens owed per NFT, which is what `positions(tokenId)` returns, while the pool position at
+// `position_key(manager, tick_lower, tick_upper)` holds the sum of their liquidity
+
+// the address of the NonfungiblePositionManager on mainnet and most other chains
+pub const NONFUNGIBLE_POSITION_MANAGER: Address =
+    address!("C36442b4a4522E871399CD717aBDD847Ab11FE88");
+
+// the number of words returned by NonfungiblePositionManager.positions
+const POSITIONS_RETURN_WORDS: usize = 12;
+
+// returns keccak256(abi.encodePacked(owner, tickLower, tickUpper)), the key of a position in the pool, mirrors
+// PositionKey.compute
+pub fn position_key(owner: Address, tick_lower: i32, tick_upper: i32) -> B256 {
+    let mut encoded = [0u8; 26];
+    encoded[..20].copy_from_slice(owner.as_slice());
+    encoded[20..23].copy_from_slice(&tick_lower.to_be_bytes()[1..]);
+    encoded[23..].copy_from_slice(&tick_upper.to_be_bytes()[1..]);
+    keccak256(encoded)
+}
+
+//A position NFT, as returned by NonfungiblePositionManager.positions(tokenId)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NftPosition {
+    // the nonce for permits
+    pub nonce: u128,
+    // the address approved to spend the NFT
+    pub operator: Address,
+    pub token_0: Address,
+    pub token_1: Address,
+    pub fee: u32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    // fee growth inside the range as of the last action on the NFT
+    pub fee_growth_inside_0_last_x_128: U256,
+    pub fee_growth_inside_1_last_x_128: U256,
+    // the fees owed to the NFT as of the last action on it
+    pub tokens_owed_0: u128,
+    pub tokens_owed_1: u128,
+}
+
+impl NftPosition {
+    // decodes the ABI encoded return data of NonfungiblePositionManager.positions(tokenId), fails if the data is not
+    // twelve words or a value does not fit its type
+    pub fn decode(data: &[u8]) -> Result<Self, UniswapV3MathError> {
+        if data.len() != POSITIONS_RETURN_WORDS * 32 {
+            return Err(UniswapV3MathError::InvalidReturnData);
+        }
+        let word = |index: usize| U256::from_be_slice(&data[index * 32..(index + 1) * 32]);
+
+        Ok(Self {
+            nonce: uint(word(0), 96)?.to::<u128>(),
+            operator: address_from_word(word(1))?,
+            token_0: address_from_word(word(2))?,
+            token_1: address_from_word(word(3))?,
+            fee: uint(word(4), 24)?.to::<u32>(),
+            tick_lower: int_24(word(5))?,
+            tick_upper: int_24(word(6))?,
+            liquidity: uint(word(7), 128)?.to::<u128>(),
+            fee_growth_inside_0_last_x_128: word(8),
+            fee_growth_inside_1_last_x_128: word(9),
+            tokens_owed_0: uint(word(10), 128)?.to::<u128>(),
+            tokens_owed_1: uint(word(11), 128)?.to::<u128>(),
+        })
+    }
+
+    // the NFT's share of the pool position, with the fee checkpoints and tokens owed kept by the manager. Fees earned
+    // since the last action on the NFT are given by `position::uncollected_fees`
+    pub fn position(&self) -> Position {
+        Position {
+            liquidity: self.liquidity,
+            fee_growth_inside_0_last_x_128: self.fee_growth_inside_0_last_x_128,
+            fee_growth_inside_1_last_x_128: self.fee_growth_inside_1_last_x_128,
+            tokens_owed_0: self.tokens_owed_0,
+            tokens_owed_1: self.tokens_owed_1,
+        }
+    }
+
+    // the key of the pool the NFT's liquidity is in, see `PoolKey::compute_address`
+    pub fn pool_key(&self) -> PoolKey {
+        PoolKey {
+            token_0: self.token_0,
+            token_1: self.token_1,
+            fee: self.fee,
+        }
+    }
+
+    // the key of the pool position shared by all NFTs of `manager` over the same range, see
+    // `pool_storage::position_slot`
+    pub fn position_key(&self, manager: Address) -> B256 {
+        position_key(manager, self.tick_lower, self.tick_upper)
+    }
+}
+
+// returns the word if it is a valid uint of `bits` bits
+fn uint(word: U256, bits: usize) -> Result<U256, UniswapV3MathError> {
+    if word.bit_len() > bits {
+        return Err(UniswapV3MathError::InvalidReturnData);
+    }
+    Ok(word)
+}
+
+fn address_from_word(word: U256) -> Result<Address, UniswapV3MathError> {
+    Ok(Address::from_word(uint(word, 160)?.into()))
+}
+
+// returns the word if it is a valid, i.e. sign extended, int24
+fn int_24(word: U256) -> Result<i32, UniswapV3MathError> {
+    i32::try_from(I256::from_raw(word))
+        .ok()
+        .filter(|value| (-(1 << 23)..1 << 23).contains(value))
+        .ok_or(UniswapV3MathError::InvalidReturnData)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, keccak256, Address, I256, U256};
+
+    use super::{position_key, NftPosition, NONFUNGIBLE_POSITION_MANAGER};
+    use crate::{
+        decode, error::UniswapV3MathError, pool_key::UNISWAP_V3_FACTORY,
+        pool_storage::position_slot, position::Position,
+    };
+
+    fn encode(words: &[U256]) -> Vec<u8> {
+        words
+            .iter()
+            .flat_map(|word| word.to_be_bytes::<32>())
+            .collect()
+    }
+
+    fn address_word(address: Address) -> U256 {
+        U256::from_be_slice(address.into_word().as_slice())
+    }
+
+    #[test]
+    fn test_position_key() {
+        let owner = address!("C36442b4a4522E871399CD717aBDD847Ab11FE88");
+        let mut packed = owner.to_vec();
+        packed.extend_from_slice(&[0xff, 0xfd, 0xa8, 0x00, 0x02, 0x58]);
+        assert_eq!(position_key(owner, -600, 600), keccak256(packed));
+    }
+
+    #[test]
+    fn test_decode_nft_position() {
+        let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+        let words = [
+            U256::from(3),
+            U256::ZERO,
+            address_word(usdc),
+            address_word(weth),
+            U256::from(500),
+            I256::unchecked_from(-201000).into_raw(),
+            I256::unchecked_from(201000).into_raw(),
+            U256::from(1e18 as u128),
+            U256::from(7) << 128,
+            U256::from(9) << 128,
+            U256::from(11),
+            U256::from(13),
+        ];
+        let nft = NftPosition::decode(&encode(&words)).unwrap();
+        assert_eq!(
+            nft,
+            NftPosition {
+                nonce: 3,
+                operator: Address::ZERO,
+                token_0: usdc,
+                token_1: weth,
+                fee: 500,
+                tick_lower: -201000,
+                tick_upper: 201000,
+                liquidity: 1e18 as u128,
+                fee_growth_inside_0_last_x_128: U256::from(7) << 128,
+                fee_growth_inside_1_last_x_128: U256::from(9) << 128,
+                tokens_owed_0: 11,
+                tokens_owed_1: 13,
+            }
+        );
+        assert_eq!(
+            nft.pool_key().compute_address(UNISWAP_V3_FACTORY),
+            address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")
+        );
+        assert_eq!(nft.position().tokens_owed_1, 13);
+
+        //the pool position of the manager is found through its key
+        let key = nft.position_key(NONFUNGIBLE_POSITION_MANAGER);
+        assert_eq!(
+            key,
+            position_key(NONFUNGIBLE_POSITION_MANAGER, -201000, 201000)
+        );
+        let pool_position = Position {
+            liquidity: 5e18 as u128,
+            fee_growth_inside_0_last_x_128: U256::from(8) << 128,
+            fee_growth_inside_1_last_x_128: U256::from(10) << 128,
+            tokens_owed_0: 1,
+            tokens_owed_1: 2,
+        };
+        let storage = [
+            U256::from(pool_position.liquidity),
+            pool_position.fee_growth_inside_0_last_x_128,
+            pool_position.fee_growth_inside_1_last_x_128,
+            U256::from(2) << 128 | U256::from(1),
+        ];
+        assert_eq!(decode::position_info(&storage), pool_position);
+        assert_ne!(
+            position_slot(key),
+            position_slot(nft.position_key(Address::ZERO))
+        );
+
+        //fails for data of the wrong length or values that do not fit their type
+        assert!(matches!(
+            NftPosition::decode(&encode(&words[..11])),
+            Err(UniswapV3MathError::InvalidReturnData)
+        ));
+        let mut invalid = vec![U256::ZERO; 12];
+        invalid[4] = U256::from(1 << 24);
+        assert!(matches!(
+            NftPosition::decode(&encode(&invalid)),
+            Err(UniswapV3MathError::InvalidReturnData)
+        ));
+        invalid[4] = U256::ZERO;
+        invalid[5] = U256::from(1 << 23);
+        assert!(matches!(
+            NftPosition::decode(&encode(&invalid)),
+            Err(UniswapV3MathError::InvalidReturnData)
+        ));
+        invalid[5] = I256::unchecked_from(-(1 << 23)).into_raw();
+        assert_eq!(
+            NftPosition::decode(&encode(&invalid)).unwrap().tick_lower,
+            -(1 << 23)
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use alloy_primitives::{U256, U512};
+
+use crate::tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO};
+
+pub const BPS_DENOMINATOR: u64 = 10000;
+
+// returns the sqrt price limit of a swap that may move the price by at most `slippage_bps` basis points, i.e. the sqrt
+// price of a price `slippage_bps` below the current price when selling token0 or above it when buying token0. The limit
+// is computed exactly in 512 bits and rounded towards the current price, so the price never moves by more than the
+// tolerance, then clamped to the range of limits a swap accepts. A tolerance of 0 returns the current price, which the
+// pool rejects as a limit
+pub fn sqrt_price_limit_from_slippage(
+    sqrt_price_x_96: U256,
+    slippage_bps: u64,
+    zero_for_one: bool,
+) -> U256 {
+    let factor = U512::from(if zero_for_one {
+        BPS_DENOMINATOR.saturating_sub(slippage_bps)
+    } else {
+        BPS_DENOMINATOR.saturating_add(slippage_bps)
+    });
+    let denominator = U512::from(BPS_DENOMINATOR);
+    // the squared limit, sqrt_price ** 2 * (10000 -+ slippage) / 10000 as a Q128.192
+    let squared = U512::from(sqrt_price_x_96).pow(U512::from(2)) * factor;
+
+    let limit = if zero_for_one {
+        let squared = squared.div_ceil(denominator);
+        let root = squared.root(2);
+        if root * root < squared {
+            root + U512::from(1)
+        } else {
+            root
+        }
+    } else {
+        (squared / denominator).root(2)
+    };
+
+    let min = MIN_SQRT_RATIO + U256::from(1);
+    let max = MAX_SQRT_RATIO - U256::from(1);
+    if limit < U512::from(min) {
+        min
+    } else if limit > U512::from(max) {
+        max
+    } else {
+        limit.to()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::sqrt_price_limit_from_slippage;
+    use crate::{
+        full_math::mul_div,
+        sqrt_price_math::Q96,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    };
+
+    #[test]
+    fn test_sqrt_price_limit_from_slippage() {
+        for tick in [-300000, -60, 0, 1, 200000] {
+            let sqrt_price = get_sqrt_ratio_at_tick(tick).unwrap();
+            let price = mul_div(sqrt_price, sqrt_price, Q96).unwrap();
+
+            //the price at the limit is within the tolerance and the limit is the closest such sqrt price
+            let limit = sqrt_price_limit_from_slippage(sqrt_price, 50, true);
+            assert!(limit < sqrt_price);
+            assert!(
+                limit * limit * U256::from(10000) >= sqrt_price * sqrt_price * U256::from(9950)
+            );
+            let below = limit - U256::from(1);
+            assert!(below * below * U256::from(10000) < sqrt_price * sqrt_price * U256::from(9950));
+            assert!(mul_div(limit, limit, Q96).unwrap() < price);
+
+            let limit = sqrt_price_limit_from_slippage(sqrt_price, 50, false);
+            assert!(limit > sqrt_price);
+            assert!(
+                limit * limit * U256::from(10000) <= sqrt_price * sqrt_price * U256::from(10050)
+            );
+            let above = limit + U256::from(1);
+            assert!(
+                above * above * U256::from(10000) > sqrt_price * sqrt_price * U256::from(10050)
+            );
+
+            //no slippage is the current price
+            assert_eq!(
+                sqrt_price_limit_from_slippage(sqrt_price, 0, true),
+                sqrt_price
+            );
+            assert_eq!(
+                sqrt_price_limit_from_slippage(sqrt_price, 0, false),
+                sqrt_price
+            );
+        }
+
+        //limits are clamped to the bounds
+        let sqrt_price = get_sqrt_ratio_at_tick(0).unwrap();
+        assert_eq!(
+            sqrt_price_limit_from_slippage(sqrt_price, 10000, true),
+            MIN_SQRT_RATIO + U256::from(1)
+        );
+        assert_eq!(
+            sqrt_price_limit_from_slippage(MAX_SQRT_RATIO - U256::from(2), 100, false),
+            MAX_SQRT_RATIO - U256::from(1)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::{fmt, ops::Neg};
+
+use alloy_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    full_math::{mul_div, Q128},
+    liquidity_math::add_delta,
+    sqrt_price_math::Q96,
+    tick_math::{get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+};
+
+// newtypes for the fixed point formats used by the pool. Raw U256s of different formats are easy to mix up, e.g.
+// scaling a fee growth by Q96, so public APIs accept these types, and every type converts from and into its raw value
+// so existing callers passing raw values keep working
+
+//A Q64.96 sqrt price, sqrt(token1 / token0) * 2^96
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SqrtPriceX96(pub U256);
+
+impl SqrtPriceX96 {
+    pub const MIN: Self = Self(MIN_SQRT_RATIO);
+    pub const MAX: Self = Self(MAX_SQRT_RATIO);
+
+    pub fn from_tick(tick: i32) -> Result<Self, UniswapV3MathError> {
+        Ok(Self(get_sqrt_ratio_at_tick(tick)?))
+    }
+
+    // returns the greatest tick whose sqrt price is less than or equal to this price
+    pub fn to_tick(self) -> Result<i32, UniswapV3MathError> {
+        get_tick_at_sqrt_ratio(self.0)
+    }
+
+    // returns the Q96 price of token0 in token1, rounded down
+    pub fn to_price_x_96(self) -> Result<U256, UniswapV3MathError> {
+        mul_div(self.0, self.0, Q96)
+    }
+}
+
+//A Q128.128 fee growth, the fees earned per unit of liquidity. Fee growth is allowed to overflow, only differences
+//between two checkpoints are meaningful
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeeGrowthX128(pub U256);
+
+impl FeeGrowthX128 {
+    // returns the fee growth of `fee_amount` spread over `liquidity`
+    pub fn from_fees(fee_amount: U256, liquidity: u128) -> Result<Self, UniswapV3MathError> {
+        Ok(Self(mul_div(fee_amount, Q128, U256::from(liquidity))?))
+    }
+
+    pub fn wrapping_add(self, other: Self) -> Self {
+        Self(self.0.wrapping_add(other.0))
+    }
+
+    pub fn wrapping_sub(self, other: Self) -> Self {
+        Self(self.0.wrapping_sub(other.0))
+    }
+
+    // returns the fees earned by `liquidity` for this fee growth, rounded down
+    pub fn fees(self, liquidity: u128) -> Result<U256, UniswapV3MathError> {
+        mul_div(self.0, U256::from(liquidity), Q128)
+    }
+}
+
+//A signed change in active liquidity, e.g. the liquidity net of a tick or the liquidity delta of a position update
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LiquidityNet(pub i128);
+
+impl LiquidityNet {
+    // returns `liquidity` with this change applied, fails on overflow or underflow
+    pub fn apply(self, liquidity: u128) -> Result<u128, UniswapV3MathError> {
+        add_delta(liquidity, self.0)
+    }
+}
+
+impl Neg for LiquidityNet {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.wrapping_neg())
+    }
+}
+
+macro_rules! impl_raw_conversions {
+    ($($q_number:ident($raw:ty)),*) => {
+        $(
+            impl From<$raw> for $q_number {
+                fn from(raw: $raw) -> Self {
+                    Self(raw)
+                }
+            }
+
+            impl From<$q_number> for $raw {
+                fn from(value: $q_number) -> Self {
+                    value.0
+                }
+            }
+
+            impl fmt::Display for $q_number {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    self.0.fmt(f)
+                }
+            }
+        )*
+    };
+}
+
+impl_raw_conversions!(SqrtPriceX96(U256), FeeGrowthX128(U256), LiquidityNet(i128));
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{FeeGrowthX128, LiquidityNet, SqrtPriceX96};
+    use crate::{
+        full_math::Q128,
+        sqrt_price_math::Q96,
+        tick_math::{MAX_TICK, MIN_TICK},
+    };
+
+    #[test]
+    fn test_sqrt_price_x_96() {
+        assert_eq!(
+            SqrtPriceX96::from_tick(MIN_TICK).unwrap(),
+            SqrtPriceX96::MIN
+        );
+        assert_eq!(
+            SqrtPriceX96::from_tick(MAX_TICK).unwrap(),
+            SqrtPriceX96::MAX
+        );
+        assert_eq!(SqrtPriceX96::from(Q96).to_tick().unwrap(), 0);
+        assert_eq!(
+            SqrtPriceX96(Q96 * U256::from(2)).to_price_x_96().unwrap(),
+            Q96 * U256::from(4)
+        );
+
+        let raw: U256 = SqrtPriceX96::MIN.into();
+        assert_eq!(raw, SqrtPriceX96::MIN.0);
+    }
+
+    #[test]
+    fn test_fee_growth_x_128() {
+        let fee_growth = FeeGrowthX128::from_fees(U256::from(500), 1000).unwrap();
+        assert_eq!(fee_growth, FeeGrowthX128(Q128 / U256::from(2)));
+        assert_eq!(fee_growth.fees(1000).unwrap(), U256::from(500));
+
+        //differences across an overflow are still correct
+        let start = FeeGrowthX128(U256::MAX);
+        let end = start.wrapping_add(fee_growth);
+        assert_eq!(end.wrapping_sub(start), fee_growth);
+
+        //fails without liquidity
+        assert!(FeeGrowthX128::from_fees(U256::from(1), 0).is_err());
+    }
+
+    #[test]
+    fn test_liquidity_net() {
+        assert_eq!(LiquidityNet(-5).apply(10).unwrap(), 5);
+        assert_eq!((-LiquidityNet(-5)).apply(10).unwrap(), 15);
+        assert!(LiquidityNet(-11).apply(10).is_err());
+        assert_eq!(i128::from(LiquidityNet::from(7)), 7);
+    }
+}
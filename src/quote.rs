@@ -0,0 +1,773 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    full_math::{mul_div, mul_div_rounding_up},
+    orientation::amounts_in_out,
+    pool::{PoolSnapshot, SwapFill, SwapParams, SwapResult},
+    sqrt_price_math::Q96,
+    storage::{InitializedTicks, TickStorage},
+    tick_bitmap::words_for_range,
+    tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK},
+    variant::PoolVariant,
+    U256_1,
+};
+
+pub use crate::price::{sqrt_price_limit_from_slippage, BPS_DENOMINATOR};
+
+//A quote against the pool at index `pool` of the pools passed to `batch_quote`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuoteRequest {
+    pub pool: usize,
+    pub params: SwapParams,
+}
+
+impl QuoteRequest {
+    pub fn new(pool: usize, params: SwapParams) -> Self {
+        Self { pool, params }
+    }
+}
+
+pub type QuoteResult = Result<SwapResult, UniswapV3MathError>;
+
+//A quote in terms of the tokens in and out, e.g. for quoting services to return from their handlers as is. With the
+//`serde` feature it serializes with camelCase field names and amounts as hex strings, new fields are only ever added
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct QuoteResponse {
+    pub zero_for_one: bool,
+    // the input amount including fees
+    pub amount_in: U256,
+    pub amount_out: U256,
+    // the pool price and tick after the swap
+    pub sqrt_price_x_96_after: U256,
+    pub tick_after: i32,
+    // the fee paid in the input token
+    pub fee_amount: U256,
+    pub initialized_ticks_crossed: u32,
+    pub gas_estimate: u64,
+}
+
+impl QuoteResponse {
+    pub fn new(zero_for_one: bool, result: &SwapResult, gas_model: &GasModel) -> Self {
+        let (amount_in, amount_out) =
+            amounts_in_out(zero_for_one, result.amount_0, result.amount_1);
+
+        Self {
+            zero_for_one,
+            amount_in,
+            amount_out,
+            sqrt_price_x_96_after: result.sqrt_price_x_96,
+            tick_after: result.tick,
+            fee_amount: result.fee_amount,
+            initialized_ticks_crossed: result.initialized_ticks_crossed,
+            gas_estimate: gas_model.estimate(result),
+        }
+    }
+}
+
+//Gas cost heuristic for a swap, the defaults approximate the cost of a single hop exact input swap through the router
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasModel {
+    // the gas used by a swap that does not cross any initialized ticks
+    pub base_gas: u64,
+    // the additional gas used for each initialized tick crossed
+    pub gas_per_tick_crossed: u64,
+}
+
+impl Default for GasModel {
+    fn default() -> Self {
+        Self {
+            base_gas: 80000,
+            gas_per_tick_crossed: 31000,
+        }
+    }
+}
+
+impl GasModel {
+    // returns the estimated gas used by a swap, like the gasEstimate returned by QuoterV2
+    pub fn estimate(&self, result: &SwapResult) -> u64 {
+        self.base_gas.saturating_add(
+            self.gas_per_tick_crossed
+                .saturating_mul(result.initialized_ticks_crossed as u64),
+        )
+    }
+}
+
+//The largest exact input swap that keeps the pool price within a limit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundedQuote {
+    // the input amount including fees
+    pub amount_in: U256,
+    pub amount_out: U256,
+    // the pool sqrt price after the swap, equal to the limit unless the limit is beyond the last initialized tick
+    // with liquidity
+    pub sqrt_price_x_96: U256,
+    // the pool tick after the swap
+    pub tick: i32,
+}
+
+//Spot and execution price of a swap. Prices are always the price of token0 denominated in token1 as a Q96,
+//regardless of the direction of the swap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceImpact {
+    // the pool price before the swap
+    pub spot_price_x_96: U256,
+    // the average price of the swap including fees, amount1 / amount0
+    pub execution_price_x_96: U256,
+    // the pool price after the swap
+    pub price_after_x_96: U256,
+    // how much worse the execution price is than the spot price in basis points, rounded down
+    pub impact_bps: U256,
+}
+
+//A quote against a partial snapshot that only has the ticks within a horizon of the current tick loaded. A quote that
+//would have continued beyond the horizon is `truncated`, its result is the swap up to the edge of the horizon and the
+//amount bounds cover whatever liquidity lies beyond it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HorizonQuote {
+    pub result: SwapResult,
+    pub truncated: bool,
+    // the sqrt price at the edge of the horizon, `None` if the horizon does not limit the swap before its price limit
+    pub horizon_sqrt_price_x_96: Option<U256>,
+    // the output amount of an exact input swap or the input amount of an exact output swap would be within these
+    // bounds had the whole pool been loaded. Both are the amount of the result unless truncated, the upper bound is
+    // `None` if it is unbounded, i.e. an exact output swap might not fill at any input
+    pub amount_min: U256,
+    pub amount_max: Option<U256>,
+}
+
+impl HorizonQuote {
+    // returns the result, or `QuoteTruncated` if the swap crosses beyond the horizon
+    pub fn into_result(self) -> Result<SwapResult, UniswapV3MathError> {
+        if self.truncated {
+            return Err(UniswapV3MathError::QuoteTruncated);
+        }
+
+        Ok(self.result)
+    }
+}
+
+// returns the price of token0 in token1 as a Q96 for a sqrt price
+pub fn sqrt_price_to_price_x_96(sqrt_price_x_96: U256) -> Result<U256, UniswapV3MathError> {
+    mul_div(sqrt_price_x_96, sqrt_price_x_96, Q96)
+}
+
+// returns the spot price, execution price and price impact of an exact input swap without a price limit
+pub fn price_impact(
+    pool: &PoolSnapshot,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<PriceImpact, UniswapV3MathError> {
+    let limit = if zero_for_one {
+        MIN_SQRT_RATIO + U256_1
+    } else {
+        MAX_SQRT_RATIO - U256_1
+    };
+    let result = pool.quote(zero_for_one, I256::from_raw(amount_in), limit)?;
+
+    let amount_0 = result.amount_0.unsigned_abs();
+    let amount_1 = result.amount_1.unsigned_abs();
+
+    let spot_price_x_96 = sqrt_price_to_price_x_96(pool.slot0.sqrt_price_x_96)?;
+    let execution_price_x_96 = mul_div(amount_1, Q96, amount_0)?;
+    let price_after_x_96 = sqrt_price_to_price_x_96(result.sqrt_price_x_96)?;
+
+    // selling token0 receives less than spot, buying token0 pays more than spot
+    let difference = if zero_for_one {
+        spot_price_x_96.saturating_sub(execution_price_x_96)
+    } else {
+        execution_price_x_96.saturating_sub(spot_price_x_96)
+    };
+    let impact_bps = mul_div(difference, U256::from(BPS_DENOMINATOR), spot_price_x_96)?;
+
+    Ok(PriceImpact {
+        spot_price_x_96,
+        execution_price_x_96,
+        price_after_x_96,
+        impact_bps,
+    })
+}
+
+// returns the largest input amount that can be swapped before the pool price reaches `sqrt_price_limit_x_96`, along
+// with the output amount and the tick the swap stops at
+pub fn max_amount_in_to_price_limit(
+    pool: &PoolSnapshot,
+    zero_for_one: bool,
+    sqrt_price_limit_x_96: U256,
+) -> Result<BoundedQuote, UniswapV3MathError> {
+    // an unbounded exact input swap stops at the price limit with exactly the input needed to get there
+    let result = pool.quote(zero_for_one, I256::MAX, sqrt_price_limit_x_96)?;
+
+    let (amount_in, amount_out) = if zero_for_one {
+        (result.amount_0, result.amount_1)
+    } else {
+        (result.amount_1, result.amount_0)
+    };
+
+    Ok(BoundedQuote {
+        amount_in: amount_in.into_raw(),
+        amount_out: amount_out.unsigned_abs(),
+        sqrt_price_x_96: result.sqrt_price_x_96,
+        tick: result.tick,
+    })
+}
+
+// returns the largest input amount that can be swapped before the pool price moves by more than `slippage_bps` basis
+// points from the current price. The price limit is clamped to the sqrt price bounds
+pub fn max_amount_in_for_slippage(
+    pool: &PoolSnapshot,
+    zero_for_one: bool,
+    slippage_bps: u64,
+) -> Result<BoundedQuote, UniswapV3MathError> {
+    let sqrt_price_limit_x_96 =
+        sqrt_price_limit_from_slippage(pool.slot0.sqrt_price_x_96, slippage_bps, zero_for_one);
+    max_amount_in_to_price_limit(pool, zero_for_one, sqrt_price_limit_x_96)
+}
+
+// quotes every request against its pool without modifying the pools, results are in the order of the requests. With
+// the `parallel` feature the requests are quoted on the rayon thread pool, which shares the pools by reference.
+// Requests for a pool index that is out of bounds fail with `PoolNotFound`
+pub fn batch_quote<T, B, V>(
+    pools: &[PoolSnapshot<T, B, V>],
+    requests: &[QuoteRequest],
+) -> Vec<QuoteResult>
+where
+    T: TickStorage + Sync,
+    B: InitializedTicks + Sync,
+    V: PoolVariant + Sync,
+{
+    let quote = |request: &QuoteRequest| {
+        pools
+            .get(request.pool)
+            .ok_or(UniswapV3MathError::PoolNotFound)?
+            .quote_with(&request.params)
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        requests.par_iter().map(quote).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    requests.iter().map(quote).collect()
+}
+
+// quotes a swap against a partial snapshot that only has the bitmap words a walk from the current tick reads loaded,
+// the first one and `words` beyond it (see `tick_bitmap::words_for_range`), along with the ticks initialized in them.
+// The swap is limited to the edge of these words, so it never reads a word or tick that is not loaded
+pub fn quote_within_horizon<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    params: &SwapParams,
+    words: usize,
+) -> Result<HorizonQuote, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let zero_for_one = params.zero_for_one;
+    let sqrt_price_x_96 = pool.slot0.sqrt_price_x_96;
+
+    // the horizon only limits the swap if it is closer than the price limit
+    let horizon_sqrt_price_x_96 = horizon_sqrt_price(
+        pool.slot0.tick,
+        pool.fee_tier.tick_spacing(),
+        zero_for_one,
+        words,
+    )?
+    .filter(|&horizon| {
+        if zero_for_one {
+            horizon > params.sqrt_price_limit_x_96
+        } else {
+            horizon < params.sqrt_price_limit_x_96
+        }
+    });
+
+    let result = match horizon_sqrt_price_x_96 {
+        None => pool.quote_with(params)?,
+        // the horizon is never on the wrong side of the current price, but can be at it when selling token0
+        Some(horizon) if horizon != sqrt_price_x_96 => pool.quote_with(&SwapParams {
+            sqrt_price_limit_x_96: horizon,
+            ..*params
+        })?,
+        // the price is already at the edge of the horizon
+        Some(_) => SwapResult {
+            amount_0: I256::ZERO,
+            amount_1: I256::ZERO,
+            sqrt_price_x_96,
+            tick: pool.slot0.tick,
+            liquidity: pool.liquidity,
+            initialized_ticks_crossed: 0,
+            fee_growth_global_0_x_128_delta: U256::ZERO,
+            fee_growth_global_1_x_128_delta: U256::ZERO,
+            amount_specified_remaining: params.amount_specified,
+            fill: SwapFill::Stopped,
+            zero_liquidity_ticks: 0,
+            fee_amount: U256::ZERO,
+        },
+    };
+
+    let truncated = horizon_sqrt_price_x_96 == Some(result.sqrt_price_x_96)
+        && !result.amount_specified_remaining.is_zero();
+
+    let exact_input = params.amount_specified.is_positive();
+    let (amount_in, amount_out) = amounts_in_out(zero_for_one, result.amount_0, result.amount_1);
+    let (amount_min, amount_max) = if !truncated {
+        let amount = if exact_input { amount_out } else { amount_in };
+        (amount, Some(amount))
+    } else {
+        // prices beyond the horizon are worse than the price at the horizon, so the rest of the swap can at best be
+        // filled at that price without fees
+        let remaining = value_at_sqrt_price(
+            result.amount_specified_remaining.unsigned_abs(),
+            result.sqrt_price_x_96,
+            zero_for_one == exact_input,
+            exact_input,
+        );
+
+        if exact_input {
+            (amount_out, Some(amount_out.saturating_add(remaining)))
+        } else {
+            (amount_in.saturating_add(remaining), None)
+        }
+    };
+
+    Ok(HorizonQuote {
+        result,
+        truncated,
+        horizon_sqrt_price_x_96,
+        amount_min,
+        amount_max,
+    })
+}
+
+// returns the sqrt price at the edge of the bitmap words a walk from `tick` reads, `None` if they reach the tick bounds
+fn horizon_sqrt_price(
+    tick: i32,
+    tick_spacing: i32,
+    zero_for_one: bool,
+    words: usize,
+) -> Result<Option<U256>, UniswapV3MathError> {
+    let word_positions =
+        words_for_range(tick, tick_spacing, zero_for_one, words.saturating_add(1))?;
+    let Some(&word_pos) = word_positions.last() else {
+        return Ok(None);
+    };
+
+    // a walk to the left ends at the first tick of the last word, a walk to the right at its last tick
+    let compressed = if zero_for_one {
+        (word_pos as i32) << 8
+    } else {
+        ((word_pos as i32) << 8) + 255
+    };
+    let horizon_tick = compressed * tick_spacing;
+
+    if horizon_tick <= MIN_TICK || horizon_tick >= MAX_TICK {
+        return Ok(None);
+    }
+
+    Ok(Some(get_sqrt_ratio_at_tick(horizon_tick)?))
+}
+
+// returns the value of an amount of token0 in token1, or of token1 in token0, at a sqrt price. Saturates at the max
+fn value_at_sqrt_price(amount: U256, sqrt_price_x_96: U256, token_0: bool, round_up: bool) -> U256 {
+    let convert = if round_up {
+        mul_div_rounding_up
+    } else {
+        mul_div
+    };
+    let (numerator, denominator) = if token_0 {
+        (sqrt_price_x_96, Q96)
+    } else {
+        (Q96, sqrt_price_x_96)
+    };
+
+    convert(amount, numerator, denominator)
+        .and_then(|value| convert(value, numerator, denominator))
+        .unwrap_or(U256::MAX)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        batch_quote, max_amount_in_for_slippage, max_amount_in_to_price_limit, price_impact,
+        quote_within_horizon, sqrt_price_limit_from_slippage, sqrt_price_to_price_x_96, GasModel,
+        QuoteRequest, QuoteResponse,
+    };
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        full_math::{mul_div, mul_div_rounding_up},
+        orientation::amounts_in_out,
+        pool::{PoolSnapshot, SwapParams, ZeroLiquidity},
+        sqrt_price_math::{encode_sqrt_ratio_x96, Q96},
+        tick, tick_bitmap,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    fn full_range_pool(sqrt_price_x_96: U256, fee_tier: FeeTier, liquidity: i128) -> PoolSnapshot {
+        let mut pool = PoolSnapshot::new(sqrt_price_x_96, fee_tier).unwrap();
+        let tick_spacing = fee_tier.tick_spacing();
+        let (tick_lower, tick_upper) = (
+            -887272 / tick_spacing * tick_spacing,
+            887272 / tick_spacing * tick_spacing,
+        );
+        for (tick, upper) in [(tick_lower, false), (tick_upper, true)] {
+            tick::update(
+                &mut pool.ticks,
+                tick,
+                pool.slot0.tick,
+                liquidity,
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+                0,
+                upper,
+                None,
+            )
+            .unwrap();
+            tick_bitmap::flip_tick(&mut pool.tick_bitmap, tick, tick_spacing).unwrap();
+        }
+        pool.liquidity = liquidity as u128;
+        pool
+    }
+
+    #[test]
+    fn test_price_impact() {
+        // price of 4 token1 per token0
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(4), U256::from(1)).unwrap();
+        let pool = full_range_pool(sqrt_price, FeeTier::Medium, 1e21 as i128);
+        assert_eq!(
+            sqrt_price_to_price_x_96(sqrt_price).unwrap(),
+            Q96 * U256::from(4)
+        );
+
+        for zero_for_one in [true, false] {
+            let small = price_impact(&pool, U256::from(1e15 as u128), zero_for_one).unwrap();
+            let large = price_impact(&pool, U256::from(1e20 as u128), zero_for_one).unwrap();
+
+            // the price is quoted in token1 per token0 in both directions
+            assert_eq!(small.spot_price_x_96, Q96 * U256::from(4));
+
+            // small swaps only pay the 30 bps fee
+            assert_eq!(small.impact_bps, U256::from(30));
+
+            // larger swaps move the price more
+            assert!(large.impact_bps > small.impact_bps);
+            if zero_for_one {
+                assert!(large.execution_price_x_96 < large.spot_price_x_96);
+                assert!(large.price_after_x_96 < large.execution_price_x_96);
+            } else {
+                assert!(large.execution_price_x_96 > large.spot_price_x_96);
+                assert!(large.price_after_x_96 > large.execution_price_x_96);
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_amount_in_to_price_limit() {
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(4), U256::from(1)).unwrap();
+        let pool = full_range_pool(sqrt_price, FeeTier::Medium, 1e21 as i128);
+
+        for (zero_for_one, tick) in [(true, 13000), (false, 15000)] {
+            let limit = get_sqrt_ratio_at_tick(tick).unwrap();
+            let quote = max_amount_in_to_price_limit(&pool, zero_for_one, limit).unwrap();
+            assert_eq!(quote.sqrt_price_x_96, limit);
+            assert_eq!(quote.tick, tick);
+            assert_eq!(
+                quote.amount_in,
+                pool.get_amount_in_to_target_price(limit).unwrap()
+            );
+
+            //swapping the amount gives the same output
+            let result = pool
+                .quote(zero_for_one, I256::from_raw(quote.amount_in), limit)
+                .unwrap();
+            let amount_out = if zero_for_one {
+                result.amount_1
+            } else {
+                result.amount_0
+            };
+            assert_eq!(amount_out.unsigned_abs(), quote.amount_out);
+        }
+    }
+
+    #[test]
+    fn test_max_amount_in_for_slippage() {
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(4), U256::from(1)).unwrap();
+        let pool = full_range_pool(sqrt_price, FeeTier::Medium, 1e21 as i128);
+        let spot_price = sqrt_price_to_price_x_96(sqrt_price).unwrap();
+
+        for zero_for_one in [true, false] {
+            let quote = max_amount_in_for_slippage(&pool, zero_for_one, 100).unwrap();
+            let price_after = sqrt_price_to_price_x_96(quote.sqrt_price_x_96).unwrap();
+
+            //the price moves by 1%, up to rounding of the sqrt price
+            let moved = if zero_for_one {
+                spot_price - price_after
+            } else {
+                price_after - spot_price
+            };
+            let moved_bps = mul_div(moved, U256::from(1_000_000), spot_price).unwrap();
+            assert!(moved_bps.abs_diff(U256::from(10_000)) <= U256_1);
+
+            //a wider bound allows a larger swap
+            let wider = max_amount_in_for_slippage(&pool, zero_for_one, 200).unwrap();
+            assert!(wider.amount_in > quote.amount_in);
+            assert!(wider.amount_out > quote.amount_out);
+        }
+
+        //slippage of 100% or more is bounded by the min sqrt price when selling token0
+        assert_eq!(
+            sqrt_price_limit_from_slippage(sqrt_price, 10_000, true),
+            MIN_SQRT_RATIO + U256_1
+        );
+        assert_eq!(
+            sqrt_price_limit_from_slippage(sqrt_price, 0, false),
+            sqrt_price
+        );
+    }
+
+    #[test]
+    fn test_gas_estimate() {
+        let sqrt_price = encode_sqrt_ratio_x96(U256::from(4), U256::from(1)).unwrap();
+        let pool = full_range_pool(sqrt_price, FeeTier::Medium, 1e21 as i128);
+        let gas_model = GasModel::default();
+
+        //no initialized ticks are crossed within the full range position
+        let result = pool
+            .quote(
+                true,
+                I256::from_raw(U256::from(1e18 as u128)),
+                MIN_SQRT_RATIO + U256_1,
+            )
+            .unwrap();
+        assert_eq!(result.initialized_ticks_crossed, 0);
+        assert_eq!(gas_model.estimate(&result), gas_model.base_gas);
+
+        //swapping through the entire liquidity crosses the lower tick
+        let result = pool
+            .quote(true, I256::MAX, MIN_SQRT_RATIO + U256_1)
+            .unwrap();
+        assert_eq!(result.initialized_ticks_crossed, 1);
+        let gas_model = GasModel {
+            base_gas: 100,
+            gas_per_tick_crossed: 10,
+        };
+        assert_eq!(gas_model.estimate(&result), 110);
+    }
+
+    #[test]
+    fn test_quote_response() {
+        let pool = full_range_pool(
+            get_sqrt_ratio_at_tick(0).unwrap(),
+            FeeTier::Medium,
+            1e21 as i128,
+        );
+        let gas_model = GasModel::default();
+
+        //exact output amounts are oriented by the direction, not by the sign
+        let amount_out = U256::from(1e18 as u128);
+        let result = pool
+            .quote(false, -I256::from_raw(amount_out), MAX_SQRT_RATIO - U256_1)
+            .unwrap();
+        let quote = QuoteResponse::new(false, &result, &gas_model);
+        assert_eq!(quote.amount_out, amount_out);
+        assert_eq!(quote.amount_in, result.amount_1.into_raw());
+        assert_eq!(quote.tick_after, result.tick);
+        assert_eq!(quote.gas_estimate, gas_model.base_gas);
+
+        //the fee is 0.3% of the input, rounded up
+        assert_eq!(
+            quote.fee_amount,
+            mul_div_rounding_up(
+                quote.amount_in - quote.fee_amount,
+                U256::from(3000),
+                U256::from(997000)
+            )
+            .unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_quote_response_serde() {
+        let pool = full_range_pool(
+            get_sqrt_ratio_at_tick(0).unwrap(),
+            FeeTier::Medium,
+            1e21 as i128,
+        );
+        let result = pool
+            .quote(
+                true,
+                I256::from_raw(U256::from(1e18 as u128)),
+                MIN_SQRT_RATIO + U256_1,
+            )
+            .unwrap();
+        let quote = QuoteResponse::new(true, &result, &GasModel::default());
+
+        //camelCase fields with amounts as hex strings, and back
+        let json = serde_json::to_value(quote).unwrap();
+        assert_eq!(json["zeroForOne"], true);
+        assert_eq!(json["amountIn"], "0xde0b6b3a7640000");
+        assert_eq!(json["tickAfter"], quote.tick_after);
+        assert_eq!(
+            serde_json::from_value::<QuoteResponse>(json).unwrap(),
+            quote
+        );
+    }
+
+    #[test]
+    fn test_batch_quote() {
+        let pools = [
+            full_range_pool(
+                get_sqrt_ratio_at_tick(0).unwrap(),
+                FeeTier::Medium,
+                1e20 as i128,
+            ),
+            full_range_pool(
+                get_sqrt_ratio_at_tick(600).unwrap(),
+                FeeTier::Low,
+                1e21 as i128,
+            ),
+        ];
+        let requests: Vec<_> = (1..=64_u128)
+            .map(|i| QuoteRequest {
+                pool: (i % 2) as usize,
+                params: SwapParams {
+                    zero_for_one: i % 3 == 0,
+                    amount_specified: I256::from_raw(U256::from(i * 1e16 as u128)),
+                    sqrt_price_limit_x_96: if i % 3 == 0 {
+                        MIN_SQRT_RATIO + U256_1
+                    } else {
+                        MAX_SQRT_RATIO - U256_1
+                    },
+                    zero_liquidity: ZeroLiquidity::Skip,
+                },
+            })
+            .collect();
+
+        //results are in request order and match quoting each request on its own
+        let results = batch_quote(&pools, &requests);
+        assert_eq!(results.len(), requests.len());
+        for (request, result) in requests.iter().zip(&results) {
+            let expected = pools[request.pool]
+                .quote(
+                    request.params.zero_for_one,
+                    request.params.amount_specified,
+                    request.params.sqrt_price_limit_x_96,
+                )
+                .unwrap();
+            assert_eq!(result.as_ref().unwrap(), &expected);
+        }
+
+        //failed quotes do not affect the rest of the batch
+        let mut invalid = requests[0];
+        invalid.params.amount_specified = I256::ZERO;
+        let results = batch_quote(&pools, &[invalid, requests[1]]);
+        assert!(matches!(results[0], Err(UniswapV3MathError::AS)));
+        assert!(results[1].is_ok());
+
+        //as do requests for a pool that does not exist
+        let mut unknown = requests[0];
+        unknown.pool = pools.len();
+        let results = batch_quote(&pools, &[unknown, requests[1]]);
+        assert!(matches!(results[0], Err(UniswapV3MathError::PoolNotFound)));
+        assert!(results[1].is_ok());
+
+        assert!(batch_quote(&pools, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_quote_within_horizon() {
+        let pool = full_range_pool(Q96, FeeTier::Medium, 1e21 as i128);
+
+        // a partial snapshot with only the two words around the current tick loaded, the full range ticks are not
+        let mut partial = pool.clone();
+        partial
+            .tick_bitmap
+            .retain(|&word_pos, _| (-1..=1).contains(&word_pos));
+        partial.ticks.clear();
+
+        // a small swap stays within the horizon and is quoted exactly
+        let params = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e18 as u128))
+            .build()
+            .unwrap();
+        let quote = quote_within_horizon(&partial, &params, 1).unwrap();
+        let full = pool.quote_with(&params).unwrap();
+        assert!(!quote.truncated);
+        assert_eq!(quote.result, full);
+        assert_eq!(quote.amount_min, full.amount_1.unsigned_abs());
+        assert_eq!(quote.amount_max, Some(quote.amount_min));
+        assert_eq!(quote.into_result().unwrap(), full);
+
+        // a price limit within the horizon is not truncated either
+        let limited = SwapParams {
+            sqrt_price_limit_x_96: get_sqrt_ratio_at_tick(-600).unwrap(),
+            amount_specified: I256::try_from(1e24 as u128).unwrap(),
+            ..params
+        };
+        let quote = quote_within_horizon(&partial, &limited, 1).unwrap();
+        assert!(!quote.truncated);
+        assert_eq!(quote.horizon_sqrt_price_x_96, None);
+        assert_eq!(quote.result, pool.quote_with(&limited).unwrap());
+
+        // without enough horizon the swap stops at the first tick of the last loaded word
+        for zero_for_one in [true, false] {
+            let horizon_tick = if zero_for_one {
+                -256 * 60
+            } else {
+                2 * 256 * 60 - 60
+            };
+            let horizon = get_sqrt_ratio_at_tick(horizon_tick).unwrap();
+
+            let exact_input = SwapParams::builder()
+                .zero_for_one(zero_for_one)
+                .amount_in(U256::from(1e24 as u128))
+                .build()
+                .unwrap();
+            let quote = quote_within_horizon(&partial, &exact_input, 1).unwrap();
+            let full = pool.quote_with(&exact_input).unwrap();
+            let full_out = amounts_in_out(zero_for_one, full.amount_0, full.amount_1).1;
+            assert!(quote.truncated);
+            assert_eq!(quote.horizon_sqrt_price_x_96, Some(horizon));
+            assert_eq!(quote.result.sqrt_price_x_96, horizon);
+            assert!(quote.amount_min < full_out);
+            assert!(quote.amount_max.unwrap() > full_out);
+            assert!(matches!(
+                quote.into_result(),
+                Err(UniswapV3MathError::QuoteTruncated)
+            ));
+
+            let exact_output = SwapParams::builder()
+                .zero_for_one(zero_for_one)
+                .amount_out(U256::from(9e20 as u128))
+                .build()
+                .unwrap();
+            let quote = quote_within_horizon(&partial, &exact_output, 1).unwrap();
+            let full = pool.quote_with(&exact_output).unwrap();
+            let full_in = amounts_in_out(zero_for_one, full.amount_0, full.amount_1).0;
+            assert!(quote.truncated);
+            assert!(quote.amount_min < full_in);
+            assert_eq!(quote.amount_max, None);
+
+            // with the whole pool loaded the horizon reaches the tick bounds
+            let quote = quote_within_horizon(&pool, &exact_input, 64).unwrap();
+            assert!(!quote.truncated);
+            assert_eq!(quote.horizon_sqrt_price_x_96, None);
+        }
+    }
+}
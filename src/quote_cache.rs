@@ -0,0 +1,237 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use alloy_primitives::U256;
+
+use crate::{
+    error::UniswapV3MathError,
+    pool::{PoolSnapshot, SwapParams, SwapResult},
+    storage::{InitializedTicks, TickStorage},
+    tick_math::{MAX_TICK, MIN_TICK},
+    variant::PoolVariant,
+};
+
+// a quote only depends on the price, the active liquidity, the fees and the initialized ticks between the price and
+// where the swap ends, not on the rest of the pool. Cached quotes are keyed by the first three and the arguments, and
+// validated on a hit by hashing the initialized ticks within reach of the cached quote, i.e. the ticks it crossed and
+// the next one it stepped towards, which only walks the tick bitmap and is much cheaper than swapping
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct QuoteKey {
+    sqrt_price_x_96: U256,
+    tick: i32,
+    liquidity: u128,
+    fee: u32,
+    fee_protocol: u32,
+    params: SwapParams,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CachedQuote {
+    reach_hash: u64,
+    result: SwapResult,
+}
+
+//Memoizes the quotes of a single pool, repeated identical quotes are served from the cache as long as the state they
+//depend on is unchanged, so the pool can be updated in place between quotes. Like `CachedTickProvider`, the cache is
+//meant to live for a block, moving to another block with `set_block` drops all quotes
+#[derive(Clone, Debug, Default)]
+pub struct QuoteCache {
+    block: Option<u64>,
+    entries: HashMap<QuoteKey, CachedQuote>,
+    hits: u64,
+    misses: u64,
+}
+
+impl QuoteCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // quotes from now on are for `block`, the cache is cleared if it holds quotes of another block
+    pub fn set_block(&mut self, block: u64) {
+        if self.block != Some(block) {
+            self.clear();
+            self.block = Some(block);
+        }
+    }
+
+    pub fn block(&self) -> Option<u64> {
+        self.block
+    }
+
+    // same as `PoolSnapshot::quote_with`, served from the cache if the same quote was made against the same state.
+    // Failed quotes are not cached
+    pub fn quote<T, B, V>(
+        &mut self,
+        pool: &PoolSnapshot<T, B, V>,
+        params: &SwapParams,
+    ) -> Result<SwapResult, UniswapV3MathError>
+    where
+        T: TickStorage,
+        B: InitializedTicks,
+        V: PoolVariant,
+    {
+        let key = QuoteKey {
+            sqrt_price_x_96: pool.slot0.sqrt_price_x_96,
+            tick: pool.slot0.tick,
+            liquidity: pool.liquidity,
+            fee: pool.fee_tier.fee(),
+            fee_protocol: pool.variant.fee_protocol(&pool.slot0, params.zero_for_one),
+            params: *params,
+        };
+
+        if let Some(cached) = self.entries.get(&key) {
+            if reach_hash(pool, params.zero_for_one, cached.result.tick)? == cached.reach_hash {
+                self.hits += 1;
+                return Ok(cached.result);
+            }
+        }
+
+        self.misses += 1;
+        let result = pool.quote_with(params)?;
+        let reach_hash = reach_hash(pool, params.zero_for_one, result.tick)?;
+        self.entries.insert(key, CachedQuote { reach_hash, result });
+
+        Ok(result)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    // the number of cached quotes
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // the number of quotes served from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    // the number of quotes that had to be computed
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+// hashes the initialized ticks and their liquidity net from the current tick of the pool up to and including the tick
+// the swap stepped towards last, visiting the same bitmap words as a swap in the same direction that ends at
+// `tick_end`
+pub(crate) fn reach_hash<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    zero_for_one: bool,
+    tick_end: i32,
+) -> Result<u64, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+{
+    let tick_spacing = pool.fee_tier.tick_spacing();
+    let mut hasher = DefaultHasher::new();
+    let mut tick = pool.slot0.tick;
+
+    loop {
+        let (tick_next, initialized) = pool.tick_bitmap.next_initialized_tick_within_one_word(
+            tick,
+            tick_spacing,
+            zero_for_one,
+        )?;
+        let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+
+        if initialized {
+            (tick_next, pool.ticks.liquidity_net(tick_next)).hash(&mut hasher);
+        }
+
+        let past_end = if zero_for_one {
+            tick_next <= tick_end
+        } else {
+            tick_next > tick_end
+        };
+        if past_end || tick_next == MIN_TICK || tick_next == MAX_TICK {
+            break;
+        }
+
+        tick = if zero_for_one {
+            tick_next - 1
+        } else {
+            tick_next
+        };
+    }
+
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::QuoteCache;
+    use crate::{
+        fee_tier::FeeTier, pool::PoolSnapshot, pool::SwapParams, tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    #[test]
+    fn test_quote_cache() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-1200, -120, 1e20 as i128).unwrap();
+        let params = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(2e19 as u128))
+            .build()
+            .unwrap();
+        let expected = pool.quote_with(&params).unwrap();
+        assert!(expected.initialized_ticks_crossed > 0);
+
+        //repeated quotes are served from the cache
+        let mut cache = QuoteCache::new();
+        cache.set_block(100);
+        for _ in 0..3 {
+            assert_eq!(cache.quote(&pool, &params).unwrap(), expected);
+        }
+        assert_eq!((cache.hits(), cache.misses()), (2, 1));
+
+        //liquidity out of reach of the quote does not invalidate it
+        pool.modify_position(600, 1200, 1e20 as i128).unwrap();
+        pool.modify_position(-60000, -30000, 1e20 as i128).unwrap();
+        assert_eq!(cache.quote(&pool, &params).unwrap(), expected);
+        assert_eq!(cache.hits(), 3);
+
+        //liquidity within reach does
+        pool.modify_position(-300, -240, 1e20 as i128).unwrap();
+        let result = cache.quote(&pool, &params).unwrap();
+        assert_eq!(result, pool.quote_with(&params).unwrap());
+        assert_ne!(result, expected);
+        assert_eq!((cache.hits(), cache.misses()), (3, 2));
+        assert_eq!(cache.len(), 1);
+
+        //as does a change of the price
+        let mut moved = pool.clone();
+        moved
+            .swap(
+                false,
+                I256::from_raw(U256::from(1e18 as u128)),
+                get_sqrt_ratio_at_tick(600).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            cache.quote(&moved, &params).unwrap(),
+            moved.quote_with(&params).unwrap()
+        );
+        assert_eq!(cache.misses(), 3);
+        assert_eq!(cache.len(), 2);
+
+        //the cache is only valid for one block
+        cache.set_block(101);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use alloy_primitives::{I256, U256};
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+use crate::{error::UniswapV3MathError, pool::SwapResult, tick_math::get_sqrt_ratio_at_tick};
+
+pub use num_rational::BigRational;
+
+// converting Q96 and Q128 values through f64 loses precision, rationals keep every bit until they are rounded for
+// display. Amounts and prices are returned as num-rational's BigRational, an exact fraction of arbitrary precision
+// integers kept in lowest terms
+
+// returns a U256 as an integer rational
+pub fn from_u256(value: U256) -> BigRational {
+    BigRational::from_integer(to_big_int(value))
+}
+
+// returns an I256 as an integer rational
+pub fn from_i256(value: I256) -> BigRational {
+    let magnitude = to_big_int(value.unsigned_abs());
+    BigRational::from_integer(if value.is_negative() {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
+
+// returns the value of a Q96 fixed point number
+pub fn from_x_96(value_x_96: U256) -> BigRational {
+    BigRational::new(to_big_int(value_x_96), BigInt::one() << 96)
+}
+
+// returns the value of a Q128 fixed point number
+pub fn from_x_128(value_x_128: U256) -> BigRational {
+    BigRational::new(to_big_int(value_x_128), BigInt::one() << 128)
+}
+
+// formats the value with `decimals` digits after the point, rounding half away from zero
+pub fn to_decimal_string(value: &BigRational, decimals: u32) -> String {
+    let scaled = value.numer().abs() * BigInt::from(10u8).pow(decimals);
+    let (mut quotient, remainder) = scaled.div_rem(value.denom());
+    if remainder * 2 >= *value.denom() {
+        quotient += 1;
+    }
+
+    let digits = quotient.to_string();
+    let sign = if value.is_negative() && !quotient.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    if decimals == 0 {
+        return format!("{sign}{digits}");
+    }
+
+    let digits = format!("{digits:0>width$}", width = decimals as usize + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals as usize);
+    format!("{sign}{integer}.{fraction}")
+}
+
+// returns the exact price of token0 in token1 in raw units, i.e. sqrt_price_x_96^2 / 2^192
+pub fn price(sqrt_price_x_96: U256) -> BigRational {
+    let sqrt_price = to_big_int(sqrt_price_x_96);
+    BigRational::new(&sqrt_price * &sqrt_price, BigInt::one() << 192)
+}
+
+// returns the exact price of token0 in token1 in whole token units
+pub fn price_with_decimals(sqrt_price_x_96: U256, decimals_0: u8, decimals_1: u8) -> BigRational {
+    price(sqrt_price_x_96) * BigRational::new(pow_10(decimals_0), pow_10(decimals_1))
+}
+
+// returns the exact price at a tick in raw units, as rounded by get_sqrt_ratio_at_tick
+pub fn tick_price(tick: i32) -> Result<BigRational, UniswapV3MathError> {
+    Ok(price(get_sqrt_ratio_at_tick(tick)?))
+}
+
+// returns a raw token amount in whole token units
+pub fn amount(amount: U256, decimals: u8) -> BigRational {
+    BigRational::new(to_big_int(amount), pow_10(decimals))
+}
+
+// returns the amounts of a swap in whole token units, signed from the perspective of the pool
+pub fn swap_amounts(
+    result: &SwapResult,
+    decimals_0: u8,
+    decimals_1: u8,
+) -> (BigRational, BigRational) {
+    (
+        from_i256(result.amount_0) / BigRational::from_integer(pow_10(decimals_0)),
+        from_i256(result.amount_1) / BigRational::from_integer(pow_10(decimals_1)),
+    )
+}
+
+fn to_big_int(value: U256) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, &value.to_be_bytes::<32>())
+}
+
+fn pow_10(exponent: u8) -> BigInt {
+    BigInt::from(10u8).pow(exponent as u32)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+    use num_bigint::BigInt;
+    use num_traits::{ToPrimitive, Zero};
+
+    use super::{
+        amount, from_i256, from_x_96, price, price_with_decimals, swap_amounts, tick_price,
+        to_decimal_string, BigRational,
+    };
+    use crate::{
+        fee_tier::FeeTier,
+        pool::PoolSnapshot,
+        sqrt_price_math::Q96,
+        tick_math::{get_sqrt_ratio_at_tick, MIN_SQRT_RATIO},
+    };
+
+    fn rational(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(BigInt::from(numer), BigInt::from(denom))
+    }
+
+    #[test]
+    fn test_to_decimal_string() {
+        //rounded only when formatted
+        assert_eq!(to_decimal_string(&rational(2, 3), 4), "0.6667");
+        assert_eq!(to_decimal_string(&rational(-1, 8), 2), "-0.13");
+        assert_eq!(to_decimal_string(&rational(-1, 1000), 2), "0.00");
+        assert_eq!(to_decimal_string(&rational(1234, 1), 0), "1234");
+        assert_eq!(to_decimal_string(&rational(5, 2), 0), "3");
+        assert_eq!(rational(1, 3).to_f64(), Some(1.0 / 3.0));
+    }
+
+    #[test]
+    fn test_price() {
+        assert_eq!(price(Q96), rational(1, 1));
+        assert_eq!(from_x_96(Q96 * U256::from(3)), rational(3, 1));
+
+        //exact, squaring the sqrt price loses nothing
+        let sqrt_price_x_96 = get_sqrt_ratio_at_tick(201000).unwrap();
+        let price = price(sqrt_price_x_96);
+        let sqrt_price = from_x_96(sqrt_price_x_96);
+        assert_eq!(price, &sqrt_price * &sqrt_price);
+        assert_eq!(tick_price(201000).unwrap(), price);
+
+        //usdc/weth, the price of one usdc in weth
+        let price = price_with_decimals(sqrt_price_x_96, 6, 18);
+        assert_eq!(to_decimal_string(&price, 10), "0.0005356519");
+
+        assert_eq!(amount(U256::from(1500000), 6), rational(3, 2));
+    }
+
+    #[test]
+    fn test_swap_amounts() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        let result = pool
+            .quote(
+                true,
+                I256::from_raw(U256::from(1e18 as u128)),
+                MIN_SQRT_RATIO + U256::from(1),
+            )
+            .unwrap();
+
+        let (amount_0, amount_1) = swap_amounts(&result, 18, 18);
+        assert_eq!(amount_0, rational(1, 1));
+        assert_eq!(
+            amount_1,
+            from_i256(result.amount_1) / BigRational::from_integer(BigInt::from(10).pow(18))
+        );
+        assert!(amount_1 < BigRational::zero());
+    }
+}
@@ -0,0 +1,256 @@
+use alloy_primitives::{I256, U256, U512};
+
+use crate::{
+    analytics::REFERENCE_LIQUIDITY,
+    error::UniswapV3MathError,
+    liquidity_amounts::get_liquidity_for_amounts,
+    pool::PoolSnapshot,
+    position::amounts_at_price,
+    tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    U256_1,
+};
+
+//A swap that brings token balances to the ratio of a range at the post-swap price
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rebalance {
+    pub zero_for_one: bool,
+    // the input amount of the swap including fees, zero if the balances already match the range
+    pub amount_in: U256,
+    pub amount_out: U256,
+    // the token0/token1 balances after the swap
+    pub amount_0: U256,
+    pub amount_1: U256,
+    // the pool sqrt price after the swap
+    pub sqrt_price_x_96: U256,
+    // the liquidity that can be minted in the range from the balances after the swap
+    pub liquidity: u128,
+}
+
+// returns the swap against the pool that turns `amount_0` and `amount_1` into balances matching the token ratio of the
+// range at the price after the swap, which accounts for the price impact of the swap itself. The swap amount is the
+// largest that does not overshoot the ratio, so minting leaves dust of the token that is sold
+pub fn rebalance(
+    pool: &PoolSnapshot,
+    amount_0: U256,
+    amount_1: U256,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Result<Rebalance, UniswapV3MathError> {
+    let sqrt_ratio_a_x_96 = get_sqrt_ratio_at_tick(tick_lower)?;
+    let sqrt_ratio_b_x_96 = get_sqrt_ratio_at_tick(tick_upper)?;
+
+    // the balances and pool price after swapping `amount_in`
+    let swap = |zero_for_one: bool, amount_in: U256| -> Result<Rebalance, UniswapV3MathError> {
+        let (amount_out, sqrt_price_x_96) = if amount_in.is_zero() {
+            (U256::ZERO, pool.slot0.sqrt_price_x_96)
+        } else {
+            let limit = if zero_for_one {
+                MIN_SQRT_RATIO + U256_1
+            } else {
+                MAX_SQRT_RATIO - U256_1
+            };
+            let result = pool.quote(zero_for_one, I256::from_raw(amount_in), limit)?;
+            let (amount_in_used, amount_out) = if zero_for_one {
+                (result.amount_0, result.amount_1)
+            } else {
+                (result.amount_1, result.amount_0)
+            };
+            // running out of liquidity leaves part of the input unswapped, which can never balance the ratio
+            if amount_in_used.into_raw() != amount_in {
+                return Err(UniswapV3MathError::LiquidityIsZero);
+            }
+            (amount_out.unsigned_abs(), result.sqrt_price_x_96)
+        };
+
+        let (amount_0, amount_1) = if zero_for_one {
+            (amount_0 - amount_in, amount_1 + amount_out)
+        } else {
+            (amount_0 + amount_out, amount_1 - amount_in)
+        };
+
+        Ok(Rebalance {
+            zero_for_one,
+            amount_in,
+            amount_out,
+            amount_0,
+            amount_1,
+            sqrt_price_x_96,
+            liquidity: 0,
+        })
+    };
+
+    // true if the balances hold more token0 than the range at the price, compared as amount_0 / amount_1 against the
+    // ratio of the range
+    let excess_0 = |state: &Rebalance| -> Result<bool, UniswapV3MathError> {
+        let (range_0, range_1) = amounts_at_price(
+            state.sqrt_price_x_96,
+            tick_lower,
+            tick_upper,
+            REFERENCE_LIQUIDITY,
+        )?;
+        Ok(U512::from(state.amount_0) * U512::from(range_1)
+            > U512::from(state.amount_1) * U512::from(range_0))
+    };
+    let excess_1 = |state: &Rebalance| -> Result<bool, UniswapV3MathError> {
+        let (range_0, range_1) = amounts_at_price(
+            state.sqrt_price_x_96,
+            tick_lower,
+            tick_upper,
+            REFERENCE_LIQUIDITY,
+        )?;
+        Ok(U512::from(state.amount_1) * U512::from(range_0)
+            > U512::from(state.amount_0) * U512::from(range_1))
+    };
+
+    let current = swap(true, U256::ZERO)?;
+    let (zero_for_one, mut high) = if excess_0(&current)? {
+        (true, amount_0)
+    } else if excess_1(&current)? {
+        (false, amount_1)
+    } else {
+        (true, U256::ZERO)
+    };
+    // the swap overshoots once the bought token is in excess, which only becomes more likely as the swap grows
+    let overshoots = |state: &Rebalance| {
+        if zero_for_one {
+            excess_1(state)
+        } else {
+            excess_0(state)
+        }
+    };
+
+    // search for the largest swap that does not overshoot
+    let mut low = U256::ZERO;
+    if overshoots(&swap(zero_for_one, high)?)? {
+        while high - low > U256_1 {
+            let mid = low + (high - low) / U256::from(2);
+            if overshoots(&swap(zero_for_one, mid)?)? {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        high = low;
+    }
+
+    let mut rebalance = swap(zero_for_one, high)?;
+    rebalance.liquidity = get_liquidity_for_amounts(
+        rebalance.sqrt_price_x_96,
+        sqrt_ratio_a_x_96,
+        sqrt_ratio_b_x_96,
+        rebalance.amount_0,
+        rebalance.amount_1,
+    )?;
+
+    Ok(rebalance)
+}
+
+// returns the swap that turns a deposit of only token0, or only token1 if `deposit_token_0` is false, into balances
+// that fit the range at the price after the swap, like the SwapToRatio library of the periphery. The periphery
+// approximates the swap within the current tick to bound gas, this searches the exact swap through the pool, including
+// ticks crossed by it, see `rebalance`. A range entirely on the side of the deposited token does not swap, a range
+// entirely on the other side swaps the whole deposit
+pub fn swap_to_ratio(
+    pool: &PoolSnapshot,
+    deposit_token_0: bool,
+    amount: U256,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Result<Rebalance, UniswapV3MathError> {
+    let (amount_0, amount_1) = if deposit_token_0 {
+        (amount, U256::ZERO)
+    } else {
+        (U256::ZERO, amount)
+    };
+    rebalance(pool, amount_0, amount_1, tick_lower, tick_upper)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{rebalance, swap_to_ratio};
+    use crate::{
+        orientation::unbounded_sqrt_price_limit, pool::PoolSnapshot, position::amounts_at_price,
+        test_utils::PoolBuilder,
+    };
+
+    fn pool_at(tick_current: i32) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .full_range(1e20 as i128)
+            .build()
+    }
+
+    #[test]
+    fn test_rebalance() {
+        let pool = pool_at(0);
+        let amount = U256::from(1e18 as u128);
+
+        //moving all token0 into a symmetric range sells less than half of it, as the swap lowers the price and the
+        //range then holds more token0
+        let result = rebalance(&pool, amount, U256::ZERO, -600, 600).unwrap();
+        assert!(result.zero_for_one);
+        assert!(result.sqrt_price_x_96 < pool.slot0.sqrt_price_x_96);
+        assert!(result.amount_in > amount * U256::from(40) / U256::from(100));
+        assert!(result.amount_in < amount / U256::from(2));
+
+        //minting the liquidity uses nearly all of both balances
+        let (used_0, used_1) =
+            amounts_at_price(result.sqrt_price_x_96, -600, 600, result.liquidity).unwrap();
+        assert!(result.amount_0 - used_0 < U256::from(1e12 as u128));
+        assert!(result.amount_1 - used_1 < U256::from(1e12 as u128));
+
+        //balances skewed the other way sell token1
+        let result = rebalance(&pool, U256::ZERO, amount, -600, 600).unwrap();
+        assert!(!result.zero_for_one);
+        assert!(result.amount_0 > U256::ZERO && result.amount_1 > U256::ZERO);
+
+        //ranges above the price only hold token0, so all token1 is sold
+        let result = rebalance(&pool, amount, amount, 600, 1200).unwrap();
+        assert!(!result.zero_for_one);
+        assert_eq!(result.amount_in, amount);
+        assert_eq!(result.amount_1, U256::ZERO);
+
+        //balances that already match the range do not swap
+        let (amount_0, amount_1) =
+            amounts_at_price(pool.slot0.sqrt_price_x_96, -600, 600, 1e18 as u128).unwrap();
+        let result = rebalance(&pool, amount_0, amount_1, -600, 600).unwrap();
+        assert!(result.amount_in <= U256::from(1));
+    }
+
+    #[test]
+    fn test_swap_to_ratio() {
+        let mut pool = pool_at(100);
+        pool.modify_position(-300, 300, 1e21 as i128).unwrap();
+        let amount = U256::from(1e20 as u128);
+
+        //a large deposit crosses ticks and still fits the range
+        for deposit_token_0 in [true, false] {
+            let result = swap_to_ratio(&pool, deposit_token_0, amount, -1800, 1800).unwrap();
+            assert_eq!(result.zero_for_one, deposit_token_0);
+            let quote = pool
+                .quote(
+                    result.zero_for_one,
+                    I256::from_raw(result.amount_in),
+                    unbounded_sqrt_price_limit(result.zero_for_one),
+                )
+                .unwrap();
+            assert!(quote.initialized_ticks_crossed > 0);
+            assert_eq!(result.sqrt_price_x_96, quote.sqrt_price_x_96);
+
+            let (used_0, used_1) =
+                amounts_at_price(result.sqrt_price_x_96, -1800, 1800, result.liquidity).unwrap();
+            assert!(result.amount_0 - used_0 < amount / U256::from(1e9 as u128));
+            assert!(result.amount_1 - used_1 < amount / U256::from(1e9 as u128));
+        }
+
+        //depositing the token the range holds does not swap, the other token is swapped entirely
+        let amount = U256::from(1e15 as u128);
+        let result = swap_to_ratio(&pool, true, amount, 600, 1200).unwrap();
+        assert_eq!(result.amount_in, U256::ZERO);
+        assert_eq!(result.amount_0, amount);
+        let result = swap_to_ratio(&pool, false, amount, 600, 1200).unwrap();
+        assert_eq!(result.amount_in, amount);
+        assert_eq!(result.amount_1, U256::ZERO);
+    }
+}
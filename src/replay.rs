@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    fee_tier::FeeTier,
+    pool::{PoolSnapshot, ProtocolFees, Slot0, SwapParams, SwapResult},
+    tick::Tick,
+};
+
+// a replay bundle is everything needed to reproduce a divergence from the contract in a single value: the pool state
+// before, the operations applied to it in order and the outputs they are expected to produce, e.g. as observed on
+// chain. With the `serde` feature it can be written to a file and attached to an issue. Ticks and words are kept in
+// sorted maps so serializing the same bundle always produces the same file
+
+// the version of the bundle format, bumped whenever a change would make old bundles replay differently
+pub const REPLAY_VERSION: u32 = 2;
+
+//The state of a pool in a replay bundle, see `PoolSnapshot`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayPool {
+    pub slot0: Slot0,
+    pub liquidity: u128,
+    pub fee_tier: FeeTier,
+    pub fee_growth_global_0_x_128: U256,
+    pub fee_growth_global_1_x_128: U256,
+    pub protocol_fees: ProtocolFees,
+    pub ticks: BTreeMap<i32, Tick>,
+    pub tick_bitmap: BTreeMap<i16, U256>,
+}
+
+impl From<&PoolSnapshot> for ReplayPool {
+    fn from(pool: &PoolSnapshot) -> Self {
+        Self {
+            slot0: pool.slot0,
+            liquidity: pool.liquidity,
+            fee_tier: pool.fee_tier,
+            fee_growth_global_0_x_128: pool.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: pool.fee_growth_global_1_x_128,
+            protocol_fees: pool.protocol_fees,
+            ticks: pool
+                .ticks
+                .iter()
+                .map(|(&tick, &info)| (tick, info))
+                .collect(),
+            tick_bitmap: pool
+                .tick_bitmap
+                .iter()
+                .map(|(&word_pos, &word)| (word_pos, word))
+                .collect(),
+        }
+    }
+}
+
+impl From<&ReplayPool> for PoolSnapshot {
+    fn from(pool: &ReplayPool) -> Self {
+        Self {
+            slot0: pool.slot0,
+            liquidity: pool.liquidity,
+            fee_tier: pool.fee_tier,
+            fee_growth_global_0_x_128: pool.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: pool.fee_growth_global_1_x_128,
+            protocol_fees: pool.protocol_fees,
+            ticks: pool
+                .ticks
+                .iter()
+                .map(|(&tick, &info)| (tick, info))
+                .collect(),
+            tick_bitmap: pool
+                .tick_bitmap
+                .iter()
+                .map(|(&word_pos, &word)| (word_pos, word))
+                .collect(),
+            variant: Default::default(),
+        }
+    }
+}
+
+// an operation applied to the pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operation {
+    // quotes without modifying the pool
+    Quote(SwapParams),
+    Swap(SwapParams),
+    ModifyPosition {
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity_delta: i128,
+    },
+}
+
+// the output of an operation. Failures are compared by the kind of error, see `UniswapV3MathError::kind`, so bundles
+// keep replaying when an error message is reworded
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    Swap(SwapResult),
+    ModifyPosition { amount_0: I256, amount_1: I256 },
+    Failed { kind: String },
+}
+
+//A pool state with the operations to apply to it and the outcomes they are expected to have
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayBundle {
+    pub version: u32,
+    pub pool: ReplayPool,
+    pub operations: Vec<Operation>,
+    // the expected outcome of each operation, by index
+    pub expected: Vec<Outcome>,
+}
+
+impl ReplayBundle {
+    // returns a bundle of the operations on `pool` with the outcomes they have with the current math, e.g. to turn a
+    // fixed divergence into a regression test
+    pub fn record(pool: &PoolSnapshot, operations: Vec<Operation>) -> Self {
+        let mut replayed = PoolSnapshot::from(&ReplayPool::from(pool));
+        let expected = operations
+            .iter()
+            .map(|operation| apply(&mut replayed, operation))
+            .collect();
+
+        Self {
+            version: REPLAY_VERSION,
+            pool: pool.into(),
+            operations,
+            expected,
+        }
+    }
+}
+
+//The first operation whose outcome differs from the expected one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub index: usize,
+    pub operation: Operation,
+    // the expected outcome, none if the bundle has fewer outcomes than operations
+    pub expected: Option<Outcome>,
+    pub actual: Outcome,
+}
+
+// applies the operations of the bundle in order to its pool and returns the outcomes, fails with the first operation
+// whose outcome differs from the expected one
+pub fn run(bundle: &ReplayBundle) -> Result<Vec<Outcome>, Box<Divergence>> {
+    let mut pool = PoolSnapshot::from(&bundle.pool);
+    let mut outcomes = Vec::with_capacity(bundle.operations.len());
+
+    for (index, operation) in bundle.operations.iter().enumerate() {
+        let actual = apply(&mut pool, operation);
+        let expected = bundle.expected.get(index);
+        if expected != Some(&actual) {
+            return Err(Box::new(Divergence {
+                index,
+                operation: *operation,
+                expected: expected.cloned(),
+                actual,
+            }));
+        }
+        outcomes.push(actual);
+    }
+
+    Ok(outcomes)
+}
+
+fn apply(pool: &mut PoolSnapshot, operation: &Operation) -> Outcome {
+    let outcome = match *operation {
+        Operation::Quote(params) => pool.quote_with(&params).map(Outcome::Swap),
+        Operation::Swap(params) => pool.swap_with(&params).map(Outcome::Swap),
+        Operation::ModifyPosition {
+            tick_lower,
+            tick_upper,
+            liquidity_delta,
+        } => pool
+            .modify_position(tick_lower, tick_upper, liquidity_delta)
+            .map(|(amount_0, amount_1)| Outcome::ModifyPosition { amount_0, amount_1 }),
+    };
+
+    outcome.unwrap_or_else(|error| Outcome::Failed { kind: error.kind() })
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{run, Operation, Outcome, ReplayBundle, ReplayPool, REPLAY_VERSION};
+    use crate::{
+        fee_tier::FeeTier,
+        pool::{PoolSnapshot, SwapParams},
+        tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    #[test]
+    fn test_replay() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        assert_eq!(PoolSnapshot::from(&ReplayPool::from(&pool)), pool);
+
+        let swap = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e19 as u128))
+            .build()
+            .unwrap();
+        let operations = vec![
+            Operation::Quote(swap),
+            Operation::Swap(swap),
+            Operation::ModifyPosition {
+                tick_lower: -1200,
+                tick_upper: -60,
+                liquidity_delta: 1e20 as i128,
+            },
+            Operation::ModifyPosition {
+                tick_lower: 60,
+                tick_upper: 0,
+                liquidity_delta: 1,
+            },
+        ];
+        let bundle = ReplayBundle::record(&pool, operations);
+        assert_eq!(bundle.version, REPLAY_VERSION);
+        assert_eq!(
+            bundle.expected[3],
+            Outcome::Failed {
+                kind: "TLU".to_string()
+            }
+        );
+
+        //the recorded outcomes are reproduced and the recorded pool is left untouched
+        let outcomes = run(&bundle).unwrap();
+        assert_eq!(outcomes, bundle.expected);
+        assert_eq!(outcomes[0], outcomes[1]);
+        assert_eq!(
+            outcomes[1],
+            Outcome::Swap(pool.clone().swap_with(&swap).unwrap())
+        );
+        assert_eq!(PoolSnapshot::from(&bundle.pool), pool);
+
+        //fails with the first operation that diverges
+        let mut diverging = bundle.clone();
+        let Outcome::Swap(mut result) = diverging.expected[1] else {
+            panic!("expected a swap");
+        };
+        result.amount_1 -= I256::ONE;
+        diverging.expected[1] = Outcome::Swap(result);
+        let divergence = run(&diverging).unwrap_err();
+        assert_eq!(divergence.index, 1);
+        assert_eq!(divergence.operation, Operation::Swap(swap));
+        assert_eq!(divergence.actual, bundle.expected[1]);
+
+        //as well as with missing outcomes
+        let mut truncated = bundle.clone();
+        truncated.expected.truncate(2);
+        let divergence = run(&truncated).unwrap_err();
+        assert_eq!((divergence.index, divergence.expected), (2, None));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_replay_bundle_serde() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        let swap = SwapParams::builder()
+            .zero_for_one(false)
+            .amount_in(U256::from(1e19 as u128))
+            .build()
+            .unwrap();
+        let operations = vec![
+            Operation::Swap(swap),
+            Operation::ModifyPosition {
+                tick_lower: -61,
+                tick_upper: 60,
+                liquidity_delta: 1,
+            },
+        ];
+        let bundle = ReplayBundle::record(&pool, operations);
+
+        //the bundle survives a round trip and still replays, failures are stored by kind
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert!(json.contains(r#"{"Failed":{"kind":"TickSpacingError"}}"#));
+        let decoded = serde_json::from_str::<ReplayBundle>(&json).unwrap();
+        assert_eq!(decoded, bundle);
+        assert_eq!(run(&decoded).unwrap(), bundle.expected);
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use alloy_primitives::{Address, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    orientation::{amounts_in_out, TradeAmount},
+    path::{decode_exact_output_path, decode_path, Hop},
+    pool::{PoolSnapshot, SwapResult},
+    pool_key::PoolKey,
+};
+
+// the pools a router call can swap in, by key
+pub type PoolSet = HashMap<PoolKey, PoolSnapshot>;
+
+//A SwapRouter call decoded from calldata, e.g. of a pending transaction. The recipient and deadline do not affect the
+//amounts and are left out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RouterCall {
+    // mirrors ISwapRouter.ExactInputSingleParams, a price limit of 0 is no limit
+    ExactInputSingle {
+        token_in: Address,
+        token_out: Address,
+        fee: u32,
+        amount_in: U256,
+        amount_out_minimum: U256,
+        sqrt_price_limit_x_96: U256,
+    },
+    // mirrors ISwapRouter.ExactInputParams, see `path::decode_path`
+    ExactInput {
+        path: Vec<u8>,
+        amount_in: U256,
+        amount_out_minimum: U256,
+    },
+    // mirrors ISwapRouter.ExactOutputSingleParams, a price limit of 0 is no limit
+    ExactOutputSingle {
+        token_in: Address,
+        token_out: Address,
+        fee: u32,
+        amount_out: U256,
+        amount_in_maximum: U256,
+        sqrt_price_limit_x_96: U256,
+    },
+    // mirrors ISwapRouter.ExactOutputParams, the path starts with the output token
+    ExactOutput {
+        path: Vec<u8>,
+        amount_out: U256,
+        amount_in_maximum: U256,
+    },
+}
+
+// the swap of a router call in a single pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolSwap {
+    pub key: PoolKey,
+    // the swap result, including the price, tick and liquidity of the pool after it
+    pub result: SwapResult,
+}
+
+//The predicted outcome of a router call
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouterSimulation {
+    // the amount the caller pays and receives
+    pub amount_in: U256,
+    pub amount_out: U256,
+    // the swap in each pool, in the order the tokens flow through them
+    pub swaps: Vec<PoolSwap>,
+    // the state after the call of every pool the call swaps in
+    pub pools: PoolSet,
+}
+
+// simulates a router call against the pools without modifying them. The swaps are executed in the order the router
+// executes them, so a path through the same pool twice sees the state left by the first swap. Fails like the router
+// reverts, with `TooLittleReceived` or `TooMuchRequested` if the slippage check fails, and with `OutputNotFilled` if an
+// exact output swap without a price limit can not be filled. Fails with `PoolNotFound` if a pool is not in `pools`
+pub fn simulate_router_call(
+    pools: &PoolSet,
+    call: &RouterCall,
+) -> Result<RouterSimulation, UniswapV3MathError> {
+    let simulation = match call {
+        RouterCall::ExactInputSingle {
+            token_in,
+            token_out,
+            fee,
+            amount_in,
+            amount_out_minimum,
+            sqrt_price_limit_x_96,
+        } => {
+            let simulation = simulate_hops(
+                pools,
+                &[Hop::new(*token_in, *token_out, *fee)],
+                TradeAmount::ExactInput(*amount_in),
+                *sqrt_price_limit_x_96,
+            )?;
+            check_amount_out(simulation, *amount_out_minimum)?
+        }
+        RouterCall::ExactInput {
+            path,
+            amount_in,
+            amount_out_minimum,
+        } => {
+            let simulation = simulate_hops(
+                pools,
+                &decode_path(path)?,
+                TradeAmount::ExactInput(*amount_in),
+                U256::ZERO,
+            )?;
+            check_amount_out(simulation, *amount_out_minimum)?
+        }
+        RouterCall::ExactOutputSingle {
+            token_in,
+            token_out,
+            fee,
+            amount_out,
+            amount_in_maximum,
+            sqrt_price_limit_x_96,
+        } => {
+            let simulation = simulate_hops(
+                pools,
+                &[Hop::new(*token_in, *token_out, *fee)],
+                TradeAmount::ExactOutput(*amount_out),
+                *sqrt_price_limit_x_96,
+            )?;
+            check_amount_in(simulation, *amount_in_maximum)?
+        }
+        RouterCall::ExactOutput {
+            path,
+            amount_out,
+            amount_in_maximum,
+        } => {
+            let simulation = simulate_hops(
+                pools,
+                &decode_exact_output_path(path)?,
+                TradeAmount::ExactOutput(*amount_out),
+                U256::ZERO,
+            )?;
+            check_amount_in(simulation, *amount_in_maximum)?
+        }
+    };
+
+    Ok(simulation)
+}
+
+fn check_amount_out(
+    simulation: RouterSimulation,
+    amount_out_minimum: U256,
+) -> Result<RouterSimulation, UniswapV3MathError> {
+    if simulation.amount_out < amount_out_minimum {
+        return Err(UniswapV3MathError::TooLittleReceived);
+    }
+
+    Ok(simulation)
+}
+
+fn check_amount_in(
+    simulation: RouterSimulation,
+    amount_in_maximum: U256,
+) -> Result<RouterSimulation, UniswapV3MathError> {
+    if simulation.amount_in > amount_in_maximum {
+        return Err(UniswapV3MathError::TooMuchRequested);
+    }
+
+    Ok(simulation)
+}
+
+// swaps through the hops in the order the tokens flow, exact input hops from the first to the last and exact output
+// hops from the last to the first like the router. The price limit only applies to single hop calls
+fn simulate_hops(
+    pools: &PoolSet,
+    hops: &[Hop],
+    amount: TradeAmount,
+    sqrt_price_limit_x_96: U256,
+) -> Result<RouterSimulation, UniswapV3MathError> {
+    let mut touched = PoolSet::new();
+    let mut swaps = Vec::with_capacity(hops.len());
+
+    let mut swap_hop = |hop: &Hop, amount: TradeAmount| {
+        let key = hop.pool_key();
+        let pool = match touched.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                pools
+                    .get(&key)
+                    .ok_or(UniswapV3MathError::PoolNotFound)?
+                    .clone(),
+            ),
+        };
+
+        let mut params = hop.swap_params(amount)?;
+        if !sqrt_price_limit_x_96.is_zero() {
+            params = params.sqrt_price_limit(sqrt_price_limit_x_96);
+        }
+        let params = params.build()?;
+
+        let result = pool.swap_with(&params)?;
+        // the router only accepts a partial exact output swap if it stopped at the given price limit
+        if matches!(amount, TradeAmount::ExactOutput(_))
+            && sqrt_price_limit_x_96.is_zero()
+            && !result.amount_specified_remaining.is_zero()
+        {
+            return Err(UniswapV3MathError::OutputNotFilled);
+        }
+
+        swaps.push(PoolSwap { key, result });
+        Ok(amounts_in_out(
+            params.zero_for_one,
+            result.amount_0,
+            result.amount_1,
+        ))
+    };
+
+    let (amount_in, amount_out) = match amount {
+        TradeAmount::ExactInput(amount_in) => {
+            let mut amount = amount_in;
+            let mut paid = None;
+            for hop in hops {
+                let (amount_in, amount_out) = swap_hop(hop, TradeAmount::ExactInput(amount))?;
+                paid.get_or_insert(amount_in);
+                amount = amount_out;
+            }
+            (paid.unwrap_or_default(), amount)
+        }
+        TradeAmount::ExactOutput(amount_out) => {
+            let mut amount = amount_out;
+            let mut received = None;
+            for hop in hops.iter().rev() {
+                let (amount_in, amount_out) = swap_hop(hop, TradeAmount::ExactOutput(amount))?;
+                received.get_or_insert(amount_out);
+                amount = amount_in;
+            }
+            swaps.reverse();
+            (amount, received.unwrap_or_default())
+        }
+    };
+
+    Ok(RouterSimulation {
+        amount_in,
+        amount_out,
+        swaps,
+        pools: touched,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, Address, U256};
+
+    use super::{simulate_router_call, PoolSet, RouterCall};
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        orientation::TradeAmount,
+        path::{encode_path, quote_path, Hop},
+        pool::{PoolSnapshot, SwapParams},
+        pool_key::PoolKey,
+        tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+
+    fn pools() -> PoolSet {
+        [
+            (PoolKey::new(WETH, USDC, 500), FeeTier::Low),
+            (PoolKey::new(USDC, DAI, 100), FeeTier::Lowest),
+        ]
+        .into_iter()
+        .map(|(key, fee_tier)| {
+            let mut pool = PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), fee_tier).unwrap();
+            let tick = 887272 / fee_tier.tick_spacing() * fee_tier.tick_spacing();
+            pool.modify_position(-tick, tick, 1e21 as i128).unwrap();
+            (key, pool)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_simulate_router_call() {
+        let pools = pools();
+        let hops = [Hop::new(WETH, USDC, 500), Hop::new(USDC, DAI, 100)];
+        let amount_in = U256::from(1e18 as u128);
+
+        //a multi hop call matches the quote of its path and returns the pools after it
+        let quote = quote_path(&hops, TradeAmount::ExactInput(amount_in), |key| {
+            pools.get(key)
+        })
+        .unwrap();
+        let call = RouterCall::ExactInput {
+            path: encode_path(&hops).unwrap(),
+            amount_in,
+            amount_out_minimum: quote.amount_out,
+        };
+        let simulation = simulate_router_call(&pools, &call).unwrap();
+        assert_eq!(simulation.amount_in, amount_in);
+        assert_eq!(simulation.amount_out, quote.amount_out);
+        for (swap, (hop, result)) in simulation.swaps.iter().zip(hops.iter().zip(&quote.results)) {
+            assert_eq!(swap.key, hop.pool_key());
+            assert_eq!(swap.result, *result);
+            assert_eq!(
+                simulation.pools[&swap.key].slot0.sqrt_price_x_96,
+                result.sqrt_price_x_96
+            );
+        }
+
+        //fails like the router if the slippage check fails
+        let call = RouterCall::ExactInput {
+            path: encode_path(&hops).unwrap(),
+            amount_in,
+            amount_out_minimum: quote.amount_out + U256::from(1),
+        };
+        assert!(matches!(
+            simulate_router_call(&pools, &call),
+            Err(UniswapV3MathError::TooLittleReceived)
+        ));
+
+        //an exact output path is reversed and buys the output of the exact input call
+        let reversed = [Hop::new(DAI, USDC, 100), Hop::new(USDC, WETH, 500)];
+        let call = RouterCall::ExactOutput {
+            path: encode_path(&reversed).unwrap(),
+            amount_out: quote.amount_out,
+            amount_in_maximum: amount_in,
+        };
+        let simulation = simulate_router_call(&pools, &call).unwrap();
+        assert_eq!(simulation.amount_out, quote.amount_out);
+        assert!(simulation.amount_in <= amount_in);
+        assert_eq!(simulation.swaps[0].key, hops[0].pool_key());
+        let call = RouterCall::ExactOutput {
+            path: encode_path(&reversed).unwrap(),
+            amount_out: quote.amount_out,
+            amount_in_maximum: simulation.amount_in - U256::from(1),
+        };
+        assert!(matches!(
+            simulate_router_call(&pools, &call),
+            Err(UniswapV3MathError::TooMuchRequested)
+        ));
+
+        //single hop calls swap in their pool, up to the price limit
+        let key = PoolKey::new(WETH, USDC, 500);
+        let zero_for_one = WETH == key.token_0;
+        let sqrt_price_limit_x_96 =
+            get_sqrt_ratio_at_tick(if zero_for_one { -10 } else { 10 }).unwrap();
+        let call = RouterCall::ExactInputSingle {
+            token_in: WETH,
+            token_out: USDC,
+            fee: 500,
+            amount_in: U256::from(1e20 as u128),
+            amount_out_minimum: U256::ZERO,
+            sqrt_price_limit_x_96,
+        };
+        let simulation = simulate_router_call(&pools, &call).unwrap();
+        let expected = pools[&key]
+            .quote_with(
+                &SwapParams::builder()
+                    .zero_for_one(zero_for_one)
+                    .amount_in(U256::from(1e20 as u128))
+                    .sqrt_price_limit(sqrt_price_limit_x_96)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(simulation.swaps[0].result, expected);
+        assert!(simulation.amount_in < U256::from(1e20 as u128));
+        assert_eq!(
+            simulation.pools[&key].slot0.sqrt_price_x_96,
+            sqrt_price_limit_x_96
+        );
+
+        //an exact output swap without a price limit must be filled
+        let call = RouterCall::ExactOutputSingle {
+            token_in: WETH,
+            token_out: USDC,
+            fee: 500,
+            amount_out: U256::from(2e21 as u128),
+            amount_in_maximum: U256::MAX,
+            sqrt_price_limit_x_96: U256::ZERO,
+        };
+        assert!(matches!(
+            simulate_router_call(&pools, &call),
+            Err(UniswapV3MathError::OutputNotFilled)
+        ));
+
+        //the pools passed in are not modified
+        assert_eq!(pools, self::pools());
+    }
+}
@@ -0,0 +1,296 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    fee_tier::FEE_DENOMINATOR,
+    full_math::{mul_div, sqrt},
+    pool::PoolSnapshot,
+    sqrt_price_math::Q96,
+    tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    U256_1,
+};
+
+//An exact input amount split across several pools of the same pair
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Split {
+    // the input amount routed through each pool, in the same order as the pools
+    pub amounts_in: Vec<U256>,
+    // the output amount received from each pool
+    pub amounts_out: Vec<U256>,
+    // the total output amount
+    pub amount_out: U256,
+}
+
+// splits `amount_in` across pools of the same pair so that the marginal price net of fees is equal in every pool that
+// receives a share, which maximizes the total output. If the pools can not absorb the full amount before reaching the
+// price bounds, the returned amounts sum to less than `amount_in`
+pub fn split_amount_in(
+    pools: &[PoolSnapshot],
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<Split, UniswapV3MathError> {
+    // sqrt(1 - fee) as a Q96 for each pool, used to convert a common marginal price into per pool sqrt price targets
+    let fee_factors_x_96 = pools
+        .iter()
+        .map(|pool| {
+            let gamma = U256::from(FEE_DENOMINATOR - pool.fee_tier.fee());
+            sqrt((gamma << 192_usize) / U256::from(FEE_DENOMINATOR))
+        })
+        .collect::<Vec<_>>();
+
+    let amounts_at = |marginal_sqrt_price_x_96: U256| -> Result<Vec<U256>, UniswapV3MathError> {
+        pools
+            .iter()
+            .zip(&fee_factors_x_96)
+            .map(|(pool, &fee_factor_x_96)| {
+                let target =
+                    target_sqrt_price(marginal_sqrt_price_x_96, fee_factor_x_96, zero_for_one)?;
+                let current = pool.slot0.sqrt_price_x_96;
+                if (zero_for_one && target >= current) || (!zero_for_one && target <= current) {
+                    return Ok(U256::ZERO);
+                }
+                pool.get_amount_in_to_target_price(target)
+            })
+            .collect()
+    };
+    let total = |amounts: &[U256]| amounts.iter().fold(U256::ZERO, |acc, amount| acc + amount);
+
+    // the marginal sqrt price, net of fees, at which no pool receives any input
+    let idle = pools
+        .iter()
+        .zip(&fee_factors_x_96)
+        .map(|(pool, &fee_factor_x_96)| {
+            marginal_sqrt_price(pool.slot0.sqrt_price_x_96, fee_factor_x_96, zero_for_one)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // binary search for the marginal price that routes the largest amount not exceeding amount_in, the total routed
+    // amount is monotonic in the marginal price
+    let (mut low, mut high) = if zero_for_one {
+        (
+            MIN_SQRT_RATIO + U256_1,
+            idle.iter()
+                .copied()
+                .max()
+                .unwrap_or(MIN_SQRT_RATIO + U256_1),
+        )
+    } else {
+        (
+            idle.iter()
+                .copied()
+                .min()
+                .unwrap_or(MAX_SQRT_RATIO - U256_1),
+            MAX_SQRT_RATIO - U256_1,
+        )
+    };
+
+    let mut amounts_in = vec![U256::ZERO; pools.len()];
+    while low <= high {
+        let mid = low + (high - low) / U256::from(2);
+        let amounts = amounts_at(mid)?;
+
+        if total(&amounts) <= amount_in {
+            amounts_in = amounts;
+            // move the marginal price further away from the current prices to route more
+            if zero_for_one {
+                high = mid - U256_1;
+            } else {
+                low = mid + U256_1;
+            }
+        } else if zero_for_one {
+            low = mid + U256_1;
+        } else {
+            high = mid - U256_1;
+        }
+    }
+
+    // the discrete search leaves a few wei unrouted, they go to the pool that produces the most output for them. When
+    // the pools are exhausted the remainder is larger, the pool only takes the part it can swap before the price bound
+    let remainder = amount_in - total(&amounts_in);
+    if !remainder.is_zero() {
+        let mut best: Option<(usize, U256, U256)> = None;
+        for (index, pool) in pools.iter().enumerate() {
+            let (_, before) = quote_swap(pool, amounts_in[index], zero_for_one)?;
+            let (consumed, after) = quote_swap(pool, amounts_in[index] + remainder, zero_for_one)?;
+            let gain = after - before;
+            if best.is_none_or(|(_, _, best_gain)| gain > best_gain) {
+                best = Some((index, consumed, gain));
+            }
+        }
+        if let Some((index, consumed, _)) = best {
+            amounts_in[index] = consumed.max(amounts_in[index]);
+        }
+    }
+
+    let amounts_out = pools
+        .iter()
+        .zip(&amounts_in)
+        .map(|(pool, &amount)| quote_output(pool, amount, zero_for_one))
+        .collect::<Result<Vec<_>, _>>()?;
+    let amount_out = total(&amounts_out);
+
+    Ok(Split {
+        amounts_in,
+        amounts_out,
+        amount_out,
+    })
+}
+
+// returns the sqrt price a pool has to move to for its marginal price net of fees to equal the given marginal price
+fn target_sqrt_price(
+    marginal_sqrt_price_x_96: U256,
+    fee_factor_x_96: U256,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    let target = if zero_for_one {
+        mul_div(marginal_sqrt_price_x_96, Q96, fee_factor_x_96)?
+    } else {
+        mul_div(marginal_sqrt_price_x_96, fee_factor_x_96, Q96)?
+    };
+
+    Ok(target.clamp(MIN_SQRT_RATIO + U256_1, MAX_SQRT_RATIO - U256_1))
+}
+
+// returns the marginal sqrt price net of fees of a pool at the given sqrt price
+fn marginal_sqrt_price(
+    sqrt_price_x_96: U256,
+    fee_factor_x_96: U256,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    if zero_for_one {
+        mul_div(sqrt_price_x_96, fee_factor_x_96, Q96)
+    } else {
+        mul_div(sqrt_price_x_96, Q96, fee_factor_x_96)
+    }
+}
+
+// returns the output amount of an exact input swap without a price limit
+fn quote_output(
+    pool: &PoolSnapshot,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    Ok(quote_swap(pool, amount_in, zero_for_one)?.1)
+}
+
+// returns the input amount an exact input swap without a price limit consumes, less than `amount_in` if the pool runs
+// out of liquidity, and its output amount
+fn quote_swap(
+    pool: &PoolSnapshot,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    if amount_in.is_zero() {
+        return Ok((U256::ZERO, U256::ZERO));
+    }
+
+    let limit = if zero_for_one {
+        MIN_SQRT_RATIO + U256_1
+    } else {
+        MAX_SQRT_RATIO - U256_1
+    };
+    let result = pool.quote(zero_for_one, I256::from_raw(amount_in), limit)?;
+
+    Ok(if zero_for_one {
+        (result.amount_0.into_raw(), (-result.amount_1).into_raw())
+    } else {
+        (result.amount_1.into_raw(), (-result.amount_0).into_raw())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{quote_output, quote_swap, split_amount_in};
+    use crate::{fee_tier::FeeTier, pool::PoolSnapshot, test_utils::PoolBuilder};
+
+    fn pool_at(tick_current: i32, fee_tier: FeeTier, liquidity: i128) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .fee_tier(fee_tier)
+            .full_range(liquidity)
+            .build()
+    }
+
+    #[test]
+    fn test_split_amount_in() {
+        let amount_in = U256::from(1e18 as u128);
+
+        for zero_for_one in [true, false] {
+            //identical pools receive the same share
+            let pools = vec![
+                pool_at(0, FeeTier::Medium, 1e20 as i128),
+                pool_at(0, FeeTier::Medium, 1e20 as i128),
+            ];
+            let split = split_amount_in(&pools, amount_in, zero_for_one).unwrap();
+            assert_eq!(split.amounts_in[0] + split.amounts_in[1], amount_in);
+            assert!(split.amounts_in[0].abs_diff(split.amounts_in[1]) <= U256::from(2));
+
+            //the split beats routing everything through a single pool
+            let single = quote_output(&pools[0], amount_in, zero_for_one).unwrap();
+            assert!(split.amount_out > single);
+
+            //deeper and cheaper pools receive a larger share
+            let pools = vec![
+                pool_at(0, FeeTier::High, 1e20 as i128),
+                pool_at(0, FeeTier::Low, 1e20 as i128),
+                pool_at(0, FeeTier::Low, 4e20 as i128),
+            ];
+            let split = split_amount_in(&pools, amount_in, zero_for_one).unwrap();
+            assert_eq!(
+                split.amounts_in.iter().fold(U256::ZERO, |acc, a| acc + a),
+                amount_in
+            );
+            assert!(split.amounts_in[0] < split.amounts_in[1]);
+            assert!(split.amounts_in[1] < split.amounts_in[2]);
+            assert_eq!(
+                split.amount_out,
+                split.amounts_out.iter().fold(U256::ZERO, |acc, a| acc + a)
+            );
+
+            //moving input between pools does not improve the output
+            for (from, to) in [(1, 2), (2, 1)] {
+                let mut amounts = split.amounts_in.clone();
+                amounts[from] -= U256::from(1000);
+                amounts[to] += U256::from(1000);
+                let out = pools
+                    .iter()
+                    .zip(&amounts)
+                    .map(|(pool, amount)| quote_output(pool, *amount, zero_for_one).unwrap())
+                    .fold(U256::ZERO, |acc, a| acc + a);
+                assert!(out <= split.amount_out);
+            }
+        }
+
+        //pools too far from the others receive nothing
+        let pools = vec![
+            pool_at(0, FeeTier::Low, 1e20 as i128),
+            pool_at(-5000, FeeTier::Low, 1e20 as i128),
+        ];
+        let split = split_amount_in(&pools, amount_in, true).unwrap();
+        assert_eq!(split.amounts_in, vec![amount_in, U256::ZERO]);
+
+        //an odd amount leaves a remainder after the search, it is routed in full
+        let pools = vec![
+            pool_at(0, FeeTier::Medium, 1e20 as i128),
+            pool_at(0, FeeTier::Medium, 1e20 as i128),
+        ];
+        let odd_amount_in = U256::from(1e18 as u128 + 7);
+        let split = split_amount_in(&pools, odd_amount_in, true).unwrap();
+        assert_eq!(split.amounts_in[0] + split.amounts_in[1], odd_amount_in);
+
+        //pools that can not absorb the amount before the price bound take what they can, the rest is not routed
+        let pools = vec![
+            pool_at(0, FeeTier::Medium, 1e6 as i128),
+            pool_at(0, FeeTier::Medium, 1e6 as i128),
+        ];
+        let huge_amount_in = U256::from(u128::MAX);
+        let split = split_amount_in(&pools, huge_amount_in, true).unwrap();
+        let routed = split.amounts_in[0] + split.amounts_in[1];
+        assert!(routed < huge_amount_in);
+        for (pool, amount) in pools.iter().zip(&split.amounts_in) {
+            let (consumed, _) = quote_swap(pool, *amount, true).unwrap();
+            assert_eq!(consumed, *amount);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    pool::{PoolSnapshot, SwapParams},
+    tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    U256_1,
+};
+
+//A front-run and back-run around a pending exact input swap, amounts are in the victim's input token unless noted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sandwich {
+    // the input amount of the front-run, swapped in the same direction as the victim
+    pub front_run_amount_in: U256,
+    // the output amount of the front-run in the victim's output token, which is the input of the back-run
+    pub front_run_amount_out: U256,
+    // the output amount the victim receives after the front-run, in the victim's output token
+    pub victim_amount_out: U256,
+    // the output amount of the back-run
+    pub back_run_amount_out: U256,
+    // the profit, back_run_amount_out - front_run_amount_in
+    pub profit: U256,
+}
+
+// returns the most profitable sandwich around `victim_swap` that still lets the victim receive at least
+// `victim_amount_out_minimum` without hitting its price limit, searching over exact quotes that account for tick
+// crossings and rounding. Returns None if there is no profitable sandwich or the victim swap is not an exact input swap
+pub fn optimal_sandwich(
+    pool: &PoolSnapshot,
+    victim_swap: &SwapParams,
+    victim_amount_out_minimum: U256,
+) -> Result<Option<Sandwich>, UniswapV3MathError> {
+    if victim_swap.amount_specified <= I256::ZERO {
+        return Ok(None);
+    }
+
+    // the largest front-run the victim tolerates, feasibility is monotonic in the front-run amount
+    let mut low = U256::ZERO;
+    let mut high = pool.get_amount_in_to_target_price(victim_swap.sqrt_price_limit_x_96)?;
+    if simulate(pool, victim_swap, victim_amount_out_minimum, low)?.is_none() {
+        return Ok(None);
+    }
+    while low < high {
+        let mid = low + (high - low + U256_1) / U256::from(2);
+        if simulate(pool, victim_swap, victim_amount_out_minimum, mid)?.is_some() {
+            low = mid;
+        } else {
+            high = mid - U256_1;
+        }
+    }
+
+    // the profit is concave in the front-run amount, so a ternary search converges to the optimum
+    let profit = |amount_in: U256| -> Result<Option<Sandwich>, UniswapV3MathError> {
+        Ok(
+            simulate(pool, victim_swap, victim_amount_out_minimum, amount_in)?
+                .filter(|sandwich| !sandwich.profit.is_zero()),
+        )
+    };
+    let profit_of = |sandwich: &Option<Sandwich>| sandwich.map(|s| s.profit).unwrap_or_default();
+
+    let mut low = U256::ZERO;
+    while high - low > U256::from(2) {
+        let third = (high - low) / U256::from(3);
+        let mid_low = low + third;
+        let mid_high = high - third;
+
+        if profit_of(&profit(mid_low)?) < profit_of(&profit(mid_high)?) {
+            low = mid_low;
+        } else {
+            high = mid_high;
+        }
+    }
+
+    let mut best: Option<Sandwich> = None;
+    let mut amount_in = low;
+    while amount_in <= high {
+        let candidate = profit(amount_in)?;
+        if profit_of(&candidate) > profit_of(&best) {
+            best = candidate;
+        }
+        amount_in += U256_1;
+    }
+
+    Ok(best)
+}
+
+// executes the front-run, the victim swap and the back-run against a copy of the pool. Returns None if the victim swap
+// would fail or receive less than the minimum, the profit saturates at zero
+fn simulate(
+    pool: &PoolSnapshot,
+    victim_swap: &SwapParams,
+    victim_amount_out_minimum: U256,
+    front_run_amount_in: U256,
+) -> Result<Option<Sandwich>, UniswapV3MathError> {
+    let zero_for_one = victim_swap.zero_for_one;
+    let (limit, reverse_limit) = if zero_for_one {
+        (MIN_SQRT_RATIO + U256_1, MAX_SQRT_RATIO - U256_1)
+    } else {
+        (MAX_SQRT_RATIO - U256_1, MIN_SQRT_RATIO + U256_1)
+    };
+    let amount_out = |amount_0: I256, amount_1: I256, zero_for_one: bool| {
+        if zero_for_one {
+            amount_1.unsigned_abs()
+        } else {
+            amount_0.unsigned_abs()
+        }
+    };
+
+    let mut pool = pool.clone();
+
+    let front_run_amount_out = if front_run_amount_in.is_zero() {
+        U256::ZERO
+    } else {
+        let front_run = pool.swap(zero_for_one, I256::from_raw(front_run_amount_in), limit)?;
+        amount_out(front_run.amount_0, front_run.amount_1, zero_for_one)
+    };
+
+    // the victim swap reverts if the front-run moved the price to or past its limit
+    let victim = match pool.swap(
+        zero_for_one,
+        victim_swap.amount_specified,
+        victim_swap.sqrt_price_limit_x_96,
+    ) {
+        Ok(victim) => victim,
+        Err(UniswapV3MathError::SPL) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let victim_amount_out = amount_out(victim.amount_0, victim.amount_1, zero_for_one);
+    if victim_amount_out < victim_amount_out_minimum {
+        return Ok(None);
+    }
+
+    let back_run_amount_out = if front_run_amount_out.is_zero() {
+        U256::ZERO
+    } else {
+        let back_run = pool.swap(
+            !zero_for_one,
+            I256::from_raw(front_run_amount_out),
+            reverse_limit,
+        )?;
+        amount_out(back_run.amount_0, back_run.amount_1, !zero_for_one)
+    };
+
+    Ok(Some(Sandwich {
+        front_run_amount_in,
+        front_run_amount_out,
+        victim_amount_out,
+        back_run_amount_out,
+        profit: back_run_amount_out.saturating_sub(front_run_amount_in),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::optimal_sandwich;
+    use crate::{
+        fee_tier::FeeTier,
+        pool::{PoolSnapshot, SwapParams, ZeroLiquidity},
+        test_utils::PoolBuilder,
+        tick_math::{get_sqrt_ratio_at_tick, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    fn pool_at(tick_current: i32, ranges: &[(i32, i32, i128)]) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .fee_tier(FeeTier::Low)
+            .positions(ranges)
+            .build()
+    }
+
+    #[test]
+    fn test_optimal_sandwich() {
+        let pool = pool_at(0, &[(-887270, 887270, 1e21 as i128)]);
+        let victim_swap = SwapParams {
+            zero_for_one: true,
+            amount_specified: I256::from_raw(U256::from(1e19 as u128)),
+            sqrt_price_limit_x_96: MIN_SQRT_RATIO + U256_1,
+            zero_liquidity: ZeroLiquidity::Skip,
+        };
+        let quote = pool
+            .quote(
+                true,
+                victim_swap.amount_specified,
+                victim_swap.sqrt_price_limit_x_96,
+            )
+            .unwrap();
+        let expected_out = quote.amount_1.unsigned_abs();
+
+        //the victim accepts 1% slippage
+        let minimum = expected_out * U256::from(99) / U256::from(100);
+        let sandwich = optimal_sandwich(&pool, &victim_swap, minimum)
+            .unwrap()
+            .unwrap();
+        assert!(sandwich.victim_amount_out >= minimum);
+        assert_eq!(
+            sandwich.profit,
+            sandwich.back_run_amount_out - sandwich.front_run_amount_in
+        );
+
+        //the slippage limit is binding, front-running more makes the victim swap fail
+        let mut front_run_pool = pool.clone();
+        front_run_pool
+            .swap(
+                true,
+                I256::from_raw(sandwich.front_run_amount_in + U256::from(1e15 as u128)),
+                MIN_SQRT_RATIO + U256_1,
+            )
+            .unwrap();
+        let victim = front_run_pool
+            .quote(
+                true,
+                victim_swap.amount_specified,
+                victim_swap.sqrt_price_limit_x_96,
+            )
+            .unwrap();
+        assert!(victim.amount_1.unsigned_abs() < minimum);
+
+        //a looser limit allows a more profitable sandwich
+        let looser = optimal_sandwich(&pool, &victim_swap, expected_out / U256::from(2))
+            .unwrap()
+            .unwrap();
+        assert!(looser.profit > sandwich.profit);
+
+        //no sandwich without slippage tolerance, or if the victim can never receive the minimum
+        assert_eq!(
+            optimal_sandwich(&pool, &victim_swap, expected_out).unwrap(),
+            None
+        );
+        assert_eq!(
+            optimal_sandwich(&pool, &victim_swap, expected_out + U256_1).unwrap(),
+            None
+        );
+
+        //the victim's price limit also bounds the front-run
+        let limited = SwapParams {
+            sqrt_price_limit_x_96: get_sqrt_ratio_at_tick(-300).unwrap(),
+            ..victim_swap
+        };
+        let sandwich = optimal_sandwich(&pool, &limited, U256::ZERO)
+            .unwrap()
+            .unwrap();
+        assert!(sandwich.victim_amount_out > U256::ZERO);
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use alloy_primitives::{Address, B256};
+use arc_swap::ArcSwap;
+
+use crate::{
+    backtest::Event,
+    error::UniswapV3MathError,
+    pool::{PersistentPoolSnapshot, PoolSnapshot, SwapParams, SwapResult},
+};
+
+// the state of every pool is published as an immutable `PoolState` behind an Arc, in a persistent map of all pools that
+// is swapped atomically. Quoting loads the map and clones the Arc of its pool without taking any lock, and runs against
+// the state, so quotes never wait for an update to be applied and always see the state of a single block. Updates are
+// serialized by a writer lock, each one forks the published state, which is O(1) for the persistent snapshot, applies
+// its events and publishes the fork in a copy of the map, which shares all but O(log n) nodes with the previous one.
+// Nothing here depends on an async runtime, the futures only need to be polled
+
+//A pool as of a block, published by `QuoteService`
+#[derive(Clone, Debug)]
+pub struct PoolState {
+    pub number: u64,
+    pub hash: B256,
+    pub pool: PersistentPoolSnapshot,
+}
+
+//The events of a pool in a block, e.g. decoded from the logs of a block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolBlock {
+    pub pool: Address,
+    pub number: u64,
+    pub hash: B256,
+    pub events: Vec<Event>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuoteRequest {
+    pub pool: Address,
+    pub params: SwapParams,
+}
+
+//A quote and the block of the state it was computed against
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuoteResponse {
+    pub number: u64,
+    pub hash: B256,
+    pub result: SwapResult,
+}
+
+//Serves quotes against synced pools while applying their updates, see the comment above `PoolState`. Shared between
+//tasks or threads by reference or in an Arc
+#[derive(Debug, Default)]
+pub struct QuoteService {
+    states: ArcSwap<im::HashMap<Address, Arc<PoolState>>>,
+    // held while an update is applied, so updates are applied one at a time and none is lost
+    writer: Mutex<()>,
+}
+
+impl QuoteService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // starts serving quotes for the pool at `address` as of the block `number`, replacing any previous state, e.g.
+    // after fetching a new snapshot
+    pub fn insert_pool(&self, address: Address, pool: PoolSnapshot, number: u64, hash: B256) {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        self.publish(
+            address,
+            PoolState {
+                number,
+                hash,
+                pool: pool.into(),
+            },
+        );
+    }
+
+    pub fn remove_pool(&self, address: Address) -> Option<Arc<PoolState>> {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut states = im::HashMap::clone(&self.states.load());
+        let state = states.remove(&address);
+        self.states.store(Arc::new(states));
+        state
+    }
+
+    // returns the published state of the pool at `address`, several quotes against it see the same block
+    pub fn state(&self, address: Address) -> Option<Arc<PoolState>> {
+        self.states.load().get(&address).cloned()
+    }
+
+    // quotes the request against the latest published state of its pool. The quote is computed inline on the task that
+    // polls the future, which does not yield, so on an async runtime quotes of swaps that cross many ticks should be
+    // moved to a blocking thread, e.g. with `spawn_blocking` and an `Arc<QuoteService>`
+    pub async fn quote(&self, request: &QuoteRequest) -> Result<QuoteResponse, UniswapV3MathError> {
+        let state = self
+            .state(request.pool)
+            .ok_or(UniswapV3MathError::PoolNotFound)?;
+
+        Ok(QuoteResponse {
+            number: state.number,
+            hash: state.hash,
+            result: state.pool.quote_with(&request.params)?,
+        })
+    }
+
+    // applies the events of a block to its pool and publishes the new state. The number must be above the block of the
+    // published state, and if an event fails the published state is left as it was
+    pub fn apply_block(&self, block: &PoolBlock) -> Result<(), UniswapV3MathError> {
+        let _writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        let state = self
+            .state(block.pool)
+            .ok_or(UniswapV3MathError::PoolNotFound)?;
+        if block.number <= state.number {
+            return Err(UniswapV3MathError::BlockOutOfOrder);
+        }
+
+        let mut pool = state.pool.clone();
+        for event in &block.events {
+            event.apply_to(&mut pool)?;
+        }
+        self.publish(
+            block.pool,
+            PoolState {
+                number: block.number,
+                hash: block.hash,
+                pool,
+            },
+        );
+
+        Ok(())
+    }
+
+    // applies the blocks returned by `next` until it returns None, e.g. `|| receiver.recv()` for the receiver of any
+    // async channel fed from a log subscription. Stops at the first block that fails to apply and returns its error,
+    // the pool should then be fetched again and inserted
+    pub async fn run<F, Fut>(&self, mut next: F) -> Result<(), UniswapV3MathError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Option<PoolBlock>>,
+    {
+        while let Some(block) = next().await {
+            self.apply_block(&block)?;
+        }
+
+        Ok(())
+    }
+
+    // must be called with the writer lock held, or concurrent updates overwrite each other
+    fn publish(&self, address: Address, state: PoolState) {
+        let mut states = im::HashMap::clone(&self.states.load());
+        states.insert(address, Arc::new(state));
+        self.states.store(Arc::new(states));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::{mpsc, Arc},
+        task::{Context, Poll, Wake, Waker},
+        thread,
+    };
+
+    use alloy_primitives::{address, Address, B256, U256};
+
+    use super::{PoolBlock, QuoteRequest, QuoteService};
+    use crate::{
+        backtest::Event,
+        error::UniswapV3MathError,
+        pool::{PoolSnapshot, SwapParams},
+        test_utils::swap,
+        test_utils::PoolBuilder,
+    };
+
+    const POOL: Address = address!("0000000000000000000000000000000000000001");
+
+    // polls a future that never waits to completion, the service does not depend on a runtime
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct NoopWaker;
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut context = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    fn pool() -> PoolSnapshot {
+        PoolBuilder::at_tick(0)
+            .position(-6000, 6000, 1e21 as i128)
+            .build()
+    }
+
+    fn block(number: u64, events: Vec<Event>) -> PoolBlock {
+        PoolBlock {
+            pool: POOL,
+            number,
+            hash: B256::with_last_byte(number as u8),
+            events,
+        }
+    }
+
+    #[test]
+    fn test_quote_service() {
+        let service = QuoteService::new();
+        let request = QuoteRequest {
+            pool: POOL,
+            params: SwapParams::builder()
+                .zero_for_one(false)
+                .amount_in(U256::from(1e15 as u128))
+                .build()
+                .unwrap(),
+        };
+        assert!(matches!(
+            block_on(service.quote(&request)).unwrap_err(),
+            UniswapV3MathError::PoolNotFound
+        ));
+
+        //quotes against the published state
+        let mut expected = pool();
+        service.insert_pool(POOL, expected.clone(), 1, B256::with_last_byte(1));
+        let response = block_on(service.quote(&request)).unwrap();
+        assert_eq!(
+            (response.number, response.hash),
+            (1, B256::with_last_byte(1))
+        );
+        assert_eq!(
+            response.result,
+            expected.quote_with(&request.params).unwrap()
+        );
+
+        //applies the blocks of a stream in order
+        let mint = Event::Mint {
+            tick_lower: -60,
+            tick_upper: 60,
+            liquidity: 1e20 as u128,
+        };
+        let (sender, receiver) = mpsc::channel();
+        sender
+            .send(block(2, vec![swap(true, 1e18 as u128)]))
+            .unwrap();
+        sender
+            .send(block(3, vec![mint, swap(false, 1e18 as u128)]))
+            .unwrap();
+        drop(sender);
+        block_on(service.run(|| std::future::ready(receiver.recv().ok()))).unwrap();
+
+        for event in [swap(true, 1e18 as u128), mint, swap(false, 1e18 as u128)] {
+            match event {
+                Event::Swap(params) => {
+                    expected.swap_with(&params).unwrap();
+                }
+                _ => {
+                    expected.modify_position(-60, 60, 1e20 as i128).unwrap();
+                }
+            }
+        }
+        let state = service.state(POOL).unwrap();
+        assert_eq!((state.number, state.hash), (3, B256::with_last_byte(3)));
+        assert_eq!(PoolSnapshot::from(state.pool.clone()), expected);
+
+        //fails for a block that is not above the published one or does not apply, leaving the state as it was
+        assert!(matches!(
+            service.apply_block(&block(3, vec![])).unwrap_err(),
+            UniswapV3MathError::BlockOutOfOrder
+        ));
+        let burn = Event::Burn {
+            tick_lower: -60,
+            tick_upper: 60,
+            liquidity: 1e21 as u128,
+        };
+        assert!(service
+            .apply_block(&block(4, vec![swap(true, 1e18 as u128), burn]))
+            .is_err());
+        assert_eq!(service.state(POOL).unwrap().number, 3);
+
+        assert!(service.remove_pool(POOL).is_some());
+        assert!(service.state(POOL).is_none());
+    }
+
+    #[test]
+    fn test_concurrent_quotes_and_updates() {
+        //the state after each block, alternating swaps in both directions
+        let mut pools = vec![pool()];
+        for number in 1..50 {
+            let mut next = pools.last().unwrap().clone();
+            if let Event::Swap(params) = swap(number % 2 == 0, 1e18 as u128) {
+                next.swap_with(&params).unwrap();
+            }
+            pools.push(next);
+        }
+
+        let service = QuoteService::new();
+        service.insert_pool(POOL, pools[0].clone(), 0, B256::ZERO);
+        let params = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e17 as u128))
+            .build()
+            .unwrap();
+
+        //every quote is the quote against the state of the block it reports
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut number = 0;
+                    while number < 49 {
+                        let request = QuoteRequest { pool: POOL, params };
+                        let response = block_on(service.quote(&request)).unwrap();
+                        assert!(response.number >= number);
+                        number = response.number;
+                        assert_eq!(
+                            response.result,
+                            pools[number as usize].quote_with(&params).unwrap()
+                        );
+                    }
+                });
+            }
+            for number in 1..50 {
+                service
+                    .apply_block(&block(number, vec![swap(number % 2 == 0, 1e18 as u128)]))
+                    .unwrap();
+            }
+        });
+    }
+}
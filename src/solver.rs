@@ -0,0 +1,186 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    pool::PoolSnapshot,
+    storage::{InitializedTicks, TickStorage},
+    tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK},
+    variant::PoolVariant,
+    U256_1,
+};
+
+// returns the smallest exact input that leaves the pool at `target_tick` after the swap, crossing any initialized ticks
+// in between, or just past it if a single unit of input moves the price over the whole tick. Swapping token0 for token1
+// moves the tick down, so the target must be at or below the current tick, and at or above it otherwise. Returns 0 if
+// the pool is already at the target tick
+pub fn amount_for_target_tick<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    target_tick: i32,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    if !(MIN_TICK..MAX_TICK).contains(&target_tick) {
+        return Err(UniswapV3MathError::T);
+    }
+    let tick = pool.slot0.tick;
+    if target_tick == tick {
+        return Ok(U256::ZERO);
+    }
+    if (zero_for_one && target_tick > tick) || (!zero_for_one && target_tick < tick) {
+        return Err(UniswapV3MathError::SPL);
+    }
+
+    // the input to the boundary of the target tick the price enters it through. Moving up, the tick is entered at its
+    // lower price, which is part of it. Moving down, the tick is entered at the lower price of the tick above, which
+    // only counts as the target tick if the swap stops there on a step boundary, so a few more units may be needed
+    let sqrt_price_boundary_x_96 = if zero_for_one {
+        get_sqrt_ratio_at_tick(target_tick + 1)?
+    } else {
+        get_sqrt_ratio_at_tick(target_tick)?
+    };
+    let guess = pool.get_amount_in_to_target_price(sqrt_price_boundary_x_96)?;
+
+    let sqrt_price_limit_x_96 = if zero_for_one {
+        MIN_SQRT_RATIO + U256_1
+    } else {
+        MAX_SQRT_RATIO - U256_1
+    };
+    // whether swapping `amount_in` reaches the target tick, the tick moves monotonically with the input
+    let reaches = |amount_in: U256| -> Result<bool, UniswapV3MathError> {
+        if amount_in.is_zero() {
+            return Ok(false);
+        }
+        let amount_specified =
+            I256::try_from(amount_in).map_err(|_| UniswapV3MathError::Overflow)?;
+        let result = pool.quote(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+        Ok(if zero_for_one {
+            result.tick <= target_tick
+        } else {
+            result.tick >= target_tick
+        })
+    };
+
+    // bracket the smallest input that reaches the tick around the guess, widening the bracket exponentially
+    let (mut low, mut high) = if reaches(guess)? {
+        let mut step = U256_1;
+        let mut low = guess.saturating_sub(step);
+        while reaches(low)? {
+            step <<= 1;
+            low = guess.saturating_sub(step);
+        }
+        (low, guess)
+    } else {
+        let mut step = U256_1;
+        let mut high = guess
+            .checked_add(step)
+            .ok_or(UniswapV3MathError::Overflow)?;
+        while !reaches(high)? {
+            step <<= 1;
+            high = guess
+                .checked_add(step)
+                .ok_or(UniswapV3MathError::Overflow)?;
+        }
+        (guess, high)
+    };
+
+    // the input does not reach the tick at `low` and reaches it at `high`
+    while high - low > U256_1 {
+        let mid = low + (high - low) / U256::from(2);
+        if reaches(mid)? {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    Ok(high)
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::amount_for_target_tick;
+    use crate::{
+        error::UniswapV3MathError,
+        pool::PoolSnapshot,
+        test_utils::PoolBuilder,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+        U256_1,
+    };
+
+    fn pool() -> PoolSnapshot {
+        PoolBuilder::at_price(
+            (get_sqrt_ratio_at_tick(10).unwrap() + get_sqrt_ratio_at_tick(11).unwrap())
+                / U256::from(2),
+        )
+        .full_range(1e18 as i128)
+        .position(-1200, 1200, 5e18 as i128)
+        .position(-600, -120, 2e18 as i128)
+        .position(120, 600, 2e18 as i128)
+        .build()
+    }
+
+    fn tick_after(pool: &PoolSnapshot, zero_for_one: bool, amount_in: U256) -> i32 {
+        let limit = if zero_for_one {
+            MIN_SQRT_RATIO + U256_1
+        } else {
+            MAX_SQRT_RATIO - U256_1
+        };
+        pool.quote(zero_for_one, I256::from_raw(amount_in), limit)
+            .unwrap()
+            .tick
+    }
+
+    #[test]
+    fn test_amount_for_target_tick() {
+        let pool = pool();
+
+        //lands on the target across initialized ticks, and one unit less falls short. The targets include initialized
+        //ticks, the ticks just past them and word boundaries
+        for (zero_for_one, targets) in [
+            (
+                true,
+                [9, 0, -1, -120, -121, -599, -600, -601, -15360, -20000],
+            ),
+            (false, [11, 60, 119, 120, 121, 600, 601, 1199, 15360, 20000]),
+        ] {
+            for target in targets {
+                let amount_in = amount_for_target_tick(&pool, target, zero_for_one).unwrap();
+                assert_eq!(tick_after(&pool, zero_for_one, amount_in), target);
+
+                let tick = tick_after(&pool, zero_for_one, amount_in - U256_1);
+                if zero_for_one {
+                    assert!(tick > target);
+                } else {
+                    assert!(tick < target);
+                }
+            }
+        }
+
+        //moving up the target tick is entered at its price
+        assert_eq!(
+            amount_for_target_tick(&pool, 600, false).unwrap(),
+            pool.get_amount_in_to_target_price(get_sqrt_ratio_at_tick(600).unwrap())
+                .unwrap()
+        );
+
+        //nothing to swap at the current tick
+        assert_eq!(amount_for_target_tick(&pool, 10, true).unwrap(), U256::ZERO);
+
+        //fails for a target on the wrong side or out of bounds
+        assert!(matches!(
+            amount_for_target_tick(&pool, 11, true).unwrap_err(),
+            UniswapV3MathError::SPL
+        ));
+        assert!(matches!(
+            amount_for_target_tick(&pool, 9, false).unwrap_err(),
+            UniswapV3MathError::SPL
+        ));
+        assert!(amount_for_target_tick(&pool, 887272, false).is_err());
+    }
+}
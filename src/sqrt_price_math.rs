@@ -2,14 +2,22 @@ use alloy_primitives::{I256, U256};
 
 use crate::{
     error::UniswapV3MathError,
-    full_math::{mul_div, mul_div_rounding_up},
+    fee_tier::FEE_DENOMINATOR,
+    full_math::{mul_div, mul_div_rounding_up, sqrt},
+    uint160::Uint160,
     unsafe_math::div_rounding_up,
 };
 
-pub const MAX_U160: U256 =
-    U256::from_limbs([18446744073709551615, 18446744073709551615, 4294967295, 0]);
-pub const Q96: U256 = U256::from_limbs([0, 4294967296, 0, 0]);
-pub const FIXED_POINT_96_RESOLUTION: U256 = U256::from_limbs([96, 0, 0, 0]);
+pub use crate::constants::{FIXED_POINT_96_RESOLUTION, MAX_U160, Q192, Q96};
+
+// returns the sqrt ratio as a Q64.96 corresponding to a given ratio of amount1 and amount0, sqrt(amount1 / amount0) * 2**96
+pub fn encode_sqrt_ratio_x96(amount_1: U256, amount_0: U256) -> Result<U256, UniswapV3MathError> {
+    if amount_0.is_zero() {
+        return Err(UniswapV3MathError::DenominatorIsZero);
+    }
+
+    Ok(sqrt(mul_div(amount_1, Q192, amount_0)?))
+}
 
 // returns (sqrtQX96)
 pub fn get_next_sqrt_price_from_input(
@@ -51,6 +59,40 @@ pub fn get_next_sqrt_price_from_output(
     }
 }
 
+// same as `get_next_sqrt_price_from_input`, but fails with `SqrtPriceOverflow` for a sqrt price that does not fit in
+// 160 bits, which the contract can not be called with. Never panics, any input returns a price or an error, e.g. for
+// fuzzed or adversarial inputs
+pub fn checked_get_next_sqrt_price_from_input(
+    sqrt_price: U256,
+    liquidity: u128,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    check_sqrt_price(sqrt_price)?;
+    get_next_sqrt_price_from_input(sqrt_price, liquidity, amount_in, zero_for_one)
+}
+
+// same as `get_next_sqrt_price_from_output`, see `checked_get_next_sqrt_price_from_input`
+pub fn checked_get_next_sqrt_price_from_output(
+    sqrt_price: U256,
+    liquidity: u128,
+    amount_out: U256,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    check_sqrt_price(sqrt_price)?;
+    get_next_sqrt_price_from_output(sqrt_price, liquidity, amount_out, zero_for_one)
+}
+
+fn check_sqrt_price(sqrt_price: U256) -> Result<(), UniswapV3MathError> {
+    if sqrt_price.is_zero() {
+        Err(UniswapV3MathError::SqrtPriceIsZero)
+    } else if sqrt_price > MAX_U160 {
+        Err(UniswapV3MathError::SqrtPriceOverflow)
+    } else {
+        Ok(())
+    }
+}
+
 // returns (uint160 sqrtQX96)
 pub fn get_next_sqrt_price_from_amount_0_rounding_up(
     sqrt_price_x_96: U256,
@@ -71,20 +113,33 @@ pub fn get_next_sqrt_price_from_amount_0_rounding_up(
             let denominator = numerator_1.wrapping_add(product);
 
             if denominator >= numerator_1 {
-                return mul_div_rounding_up(numerator_1, sqrt_price_x_96, denominator);
+                // always fits since the denominator is at least numerator_1
+                return Ok(Uint160::truncate(mul_div_rounding_up(
+                    numerator_1,
+                    sqrt_price_x_96,
+                    denominator,
+                )?)
+                .into());
             }
         }
 
-        Ok(div_rounding_up(
-            numerator_1,
-            (numerator_1.wrapping_div(sqrt_price_x_96)).wrapping_add(amount),
-        ))
+        // (numerator1 / sqrtPX96).add(amount) reverts on overflow, which also keeps the denominator from being 0
+        let denominator = (numerator_1 / sqrt_price_x_96)
+            .checked_add(amount)
+            .ok_or(UniswapV3MathError::Overflow)?;
+        Ok(Uint160::truncate(div_rounding_up(numerator_1, denominator)).into())
     } else {
         let product = amount.wrapping_mul(sqrt_price_x_96);
         if product.wrapping_div(amount) == sqrt_price_x_96 && numerator_1 > product {
             let denominator = numerator_1.wrapping_sub(product);
 
-            mul_div_rounding_up(numerator_1, sqrt_price_x_96, denominator)
+            // mulDivRoundingUp(...).toUint160(), the cast reverts if the price does not fit
+            Ok(Uint160::new(mul_div_rounding_up(
+                numerator_1,
+                sqrt_price_x_96,
+                denominator,
+            )?)?
+            .into())
         } else {
             Err(UniswapV3MathError::ProductDivAmount)
         }
@@ -98,6 +153,9 @@ pub fn get_next_sqrt_price_from_amount_1_rounding_down(
     amount: U256,
     add: bool,
 ) -> Result<U256, UniswapV3MathError> {
+    if liquidity == 0 {
+        return Err(UniswapV3MathError::LiquidityIsZero);
+    }
     let liquidity = U256::from(liquidity);
 
     if add {
@@ -107,13 +165,12 @@ pub fn get_next_sqrt_price_from_amount_1_rounding_down(
             mul_div(amount, Q96, liquidity)?
         };
 
-        let next_sqrt_price = sqrt_price_x_96 + quotient;
-
-        if next_sqrt_price > MAX_U160 {
-            Err(UniswapV3MathError::SafeCastToU160Overflow)
-        } else {
-            Ok(next_sqrt_price)
-        }
+        // uint256(sqrtPX96).add(quotient).toUint160(), the addition reverts on overflow and the cast if the price
+        // does not fit
+        let next_sqrt_price = sqrt_price_x_96
+            .checked_add(quotient)
+            .ok_or(UniswapV3MathError::Overflow)?;
+        Ok(Uint160::new(next_sqrt_price)?.into())
     } else {
         let quotient = if amount <= MAX_U160 {
             div_rounding_up(amount << FIXED_POINT_96_RESOLUTION, liquidity)
@@ -126,106 +183,273 @@ pub fn get_next_sqrt_price_from_amount_1_rounding_down(
             return Err(UniswapV3MathError::SqrtPriceIsLteQuotient);
         }
 
-        Ok(sqrt_price_x_96 - quotient)
+        Ok(Uint160::truncate(sqrt_price_x_96 - quotient).into())
     }
 }
 
-// returns (uint256 amount0)
-pub fn _get_amount_0_delta(
-    mut sqrt_ratio_a_x_96: U256,
-    mut sqrt_ratio_b_x_96: U256,
+//The direction to round an amount in, amounts owed to the pool round up and amounts paid by the pool round down
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+impl From<bool> for Rounding {
+    fn from(round_up: bool) -> Self {
+        if round_up {
+            Self::Up
+        } else {
+            Self::Down
+        }
+    }
+}
+
+// returns the amount of token0 between two sqrt prices for `liquidity`, i.e. L * (upper - lower) / (upper * lower).
+// Fails if the bounds are out of order instead of sorting them, a swapped pair of arguments is a bug at the call site
+pub fn amount_0_delta(
+    sqrt_lower_x_96: U256,
+    sqrt_upper_x_96: U256,
     liquidity: u128,
-    round_up: bool,
+    rounding: Rounding,
 ) -> Result<U256, UniswapV3MathError> {
-    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
-        (sqrt_ratio_a_x_96, sqrt_ratio_b_x_96) = (sqrt_ratio_b_x_96, sqrt_ratio_a_x_96)
-    };
+    if sqrt_lower_x_96 > sqrt_upper_x_96 {
+        return Err(UniswapV3MathError::SqrtPricesOutOfOrder);
+    }
+    if sqrt_lower_x_96.is_zero() {
+        return Err(UniswapV3MathError::SqrtPriceIsZero);
+    }
 
     let numerator_1 = U256::from(liquidity) << 96;
-    let numerator_2 = sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96;
+    let numerator_2 = sqrt_upper_x_96 - sqrt_lower_x_96;
 
-    if sqrt_ratio_a_x_96.is_zero() {
-        return Err(UniswapV3MathError::SqrtPriceIsZero);
+    match rounding {
+        Rounding::Up => {
+            let numerator_partial = mul_div_rounding_up(numerator_1, numerator_2, sqrt_upper_x_96)?;
+            Ok(div_rounding_up(numerator_partial, sqrt_lower_x_96))
+        }
+        Rounding::Down => Ok(mul_div(numerator_1, numerator_2, sqrt_upper_x_96)? / sqrt_lower_x_96),
     }
+}
 
-    if round_up {
-        let numerator_partial = mul_div_rounding_up(numerator_1, numerator_2, sqrt_ratio_b_x_96)?;
-        Ok(div_rounding_up(numerator_partial, sqrt_ratio_a_x_96))
-    } else {
-        Ok(mul_div(numerator_1, numerator_2, sqrt_ratio_b_x_96)? / sqrt_ratio_a_x_96)
+// returns the amount of token1 between two sqrt prices for `liquidity`, i.e. L * (upper - lower). Fails if the bounds
+// are out of order
+pub fn amount_1_delta(
+    sqrt_lower_x_96: U256,
+    sqrt_upper_x_96: U256,
+    liquidity: u128,
+    rounding: Rounding,
+) -> Result<U256, UniswapV3MathError> {
+    if sqrt_lower_x_96 > sqrt_upper_x_96 {
+        return Err(UniswapV3MathError::SqrtPricesOutOfOrder);
+    }
+
+    let numerator = sqrt_upper_x_96 - sqrt_lower_x_96;
+    match rounding {
+        Rounding::Up => mul_div_rounding_up(U256::from(liquidity), numerator, Q96),
+        Rounding::Down => mul_div(U256::from(liquidity), numerator, Q96),
     }
 }
 
-// returns (uint256 amount1)
+// returns (uint256 amount0), mirrors SqrtPriceMath.getAmount0Delta which accepts the sqrt prices in either order and
+// sorts them, see `amount_0_delta` for a version that validates the order instead
+pub fn _get_amount_0_delta(
+    sqrt_ratio_a_x_96: U256,
+    sqrt_ratio_b_x_96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<U256, UniswapV3MathError> {
+    amount_0_delta(
+        sqrt_ratio_a_x_96.min(sqrt_ratio_b_x_96),
+        sqrt_ratio_a_x_96.max(sqrt_ratio_b_x_96),
+        liquidity,
+        round_up.into(),
+    )
+}
+
+// returns (uint256 amount1), mirrors SqrtPriceMath.getAmount1Delta which accepts the sqrt prices in either order and
+// sorts them, see `amount_1_delta` for a version that validates the order instead
 pub fn _get_amount_1_delta(
-    mut sqrt_ratio_a_x_96: U256,
-    mut sqrt_ratio_b_x_96: U256,
+    sqrt_ratio_a_x_96: U256,
+    sqrt_ratio_b_x_96: U256,
     liquidity: u128,
     round_up: bool,
 ) -> Result<U256, UniswapV3MathError> {
-    if sqrt_ratio_a_x_96 > sqrt_ratio_b_x_96 {
-        (sqrt_ratio_a_x_96, sqrt_ratio_b_x_96) = (sqrt_ratio_b_x_96, sqrt_ratio_a_x_96)
-    };
+    amount_1_delta(
+        sqrt_ratio_a_x_96.min(sqrt_ratio_b_x_96),
+        sqrt_ratio_a_x_96.max(sqrt_ratio_b_x_96),
+        liquidity,
+        round_up.into(),
+    )
+}
 
-    let denominator = U256::from_limbs([0, 4294967296, 0, 0]);
+// same as `_get_amount_0_delta`, but fails with `SqrtPriceOverflow` if a sqrt price does not fit in 160 bits. Never
+// panics, any input returns an amount or an error
+pub fn checked_get_amount_0_delta(
+    sqrt_ratio_a_x_96: U256,
+    sqrt_ratio_b_x_96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<U256, UniswapV3MathError> {
+    check_sqrt_price(sqrt_ratio_a_x_96)?;
+    check_sqrt_price(sqrt_ratio_b_x_96)?;
+    _get_amount_0_delta(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, liquidity, round_up)
+}
 
-    if round_up {
-        mul_div_rounding_up(
-            U256::from(liquidity),
-            sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96,
-            denominator,
-        )
-    } else {
-        mul_div(
-            U256::from(liquidity),
-            sqrt_ratio_b_x_96 - sqrt_ratio_a_x_96,
-            denominator,
-        )
+// same as `_get_amount_1_delta`, see `checked_get_amount_0_delta`. A sqrt price of 0 is valid here like in the
+// contract, the amount of token1 does not divide by the price
+pub fn checked_get_amount_1_delta(
+    sqrt_ratio_a_x_96: U256,
+    sqrt_ratio_b_x_96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<U256, UniswapV3MathError> {
+    if sqrt_ratio_a_x_96 > MAX_U160 || sqrt_ratio_b_x_96 > MAX_U160 {
+        return Err(UniswapV3MathError::SqrtPriceOverflow);
     }
+    _get_amount_1_delta(sqrt_ratio_a_x_96, sqrt_ratio_b_x_96, liquidity, round_up)
 }
 
+// returns the input amount, including the swap fee, required to move the price from `sqrt_price_current_x_96` to
+// `sqrt_price_target_x_96` within a single tick range of constant liquidity
+pub fn get_amount_in_to_target_price(
+    sqrt_price_current_x_96: U256,
+    sqrt_price_target_x_96: U256,
+    liquidity: u128,
+    fee_pips: u32,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    if fee_pips >= FEE_DENOMINATOR {
+        return Err(UniswapV3MathError::InvalidFee);
+    }
+    if sqrt_price_target_x_96 == sqrt_price_current_x_96 {
+        return Ok(U256::ZERO);
+    }
+    if zero_for_one != (sqrt_price_target_x_96 < sqrt_price_current_x_96) {
+        return Err(UniswapV3MathError::SPL);
+    }
+
+    let amount_in = if zero_for_one {
+        _get_amount_0_delta(
+            sqrt_price_target_x_96,
+            sqrt_price_current_x_96,
+            liquidity,
+            true,
+        )?
+    } else {
+        _get_amount_1_delta(
+            sqrt_price_current_x_96,
+            sqrt_price_target_x_96,
+            liquidity,
+            true,
+        )?
+    };
+
+    // the smallest amount that still covers amount_in after the fee is taken, mirrors the exact input path of
+    // compute_swap_step
+    mul_div_rounding_up(
+        amount_in,
+        U256::from(FEE_DENOMINATOR),
+        U256::from(FEE_DENOMINATOR - fee_pips),
+    )
+}
+
+// returns the signed token0 delta for a signed liquidity delta, mirrors SqrtPriceMath.getAmount0Delta(int128). Amounts
+// owed to the pool round up and amounts paid out round down. The magnitude of the liquidity is widened to u128 without
+// overflow, so i128::MIN is handled, use `_get_amount_0_delta` or `amount_0_delta` for u128 liquidity above i128::MAX
 pub fn get_amount_0_delta(
     sqrt_ratio_a_x_96: U256,
     sqrt_ratio_b_x_96: U256,
     liquidity: i128,
 ) -> Result<I256, UniswapV3MathError> {
-    if liquidity < 0 {
-        Ok(-I256::from_raw(_get_amount_0_delta(
-            sqrt_ratio_a_x_96,
-            sqrt_ratio_b_x_96,
-            -liquidity as u128,
-            false,
-        )?))
-    } else {
-        Ok(I256::from_raw(_get_amount_0_delta(
+    signed_amount(
+        _get_amount_0_delta(
             sqrt_ratio_a_x_96,
             sqrt_ratio_b_x_96,
-            liquidity as u128,
-            true,
-        )?))
-    }
+            liquidity.unsigned_abs(),
+            liquidity >= 0,
+        )?,
+        liquidity,
+    )
 }
 
+// returns the signed token1 delta for a signed liquidity delta, mirrors SqrtPriceMath.getAmount1Delta(int128), see
+// `get_amount_0_delta`
 pub fn get_amount_1_delta(
     sqrt_ratio_a_x_96: U256,
     sqrt_ratio_b_x_96: U256,
     liquidity: i128,
 ) -> Result<I256, UniswapV3MathError> {
-    if liquidity < 0 {
-        Ok(-I256::from_raw(_get_amount_1_delta(
+    signed_amount(
+        _get_amount_1_delta(
             sqrt_ratio_a_x_96,
             sqrt_ratio_b_x_96,
-            -liquidity as u128,
-            false,
-        )?))
-    } else {
-        Ok(I256::from_raw(_get_amount_1_delta(
-            sqrt_ratio_a_x_96,
-            sqrt_ratio_b_x_96,
-            liquidity as u128,
-            true,
-        )?))
+            liquidity.unsigned_abs(),
+            liquidity >= 0,
+        )?,
+        liquidity,
+    )
+}
+
+// returns the token0 and token1 amounts of `liquidity` in the range between `sqrt_price_lower_x_96` and
+// `sqrt_price_upper_x_96` at `sqrt_price_current_x_96`, the single implementation of the range cases every other
+// function routes through. At or below the lower bound the range is entirely in token0 and at or above the upper bound
+// entirely in token1, a price exactly on a bound gives the same amounts with either convention because the amount of
+// the other token is 0 there, so it does not matter whether the pool branches on its tick or its price. The whole tick
+// range and liquidity up to u128::MAX are supported, the amounts stay below 2**225 and never overflow. Amounts rounded
+// down never exceed the exact amounts and amounts rounded up are never below them, the two differ by at most 1 each.
+// Fails if the bounds are out of order or the lower bound is 0
+pub fn amounts_for_range(
+    sqrt_price_current_x_96: U256,
+    sqrt_price_lower_x_96: U256,
+    sqrt_price_upper_x_96: U256,
+    liquidity: u128,
+    rounding: Rounding,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    if sqrt_price_lower_x_96 > sqrt_price_upper_x_96 {
+        return Err(UniswapV3MathError::SqrtPricesOutOfOrder);
     }
+    if sqrt_price_lower_x_96.is_zero() {
+        return Err(UniswapV3MathError::SqrtPriceIsZero);
+    }
+
+    // the price clamped to the range, below the range the token1 amount is then 0 and above it the token0 amount
+    let sqrt_price_x_96 =
+        sqrt_price_current_x_96.clamp(sqrt_price_lower_x_96, sqrt_price_upper_x_96);
+    Ok((
+        amount_0_delta(sqrt_price_x_96, sqrt_price_upper_x_96, liquidity, rounding)?,
+        amount_1_delta(sqrt_price_lower_x_96, sqrt_price_x_96, liquidity, rounding)?,
+    ))
+}
+
+// returns the signed token0 and token1 deltas of adding or removing `liquidity_delta` in the range between
+// `sqrt_price_lower_x_96` and `sqrt_price_upper_x_96` at `sqrt_price_current_x_96`, like UniswapV3Pool._modifyPosition:
+// below the range only token0 is owed, inside the range both tokens and above it only token1. Amounts owed to the
+// pool round up and amounts paid out round down, see `amounts_for_range` for the boundary cases
+pub fn get_amounts_delta(
+    sqrt_price_current_x_96: U256,
+    sqrt_price_lower_x_96: U256,
+    sqrt_price_upper_x_96: U256,
+    liquidity_delta: i128,
+) -> Result<(I256, I256), UniswapV3MathError> {
+    let (amount_0, amount_1) = amounts_for_range(
+        sqrt_price_current_x_96,
+        sqrt_price_lower_x_96,
+        sqrt_price_upper_x_96,
+        liquidity_delta.unsigned_abs(),
+        (liquidity_delta >= 0).into(),
+    )?;
+
+    Ok((
+        signed_amount(amount_0, liquidity_delta)?,
+        signed_amount(amount_1, liquidity_delta)?,
+    ))
+}
+
+// gives the amount the sign of the liquidity delta, fails if it does not fit an int256 like SafeCast.toInt256. Amounts
+// for u128 liquidity stay below 2**225, so the check mirrors the contract rather than a reachable failure
+fn signed_amount(amount: U256, liquidity: i128) -> Result<I256, UniswapV3MathError> {
+    let amount = I256::try_from(amount).map_err(|_| UniswapV3MathError::Overflow)?;
+    Ok(if liquidity < 0 { -amount } else { amount })
 }
 
 #[cfg(test)]
@@ -235,14 +459,27 @@ mod test {
         str::FromStr,
     };
 
-    use alloy_primitives::U256;
+    use alloy_primitives::{I256, U256};
 
     use crate::{
-        sqrt_price_math::{_get_amount_1_delta, get_next_sqrt_price_from_output, MAX_U160},
+        error::UniswapV3MathError,
+        sqrt_price_math::{
+            _get_amount_1_delta, encode_sqrt_ratio_x96, get_amount_in_to_target_price,
+            get_next_sqrt_price_from_output, MAX_U160, Q96,
+        },
+        swap_math::compute_swap_step,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
         U256_1, U256_2,
     };
 
-    use super::{_get_amount_0_delta, get_next_sqrt_price_from_input};
+    use super::{
+        _get_amount_0_delta, amount_0_delta, amount_1_delta, amounts_for_range,
+        checked_get_amount_0_delta, checked_get_amount_1_delta,
+        checked_get_next_sqrt_price_from_input, checked_get_next_sqrt_price_from_output,
+        get_amount_0_delta, get_amount_1_delta, get_amounts_delta,
+        get_next_sqrt_price_from_amount_0_rounding_up,
+        get_next_sqrt_price_from_amount_1_rounding_down, get_next_sqrt_price_from_input, Rounding,
+    };
 
     #[test]
     fn test_get_next_sqrt_price_from_input() {
@@ -521,6 +758,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_next_sqrt_price_uint160() {
+        let one = U256::from(1);
+
+        //the addition fails instead of overflowing 256 bits, the largest quotient that fits goes past 2**256
+        let result = get_next_sqrt_price_from_amount_1_rounding_down(Q96, 1, MAX_U160, true);
+        assert!(matches!(result, Err(UniswapV3MathError::Overflow)));
+
+        //a sum that fits 256 but not 160 bits fails the cast
+        let result = get_next_sqrt_price_from_amount_1_rounding_down(Q96, 1, one << 64, true);
+        assert!(matches!(
+            result,
+            Err(UniswapV3MathError::SafeCastToU160Overflow)
+        ));
+
+        //removing nearly all token0 pushes the price past 2**160, which fails the cast
+        let result = get_next_sqrt_price_from_amount_0_rounding_up(Q96 - one, 1, one, false);
+        assert!(matches!(
+            result,
+            Err(UniswapV3MathError::SafeCastToU160Overflow)
+        ));
+        let result = get_next_sqrt_price_from_output(Q96 - one, 1, one, false);
+        assert!(matches!(
+            result,
+            Err(UniswapV3MathError::SafeCastToU160Overflow)
+        ));
+    }
+
     #[test]
     fn test_get_amount_0_delta() {
         // returns 0 if liquidity is 0
@@ -652,4 +917,370 @@ mod test {
 
         assert_eq!(amount_0_delta, U256::from(406));
     }
+
+    #[test]
+    fn test_encode_sqrt_ratio_x96() {
+        // 1:1
+        let result = encode_sqrt_ratio_x96(U256_1, U256_1);
+        assert_eq!(result.unwrap(), Q96);
+
+        // 100:1
+        let result = encode_sqrt_ratio_x96(U256::from(100), U256_1);
+        assert_eq!(
+            result.unwrap(),
+            U256::from_str("792281625142643375935439503360").unwrap()
+        );
+
+        // 1:100
+        let result = encode_sqrt_ratio_x96(U256_1, U256::from(100));
+        assert_eq!(
+            result.unwrap(),
+            U256::from_str("7922816251426433759354395033").unwrap()
+        );
+
+        // 111:333
+        let result = encode_sqrt_ratio_x96(U256::from(111), U256::from(333));
+        assert_eq!(
+            result.unwrap(),
+            U256::from_str("45742400955009932534161870629").unwrap()
+        );
+
+        // 333:111
+        let result = encode_sqrt_ratio_x96(U256::from(333), U256::from(111));
+        assert_eq!(
+            result.unwrap(),
+            U256::from_str("137227202865029797602485611888").unwrap()
+        );
+
+        // fails if amount0 is zero
+        let result = encode_sqrt_ratio_x96(U256_1, U256::ZERO);
+        assert_eq!(result.unwrap_err().to_string(), "Denominator is 0");
+    }
+
+    #[test]
+    fn test_get_amount_in_to_target_price() {
+        let price = U256::from_str("79228162514264337593543950336").unwrap();
+        let liquidity = 2e18 as u128;
+
+        for (target, zero_for_one) in [
+            (
+                U256::from_str("79623317895830914510639640423").unwrap(),
+                false,
+            ),
+            (
+                U256::from_str("78833005054369209394398386549").unwrap(),
+                true,
+            ),
+        ] {
+            let amount_in =
+                get_amount_in_to_target_price(price, target, liquidity, 3000, zero_for_one)
+                    .unwrap();
+
+            //the amount is exactly enough to reach the target
+            let (sqrt_price_next, _, _, _) =
+                compute_swap_step(price, target, liquidity, I256::from_raw(amount_in), 3000)
+                    .unwrap();
+            assert_eq!(sqrt_price_next, target);
+
+            //one wei less stops short of the target
+            let (sqrt_price_next, _, _, _) = compute_swap_step(
+                price,
+                target,
+                liquidity,
+                I256::from_raw(amount_in - U256_1),
+                3000,
+            )
+            .unwrap();
+            assert_ne!(sqrt_price_next, target);
+        }
+
+        //returns zero in either direction if the price is already at the target
+        for zero_for_one in [true, false] {
+            let amount_in =
+                get_amount_in_to_target_price(price, price, liquidity, 3000, zero_for_one).unwrap();
+            assert_eq!(amount_in, U256::ZERO);
+        }
+
+        //fails if the target is on the wrong side of the current price
+        let result = get_amount_in_to_target_price(price, price + U256_1, liquidity, 3000, true);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_amount_deltas_ordered() {
+        let lower = encode_sqrt_ratio_x96(U256::from(1), U256::from(1)).unwrap();
+        let upper = encode_sqrt_ratio_x96(U256::from(121), U256::from(100)).unwrap();
+        let liquidity = 1e18 as u128;
+
+        //matches the unordered functions
+        for (rounding, round_up) in [(Rounding::Down, false), (Rounding::Up, true)] {
+            assert_eq!(
+                amount_0_delta(lower, upper, liquidity, rounding).unwrap(),
+                _get_amount_0_delta(upper, lower, liquidity, round_up).unwrap()
+            );
+            assert_eq!(
+                amount_1_delta(lower, upper, liquidity, rounding).unwrap(),
+                _get_amount_1_delta(upper, lower, liquidity, round_up).unwrap()
+            );
+        }
+        assert_eq!(
+            amount_0_delta(lower, upper, liquidity, Rounding::Up).unwrap(),
+            amount_0_delta(lower, upper, liquidity, Rounding::Down).unwrap() + U256_1
+        );
+
+        //fails if the bounds are out of order
+        assert!(amount_0_delta(upper, lower, liquidity, Rounding::Down).is_err());
+        assert!(amount_1_delta(upper, lower, liquidity, Rounding::Up).is_err());
+        //fails if the lower price is zero
+        assert!(amount_0_delta(U256::ZERO, upper, liquidity, Rounding::Down).is_err());
+    }
+
+    #[test]
+    fn test_signed_amount_deltas() {
+        let lower = encode_sqrt_ratio_x96(U256::from(1), U256::from(1)).unwrap();
+        let upper = encode_sqrt_ratio_x96(U256::from(121), U256::from(100)).unwrap();
+
+        //rounds up when adding and down when removing liquidity
+        let liquidity = 1e18 as u128;
+        assert_eq!(
+            get_amount_0_delta(lower, upper, liquidity as i128).unwrap(),
+            I256::from_raw(amount_0_delta(lower, upper, liquidity, Rounding::Up).unwrap())
+        );
+        assert_eq!(
+            get_amount_1_delta(upper, lower, -(liquidity as i128)).unwrap(),
+            -I256::from_raw(amount_1_delta(lower, upper, liquidity, Rounding::Down).unwrap())
+        );
+
+        //widens i128::MIN without overflowing
+        assert_eq!(
+            get_amount_1_delta(lower, upper, i128::MIN).unwrap(),
+            -I256::from_raw(amount_1_delta(lower, upper, 1 << 127, Rounding::Down).unwrap())
+        );
+
+        //liquidity above i128::MAX is only accepted by the unsigned functions
+        assert!(amount_0_delta(lower, upper, u128::MAX, Rounding::Up).unwrap() > U256::ZERO);
+    }
+
+    #[test]
+    fn test_get_amounts_delta() {
+        let (lower, upper) = (
+            get_sqrt_ratio_at_tick(-600).unwrap(),
+            get_sqrt_ratio_at_tick(600).unwrap(),
+        );
+        let liquidity = 1e18 as i128;
+
+        //only token0 below the range
+        let below = get_sqrt_ratio_at_tick(-601).unwrap();
+        assert_eq!(
+            get_amounts_delta(below, lower, upper, liquidity).unwrap(),
+            (
+                get_amount_0_delta(lower, upper, liquidity).unwrap(),
+                I256::ZERO
+            )
+        );
+
+        //both tokens inside the range, a boundary price counts as inside
+        let current = get_sqrt_ratio_at_tick(100).unwrap();
+        assert_eq!(
+            get_amounts_delta(current, lower, upper, liquidity).unwrap(),
+            (
+                get_amount_0_delta(current, upper, liquidity).unwrap(),
+                get_amount_1_delta(lower, current, liquidity).unwrap()
+            )
+        );
+        assert_eq!(
+            get_amounts_delta(lower, lower, upper, liquidity).unwrap(),
+            get_amounts_delta(below, lower, upper, liquidity).unwrap()
+        );
+
+        //only token1 at and above the upper boundary
+        assert_eq!(
+            get_amounts_delta(upper, lower, upper, -liquidity).unwrap(),
+            (
+                I256::ZERO,
+                get_amount_1_delta(lower, upper, -liquidity).unwrap()
+            )
+        );
+
+        //fails if the bounds are out of order
+        assert!(get_amounts_delta(current, upper, lower, liquidity).is_err());
+    }
+
+    #[test]
+    fn test_checked_variants() {
+        let sqrt_prices = [
+            U256::ZERO,
+            U256_1,
+            MIN_SQRT_RATIO,
+            Q96,
+            MAX_SQRT_RATIO,
+            MAX_U160,
+            MAX_U160 + U256_1,
+            U256::MAX,
+        ];
+        let liquidities = [0, 1, 1e18 as u128, u128::MAX];
+        let amounts = [
+            U256::ZERO,
+            U256_1,
+            Q96,
+            MAX_U160,
+            MAX_U160 + U256_1,
+            U256::MAX,
+        ];
+
+        //never panics, and agrees with the unchecked functions on the inputs the contract accepts
+        for &sqrt_price in &sqrt_prices {
+            for &liquidity in &liquidities {
+                for &amount in &amounts {
+                    for zero_for_one in [true, false] {
+                        let input = checked_get_next_sqrt_price_from_input(
+                            sqrt_price,
+                            liquidity,
+                            amount,
+                            zero_for_one,
+                        );
+                        let output = checked_get_next_sqrt_price_from_output(
+                            sqrt_price,
+                            liquidity,
+                            amount,
+                            zero_for_one,
+                        );
+                        if sqrt_price <= MAX_U160 {
+                            assert_eq!(
+                                input.ok(),
+                                get_next_sqrt_price_from_input(
+                                    sqrt_price,
+                                    liquidity,
+                                    amount,
+                                    zero_for_one
+                                )
+                                .ok()
+                            );
+                            assert_eq!(
+                                output.ok(),
+                                get_next_sqrt_price_from_output(
+                                    sqrt_price,
+                                    liquidity,
+                                    amount,
+                                    zero_for_one
+                                )
+                                .ok()
+                            );
+                        } else {
+                            assert!(matches!(input, Err(UniswapV3MathError::SqrtPriceOverflow)));
+                            assert!(matches!(output, Err(UniswapV3MathError::SqrtPriceOverflow)));
+                        }
+                    }
+                }
+
+                for &other in &sqrt_prices {
+                    for round_up in [true, false] {
+                        let amount_0 =
+                            checked_get_amount_0_delta(sqrt_price, other, liquidity, round_up);
+                        let amount_1 =
+                            checked_get_amount_1_delta(sqrt_price, other, liquidity, round_up);
+                        if sqrt_price <= MAX_U160 && other <= MAX_U160 {
+                            assert_eq!(
+                                amount_1.unwrap(),
+                                _get_amount_1_delta(sqrt_price, other, liquidity, round_up)
+                                    .unwrap()
+                            );
+                            if !sqrt_price.is_zero() && !other.is_zero() {
+                                assert_eq!(
+                                    amount_0.unwrap(),
+                                    _get_amount_0_delta(sqrt_price, other, liquidity, round_up)
+                                        .unwrap()
+                                );
+                            }
+                        } else {
+                            assert!(amount_0.is_err() && amount_1.is_err());
+                        }
+                    }
+                }
+            }
+        }
+
+        //an input that overflows the denominator fails like the contract instead of wrapping
+        assert!(matches!(
+            get_next_sqrt_price_from_amount_0_rounding_up(U256_1, 1, U256::MAX, true),
+            Err(UniswapV3MathError::Overflow)
+        ));
+        assert!(matches!(
+            get_next_sqrt_price_from_amount_1_rounding_down(Q96, 0, U256_1, true),
+            Err(UniswapV3MathError::LiquidityIsZero)
+        ));
+
+        //a next price past 2**160 fails the cast where the contract reverts instead of wrapping
+        assert!(matches!(
+            checked_get_next_sqrt_price_from_output(Q96 - U256_1, 1, U256_1, false),
+            Err(UniswapV3MathError::SafeCastToU160Overflow)
+        ));
+    }
+
+    #[test]
+    fn test_amounts_for_range() {
+        let (lower, upper) = (
+            get_sqrt_ratio_at_tick(-600).unwrap(),
+            get_sqrt_ratio_at_tick(600).unwrap(),
+        );
+        let liquidity = 1e18 as u128;
+        let amount_0 = amount_0_delta(lower, upper, liquidity, Rounding::Down).unwrap();
+        let amount_1 = amount_1_delta(lower, upper, liquidity, Rounding::Down).unwrap();
+
+        //exactly on a bound the range is entirely in one token, the same as just outside of it
+        for price in [lower, lower - U256::from(1), MIN_SQRT_RATIO] {
+            assert_eq!(
+                amounts_for_range(price, lower, upper, liquidity, Rounding::Down).unwrap(),
+                (amount_0, U256::ZERO)
+            );
+        }
+        for price in [upper, upper + U256::from(1), MAX_SQRT_RATIO] {
+            assert_eq!(
+                amounts_for_range(price, lower, upper, liquidity, Rounding::Down).unwrap(),
+                (U256::ZERO, amount_1)
+            );
+        }
+
+        //one wei inside a bound both tokens are held
+        let (amount_0, amount_1) =
+            amounts_for_range(lower + U256::from(1), lower, upper, liquidity, Rounding::Up)
+                .unwrap();
+        assert!(amount_0 > U256::ZERO && amount_1 == U256::from(1));
+
+        //the whole tick range at the largest liquidity does not overflow, and rounding up adds at most 1
+        for price in [
+            MIN_SQRT_RATIO,
+            get_sqrt_ratio_at_tick(0).unwrap(),
+            MAX_SQRT_RATIO,
+        ] {
+            let down = amounts_for_range(
+                price,
+                MIN_SQRT_RATIO,
+                MAX_SQRT_RATIO,
+                u128::MAX,
+                Rounding::Down,
+            )
+            .unwrap();
+            let up = amounts_for_range(
+                price,
+                MIN_SQRT_RATIO,
+                MAX_SQRT_RATIO,
+                u128::MAX,
+                Rounding::Up,
+            )
+            .unwrap();
+            assert!(down.0 < U256::from(1) << 225 && down.1 < U256::from(1) << 225);
+            assert!(up.0 - down.0 <= U256::from(1) && up.1 - down.1 <= U256::from(1));
+        }
+
+        //an empty range holds nothing
+        assert_eq!(
+            amounts_for_range(lower, lower, lower, liquidity, Rounding::Up).unwrap(),
+            (U256::ZERO, U256::ZERO)
+        );
+
+        //fails if the bounds are out of order or zero
+        assert!(amounts_for_range(lower, upper, lower, liquidity, Rounding::Down).is_err());
+        assert!(amounts_for_range(lower, U256::ZERO, upper, liquidity, Rounding::Down).is_err());
+    }
 }
@@ -0,0 +1,164 @@
+use alloy_primitives::{keccak256, Address, B256, U256};
+
+use crate::{error::UniswapV3MathError, full_math::mul_div, sqrt_price_math::MAX_U160};
+
+//Parameters of a canonical UniswapV3Staker incentive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct IncentiveKey {
+    pub reward_token: Address,
+    pub pool: Address,
+    pub start_time: U256,
+    pub end_time: U256,
+    pub refundee: Address,
+}
+
+impl IncentiveKey {
+    // returns the incentive id, keccak256(abi.encode(key)), mirrors IncentiveId.compute
+    pub fn compute_id(&self) -> B256 {
+        let mut encoded = [0u8; 160];
+        encoded[12..32].copy_from_slice(self.reward_token.as_slice());
+        encoded[44..64].copy_from_slice(self.pool.as_slice());
+        encoded[64..96].copy_from_slice(&self.start_time.to_be_bytes::<32>());
+        encoded[96..128].copy_from_slice(&self.end_time.to_be_bytes::<32>());
+        encoded[140..160].copy_from_slice(self.refundee.as_slice());
+        keccak256(encoded)
+    }
+}
+
+// returns (uint256 reward, uint160 secondsInsideX128), mirrors RewardMath.computeRewardAmount
+#[allow(clippy::too_many_arguments)]
+pub fn compute_reward_amount(
+    total_reward_unclaimed: U256,
+    total_seconds_claimed_x_128: U256,
+    start_time: U256,
+    end_time: U256,
+    liquidity: u128,
+    seconds_per_liquidity_inside_initial_x_128: U256,
+    seconds_per_liquidity_inside_x_128: U256,
+    current_time: U256,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    // this should never be called before the start time
+    if current_time < start_time {
+        return Err(UniswapV3MathError::IncentiveNotStarted);
+    }
+
+    // the difference cannot be greater than 1/stake.liquidity, the result is truncated to a uint160 like the contract
+    let seconds_inside_x_128 = seconds_per_liquidity_inside_x_128
+        .wrapping_sub(seconds_per_liquidity_inside_initial_x_128)
+        .wrapping_mul(U256::from(liquidity))
+        & MAX_U160;
+
+    let elapsed = end_time.max(current_time) - start_time;
+    let total_seconds_unclaimed_x_128 =
+        (elapsed << 128_usize).wrapping_sub(total_seconds_claimed_x_128);
+
+    let reward = mul_div(
+        total_reward_unclaimed,
+        seconds_inside_x_128,
+        total_seconds_unclaimed_x_128,
+    )?;
+
+    Ok((reward, seconds_inside_x_128))
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, keccak256, B256, U256};
+
+    use super::{compute_reward_amount, IncentiveKey};
+
+    #[test]
+    fn test_compute_reward_amount() {
+        //half the liquidity over 20% of the total duration
+        let (reward, seconds_inside_x_128) = compute_reward_amount(
+            U256::from(1000),
+            U256::ZERO,
+            U256::from(100),
+            U256::from(200),
+            5,
+            U256::ZERO,
+            (U256::from(20) << 128) / U256::from(10),
+            U256::from(120),
+        )
+        .unwrap();
+        // 1000 * 0.5 * 0.2
+        assert_eq!(seconds_inside_x_128, U256::from(10) << 128);
+        assert_eq!(reward, U256::from(100));
+
+        //all the liquidity for the duration and none of the liquidity after the end time
+        let (reward, seconds_inside_x_128) = compute_reward_amount(
+            U256::from(1000),
+            U256::ZERO,
+            U256::from(100),
+            U256::from(200),
+            100,
+            U256::ZERO,
+            (U256::from(100) << 128) / U256::from(100),
+            U256::from(300),
+        )
+        .unwrap();
+        // 1000 * 1 * 0.5, the time after the end time dilutes the reward
+        assert_eq!(seconds_inside_x_128, U256::from(100) << 128);
+        assert_eq!(reward, U256::from(500));
+
+        //accounts for previously claimed seconds
+        let (reward, _) = compute_reward_amount(
+            U256::from(1000),
+            U256::from(50) << 128,
+            U256::from(100),
+            U256::from(200),
+            1,
+            U256::ZERO,
+            U256::from(25) << 128,
+            U256::from(150),
+        )
+        .unwrap();
+        // 1000 * 25 / 50
+        assert_eq!(reward, U256::from(500));
+
+        //fails before the start time
+        let result = compute_reward_amount(
+            U256::from(1000),
+            U256::ZERO,
+            U256::from(100),
+            U256::from(200),
+            1,
+            U256::ZERO,
+            U256::ZERO,
+            U256::from(99),
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Current time must be greater than or equal to the incentive start time"
+        );
+    }
+
+    #[test]
+    fn test_incentive_id() {
+        let key = IncentiveKey {
+            reward_token: address!("1f9840a85d5af5bf1d1762f925bdaddc4201f984"),
+            pool: address!("8ad599c3a0ff1de082011efddc58f1908eb6e6d8"),
+            start_time: U256::from(1000),
+            end_time: U256::from(2000),
+            refundee: address!("0000000000000000000000000000000000000001"),
+        };
+
+        // abi.encode of a static struct is the concatenation of its left padded 32 byte words
+        let encoded = [
+            key.reward_token.into_word(),
+            key.pool.into_word(),
+            B256::from(key.start_time),
+            B256::from(key.end_time),
+            key.refundee.into_word(),
+        ]
+        .concat();
+        assert_eq!(key.compute_id(), keccak256(encoded));
+
+        //differs per key
+        let other = IncentiveKey {
+            end_time: U256::from(2001),
+            ..key
+        };
+        assert_ne!(key.compute_id(), other.compute_id());
+    }
+}
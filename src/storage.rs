@@ -0,0 +1,225 @@
+use std::{collections::HashMap, fmt, hash::Hash};
+
+use alloy_primitives::U256;
+
+use crate::{error::UniswapV3MathError, tick::Tick, tick_bitmap, tick_provider::TickProvider};
+
+//A key-value store for pool ticks and tick bitmap words, implemented for HashMap and the persistent CowMap. Values
+//are returned by copy, so layouts that do not keep whole values in memory can implement it
+pub trait Storage<K, V>: Clone + Default {
+    fn get(&self, key: &K) -> Option<V>;
+
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+
+    fn remove(&mut self, key: &K) -> Option<V>;
+
+    // returns every stored key in no particular order, for scans of the whole storage
+    fn keys(&self) -> impl Iterator<Item = K> + '_;
+}
+
+impl<K: Clone + Eq + Hash, V: Copy> Storage<K, V> for HashMap<K, V> {
+    fn get(&self, key: &K) -> Option<V> {
+        HashMap::get(self, key).copied()
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        HashMap::insert(self, key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        HashMap::remove(self, key)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        HashMap::keys(self).cloned()
+    }
+}
+
+//A persistent map, backed by the hash array mapped trie of `im::HashMap`. Cloning is O(1), and a write to a clone
+//copies only the O(log n) nodes on the path to the written key while all other nodes stay shared with the original,
+//regardless of how the keys are distributed, e.g. ticks of a wide tick spacing
+#[derive(Clone)]
+pub struct CowMap<K, V> {
+    map: im::HashMap<K, V>,
+}
+
+impl<K, V> Default for CowMap<K, V> {
+    fn default() -> Self {
+        Self {
+            map: im::HashMap::new(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash + fmt::Debug, V: fmt::Debug> fmt::Debug for CowMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.map.iter()).finish()
+    }
+}
+
+impl<K: Copy + Eq + Hash, V: Clone> CowMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.iter()
+    }
+
+    // returns true if both maps share their root node, i.e. neither was written to since one was cloned from the
+    // other
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.map.ptr_eq(&other.map)
+    }
+}
+
+impl<K: Copy + Eq + Hash, V: Copy> Storage<K, V> for CowMap<K, V> {
+    fn get(&self, key: &K) -> Option<V> {
+        self.map.get(key).copied()
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        // avoid copying shared nodes if there is nothing to remove
+        self.map.get(key)?;
+        self.map.remove(key)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.map.keys().copied()
+    }
+}
+
+impl<K: Copy + Eq + Hash, V: Copy + PartialEq> PartialEq for CowMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: Copy + Eq + Hash, V: Copy + Eq> Eq for CowMap<K, V> {}
+
+impl<K: Copy + Eq + Hash, V: Copy> FromIterator<(K, V)> for CowMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            map: iter.into_iter().collect(),
+        }
+    }
+}
+
+//Storage for pool ticks. Swaps only read the liquidity net of crossed ticks, which layouts that keep it apart from
+//the rest of the tick can serve without reading whole ticks
+pub trait TickStorage: Storage<i32, Tick> {
+    // false for layouts that drop the gross liquidity of ticks, see `MinimalTicks`
+    const LIQUIDITY_GROSS: bool = true;
+
+    fn liquidity_net(&self, tick: i32) -> i128 {
+        self.get(&tick)
+            .map(|info| info.liquidity_net)
+            .unwrap_or_default()
+    }
+
+    // reads `tick` through the provider and stores it, storages that keep only part of a tick can read only that part
+    fn load_tick<P: TickProvider>(&mut self, provider: &mut P, tick: i32) -> Result<(), P::Error> {
+        let info = provider.tick(tick)?;
+        self.insert(tick, info);
+        Ok(())
+    }
+}
+
+impl TickStorage for HashMap<i32, Tick> {}
+
+impl TickStorage for CowMap<i32, Tick> {}
+
+//Tracks which ticks are initialized, implemented for tick bitmaps stored by word and for the bitmap free TickIndex
+pub trait InitializedTicks: Clone + Default {
+    // flips the initialized state of `tick`, see `tick_bitmap::flip_tick`
+    fn flip_tick(&mut self, tick: i32, tick_spacing: i32) -> Result<(), UniswapV3MathError>;
+
+    // returns the next initialized tick in the same bitmap word as `tick`, or the last tick of the word if there is
+    // none, see `tick_bitmap::next_initialized_tick_within_one_word`
+    fn next_initialized_tick_within_one_word(
+        &self,
+        tick: i32,
+        tick_spacing: i32,
+        lte: bool,
+    ) -> Result<(i32, bool), UniswapV3MathError>;
+
+    // returns every initialized tick in no particular order. Ticks are widened to i64, a corrupt bitmap can mark
+    // positions whose tick does not fit into an i32
+    fn initialized_ticks(&self, tick_spacing: i32) -> impl Iterator<Item = i64> + '_;
+}
+
+impl<S: Storage<i16, U256>> InitializedTicks for S {
+    fn flip_tick(&mut self, tick: i32, tick_spacing: i32) -> Result<(), UniswapV3MathError> {
+        tick_bitmap::flip_tick(self, tick, tick_spacing)
+    }
+
+    fn next_initialized_tick_within_one_word(
+        &self,
+        tick: i32,
+        tick_spacing: i32,
+        lte: bool,
+    ) -> Result<(i32, bool), UniswapV3MathError> {
+        tick_bitmap::next_initialized_tick_within_one_word(self, tick, tick_spacing, lte)
+    }
+
+    fn initialized_ticks(&self, tick_spacing: i32) -> impl Iterator<Item = i64> + '_ {
+        self.keys().flat_map(move |word_pos| {
+            let word = self.get(&word_pos).unwrap_or_default();
+            (0..256)
+                .filter(move |&bit_pos| word.bit(bit_pos))
+                .map(move |bit_pos| {
+                    ((word_pos as i64) * 256 + bit_pos as i64) * tick_spacing as i64
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CowMap, Storage};
+
+    #[test]
+    fn test_cow_map() {
+        let mut map: CowMap<i32, u32> = (-1000..1000).map(|key| (key * 60, key as u32)).collect();
+        assert_eq!(map.len(), 2000);
+        assert_eq!(map.get(&-600), Some(-10_i32 as u32));
+
+        //clones share all nodes until written to
+        let mut fork = map.clone();
+        assert!(fork.ptr_eq(&map));
+        assert_eq!(fork.insert(60, 100), Some(1));
+        assert_eq!(fork.insert(61, 100), None);
+        assert!(!fork.ptr_eq(&map));
+        assert_eq!(fork.len(), 2001);
+
+        //the original is unchanged by writes to the fork
+        assert_eq!(map.get(&60), Some(1));
+        assert_eq!(map.get(&61), None);
+        assert_ne!(map, fork);
+
+        //removing missing keys does not copy any nodes
+        let fork = map.clone();
+        map.remove(&59);
+        assert!(fork.ptr_eq(&map));
+
+        assert_eq!(map.remove(&60), Some(1));
+        assert_eq!(map.len(), 1999);
+        assert_eq!(fork.get(&60), Some(1));
+
+        //maps with the same entries are equal regardless of their history
+        map.insert(60, 1);
+        assert_eq!(map, fork);
+    }
+}
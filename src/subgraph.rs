@@ -0,0 +1,319 @@
+use std::{collections::HashMap, fmt};
+
+use alloy_primitives::U256;
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::{
+    error::UniswapV3MathError,
+    fee_tier::FeeTier,
+    pool::{PoolSnapshot, Slot0},
+    tick::Tick,
+    tick_bitmap::flip_tick,
+    tick_math::get_tick_at_sqrt_ratio,
+    variant::UniswapV3,
+};
+
+//A pool entity as returned by the Uniswap v3 subgraph. BigInt fields are kept as decimal strings, numbers are accepted
+//as well for indexers that return them unquoted
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphPool {
+    #[serde(deserialize_with = "decimal")]
+    pub fee_tier: String,
+    #[serde(deserialize_with = "decimal")]
+    pub sqrt_price: String,
+    // null until the pool is initialized
+    #[serde(default, deserialize_with = "optional_decimal")]
+    pub tick: Option<String>,
+    #[serde(deserialize_with = "decimal")]
+    pub liquidity: String,
+    #[serde(
+        default,
+        rename = "feeGrowthGlobal0X128",
+        deserialize_with = "optional_decimal"
+    )]
+    pub fee_growth_global_0_x_128: Option<String>,
+    #[serde(
+        default,
+        rename = "feeGrowthGlobal1X128",
+        deserialize_with = "optional_decimal"
+    )]
+    pub fee_growth_global_1_x_128: Option<String>,
+}
+
+//A tick entity as returned by the Uniswap v3 subgraph
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphTick {
+    #[serde(deserialize_with = "decimal")]
+    pub tick_idx: String,
+    #[serde(deserialize_with = "decimal")]
+    pub liquidity_net: String,
+    #[serde(deserialize_with = "decimal")]
+    pub liquidity_gross: String,
+    #[serde(
+        default,
+        rename = "feeGrowthOutside0X128",
+        deserialize_with = "optional_decimal"
+    )]
+    pub fee_growth_outside_0_x_128: Option<String>,
+    #[serde(
+        default,
+        rename = "feeGrowthOutside1X128",
+        deserialize_with = "optional_decimal"
+    )]
+    pub fee_growth_outside_1_x_128: Option<String>,
+}
+
+// returns a pool snapshot from a subgraph pool and its ticks. Ticks without liquidity are skipped, the subgraph keeps
+// them around after all positions referencing them are burned. Custom fee tiers need their tick spacing, which the
+// subgraph pool does not include
+pub fn pool_from_subgraph(
+    pool: &SubgraphPool,
+    ticks: &[SubgraphTick],
+    tick_spacing: Option<i32>,
+) -> Result<PoolSnapshot, UniswapV3MathError> {
+    let fee = parse_u128(&pool.fee_tier)?;
+    let fee = u32::try_from(fee).map_err(|_| UniswapV3MathError::InvalidFee)?;
+    let fee_tier = match (FeeTier::from_fee(fee), tick_spacing) {
+        (_, Some(tick_spacing)) => FeeTier::custom(fee, tick_spacing)?,
+        (Some(fee_tier), None) => fee_tier,
+        (None, None) => return Err(UniswapV3MathError::UnknownFeeTier),
+    };
+
+    let sqrt_price_x_96 = parse_u256(&pool.sqrt_price)?;
+    if sqrt_price_x_96.is_zero() {
+        return Err(UniswapV3MathError::SqrtPriceIsZero);
+    }
+    let tick = match &pool.tick {
+        Some(tick) => parse_i32(tick)?,
+        None => get_tick_at_sqrt_ratio(sqrt_price_x_96)?,
+    };
+
+    let mut snapshot = PoolSnapshot {
+        slot0: Slot0 {
+            sqrt_price_x_96,
+            tick,
+            observation_cardinality: 1,
+            observation_cardinality_next: 1,
+            unlocked: true,
+            ..Default::default()
+        },
+        liquidity: parse_u128(&pool.liquidity)?,
+        fee_tier,
+        fee_growth_global_0_x_128: parse_optional_u256(&pool.fee_growth_global_0_x_128)?,
+        fee_growth_global_1_x_128: parse_optional_u256(&pool.fee_growth_global_1_x_128)?,
+        protocol_fees: Default::default(),
+        ticks: HashMap::new(),
+        tick_bitmap: HashMap::new(),
+        variant: UniswapV3,
+    };
+
+    for tick in ticks {
+        let tick_idx = parse_i32(&tick.tick_idx)?;
+        let liquidity_gross = parse_u128(&tick.liquidity_gross)?;
+        if liquidity_gross == 0 {
+            continue;
+        }
+
+        snapshot.ticks.insert(
+            tick_idx,
+            Tick {
+                liquidity_gross,
+                liquidity_net: parse_i128(&tick.liquidity_net)?,
+                fee_growth_outside_0_x_128: parse_optional_u256(&tick.fee_growth_outside_0_x_128)?,
+                fee_growth_outside_1_x_128: parse_optional_u256(&tick.fee_growth_outside_1_x_128)?,
+                initialized: true,
+                ..Default::default()
+            },
+        );
+        flip_tick(&mut snapshot.tick_bitmap, tick_idx, fee_tier.tick_spacing())?;
+    }
+
+    Ok(snapshot)
+}
+
+fn parse_u256(value: &str) -> Result<U256, UniswapV3MathError> {
+    Ok(U256::from_str_radix(value.trim(), 10)?)
+}
+
+fn parse_optional_u256(value: &Option<String>) -> Result<U256, UniswapV3MathError> {
+    value.as_deref().map_or(Ok(U256::ZERO), parse_u256)
+}
+
+fn parse_u128(value: &str) -> Result<u128, UniswapV3MathError> {
+    u128::try_from(parse_u256(value)?).map_err(|_| UniswapV3MathError::Overflow)
+}
+
+// parses a signed decimal, the sign is split off before parsing the magnitude
+fn parse_i128(value: &str) -> Result<i128, UniswapV3MathError> {
+    let value = value.trim();
+    match value.strip_prefix('-') {
+        Some(magnitude) => {
+            let magnitude = parse_u128(magnitude)?;
+            0_i128
+                .checked_sub_unsigned(magnitude)
+                .ok_or(UniswapV3MathError::Overflow)
+        }
+        None => i128::try_from(parse_u128(value)?).map_err(|_| UniswapV3MathError::Overflow),
+    }
+}
+
+fn parse_i32(value: &str) -> Result<i32, UniswapV3MathError> {
+    i32::try_from(parse_i128(value)?).map_err(|_| UniswapV3MathError::Overflow)
+}
+
+struct DecimalVisitor;
+
+impl Visitor<'_> for DecimalVisitor {
+    type Value = String;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal string or an integer")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<String, E> {
+        Ok(value.to_owned())
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<String, E> {
+        Ok(value.to_string())
+    }
+}
+
+fn decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    deserializer.deserialize_any(DecimalVisitor)
+}
+
+fn optional_decimal<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    struct Decimal(#[serde(deserialize_with = "decimal")] String);
+
+    Ok(Option::<Decimal>::deserialize(deserializer)?.map(|Decimal(value)| value))
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+    use serde::{
+        de::{value::MapDeserializer, IntoDeserializer},
+        Deserialize,
+    };
+
+    use super::{parse_i128, pool_from_subgraph, SubgraphPool, SubgraphTick};
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        pool::PoolSnapshot,
+        tick_math::{get_sqrt_ratio_at_tick, MIN_SQRT_RATIO},
+    };
+
+    fn subgraph_tick(tick_idx: i32, liquidity_net: i128) -> SubgraphTick {
+        SubgraphTick {
+            tick_idx: tick_idx.to_string(),
+            liquidity_net: liquidity_net.to_string(),
+            liquidity_gross: liquidity_net.unsigned_abs().to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let tick = SubgraphTick::deserialize(MapDeserializer::<_, serde::de::value::Error>::new(
+            [
+                ("tickIdx", "-887220"),
+                ("liquidityNet", "-170141183460469231731687303715884105727"),
+                ("liquidityGross", "170141183460469231731687303715884105727"),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key, value.into_deserializer())),
+        ))
+        .unwrap();
+        assert_eq!(tick.tick_idx, "-887220");
+        assert_eq!(tick.fee_growth_outside_0_x_128, None);
+
+        //values outside of the i128 range are rejected instead of wrapping
+        assert_eq!(parse_i128(&tick.liquidity_net).unwrap(), -i128::MAX);
+        assert!(matches!(
+            parse_i128("170141183460469231731687303715884105728"),
+            Err(UniswapV3MathError::Overflow)
+        ));
+        assert_eq!(
+            parse_i128("-170141183460469231731687303715884105728").unwrap(),
+            i128::MIN
+        );
+    }
+
+    #[test]
+    fn test_pool_from_subgraph() {
+        let sqrt_price_x_96 = get_sqrt_ratio_at_tick(10).unwrap();
+        let subgraph_pool = SubgraphPool {
+            fee_tier: "3000".to_string(),
+            sqrt_price: sqrt_price_x_96.to_string(),
+            tick: Some("10".to_string()),
+            liquidity: "1100000000000000000000".to_string(),
+            ..Default::default()
+        };
+        let ticks = [
+            subgraph_tick(-887220, 1e20 as i128),
+            subgraph_tick(887220, -(1e20 as i128)),
+            subgraph_tick(-600, 1e21 as i128),
+            subgraph_tick(600, -(1e21 as i128)),
+            subgraph_tick(1200, 0),
+        ];
+
+        //the converted pool quotes like a pool built from the same positions
+        let pool = pool_from_subgraph(&subgraph_pool, &ticks, None).unwrap();
+        let mut expected = PoolSnapshot::new(sqrt_price_x_96, FeeTier::Medium).unwrap();
+        expected
+            .modify_position(-887220, 887220, 1e20 as i128)
+            .unwrap();
+        expected.modify_position(-600, 600, 1e21 as i128).unwrap();
+        assert_eq!(pool.ticks.len(), 4);
+        assert_eq!(pool.liquidity, expected.liquidity);
+
+        let amount = I256::from_raw(U256::from(1e22 as u128));
+        assert_eq!(
+            pool.quote(true, amount, MIN_SQRT_RATIO + U256::from(1))
+                .unwrap(),
+            expected
+                .quote(true, amount, MIN_SQRT_RATIO + U256::from(1))
+                .unwrap()
+        );
+
+        //fails if a custom fee tier has no tick spacing
+        let custom = SubgraphPool {
+            fee_tier: "2500".to_string(),
+            ..subgraph_pool.clone()
+        };
+        assert!(matches!(
+            pool_from_subgraph(&custom, &ticks, None),
+            Err(UniswapV3MathError::UnknownFeeTier)
+        ));
+        assert!(pool_from_subgraph(&custom, &ticks, Some(60)).is_ok());
+
+        //fails on malformed decimals
+        let malformed = SubgraphPool {
+            liquidity: "1.5e18".to_string(),
+            ..subgraph_pool
+        };
+        assert!(pool_from_subgraph(&malformed, &ticks, None).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use alloy_primitives::{I256, U256};
 
 use crate::{
     error::UniswapV3MathError,
+    fee_tier::FEE_DENOMINATOR,
     full_math::{mul_div, mul_div_rounding_up},
     sqrt_price_math::{
         _get_amount_0_delta, _get_amount_1_delta, get_next_sqrt_price_from_input,
@@ -22,8 +23,40 @@ pub fn compute_swap_step(
     amount_remaining: I256,
     fee_pips: u32,
 ) -> Result<(U256, U256, U256, U256), UniswapV3MathError> {
+    compute_swap_step_with_fee_denominator(
+        sqrt_ratio_current_x_96,
+        sqrt_ratio_target_x_96,
+        liquidity,
+        amount_remaining,
+        fee_pips,
+        FEE_DENOMINATOR,
+    )
+}
+
+// same as `compute_swap_step` for forks that express fees in a different denominator than 1e6
+pub fn compute_swap_step_with_fee_denominator(
+    sqrt_ratio_current_x_96: U256,
+    sqrt_ratio_target_x_96: U256,
+    liquidity: u128,
+    amount_remaining: I256,
+    fee_pips: u32,
+    fee_denominator: u32,
+) -> Result<(U256, U256, U256, U256), UniswapV3MathError> {
+    if fee_pips >= fee_denominator {
+        return Err(UniswapV3MathError::InvalidFee);
+    }
+
     let zero_for_one = sqrt_ratio_current_x_96 >= sqrt_ratio_target_x_96;
     let exact_in = amount_remaining >= I256::ZERO;
+    // the amount still to be paid out when exact out, INT256_MIN has no positive counterpart
+    let amount_remaining_out = if exact_in {
+        U256::ZERO
+    } else {
+        amount_remaining
+            .checked_neg()
+            .ok_or(UniswapV3MathError::Overflow)?
+            .into_raw()
+    };
 
     let sqrt_ratio_next_x_96: U256;
     let mut amount_in = U256::ZERO;
@@ -32,8 +65,8 @@ pub fn compute_swap_step(
     if exact_in {
         let amount_remaining_less_fee = mul_div(
             amount_remaining.into_raw(),
-            U256::from(1e6 as u32 - fee_pips),    //1e6 - fee_pips
-            U256::from_limbs([1000000, 0, 0, 0]), //1e6
+            U256::from(fee_denominator - fee_pips),
+            U256::from(fee_denominator),
         )?;
 
         amount_in = if zero_for_one {
@@ -79,13 +112,13 @@ pub fn compute_swap_step(
             )?
         };
 
-        sqrt_ratio_next_x_96 = if (-amount_remaining).into_raw() >= amount_out {
+        sqrt_ratio_next_x_96 = if amount_remaining_out >= amount_out {
             sqrt_ratio_target_x_96
         } else {
             get_next_sqrt_price_from_output(
                 sqrt_ratio_current_x_96,
                 liquidity,
-                (-amount_remaining).into_raw(),
+                amount_remaining_out,
                 zero_for_one,
             )?
         };
@@ -131,8 +164,8 @@ pub fn compute_swap_step(
         }
     }
 
-    if !exact_in && amount_out > (-amount_remaining).into_raw() {
-        amount_out = (-amount_remaining).into_raw();
+    if !exact_in && amount_out > amount_remaining_out {
+        amount_out = amount_remaining_out;
     }
 
     if exact_in && sqrt_ratio_next_x_96 != sqrt_ratio_target_x_96 {
@@ -142,7 +175,7 @@ pub fn compute_swap_step(
         let fee_amount = mul_div_rounding_up(
             amount_in,
             U256::from(fee_pips),
-            U256::from(1e6 as u32 - fee_pips),
+            U256::from(fee_denominator - fee_pips),
         )?;
 
         Ok((sqrt_ratio_next_x_96, amount_in, amount_out, fee_amount))
@@ -152,6 +185,8 @@ pub fn compute_swap_step(
 #[cfg(test)]
 mod test {
 
+    use crate::error::UniswapV3MathError;
+    use crate::fee_tier::FeeTier;
     use crate::sqrt_price_math::{get_next_sqrt_price_from_input, get_next_sqrt_price_from_output};
     use crate::swap_math::compute_swap_step;
     use crate::U256_1;
@@ -392,4 +427,37 @@ mod test {
         assert_eq!(amount_in, U256_1);
         assert_eq!(fee_amount, U256_1);
     }
+
+    #[test]
+    fn test_compute_swap_step_fee_tier() {
+        let price = U256::from_str("79228162514264337593543950336").unwrap();
+        let price_target = U256::from_str("79623317895830914510639640423").unwrap();
+        let liquidity = 2e18 as u128;
+        let amount = I256::from_str("1000000000000000000").unwrap();
+
+        //custom fee tiers are passed through as pips
+        let tier = FeeTier::custom(600, 12).unwrap();
+        let (_, amount_in, _, fee_amount) =
+            compute_swap_step(price, price_target, liquidity, amount, tier.into()).unwrap();
+
+        assert_eq!(amount_in, U256::from_str("9975124224178055").unwrap());
+        assert_eq!(fee_amount, U256::from_str("5988667735148").unwrap());
+
+        //fails if the fee is 100% or more
+        let result = compute_swap_step(price, price_target, liquidity, amount, 1000000);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Fee must be less than 1e6 pips"
+        );
+    }
+
+    #[test]
+    fn test_compute_swap_step_min_amount_remaining() {
+        let price = U256::from_str("79228162514264337593543950336").unwrap();
+        let price_target = U256::from_str("79623317895830914510639640423").unwrap();
+
+        //an exact output of INT256_MIN cannot be negated
+        let result = compute_swap_step(price, price_target, 2e18 as u128, I256::MIN, 600);
+        assert!(matches!(result, Err(UniswapV3MathError::Overflow)));
+    }
 }
@@ -0,0 +1,82 @@
+use alloy_primitives::U256;
+
+use crate::{
+    backtest::Event,
+    fee_tier::FeeTier,
+    pool::{PoolSnapshot, SwapParams},
+    tick_math::{get_sqrt_ratio_at_tick, MAX_TICK},
+};
+
+// fixtures shared by the tests of the modules
+
+//Builds the pools of tests, a pool at a price with positions minted in the order they are added. The fee tier
+//defaults to `FeeTier::Medium`
+#[derive(Clone, Debug)]
+pub(crate) struct PoolBuilder {
+    sqrt_price_x_96: U256,
+    fee_tier: FeeTier,
+    // the range of each position, None for the full range of the fee tier
+    positions: Vec<(Option<(i32, i32)>, i128)>,
+}
+
+impl PoolBuilder {
+    pub(crate) fn at_tick(tick: i32) -> Self {
+        Self::at_price(get_sqrt_ratio_at_tick(tick).unwrap())
+    }
+
+    pub(crate) fn at_price(sqrt_price_x_96: U256) -> Self {
+        Self {
+            sqrt_price_x_96,
+            fee_tier: FeeTier::Medium,
+            positions: vec![],
+        }
+    }
+
+    pub(crate) fn fee_tier(mut self, fee_tier: FeeTier) -> Self {
+        self.fee_tier = fee_tier;
+        self
+    }
+
+    pub(crate) fn position(mut self, tick_lower: i32, tick_upper: i32, liquidity: i128) -> Self {
+        self.positions
+            .push((Some((tick_lower, tick_upper)), liquidity));
+        self
+    }
+
+    // a position between the outermost usable ticks of the fee tier
+    pub(crate) fn full_range(mut self, liquidity: i128) -> Self {
+        self.positions.push((None, liquidity));
+        self
+    }
+
+    pub(crate) fn positions(self, ranges: &[(i32, i32, i128)]) -> Self {
+        ranges
+            .iter()
+            .fold(self, |builder, &(tick_lower, tick_upper, liquidity)| {
+                builder.position(tick_lower, tick_upper, liquidity)
+            })
+    }
+
+    pub(crate) fn build(self) -> PoolSnapshot {
+        let mut pool = PoolSnapshot::new(self.sqrt_price_x_96, self.fee_tier).unwrap();
+        let tick_spacing = self.fee_tier.tick_spacing();
+        let full_range = MAX_TICK / tick_spacing * tick_spacing;
+        for (range, liquidity) in self.positions {
+            let (tick_lower, tick_upper) = range.unwrap_or((-full_range, full_range));
+            pool.modify_position(tick_lower, tick_upper, liquidity)
+                .unwrap();
+        }
+        pool
+    }
+}
+
+// an exact input swap without a price limit
+pub(crate) fn swap(zero_for_one: bool, amount_in: u128) -> Event {
+    Event::Swap(
+        SwapParams::builder()
+            .zero_for_one(zero_for_one)
+            .amount_in(U256::from(amount_in))
+            .build()
+            .unwrap(),
+    )
+}
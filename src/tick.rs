@@ -1,5 +1,15 @@
 use alloy_primitives::U256;
 
+use crate::{
+    error::UniswapV3MathError,
+    liquidity_math::add_delta,
+    overflow::checked_fee_growth_sub,
+    storage::Storage,
+    tick_math::{MAX_TICK, MIN_TICK},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tick {
     pub liquidity_gross: u128,
     pub liquidity_net: i128,
@@ -10,3 +20,469 @@ pub struct Tick {
     pub seconds_outside: u32,
     pub initialized: bool,
 }
+
+// derives max liquidity per tick from given tick spacing, executed within the pool constructor
+pub fn tick_spacing_to_max_liquidity_per_tick(
+    tick_spacing: i32,
+) -> Result<u128, UniswapV3MathError> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3MathError::TickSpacingError);
+    }
+
+    let min_tick = (MIN_TICK / tick_spacing) * tick_spacing;
+    let max_tick = (MAX_TICK / tick_spacing) * tick_spacing;
+    let num_ticks = ((max_tick - min_tick) / tick_spacing) as u128 + 1;
+
+    Ok(u128::MAX / num_ticks)
+}
+
+// returns (uint256 feeGrowthInside0X128, uint256 feeGrowthInside1X128), the all-time fee growth per unit of liquidity
+// inside the position's tick boundaries. Uninitialized ticks are treated as having zero fee growth outside. The
+// subtractions wrap like the contract, so the result matches on chain values even after fee growth overflowed
+pub fn get_fee_growth_inside(
+    ticks: &impl Storage<i32, Tick>,
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_current: i32,
+    fee_growth_global_0_x_128: U256,
+    fee_growth_global_1_x_128: U256,
+) -> (U256, U256) {
+    let lower = ticks.get(&tick_lower).unwrap_or_default();
+    let upper = ticks.get(&tick_upper).unwrap_or_default();
+
+    // calculate fee growth below
+    let (fee_growth_below_0_x_128, fee_growth_below_1_x_128) = if tick_current >= tick_lower {
+        (
+            lower.fee_growth_outside_0_x_128,
+            lower.fee_growth_outside_1_x_128,
+        )
+    } else {
+        (
+            fee_growth_global_0_x_128.wrapping_sub(lower.fee_growth_outside_0_x_128),
+            fee_growth_global_1_x_128.wrapping_sub(lower.fee_growth_outside_1_x_128),
+        )
+    };
+
+    // calculate fee growth above
+    let (fee_growth_above_0_x_128, fee_growth_above_1_x_128) = if tick_current < tick_upper {
+        (
+            upper.fee_growth_outside_0_x_128,
+            upper.fee_growth_outside_1_x_128,
+        )
+    } else {
+        (
+            fee_growth_global_0_x_128.wrapping_sub(upper.fee_growth_outside_0_x_128),
+            fee_growth_global_1_x_128.wrapping_sub(upper.fee_growth_outside_1_x_128),
+        )
+    };
+
+    (
+        fee_growth_global_0_x_128
+            .wrapping_sub(fee_growth_below_0_x_128)
+            .wrapping_sub(fee_growth_above_0_x_128),
+        fee_growth_global_1_x_128
+            .wrapping_sub(fee_growth_below_1_x_128)
+            .wrapping_sub(fee_growth_above_1_x_128),
+    )
+}
+
+//Updates a tick and returns true if the tick was flipped from initialized to uninitialized, or vice versa.
+//The max liquidity check is only enforced when `max_liquidity` is set, which allows replaying state from
+//pools whose max liquidity per tick is unknown
+#[allow(clippy::too_many_arguments)]
+pub fn update(
+    ticks: &mut impl Storage<i32, Tick>,
+    tick: i32,
+    tick_current: i32,
+    liquidity_delta: i128,
+    fee_growth_global_0_x_128: U256,
+    fee_growth_global_1_x_128: U256,
+    seconds_per_liquidity_cumulative_x_128: U256,
+    tick_cumulative: U256,
+    time: u32,
+    upper: bool,
+    max_liquidity: Option<u128>,
+) -> Result<bool, UniswapV3MathError> {
+    let mut info = ticks.get(&tick).unwrap_or_default();
+
+    let liquidity_gross_before = info.liquidity_gross;
+    let liquidity_gross_after = add_delta(liquidity_gross_before, liquidity_delta)?;
+
+    if let Some(max_liquidity) = max_liquidity {
+        if liquidity_gross_after > max_liquidity {
+            return Err(UniswapV3MathError::LiquidityOverflow);
+        }
+    }
+
+    let liquidity_net = if upper {
+        info.liquidity_net.checked_sub(liquidity_delta)
+    } else {
+        info.liquidity_net.checked_add(liquidity_delta)
+    }
+    .ok_or(UniswapV3MathError::LiquidityNetOverflow)?;
+
+    let flipped = (liquidity_gross_after == 0) != (liquidity_gross_before == 0);
+
+    if liquidity_gross_before == 0 {
+        // by convention, we assume that all growth before a tick was initialized happened _below_ the tick
+        if tick <= tick_current {
+            info.fee_growth_outside_0_x_128 = fee_growth_global_0_x_128;
+            info.fee_growth_outside_1_x_128 = fee_growth_global_1_x_128;
+            info.seconds_per_liquidity_outside_x_128 = seconds_per_liquidity_cumulative_x_128;
+            info.tick_cumulative_outside = tick_cumulative;
+            info.seconds_outside = time;
+        }
+        info.initialized = true;
+    }
+
+    info.liquidity_gross = liquidity_gross_after;
+    // when the lower (upper) tick is crossed left to right (right to left), liquidity must be added (removed)
+    info.liquidity_net = liquidity_net;
+    ticks.insert(tick, info);
+
+    Ok(flipped)
+}
+
+// same as get_fee_growth_inside, but fails instead of wrapping if any subtraction underflows, e.g. because the fee
+// growth outside of a tick was not initialized consistently with the global fee growth
+pub fn checked_get_fee_growth_inside(
+    ticks: &impl Storage<i32, Tick>,
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_current: i32,
+    fee_growth_global_0_x_128: U256,
+    fee_growth_global_1_x_128: U256,
+) -> Result<(U256, U256), UniswapV3MathError> {
+    let lower = ticks.get(&tick_lower).unwrap_or_default();
+    let upper = ticks.get(&tick_upper).unwrap_or_default();
+
+    let fee_growth_inside = |fee_growth_global: U256,
+                             lower_outside: U256,
+                             upper_outside: U256|
+     -> Result<U256, UniswapV3MathError> {
+        let fee_growth_below = if tick_current >= tick_lower {
+            lower_outside
+        } else {
+            checked_fee_growth_sub(fee_growth_global, lower_outside)?
+        };
+        let fee_growth_above = if tick_current < tick_upper {
+            upper_outside
+        } else {
+            checked_fee_growth_sub(fee_growth_global, upper_outside)?
+        };
+
+        checked_fee_growth_sub(
+            checked_fee_growth_sub(fee_growth_global, fee_growth_below)?,
+            fee_growth_above,
+        )
+    };
+
+    Ok((
+        fee_growth_inside(
+            fee_growth_global_0_x_128,
+            lower.fee_growth_outside_0_x_128,
+            upper.fee_growth_outside_0_x_128,
+        )?,
+        fee_growth_inside(
+            fee_growth_global_1_x_128,
+            lower.fee_growth_outside_1_x_128,
+            upper.fee_growth_outside_1_x_128,
+        )?,
+    ))
+}
+
+//Clears tick data
+pub fn clear(ticks: &mut impl Storage<i32, Tick>, tick: i32) {
+    ticks.remove(&tick);
+}
+
+//Transitions to next tick as needed by price movement, returns the liquidity net of the crossed tick.
+//Only the fee growth outside values are flipped, the oracle outside values are left untouched. The flip wraps like
+//the contract
+pub fn cross(
+    ticks: &mut impl Storage<i32, Tick>,
+    tick: i32,
+    fee_growth_global_0_x_128: U256,
+    fee_growth_global_1_x_128: U256,
+) -> i128 {
+    let mut info = ticks.get(&tick).unwrap_or_default();
+    info.fee_growth_outside_0_x_128 =
+        fee_growth_global_0_x_128.wrapping_sub(info.fee_growth_outside_0_x_128);
+    info.fee_growth_outside_1_x_128 =
+        fee_growth_global_1_x_128.wrapping_sub(info.fee_growth_outside_1_x_128);
+    ticks.insert(tick, info);
+    info.liquidity_net
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{
+        checked_get_fee_growth_inside, clear, cross, get_fee_growth_inside,
+        tick_spacing_to_max_liquidity_per_tick, update, Tick,
+    };
+    use crate::{error::UniswapV3MathError, tick_math::MAX_TICK};
+    use alloy_primitives::U256;
+
+    #[allow(clippy::too_many_arguments)]
+    fn update_simple(
+        ticks: &mut HashMap<i32, Tick>,
+        tick: i32,
+        tick_current: i32,
+        liquidity_delta: i128,
+        fee_growth_global_0_x_128: u64,
+        fee_growth_global_1_x_128: u64,
+        upper: bool,
+        max_liquidity: Option<u128>,
+    ) -> Result<bool, crate::error::UniswapV3MathError> {
+        update(
+            ticks,
+            tick,
+            tick_current,
+            liquidity_delta,
+            U256::from(fee_growth_global_0_x_128),
+            U256::from(fee_growth_global_1_x_128),
+            U256::ZERO,
+            U256::ZERO,
+            0,
+            upper,
+            max_liquidity,
+        )
+    }
+
+    #[test]
+    fn test_tick_spacing_to_max_liquidity_per_tick() {
+        //returns the correct value for low fee
+        let result = tick_spacing_to_max_liquidity_per_tick(10).unwrap();
+        assert_eq!(result, 1917569901783203986719870431555990);
+
+        //returns the correct value for medium fee
+        let result = tick_spacing_to_max_liquidity_per_tick(60).unwrap();
+        assert_eq!(result, 11505743598341114571880798222544994);
+
+        //returns the correct value for high fee
+        let result = tick_spacing_to_max_liquidity_per_tick(200).unwrap();
+        assert_eq!(result, 38350317471085141830651933667504588);
+
+        //returns the correct value for entire range
+        let result = tick_spacing_to_max_liquidity_per_tick(MAX_TICK).unwrap();
+        assert_eq!(result, u128::MAX / 3);
+
+        //returns the correct value for 2302
+        let result = tick_spacing_to_max_liquidity_per_tick(2302).unwrap();
+        assert_eq!(result, 441351967472034323558203122479595605);
+
+        //fails for a non positive tick spacing
+        assert!(tick_spacing_to_max_liquidity_per_tick(0).is_err());
+    }
+
+    #[test]
+    fn test_update() -> eyre::Result<()> {
+        //flips from zero to nonzero
+        let mut ticks = HashMap::new();
+        assert!(update_simple(&mut ticks, 0, 0, 1, 0, 0, false, Some(3))?);
+
+        //does not flip from nonzero to greater nonzero
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 0, 0, 1, 0, 0, false, Some(3))?;
+        assert!(!update_simple(&mut ticks, 0, 0, 1, 0, 0, false, Some(3))?);
+
+        //flips from nonzero to zero
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 0, 0, 1, 0, 0, false, Some(3))?;
+        assert!(update_simple(&mut ticks, 0, 0, -1, 0, 0, false, Some(3))?);
+
+        //does not flip from nonzero to lesser nonzero
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 0, 0, 2, 0, 0, false, Some(3))?;
+        assert!(!update_simple(&mut ticks, 0, 0, -1, 0, 0, false, Some(3))?);
+
+        //reverts if total liquidity gross is greater than max
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 0, 0, 2, 0, 0, false, Some(3))?;
+        update_simple(&mut ticks, 0, 0, 1, 0, 0, true, Some(3))?;
+        let result = update_simple(&mut ticks, 0, 0, 1, 0, 0, false, Some(3));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Liquidity gross exceeds the max liquidity per tick"
+        );
+
+        //does not enforce the max liquidity when it is not set
+        assert!(!update_simple(&mut ticks, 0, 0, 1, 0, 0, false, None)?);
+        assert_eq!(ticks[&0].liquidity_gross, 4);
+
+        //nets the liquidity based on upper flag
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 0, 0, 2, 0, 0, false, Some(10))?;
+        update_simple(&mut ticks, 0, 0, 1, 0, 0, true, Some(10))?;
+        update_simple(&mut ticks, 0, 0, 3, 0, 0, true, Some(10))?;
+        update_simple(&mut ticks, 0, 0, 1, 0, 0, false, Some(10))?;
+        assert_eq!(ticks[&0].liquidity_gross, 2 + 1 + 3 + 1);
+        assert_eq!(ticks[&0].liquidity_net, 2 - 1 - 3 + 1);
+
+        //reverts on overflow liquidity gross
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 0, 0, i128::MAX, 0, 0, false, None)?;
+        update_simple(&mut ticks, 0, 0, i128::MAX, 0, 0, true, None)?;
+        let result = update_simple(&mut ticks, 0, 0, i128::MAX, 0, 0, false, None);
+        assert_eq!(result.unwrap_err().to_string(), "Liquidity Add");
+
+        //fails on overflow liquidity net, distinct from the max liquidity per tick
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 0, 0, i128::MAX, 0, 0, false, None)?;
+        let result = update_simple(&mut ticks, 0, 0, 1, 0, 0, false, None);
+        assert!(matches!(
+            result,
+            Err(UniswapV3MathError::LiquidityNetOverflow)
+        ));
+
+        //assumes all growth happens below ticks lte current tick
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 1, 1, 1, 1, 2, false, None)?;
+        assert_eq!(ticks[&1].fee_growth_outside_0_x_128, U256::from(1));
+        assert_eq!(ticks[&1].fee_growth_outside_1_x_128, U256::from(2));
+        assert!(ticks[&1].initialized);
+
+        //does not set any growth fields if tick is already initialized
+        update_simple(&mut ticks, 1, 1, 1, 6, 7, false, None)?;
+        assert_eq!(ticks[&1].fee_growth_outside_0_x_128, U256::from(1));
+        assert_eq!(ticks[&1].fee_growth_outside_1_x_128, U256::from(2));
+
+        //does not set any growth fields for ticks gt current tick
+        let mut ticks = HashMap::new();
+        update_simple(&mut ticks, 2, 1, 1, 1, 2, false, None)?;
+        assert_eq!(ticks[&2].fee_growth_outside_0_x_128, U256::ZERO);
+        assert_eq!(ticks[&2].fee_growth_outside_1_x_128, U256::ZERO);
+
+        //clear deletes all the data in the tick
+        clear(&mut ticks, 2);
+        assert!(!ticks.contains_key(&2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cross() {
+        //flips the growth variables
+        let mut ticks = HashMap::new();
+        ticks.insert(
+            2,
+            Tick {
+                liquidity_gross: 3,
+                liquidity_net: 4,
+                fee_growth_outside_0_x_128: U256::from(1),
+                fee_growth_outside_1_x_128: U256::from(2),
+                initialized: true,
+                ..Default::default()
+            },
+        );
+        let liquidity_net = cross(&mut ticks, 2, U256::from(7), U256::from(9));
+        assert_eq!(liquidity_net, 4);
+        assert_eq!(ticks[&2].fee_growth_outside_0_x_128, U256::from(6));
+        assert_eq!(ticks[&2].fee_growth_outside_1_x_128, U256::from(7));
+
+        //two flips are no op
+        cross(&mut ticks, 2, U256::from(7), U256::from(9));
+        assert_eq!(ticks[&2].fee_growth_outside_0_x_128, U256::from(1));
+        assert_eq!(ticks[&2].fee_growth_outside_1_x_128, U256::from(2));
+
+        //wraps like solidity on underflow
+        cross(&mut ticks, 2, U256::ZERO, U256::ZERO);
+        assert_eq!(ticks[&2].fee_growth_outside_0_x_128, U256::MAX);
+        assert_eq!(
+            ticks[&2].fee_growth_outside_1_x_128,
+            U256::MAX - U256::from(1)
+        );
+    }
+
+    #[test]
+    fn test_get_fee_growth_inside() {
+        //returns all for two uninitialized ticks if tick is inside
+        let ticks = HashMap::new();
+        let result = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(result, (U256::from(15), U256::from(15)));
+
+        //returns 0 for two uninitialized ticks if tick is above
+        let result = get_fee_growth_inside(&ticks, -2, 2, 4, U256::from(15), U256::from(15));
+        assert_eq!(result, (U256::ZERO, U256::ZERO));
+
+        //returns 0 for two uninitialized ticks if tick is below
+        let result = get_fee_growth_inside(&ticks, -2, 2, -4, U256::from(15), U256::from(15));
+        assert_eq!(result, (U256::ZERO, U256::ZERO));
+
+        //subtracts upper tick if below
+        let mut ticks = HashMap::new();
+        ticks.insert(
+            2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::from(2),
+                fee_growth_outside_1_x_128: U256::from(3),
+                liquidity_gross: 0,
+                liquidity_net: 0,
+                ..Default::default()
+            },
+        );
+        let result = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(result, (U256::from(13), U256::from(12)));
+
+        //subtracts lower tick if above
+        let mut ticks = HashMap::new();
+        ticks.insert(
+            -2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::from(2),
+                fee_growth_outside_1_x_128: U256::from(3),
+                ..Default::default()
+            },
+        );
+        let result = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(result, (U256::from(13), U256::from(12)));
+
+        //subtracts upper and lower tick if inside
+        ticks.insert(
+            2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::from(4),
+                fee_growth_outside_1_x_128: U256::from(1),
+                ..Default::default()
+            },
+        );
+        let result = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(result, (U256::from(9), U256::from(11)));
+
+        //works correctly with overflow on inside tick
+        let mut ticks = HashMap::new();
+        ticks.insert(
+            -2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::MAX - U256::from(3),
+                fee_growth_outside_1_x_128: U256::MAX - U256::from(2),
+                ..Default::default()
+            },
+        );
+        ticks.insert(
+            2,
+            Tick {
+                fee_growth_outside_0_x_128: U256::from(3),
+                fee_growth_outside_1_x_128: U256::from(5),
+                ..Default::default()
+            },
+        );
+        let result = get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15));
+        assert_eq!(result, (U256::from(16), U256::from(13)));
+
+        //fails if the contract would have wrapped
+        assert!(
+            checked_get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15))
+                .is_err()
+        );
+
+        //matches the wrapping result without overflow
+        ticks.remove(&-2);
+        assert_eq!(
+            checked_get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15))
+                .unwrap(),
+            get_fee_growth_inside(&ticks, -2, 2, 0, U256::from(15), U256::from(15))
+        );
+    }
+}
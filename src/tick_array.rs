@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+
+use alloy_primitives::U256;
+
+use crate::{
+    storage::{Storage, TickStorage},
+    tick::Tick,
+};
+
+// the fields of a tick that are not read while swapping
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct TickOutside {
+    liquidity_gross: u128,
+    fee_growth_outside_0_x_128: U256,
+    fee_growth_outside_1_x_128: U256,
+    tick_cumulative_outside: U256,
+    seconds_per_liquidity_outside_x_128: U256,
+    seconds_outside: u32,
+}
+
+impl From<Tick> for TickOutside {
+    fn from(info: Tick) -> Self {
+        Self {
+            liquidity_gross: info.liquidity_gross,
+            fee_growth_outside_0_x_128: info.fee_growth_outside_0_x_128,
+            fee_growth_outside_1_x_128: info.fee_growth_outside_1_x_128,
+            tick_cumulative_outside: info.tick_cumulative_outside,
+            seconds_per_liquidity_outside_x_128: info.seconds_per_liquidity_outside_x_128,
+            seconds_outside: info.seconds_outside,
+        }
+    }
+}
+
+//Tick storage that keeps the ticks sorted in dense vectors, with the liquidity net and initialized flag of each tick
+//stored apart from the rest of its fields. Crossing a tick during a swap only reads the tick and liquidity net vectors,
+//while inserting or removing a tick shifts the vectors, which makes it suited for pools that are quoted far more often
+//than their positions change
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TickArray {
+    ticks: Vec<i32>,
+    liquidity_net: Vec<i128>,
+    initialized: Vec<bool>,
+    outside: Vec<TickOutside>,
+}
+
+impl TickArray {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    // returns the ticks in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = (i32, Tick)> + '_ {
+        (0..self.ticks.len()).map(|index| (self.ticks[index], self.tick_at(index)))
+    }
+
+    // returns the next initialized tick strictly above `tick`, or at or below it if `lte`, without a tick bitmap
+    pub fn next_initialized_tick(&self, tick: i32, lte: bool) -> Option<i32> {
+        if lte {
+            let end = self.ticks.partition_point(|&next| next <= tick);
+            (0..end)
+                .rev()
+                .find(|&index| self.initialized[index])
+                .map(|index| self.ticks[index])
+        } else {
+            let start = self.ticks.partition_point(|&next| next <= tick);
+            (start..self.ticks.len())
+                .find(|&index| self.initialized[index])
+                .map(|index| self.ticks[index])
+        }
+    }
+
+    fn tick_at(&self, index: usize) -> Tick {
+        let outside = self.outside[index];
+        Tick {
+            liquidity_gross: outside.liquidity_gross,
+            liquidity_net: self.liquidity_net[index],
+            fee_growth_outside_0_x_128: outside.fee_growth_outside_0_x_128,
+            fee_growth_outside_1_x_128: outside.fee_growth_outside_1_x_128,
+            tick_cumulative_outside: outside.tick_cumulative_outside,
+            seconds_per_liquidity_outside_x_128: outside.seconds_per_liquidity_outside_x_128,
+            seconds_outside: outside.seconds_outside,
+            initialized: self.initialized[index],
+        }
+    }
+}
+
+impl Storage<i32, Tick> for TickArray {
+    fn get(&self, key: &i32) -> Option<Tick> {
+        let index = self.ticks.binary_search(key).ok()?;
+        Some(self.tick_at(index))
+    }
+
+    fn insert(&mut self, key: i32, value: Tick) -> Option<Tick> {
+        let outside = TickOutside::from(value);
+
+        match self.ticks.binary_search(&key) {
+            Ok(index) => {
+                let previous = self.tick_at(index);
+                self.liquidity_net[index] = value.liquidity_net;
+                self.initialized[index] = value.initialized;
+                self.outside[index] = outside;
+                Some(previous)
+            }
+            Err(index) => {
+                self.ticks.insert(index, key);
+                self.liquidity_net.insert(index, value.liquidity_net);
+                self.initialized.insert(index, value.initialized);
+                self.outside.insert(index, outside);
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &i32) -> Option<Tick> {
+        let index = self.ticks.binary_search(key).ok()?;
+        let previous = self.tick_at(index);
+
+        self.ticks.remove(index);
+        self.liquidity_net.remove(index);
+        self.initialized.remove(index);
+        self.outside.remove(index);
+
+        Some(previous)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = i32> + '_ {
+        self.ticks.iter().copied()
+    }
+}
+
+impl TickStorage for TickArray {
+    fn liquidity_net(&self, tick: i32) -> i128 {
+        self.ticks
+            .binary_search(&tick)
+            .map_or(0, |index| self.liquidity_net[index])
+    }
+}
+
+impl FromIterator<(i32, Tick)> for TickArray {
+    fn from_iter<I: IntoIterator<Item = (i32, Tick)>>(iter: I) -> Self {
+        let ticks: BTreeMap<i32, Tick> = iter.into_iter().collect();
+
+        let mut array = Self::new();
+        for (tick, info) in ticks {
+            array.ticks.push(tick);
+            array.liquidity_net.push(info.liquidity_net);
+            array.initialized.push(info.initialized);
+            array.outside.push(TickOutside::from(info));
+        }
+
+        array
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::TickArray;
+    use crate::{
+        pool::PoolSnapshot,
+        storage::{Storage, TickStorage},
+        test_utils::PoolBuilder,
+        tick::Tick,
+        tick_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    };
+
+    fn pool_at(tick_current: i32) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .full_range(1e20 as i128)
+            .position(-600, 600, 1e21 as i128)
+            .position(-1200, -60, 1e19 as i128)
+            .build()
+    }
+
+    #[test]
+    fn test_tick_array() {
+        let mut array = TickArray::new();
+        let tick = Tick {
+            liquidity_gross: 10,
+            liquidity_net: -10,
+            initialized: true,
+            ..Default::default()
+        };
+
+        //ticks are kept sorted regardless of insertion order
+        for key in [60, -120, 0] {
+            assert_eq!(array.insert(key, tick), None);
+        }
+        let keys: Vec<_> = array.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec![-120, 0, 60]);
+        assert_eq!(array.get(&0), Some(tick));
+        assert_eq!(array.liquidity_net(0), -10);
+        assert_eq!(array.liquidity_net(1), 0);
+
+        //overwriting returns the previous tick
+        let updated = Tick {
+            liquidity_net: 5,
+            ..tick
+        };
+        assert_eq!(array.insert(0, updated), Some(tick));
+        assert_eq!(array.liquidity_net(0), 5);
+
+        assert_eq!(array.next_initialized_tick(0, true), Some(0));
+        assert_eq!(array.next_initialized_tick(0, false), Some(60));
+        assert_eq!(array.next_initialized_tick(-121, true), None);
+        assert_eq!(array.next_initialized_tick(60, false), None);
+
+        assert_eq!(array.remove(&0), Some(updated));
+        assert_eq!(array.remove(&0), None);
+        assert_eq!(array.len(), 2);
+        assert_eq!(array.next_initialized_tick(30, true), Some(-120));
+    }
+
+    #[test]
+    fn test_tick_array_pool() {
+        let mut pool = pool_at(0);
+        let mut array_pool = PoolSnapshot::<TickArray>::from(pool.clone());
+
+        //swaps and position updates match the HashMap backed pool
+        let amount = I256::from_raw(U256::from(1e21 as u128));
+        for (zero_for_one, limit) in [
+            (true, MIN_SQRT_RATIO + U256::from(1)),
+            (false, MAX_SQRT_RATIO - U256::from(1)),
+        ] {
+            assert_eq!(
+                array_pool.swap(zero_for_one, amount, limit).unwrap(),
+                pool.swap(zero_for_one, amount, limit).unwrap()
+            );
+        }
+        assert_eq!(
+            array_pool
+                .modify_position(-600, 600, -(1e21 as i128))
+                .unwrap(),
+            pool.modify_position(-600, 600, -(1e21 as i128)).unwrap()
+        );
+
+        let ticks: Vec<_> = array_pool.ticks.iter().collect();
+        let mut expected: Vec<_> = pool.ticks.into_iter().collect();
+        expected.sort_by_key(|&(tick, _)| tick);
+        assert_eq!(ticks, expected);
+    }
+}
@@ -1,7 +1,11 @@
 use crate::U256_1;
-use crate::{bit_math, error::UniswapV3MathError};
+use crate::{
+    bit_math,
+    error::UniswapV3MathError,
+    storage::Storage,
+    tick_math::{MAX_TICK, MIN_TICK},
+};
 use alloy_primitives::U256;
-use std::collections::HashMap;
 
 #[cfg(feature = "contract")]
 pub use contract::*;
@@ -126,7 +130,7 @@ mod contract {
 
 //Flips the initialized state for a given tick from false to true, or vice versa
 pub fn flip_tick(
-    tick_bitmap: &mut HashMap<i16, U256>,
+    tick_bitmap: &mut impl Storage<i16, U256>,
     tick: i32,
     tick_spacing: i32,
 ) -> Result<(), UniswapV3MathError> {
@@ -136,7 +140,7 @@ pub fn flip_tick(
 
     let (word_pos, bit_pos) = position(tick / tick_spacing);
     let mask = U256_1 << bit_pos;
-    let word = *tick_bitmap.get(&word_pos).unwrap_or(&U256::ZERO);
+    let word = tick_bitmap.get(&word_pos).unwrap_or_default();
     tick_bitmap.insert(word_pos, word ^ mask);
     Ok(())
 }
@@ -144,7 +148,7 @@ pub fn flip_tick(
 //Returns the next initialized tick contained in the same word (or adjacent word) as the tick that is either
 //to the left (less than or equal to) or right (greater than) of the given tick
 pub fn next_initialized_tick_within_one_word(
-    tick_bitmap: &HashMap<i16, U256>,
+    tick_bitmap: &impl Storage<i16, U256>,
     tick: i32,
     tick_spacing: i32,
     lte: bool,
@@ -160,7 +164,7 @@ pub fn next_initialized_tick_within_one_word(
 
         let mask = (U256_1 << bit_pos) - U256_1 + (U256_1 << bit_pos);
 
-        let masked = *tick_bitmap.get(&word_pos).unwrap_or(&U256::ZERO) & mask;
+        let masked = tick_bitmap.get(&word_pos).unwrap_or_default() & mask;
 
         let initialized = !masked.is_zero();
 
@@ -180,7 +184,7 @@ pub fn next_initialized_tick_within_one_word(
 
         let mask = !((U256_1 << bit_pos) - U256_1);
 
-        let masked = *tick_bitmap.get(&word_pos).unwrap_or(&U256::ZERO) & mask;
+        let masked = tick_bitmap.get(&word_pos).unwrap_or_default() & mask;
 
         let initialized = !masked.is_zero();
 
@@ -199,6 +203,136 @@ pub fn next_initialized_tick_within_one_word(
     }
 }
 
+//Returns the positions of the next `count` bitmap words a walk from `tick` reads, in the order it reads them, starting
+//with the word `next_initialized_tick_within_one_word` reads first. A walk to the left (`lte`) reads descending words
+//and a walk to the right ascending words, stopping at the words of the min and max tick. Lets providers that read the
+//bitmap over RPC fetch the words of a swap in a single batch instead of one round trip per word
+pub fn words_for_range(
+    tick: i32,
+    tick_spacing: i32,
+    lte: bool,
+    count: usize,
+) -> Result<Vec<i16>, UniswapV3MathError> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3MathError::TickSpacingError);
+    }
+
+    let compressed = tick.div_euclid(tick_spacing);
+    let (min_word, max_word) = (
+        MIN_TICK.div_euclid(tick_spacing) >> 8,
+        MAX_TICK.div_euclid(tick_spacing) >> 8,
+    );
+
+    let words: Vec<i16> = if lte {
+        let start = (compressed >> 8).min(max_word);
+        (min_word..=start)
+            .rev()
+            .take(count)
+            .map(|word_pos| word_pos as i16)
+            .collect()
+    } else {
+        let start = ((compressed + 1) >> 8).max(min_word);
+        (start..=max_word)
+            .take(count)
+            .map(|word_pos| word_pos as i16)
+            .collect()
+    };
+
+    Ok(words)
+}
+
+//Returns the initialized ticks between `from_tick` and `to_tick`, both inclusive, in the order of walking from one to
+//the other, so descending if `to_tick` is below `from_tick`. Each bitmap word is read once, independent of how many of
+//its ticks are initialized
+pub fn initialized_ticks_in_range<S: Storage<i16, U256>>(
+    tick_bitmap: &S,
+    from_tick: i32,
+    to_tick: i32,
+    tick_spacing: i32,
+) -> Result<InitializedTicksInRange<'_, S>, UniswapV3MathError> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3MathError::TickSpacingError);
+    }
+
+    // the compressed ticks that are multiples of the spacing within the range
+    let lower = -(-from_tick.min(to_tick)).div_euclid(tick_spacing);
+    let upper = from_tick.max(to_tick).div_euclid(tick_spacing);
+    let ascending = to_tick >= from_tick;
+
+    let mut ticks = InitializedTicksInRange {
+        tick_bitmap,
+        tick_spacing,
+        ascending,
+        lower,
+        upper,
+        word_pos: if ascending { lower >> 8 } else { upper >> 8 },
+        word: U256::ZERO,
+    };
+    if lower <= upper {
+        ticks.load_word();
+    }
+
+    Ok(ticks)
+}
+
+//Iterator over initialized ticks, see `initialized_ticks_in_range`
+pub struct InitializedTicksInRange<'a, S> {
+    tick_bitmap: &'a S,
+    tick_spacing: i32,
+    ascending: bool,
+    // the compressed bounds of the range
+    lower: i32,
+    upper: i32,
+    word_pos: i32,
+    // the initialized bits of the current word within the range that have not been yielded yet
+    word: U256,
+}
+
+impl<S: Storage<i16, U256>> InitializedTicksInRange<'_, S> {
+    fn load_word(&mut self) {
+        let word_start = self.word_pos << 8;
+        let low_bit = (self.lower - word_start).max(0) as usize;
+        let high_bit = (self.upper - word_start).min(255) as usize;
+        let mask = (U256::MAX >> (255 - high_bit)) & (U256::MAX << low_bit);
+
+        self.word = self
+            .tick_bitmap
+            .get(&(self.word_pos as i16))
+            .unwrap_or_default()
+            & mask;
+    }
+}
+
+impl<S: Storage<i16, U256>> Iterator for InitializedTicksInRange<'_, S> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<i32> {
+        while self.word.is_zero() {
+            if self.ascending {
+                if self.word_pos >= self.upper >> 8 {
+                    return None;
+                }
+                self.word_pos += 1;
+            } else {
+                if self.word_pos <= self.lower >> 8 {
+                    return None;
+                }
+                self.word_pos -= 1;
+            }
+            self.load_word();
+        }
+
+        let bit_pos = if self.ascending {
+            self.word.trailing_zeros()
+        } else {
+            255 - self.word.leading_zeros()
+        };
+        self.word ^= U256_1 << bit_pos;
+
+        Some(((self.word_pos << 8) + bit_pos as i32) * self.tick_spacing)
+    }
+}
+
 //Computes the position in the mapping where the initialized bit for a tick lives
 pub fn position(tick: i32) -> (i16, u8) {
     ((tick >> 8) as i16, (tick % 256) as u8)
@@ -206,7 +340,10 @@ pub fn position(tick: i32) -> (i16, u8) {
 
 #[cfg(test)]
 mod test {
-    use super::{flip_tick, next_initialized_tick_within_one_word};
+    use super::{
+        flip_tick, initialized_ticks_in_range, next_initialized_tick_within_one_word,
+        words_for_range,
+    };
     use alloy_primitives::U256;
     use std::{collections::HashMap, vec};
 
@@ -439,4 +576,77 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    pub fn test_initialized_ticks_in_range() -> eyre::Result<()> {
+        let tick_bitmap = init_test_ticks()?;
+
+        let ticks: Vec<i32> = initialized_ticks_in_range(&tick_bitmap, -60, 100, 1)?.collect();
+        assert_eq!(ticks, vec![-55, -4, 70, 78, 84]);
+        //walks downwards if the range is reversed
+        let ticks: Vec<i32> = initialized_ticks_in_range(&tick_bitmap, 100, -60, 1)?.collect();
+        assert_eq!(ticks, vec![84, 78, 70, -4, -55]);
+        //the bounds are inclusive
+        let ticks: Vec<i32> = initialized_ticks_in_range(&tick_bitmap, -55, 70, 1)?.collect();
+        assert_eq!(ticks, vec![-55, -4, 70]);
+        //across several words
+        let ticks: Vec<i32> = initialized_ticks_in_range(&tick_bitmap, -1000, 1000, 1)?.collect();
+        assert_eq!(ticks, vec![-200, -55, -4, 70, 78, 84, 139, 240, 535]);
+        assert_eq!(
+            initialized_ticks_in_range(&tick_bitmap, 85, 138, 1)?.count(),
+            0
+        );
+        assert_eq!(
+            initialized_ticks_in_range(&tick_bitmap, 70, 70, 1)?.count(),
+            1
+        );
+
+        //ticks are scaled by the spacing, bounds between multiples of the spacing are rounded into the range
+        let mut tick_bitmap = HashMap::new();
+        for tick in [-887220, -15360, -60, 0, 15300, 887220] {
+            flip_tick(&mut tick_bitmap, tick, 60)?;
+        }
+        let ticks: Vec<i32> =
+            initialized_ticks_in_range(&tick_bitmap, -887272, 887272, 60)?.collect();
+        assert_eq!(ticks, vec![-887220, -15360, -60, 0, 15300, 887220]);
+        let ticks: Vec<i32> =
+            initialized_ticks_in_range(&tick_bitmap, 15359, -15419, 60)?.collect();
+        assert_eq!(ticks, vec![15300, 0, -60, -15360]);
+        let ticks: Vec<i32> = initialized_ticks_in_range(&tick_bitmap, -59, 59, 60)?.collect();
+        assert_eq!(ticks, vec![0]);
+
+        assert!(initialized_ticks_in_range(&tick_bitmap, 0, 60, 0).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_words_for_range() -> eyre::Result<()> {
+        assert_eq!(words_for_range(0, 1, true, 3)?, vec![0, -1, -2]);
+        assert_eq!(words_for_range(0, 1, false, 3)?, vec![0, 1, 2]);
+        //a walk to the right starts with the word of the next tick
+        assert_eq!(words_for_range(255, 1, false, 2)?, vec![1, 2]);
+        assert_eq!(words_for_range(255, 1, true, 2)?, vec![0, -1]);
+        assert_eq!(words_for_range(-1, 60, true, 1)?, vec![-1]);
+
+        //stops at the words of the tick bounds
+        assert_eq!(words_for_range(887220, 60, false, 10)?, vec![57]);
+        assert_eq!(words_for_range(-887220, 60, true, 10)?, vec![-58]);
+        assert!(words_for_range(0, 1, true, 0)?.is_empty());
+
+        //the words match the ones read by a walk
+        let tick_bitmap = HashMap::new();
+        for lte in [true, false] {
+            let mut words = vec![];
+            let mut tick = 1234;
+            for _ in 0..5 {
+                let (next, _) = next_initialized_tick_within_one_word(&tick_bitmap, tick, 10, lte)?;
+                words.push(((next / 10) >> 8) as i16);
+                tick = if lte { next - 1 } else { next };
+            }
+            assert_eq!(words_for_range(1234, 10, lte, 5)?, words);
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,205 @@
+use std::collections::BTreeSet;
+
+use crate::{error::UniswapV3MathError, storage::InitializedTicks};
+
+//A sorted set of initialized ticks that can be used in place of a tick bitmap, for data sources that provide the list
+//of initialized ticks rather than bitmap words
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TickIndex {
+    ticks: BTreeSet<i32>,
+}
+
+impl TickIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    pub fn contains(&self, tick: i32) -> bool {
+        self.ticks.contains(&tick)
+    }
+
+    // returns the initialized ticks in [tick_lower, tick_upper] in ascending order
+    pub fn range(
+        &self,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> impl DoubleEndedIterator<Item = i32> + '_ {
+        // an empty range would panic
+        let tick_upper = tick_upper.max(tick_lower);
+        self.ticks.range(tick_lower..=tick_upper).copied()
+    }
+
+    // returns the next initialized tick at or below `tick` if `lte`, or strictly above it otherwise, regardless of how
+    // far away it is
+    pub fn next_initialized_tick(&self, tick: i32, lte: bool) -> Option<i32> {
+        if lte {
+            self.ticks.range(..=tick).next_back().copied()
+        } else {
+            self.ticks.range(tick + 1..).next().copied()
+        }
+    }
+}
+
+impl InitializedTicks for TickIndex {
+    fn flip_tick(&mut self, tick: i32, tick_spacing: i32) -> Result<(), UniswapV3MathError> {
+        if (tick % tick_spacing) != 0 {
+            return Err(UniswapV3MathError::TickSpacingError);
+        }
+
+        if !self.ticks.remove(&tick) {
+            self.ticks.insert(tick);
+        }
+        Ok(())
+    }
+
+    // searches the ticks covered by the bitmap word of `tick`, so swaps step through the same prices as they do with a
+    // tick bitmap
+    fn next_initialized_tick_within_one_word(
+        &self,
+        tick: i32,
+        tick_spacing: i32,
+        lte: bool,
+    ) -> Result<(i32, bool), UniswapV3MathError> {
+        let compressed = if tick < 0 && tick % tick_spacing != 0 {
+            (tick / tick_spacing) - 1
+        } else {
+            tick / tick_spacing
+        };
+
+        if lte {
+            let word_start = (compressed >> 8) << 8;
+            let next = self
+                .range(word_start * tick_spacing, compressed * tick_spacing)
+                .next_back();
+
+            Ok(next.map_or((word_start * tick_spacing, false), |next| (next, true)))
+        } else {
+            let word_end = (((compressed + 1) >> 8) << 8) + 255;
+            let next = self
+                .range((compressed + 1) * tick_spacing, word_end * tick_spacing)
+                .next();
+
+            Ok(next.map_or((word_end * tick_spacing, false), |next| (next, true)))
+        }
+    }
+    fn initialized_ticks(&self, _tick_spacing: i32) -> impl Iterator<Item = i64> + '_ {
+        self.ticks.iter().map(|&tick| tick as i64)
+    }
+}
+
+impl FromIterator<i32> for TickIndex {
+    fn from_iter<I: IntoIterator<Item = i32>>(iter: I) -> Self {
+        Self {
+            ticks: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use alloy_primitives::{I256, U256};
+
+    use super::TickIndex;
+    use crate::{
+        pool::PoolSnapshot,
+        storage::InitializedTicks,
+        test_utils::PoolBuilder,
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    };
+
+    fn pool_at(tick_current: i32) -> PoolSnapshot {
+        PoolBuilder::at_tick(tick_current)
+            .full_range(1e20 as i128)
+            .position(-600, 600, 1e21 as i128)
+            .position(-30000, -15360, 1e19 as i128)
+            .build()
+    }
+
+    #[test]
+    fn test_next_initialized_tick() {
+        let index: TickIndex = [-200, 70, 78, 84, 139, 240, 535].into_iter().collect();
+
+        assert_eq!(index.next_initialized_tick(78, true), Some(78));
+        assert_eq!(index.next_initialized_tick(78, false), Some(84));
+        assert_eq!(index.next_initialized_tick(-201, true), None);
+        assert_eq!(index.next_initialized_tick(535, false), None);
+        assert_eq!(index.next_initialized_tick(-1000, false), Some(-200));
+        assert_eq!(
+            index.range(70, 139).collect::<Vec<_>>(),
+            vec![70, 78, 84, 139]
+        );
+        assert_eq!(index.range(1, 0).count(), 0);
+
+        //searching within one word matches the tick bitmap
+        let mut tick_bitmap = HashMap::new();
+        let mut index = TickIndex::new();
+        for tick in [-15360, -600, -60, 0, 600, 887220, -887220] {
+            tick_bitmap.flip_tick(tick, 60).unwrap();
+            index.flip_tick(tick, 60).unwrap();
+        }
+        for tick in (-20000..20000).step_by(37) {
+            for lte in [true, false] {
+                assert_eq!(
+                    index
+                        .next_initialized_tick_within_one_word(tick, 60, lte)
+                        .unwrap(),
+                    tick_bitmap
+                        .next_initialized_tick_within_one_word(tick, 60, lte)
+                        .unwrap()
+                );
+            }
+        }
+
+        //fails if the tick is not a multiple of the tick spacing
+        assert!(index.flip_tick(61, 60).is_err());
+        index.flip_tick(600, 60).unwrap();
+        assert!(!index.contains(600));
+    }
+
+    #[test]
+    fn test_tick_index_pool() {
+        let mut pool = pool_at(0);
+        let mut index_pool = PoolSnapshot {
+            slot0: pool.slot0,
+            liquidity: pool.liquidity,
+            fee_tier: pool.fee_tier,
+            fee_growth_global_0_x_128: pool.fee_growth_global_0_x_128,
+            fee_growth_global_1_x_128: pool.fee_growth_global_1_x_128,
+            protocol_fees: pool.protocol_fees,
+            ticks: pool.ticks.clone(),
+            tick_bitmap: pool.ticks.keys().copied().collect::<TickIndex>(),
+            variant: pool.variant,
+        };
+
+        //swaps and position updates match the tick bitmap backed pool
+        let amount = I256::from_raw(U256::from(1e22 as u128));
+        for (zero_for_one, limit) in [
+            (true, MIN_SQRT_RATIO + U256::from(1)),
+            (false, MAX_SQRT_RATIO - U256::from(1)),
+            (true, get_sqrt_ratio_at_tick(-20000).unwrap()),
+        ] {
+            assert_eq!(
+                index_pool.swap(zero_for_one, amount, limit).unwrap(),
+                pool.swap(zero_for_one, amount, limit).unwrap()
+            );
+        }
+        assert_eq!(
+            index_pool
+                .modify_position(-600, 600, -(1e21 as i128))
+                .unwrap(),
+            pool.modify_position(-600, 600, -(1e21 as i128)).unwrap()
+        );
+        assert_eq!(index_pool.ticks, pool.ticks);
+        assert_eq!(index_pool.tick_bitmap.len(), 4);
+    }
+}
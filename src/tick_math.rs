@@ -1,19 +1,12 @@
 use alloy_primitives::{I256, U256};
-use std::ops::{BitOr, Neg, Shl, Shr};
+use std::ops::{BitOr, Shl, Shr};
 
 use crate::{
-    error::UniswapV3MathError, U256_1, U256_1024, U256_127, U256_128, U256_131072, U256_15,
-    U256_16, U256_16384, U256_2, U256_2048, U256_255, U256_256, U256_262144, U256_3, U256_32,
-    U256_32768, U256_4, U256_4096, U256_5, U256_512, U256_524288, U256_6, U256_64, U256_65536,
-    U256_7, U256_8, U256_8192, U256_MAX_TICK,
+    error::UniswapV3MathError, U256_1, U256_127, U256_128, U256_15, U256_2, U256_255, U256_3,
+    U256_4, U256_5, U256_6, U256_7,
 };
 
-pub const MIN_TICK: i32 = -887272;
-pub const MAX_TICK: i32 = -MIN_TICK;
-
-pub const MIN_SQRT_RATIO: U256 = U256::from_limbs([4295128739, 0, 0, 0]);
-pub const MAX_SQRT_RATIO: U256 =
-    U256::from_limbs([6743328256752651558, 17280870778742802505, 4294805859, 0]);
+pub use crate::constants::{MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK};
 
 pub const SQRT_10001: I256 = I256::from_raw(U256::from_limbs([11745905768312294533, 13863, 0, 0]));
 pub const TICK_LOW: I256 = I256::from_raw(U256::from_limbs([
@@ -29,95 +22,135 @@ pub const TICK_HIGH: I256 = I256::from_raw(U256::from_limbs([
     0,
 ]));
 
-pub fn get_sqrt_ratio_at_tick(tick: i32) -> Result<U256, UniswapV3MathError> {
-    let abs_tick = if tick < 0 {
-        U256::from(tick.neg())
-    } else {
-        U256::from(tick)
-    };
+// the factors the ratio is multiplied by for each set bit of the absolute tick, as Q128 numbers, mirrors the magic
+// numbers of TickMath.getSqrtRatioAtTick. All of them are below 2**128, so the ratio fits in two limbs until the
+// inversion for positive ticks
+const SQRT_RATIO_FACTORS: [u128; 20] = [
+    factor(12262481743371124737, 18445821805675392311),
+    factor(6459403834229662010, 18444899583751176498),
+    factor(17226890335427755468, 18443055278223354162),
+    factor(2032852871939366096, 18439367220385604838),
+    factor(14545316742740207172, 18431993317065449817),
+    factor(5129152022828963008, 18417254355718160513),
+    factor(4894419605888772193, 18387811781193591352),
+    factor(1280255884321894483, 18329067761203520168),
+    factor(15924666964335305636, 18212142134806087854),
+    factor(8010504389359918676, 17980523815641551639),
+    factor(10668036004952895731, 17526086738831147013),
+    factor(4878133418470705625, 16651378430235024244),
+    factor(9537173718739605541, 15030750278693429944),
+    factor(9972618978014552549, 12247334978882834399),
+    factor(10428997489610666743, 8131365268884726200),
+    factor(9305304367709015974, 3584323654723342297),
+    factor(14301143598189091785, 696457651847595233),
+    factor(7393154844743099908, 26294789957452057),
+    factor(2209338891292245656, 37481735321082),
+    factor(10518117631919034274, 76158723),
+];
+
+const fn factor(low: u64, high: u64) -> u128 {
+    (high as u128) << 64 | low as u128
+}
+
+// returns (a * b) >> 128 from the four 64 bit limb products, without widening to 256 bits
+const fn mul_shift_128(a: u128, b: u128) -> u128 {
+    let (a_0, a_1) = (a as u64 as u128, a >> 64);
+    let (b_0, b_1) = (b as u64 as u128, b >> 64);
+    let (p_00, p_01, p_10, p_11) = (a_0 * b_0, a_0 * b_1, a_1 * b_0, a_1 * b_1);
+
+    let middle = (p_00 >> 64) + (p_01 as u64 as u128) + (p_10 as u64 as u128);
+    p_11 + (p_01 >> 64) + (p_10 >> 64) + (middle >> 64)
+}
+
+// returns type(uint256).max / divisor as the high and low limbs of the quotient, by long division of the low limb
+const fn max_div(divisor: u128) -> (u128, u128) {
+    let high = u128::MAX / divisor;
+    let mut remainder = u128::MAX % divisor;
+    let mut low = 0;
+    let mut bit = 0;
+    while bit < 128 {
+        // the remainder is below the divisor, so it overflows at most into a 129th bit, in which case it exceeds the
+        // divisor and the wrapping subtraction is exact
+        let overflow = remainder >> 127 == 1;
+        remainder = remainder << 1 | 1;
+        low <<= 1;
+        if overflow || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            low |= 1;
+        }
+        bit += 1;
+    }
 
-    if abs_tick > U256_MAX_TICK {
+    (high, low)
+}
+
+// returns the sqrt ratio at `tick` as a Q64.96, mirrors TickMath.getSqrtRatioAtTick
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> Result<U256, UniswapV3MathError> {
+    if tick.unsigned_abs() > MAX_TICK.unsigned_abs() {
         return Err(UniswapV3MathError::T);
     }
 
-    let mut ratio = if abs_tick & (U256_1) != U256::ZERO {
-        U256::from_limbs([12262481743371124737, 18445821805675392311, 0, 0])
+    Ok(sqrt_ratio_at_tick(tick))
+}
+
+// same as get_sqrt_ratio_at_tick in const contexts, e.g. to compute tables at compile time. Panics if the tick is out
+// of bounds, which fails the compilation when evaluated in a constant
+pub const fn sqrt_ratio_at_tick(tick: i32) -> U256 {
+    let abs_tick = tick.unsigned_abs();
+    assert!(abs_tick <= MAX_TICK.unsigned_abs(), "tick out of bounds");
+
+    // the Q128.128 ratio as high and low limbs. It starts at the factor of the lowest set bit, as one does not fit in
+    // a single limb, and the remaining factors are applied without branching on the bits of the tick
+    let (high, low) = if abs_tick == 0 {
+        (1, 0)
     } else {
-        U256::from_limbs([0, 0, 1, 0])
+        let lowest = abs_tick.trailing_zeros() as usize;
+        let mut ratio = SQRT_RATIO_FACTORS[lowest];
+        let mut bit = lowest + 1;
+        while bit < SQRT_RATIO_FACTORS.len() {
+            let mask = ((abs_tick >> bit) & 1) as u128 * u128::MAX;
+            ratio = (mul_shift_128(ratio, SQRT_RATIO_FACTORS[bit]) & mask) | (ratio & !mask);
+            bit += 1;
+        }
+
+        if tick > 0 {
+            max_div(ratio)
+        } else {
+            (0, ratio)
+        }
     };
 
-    if !(abs_tick & U256_2).is_zero() {
-        ratio = (ratio * U256::from_limbs([6459403834229662010, 18444899583751176498, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_4).is_zero() {
-        ratio =
-            (ratio * U256::from_limbs([17226890335427755468, 18443055278223354162, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_8).is_zero() {
-        ratio = (ratio * U256::from_limbs([2032852871939366096, 18439367220385604838, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_16).is_zero() {
-        ratio =
-            (ratio * U256::from_limbs([14545316742740207172, 18431993317065449817, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_32).is_zero() {
-        ratio = (ratio * U256::from_limbs([5129152022828963008, 18417254355718160513, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_64).is_zero() {
-        ratio = (ratio * U256::from_limbs([4894419605888772193, 18387811781193591352, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_128).is_zero() {
-        ratio = (ratio * U256::from_limbs([1280255884321894483, 18329067761203520168, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_256).is_zero() {
-        ratio =
-            (ratio * U256::from_limbs([15924666964335305636, 18212142134806087854, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_512).is_zero() {
-        ratio = (ratio * U256::from_limbs([8010504389359918676, 17980523815641551639, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_1024).is_zero() {
-        ratio =
-            (ratio * U256::from_limbs([10668036004952895731, 17526086738831147013, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_2048).is_zero() {
-        ratio = (ratio * U256::from_limbs([4878133418470705625, 16651378430235024244, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_4096).is_zero() {
-        ratio = (ratio * U256::from_limbs([9537173718739605541, 15030750278693429944, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_8192).is_zero() {
-        ratio = (ratio * U256::from_limbs([9972618978014552549, 12247334978882834399, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_16384).is_zero() {
-        ratio = (ratio * U256::from_limbs([10428997489610666743, 8131365268884726200, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_32768).is_zero() {
-        ratio = (ratio * U256::from_limbs([9305304367709015974, 3584323654723342297, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_65536).is_zero() {
-        ratio = (ratio * U256::from_limbs([14301143598189091785, 696457651847595233, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_131072).is_zero() {
-        ratio = (ratio * U256::from_limbs([7393154844743099908, 26294789957452057, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_262144).is_zero() {
-        ratio = (ratio * U256::from_limbs([2209338891292245656, 37481735321082, 0, 0])) >> 128
-    }
-    if !(abs_tick & U256_524288).is_zero() {
-        ratio = (ratio * U256::from_limbs([10518117631919034274, 76158723, 0, 0])) >> 128
-    }
+    // divides by 1 << 32 rounding up, so that getTickAtSqrtRatio of the result is the tick
+    let (low, carry) = (low >> 32 | high << 96).overflowing_add((low as u32 != 0) as u128);
+    let high = (high >> 32) + carry as u128;
+
+    U256::from_limbs([
+        low as u64,
+        (low >> 64) as u64,
+        high as u64,
+        (high >> 64) as u64,
+    ])
+}
 
-    if tick > 0 {
-        ratio = U256::MAX / ratio;
+// returns the sqrt ratios of `N` ticks `tick_spacing` apart starting at `tick`, e.g.
+// `const TABLE: [U256; 64] = sqrt_ratio_table(-320, 10);`
+pub const fn sqrt_ratio_table<const N: usize>(tick: i32, tick_spacing: i32) -> [U256; N] {
+    let mut table = [U256::ZERO; N];
+    let mut index = 0;
+    while index < N {
+        table[index] = sqrt_ratio_at_tick(tick + index as i32 * tick_spacing);
+        index += 1;
     }
 
-    Ok((ratio >> 32)
-        + if (ratio.wrapping_rem(U256_1 << 32)).is_zero() {
-            U256::ZERO
-        } else {
-            U256_1
-        })
+    table
+}
+
+// returns the sqrt ratios at many ticks at once, fails if any tick is out of bounds
+pub fn get_sqrt_ratios_at_ticks(ticks: &[i32]) -> Result<Vec<U256>, UniswapV3MathError> {
+    ticks
+        .iter()
+        .map(|&tick| get_sqrt_ratio_at_tick(tick))
+        .collect()
 }
 
 pub fn get_tick_at_sqrt_ratio(sqrt_price_x_96: U256) -> Result<i32, UniswapV3MathError> {
@@ -226,11 +259,92 @@ pub fn get_tick_at_sqrt_ratio(sqrt_price_x_96: U256) -> Result<i32, UniswapV3Mat
     Ok(tick)
 }
 
+// returns the closest tick to `tick` that is a multiple of `tick_spacing` and within the tick bounds
+pub const fn nearest_usable_tick(tick: i32, tick_spacing: i32) -> Result<i32, UniswapV3MathError> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3MathError::TickSpacingError);
+    }
+    if tick < MIN_TICK || tick > MAX_TICK {
+        return Err(UniswapV3MathError::T);
+    }
+
+    // round half towards positive infinity, matching Math.round in the sdk
+    let rounded = (tick + tick_spacing / 2).div_euclid(tick_spacing) * tick_spacing;
+
+    if rounded < MIN_TICK {
+        Ok(rounded + tick_spacing)
+    } else if rounded > MAX_TICK {
+        Ok(rounded - tick_spacing)
+    } else {
+        Ok(rounded)
+    }
+}
+
+// validates the tick bounds of a position, mirrors UniswapV3Pool.checkTicks
+pub const fn check_ticks(tick_lower: i32, tick_upper: i32) -> Result<(), UniswapV3MathError> {
+    if tick_lower >= tick_upper {
+        return Err(UniswapV3MathError::TLU);
+    }
+    if tick_lower < MIN_TICK {
+        return Err(UniswapV3MathError::TLM);
+    }
+    if tick_upper > MAX_TICK {
+        return Err(UniswapV3MathError::TUM);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use std::{ops::Sub, str::FromStr};
 
+    #[test]
+    fn test_get_sqrt_ratios_at_ticks() {
+        //matches get_sqrt_ratio_at_tick across the whole range
+        let ticks: Vec<i32> = (MIN_TICK..=MAX_TICK)
+            .step_by(97)
+            .chain([MIN_TICK, -1, 0, 1, 887220, MAX_TICK])
+            .collect();
+        let sqrt_ratios = get_sqrt_ratios_at_ticks(&ticks).unwrap();
+        for (&tick, &sqrt_ratio) in ticks.iter().zip(&sqrt_ratios) {
+            assert_eq!(sqrt_ratio, get_sqrt_ratio_at_tick(tick).unwrap());
+            //both share the kernel, the tick math in the other direction is an independent check
+            if tick < MAX_TICK {
+                assert_eq!(get_tick_at_sqrt_ratio(sqrt_ratio).unwrap(), tick);
+            }
+        }
+        assert_eq!(sqrt_ratios[sqrt_ratios.len() - 6], MIN_SQRT_RATIO);
+        assert_eq!(sqrt_ratios[sqrt_ratios.len() - 1], MAX_SQRT_RATIO);
+
+        //fails if any tick is out of bounds
+        assert!(matches!(
+            get_sqrt_ratios_at_ticks(&[0, MAX_TICK + 1]),
+            Err(UniswapV3MathError::T)
+        ));
+    }
+
+    #[test]
+    fn test_const_tick_math() {
+        const MIN: U256 = sqrt_ratio_at_tick(MIN_TICK);
+        const MAX: U256 = sqrt_ratio_at_tick(MAX_TICK);
+        const TABLE: [U256; 5] = sqrt_ratio_table(-120, 60);
+        const USABLE: Result<i32, UniswapV3MathError> = nearest_usable_tick(MAX_TICK, 60);
+        const CHECKED: Result<(), UniswapV3MathError> = check_ticks(-60, 60);
+
+        assert_eq!(MIN, MIN_SQRT_RATIO);
+        assert_eq!(MAX, MAX_SQRT_RATIO);
+        for (index, sqrt_ratio) in TABLE.into_iter().enumerate() {
+            assert_eq!(
+                sqrt_ratio,
+                get_sqrt_ratio_at_tick(-120 + index as i32 * 60).unwrap()
+            );
+        }
+        assert_eq!(TABLE[2], U256::from(1) << 96);
+        assert_eq!(USABLE.unwrap(), 887220);
+        assert!(CHECKED.is_ok());
+    }
+
     #[test]
     fn test_get_sqrt_ratio_at_tick_bounds() {
         // the function should return an error if the tick is out of bounds
@@ -360,4 +474,81 @@ mod test {
         let result = get_tick_at_sqrt_ratio(U256::from_str("4295343490").unwrap()).unwrap();
         assert_eq!(result, MIN_TICK + 1);
     }
+
+    #[test]
+    fn test_nearest_usable_tick() {
+        //fails if tick spacing is 0 or negative
+        assert!(matches!(
+            nearest_usable_tick(1, 0).unwrap_err(),
+            UniswapV3MathError::TickSpacingError
+        ));
+        assert!(matches!(
+            nearest_usable_tick(1, -5).unwrap_err(),
+            UniswapV3MathError::TickSpacingError
+        ));
+
+        //fails if tick is out of bounds
+        assert!(matches!(
+            nearest_usable_tick(MAX_TICK + 1, 1).unwrap_err(),
+            UniswapV3MathError::T
+        ));
+        assert!(matches!(
+            nearest_usable_tick(MIN_TICK - 1, 1).unwrap_err(),
+            UniswapV3MathError::T
+        ));
+
+        //rounds at positive half
+        assert_eq!(nearest_usable_tick(5, 10).unwrap(), 10);
+
+        //rounds down below positive half
+        assert_eq!(nearest_usable_tick(4, 10).unwrap(), 0);
+
+        //rounds up for negative half
+        assert_eq!(nearest_usable_tick(-5, 10).unwrap(), 0);
+
+        //rounds up for negative half
+        assert_eq!(nearest_usable_tick(-6, 10).unwrap(), -10);
+
+        //cannot round past MIN_TICK
+        assert_eq!(
+            nearest_usable_tick(MIN_TICK, MAX_TICK / 2 + 100).unwrap(),
+            -(MAX_TICK / 2 + 100)
+        );
+
+        //cannot round past MAX_TICK
+        assert_eq!(
+            nearest_usable_tick(MAX_TICK, MAX_TICK / 2 + 100).unwrap(),
+            MAX_TICK / 2 + 100
+        );
+
+        //min and max ticks with common spacings
+        assert_eq!(nearest_usable_tick(MIN_TICK, 60).unwrap(), -887220);
+        assert_eq!(nearest_usable_tick(MAX_TICK, 60).unwrap(), 887220);
+        assert_eq!(nearest_usable_tick(MIN_TICK, 200).unwrap(), -887200);
+        assert_eq!(nearest_usable_tick(MAX_TICK, 200).unwrap(), 887200);
+    }
+
+    #[test]
+    fn test_check_ticks() {
+        assert!(check_ticks(-60, 60).is_ok());
+        assert!(check_ticks(MIN_TICK, MAX_TICK).is_ok());
+
+        let result = check_ticks(60, 60);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "The lower tick must be less than the upper tick"
+        );
+
+        let result = check_ticks(MIN_TICK - 1, 0);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "The lower tick must be greater than, or equal to, the minimum tick"
+        );
+
+        let result = check_ticks(0, MAX_TICK + 1);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "The upper tick must be less than, or equal to, the maximum tick"
+        );
+    }
 }
@@ -0,0 +1,300 @@
+use std::collections::{BTreeMap, HashMap};
+
+use alloy_primitives::{Address, U256};
+
+use crate::{
+    decode,
+    minimal_ticks::MinimalTick,
+    pool_storage::{tick_bitmap_slot, tick_slot, StorageProvider},
+    tick::Tick,
+};
+
+//Reads the ticks and tick bitmap words of a single pool on demand, e.g. over RPC or from an EVM database
+pub trait TickProvider {
+    type Error;
+
+    fn tick(&mut self, tick: i32) -> Result<Tick, Self::Error>;
+
+    fn tick_bitmap_word(&mut self, word_pos: i16) -> Result<U256, Self::Error>;
+
+    // reads only the part of a tick a swap needs, providers that can read it without the whole tick should
+    fn minimal_tick(&mut self, tick: i32) -> Result<MinimalTick, Self::Error> {
+        Ok(self.tick(tick)?.into())
+    }
+}
+
+//Reads the ticks of the pool at `address` from raw storage, see `pool_storage`
+#[derive(Clone, Debug)]
+pub struct StorageTickProvider<P> {
+    pub provider: P,
+    pub address: Address,
+}
+
+impl<P: StorageProvider> TickProvider for StorageTickProvider<P> {
+    type Error = P::Error;
+
+    fn tick(&mut self, tick: i32) -> Result<Tick, P::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(address = %self.address, tick, "reading tick");
+
+        let slot = tick_slot(tick);
+        let words = [
+            self.provider.storage(self.address, slot)?,
+            self.provider.storage(self.address, slot + U256::from(1))?,
+            self.provider.storage(self.address, slot + U256::from(2))?,
+            self.provider.storage(self.address, slot + U256::from(3))?,
+        ];
+        Ok(decode::tick_info(&words))
+    }
+
+    fn tick_bitmap_word(&mut self, word_pos: i16) -> Result<U256, P::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(address = %self.address, word_pos, "reading tick bitmap word");
+
+        self.provider
+            .storage(self.address, tick_bitmap_slot(word_pos))
+    }
+
+    fn minimal_tick(&mut self, tick: i32) -> Result<MinimalTick, P::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(address = %self.address, tick, "reading minimal tick");
+
+        let slot = tick_slot(tick);
+        Ok(decode::minimal_tick_info(
+            self.provider.storage(self.address, slot)?,
+            self.provider.storage(self.address, slot + U256::from(3))?,
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Tick(i32),
+    MinimalTick(i32),
+    Word(i16),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Entry {
+    Tick(Tick),
+    MinimalTick(MinimalTick),
+    Word(U256),
+}
+
+//Caches the ticks and words read through a provider, evicting the least recently used entry once `capacity` entries
+//are cached. Entries are only valid for the block they were read at, moving to another block with `set_block` drops
+//them all. Within a block, entries touched by a Mint, Burn or Swap can be dropped individually instead
+#[derive(Clone, Debug)]
+pub struct CachedTickProvider<P> {
+    provider: P,
+    capacity: usize,
+    block: Option<u64>,
+    // each entry with the time it was last used, and the entries by the time they were last used
+    entries: HashMap<Key, (Entry, u64)>,
+    recency: BTreeMap<u64, Key>,
+    clock: u64,
+}
+
+impl<P> CachedTickProvider<P> {
+    pub fn new(provider: P, capacity: usize) -> Self {
+        Self {
+            provider,
+            capacity,
+            block: None,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    // reads from now on are for `block`, the cache is cleared if it holds entries of another block
+    pub fn set_block(&mut self, block: u64) {
+        if self.block != Some(block) {
+            self.clear();
+            self.block = Some(block);
+        }
+    }
+
+    pub fn block(&self) -> Option<u64> {
+        self.block
+    }
+
+    pub fn invalidate_tick(&mut self, tick: i32) {
+        self.remove(Key::Tick(tick));
+        self.remove(Key::MinimalTick(tick));
+    }
+
+    pub fn invalidate_tick_bitmap_word(&mut self, word_pos: i16) {
+        self.remove(Key::Word(word_pos));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    // the number of cached ticks and words
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    pub fn into_inner(self) -> P {
+        self.provider
+    }
+
+    fn remove(&mut self, key: Key) {
+        if let Some((_, last_used)) = self.entries.remove(&key) {
+            self.recency.remove(&last_used);
+        }
+    }
+
+    // returns a cached entry and marks it as the most recently used
+    fn get(&mut self, key: Key) -> Option<Entry> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(?key, hit = self.entries.contains_key(&key), block = ?self.block, "tick cache lookup");
+
+        let (entry, last_used) = self.entries.get_mut(&key)?;
+        self.recency.remove(last_used);
+        self.clock += 1;
+        *last_used = self.clock;
+        self.recency.insert(self.clock, key);
+        Some(*entry)
+    }
+
+    fn insert(&mut self, key: Key, entry: Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some((_, evicted)) = self.recency.pop_first() {
+                self.entries.remove(&evicted);
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(key, (entry, self.clock));
+        self.recency.insert(self.clock, key);
+    }
+}
+
+impl<P: TickProvider> TickProvider for CachedTickProvider<P> {
+    type Error = P::Error;
+
+    fn tick(&mut self, tick: i32) -> Result<Tick, P::Error> {
+        if let Some(Entry::Tick(info)) = self.get(Key::Tick(tick)) {
+            return Ok(info);
+        }
+
+        let info = self.provider.tick(tick)?;
+        self.insert(Key::Tick(tick), Entry::Tick(info));
+        Ok(info)
+    }
+
+    fn tick_bitmap_word(&mut self, word_pos: i16) -> Result<U256, P::Error> {
+        if let Some(Entry::Word(word)) = self.get(Key::Word(word_pos)) {
+            return Ok(word);
+        }
+
+        let word = self.provider.tick_bitmap_word(word_pos)?;
+        self.insert(Key::Word(word_pos), Entry::Word(word));
+        Ok(word)
+    }
+
+    // served from a cached whole tick if there is one
+    fn minimal_tick(&mut self, tick: i32) -> Result<MinimalTick, P::Error> {
+        if let Some(Entry::Tick(info)) = self.get(Key::Tick(tick)) {
+            return Ok(info.into());
+        }
+        if let Some(Entry::MinimalTick(info)) = self.get(Key::MinimalTick(tick)) {
+            return Ok(info);
+        }
+
+        let info = self.provider.minimal_tick(tick)?;
+        self.insert(Key::MinimalTick(tick), Entry::MinimalTick(info));
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::{CachedTickProvider, TickProvider};
+    use crate::tick::Tick;
+
+    // counts the reads and serves ticks whose liquidity is the tick itself
+    #[derive(Default)]
+    struct CountingProvider {
+        reads: usize,
+    }
+
+    impl TickProvider for CountingProvider {
+        type Error = ();
+
+        fn tick(&mut self, tick: i32) -> Result<Tick, ()> {
+            self.reads += 1;
+            Ok(Tick {
+                liquidity_gross: tick.unsigned_abs() as u128,
+                ..Default::default()
+            })
+        }
+
+        fn tick_bitmap_word(&mut self, word_pos: i16) -> Result<U256, ()> {
+            self.reads += 1;
+            Ok(U256::from(word_pos.unsigned_abs()))
+        }
+    }
+
+    #[test]
+    fn test_cached_tick_provider() {
+        let mut cache = CachedTickProvider::new(CountingProvider::default(), 3);
+        cache.set_block(100);
+
+        //repeated reads are served from the cache
+        for _ in 0..3 {
+            assert_eq!(cache.tick(-60).unwrap().liquidity_gross, 60);
+            assert_eq!(cache.tick_bitmap_word(-1).unwrap(), U256::from(1));
+        }
+        assert_eq!(cache.provider().reads, 2);
+        assert_eq!(cache.len(), 2);
+
+        //the least recently used entry is evicted
+        cache.tick(120).unwrap();
+        cache.tick(-60).unwrap();
+        cache.tick(180).unwrap();
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.provider().reads, 4);
+        cache.tick(-60).unwrap();
+        cache.tick(120).unwrap();
+        assert_eq!(cache.provider().reads, 4);
+        cache.tick_bitmap_word(-1).unwrap();
+        assert_eq!(cache.provider().reads, 5);
+
+        //entries can be invalidated individually
+        cache.invalidate_tick(120);
+        cache.tick(120).unwrap();
+        assert_eq!(cache.provider().reads, 6);
+
+        //the cache is only valid for one block
+        cache.set_block(100);
+        assert_eq!(cache.len(), 3);
+        cache.set_block(101);
+        assert!(cache.is_empty());
+        cache.tick(120).unwrap();
+        assert_eq!(cache.provider().reads, 7);
+
+        //nothing is cached without capacity
+        let mut cache = CachedTickProvider::new(CountingProvider::default(), 0);
+        cache.tick(0).unwrap();
+        cache.tick(0).unwrap();
+        assert_eq!(cache.into_inner().reads, 2);
+    }
+}
@@ -0,0 +1,231 @@
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    full_math::mul_div,
+    orientation::amounts_in_out,
+    pool::{PoolSnapshot, SwapParams, SwapResult},
+    price::BPS_DENOMINATOR,
+    storage::{InitializedTicks, TickStorage},
+    variant::PoolVariant,
+};
+
+// fee on transfer tokens take a tax on transfers, so the pool receives less than the trader sends and the trader
+// receives less than the pool sends. The pool math is unaware of them, the taxes are applied to the amounts around the
+// swap: an exact input is taxed before it reaches the pool, an exact output is grossed up so the trader receives it
+// after the tax. Like the pool, which checks its balance after asking for the input, the trader always has to send
+// enough for the pool to receive its input after the tax. Taxes are rounded down, the way tokens usually compute them
+
+//The taxes of a token in basis points of the amount transferred
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TransferFee {
+    // taken when the token is sent to the pool, i.e. sold
+    pub sell_bps: u64,
+    // taken when the pool sends the token, i.e. bought
+    pub buy_bps: u64,
+}
+
+impl TransferFee {
+    // fails if a tax is 100% or more
+    pub fn new(sell_bps: u64, buy_bps: u64) -> Result<Self, UniswapV3MathError> {
+        check_bps(sell_bps)?;
+        check_bps(buy_bps)?;
+        Ok(Self { sell_bps, buy_bps })
+    }
+}
+
+fn check_bps(bps: u64) -> Result<(), UniswapV3MathError> {
+    if bps >= BPS_DENOMINATOR {
+        return Err(UniswapV3MathError::InvalidTransferFee);
+    }
+    Ok(())
+}
+
+// returns the tax taken from a transfer of `amount`, rounded down
+pub fn transfer_fee(amount: U256, bps: u64) -> Result<U256, UniswapV3MathError> {
+    check_bps(bps)?;
+    mul_div(amount, U256::from(bps), U256::from(BPS_DENOMINATOR))
+}
+
+// returns the smallest amount whose transfer delivers at least `amount` after the tax. The amount delivered is
+// gross - floor(gross * bps / 10000) = ceil(gross * (10000 - bps) / 10000), which is at least `amount` exactly when
+// gross * (10000 - bps) > (amount - 1) * 10000
+pub fn gross_amount(amount: U256, bps: u64) -> Result<U256, UniswapV3MathError> {
+    check_bps(bps)?;
+    if amount.is_zero() {
+        return Ok(U256::ZERO);
+    }
+
+    mul_div(
+        amount - U256::from(1),
+        U256::from(BPS_DENOMINATOR),
+        U256::from(BPS_DENOMINATOR - bps),
+    )?
+    .checked_add(U256::from(1))
+    .ok_or(UniswapV3MathError::Overflow)
+}
+
+//A quote of a swap between fee on transfer tokens
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransferFeeQuote {
+    // the amount the trader sends, including the tax on the transfer to the pool
+    pub amount_in: U256,
+    // the amount the trader receives, after the tax on the transfer out of the pool
+    pub amount_out: U256,
+    // the taxes taken on the transfers to and from the pool
+    pub transfer_fee_in: U256,
+    pub transfer_fee_out: U256,
+    // the swap the pool executes, on the amounts it receives and sends
+    pub result: SwapResult,
+}
+
+// quotes a swap of `params` between tokens with the given taxes. The amount specified is what the trader sends for an
+// exact input and what the trader wants to receive for an exact output, the price limit applies to the pool as usual
+pub fn quote_with_transfer_fees<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    params: &SwapParams,
+    transfer_fee_0: TransferFee,
+    transfer_fee_1: TransferFee,
+) -> Result<TransferFeeQuote, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let (fee_in, fee_out) = if params.zero_for_one {
+        (transfer_fee_0, transfer_fee_1)
+    } else {
+        (transfer_fee_1, transfer_fee_0)
+    };
+
+    let amount = params.amount_specified.unsigned_abs();
+    let amount_specified = if params.amount_specified > I256::ZERO {
+        I256::from_raw(amount - transfer_fee(amount, fee_in.sell_bps)?)
+    } else {
+        let gross = gross_amount(amount, fee_out.buy_bps)?;
+        -I256::try_from(gross).map_err(|_| UniswapV3MathError::Overflow)?
+    };
+
+    let result = pool.quote_with(&SwapParams {
+        amount_specified,
+        ..*params
+    })?;
+    let (pool_in, pool_out) = amounts_in_out(params.zero_for_one, result.amount_0, result.amount_1);
+
+    let amount_in = gross_amount(pool_in, fee_in.sell_bps)?;
+    let transfer_fee_out = transfer_fee(pool_out, fee_out.buy_bps)?;
+
+    Ok(TransferFeeQuote {
+        amount_in,
+        amount_out: pool_out - transfer_fee_out,
+        transfer_fee_in: amount_in - pool_in,
+        transfer_fee_out,
+        result,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{gross_amount, quote_with_transfer_fees, transfer_fee, TransferFee};
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        orientation::amounts_in_out,
+        pool::{PoolSnapshot, SwapParams},
+        tick_math::get_sqrt_ratio_at_tick,
+    };
+
+    #[test]
+    fn test_gross_amount() {
+        for bps in [0, 1, 300, 5000, 9999] {
+            for amount in 0..200u64 {
+                let amount = U256::from(amount);
+                let gross = gross_amount(amount, bps).unwrap();
+                let net = |gross: U256| gross - transfer_fee(gross, bps).unwrap();
+                assert!(net(gross) >= amount);
+                assert!(gross.is_zero() || net(gross - U256::from(1)) < amount);
+            }
+        }
+
+        //fails if the tax takes everything
+        assert!(matches!(
+            TransferFee::new(10000, 0),
+            Err(UniswapV3MathError::InvalidTransferFee)
+        ));
+        assert!(gross_amount(U256::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn test_quote_with_transfer_fees() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-6000, 6000, 1e21 as i128).unwrap();
+        let exact_input = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e18 as u128))
+            .build()
+            .unwrap();
+        let exact_output = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_out(U256::from(1e18 as u128))
+            .build()
+            .unwrap();
+
+        //without taxes the quote is the plain quote
+        let quote = quote_with_transfer_fees(
+            &pool,
+            &exact_input,
+            TransferFee::default(),
+            TransferFee::default(),
+        )
+        .unwrap();
+        let result = pool.quote_with(&exact_input).unwrap();
+        assert_eq!(quote.result, result);
+        assert_eq!(
+            (quote.amount_in, quote.amount_out),
+            amounts_in_out(true, result.amount_0, result.amount_1)
+        );
+
+        //5% when selling token0 and 3% when buying token1
+        let transfer_fee_0 = TransferFee::new(500, 0).unwrap();
+        let transfer_fee_1 = TransferFee::new(0, 300).unwrap();
+
+        let quote =
+            quote_with_transfer_fees(&pool, &exact_input, transfer_fee_0, transfer_fee_1).unwrap();
+        assert_eq!(
+            quote.result.amount_0,
+            I256::from_raw(U256::from(0.95e18 as u128))
+        );
+        //the tax is rounded down, so slightly less than the amount specified is enough for the pool
+        assert_eq!(quote.amount_in, U256::from(1e18 as u128 - 1));
+        assert_eq!(quote.transfer_fee_in, U256::from(0.05e18 as u128 - 1));
+        let pool_out = quote.result.amount_1.unsigned_abs();
+        assert_eq!(quote.amount_out + quote.transfer_fee_out, pool_out);
+        assert_eq!(
+            quote.transfer_fee_out,
+            pool_out * U256::from(3) / U256::from(100)
+        );
+
+        let quote =
+            quote_with_transfer_fees(&pool, &exact_output, transfer_fee_0, transfer_fee_1).unwrap();
+        assert_eq!(quote.amount_out, U256::from(1e18 as u128));
+        assert_eq!(
+            quote.result.amount_1.unsigned_abs(),
+            gross_amount(U256::from(1e18 as u128), 300).unwrap()
+        );
+        assert_eq!(
+            quote.amount_in - quote.transfer_fee_in,
+            quote.result.amount_0.unsigned_abs()
+        );
+        assert!(
+            quote.amount_in
+                > pool
+                    .quote_with(&exact_output)
+                    .unwrap()
+                    .amount_0
+                    .unsigned_abs()
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use alloy_primitives::U256;
+
+use crate::{constants::MAX_U160, error::UniswapV3MathError};
+
+// the contract stores sqrt prices and the seconds per liquidity accumulator as uint160 and converts between uint160
+// and uint256 in three ways: SafeCast.toUint160 reverts if the value does not fit, an explicit uint160(x) cast keeps
+// the low 160 bits, and unchecked arithmetic on a uint160 wraps at 2**160. Computing these values as plain U256s
+// neither reverts nor wraps where the contract does, so they go through this type, which names the conversion the
+// contract uses at each site
+
+//A uint160, the value always fits in 160 bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uint160(U256);
+
+impl Uint160 {
+    pub const ZERO: Self = Self(U256::ZERO);
+    pub const MAX: Self = Self(MAX_U160);
+
+    // SafeCast.toUint160, fails if the value does not fit
+    pub fn new(value: U256) -> Result<Self, UniswapV3MathError> {
+        if value > MAX_U160 {
+            return Err(UniswapV3MathError::SafeCastToU160Overflow);
+        }
+        Ok(Self(value))
+    }
+
+    // an explicit uint160(value) cast, keeps the low 160 bits
+    pub fn truncate(value: U256) -> Self {
+        Self(value & MAX_U160)
+    }
+
+    pub fn get(self) -> U256 {
+        self.0
+    }
+
+    // checked uint160 addition, fails on overflow like solidity 0.8
+    pub fn checked_add(self, other: Self) -> Result<Self, UniswapV3MathError> {
+        Self::new(self.0 + other.0).map_err(|_| UniswapV3MathError::Overflow)
+    }
+
+    // unchecked uint160 addition, wraps at 2**160 like solidity 0.7
+    pub fn wrapping_add(self, other: Self) -> Self {
+        Self::truncate(self.0 + other.0)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self, UniswapV3MathError> {
+        self.0
+            .checked_sub(other.0)
+            .map(Self)
+            .ok_or(UniswapV3MathError::Overflow)
+    }
+
+    pub fn wrapping_sub(self, other: Self) -> Self {
+        Self::truncate(self.0.wrapping_sub(other.0))
+    }
+}
+
+impl From<Uint160> for U256 {
+    fn from(value: Uint160) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::U256;
+
+    use super::Uint160;
+    use crate::{constants::MAX_U160, error::UniswapV3MathError};
+
+    #[test]
+    fn test_uint160() {
+        let one = Uint160::new(U256::from(1)).unwrap();
+
+        //casts either fail or keep the low 160 bits
+        assert_eq!(Uint160::new(MAX_U160).unwrap(), Uint160::MAX);
+        assert!(matches!(
+            Uint160::new(MAX_U160 + U256::from(1)),
+            Err(UniswapV3MathError::SafeCastToU160Overflow)
+        ));
+        assert_eq!(Uint160::truncate(U256::MAX), Uint160::MAX);
+        assert_eq!(Uint160::truncate(U256::from(1) << 160), Uint160::ZERO);
+        assert_eq!(
+            Uint160::truncate((U256::from(1) << 160) + U256::from(1)),
+            one
+        );
+
+        //arithmetic either fails or wraps at 2**160
+        assert_eq!(Uint160::MAX.wrapping_add(one), Uint160::ZERO);
+        assert!(Uint160::MAX.checked_add(one).is_err());
+        assert_eq!(Uint160::ZERO.wrapping_sub(one), Uint160::MAX);
+        assert!(Uint160::ZERO.checked_sub(one).is_err());
+        assert_eq!(
+            Uint160::MAX.checked_sub(one).unwrap().get(),
+            MAX_U160 - U256::from(1)
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{keccak256, Address, B256, I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    fee_tier::FeeTier,
+    full_math::{mul_div, Q128},
+    pool::PoolSnapshot,
+    storage::{InitializedTicks, TickStorage},
+    variant::PoolVariant,
+};
+
+// V4 pools accept any tick spacing that fits in an int16
+pub const MAX_TICK_SPACING: i32 = i16::MAX as i32;
+pub const MIN_TICK_SPACING: i32 = 1;
+// lp fees are in hundredths of a bip, up to 100%
+pub const MAX_LP_FEE: u32 = 1000000;
+// set in the fee of pools whose hook sets the fee dynamically
+pub const DYNAMIC_FEE_FLAG: u32 = 0x800000;
+
+//Identifies a V4 pool in the singleton pool manager, mirrors PoolKey. Currencies are sorted, the zero address is the
+//native currency
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    pub currency_0: Address,
+    pub currency_1: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub hooks: Address,
+}
+
+impl PoolKey {
+    // returns keccak256(abi.encode(key)), the id of the pool in the pool manager
+    pub fn pool_id(&self) -> B256 {
+        let mut encoded = [0u8; 160];
+        encoded[12..32].copy_from_slice(self.currency_0.as_slice());
+        encoded[44..64].copy_from_slice(self.currency_1.as_slice());
+        encoded[64..96].copy_from_slice(&U256::from(self.fee).to_be_bytes::<32>());
+        encoded[96..128].copy_from_slice(
+            &I256::unchecked_from(self.tick_spacing)
+                .into_raw()
+                .to_be_bytes::<32>(),
+        );
+        encoded[140..160].copy_from_slice(self.hooks.as_slice());
+        keccak256(encoded)
+    }
+
+    // returns the fee tier of a hookless pool. Pools with a dynamic fee are rejected, their fee is set by the hook, and
+    // so is a 100% fee, which PoolManager.initialize accepts but no swap can pay
+    pub fn fee_tier(&self) -> Result<FeeTier, UniswapV3MathError> {
+        if self.fee & DYNAMIC_FEE_FLAG != 0 {
+            return Err(UniswapV3MathError::InvalidFee);
+        }
+
+        FeeTier::custom_with_max_tick_spacing(self.fee, self.tick_spacing, MAX_TICK_SPACING)
+    }
+}
+
+//The amounts of currency0 and currency1 owed to or by the caller, mirrors BalanceDelta. Unlike the amounts returned by
+//`PoolSnapshot`, which are from the perspective of the pool, deltas are from the perspective of the caller, negative
+//amounts are owed by the caller and positive amounts are owed to the caller
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BalanceDelta {
+    pub amount_0: i128,
+    pub amount_1: i128,
+}
+
+impl BalanceDelta {
+    pub const ZERO: Self = Self {
+        amount_0: 0,
+        amount_1: 0,
+    };
+
+    // returns the caller's delta for amounts owed to the pool, as returned by `PoolSnapshot::swap` and `modify_position`
+    pub fn from_pool_amounts(amount_0: I256, amount_1: I256) -> Result<Self, UniswapV3MathError> {
+        let caller_amount = |amount: I256| {
+            amount
+                .checked_neg()
+                .and_then(|amount| i128::try_from(amount).ok())
+                .ok_or(UniswapV3MathError::Overflow)
+        };
+
+        Ok(Self {
+            amount_0: caller_amount(amount_0)?,
+            amount_1: caller_amount(amount_1)?,
+        })
+    }
+
+    // returns the int256 the pool manager packs the delta into, amount0 in the upper 128 bits
+    pub fn packed(&self) -> I256 {
+        let amount_0 = U256::from(self.amount_0 as u128) << 128;
+        let amount_1 = U256::from(self.amount_1 as u128);
+        I256::from_raw(amount_0 | amount_1)
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        Some(Self {
+            amount_0: self.amount_0.checked_add(other.amount_0)?,
+            amount_1: self.amount_1.checked_add(other.amount_1)?,
+        })
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        Some(Self {
+            amount_0: self.amount_0.checked_sub(other.amount_0)?,
+            amount_1: self.amount_1.checked_sub(other.amount_1)?,
+        })
+    }
+}
+
+// swaps against a hookless V4 pool and returns the caller's balance delta, mirrors Pool.swap. V4 flips the sign of the
+// specified amount, negative amounts are exact input and positive amounts are exact output
+pub fn swap<T, B, V>(
+    pool: &mut PoolSnapshot<T, B, V>,
+    zero_for_one: bool,
+    amount_specified: I256,
+    sqrt_price_limit_x_96: U256,
+) -> Result<BalanceDelta, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let amount_specified = amount_specified
+        .checked_neg()
+        .ok_or(UniswapV3MathError::Overflow)?;
+    let result = pool.swap(zero_for_one, amount_specified, sqrt_price_limit_x_96)?;
+
+    BalanceDelta::from_pool_amounts(result.amount_0, result.amount_1)
+}
+
+// adds or removes liquidity from a hookless V4 pool and returns the caller's balance delta, excluding any fees owed to
+// the position, mirrors the principal delta of Pool.modifyLiquidity
+pub fn modify_liquidity<T, B, V>(
+    pool: &mut PoolSnapshot<T, B, V>,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity_delta: i128,
+) -> Result<BalanceDelta, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let (amount_0, amount_1) = pool.modify_position(tick_lower, tick_upper, liquidity_delta)?;
+    BalanceDelta::from_pool_amounts(amount_0, amount_1)
+}
+
+// donates the given amounts to the in range liquidity and returns the caller's balance delta, mirrors Pool.donate
+pub fn donate<T, B, V>(
+    pool: &mut PoolSnapshot<T, B, V>,
+    amount_0: u128,
+    amount_1: u128,
+) -> Result<BalanceDelta, UniswapV3MathError> {
+    if pool.liquidity == 0 {
+        return Err(UniswapV3MathError::NoLiquidityToReceiveFees);
+    }
+
+    let liquidity = U256::from(pool.liquidity);
+    if amount_0 > 0 {
+        pool.fee_growth_global_0_x_128 = pool.fee_growth_global_0_x_128.wrapping_add(mul_div(
+            U256::from(amount_0),
+            Q128,
+            liquidity,
+        )?);
+    }
+    if amount_1 > 0 {
+        pool.fee_growth_global_1_x_128 = pool.fee_growth_global_1_x_128.wrapping_add(mul_div(
+            U256::from(amount_1),
+            Q128,
+            liquidity,
+        )?);
+    }
+
+    BalanceDelta::from_pool_amounts(
+        I256::try_from(amount_0).map_err(|_| UniswapV3MathError::Overflow)?,
+        I256::try_from(amount_1).map_err(|_| UniswapV3MathError::Overflow)?,
+    )
+}
+
+//The per currency deltas of an unlocked pool manager, mirrors CurrencyDelta and the nonzero delta count. Every delta
+//has to be settled or taken before the pool manager can be locked again
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CurrencyDeltas {
+    deltas: HashMap<Address, i128>,
+}
+
+impl CurrencyDeltas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // returns the delta of a currency, negative if owed by the caller
+    pub fn delta(&self, currency: Address) -> i128 {
+        self.deltas.get(&currency).copied().unwrap_or_default()
+    }
+
+    // accounts a balance delta returned by an action on the pool of `key`
+    pub fn account(
+        &mut self,
+        key: &PoolKey,
+        delta: BalanceDelta,
+    ) -> Result<(), UniswapV3MathError> {
+        self.account_delta(key.currency_0, delta.amount_0)?;
+        self.account_delta(key.currency_1, delta.amount_1)
+    }
+
+    // credits the caller for `amount` paid to the pool manager, mirrors PoolManager.settle
+    pub fn settle(&mut self, currency: Address, amount: u128) -> Result<(), UniswapV3MathError> {
+        let amount = i128::try_from(amount).map_err(|_| UniswapV3MathError::Overflow)?;
+        self.account_delta(currency, amount)
+    }
+
+    // debits the caller for `amount` sent out by the pool manager, mirrors PoolManager.take
+    pub fn take(&mut self, currency: Address, amount: u128) -> Result<(), UniswapV3MathError> {
+        let amount = i128::try_from(amount).map_err(|_| UniswapV3MathError::Overflow)?;
+        self.account_delta(currency, -amount)
+    }
+
+    pub fn nonzero_delta_count(&self) -> usize {
+        self.deltas.len()
+    }
+
+    // returns true if the pool manager could be locked, i.e. every delta is zero
+    pub fn is_settled(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    fn account_delta(&mut self, currency: Address, delta: i128) -> Result<(), UniswapV3MathError> {
+        if delta == 0 {
+            return Ok(());
+        }
+
+        let next = self
+            .delta(currency)
+            .checked_add(delta)
+            .ok_or(UniswapV3MathError::Overflow)?;
+        if next == 0 {
+            self.deltas.remove(&currency);
+        } else {
+            self.deltas.insert(currency, next);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{address, keccak256, Address, I256, U256};
+
+    use super::{donate, modify_liquidity, swap, BalanceDelta, CurrencyDeltas, PoolKey};
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        full_math::Q128,
+        pool::PoolSnapshot,
+        tick_math::{get_sqrt_ratio_at_tick, MIN_SQRT_RATIO},
+    };
+
+    const TOKEN_0: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+    const TOKEN_1: Address = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+
+    fn pool_key(fee: u32, tick_spacing: i32) -> PoolKey {
+        PoolKey {
+            currency_0: TOKEN_0,
+            currency_1: TOKEN_1,
+            fee,
+            tick_spacing,
+            hooks: Address::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_pool_key() {
+        //the key is abi encoded as five words
+        let key = pool_key(3000, -60);
+        let mut encoded = [0u8; 160];
+        encoded[12..32].copy_from_slice(TOKEN_0.as_slice());
+        encoded[44..64].copy_from_slice(TOKEN_1.as_slice());
+        encoded[94..96].copy_from_slice(&3000u16.to_be_bytes());
+        encoded[96..128].fill(0xff);
+        encoded[127] = 0xc4;
+        assert_eq!(key.pool_id(), keccak256(encoded));
+
+        //any tick spacing up to int16 max
+        assert_eq!(pool_key(3000, 60).fee_tier().unwrap(), FeeTier::Medium);
+        let tier = pool_key(3000, 32767).fee_tier().unwrap();
+        assert_eq!(tier.tick_spacing(), 32767);
+        assert!(matches!(
+            pool_key(3000, 32768).fee_tier(),
+            Err(UniswapV3MathError::TickSpacingError)
+        ));
+        assert!(pool_key(3000, 0).fee_tier().is_err());
+
+        //fees below 100%, dynamic fees are set by hooks
+        assert!(pool_key(999999, 1).fee_tier().is_ok());
+        assert!(matches!(
+            pool_key(1000000, 1).fee_tier(),
+            Err(UniswapV3MathError::InvalidFee)
+        ));
+        assert!(pool_key(1000001, 1).fee_tier().is_err());
+        assert!(pool_key(0x800000, 1).fee_tier().is_err());
+    }
+
+    #[test]
+    fn test_balance_delta() {
+        let delta =
+            BalanceDelta::from_pool_amounts(I256::try_from(5).unwrap(), I256::MINUS_ONE).unwrap();
+        assert_eq!(
+            delta,
+            BalanceDelta {
+                amount_0: -5,
+                amount_1: 1
+            }
+        );
+        assert_eq!(
+            delta.packed(),
+            I256::from_raw((U256::from(-5i128 as u128) << 128) | U256::from(1))
+        );
+        assert_eq!(delta.checked_sub(delta), Some(BalanceDelta::ZERO));
+        assert!(BalanceDelta::from_pool_amounts(I256::MAX, I256::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_settle_swap() {
+        let key = pool_key(3000, 60);
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), key.fee_tier().unwrap()).unwrap();
+        let mut deltas = CurrencyDeltas::new();
+
+        let delta = modify_liquidity(&mut pool, -600, 600, 1e21 as i128).unwrap();
+        assert!(delta.amount_0 < 0 && delta.amount_1 < 0);
+        deltas.account(&key, delta).unwrap();
+        assert_eq!(deltas.nonzero_delta_count(), 2);
+
+        //negative amounts are exact input
+        let delta = swap(
+            &mut pool,
+            true,
+            I256::try_from(-1e18 as i128).unwrap(),
+            MIN_SQRT_RATIO + U256::from(1),
+        )
+        .unwrap();
+        assert_eq!(delta.amount_0, -1e18 as i128);
+        assert!(delta.amount_1 > 0);
+        deltas.account(&key, delta).unwrap();
+
+        //settling what is owed and taking what is owed to the caller clears every delta
+        for currency in [TOKEN_0, TOKEN_1] {
+            let delta = deltas.delta(currency);
+            if delta < 0 {
+                deltas.settle(currency, delta.unsigned_abs()).unwrap();
+            } else {
+                deltas.take(currency, delta.unsigned_abs()).unwrap();
+            }
+        }
+        assert!(deltas.is_settled());
+        deltas.take(TOKEN_1, 1).unwrap();
+        assert_eq!(deltas.delta(TOKEN_1), -1);
+    }
+
+    #[test]
+    fn test_donate() {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+
+        //fails if there is no liquidity to receive the donation
+        assert!(matches!(
+            donate(&mut pool, 1, 1),
+            Err(UniswapV3MathError::NoLiquidityToReceiveFees)
+        ));
+
+        pool.modify_position(-600, 600, 1e18 as i128).unwrap();
+        let delta = donate(&mut pool, 1e18 as u128, 0).unwrap();
+        assert_eq!(delta.amount_0, -1e18 as i128);
+        assert_eq!(delta.amount_1, 0);
+        assert_eq!(pool.fee_growth_global_0_x_128, Q128);
+        assert_eq!(pool.fee_growth_global_1_x_128, U256::ZERO);
+    }
+}
@@ -0,0 +1,184 @@
+use alloy_primitives::U256;
+
+use crate::{
+    fee_tier::{FeeTier, FEE_DENOMINATOR},
+    pool::Slot0,
+};
+
+// PancakeSwap V3 protocol fees are in basis points of the swap fee, 16 bits per token
+pub const PANCAKESWAP_PROTOCOL_FEE_DENOMINATOR: u32 = 10000;
+const PANCAKESWAP_PROTOCOL_FEE_SP: u32 = 65536;
+
+//The parts of a Uniswap V3 fork that differ from the canonical pool. The tick and sqrt price math is shared by every
+//variant, forks only differ in how fees are denominated, how the protocol fee is encoded in slot0 and which fee
+//amounts their factory enables
+pub trait PoolVariant {
+    // the denominator of the pool fee, 1e6 for Uniswap V3
+    fn fee_denominator(&self) -> u32 {
+        FEE_DENOMINATOR
+    }
+
+    // returns the fee tier enabled on the variant's factory for a fee amount, if any
+    fn fee_tier(&self, fee: u32) -> Option<FeeTier>;
+
+    // returns the protocol fee setting for the input token of a swap from slot0
+    fn fee_protocol(&self, slot0: &Slot0, zero_for_one: bool) -> u32;
+
+    // returns the part of `fee_amount` owed to the protocol for the setting returned by `fee_protocol`
+    fn protocol_fee(&self, fee_amount: U256, fee_protocol: u32) -> U256;
+}
+
+//The canonical Uniswap V3 pool
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct UniswapV3;
+
+impl PoolVariant for UniswapV3 {
+    fn fee_tier(&self, fee: u32) -> Option<FeeTier> {
+        FeeTier::from_fee(fee)
+    }
+
+    fn fee_protocol(&self, slot0: &Slot0, zero_for_one: bool) -> u32 {
+        if zero_for_one {
+            slot0.fee_protocol_0() as u32
+        } else {
+            slot0.fee_protocol_1() as u32
+        }
+    }
+
+    fn protocol_fee(&self, fee_amount: U256, fee_protocol: u32) -> U256 {
+        if fee_protocol == 0 {
+            return U256::ZERO;
+        }
+        fee_amount / U256::from(fee_protocol)
+    }
+}
+
+//SushiSwap V3 pools are deployed from an unmodified fork of the Uniswap V3 core contracts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SushiSwapV3;
+
+impl PoolVariant for SushiSwapV3 {
+    fn fee_tier(&self, fee: u32) -> Option<FeeTier> {
+        UniswapV3.fee_tier(fee)
+    }
+
+    fn fee_protocol(&self, slot0: &Slot0, zero_for_one: bool) -> u32 {
+        UniswapV3.fee_protocol(slot0, zero_for_one)
+    }
+
+    fn protocol_fee(&self, fee_amount: U256, fee_protocol: u32) -> U256 {
+        UniswapV3.protocol_fee(fee_amount, fee_protocol)
+    }
+}
+
+//PancakeSwap V3 pools. The protocol fee of each token is a 16 bit fraction of the swap fee in basis points packed into
+//a uint32, and the factory enables a 0.25% tier in place of 0.3%
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PancakeSwapV3;
+
+impl PoolVariant for PancakeSwapV3 {
+    fn fee_tier(&self, fee: u32) -> Option<FeeTier> {
+        let tick_spacing = match fee {
+            100 => 1,
+            500 => 10,
+            2500 => 50,
+            10000 => 200,
+            _ => return None,
+        };
+        FeeTier::custom(fee, tick_spacing).ok()
+    }
+
+    fn fee_protocol(&self, slot0: &Slot0, zero_for_one: bool) -> u32 {
+        if zero_for_one {
+            slot0.fee_protocol % PANCAKESWAP_PROTOCOL_FEE_SP
+        } else {
+            slot0.fee_protocol >> 16
+        }
+    }
+
+    fn protocol_fee(&self, fee_amount: U256, fee_protocol: u32) -> U256 {
+        fee_amount * U256::from(fee_protocol) / U256::from(PANCAKESWAP_PROTOCOL_FEE_DENOMINATOR)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{PancakeSwapV3, PoolVariant, SushiSwapV3, UniswapV3};
+    use crate::{
+        fee_tier::FeeTier,
+        pool::{PoolSnapshot, Slot0, SwapResult},
+        tick_math::{get_sqrt_ratio_at_tick, MIN_SQRT_RATIO},
+    };
+
+    #[test]
+    fn test_fee_tiers() {
+        assert_eq!(UniswapV3.fee_tier(3000), Some(FeeTier::Medium));
+        assert_eq!(UniswapV3.fee_tier(2500), None);
+        assert_eq!(SushiSwapV3.fee_tier(500), Some(FeeTier::Low));
+
+        let tier = PancakeSwapV3.fee_tier(2500).unwrap();
+        assert_eq!(tier.fee(), 2500);
+        assert_eq!(tier.tick_spacing(), 50);
+        assert_eq!(PancakeSwapV3.fee_tier(10000), Some(FeeTier::High));
+        assert_eq!(PancakeSwapV3.fee_tier(3000), None);
+    }
+
+    #[test]
+    fn test_protocol_fee() {
+        //uniswap packs two 4 bit denominators
+        let slot0 = Slot0 {
+            fee_protocol: 4 + (5 << 4),
+            ..Default::default()
+        };
+        assert_eq!(UniswapV3.fee_protocol(&slot0, true), 4);
+        assert_eq!(UniswapV3.fee_protocol(&slot0, false), 5);
+        assert_eq!(UniswapV3.protocol_fee(U256::from(100), 4), U256::from(25));
+        assert_eq!(UniswapV3.protocol_fee(U256::from(100), 0), U256::ZERO);
+
+        //pancakeswap packs two 16 bit fractions in basis points
+        let slot0 = Slot0 {
+            fee_protocol: 3200 + (3300 << 16),
+            ..Default::default()
+        };
+        assert_eq!(PancakeSwapV3.fee_protocol(&slot0, true), 3200);
+        assert_eq!(PancakeSwapV3.fee_protocol(&slot0, false), 3300);
+        assert_eq!(
+            PancakeSwapV3.protocol_fee(U256::from(10000), 3200),
+            U256::from(3200)
+        );
+    }
+
+    #[test]
+    fn test_variant_swap() {
+        let mut pool = PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium)
+            .unwrap()
+            .with_variant(PancakeSwapV3);
+        pool.modify_position(-887220, 887220, 1e20 as i128).unwrap();
+        pool.slot0.fee_protocol = 3200 + (3300 << 16);
+
+        //the swap math is the same, only the protocol fee differs, which lowers the fee growth of the lps
+        let mut uniswap_pool = pool.clone().with_variant(UniswapV3);
+        uniswap_pool.slot0.fee_protocol = 0;
+        let amount = I256::from_raw(U256::from(1e18 as u128));
+        let limit = MIN_SQRT_RATIO + U256::from(1);
+        let result = pool.quote(true, amount, limit).unwrap();
+        let uniswap_result = uniswap_pool.quote(true, amount, limit).unwrap();
+        assert_eq!(
+            SwapResult {
+                fee_growth_global_0_x_128_delta: uniswap_result.fee_growth_global_0_x_128_delta,
+                ..result
+            },
+            uniswap_result
+        );
+        assert!(
+            result.fee_growth_global_0_x_128_delta < uniswap_result.fee_growth_global_0_x_128_delta
+        );
+
+        //32% of the fee goes to the protocol
+        pool.swap(true, amount, limit).unwrap();
+        assert_eq!(pool.protocol_fees.token_0, 960000000000000);
+        assert_eq!(pool.protocol_fees.token_1, 0);
+    }
+}
@@ -0,0 +1,993 @@
+use std::collections::BTreeSet;
+
+use alloy_primitives::{I256, U256};
+
+use crate::{
+    error::UniswapV3MathError,
+    invariants::{
+        check_amount_remaining, check_price, check_swap_step, check_tick, swap_step_bounds,
+        InvariantViolation,
+    },
+    orientation::amounts_in_out,
+    pool::{PoolSnapshot, SwapFill, SwapParams, SwapResult},
+    storage::{InitializedTicks, TickStorage},
+    tick_math::{MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK},
+    variant::PoolVariant,
+};
+
+//The fields of a Swap event emitted by UniswapV3Pool, amounts are signed from the perspective of the pool and include
+//the fee, the price, liquidity and tick are the pool state after the swap
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapEvent {
+    pub amount_0: I256,
+    pub amount_1: I256,
+    pub sqrt_price_x_96: U256,
+    pub liquidity: u128,
+    pub tick: i32,
+}
+
+impl From<SwapResult> for SwapEvent {
+    fn from(result: SwapResult) -> Self {
+        Self {
+            amount_0: result.amount_0,
+            amount_1: result.amount_1,
+            sqrt_price_x_96: result.sqrt_price_x_96,
+            liquidity: result.liquidity,
+            tick: result.tick,
+        }
+    }
+}
+
+//An observed swap next to the swap simulated against the local state, see `reconcile_swap`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapReconciliation {
+    pub observed: SwapEvent,
+    pub simulated: SwapResult,
+    // whether the swap was simulated as an exact input or an exact output swap
+    pub exact_input: bool,
+}
+
+impl SwapReconciliation {
+    // the simulated minus the observed amount of token0
+    pub fn amount_0_divergence(&self) -> I256 {
+        self.simulated.amount_0 - self.observed.amount_0
+    }
+
+    // the simulated minus the observed amount of token1
+    pub fn amount_1_divergence(&self) -> I256 {
+        self.simulated.amount_1 - self.observed.amount_1
+    }
+
+    // the simulated minus the observed sqrt price after the swap
+    pub fn sqrt_price_divergence(&self) -> I256 {
+        I256::from_raw(self.simulated.sqrt_price_x_96)
+            - I256::from_raw(self.observed.sqrt_price_x_96)
+    }
+
+    pub fn tick_divergence(&self) -> i32 {
+        self.simulated.tick - self.observed.tick
+    }
+
+    pub fn liquidity_matches(&self) -> bool {
+        self.simulated.liquidity == self.observed.liquidity
+    }
+
+    // returns true if the simulation reproduces the event bit for bit
+    pub fn is_exact(&self) -> bool {
+        SwapEvent::from(self.simulated) == self.observed
+    }
+
+    // returns true if the amounts, the sqrt price and the tick are within the tolerance and the liquidity matches
+    pub fn is_within(&self, tolerance: Tolerance) -> bool {
+        self.amount_0_divergence().unsigned_abs() <= tolerance.amount
+            && self.amount_1_divergence().unsigned_abs() <= tolerance.amount
+            && self.sqrt_price_divergence().unsigned_abs() <= tolerance.sqrt_price
+            && self.tick_divergence().unsigned_abs() <= tolerance.tick
+            && self.liquidity_matches()
+    }
+
+    // the total divergence of the amounts and the sqrt price, used to pick the closest simulation
+    fn divergence(&self) -> (U256, U256) {
+        (
+            self.amount_0_divergence()
+                .unsigned_abs()
+                .saturating_add(self.amount_1_divergence().unsigned_abs()),
+            self.sqrt_price_divergence().unsigned_abs(),
+        )
+    }
+}
+
+// re-simulates an observed swap against the pool state before it and reports how far the simulation diverges from
+// the event, a divergence means the local state drifted from the chain. The event does not say whether the swap was
+// exact input or exact output, or which price limit was used, so the swap is simulated both ways without a price
+// limit and the closer simulation is returned. Swaps that stopped at a price limit are reproduced within rounding by
+// the exact input simulation, since the pool only takes the input needed to reach the limit
+pub fn reconcile_swap<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    event: &SwapEvent,
+) -> Result<SwapReconciliation, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let zero_for_one = if event.sqrt_price_x_96 == pool.slot0.sqrt_price_x_96 {
+        event.amount_0.is_positive() || event.amount_1.is_negative()
+    } else {
+        event.sqrt_price_x_96 < pool.slot0.sqrt_price_x_96
+    };
+    let (amount_in, amount_out) = if zero_for_one {
+        (event.amount_0, event.amount_1)
+    } else {
+        (event.amount_1, event.amount_0)
+    };
+    let sqrt_price_limit_x_96 = if zero_for_one {
+        MIN_SQRT_RATIO + U256::from(1)
+    } else {
+        MAX_SQRT_RATIO - U256::from(1)
+    };
+
+    let mut closest: Option<SwapReconciliation> = None;
+    for (amount_specified, exact_input) in [(amount_in, true), (amount_out, false)] {
+        // a swap that did not move an amount can not be specified by it
+        if amount_specified.is_zero() {
+            continue;
+        }
+
+        let reconciliation = SwapReconciliation {
+            observed: *event,
+            simulated: pool.quote(zero_for_one, amount_specified, sqrt_price_limit_x_96)?,
+            exact_input,
+        };
+        if reconciliation.is_exact() {
+            return Ok(reconciliation);
+        }
+        if closest.is_none_or(|closest| reconciliation.divergence() < closest.divergence()) {
+            closest = Some(reconciliation);
+        }
+    }
+
+    // nothing was swapped, the pool state is compared as is
+    Ok(closest.unwrap_or(SwapReconciliation {
+        observed: *event,
+        simulated: SwapResult {
+            amount_0: I256::ZERO,
+            amount_1: I256::ZERO,
+            sqrt_price_x_96: pool.slot0.sqrt_price_x_96,
+            tick: pool.slot0.tick,
+            liquidity: pool.liquidity,
+            initialized_ticks_crossed: 0,
+            fee_growth_global_0_x_128_delta: U256::ZERO,
+            fee_growth_global_1_x_128_delta: U256::ZERO,
+            amount_specified_remaining: I256::ZERO,
+            fill: SwapFill::Full,
+            zero_liquidity_ticks: 0,
+            fee_amount: U256::ZERO,
+        },
+        exact_input: true,
+    }))
+}
+
+//The divergence a reconciliation or symmetry check may have and still pass, in wei of the amounts and of the Q96 sqrt
+//price and in ticks. Alternative integer backends usually round a few wei differently, see `ReconciliationReport`. A
+//sqrt price that diverges at all can straddle a tick boundary, so such tolerances usually allow a tick of divergence
+//as well. The conservation and snapshot checks verify invariants that hold exactly and take no tolerance
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tolerance {
+    pub amount: U256,
+    pub sqrt_price: U256,
+    pub tick: u32,
+}
+
+impl Tolerance {
+    pub fn new(amount: U256, sqrt_price: U256, tick: u32) -> Self {
+        Self {
+            amount,
+            sqrt_price,
+            tick,
+        }
+    }
+}
+
+//Statistics of a signed divergence over many samples, min and max are zero until the first sample
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DivergenceStats {
+    pub samples: u64,
+    pub min: I256,
+    pub max: I256,
+    // the sum of the divergences, saturating, far from zero if the samples are biased in one direction
+    pub sum: I256,
+    // the sum of the absolute divergences, saturating
+    pub abs_sum: U256,
+    // the number of samples whose absolute divergence exceeds the tolerance they were recorded with
+    pub exceeded: u64,
+}
+
+impl DivergenceStats {
+    // records a sample, returns true if its absolute divergence is within `tolerance`
+    pub fn record(&mut self, divergence: I256, tolerance: U256) -> bool {
+        if self.samples == 0 {
+            self.min = divergence;
+            self.max = divergence;
+        } else {
+            self.min = self.min.min(divergence);
+            self.max = self.max.max(divergence);
+        }
+        self.samples += 1;
+        self.sum = self.sum.saturating_add(divergence);
+        self.abs_sum = self.abs_sum.saturating_add(divergence.unsigned_abs());
+
+        let within = divergence.unsigned_abs() <= tolerance;
+        if !within {
+            self.exceeded += 1;
+        }
+        within
+    }
+
+    // the mean divergence rounded towards zero, zero if there are no samples
+    pub fn mean(&self) -> I256 {
+        if self.samples == 0 {
+            return I256::ZERO;
+        }
+        self.sum / I256::from_raw(U256::from(self.samples))
+    }
+
+    // the largest absolute divergence
+    pub fn max_abs(&self) -> U256 {
+        self.min.unsigned_abs().max(self.max.unsigned_abs())
+    }
+}
+
+//Divergence statistics of many reconciled swaps against a tolerance, e.g. to quantify the drift of a local state or
+//of an alternative integer backend over a block range and gate on it, rather than failing on the first divergence
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReconciliationReport {
+    pub tolerance: Tolerance,
+    pub amount_0: DivergenceStats,
+    pub amount_1: DivergenceStats,
+    pub sqrt_price: DivergenceStats,
+    pub tick: DivergenceStats,
+    // the number of reconciliations whose liquidity does not match
+    pub liquidity_mismatches: u64,
+    // the number of reconciliations that are not within the tolerance, see `SwapReconciliation::is_within`
+    pub exceeded: u64,
+}
+
+impl ReconciliationReport {
+    pub fn new(tolerance: Tolerance) -> Self {
+        Self {
+            tolerance,
+            ..Default::default()
+        }
+    }
+
+    // records a reconciliation, returns true if it is within the tolerance
+    pub fn record(&mut self, reconciliation: &SwapReconciliation) -> bool {
+        let tolerance = self.tolerance;
+        self.amount_0
+            .record(reconciliation.amount_0_divergence(), tolerance.amount);
+        self.amount_1
+            .record(reconciliation.amount_1_divergence(), tolerance.amount);
+        self.sqrt_price
+            .record(reconciliation.sqrt_price_divergence(), tolerance.sqrt_price);
+        self.tick.record(
+            I256::try_from(reconciliation.tick_divergence()).unwrap(),
+            U256::from(tolerance.tick),
+        );
+        if !reconciliation.liquidity_matches() {
+            self.liquidity_mismatches += 1;
+        }
+
+        let within = reconciliation.is_within(tolerance);
+        if !within {
+            self.exceeded += 1;
+        }
+        within
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.amount_0.samples
+    }
+
+    // returns true if every recorded reconciliation is within the tolerance
+    pub fn is_within(&self) -> bool {
+        self.exceeded == 0
+    }
+}
+
+// returns the parameters of a swap without a price limit that moves exactly the given token0 and token1 amounts, signed
+// from the perspective of the pool like the amounts of a Swap event, e.g. to classify balance changes of a pool into
+// swaps when the events are missing. The input is positive and the output is negative or zero, which gives the
+// direction. The exact input swap of the input is tried first and then the exact output swap of the output, none is
+// returned if neither moves exactly the observed amounts, e.g. if the balance changes are not a single swap against
+// this state
+pub fn infer_swap<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    amount_0: I256,
+    amount_1: I256,
+) -> Result<Option<SwapParams>, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let zero_for_one = if amount_0.is_positive() && !amount_1.is_positive() {
+        true
+    } else if amount_1.is_positive() && !amount_0.is_positive() {
+        false
+    } else {
+        return Ok(None);
+    };
+    let (amount_in, amount_out) = if zero_for_one {
+        (amount_0, amount_1)
+    } else {
+        (amount_1, amount_0)
+    };
+
+    for amount_specified in [amount_in, amount_out] {
+        if amount_specified.is_zero() {
+            continue;
+        }
+
+        let params = SwapParams::builder()
+            .zero_for_one(zero_for_one)
+            .amount_specified(amount_specified)
+            .build()?;
+        let result = pool.quote_with(&params)?;
+        if (result.amount_0, result.amount_1) == (amount_0, amount_1) {
+            return Ok(Some(params));
+        }
+    }
+
+    Ok(None)
+}
+
+//An exact input quote next to the exact output quote for the output it produced, see `check_symmetry`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapSymmetry {
+    pub exact_input: SwapResult,
+    pub exact_output: SwapResult,
+    pub zero_for_one: bool,
+}
+
+impl SwapSymmetry {
+    // the input of the exact input swap minus the input the exact output swap takes for the same output. Amounts out
+    // are rounded down and amounts in up, so the exact input swap pays for the rounding of its output and this is
+    // usually a few wei per step and never negative for swaps that do not stop at a price limit
+    pub fn amount_in_asymmetry(&self) -> I256 {
+        let (exact_input, exact_output) = if self.zero_for_one {
+            (self.exact_input.amount_0, self.exact_output.amount_0)
+        } else {
+            (self.exact_input.amount_1, self.exact_output.amount_1)
+        };
+        exact_input - exact_output
+    }
+
+    // the sqrt price after the exact input swap minus the sqrt price after the exact output swap
+    pub fn sqrt_price_asymmetry(&self) -> I256 {
+        I256::from_raw(self.exact_input.sqrt_price_x_96)
+            - I256::from_raw(self.exact_output.sqrt_price_x_96)
+    }
+
+    // the tick after the exact input swap minus the tick after the exact output swap
+    pub fn tick_asymmetry(&self) -> i32 {
+        self.exact_input.tick - self.exact_output.tick
+    }
+
+    // returns true if both swaps produce the same output and their inputs, sqrt prices and ticks are within the
+    // tolerance
+    pub fn is_within(&self, tolerance: Tolerance) -> bool {
+        let (output, exact_output) = if self.zero_for_one {
+            (self.exact_input.amount_1, self.exact_output.amount_1)
+        } else {
+            (self.exact_input.amount_0, self.exact_output.amount_0)
+        };
+        output == exact_output
+            && self.amount_in_asymmetry().unsigned_abs() <= tolerance.amount
+            && self.sqrt_price_asymmetry().unsigned_abs() <= tolerance.sqrt_price
+            && self.tick_asymmetry().unsigned_abs() <= tolerance.tick
+    }
+}
+
+// quotes an exact input swap of `amount_in`, then the exact output swap for the output it produced, and reports how
+// far the two differ due to rounding. Routers can use the asymmetry as a sanity bound when converting between exact
+// input and exact output quotes
+pub fn check_symmetry<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    zero_for_one: bool,
+    amount_in: U256,
+    sqrt_price_limit_x_96: U256,
+) -> Result<SwapSymmetry, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let amount_in = I256::try_from(amount_in).map_err(|_| UniswapV3MathError::Overflow)?;
+    let exact_input = pool.quote(zero_for_one, amount_in, sqrt_price_limit_x_96)?;
+    let amount_out = if zero_for_one {
+        exact_input.amount_1
+    } else {
+        exact_input.amount_0
+    };
+    // the output is negative from the perspective of the pool, which is the sign of an exact output amount
+    let exact_output = pool.quote(zero_for_one, amount_out, sqrt_price_limit_x_96)?;
+
+    Ok(SwapSymmetry {
+        exact_input,
+        exact_output,
+        zero_for_one,
+    })
+}
+
+//Asymmetry statistics of many symmetry checks against a tolerance, see `check_symmetry`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SymmetryReport {
+    pub tolerance: Tolerance,
+    pub amount_in: DivergenceStats,
+    pub sqrt_price: DivergenceStats,
+    pub tick: DivergenceStats,
+    // the number of checks that are not within the tolerance, see `SwapSymmetry::is_within`
+    pub exceeded: u64,
+}
+
+impl SymmetryReport {
+    pub fn new(tolerance: Tolerance) -> Self {
+        Self {
+            tolerance,
+            ..Default::default()
+        }
+    }
+
+    // records a symmetry check, returns true if it is within the tolerance. The sqrt prices differ by the rounding of
+    // the amounts in Q96, so the sqrt price tolerance is usually far wider than the amount tolerance
+    pub fn record(&mut self, symmetry: &SwapSymmetry) -> bool {
+        let tolerance = self.tolerance;
+        self.amount_in
+            .record(symmetry.amount_in_asymmetry(), tolerance.amount);
+        self.sqrt_price
+            .record(symmetry.sqrt_price_asymmetry(), tolerance.sqrt_price);
+        self.tick.record(
+            I256::try_from(symmetry.tick_asymmetry()).unwrap(),
+            U256::from(tolerance.tick),
+        );
+
+        let within = symmetry.is_within(self.tolerance);
+        if !within {
+            self.exceeded += 1;
+        }
+        within
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.amount_in.samples
+    }
+
+    // returns true if every recorded check is within the tolerance
+    pub fn is_within(&self) -> bool {
+        self.exceeded == 0
+    }
+}
+
+//The amounts of a swap accounted for by its steps, see `conservation`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Conservation {
+    // the input including fees, the fees and the output of the swap, each the sum over its steps
+    pub amount_in: U256,
+    pub fee_amount: U256,
+    pub amount_out: U256,
+    pub steps: u32,
+    // the input received above and the output withheld below the amounts of moving the price along the curve, summed
+    // over the steps. Every rounding goes in favor of the pool, so these are what the pool gained from rounding,
+    // usually a wei or two per step
+    pub rounding_in: U256,
+    pub rounding_out: U256,
+}
+
+// checks that the amounts of a quoted swap are conserved: `result` must be the quote of `params` against `pool`, the
+// swap is stepped again and every step must receive at least and pay at most what moving the price along the curve
+// is worth, the swap must not use more than the amount specified and its input, fees and output must be exactly the
+// sums of those of its steps. Simulators can assert this on every quote in debug builds, e.g.
+// `debug_assert!(conservation(&pool, &params, &result).is_ok())`. Fails with an `InvariantViolation` otherwise
+pub fn conservation<T, B, V>(
+    pool: &PoolSnapshot<T, B, V>,
+    params: &SwapParams,
+    result: &SwapResult,
+) -> Result<Conservation, UniswapV3MathError>
+where
+    T: TickStorage,
+    B: InitializedTicks,
+    V: PoolVariant,
+{
+    let steps = pool
+        .swap_steps(
+            params.zero_for_one,
+            params.amount_specified,
+            params.sqrt_price_limit_x_96,
+        )?
+        .zero_liquidity(params.zero_liquidity);
+
+    let mut conservation = Conservation::default();
+    for step in steps {
+        let step = step?;
+        check_swap_step(&step, params.zero_for_one)?;
+        let (min_amount_in, max_amount_out) = swap_step_bounds(&step, params.zero_for_one)?;
+
+        conservation.amount_in += step.amount_in + step.fee_amount;
+        conservation.fee_amount += step.fee_amount;
+        conservation.amount_out += step.amount_out;
+        conservation.steps += 1;
+        conservation.rounding_in += step.amount_in - min_amount_in;
+        conservation.rounding_out += max_amount_out - step.amount_out;
+    }
+
+    check_amount_remaining(params.amount_specified, result.amount_specified_remaining)?;
+    let (amount_in, amount_out) =
+        amounts_in_out(params.zero_for_one, result.amount_0, result.amount_1);
+    if amount_in != conservation.amount_in
+        || amount_out != conservation.amount_out
+        || result.fee_amount != conservation.fee_amount
+    {
+        return Err(InvariantViolation::SwapAmountMismatch {
+            amount_in,
+            amount_out,
+            steps_amount_in: conservation.amount_in,
+            steps_amount_out: conservation.amount_out,
+        }
+        .into());
+    }
+
+    Ok(conservation)
+}
+
+// checks a snapshot from an external source, e.g. the subgraph, for consistency before it is used. Unlike the checks
+// of the `debug-invariants` feature this scans the whole snapshot: the price must match the tick, every initialized
+// tick and every bit of the bitmap must agree and have a liquidity gross of at least its liquidity net, the liquidity
+// net of all ticks must sum to zero and the in range liquidity must be the sum of the liquidity net of the ticks up to
+// the current tick. Ticks are checked in ascending order and the first violation is returned, a tick outside of the
+// tick range before any other
+pub fn check_snapshot<T: TickStorage, B: InitializedTicks, V>(
+    pool: &PoolSnapshot<T, B, V>,
+) -> Result<(), UniswapV3MathError> {
+    check_price(pool.slot0.sqrt_price_x_96, pool.slot0.tick)?;
+
+    // positions of a corrupt bitmap can be far outside the tick range, so ticks are gathered as i64
+    let tick_spacing = pool.fee_tier.tick_spacing();
+    let mut ticks: BTreeSet<i64> = pool.ticks.keys().map(i64::from).collect();
+    ticks.extend(pool.tick_bitmap.initialized_ticks(tick_spacing));
+    let ticks = ticks
+        .into_iter()
+        .map(|tick| {
+            i32::try_from(tick)
+                .ok()
+                .filter(|tick| (MIN_TICK..=MAX_TICK).contains(tick))
+                .ok_or_else(|| InvariantViolation::TickOutOfRange { tick }.into())
+        })
+        .collect::<Result<Vec<_>, UniswapV3MathError>>()?;
+
+    let mut sum = I256::ZERO;
+    let mut expected = I256::ZERO;
+    for tick in ticks {
+        check_tick(&pool.ticks, &pool.tick_bitmap, tick, tick_spacing)?;
+
+        let liquidity_net = pool.ticks.liquidity_net(tick);
+        sum += I256::try_from(liquidity_net).map_err(|_| UniswapV3MathError::Overflow)?;
+        if tick <= pool.slot0.tick {
+            expected = sum;
+        }
+    }
+
+    if !sum.is_zero() {
+        return Err(InvariantViolation::LiquidityNetNotBalanced { sum }.into());
+    }
+    if I256::try_from(pool.liquidity).ok() != Some(expected) {
+        return Err(InvariantViolation::LiquidityMismatch {
+            liquidity: pool.liquidity,
+            expected,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::{I256, U256};
+
+    use super::{
+        check_snapshot, check_symmetry, conservation, infer_swap, reconcile_swap, DivergenceStats,
+        ReconciliationReport, SwapEvent, SymmetryReport, Tolerance,
+    };
+    use crate::{
+        error::UniswapV3MathError,
+        fee_tier::FeeTier,
+        invariants::InvariantViolation,
+        pool::{PersistentPoolSnapshot, PoolSnapshot, SwapParams},
+        tick_math::{get_sqrt_ratio_at_tick, MAX_SQRT_RATIO, MIN_SQRT_RATIO},
+    };
+
+    fn pool() -> PoolSnapshot {
+        let mut pool =
+            PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), FeeTier::Medium).unwrap();
+        pool.modify_position(-600, 600, 1e21 as i128).unwrap();
+        pool.modify_position(-6000, 6000, 1e20 as i128).unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_reconcile_swap() {
+        let pool = pool();
+        let amount = I256::from_raw(U256::from(1e19 as u128));
+
+        for (zero_for_one, limit) in [
+            (true, MIN_SQRT_RATIO + U256::from(1)),
+            (false, MAX_SQRT_RATIO - U256::from(1)),
+        ] {
+            //exact input
+            let event = SwapEvent::from(pool.quote(zero_for_one, amount, limit).unwrap());
+            let reconciliation = reconcile_swap(&pool, &event).unwrap();
+            assert!(reconciliation.is_exact());
+            assert!(reconciliation.exact_input);
+
+            //exact output
+            let event = SwapEvent::from(pool.quote(zero_for_one, -amount, limit).unwrap());
+            let reconciliation = reconcile_swap(&pool, &event).unwrap();
+            assert!(reconciliation.is_exact());
+            assert!(!reconciliation.exact_input);
+        }
+
+        //stopped at a price limit, reproduced within rounding
+        let limit = get_sqrt_ratio_at_tick(-300).unwrap() + U256::from(12345);
+        let event = SwapEvent::from(pool.quote(true, amount, limit).unwrap());
+        let reconciliation = reconcile_swap(&pool, &event).unwrap();
+        assert!(reconciliation.is_within(Tolerance::new(U256::from(1), U256::from(1 << 20), 1)));
+    }
+
+    #[test]
+    fn test_reconcile_swap_drift() {
+        let pool = pool();
+        let amount = I256::from_raw(U256::from(1e19 as u128));
+        let event = SwapEvent::from(
+            pool.quote(true, amount, MIN_SQRT_RATIO + U256::from(1))
+                .unwrap(),
+        );
+
+        //the local state missed a mint
+        let mut drifted = pool.clone();
+        drifted.modify_position(-6000, 6000, 1e19 as i128).unwrap();
+        let reconciliation = reconcile_swap(&drifted, &event).unwrap();
+        assert!(!reconciliation.is_exact());
+        assert!(!reconciliation.is_within(Tolerance::new(U256::from(1), U256::from(1), 1)));
+        //more liquidity moves the price less
+        assert!(reconciliation.sqrt_price_divergence().is_positive());
+        assert!(!reconciliation.liquidity_matches());
+    }
+
+    #[test]
+    fn test_divergence_report() {
+        let pool = pool();
+        let mut drifted = pool.clone();
+        drifted.modify_position(-6000, 6000, 1e17 as i128).unwrap();
+
+        let mut exact = ReconciliationReport::new(Tolerance::default());
+        let mut report = ReconciliationReport::new(Tolerance::new(U256::from(1), U256::ZERO, 0));
+        for amount in [1e15, 1e17, 1e19] {
+            let amount = I256::from_raw(U256::from(amount as u128));
+            let event = SwapEvent::from(
+                pool.quote(true, amount, MIN_SQRT_RATIO + U256::from(1))
+                    .unwrap(),
+            );
+            assert!(exact.record(&reconcile_swap(&pool, &event).unwrap()));
+            report.record(&reconcile_swap(&drifted, &event).unwrap());
+        }
+        assert!(exact.is_within());
+        assert_eq!(exact.samples(), 3);
+        assert_eq!(exact.sqrt_price.max_abs(), U256::ZERO);
+
+        //more liquidity moves the price less, so the divergence is biased upwards
+        assert!(!report.is_within());
+        assert_eq!(report.samples(), 3);
+        assert_eq!(report.liquidity_mismatches, 3);
+        assert!(report.sqrt_price.min.is_positive());
+        assert!(report.sqrt_price.mean().is_positive());
+        assert!(report.sqrt_price.mean() <= report.sqrt_price.max);
+        assert_eq!(report.sqrt_price.exceeded, 3);
+
+        //fails if the tolerance is exceeded
+        let mut stats = DivergenceStats::default();
+        assert!(stats.record(I256::MINUS_ONE, U256::from(1)));
+        assert!(!stats.record(I256::try_from(3).unwrap(), U256::from(1)));
+        assert_eq!(
+            (stats.min, stats.max, stats.sum, stats.abs_sum),
+            (
+                I256::MINUS_ONE,
+                I256::try_from(3).unwrap(),
+                I256::try_from(2).unwrap(),
+                U256::from(4)
+            )
+        );
+        assert_eq!(stats.mean(), I256::ONE);
+        assert_eq!(stats.max_abs(), U256::from(3));
+        assert_eq!(stats.exceeded, 1);
+
+        let mut symmetry = SymmetryReport::new(Tolerance::new(
+            U256::from(4),
+            pool.slot0.sqrt_price_x_96 >> 64,
+            1,
+        ));
+        for amount in [1e3, 1e15, 1e19] {
+            assert!(symmetry.record(
+                &check_symmetry(
+                    &pool,
+                    true,
+                    U256::from(amount as u128),
+                    MIN_SQRT_RATIO + U256::from(1)
+                )
+                .unwrap()
+            ));
+        }
+        assert!(symmetry.is_within());
+        assert!(!symmetry.amount_in.min.is_negative());
+        assert!(symmetry.amount_in.max_abs() <= U256::from(4));
+        assert_eq!(symmetry.sqrt_price.samples, 3);
+
+        //the sqrt price and tick have tolerances of their own
+        let mut strict = SymmetryReport::new(Tolerance::new(U256::from(4), U256::ZERO, 0));
+        assert!(!strict.record(
+            &check_symmetry(
+                &pool,
+                true,
+                U256::from(1e19 as u128),
+                MIN_SQRT_RATIO + U256::from(1)
+            )
+            .unwrap()
+        ));
+        assert_eq!(strict.amount_in.exceeded, 0);
+        assert_eq!(strict.sqrt_price.exceeded, 1);
+    }
+
+    #[test]
+    fn test_infer_swap() {
+        let pool = pool();
+        let amount = U256::from(1e19 as u128);
+
+        for zero_for_one in [true, false] {
+            let exact_input = SwapParams::builder()
+                .zero_for_one(zero_for_one)
+                .amount_in(amount)
+                .build()
+                .unwrap();
+            let exact_output = SwapParams::builder()
+                .zero_for_one(zero_for_one)
+                .amount_out(amount)
+                .build()
+                .unwrap();
+
+            let result = pool.quote_with(&exact_input).unwrap();
+            let inferred = infer_swap(&pool, result.amount_0, result.amount_1).unwrap();
+            assert_eq!(inferred, Some(exact_input));
+
+            //an exact output swap is often reproduced by the exact input swap of its input as well, either way the
+            //inferred swap moves the same amounts
+            let result = pool.quote_with(&exact_output).unwrap();
+            let inferred = infer_swap(&pool, result.amount_0, result.amount_1)
+                .unwrap()
+                .unwrap();
+            assert_eq!(inferred.zero_for_one, zero_for_one);
+            let inferred = pool.quote_with(&inferred).unwrap();
+            assert_eq!(
+                (inferred.amount_0, inferred.amount_1),
+                (result.amount_0, result.amount_1)
+            );
+        }
+
+        //fails if the amounts are not a swap against the pool
+        let result = pool
+            .quote_with(
+                &SwapParams::builder()
+                    .zero_for_one(true)
+                    .amount_in(amount)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            infer_swap(&pool, result.amount_0, result.amount_1 - I256::ONE).unwrap(),
+            None
+        );
+        assert_eq!(
+            infer_swap(&pool, result.amount_0, -result.amount_1).unwrap(),
+            None
+        );
+        assert_eq!(infer_swap(&pool, I256::ZERO, I256::ZERO).unwrap(), None);
+    }
+
+    #[test]
+    fn test_check_symmetry() {
+        let pool = pool();
+        let tolerance = Tolerance::new(U256::from(4), pool.slot0.sqrt_price_x_96 >> 64, 1);
+
+        //the exact output swap takes at most a few wei less than the exact input swap, also across ticks
+        for zero_for_one in [true, false] {
+            let limit = if zero_for_one {
+                MIN_SQRT_RATIO + U256::from(1)
+            } else {
+                MAX_SQRT_RATIO - U256::from(1)
+            };
+            for amount in [1e3, 1e15, 1e19] {
+                let symmetry =
+                    check_symmetry(&pool, zero_for_one, U256::from(amount as u128), limit).unwrap();
+                assert!(!symmetry.amount_in_asymmetry().is_negative());
+                // the prices only differ by the rounding of the amounts in Q96
+                assert!(symmetry.is_within(tolerance));
+            }
+        }
+
+        //a swap that runs out of liquidity moves the price to the limit, reversed it stops at the last initialized tick
+        let symmetry = check_symmetry(
+            &pool,
+            true,
+            U256::from(1e21 as u128),
+            MIN_SQRT_RATIO + U256::from(1),
+        )
+        .unwrap();
+        //the amounts match but the prices do not
+        assert!(!symmetry.is_within(tolerance));
+        assert!(symmetry.is_within(Tolerance {
+            sqrt_price: U256::MAX,
+            tick: u32::MAX,
+            ..tolerance
+        }));
+        assert_eq!(
+            symmetry.exact_input.sqrt_price_x_96,
+            MIN_SQRT_RATIO + U256::from(1)
+        );
+        assert_eq!(
+            symmetry.exact_output.sqrt_price_x_96,
+            get_sqrt_ratio_at_tick(-6000).unwrap()
+        );
+
+        //an exact input swap that does not produce any output can not be reversed
+        assert!(
+            check_symmetry(&pool, true, U256::from(1), MIN_SQRT_RATIO + U256::from(1)).is_err()
+        );
+    }
+
+    #[test]
+    fn test_conservation() {
+        let mut pool = pool();
+        pool.slot0.fee_protocol = 4 | (4 << 4);
+
+        for zero_for_one in [true, false] {
+            for amount in [1e3, 1e19, 1e21] {
+                let amount = U256::from(amount as u128);
+                for params in [
+                    SwapParams::builder()
+                        .zero_for_one(zero_for_one)
+                        .amount_in(amount)
+                        .build()
+                        .unwrap(),
+                    SwapParams::builder()
+                        .zero_for_one(zero_for_one)
+                        .amount_out(amount)
+                        .build()
+                        .unwrap(),
+                ] {
+                    let result = pool.quote_with(&params).unwrap();
+                    let conservation = conservation(&pool, &params, &result).unwrap();
+                    assert!(conservation.steps > 0);
+                    assert_eq!(conservation.fee_amount, result.fee_amount);
+                    assert!(conservation.rounding_in <= U256::from(conservation.steps));
+                    assert!(conservation.rounding_out <= U256::from(conservation.steps));
+                }
+            }
+        }
+
+        //fails if the result was not produced by the swap
+        let params = SwapParams::builder()
+            .zero_for_one(true)
+            .amount_in(U256::from(1e19 as u128))
+            .build()
+            .unwrap();
+        let mut result = pool.quote_with(&params).unwrap();
+        result.amount_1 -= I256::ONE;
+        assert!(conservation(&pool, &params, &result)
+            .unwrap_err()
+            .to_string()
+            .contains("do not match the"));
+        result.amount_1 += I256::ONE;
+        result.amount_specified_remaining = -I256::ONE;
+        assert!(conservation(&pool, &params, &result).is_err());
+    }
+
+    #[test]
+    fn test_check_snapshot() {
+        let mut pool = pool();
+        pool.swap(
+            true,
+            I256::from_raw(U256::from(1e21 as u128)),
+            MIN_SQRT_RATIO + U256::from(1),
+        )
+        .unwrap();
+        check_snapshot(&pool).unwrap();
+
+        let violation = |pool: &PoolSnapshot| match check_snapshot(pool) {
+            Err(UniswapV3MathError::InvariantViolation(violation)) => *violation,
+            result => panic!("expected an invariant violation, got {result:?}"),
+        };
+
+        //fails if the in range liquidity is not the sum of the ticks below the current tick
+        let mut broken = pool.clone();
+        broken.liquidity += 1;
+        assert_eq!(
+            violation(&broken),
+            InvariantViolation::LiquidityMismatch {
+                liquidity: pool.liquidity + 1,
+                expected: I256::try_from(pool.liquidity).unwrap(),
+            }
+        );
+
+        //fails if the liquidity net does not sum to zero
+        let mut broken = pool.clone();
+        broken.ticks.get_mut(&6000).unwrap().liquidity_net += 1;
+        assert_eq!(
+            violation(&broken),
+            InvariantViolation::LiquidityNetNotBalanced {
+                sum: I256::try_from(1).unwrap()
+            }
+        );
+
+        //fails if the liquidity net exceeds the liquidity gross
+        let mut broken = pool.clone();
+        broken.ticks.get_mut(&-600).unwrap().liquidity_gross = 1;
+        assert!(matches!(
+            violation(&broken),
+            InvariantViolation::LiquidityNetExceedsGross { tick: -600, .. }
+        ));
+
+        //fails if a tick is missing from the bitmap or a bit has no tick
+        let mut broken = pool.clone();
+        broken.tick_bitmap.clear();
+        assert!(matches!(
+            violation(&broken),
+            InvariantViolation::TickBitmapMismatch {
+                tick: -6000,
+                in_bitmap: false,
+                in_ticks: true
+            }
+        ));
+        let mut broken = pool.clone();
+        broken.ticks.remove(&6000);
+        assert!(matches!(
+            violation(&broken),
+            InvariantViolation::TickBitmapMismatch {
+                tick: 6000,
+                in_bitmap: true,
+                in_ticks: false
+            }
+        ));
+
+        //fails if the tick is not the tick of the price
+        let mut broken = pool.clone();
+        broken.slot0.tick += 100;
+        assert!(matches!(
+            violation(&broken),
+            InvariantViolation::TickPriceMismatch { .. }
+        ));
+
+        //other storages are scanned the same way
+        check_snapshot(&PersistentPoolSnapshot::from(pool)).unwrap();
+
+        //fails if a bit marks a tick outside of the tick range, even one that does not fit into an i32
+        let tier = FeeTier::custom(3000, 16383).unwrap();
+        let mut broken = PoolSnapshot::new(get_sqrt_ratio_at_tick(0).unwrap(), tier).unwrap();
+        broken.tick_bitmap.insert(1000, U256::from(1));
+        assert_eq!(
+            violation(&broken),
+            InvariantViolation::TickOutOfRange {
+                tick: 1000 * 256 * 16383
+            }
+        );
+    }
+}